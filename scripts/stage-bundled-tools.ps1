@@ -0,0 +1,45 @@
+#!/usr/bin/env pwsh
+# Stage a tool for installers built with the bundled-tools feature
+#
+# Copies a tool's folder (as extracted from its archive) into
+# src-tauri/bundled-tools/<os>-<arch>/<tool>/, which tauri.conf.bundled.json ships as
+# resources. Windows on ARM can take windows-aarch64 and falls back to windows-x86_64
+# tools (emulated) if that's all there is.
+
+param(
+    [Parameter(Mandatory=$true)]
+    [string]$Platform,
+    [Parameter(Mandatory=$true)]
+    [ValidateSet("ffmpeg", "imagemagick")]
+    [string]$Tool,
+    [Parameter(Mandatory=$true)]
+    [string]$Source
+)
+
+if ($Platform -notmatch '^(windows|macos|linux)-(x86_64|aarch64)$') {
+    Write-Host "Error: platform must be windows, macos or linux with x86_64 or aarch64 (e.g. windows-aarch64)" -ForegroundColor Red
+    exit 1
+}
+
+$Exe = if ($Tool -eq "ffmpeg") { "ffmpeg" } else { "magick" }
+if ($Platform -like "windows-*") {
+    $Exe = "$Exe.exe"
+}
+
+# Same places get_tool_path looks (see bundled_tools::tool_subdirs)
+if (-not (Test-Path (Join-Path $Source $Exe)) -and -not (Test-Path (Join-Path $Source "bin" $Exe))) {
+    Write-Host "Error: $Exe not found in $Source or $Source/bin" -ForegroundColor Red
+    exit 1
+}
+
+$RootDir = Split-Path -Parent $PSScriptRoot
+$Target = Join-Path $RootDir "src-tauri" "bundled-tools" $Platform $Tool
+
+if (Test-Path $Target) {
+    Remove-Item -Recurse -Force $Target
+}
+New-Item -ItemType Directory -Force -Path $Target | Out-Null
+Copy-Item -Recurse -Path (Join-Path $Source "*") -Destination $Target
+
+Write-Host "Staged $Tool for $Platform in src-tauri/bundled-tools/$Platform/$Tool" -ForegroundColor Green
+Write-Host "Build with: tauri build --features bundled-tools --config src-tauri/tauri.conf.bundled.json"
@@ -0,0 +1,58 @@
+#!/usr/bin/env bash
+# Stage a tool for installers built with the bundled-tools feature
+#
+# Copies a tool's folder (as extracted from its archive) into
+# src-tauri/bundled-tools/<os>-<arch>/<tool>/, which tauri.conf.bundled.json ships as
+# resources. Run it once per tool and platform; a universal macOS build takes both
+# macos-aarch64 and macos-x86_64, Windows on ARM can take windows-aarch64 and falls back
+# to windows-x86_64 tools (emulated) if that's all there is.
+
+set -e
+
+RED='\033[0;31m'
+GREEN='\033[0;32m'
+NC='\033[0m' # No Color
+
+if [ "$#" -ne 3 ]; then
+    echo -e "${RED}Error: platform, tool and folder required${NC}"
+    echo "Usage: $0 <os>-<arch> <ffmpeg|imagemagick> <tool folder>"
+    echo "Example: $0 macos-aarch64 imagemagick ~/Downloads/ImageMagick-7.1.1"
+    exit 1
+fi
+
+PLATFORM=$1
+TOOL=$2
+SOURCE=$3
+
+if ! [[ $PLATFORM =~ ^(windows|macos|linux)-(x86_64|aarch64)$ ]]; then
+    echo -e "${RED}Error: platform must be windows, macos or linux with x86_64 or aarch64 (e.g. windows-aarch64)${NC}"
+    exit 1
+fi
+
+case "$TOOL" in
+    ffmpeg) EXE=ffmpeg ;;
+    imagemagick) EXE=magick ;;
+    *)
+        echo -e "${RED}Error: only ffmpeg and imagemagick can be bundled${NC}"
+        exit 1
+        ;;
+esac
+if [[ $PLATFORM == windows-* ]]; then
+    EXE="$EXE.exe"
+fi
+
+# Same places get_tool_path looks (see bundled_tools::tool_subdirs)
+if [ ! -f "$SOURCE/$EXE" ] && [ ! -f "$SOURCE/bin/$EXE" ]; then
+    echo -e "${RED}Error: $EXE not found in $SOURCE or $SOURCE/bin${NC}"
+    exit 1
+fi
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+TARGET="$SCRIPT_DIR/../src-tauri/bundled-tools/$PLATFORM/$TOOL"
+
+rm -rf "$TARGET"
+mkdir -p "$TARGET"
+cp -R "$SOURCE/." "$TARGET/"
+
+echo -e "${GREEN}Staged $TOOL for $PLATFORM in src-tauri/bundled-tools/$PLATFORM/$TOOL${NC}"
+echo "Build with: tauri build --features bundled-tools --config src-tauri/tauri.conf.bundled.json"
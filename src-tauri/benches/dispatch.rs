@@ -0,0 +1,92 @@
+//! Benchmarks for choosing a conversion tool and the format lookups behind it
+//!
+//! Run with `cargo bench --bench dispatch`. Batches and the format picker call these for
+//! every file, so a registry or caching redesign should at least not make them slower.
+
+use convertsave_lib::conversion::{self, ConversionSettings};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Pairs across the tools, including misses that walk every format list
+const PAIRS: &[(&str, &str)] = &[
+    ("mp4", "webm"),
+    ("mov", "gif"),
+    ("flac", "mp3"),
+    ("png", "jpg"),
+    ("heic", "png"),
+    ("nef", "tiff"),
+    ("svg", "pdf"),
+    ("pdf", "png"),
+    ("docx", "pdf"),
+    ("epub", "mobi"),
+    ("html", "png"),
+    ("ics", "csv"),
+    ("xyz", "abc"),
+];
+
+const EXTENSIONS: &[&str] = &["mp4", "MKV", "wav", "png", "cr3", "svg", "docx", "eml", "ics", "xyz"];
+
+fn bench_determine_tool(c: &mut Criterion) {
+    c.bench_function("determine_conversion_tool", |b| {
+        b.iter(|| {
+            for (input, output) in PAIRS {
+                black_box(conversion::determine_conversion_tool(black_box(input), black_box(output)));
+            }
+        })
+    });
+
+    let vector = ConversionSettings { vector_output: true, ..Default::default() };
+    c.bench_function("determine_conversion_tool_with_settings", |b| {
+        b.iter(|| {
+            for (input, output) in PAIRS {
+                black_box(conversion::determine_conversion_tool_with_settings(input, output, black_box(&vector)));
+            }
+        })
+    });
+}
+
+fn bench_format_lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format_lookups");
+    group.bench_function("is_supported_input", |b| {
+        b.iter(|| {
+            for ext in EXTENSIONS {
+                black_box(conversion::is_supported_input(black_box(ext)));
+            }
+        })
+    });
+    group.bench_function("category", |b| {
+        b.iter(|| {
+            for ext in EXTENSIONS {
+                black_box((
+                    conversion::is_video_format(ext),
+                    conversion::is_audio_format(ext),
+                    conversion::is_image_format(ext),
+                    conversion::is_document_format(ext),
+                ));
+            }
+        })
+    });
+    group.bench_function("display_name", |b| {
+        b.iter(|| {
+            for ext in EXTENSIONS {
+                let ext = conversion::normalize_extension(ext);
+                black_box((conversion::get_format_display_name(&ext), conversion::get_format_color(&ext)));
+            }
+        })
+    });
+    group.bench_function("special_routes", |b| {
+        b.iter(|| {
+            for (input, output) in PAIRS {
+                black_box((
+                    conversion::supports_builtin_conversion(input, output),
+                    conversion::prefers_svg_renderer(input, output),
+                    conversion::needs_ghostscript(input),
+                    conversion::is_animated_conversion(input, output),
+                ));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_determine_tool, bench_format_lookups);
+criterion_main!(benches);
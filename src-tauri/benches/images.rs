@@ -0,0 +1,50 @@
+//! Benchmarks for thumbnails and transparency detection
+//!
+//! Run with `cargo bench --bench images`. Both run for every image added to the file list;
+//! the tool invocations around them aren't measured, only the work done in the app.
+
+use convertsave_lib::conversion;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Typical `ffmpeg -i` output for a PNG, where the pixel format sits in the stream line
+const FFMPEG_PNG_INFO: &str = "ffmpeg version 7.1 Copyright (c) 2000-2024 the FFmpeg developers
+  built with Apple clang version 16.0.0 (clang-1600.0.26.4)
+  libavutil      59. 39.100 / 59. 39.100
+  libavcodec     61. 19.100 / 61. 19.100
+  libavformat    61.  7.100 / 61.  7.100
+Input #0, png_pipe, from 'photo.png':
+  Duration: N/A, bitrate: N/A
+  Stream #0:0: Video: png, rgb24(pc, gbr/unknown/unknown), 4032x3024, 25 fps, 25 tbr, 25 tbn
+At least one output file must be specified
+";
+
+fn bench_thumbnails(c: &mut Criterion) {
+    let mut group = c.benchmark_group("thumbnail_data_url");
+    for size in [64 * 1024, 1024 * 1024, 8 * 1024 * 1024] {
+        // Incompressible-looking bytes, like an encoded photo
+        let data: Vec<u8> = (0..size).map(|i: usize| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| black_box(conversion::thumbnail_data_url("jpg", black_box(data))))
+        });
+    }
+    group.finish();
+}
+
+fn bench_transparency(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transparency_detection");
+    group.bench_function("imagemagick_channels", |b| {
+        b.iter(|| {
+            for channels in ["srgb", "srgba", "gray", "cmyk"] {
+                black_box(conversion::identify_channels_have_alpha(black_box(channels)));
+            }
+        })
+    });
+    group.bench_function("ffmpeg_stream_info", |b| {
+        b.iter(|| black_box(conversion::ffmpeg_stream_has_alpha(black_box(FFMPEG_PNG_INFO))))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_thumbnails, bench_transparency);
+criterion_main!(benches);
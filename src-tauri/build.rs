@@ -1,3 +1,13 @@
 fn main() {
+    // Installers with the bundled-tools feature need the tools staged first
+    if std::env::var_os("CARGO_FEATURE_BUNDLED_TOOLS").is_some() {
+        println!("cargo:rerun-if-changed=bundled-tools");
+        let staged = std::fs::read_dir("bundled-tools")
+            .map(|entries| entries.flatten().any(|entry| entry.path().is_dir()))
+            .unwrap_or(false);
+        if !staged {
+            panic!("The bundled-tools feature needs the tools in src-tauri/bundled-tools/<os>-<arch>/; run scripts/stage-bundled-tools.sh first");
+        }
+    }
     tauri_build::build()
 }
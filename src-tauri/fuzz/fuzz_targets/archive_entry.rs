@@ -0,0 +1,24 @@
+//! Entry names in a user's archive decide where extracted files are written, so no name may
+//! lead outside the extraction directory
+
+#![no_main]
+
+use convertsave_lib::archive;
+use libfuzzer_sys::fuzz_target;
+use std::path::{Component, Path};
+
+fuzz_target!(|data: (&str, Option<&str>)| {
+    let (name, root) = data;
+    let _ = archive::is_junk_entry(name);
+
+    if let Some(destination) = archive::entry_destination(name, root) {
+        assert!(
+            destination.components().all(|component| matches!(component, Component::Normal(_))),
+            "{:?} escapes the extraction directory",
+            destination
+        );
+        assert!(!destination.has_root());
+    }
+
+    let _ = archive::archive_entry_name(Path::new(name));
+});
@@ -0,0 +1,20 @@
+//! Extensions come from user file names: normalizing and routing them must never panic,
+//! and a normalized extension must stay normalized
+
+#![no_main]
+
+use convertsave_lib::conversion;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: (&str, &str)| {
+    let (input, output) = data;
+    let input_ext = conversion::normalize_extension(input);
+    let output_ext = conversion::normalize_extension(output);
+    assert_eq!(conversion::normalize_extension(&input_ext), input_ext);
+    assert!(!input_ext.starts_with('.'));
+
+    let _ = conversion::is_supported_input(&input_ext);
+    let _ = conversion::get_format_display_name(&input_ext);
+    let _ = conversion::get_format_color(&output_ext);
+    let _ = conversion::determine_conversion_tool(&input_ext, &output_ext);
+});
@@ -0,0 +1,14 @@
+//! Format detection reads the start of untrusted files and tool output about them
+
+#![no_main]
+
+use convertsave_lib::{conversion, metadata, probe};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = conversion::detect_aac_container(data);
+    if let Some(orientation) = metadata::exif_orientation(data) {
+        assert!((1..=8).contains(&orientation), "orientation {} out of range", orientation);
+    }
+    let _ = probe::parse_ffmpeg_banner(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,21 @@
+//! Conversion settings arrive as JSON from the frontend and from saved presets
+
+#![no_main]
+
+use convertsave_lib::conversion::{AudioPreset, ConversionSettings};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(settings) = serde_json::from_slice::<ConversionSettings>(data) {
+        if let Some(crop) = &settings.crop {
+            let _ = crop.region();
+        }
+        if let Some(watermark) = &settings.watermark {
+            let _ = watermark.validate();
+        }
+        let _ = settings.pdf_export.validate();
+        // Settings are saved with presets
+        let _ = serde_json::to_string(&settings);
+    }
+    let _ = serde_json::from_slice::<AudioPreset>(data);
+});
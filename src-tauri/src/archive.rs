@@ -0,0 +1,154 @@
+//! Archive entry paths - Maps entries of downloaded tool archives to where they're extracted
+//!
+//! Tool archives usually wrap everything in one versioned folder
+//! (`ImageMagick-7.1.2-8-portable-Q16-HDRI-x64/magick.exe`). Stripping that folder while
+//! extracting puts the binary where get_tool_path looks, without moving files afterwards.
+//!
+//! User archives (a .zip of photos to convert) are extracted with their folders kept, and
+//! the results are packed back under the same relative paths.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Splits an entry name into its path components (archives may use either separator)
+fn components(name: &str) -> Vec<&str> {
+    name.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect()
+}
+
+/// The single top-level folder every entry lives under, if there is one
+pub fn archive_root<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let mut root: Option<&str> = None;
+    let mut has_nested = false;
+
+    for name in names {
+        let parts = components(name);
+        let first = *parts.first()?;
+        match root {
+            Some(existing) if existing != first => return None,
+            _ => root = Some(first),
+        }
+        has_nested |= parts.len() > 1;
+    }
+
+    // A lone file at the top level isn't a wrapping folder
+    root.filter(|_| has_nested).map(str::to_string)
+}
+
+/// Where an entry goes, relative to the extraction directory, with `root` removed.
+///
+/// Returns `None` for the root folder itself and for entries that would escape the
+/// extraction directory (absolute paths or `..`).
+pub fn entry_destination(name: &str, root: Option<&str>) -> Option<PathBuf> {
+    let mut parts = components(name);
+    if let (Some(root), Some(first)) = (root, parts.first()) {
+        if *first == root {
+            parts.remove(0);
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    let relative: PathBuf = parts.iter().collect();
+    let is_safe = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe || Path::new(parts[0]).has_root() || parts[0].ends_with(':') {
+        return None;
+    }
+    Some(relative)
+}
+
+/// Checks if an entry of a user's archive is metadata rather than content: macOS resource
+/// forks (`__MACOSX/`, `._photo.jpg`) and other hidden files
+pub fn is_junk_entry(name: &str) -> bool {
+    let parts = components(name);
+    parts.first() == Some(&"__MACOSX") || parts.iter().any(|part| part.starts_with('.'))
+}
+
+/// The name a file gets inside an archive: its path relative to the packed folder, with `/`
+/// separators whatever the platform
+pub fn archive_entry_name(relative: &Path) -> String {
+    relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whole-number percentage of entries done, for progress messages
+pub fn percent_done(done: usize, total: usize) -> u32 {
+    if total == 0 {
+        100
+    } else {
+        ((done.min(total) * 100) / total) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_root_found() {
+        let names = ["ImageMagick-7.1/", "ImageMagick-7.1/magick.exe", "ImageMagick-7.1/modules/coders/png.dll"];
+        assert_eq!(archive_root(names), Some("ImageMagick-7.1".to_string()));
+    }
+
+    #[test]
+    fn test_archive_root_with_backslashes() {
+        let names = ["inkscape\\bin\\inkscape.exe", "inkscape\\share\\icons"];
+        assert_eq!(archive_root(names), Some("inkscape".to_string()));
+    }
+
+    #[test]
+    fn test_no_archive_root() {
+        assert_eq!(archive_root(["magick.exe", "modules/coders/png.dll"]), None);
+        assert_eq!(archive_root(["magick.exe"]), None);
+        assert_eq!(archive_root(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn test_entry_destination_strips_root() {
+        assert_eq!(
+            entry_destination("ImageMagick-7.1/modules/coders/png.dll", Some("ImageMagick-7.1")),
+            Some(PathBuf::from("modules").join("coders").join("png.dll"))
+        );
+        assert_eq!(entry_destination("ImageMagick-7.1/", Some("ImageMagick-7.1")), None);
+        assert_eq!(entry_destination("magick.exe", None), Some(PathBuf::from("magick.exe")));
+    }
+
+    #[test]
+    fn test_entry_destination_rejects_escapes() {
+        assert_eq!(entry_destination("../evil.dll", None), None);
+        assert_eq!(entry_destination("root/../../evil.dll", Some("root")), None);
+        assert_eq!(entry_destination("C:/Windows/evil.dll", None), None);
+    }
+
+    #[test]
+    fn test_junk_entries() {
+        assert!(is_junk_entry("__MACOSX/photos/._a.jpg"));
+        assert!(is_junk_entry("photos/._a.jpg"));
+        assert!(is_junk_entry(".DS_Store"));
+        assert!(!is_junk_entry("photos/a.jpg"));
+    }
+
+    #[test]
+    fn test_archive_entry_name() {
+        let relative = PathBuf::from("2024").join("june").join("a.jpg");
+        assert_eq!(archive_entry_name(&relative), "2024/june/a.jpg");
+        assert_eq!(archive_entry_name(Path::new("a.jpg")), "a.jpg");
+    }
+
+    #[test]
+    fn test_percent_done() {
+        assert_eq!(percent_done(0, 200), 0);
+        assert_eq!(percent_done(50, 200), 25);
+        assert_eq!(percent_done(200, 200), 100);
+        assert_eq!(percent_done(0, 0), 100);
+    }
+}
@@ -0,0 +1,187 @@
+//! Folder audit - What a folder holds and what converting it would save
+//!
+//! Counts files and bytes per format, estimates the space modern image formats would save
+//! from typical size ratios (the same kind of rule of thumb as the HEIC migration uses),
+//! and lists files nothing can convert. Nothing is decoded, so large folders scan quickly.
+
+use crate::conversion;
+use crate::migration;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Formats the savings estimate considers converting to
+pub const SAVINGS_TARGETS: &[&str] = &["webp", "avif", "heic", "jpg"];
+
+/// Typical output size relative to the source, at the default quality settings
+const SIZE_RATIOS: &[(&[&str], &str, f64)] = &[
+    (&["jpg", "jpeg"], "webp", 0.7),
+    (&["jpg", "jpeg"], "avif", 0.5),
+    (&["jpg", "jpeg"], "heic", migration::JPEG_TO_HEIC_SIZE_RATIO),
+    (&["png"], "webp", 0.5),
+    (&["png"], "avif", 0.4),
+    (&["png"], "heic", 0.4),
+    (&["png"], "jpg", 0.4),
+    (&["bmp", "dib", "tif", "tiff"], "webp", 0.08),
+    (&["bmp", "dib", "tif", "tiff"], "avif", 0.06),
+    (&["bmp", "dib", "tif", "tiff"], "heic", 0.06),
+    (&["bmp", "dib", "tif", "tiff"], "jpg", 0.1),
+    (&["gif"], "webp", 0.6),
+];
+
+/// Estimated size of a `source` file of `bytes` after converting to `target`, if it's a
+/// conversion that usually saves space
+pub fn projected_size(source: &str, target: &str, bytes: u64) -> Option<u64> {
+    SIZE_RATIOS
+        .iter()
+        .find(|(sources, to, _)| *to == target && sources.contains(&source))
+        .map(|(_, _, ratio)| (bytes as f64 * ratio).round() as u64)
+}
+
+/// Files of one format
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FormatSummary {
+    /// Lowercase extension ("" for files without one)
+    pub format: String,
+    pub count: usize,
+    pub total_bytes: u64,
+}
+
+/// What converting every file that would shrink to one format would save
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TargetSavings {
+    pub format: String,
+    pub file_count: usize,
+    pub current_bytes: u64,
+    pub projected_bytes: u64,
+    pub saved_bytes: u64,
+}
+
+/// Summary of a folder for the "convert this folder" screen
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FolderReport {
+    pub folder: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    /// Largest total first
+    pub formats: Vec<FormatSummary>,
+    /// Biggest saving first; targets that save nothing are left out
+    pub savings: Vec<TargetSavings>,
+    /// Files no conversion accepts
+    pub unsupported: Vec<String>,
+    pub unsupported_bytes: u64,
+}
+
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Builds the report from file paths and their sizes
+pub fn build_report(folder: &str, files: &[(PathBuf, u64)]) -> FolderReport {
+    let mut report = FolderReport { folder: folder.to_string(), file_count: files.len(), ..Default::default() };
+
+    for (path, bytes) in files {
+        let ext = extension_of(path);
+        report.total_bytes += bytes;
+
+        match report.formats.iter_mut().find(|summary| summary.format == ext) {
+            Some(summary) => {
+                summary.count += 1;
+                summary.total_bytes += bytes;
+            }
+            None => report.formats.push(FormatSummary { format: ext.clone(), count: 1, total_bytes: *bytes }),
+        }
+
+        if !conversion::is_supported_input(&ext) {
+            report.unsupported.push(path.to_string_lossy().to_string());
+            report.unsupported_bytes += bytes;
+        }
+    }
+
+    for target in SAVINGS_TARGETS {
+        let mut savings = TargetSavings { format: target.to_string(), ..Default::default() };
+        for summary in &report.formats {
+            if let Some(projected) = projected_size(&summary.format, target, summary.total_bytes) {
+                savings.file_count += summary.count;
+                savings.current_bytes += summary.total_bytes;
+                savings.projected_bytes += projected;
+            }
+        }
+        savings.saved_bytes = savings.current_bytes.saturating_sub(savings.projected_bytes);
+        if savings.saved_bytes > 0 {
+            report.savings.push(savings);
+        }
+    }
+
+    report.formats.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| a.format.cmp(&b.format)));
+    report.savings.sort_by(|a, b| b.saved_bytes.cmp(&a.saved_bytes));
+    report
+}
+
+/// Scans `folder` recursively (skipping hidden files) and builds its report
+pub fn scan_folder(folder: &Path) -> FolderReport {
+    let files: Vec<(PathBuf, u64)> = migration::collect_files(folder)
+        .into_iter()
+        .map(|path| {
+            let bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            (path, bytes)
+        })
+        .collect();
+    build_report(&folder.to_string_lossy(), &files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, u64)]) -> Vec<(PathBuf, u64)> {
+        entries.iter().map(|(path, bytes)| (PathBuf::from(path), *bytes)).collect()
+    }
+
+    #[test]
+    fn test_counts_per_format() {
+        let report = build_report("/photos", &files(&[
+            ("/photos/a.JPG", 1000),
+            ("/photos/b.jpg", 3000),
+            ("/photos/c.png", 500),
+            ("/photos/notes", 10),
+        ]));
+        assert_eq!(report.file_count, 4);
+        assert_eq!(report.total_bytes, 4510);
+        assert_eq!(report.formats[0], FormatSummary { format: "jpg".to_string(), count: 2, total_bytes: 4000 });
+        assert_eq!(report.formats[1].format, "png");
+        assert_eq!(report.formats[2].format, "");
+    }
+
+    #[test]
+    fn test_savings_by_target() {
+        let report = build_report("/photos", &files(&[("/photos/a.jpg", 1000), ("/photos/b.bmp", 1000), ("/photos/c.heic", 1000)]));
+        let heic = report.savings.iter().find(|savings| savings.format == "heic").unwrap();
+        // HEIC files are already as small as they get, so they aren't counted
+        assert_eq!(heic.file_count, 2);
+        assert_eq!(heic.current_bytes, 2000);
+        assert_eq!(heic.projected_bytes, 500 + 60);
+        assert_eq!(heic.saved_bytes, 1440);
+        // Biggest saving first
+        assert!(report.savings.windows(2).all(|pair| pair[0].saved_bytes >= pair[1].saved_bytes));
+        // Only the BMP shrinks as JPEG
+        assert_eq!(report.savings.iter().find(|savings| savings.format == "jpg").unwrap().file_count, 1);
+    }
+
+    #[test]
+    fn test_unsupported_files() {
+        let report = build_report("/mixed", &files(&[("/mixed/setup.exe", 700), ("/mixed/song.mp3", 100), ("/mixed/README", 5)]));
+        assert_eq!(report.unsupported, vec!["/mixed/setup.exe", "/mixed/README"]);
+        assert_eq!(report.unsupported_bytes, 705);
+        assert!(report.savings.is_empty());
+    }
+
+    #[test]
+    fn test_projected_size() {
+        assert_eq!(projected_size("png", "webp", 1000), Some(500));
+        assert_eq!(projected_size("heic", "jpg", 1000), None);
+        assert_eq!(projected_size("jpg", "jpg", 1000), None);
+    }
+}
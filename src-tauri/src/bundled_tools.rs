@@ -0,0 +1,109 @@
+//! Bundled tools - FFmpeg and ImageMagick shipped inside the installer
+//!
+//! Builds with the `bundled-tools` feature carry the tools as resources under
+//! `tools/<os>-<arch>/<tool>/` (see scripts/stage-bundled-tools.sh). On first run, and after
+//! each update, they are copied into the app data folder next to downloaded tools, since a
+//! signed macOS bundle is read-only. Everything here is also used to search tool folders,
+//! whichever way the tool got there.
+
+use std::path::{Path, PathBuf};
+
+/// Tools that can be bundled into the installer
+pub const BUNDLED_TOOLS: &[&str] = &["ffmpeg", "imagemagick"];
+
+/// Written into a tool's folder once a bundled copy is installed, holding the app version
+pub const STAMP_FILE: &str = ".bundled-version";
+
+/// Bundle folders for a platform (`std::env::consts` names), best match first. ARM Windows
+/// and Apple Silicon can fall back to x86_64 tools, which run under emulation (Rosetta), so
+/// a universal macOS bundle may carry one folder per architecture or only one.
+pub fn bundle_platform_dirs(os: &str, arch: &str) -> Vec<String> {
+    let mut dirs = vec![format!("{}-{}", os, arch)];
+    if arch == "aarch64" && (os == "windows" || os == "macos") {
+        dirs.push(format!("{}-x86_64", os));
+    }
+    dirs
+}
+
+/// Subfolders of a tool's folder that can hold its executable, in the order they're
+/// searched: downloads and bundles keep the layout of each tool's own archive
+pub fn tool_subdirs(tool_name: &str) -> &'static [&'static str] {
+    match tool_name {
+        // The extracted MSI keeps soffice in program/
+        "libreoffice" => &["", "program"],
+        // Portable Inkscape, Ghostscript's installer and the macOS ImageMagick use bin/
+        "imagemagick" | "inkscape" | "ghostscript" => &["", "bin"],
+        _ => &[""],
+    }
+}
+
+/// Where `exe_name` can be under a folder of tools (app data, a dev `tools/<platform>` folder,
+/// a bundle): in the tool's own folder, then loose in the root as older layouts had it
+pub fn tool_candidates(root: &Path, tool_name: &str, exe_name: &str) -> Vec<PathBuf> {
+    let tool_dir = root.join(tool_name);
+    let mut candidates: Vec<PathBuf> = tool_subdirs(tool_name)
+        .iter()
+        .map(|subdir| if subdir.is_empty() { tool_dir.join(exe_name) } else { tool_dir.join(subdir).join(exe_name) })
+        .collect();
+    candidates.push(root.join(exe_name));
+    candidates
+}
+
+/// Whether a bundled tool should be copied over the installed one: always on first run,
+/// again when the app was updated, and never over a tool the user downloaded or set up
+/// (a folder with files but no stamp)
+pub fn should_install(installed_stamp: Option<&str>, tool_dir_in_use: bool, app_version: &str) -> bool {
+    match installed_stamp {
+        Some(version) => version.trim() != app_version,
+        None => !tool_dir_in_use,
+    }
+}
+
+/// Files in an installed tool folder that macOS should re-sign after copying: executables
+/// in the folder and its bin/, plus every dylib
+pub fn needs_signing(relative: &Path) -> bool {
+    let is_dylib = relative.extension().is_some_and(|ext| ext == "dylib");
+    let depth = relative.components().count();
+    let in_bin = depth == 2 && relative.starts_with("bin");
+    is_dylib || depth == 1 || in_bin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_platform_dirs() {
+        assert_eq!(bundle_platform_dirs("windows", "x86_64"), vec!["windows-x86_64"]);
+        assert_eq!(bundle_platform_dirs("windows", "aarch64"), vec!["windows-aarch64", "windows-x86_64"]);
+        assert_eq!(bundle_platform_dirs("macos", "aarch64"), vec!["macos-aarch64", "macos-x86_64"]);
+        assert_eq!(bundle_platform_dirs("linux", "aarch64"), vec!["linux-aarch64"]);
+    }
+
+    #[test]
+    fn test_tool_candidates() {
+        let root = Path::new("/data/com.convertsave");
+        assert_eq!(
+            tool_candidates(root, "imagemagick", "magick"),
+            vec![root.join("imagemagick/magick"), root.join("imagemagick/bin/magick"), root.join("magick")]
+        );
+        assert_eq!(tool_candidates(root, "ffmpeg", "ffmpeg"), vec![root.join("ffmpeg/ffmpeg"), root.join("ffmpeg")]);
+        assert_eq!(tool_candidates(root, "libreoffice", "soffice")[1], root.join("libreoffice/program/soffice"));
+    }
+
+    #[test]
+    fn test_should_install() {
+        assert!(should_install(None, false, "0.6.1"));
+        assert!(!should_install(None, true, "0.6.1"));
+        assert!(should_install(Some("0.6.0\n"), true, "0.6.1"));
+        assert!(!should_install(Some("0.6.1"), true, "0.6.1"));
+    }
+
+    #[test]
+    fn test_needs_signing() {
+        assert!(needs_signing(Path::new("ffmpeg")));
+        assert!(needs_signing(Path::new("bin/magick")));
+        assert!(needs_signing(Path::new("lib/libMagickCore-7.Q16HDRI.10.dylib")));
+        assert!(!needs_signing(Path::new("etc/ImageMagick-7/policy.xml")));
+    }
+}
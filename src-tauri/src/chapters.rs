@@ -0,0 +1,238 @@
+//! Chapters - Audiobook chapters from the file itself or a cue sheet next to it
+//!
+//! M4B/M4A audiobooks carry chapter markers that FFmpeg reads (see probe.rs); long MP3s
+//! usually come with a `.cue` sheet instead. Either way the chapters can be kept in the
+//! converted file, passed to FFmpeg as an ffmetadata input, or used to split the book
+//! into one file per chapter.
+
+use crate::naming::{self, FileNameMode};
+use crate::probe::Chapter;
+use std::path::{Path, PathBuf};
+
+/// Cue sheet times are minutes:seconds:frames, with 75 frames per second (from audio CDs)
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+
+/// Formats that can be split by chapter
+pub const CHAPTER_INPUTS: &[&str] = &["m4b", "m4a", "mp3", "aac", "flac", "ogg", "opus", "wav", "mka"];
+
+/// A cue sheet value, without the quotes around it
+fn cue_value(rest: &str) -> String {
+    rest.trim().trim_matches('"').to_string()
+}
+
+/// "12:34:56" (minutes, seconds, frames) in seconds
+fn parse_cue_time(time: &str) -> Option<f64> {
+    let mut parts = time.trim().split(':').map(|part| part.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / CUE_FRAMES_PER_SECOND)
+}
+
+/// Chapters of a cue sheet: each TRACK starts at its INDEX 01 and ends where the next one
+/// starts; the last runs to the end of the file. The album TITLE before the first TRACK
+/// isn't a chapter title.
+pub fn parse_cue_sheet(text: &str) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut in_track = false;
+    let mut title = None;
+
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command.to_ascii_uppercase().as_str() {
+            "TRACK" => {
+                in_track = true;
+                title = None;
+            }
+            "TITLE" if in_track => {
+                title = Some(cue_value(rest)).filter(|title| !title.is_empty());
+            }
+            "INDEX" if in_track => {
+                let (number, time) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if number.parse::<u32>() != Ok(1) {
+                    continue;
+                }
+                if let Some(start_secs) = parse_cue_time(time) {
+                    if let Some(previous) = chapters.last_mut() {
+                        previous.end_secs = Some(start_secs);
+                    }
+                    chapters.push(Chapter { start_secs, end_secs: None, title: title.take() });
+                }
+            }
+            _ => {}
+        }
+    }
+    chapters
+}
+
+/// The cue sheet that goes with `media`: `book.cue` or `book.mp3.cue` in the same folder
+pub fn cue_sheet_for(media: &Path) -> Option<PathBuf> {
+    let with_ext = |path: PathBuf, ext: &str| {
+        let mut name = path.into_os_string();
+        name.push(ext);
+        PathBuf::from(name)
+    };
+    [".cue", ".CUE"]
+        .iter()
+        .flat_map(|ext| [with_ext(media.with_extension(""), ext), with_ext(media.to_path_buf(), ext)])
+        .find(|candidate| candidate.is_file())
+}
+
+/// Fills in the ends that aren't known (the last track of a cue sheet) with the next
+/// chapter's start or the length of the file, and drops chapters that end up empty
+pub fn resolve_chapter_ends(chapters: &[Chapter], duration_secs: Option<f64>) -> Vec<Chapter> {
+    let mut resolved = Vec::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        let end = chapter
+            .end_secs
+            .or_else(|| chapters.get(i + 1).map(|next| next.start_secs))
+            .or(duration_secs);
+        if let Some(end) = end.filter(|end| *end > chapter.start_secs) {
+            resolved.push(Chapter { end_secs: Some(end), ..chapter.clone() });
+        }
+    }
+    resolved
+}
+
+/// Escapes `=`, `;`, `#`, `\` and line breaks, which have a meaning in ffmetadata files
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// An ffmetadata file holding `chapters`, for `-f ffmetadata -i <file> -map_chapters 1`.
+/// Chapters need their ends resolved first.
+pub fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        let millis = |secs: f64| (secs * 1000.0).round() as u64;
+        text.push_str("\n[CHAPTER]\nTIMEBASE=1/1000\n");
+        text.push_str(&format!("START={}\n", millis(chapter.start_secs)));
+        text.push_str(&format!("END={}\n", millis(chapter.end_secs.unwrap_or(chapter.start_secs))));
+        if let Some(title) = &chapter.title {
+            text.push_str(&format!("title={}\n", escape_ffmetadata(title)));
+        }
+    }
+    text
+}
+
+/// Title of chapter `index` (from 0): its own, or "Chapter 3"
+pub fn chapter_title(chapter: &Chapter, index: usize) -> String {
+    chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", index + 1))
+}
+
+/// File stem for chapter `index` (from 0) of `count`: "03 - The Storm", numbered with as
+/// many digits as the last chapter needs (at least two) so the files sort in order
+pub fn chapter_file_stem(chapter: &Chapter, index: usize, count: usize) -> String {
+    let width = count.to_string().len().max(2);
+    let stem = format!("{:0width$} - {}", index + 1, chapter_title(chapter, index), width = width);
+    // Titles like "Part 1: The Storm" aren't valid file names everywhere
+    naming::sanitize_file_name(&stem, FileNameMode::Portable)
+}
+
+/// FFmpeg arguments cutting one chapter (with its end resolved) out of `input`, up to the
+/// output options: seeking before the input is fast, and the chapter markers and cover
+/// art of the whole book are left out
+pub fn chapter_split_args(input: &str, chapter: &Chapter) -> Vec<String> {
+    let duration = chapter.end_secs.unwrap_or(chapter.start_secs) - chapter.start_secs;
+    vec![
+        "-ss".to_string(),
+        format!("{:.3}", chapter.start_secs),
+        "-i".to_string(),
+        input.to_string(),
+        "-t".to_string(),
+        format!("{:.3}", duration),
+        "-map".to_string(),
+        "0:a".to_string(),
+        "-map_chapters".to_string(),
+        "-1".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(start_secs: f64, end_secs: Option<f64>, title: Option<&str>) -> Chapter {
+        Chapter { start_secs, end_secs, title: title.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_parse_cue_sheet() {
+        let cue = "\u{feff}REM GENRE Audiobook
+PERFORMER \"Jane Author\"
+TITLE \"The Whole Book\"
+FILE \"book.mp3\" MP3
+  TRACK 01 AUDIO
+    TITLE \"Opening Credits\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Chapter 1: The Storm\"
+    INDEX 00 30:04:00
+    INDEX 01 30:06:00
+  TRACK 03 AUDIO
+    INDEX 01 61:30:15
+";
+        assert_eq!(parse_cue_sheet(cue), vec![
+            chapter(0.0, Some(1806.0), Some("Opening Credits")),
+            chapter(1806.0, Some(3690.2), Some("Chapter 1: The Storm")),
+            chapter(3690.2, None, None),
+        ]);
+        assert!(parse_cue_sheet("TITLE \"No tracks\"").is_empty());
+        assert_eq!(parse_cue_time("01:02:03:04"), None);
+    }
+
+    #[test]
+    fn test_resolve_chapter_ends() {
+        let chapters = vec![chapter(0.0, None, None), chapter(10.0, Some(10.0), None), chapter(10.0, None, None)];
+        assert_eq!(resolve_chapter_ends(&chapters, Some(25.0)), vec![chapter(0.0, Some(10.0), None), chapter(10.0, Some(25.0), None)]);
+        assert!(resolve_chapter_ends(&[chapter(5.0, None, None)], None).is_empty());
+    }
+
+    #[test]
+    fn test_ffmetadata() {
+        let chapters = vec![chapter(0.0, Some(1806.0), Some("Intro; part=1")), chapter(1806.0, Some(3690.2), None)];
+        assert_eq!(
+            ffmetadata(&chapters),
+            ";FFMETADATA1\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1806000\ntitle=Intro\\; part\\=1\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=1806000\nEND=3690200\n"
+        );
+    }
+
+    #[test]
+    fn test_chapter_file_stem() {
+        let storm = chapter(0.0, Some(10.0), Some("Part 1: The Storm"));
+        assert_eq!(chapter_file_stem(&storm, 2, 12), "03 - Part 1_ The Storm");
+        assert_eq!(chapter_file_stem(&chapter(0.0, None, None), 6, 120), "007 - Chapter 7");
+    }
+
+    #[test]
+    fn test_chapter_split_args() {
+        let args = chapter_split_args("book.m4b", &chapter(1806.0, Some(3690.2), None));
+        assert_eq!(args[..6], ["-ss", "1806.000", "-i", "book.m4b", "-t", "1884.200"]);
+        assert!(args.windows(2).any(|pair| pair == ["-map_chapters", "-1"]));
+    }
+
+    #[test]
+    fn test_cue_sheet_for() {
+        let dir = std::env::temp_dir().join(format!("convertsave-chapters-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let book = dir.join("book.mp3");
+        std::fs::write(&book, b"audio").unwrap();
+        assert_eq!(cue_sheet_for(&book), None);
+
+        std::fs::write(dir.join("book.mp3.cue"), b"TRACK 01 AUDIO").unwrap();
+        assert_eq!(cue_sheet_for(&book), Some(dir.join("book.mp3.cue")));
+        std::fs::write(dir.join("book.cue"), b"TRACK 01 AUDIO").unwrap();
+        assert_eq!(cue_sheet_for(&book), Some(dir.join("book.cue")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+//! Checksums - SHA-256 of converted files, for handing off deliverables
+//!
+//! With `checksum` on, each output's SHA-256 is returned with the result; with
+//! `write_checksum_file` it's also saved next to the output as `<file>.sha256`, in the
+//! format `sha256sum -c` and `shasum -a 256 -c` read.
+
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Appended to the output's file name
+pub const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Lowercase hex SHA-256 of everything `reader` yields
+pub fn sha256_hex(mut reader: impl Read) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Lowercase hex SHA-256 of a file, read in chunks so large videos aren't loaded whole
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    sha256_hex(std::io::BufReader::new(file)).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// Where the checksum of `output` goes: `clip.mp4` -> `clip.mp4.sha256`
+pub fn checksum_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(CHECKSUM_SUFFIX);
+    output.with_file_name(name)
+}
+
+/// A checksum file line: the hash, two spaces and the file name (relative, so the pair can
+/// be moved together)
+pub fn checksum_line(hash: &str, output: &Path) -> String {
+    let name = output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    format!("{}  {}\n", hash, name)
+}
+
+/// The hash and file name of the first line of a checksum file. Binary mode (`hash *name`)
+/// is accepted too.
+pub fn parse_checksum_line(text: &str) -> Option<(String, String)> {
+    let line = text.lines().find(|line| !line.trim().is_empty())?;
+    let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+    let name = name.trim_start().trim_start_matches('*');
+    let valid = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    (valid && !name.is_empty()).then(|| (hash.to_ascii_lowercase(), name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(sha256_hex(&b""[..]).unwrap(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256_hex(&b"abc"[..]).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn test_checksum_file() {
+        let output = Path::new("/out/clip.mp4");
+        assert_eq!(checksum_path(output), Path::new("/out/clip.mp4.sha256"));
+
+        let hash = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let line = checksum_line(hash, output);
+        assert_eq!(line, format!("{}  clip.mp4\n", hash));
+        assert_eq!(parse_checksum_line(&line), Some((hash.to_string(), "clip.mp4".to_string())));
+        assert_eq!(
+            parse_checksum_line(&format!("{} *clip.mp4", hash.to_uppercase())),
+            Some((hash.to_string(), "clip.mp4".to_string()))
+        );
+        assert_eq!(parse_checksum_line("not-a-hash  clip.mp4"), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Configuration layers - A machine-wide config under each user's own
+//!
+//! IT can put a config.json where every account on the machine reads it (/etc/convertsave
+//! on Linux, /Library/Application Support/ConvertSave on macOS, %ProgramData%\ConvertSave
+//! on Windows). It has the same keys as the user's config and fills in whatever the user
+//! hasn't set; objects such as the feature flags merge key by key, so users keep their own
+//! settings next to the ones IT set. Keys listed under "locked" (dotted for nested keys, as
+//! in "feature_flags.plugins") always take the machine's value, and can't be saved over.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Key of the machine config listing the keys users can't override
+pub const LOCKED_KEY: &str = "locked";
+
+pub const MACHINE_CONFIG_FILE: &str = "config.json";
+
+/// Folder of the machine config on `os`. `program_data` is the ProgramData known folder
+/// on Windows (not the %ProgramData% variable, which users can change).
+pub fn machine_config_dir(os: &str, program_data: Option<&str>) -> Option<PathBuf> {
+    match os {
+        "linux" => Some(PathBuf::from("/etc/convertsave")),
+        "macos" => Some(PathBuf::from("/Library/Application Support/ConvertSave")),
+        "windows" => program_data.filter(|dir| !dir.is_empty()).map(|dir| PathBuf::from(dir).join("ConvertSave")),
+        _ => None,
+    }
+}
+
+/// The machine layer: its settings, and which of them are locked
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MachineConfig {
+    pub values: Map<String, Value>,
+    pub locked: BTreeSet<String>,
+}
+
+impl MachineConfig {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut values = match serde_json::from_str(contents) {
+            Ok(Value::Object(values)) => values,
+            Ok(_) => return Err("The machine config is not a JSON object".to_string()),
+            Err(e) => return Err(format!("Failed to parse the machine config: {}", e)),
+        };
+        let locked = match values.remove(LOCKED_KEY) {
+            None => BTreeSet::new(),
+            Some(Value::Array(keys)) => keys.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            Some(_) => return Err(format!("\"{}\" in the machine config must be a list of keys", LOCKED_KEY)),
+        };
+        Ok(MachineConfig { values, locked })
+    }
+
+    /// Whether `key` is locked, by itself or through an object it's in
+    pub fn is_locked(&self, key: &str) -> bool {
+        self.locked.iter().any(|locked| key == locked || key.starts_with(&format!("{}.", locked)))
+    }
+
+    /// The config in effect: the user's settings over the machine's, then the locked keys
+    /// set back to the machine's values. A null in the user's config counts as unset.
+    pub fn resolve(&self, user: &Value) -> Value {
+        let mut effective = merge(&Value::Object(self.values.clone()), user);
+        for key in &self.locked {
+            set_path(&mut effective, key, get_path(&Value::Object(self.values.clone()), key).cloned());
+        }
+        effective
+    }
+
+    /// Locked keys whose value in `effective` isn't the machine's, i.e. that a save would change
+    pub fn locked_changes(&self, effective: &Value) -> Vec<String> {
+        let machine = Value::Object(self.values.clone());
+        let value = |config: &Value, key: &str| get_path(config, key).filter(|value| !value.is_null()).cloned();
+        self.locked.iter().filter(|key| value(effective, key) != value(&machine, key)).cloned().collect()
+    }
+
+    /// What to write to the user's config: `effective` without the locked keys and without
+    /// what it shares with the machine config, so later changes by IT still come through
+    pub fn user_layer(&self, effective: &Value) -> Value {
+        let mut user = difference(effective, &Value::Object(self.values.clone())).unwrap_or_else(|| Value::Object(Map::new()));
+        for key in &self.locked {
+            set_path(&mut user, key, None);
+        }
+        user
+    }
+}
+
+/// `over` merged onto `base`: objects key by key, anything else replaced unless `over` is null
+fn merge(base: &Value, over: &Value) -> Value {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            let mut merged = base.clone();
+            for (key, value) in over {
+                let value = match merged.get(key) {
+                    Some(existing) => merge(existing, value),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        (base, Value::Null) => base.clone(),
+        (_, over) => over.clone(),
+    }
+}
+
+/// The parts of `value` that differ from `base`, or None when there are none
+fn difference(value: &Value, base: &Value) -> Option<Value> {
+    match (value, base) {
+        (Value::Object(value), Value::Object(base)) => {
+            let differing: Map<String, Value> = value
+                .iter()
+                .filter_map(|(key, value)| match base.get(key) {
+                    Some(base) => difference(value, base).map(|value| (key.clone(), value)),
+                    None => Some((key.clone(), value.clone())),
+                })
+                .collect();
+            (!differing.is_empty()).then_some(Value::Object(differing))
+        }
+        (value, base) if value == base => None,
+        (value, _) => Some(value.clone()),
+    }
+}
+
+fn get_path<'a>(config: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.').try_fold(config, |value, part| value.get(part))
+}
+
+/// Sets the value at a dotted key, creating objects on the way, or removes it for None
+fn set_path(config: &mut Value, key: &str, value: Option<Value>) {
+    let (parents, last) = match key.rsplit_once('.') {
+        Some((parents, last)) => (Some(parents), last),
+        None => (None, key),
+    };
+    let mut object = config;
+    for part in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        if value.is_none() && object.get(part).is_none() {
+            return;
+        }
+        if !object.is_object() {
+            *object = Value::Object(Map::new());
+        }
+        object = object.as_object_mut().unwrap().entry(part).or_insert_with(|| Value::Object(Map::new()));
+    }
+    match (object.as_object_mut(), value) {
+        (Some(object), Some(value)) => {
+            object.insert(last.to_string(), value);
+        }
+        (Some(object), None) => {
+            object.remove(last);
+        }
+        (None, _) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn machine() -> MachineConfig {
+        MachineConfig::parse(
+            r#"{
+                "proxy": "http://proxy.corp:3128",
+                "ffmpeg_path": "/opt/tools/ffmpeg",
+                "name_template": "{name}-converted",
+                "feature_flags": { "plugins": false, "pandoc": true },
+                "locked": ["proxy", "feature_flags.plugins"]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let machine = machine();
+        assert!(!machine.values.contains_key(LOCKED_KEY));
+        assert!(machine.is_locked("proxy"));
+        assert!(machine.is_locked("feature_flags.plugins"));
+        assert!(!machine.is_locked("feature_flags.pandoc"));
+        assert!(MachineConfig::parse("[]").is_err());
+        assert!(MachineConfig::parse(r#"{"locked": "proxy"}"#).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let user = json!({
+            "proxy": null,
+            "ffmpeg_path": null,
+            "name_template": "{name}-mine",
+            "feature_flags": { "plugins": true, "libvips": true }
+        });
+        let effective = machine().resolve(&user);
+        assert_eq!(effective["proxy"], "http://proxy.corp:3128");
+        // Unset by the user, so the machine's
+        assert_eq!(effective["ffmpeg_path"], "/opt/tools/ffmpeg");
+        assert_eq!(effective["name_template"], "{name}-mine");
+        assert_eq!(effective["feature_flags"], json!({ "plugins": false, "pandoc": true, "libvips": true }));
+
+        let overridden = machine().resolve(&json!({ "proxy": "http://elsewhere:8080" }));
+        assert_eq!(overridden["proxy"], "http://proxy.corp:3128");
+    }
+
+    #[test]
+    fn test_user_layer() {
+        let machine = machine();
+        let mut effective = machine.resolve(&json!({ "calibre_path": null }));
+        effective["name_template"] = json!("{name}-mine");
+        effective["feature_flags"]["libvips"] = json!(true);
+        assert_eq!(machine.locked_changes(&effective), Vec::<String>::new());
+        assert_eq!(
+            machine.user_layer(&effective),
+            json!({ "calibre_path": null, "name_template": "{name}-mine", "feature_flags": { "libvips": true } })
+        );
+
+        effective["feature_flags"]["plugins"] = json!(true);
+        assert_eq!(machine.locked_changes(&effective), vec!["feature_flags.plugins".to_string()]);
+    }
+
+    #[test]
+    fn test_machine_config_dir() {
+        assert_eq!(machine_config_dir("linux", None), Some(PathBuf::from("/etc/convertsave")));
+        assert_eq!(machine_config_dir("windows", Some("C:\\ProgramData")), Some(PathBuf::from("C:\\ProgramData").join("ConvertSave")));
+        assert_eq!(machine_config_dir("windows", None), None);
+    }
+}
@@ -2,7 +2,20 @@
 //! 
 //! This module extracts the core conversion logic from main.rs to make it testable.
 
+use crate::error_summary::ErrorSummary;
+use crate::feature_flags;
+use crate::filter_graph::{self, Filter, FilterGraph, LoudnessMeasurement};
+use crate::magick::{MagickCommand, MagickOp};
+use crate::metadata::FileBy;
+use crate::naming::FileNameMode;
+use crate::probe::{MediaInfo, StreamInfo, StreamKind, SubtitleStreamInfo};
+use crate::scheduler::JobPriority;
+use crate::spreadsheet::SpreadsheetLayout;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::Path;
+use std::sync::OnceLock;
 
 /// Represents a conversion option that can be presented to the user
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -11,10 +24,767 @@ pub struct ConversionOption {
     pub tool: String,
     pub display_name: String,
     pub color: String,
+    /// The output format's category in the format registry
+    #[serde(default)]
+    pub category: String,
 }
 
-/// Feature flag for Pandoc support
-pub const ENABLE_PANDOC: bool = false;
+/// Per-conversion settings sent by the frontend alongside the raw advanced options
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ConversionSettings {
+    /// Keep vector data intact (via Inkscape) instead of rasterizing through ImageMagick
+    pub vector_output: bool,
+    /// How many times an animation plays (0 = loop forever, None = keep the tool default)
+    pub loop_count: Option<u32>,
+    /// Output frame rate for animations (None = keep the source timing)
+    pub frame_rate: Option<f32>,
+    /// Viewport width in pixels for HTML snapshots
+    pub snapshot_width: Option<u32>,
+    /// Viewport height in pixels for HTML snapshots (ignored when `full_page` is set)
+    pub snapshot_height: Option<u32>,
+    /// Capture the whole scrollable page instead of just the viewport
+    pub full_page: bool,
+    /// Encoder quality (1-100) for lossy image outputs
+    pub quality: Option<u8>,
+    /// Maximum output size in kilobytes for image compression; quality is searched to fit
+    pub target_size_kb: Option<u64>,
+    /// Desired output size in megabytes for video/audio; bitrate is derived from the duration
+    pub target_size_mb: Option<f64>,
+    /// Remove EXIF/IPTC/XMP metadata (camera, GPS, timestamps) from the output
+    pub strip_metadata: bool,
+    /// Carry tags, capture date and GPS over from the source (ignored when `strip_metadata` is set)
+    pub preserve_metadata: bool,
+    /// Save a `.convertsave.json` next to the output with the tool version, command line and
+    /// these settings (see sidecar)
+    pub write_sidecar: bool,
+    /// Return the SHA-256 of the output with the result (see checksum)
+    pub checksum: bool,
+    /// Also save it next to the output as `<file>.sha256`
+    pub write_checksum_file: bool,
+    /// Converting the same input twice gives the same bytes, where the format allows: no
+    /// metadata, timestamps fixed to DETERMINISTIC_EPOCH, bit-exact FFmpeg encoding
+    pub deterministic: bool,
+    /// Normalize integrated loudness to this target in LUFS (e.g. -16 for podcasts)
+    pub loudness_lufs: Option<f32>,
+    /// Measure the loudness in a first pass so the second normalizes linearly: slower, but
+    /// lands on the target and keeps the dynamics, which one pass compresses
+    pub loudness_two_pass: bool,
+    /// Cut silence from the start of audio outputs, and any over TRAILING_SILENCE_SECS after
+    /// that, the end included (voice memos, recordings)
+    pub trim_silence: bool,
+    /// Fade audio outputs in over this many milliseconds
+    pub fade_in_ms: Option<u32>,
+    /// Fade audio outputs out over their last this many milliseconds
+    pub fade_out_ms: Option<u32>,
+    /// Output audio channels (1 = mono, 2 = stereo, None = keep the source layout)
+    pub audio_channels: Option<u8>,
+    /// Output audio bitrate in kbps (None = encoder default)
+    pub audio_bitrate_kbps: Option<u32>,
+    /// Carry chapter markers over from the source
+    pub keep_chapters: bool,
+    /// Tags written into the output (ID3 for MP3, iTunes atoms for M4A)
+    pub audio_tags: AudioTags,
+    /// Output file name template such as `{name}-{width}x{height}` (None = the saved default)
+    pub name_template: Option<String>,
+    /// Value of `{counter}` for this file, its position in the batch (starting at 1)
+    pub name_counter: Option<u32>,
+    /// Output width in pixels when rasterizing SVGs (height follows the aspect ratio if unset)
+    pub raster_width: Option<u32>,
+    /// Output height in pixels when rasterizing SVGs (width follows the aspect ratio if unset)
+    pub raster_height: Option<u32>,
+    /// Resolution for rasterizing SVGs without an explicit size (96 = the SVG's own size)
+    pub raster_dpi: Option<f32>,
+    /// Background jobs (e.g. from a watch folder) wait while user-started jobs are queued
+    pub priority: JobPriority,
+    /// Seconds to wait for an input that is still downloading or being written before
+    /// failing with "file in use" (None = input_state::DEFAULT_INPUT_WAIT_SECS, 0 = don't wait)
+    pub input_wait_secs: Option<u32>,
+    /// Stop a tool that runs longer than this many minutes (None or 0 = no limit)
+    pub timeout_minutes: Option<u32>,
+    /// Stop FFmpeg when its progress stops for this many minutes (None =
+    /// watchdog::DEFAULT_STALL_MINUTES, 0 = never)
+    pub stall_minutes: Option<u32>,
+    /// Run tools at low CPU priority (nice on Unix, below normal on Windows)
+    pub low_priority: bool,
+    /// Threads FFmpeg may use (None = all, or half the cores with low_priority)
+    pub ffmpeg_threads: Option<u32>,
+    /// Adapt output names for USB drives and network shares (Portable), optionally in plain ASCII
+    pub file_name_mode: FileNameMode,
+    /// LibreOffice PDF export options for office documents converted to PDF
+    pub pdf_export: PdfExportOptions,
+    /// Page layout for spreadsheets converted to PDF
+    pub spreadsheet_layout: SpreadsheetLayout,
+    /// External subtitle file (SRT/ASS) to add to a video output
+    pub subtitles: Option<ExternalSubtitles>,
+    /// Audio and subtitle streams to keep (None = FFmpeg's default pick of one of each)
+    pub stream_selection: Option<StreamSelection>,
+    /// Leave photos as stored instead of rotating them upright from their EXIF orientation
+    pub keep_orientation: bool,
+    /// Part of the picture to keep, for images and video
+    pub crop: Option<CropOptions>,
+    /// Logo or text drawn over images and video
+    pub watermark: Option<Watermark>,
+    /// ICC profile handling and bit depth for images
+    pub color: ColorOptions,
+    /// Development of camera RAW photos
+    pub raw: RawOptions,
+    /// File image outputs into subfolders by capture date (YYYY/MM) or camera
+    pub file_by: Option<FileBy>,
+}
+
+impl ConversionSettings {
+    /// Whether embedded metadata is dropped: asked for, or needed for deterministic output
+    pub fn strips_metadata(&self) -> bool {
+        self.strip_metadata || self.deterministic
+    }
+
+    /// Whether the source's metadata is copied to the output
+    pub fn keeps_metadata(&self) -> bool {
+        self.preserve_metadata && !self.strips_metadata()
+    }
+
+    /// A copy that's safe to write to disk: the PDF open password is left out
+    pub fn without_secrets(&self) -> ConversionSettings {
+        let mut settings = self.clone();
+        settings.pdf_export.password = None;
+        settings
+    }
+}
+
+/// Camera RAW formats, developed by LibRaw when it's installed
+pub const CAMERA_RAW_INPUTS: &[&str] = &[
+    "arw", "cr2", "cr3", "crw", "dng", "nef", "nrw", "orf", "raf", "raw", "rw2", "rwl", "srw",
+];
+
+pub fn is_camera_raw(ext: &str) -> bool {
+    CAMERA_RAW_INPUTS.contains(&ext)
+}
+
+/// Where a RAW photo's white balance comes from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RawWhiteBalance {
+    /// As shot, from the camera's settings
+    #[default]
+    Camera,
+    /// Estimated from the picture itself
+    Auto,
+}
+
+/// Exposure compensation LibRaw can apply, in stops
+pub const RAW_EXPOSURE_RANGE: std::ops::RangeInclusive<f32> = -2.0..=3.0;
+
+/// How camera RAW photos are developed
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RawOptions {
+    pub white_balance: RawWhiteBalance,
+    /// Exposure compensation in stops (see RAW_EXPOSURE_RANGE)
+    pub exposure: f32,
+    /// Bits per channel of the output, 8 or 16 (16 only for TIFF, PNG and EXR)
+    pub bit_depth: u8,
+    /// Bilinear demosaicing instead of AHD: much faster, softer detail
+    pub fast_demosaic: bool,
+}
+
+impl Default for RawOptions {
+    fn default() -> Self {
+        RawOptions { white_balance: RawWhiteBalance::default(), exposure: 0.0, bit_depth: 8, fast_demosaic: false }
+    }
+}
+
+impl RawOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !RAW_EXPOSURE_RANGE.contains(&self.exposure) {
+            return Err(format!(
+                "RAW exposure must be between {} and +{} stops",
+                RAW_EXPOSURE_RANGE.start(),
+                RAW_EXPOSURE_RANGE.end()
+            ));
+        }
+        if ![8, 16].contains(&self.bit_depth) {
+            return Err(format!("Unsupported RAW bit depth {}; use 8 or 16", self.bit_depth));
+        }
+        Ok(())
+    }
+}
+
+/// Outputs that can hold 16 bits per channel
+pub const DEEP_COLOR_OUTPUTS: &[&str] = &["tif", "tiff", "png", "exr"];
+
+/// What happens to the embedded ICC color profile
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IccProfileMode {
+    /// Keep the profile in the output: the source's, or sRGB after converting to it
+    #[default]
+    Embed,
+    /// Leave the profile out. Viewers then assume sRGB, so this is best combined with
+    /// `convert_to_srgb`.
+    Strip,
+}
+
+/// Color management for images. Stripping metadata (`strip_metadata`) removes the ICC
+/// profile as well, after any conversion to sRGB.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ColorOptions {
+    /// Convert colors from the embedded profile (Adobe RGB, ProPhoto, CMYK, ...) to sRGB.
+    /// Images without a profile are taken to be sRGB already.
+    pub convert_to_srgb: bool,
+    pub icc_profile: IccProfileMode,
+    /// Write 16 bits per channel to TIFF, PNG and EXR, so 16-bit sources keep their precision
+    pub keep_16_bit: bool,
+}
+
+impl ColorOptions {
+    /// True when the options change anything
+    pub fn is_active(&self) -> bool {
+        self.convert_to_srgb || self.icc_profile == IccProfileMode::Strip || self.keep_16_bit
+    }
+}
+
+/// Aspect ratios offered as crop presets (square, portrait and landscape social media sizes)
+pub const CROP_ASPECT_PRESETS: &[&str] = &["1:1", "4:5", "16:9", "9:16", "4:3", "3:2"];
+
+/// What to crop: a rectangle in pixels of the upright picture, or an aspect ratio that keeps
+/// the largest centered area of that shape. The rectangle wins when both are given.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct CropOptions {
+    pub x: u32,
+    pub y: u32,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Such as "16:9" or "1:1" (see CROP_ASPECT_PRESETS)
+    pub aspect: Option<String>,
+}
+
+/// A validated crop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropRegion {
+    Rect { x: u32, y: u32, width: u32, height: u32 },
+    /// Width to height, e.g. 16 and 9
+    Aspect { width: u32, height: u32 },
+}
+
+impl CropOptions {
+    /// The crop to apply, or None when the options don't ask for one
+    pub fn region(&self) -> Result<Option<CropRegion>, String> {
+        match (self.width, self.height) {
+            (Some(0), _) | (_, Some(0)) => Err("The crop width and height must be at least 1 pixel".to_string()),
+            (Some(width), Some(height)) => Ok(Some(CropRegion::Rect { x: self.x, y: self.y, width, height })),
+            (Some(_), None) | (None, Some(_)) => Err("Give both a width and a height to crop to".to_string()),
+            (None, None) => match self.aspect.as_deref().map(str::trim).filter(|aspect| !aspect.is_empty()) {
+                Some(aspect) => parse_aspect_ratio(aspect)
+                    .map(|(width, height)| Some(CropRegion::Aspect { width, height }))
+                    .ok_or_else(|| format!("Unknown aspect ratio \"{}\"; use a ratio such as 16:9", aspect)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+impl CropRegion {
+    /// The rectangle cut from a `width` x `height` picture, kept inside it
+    pub fn rect_within(self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        match self {
+            CropRegion::Rect { x, y, width: crop_width, height: crop_height } => {
+                let x = x.min(width.saturating_sub(1));
+                let y = y.min(height.saturating_sub(1));
+                (x, y, crop_width.min(width - x), crop_height.min(height - y))
+            }
+            CropRegion::Aspect { width: ratio_width, height: ratio_height } => {
+                let (w, h) = (width as u64, height as u64);
+                let (ratio_width, ratio_height) = (ratio_width as u64, ratio_height as u64);
+                let crop_width = w.min(h * ratio_width / ratio_height).max(1) as u32;
+                let crop_height = h.min(w * ratio_height / ratio_width).max(1) as u32;
+                ((width - crop_width) / 2, (height - crop_height) / 2, crop_width, crop_height)
+            }
+        }
+    }
+}
+
+/// Corner or edge of the picture a watermark is placed at
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+
+impl WatermarkPosition {
+    /// ImageMagick `-gravity` for the position
+    pub fn gravity(self) -> &'static str {
+        match self {
+            WatermarkPosition::TopLeft => "northwest",
+            WatermarkPosition::Top => "north",
+            WatermarkPosition::TopRight => "northeast",
+            WatermarkPosition::Left => "west",
+            WatermarkPosition::Center => "center",
+            WatermarkPosition::Right => "east",
+            WatermarkPosition::BottomLeft => "southwest",
+            WatermarkPosition::Bottom => "south",
+            WatermarkPosition::BottomRight => "southeast",
+        }
+    }
+
+    /// Column and row: 0 = left/top, 1 = center, 2 = right/bottom
+    fn cell(self) -> (u8, u8) {
+        match self {
+            WatermarkPosition::TopLeft => (0, 0),
+            WatermarkPosition::Top => (1, 0),
+            WatermarkPosition::TopRight => (2, 0),
+            WatermarkPosition::Left => (0, 1),
+            WatermarkPosition::Center => (1, 1),
+            WatermarkPosition::Right => (2, 1),
+            WatermarkPosition::BottomLeft => (0, 2),
+            WatermarkPosition::Bottom => (1, 2),
+            WatermarkPosition::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// A logo or line of text drawn over images and video. Saved with presets as part of
+/// their settings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Watermark {
+    /// Picture to draw, ideally a PNG with transparency
+    pub image_path: Option<String>,
+    /// Text to draw instead of a picture
+    pub text: Option<String>,
+    pub position: WatermarkPosition,
+    /// 0 (invisible) to 1 (opaque)
+    pub opacity: f32,
+    /// Picture width as a share of the frame width, or text height as a share of the frame
+    /// height (None = 20% for pictures, 5% for text)
+    pub scale: Option<f32>,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Watermark { image_path: None, text: None, position: WatermarkPosition::default(), opacity: 0.5, scale: None }
+    }
+}
+
+/// Share of the frame's shorter side left between a watermark and the edge
+const WATERMARK_MARGIN: f32 = 0.03;
+
+impl Watermark {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.image(), self.text()) {
+            (Some(_), Some(_)) => return Err("Choose either a watermark picture or watermark text, not both".to_string()),
+            (None, None) => return Err("Choose a picture or enter text for the watermark".to_string()),
+            _ => {}
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err("The watermark opacity must be between 0 and 1".to_string());
+        }
+        if let Some(scale) = self.scale {
+            if !(scale > 0.0 && scale <= 1.0) {
+                return Err("The watermark size must be more than 0% and at most 100% of the picture".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn image(&self) -> Option<&str> {
+        self.image_path.as_deref().map(str::trim).filter(|path| !path.is_empty())
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref().filter(|text| !text.trim().is_empty())
+    }
+
+    fn scale_or_default(&self) -> f32 {
+        self.scale.unwrap_or(if self.text().is_some() { 0.05 } else { 0.2 })
+    }
+
+    /// Width in pixels of a picture watermark on a `width` pixel wide frame
+    pub fn image_width(&self, width: u32) -> u32 {
+        ((width as f32 * self.scale_or_default()).round() as u32).max(1)
+    }
+
+    /// Text height in pixels on a `height` pixel tall frame
+    pub fn font_size(&self, height: u32) -> u32 {
+        ((height as f32 * self.scale_or_default()).round() as u32).max(1)
+    }
+
+    /// Pixels between the watermark and the edge of a `width` x `height` frame
+    pub fn margin(&self, width: u32, height: u32) -> u32 {
+        (width.min(height) as f32 * WATERMARK_MARGIN).round() as u32
+    }
+
+    /// Top-left corner of an `item_width` x `item_height` watermark on a `width` x `height`
+    /// frame, kept inside the frame
+    pub fn origin(&self, width: u32, height: u32, item_width: u32, item_height: u32) -> (u32, u32) {
+        let margin = self.margin(width, height);
+        let place = |cell: u8, size: u32, item: u32| {
+            let room = size.saturating_sub(item);
+            match cell {
+                0 => margin.min(room),
+                1 => room / 2,
+                _ => room.saturating_sub(margin),
+            }
+        };
+        let (column, row) = self.position.cell();
+        (place(column, width, item_width), place(row, height, item_height))
+    }
+}
+
+/// Streams a conversion keeps, by their index in the input (see `get_streams`). Video is
+/// always kept; an empty list drops every stream of that kind.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct StreamSelection {
+    pub audio: Vec<u32>,
+    pub subtitles: Vec<u32>,
+}
+
+/// How an external subtitle file is added to a video
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleMode {
+    /// As a separate track players can turn on and off
+    #[default]
+    Mux,
+    /// Drawn into the picture, so every player shows them (re-encodes the video)
+    Burn,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ExternalSubtitles {
+    pub path: String,
+    pub mode: SubtitleMode,
+}
+
+/// LibreOffice PDF export options. The defaults leave LibreOffice's own settings alone.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct PdfExportOptions {
+    /// Also embed the 14 standard PDF fonts (Times, Helvetica, ...), which viewers otherwise
+    /// substitute with lookalikes that can reflow text
+    pub embed_standard_fonts: bool,
+    /// Archival PDF/A part 1, 2 or 3 (conformance level b)
+    pub pdf_a: Option<u8>,
+    /// Keep images lossless instead of re-compressing them as JPEG, at their full resolution
+    pub lossless_images: bool,
+    /// Password needed to open the PDF
+    pub password: Option<String>,
+}
+
+impl PdfExportOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(part) = self.pdf_a {
+            if !(1..=3).contains(&part) {
+                return Err(format!("Unknown PDF/A version {}; use 1, 2 or 3", part));
+            }
+            // PDF/A must be readable without a key
+            if self.password.is_some() {
+                return Err("PDF/A files can't be password protected".to_string());
+            }
+        }
+        if self.password.as_deref().is_some_and(str::is_empty) {
+            return Err("The PDF password can't be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Descriptive tags for audio outputs. Empty fields are left untouched.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<String>,
+    pub year: Option<String>,
+    pub genre: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// A built-in bundle of output format and settings, e.g. "Podcast MP3"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AudioPreset {
+    pub id: String,
+    pub name: String,
+    pub output_format: String,
+    pub settings: ConversionSettings,
+}
+
+/// Options for shrinking an existing animated GIF/WebP without changing its format
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct AnimationOptimizeOptions {
+    /// How lossy the optimization may be, 0 (lossless) to 100
+    pub lossy: u8,
+    /// Reduce the palette to this many colors (GIF only, 2-256)
+    pub colors: Option<u32>,
+    /// Keep only every Nth frame (2 = drop every other frame)
+    pub keep_every: Option<u32>,
+}
+
+/// Size comparison returned after an optimization pass
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OptimizationReport {
+    pub output_path: String,
+    pub original_size: u64,
+    pub optimized_size: u64,
+    /// Bytes saved (negative if the output grew)
+    pub saved_bytes: i64,
+    /// Percentage of the original size saved
+    pub saved_percent: f64,
+}
+
+impl OptimizationReport {
+    pub fn new(output_path: String, original_size: u64, optimized_size: u64) -> Self {
+        let saved_bytes = original_size as i64 - optimized_size as i64;
+        let saved_percent = if original_size == 0 {
+            0.0
+        } else {
+            (saved_bytes as f64 / original_size as f64 * 1000.0).round() / 10.0
+        };
+
+        Self {
+            output_path,
+            original_size,
+            optimized_size,
+            saved_bytes,
+            saved_percent,
+        }
+    }
+}
+
+/// Statistics for a finished conversion, returned to the frontend by convert_file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConversionResult {
+    pub output_path: String,
+    pub elapsed_ms: u64,
+    pub input_size: u64,
+    pub output_size: u64,
+    /// Output size divided by input size (0.25 = a quarter of the original)
+    pub compression_ratio: f64,
+    /// The command that produced the output, as it could be pasted into a terminal
+    /// (None when the output wasn't made by a single external command)
+    pub command_line: Option<String>,
+    /// The tool that did the conversion (e.g. "ffmpeg"), see tool_display_name
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// What was lost on the way, e.g. transparency filled with white
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Tools that stood in for a preferred one that isn't installed
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+    /// What the tool warned about while the conversion still succeeded
+    #[serde(default)]
+    pub tool_warnings: Vec<ToolWarning>,
+    /// Lowercase hex SHA-256 of the output, when the checksum setting is on
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// A warning a tool printed during a successful conversion, e.g. FFmpeg's "non-monotonic DTS"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolWarning {
+    /// What printed it, e.g. "mp4" from `[mp4 @ 0x7f...]`
+    pub component: Option<String>,
+    pub message: String,
+    /// How often it was printed
+    pub count: u32,
+}
+
+impl ConversionResult {
+    pub fn new(
+        output_path: String,
+        elapsed_ms: u64,
+        input_size: u64,
+        output_size: u64,
+        command_line: Option<String>,
+    ) -> Self {
+        let compression_ratio = if input_size == 0 {
+            0.0
+        } else {
+            (output_size as f64 / input_size as f64 * 1000.0).round() / 1000.0
+        };
+
+        Self {
+            output_path,
+            elapsed_ms,
+            input_size,
+            output_size,
+            compression_ratio,
+            command_line,
+            tool: None,
+            warnings: Vec::new(),
+            fallbacks: Vec::new(),
+            tool_warnings: Vec::new(),
+            sha256: None,
+        }
+    }
+
+    /// Records the tool that ran and what the user should be told about the output
+    pub fn with_notes(self, tool: &str, warnings: Vec<String>, fallbacks: Vec<String>) -> Self {
+        Self {
+            tool: Some(tool.to_string()),
+            warnings,
+            fallbacks,
+            ..self
+        }
+    }
+}
+
+/// Outcome of one file in a batch run by convert_batch or convert_image_batch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchItemResult {
+    pub input_path: String,
+    pub result: Option<ConversionResult>,
+    pub error: Option<String>,
+    /// Short version of `error` for the list row
+    #[serde(default)]
+    pub error_summary: Option<ErrorSummary>,
+}
+
+/// Outcome of one format of a convert_to_formats job
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FormatItemResult {
+    pub format: String,
+    pub result: Option<ConversionResult>,
+    pub error: Option<String>,
+    /// Short version of `error` for the list row
+    #[serde(default)]
+    pub error_summary: Option<ErrorSummary>,
+}
+
+/// Results of a batch conversion, in the order the inputs were given
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchConversionResult {
+    pub items: Vec<BatchItemResult>,
+    /// Worker threads the batch ran on
+    pub workers: usize,
+    pub elapsed_ms: u64,
+}
+
+/// Results of converting the images inside an archive
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchiveConversionResult {
+    /// One item per converted image. When the results were packed into a new archive, output
+    /// paths are entry names inside it.
+    pub batch: BatchConversionResult,
+    /// The archive of results, when one was asked for
+    pub archive_path: Option<String>,
+    /// Folder the results were extracted to otherwise
+    pub output_directory: Option<String>,
+    /// Entries that aren't images and were left out
+    pub skipped: Vec<String>,
+}
+
+/// Frames written by extract_frames
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FrameExtractionResult {
+    /// Folder created for the frames
+    pub output_directory: String,
+    /// Frame files, in video order
+    pub frames: Vec<String>,
+    /// Requested times past the end of the video, which produced no frame
+    pub skipped_timestamps: Vec<f64>,
+}
+
+/// Expected duration of a batch, from recorded throughput where there is some
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchEstimate {
+    /// Wall-clock time for the whole batch on `workers` workers
+    pub total_ms: u64,
+    /// Expected time per file, in input order
+    pub file_ms: Vec<u64>,
+    pub workers: usize,
+    /// Files estimated from earlier conversions of the same kind (the rest use rough defaults)
+    pub measured_files: usize,
+}
+
+/// What convert_file would run for a given input and settings, without running it
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ConversionPreview {
+    pub tool: String,
+    /// Where the output would be written (the next free name right now)
+    pub output_path: String,
+    /// Commands in the order they'd run, ready to paste into a terminal
+    pub command_lines: Vec<String>,
+    /// Caveats, e.g. when the real run adjusts arguments as it goes
+    pub note: Option<String>,
+}
+
+/// A quick rendering of the start of a document, written to a temporary folder
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DocumentPreview {
+    pub preview_path: String,
+    /// "pdf" for LibreOffice conversions, "html" for Pandoc ones
+    pub preview_format: String,
+    pub tool: String,
+    /// Pages (PDF) or top-level sections (HTML) included
+    pub pages: u32,
+}
+
+/// Frames to remove and the new per-frame delay when thinning out an animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameDropPlan {
+    /// Zero-based indexes of the frames to delete
+    pub delete: Vec<usize>,
+    /// New delay for every remaining frame, in 1/100ths of a second
+    pub delay: u32,
+}
+
+/// Why a conversion pair can't run right now
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedReason {
+    /// The tool for this pair isn't installed (it can be downloaded or pointed at)
+    ToolNotInstalled,
+    /// A backend exists but is switched off in this build (e.g. Pandoc)
+    FeatureDisabled,
+    /// Conversions need an active license
+    LicenseRequired,
+    /// No backend converts between these formats
+    NotSupported,
+}
+
+/// Answer to "why can't I convert X to Y?", with a message the UI can show as-is
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UnsupportedExplanation {
+    pub supported: bool,
+    /// The tool that would handle the pair, if any
+    pub tool: Option<String>,
+    pub reasons: Vec<UnsupportedReason>,
+    pub message: String,
+}
+
+/// How AAC audio is actually stored in a file, regardless of its extension
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AacContainer {
+    /// Raw AAC stream with ADTS frame headers (.aac)
+    Adts,
+    /// AAC inside an MP4/M4A container (.m4a)
+    Mp4,
+    Unknown,
+}
+
+/// Whether Pandoc support is switched on (a runtime flag, see feature_flags)
+pub fn pandoc_enabled() -> bool {
+    feature_flags::is_enabled(feature_flags::PANDOC)
+}
 
 /// Supported video input formats
 pub const VIDEO_INPUTS: &[&str] = &[
@@ -112,6 +882,112 @@ pub const AV_OUTPUTS: &[&str] = &[
     "mp4", "mov", "avi", "mkv", "webm", "mp3", "wav", "flac", "ogg", "m4a", "aac", "gif"
 ];
 
+/// Vector input formats (for Inkscape)
+pub const VECTOR_INPUTS: &[&str] = &[
+    "svg", "svgz", "pdf", "eps", "ps", "ai", "emf", "wmf"
+];
+
+/// Vector output formats (for Inkscape)
+pub const VECTOR_OUTPUTS: &[&str] = &[
+    "svg", "pdf", "eps", "ps", "emf", "wmf"
+];
+
+/// SVG inputs rasterized by the built-in resvg renderer instead of ImageMagick
+pub const SVG_INPUTS: &[&str] = &["svg", "svgz"];
+
+/// Resolution SVG user units are defined at (one unit = one CSS pixel)
+pub const SVG_BASE_DPI: f32 = 96.0;
+
+/// Largest width or height resvg renders, to keep a typo from allocating gigabytes
+pub const MAX_RASTER_DIMENSION: u32 = 16384;
+
+/// Largest pixel count resvg renders (256 MB of RGBA)
+pub const MAX_RASTER_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Outputs without alpha (or with only GIF's binary transparency); transparent inputs are
+/// flattened onto white for these
+pub const OPAQUE_OUTPUTS: &[&str] = &[
+    "jpg", "jpeg", "bmp", "gif", "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
+    "hdr", "pbm", "pgm", "ppm"
+];
+
+/// Tool name for conversions done in-process with the `image` crate
+pub const BUILTIN_TOOL: &str = "builtin";
+
+/// Formats the built-in converter reads and writes, so basic conversions work before any tool is installed
+pub const BUILTIN_FORMATS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "gif", "tiff", "tif"];
+
+/// Most files passed to one `magick mogrify` call, to stay under command line length limits
+pub const MAX_MOGRIFY_FILES: usize = 200;
+
+/// Formats that can carry multiple frames and are converted frame-for-frame
+pub const ANIMATED_FORMATS: &[&str] = &[
+    "gif", "webp", "apng", "mp4"
+];
+
+/// HEIF containers, which can hold a burst or sequence of pictures besides the main one
+pub const HEIC_INPUTS: &[&str] = &["heic", "heif"];
+
+/// Outputs that keep every picture of a HEIC sequence, as animation frames
+pub const HEIC_SEQUENCE_OUTPUTS: &[&str] = &["gif", "webp", "apng"];
+
+pub fn is_heic(ext: &str) -> bool {
+    HEIC_INPUTS.contains(&ext)
+}
+
+/// HTML inputs that can be rendered by headless Chromium
+pub const HTML_INPUTS: &[&str] = &[
+    "html", "htm"
+];
+
+/// Snapshot outputs supported by headless Chromium
+pub const SNAPSHOT_OUTPUTS: &[&str] = &[
+    "png", "pdf"
+];
+
+/// Default viewport for HTML snapshots (a typical desktop browser window)
+pub const DEFAULT_SNAPSHOT_WIDTH: u32 = 1280;
+pub const DEFAULT_SNAPSHOT_HEIGHT: u32 = 800;
+
+/// Tallest viewport used for full-page snapshots; blank space below the page is trimmed afterwards
+pub const FULL_PAGE_MAX_HEIGHT: u32 = 16384;
+
+/// Pseudo output format that re-encodes an image in its own format to make it smaller
+pub const COMPRESS_TARGET: &str = "compress";
+
+/// Image formats that can be compressed in place
+pub const COMPRESSIBLE_FORMATS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "avif", "heic", "heif", "jxl"
+];
+
+/// PostScript-based formats ImageMagick can only read through Ghostscript
+pub const GHOSTSCRIPT_INPUTS: &[&str] = &["pdf", "ps", "eps", "ai"];
+
+/// Image resolution range used when compressing PDFs (DPI)
+pub const MIN_PDF_COMPRESS_DPI: u32 = 72;
+pub const MAX_PDF_COMPRESS_DPI: u32 = 300;
+
+/// Quality range used for compression and the target-size search
+pub const DEFAULT_COMPRESS_QUALITY: u8 = 80;
+pub const MIN_COMPRESS_QUALITY: u8 = 10;
+pub const MAX_COMPRESS_QUALITY: u8 = 95;
+
+/// Audio bitrate reserved when encoding a video to a target size (kbps)
+pub const TARGET_SIZE_AUDIO_KBPS: u32 = 128;
+
+/// Lowest video bitrate worth encoding at; anything below is unwatchable (kbps)
+pub const MIN_TARGET_VIDEO_KBPS: u32 = 100;
+
+/// Podcast loudness target (Apple Podcasts / Spotify recommendation)
+pub const PODCAST_LOUDNESS_LUFS: f32 = -16.0;
+
+/// True peak ceiling and loudness range used with every loudness target
+pub const LOUDNESS_TRUE_PEAK_DB: f32 = -1.5;
+pub const LOUDNESS_RANGE_LU: f32 = 11.0;
+
+/// Podcast output formats offered as presets
+pub const PODCAST_FORMATS: &[&str] = &["mp3", "m4a"];
+
 /// Document input formats (for Pandoc)
 pub const DOC_INPUTS: &[&str] = &[
     "md", "markdown", "txt", "html", "htm", "docx", "odt", "rtf", "tex", "latex", "epub", "rst"
@@ -132,6 +1008,43 @@ pub const OFFICE_OUTPUTS: &[&str] = &[
     "pdf", "html", "txt", "docx", "odt", "rtf"
 ];
 
+/// E-book formats; pairs involving one go through Pandoc (when enabled) or Calibre
+pub const EBOOK_FORMATS: &[&str] = &["epub", "mobi", "azw3", "azw", "fb2"];
+
+/// Formats Calibre's ebook-convert reads
+pub const CALIBRE_INPUTS: &[&str] = &[
+    "epub", "mobi", "azw3", "azw", "fb2", "docx", "odt", "rtf", "txt", "md", "markdown", "html", "htm", "pdf"
+];
+
+/// Formats Calibre's ebook-convert writes
+pub const CALIBRE_OUTPUTS: &[&str] = &[
+    "epub", "mobi", "azw3", "fb2", "pdf", "docx", "txt", "rtf"
+];
+
+/// Picks the tool for a conversion to or from an e-book format.
+///
+/// Pandoc is preferred when it's enabled and handles the pair (it only knows EPUB);
+/// Calibre covers the Kindle formats and everything Pandoc can't.
+pub fn ebook_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'static str> {
+    if input_ext == output_ext
+        || !(EBOOK_FORMATS.contains(&input_ext) || EBOOK_FORMATS.contains(&output_ext))
+    {
+        return None;
+    }
+
+    if pandoc_enabled() && DOC_INPUTS.contains(&input_ext) && DOC_OUTPUTS.contains(&output_ext) {
+        Some("pandoc")
+    } else if pandoc_enabled() && input_ext == "doc" && output_ext == "epub" {
+        // Offered with the Pandoc document conversions, but neither Pandoc nor Calibre
+        // reads Word 97 files, so LibreOffice's EPUB export does it
+        Some("libreoffice")
+    } else if CALIBRE_INPUTS.contains(&input_ext) && CALIBRE_OUTPUTS.contains(&output_ext) {
+        Some("calibre")
+    } else {
+        None
+    }
+}
+
 /// Determines which conversion tool should be used for a given input/output format pair.
 /// 
 /// # Arguments
@@ -139,7 +1052,7 @@ pub const OFFICE_OUTPUTS: &[&str] = &[
 /// * `output_ext` - The desired output format (lowercase, without dot)
 /// 
 /// # Returns
-/// * `Some(&'static str)` - The name of the tool to use ("ffmpeg", "imagemagick", "pandoc", "libreoffice", "rename")
+/// * `Some(&'static str)` - The name of the tool to use ("ffmpeg", "imagemagick", "inkscape", "chromium", "pandoc", "libreoffice", "rename")
 /// * `None` - If no conversion is available for this format pair
 /// 
 /// # Examples
@@ -155,6 +1068,16 @@ pub fn determine_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'
     if (input_ext == "jpg" && output_ext == "jpeg") || (input_ext == "jpeg" && output_ext == "jpg") {
         return Some("rename");
     }
+
+    // Calendars and contacts are converted in-process
+    if crate::pim::supports_pim_conversion(input_ext, output_ext) {
+        return Some(BUILTIN_TOOL);
+    }
+    
+    // Animated formats keep every frame (e.g. GIF -> MP4, MP4 -> APNG)
+    if let Some(tool) = animated_conversion_tool(input_ext, output_ext) {
+        return Some(tool);
+    }
     
     // Use ffmpeg for media and image conversions
     if (VIDEO_INPUTS.contains(&input_ext) || AUDIO_INPUTS.contains(&input_ext)) 
@@ -183,781 +1106,5562 @@ pub fn determine_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'
         return Some("ffmpeg");
     }
     
+    // E-books via Pandoc (when enabled) or Calibre
+    if let Some(tool) = ebook_conversion_tool(input_ext, output_ext) {
+        return Some(tool);
+    }
+    
     // Document conversions via Pandoc (when enabled)
-    if ENABLE_PANDOC && DOC_INPUTS.contains(&input_ext) && DOC_OUTPUTS.contains(&output_ext) {
+    if pandoc_enabled() && DOC_INPUTS.contains(&input_ext) && DOC_OUTPUTS.contains(&output_ext) {
         return Some("pandoc");
     }
     
+    // HTML pages are rendered by headless Chromium
+    if supports_html_snapshot(input_ext, output_ext) {
+        return Some("chromium");
+    }
+
+    // Emails are laid out as HTML and printed by headless Chromium too
+    if crate::email::supports_email_conversion(input_ext, output_ext) {
+        return Some("chromium");
+    }
+    
     // Office conversions via LibreOffice
     if OFFICE_INPUTS.contains(&input_ext) && OFFICE_OUTPUTS.contains(&output_ext) {
         return Some("libreoffice");
     }
-    
+
+    // Vector-only pairs (e.g. SVG -> EPS) have no raster path, so Inkscape handles them
+    if supports_vector_conversion(input_ext, output_ext) {
+        return Some("inkscape");
+    }
+
     None
 }
 
-/// Checks if an extension is a valid video format
-pub fn is_video_format(ext: &str) -> bool {
-    VIDEO_INPUTS.contains(&ext.to_lowercase().as_str())
+/// Checks if Inkscape can convert between two vector formats without rasterizing
+pub fn supports_vector_conversion(input_ext: &str, output_ext: &str) -> bool {
+    input_ext != output_ext
+        && VECTOR_INPUTS.contains(&input_ext)
+        && VECTOR_OUTPUTS.contains(&output_ext)
 }
 
-/// Checks if an extension is a valid audio format
-pub fn is_audio_format(ext: &str) -> bool {
-    AUDIO_INPUTS.contains(&ext.to_lowercase().as_str())
+/// Checks if an SVG should be rendered by resvg: the output is a raster image
+/// (vector outputs still go through Inkscape or ImageMagick)
+pub fn prefers_svg_renderer(input_ext: &str, output_ext: &str) -> bool {
+    SVG_INPUTS.contains(&input_ext)
+        && IMAGE_OUTPUTS_IMAGEMAGICK.contains(&output_ext)
+        && !VECTOR_OUTPUTS.contains(&output_ext)
+        && !SVG_INPUTS.contains(&output_ext)
 }
 
-/// Checks if an extension is a valid image format
-pub fn is_image_format(ext: &str) -> bool {
-    IMAGE_INPUTS.contains(&ext.to_lowercase().as_str())
+/// Pixel size to render an SVG whose own size is `svg_width` x `svg_height` units.
+///
+/// An explicit width and height are used as given; with only one of them the other follows
+/// the aspect ratio. Without either, the SVG's size is scaled by `dpi` / 96. A size over
+/// MAX_RASTER_DIMENSION on either side or MAX_RASTER_PIXELS in total is scaled down as a
+/// whole, so the aspect ratio survives; each side is at least 1.
+pub fn svg_raster_size(
+    svg_width: f32,
+    svg_height: f32,
+    width: Option<u32>,
+    height: Option<u32>,
+    dpi: Option<f32>,
+) -> (u32, u32) {
+    let aspect = if svg_width > 0.0 && svg_height > 0.0 { svg_width / svg_height } else { 1.0 };
+    let (w, h) = match (width, height) {
+        (Some(w), Some(h)) => (w as f32, h as f32),
+        (Some(w), None) => (w as f32, w as f32 / aspect),
+        (None, Some(h)) => (h as f32 * aspect, h as f32),
+        (None, None) => {
+            let scale = dpi.filter(|dpi| *dpi > 0.0).unwrap_or(SVG_BASE_DPI) / SVG_BASE_DPI;
+            (svg_width * scale, svg_height * scale)
+        }
+    };
+    let (w, h) = (w as f64, h as f64);
+    let max_side = MAX_RASTER_DIMENSION as f64;
+    let mut factor = (max_side / w).min(max_side / h).min(1.0);
+    if w * h > 0.0 {
+        factor = factor.min((MAX_RASTER_PIXELS as f64 / (w * h)).sqrt());
+    }
+    // Round down once scaled, so the result never creeps back over a limit
+    let side = |side: f64| {
+        let scaled = if factor < 1.0 { (side * factor).floor() } else { side.round() };
+        (scaled as u32).clamp(1, MAX_RASTER_DIMENSION)
+    };
+    (side(w), side(h))
 }
 
-/// Checks if an extension is a valid document format
-pub fn is_document_format(ext: &str) -> bool {
-    DOC_INPUTS.contains(&ext.to_lowercase().as_str()) 
-        || OFFICE_INPUTS.contains(&ext.to_lowercase().as_str())
+/// Checks if the built-in converter handles a pair (animations keep needing an external tool)
+pub fn supports_builtin_conversion(input_ext: &str, output_ext: &str) -> bool {
+    BUILTIN_FORMATS.contains(&input_ext)
+        && BUILTIN_FORMATS.contains(&output_ext)
+        && !is_animated_conversion(input_ext, output_ext)
 }
 
-/// Normalizes a file extension by removing the leading dot and converting to lowercase
-pub fn normalize_extension(ext: &str) -> String {
-    ext.trim_start_matches('.').to_lowercase()
+/// Swaps ImageMagick or FFmpeg for the built-in converter when neither is installed and
+/// the pair is one it handles. Any other tool (or an installed one) is returned unchanged.
+pub fn builtin_fallback(
+    tool: Option<&'static str>,
+    input_ext: &str,
+    output_ext: &str,
+    tool_installed: impl Fn(&str) -> bool,
+) -> Option<&'static str> {
+    match tool {
+        Some("imagemagick") | Some("ffmpeg")
+            if supports_builtin_conversion(input_ext, output_ext)
+                && !tool_installed("imagemagick")
+                && !tool_installed("ffmpeg") =>
+        {
+            Some(BUILTIN_TOOL)
+        }
+        other => other,
+    }
 }
 
-/// Returns the display name for a given format
-pub fn get_format_display_name(format: &str) -> &'static str {
-    match format {
-        // Video
-        "mp4" => "MP4 Video",
-        "mov" => "QuickTime Video",
-        "avi" => "AVI Video",
-        "mkv" => "Matroska Video",
-        "webm" => "WebM Video",
-        "flv" => "Flash Video",
-        "wmv" => "Windows Media Video",
-        "m4v" => "M4V Video",
-        "gif" => "Animated GIF",
-        // Audio
-        "mp3" => "MP3 Audio",
-        "wav" => "WAV Audio",
-        "flac" => "FLAC Audio (Lossless)",
-        "ogg" => "OGG Audio",
-        "m4a" => "M4A Audio",
-        "aac" => "AAC Audio",
-        "wma" => "Windows Media Audio",
-        // Images
-        "jpg" | "jpeg" => "JPEG Image",
-        "png" => "PNG Image",
-        "bmp" => "BMP Image",
-        "tiff" | "tif" => "TIFF Image",
-        "webp" => "WebP Image",
-        "heic" | "heif" => "HEIC Image",
-        "avif" => "AVIF Image",
-        "ico" => "Icon",
-        "svg" => "SVG Vector",
-        "psd" => "Photoshop Document",
-        // Documents
-        "pdf" => "PDF Document",
-        "docx" => "Word Document",
-        "doc" => "Word Document (Legacy)",
-        "txt" => "Plain Text",
-        "html" => "HTML Document",
-        "md" => "Markdown",
-        "epub" => "E-Book",
-        "rtf" => "Rich Text",
-        "odt" => "OpenDocument Text",
-        _ => "Unknown Format",
-    }
-}
-
-/// Returns the color category for a given format (for UI styling)
-pub fn get_format_color(format: &str) -> &'static str {
-    match format {
-        // Video - blue
-        "mp4" | "mov" | "avi" | "mkv" | "m4v" | "flv" | "wmv" => "blue",
-        // Web video - green
-        "webm" => "green",
-        // Audio - green variants
-        "mp3" | "wav" => "green",
-        "flac" => "aquamarine",
-        "ogg" => "orange",
-        "m4a" => "light-purple",
-        "aac" => "yellow",
-        // Images - varies
-        "jpg" | "jpeg" | "png" | "bmp" => "light-tan",
-        "gif" => "pink",
-        "webp" | "avif" | "heic" | "heif" => "green",
-        "ico" => "blue",
-        "svg" => "orange",
-        "psd" => "blue",
-        "tiff" | "tif" => "lavender",
-        // Documents - varies
-        "pdf" => "pink",
-        "docx" | "doc" => "blue",
-        "html" => "orange",
-        "txt" => "lavender",
-        "md" => "light-tan",
-        "epub" => "pink",
-        _ => "gray",
-    }
+/// Composites one color channel over a white background, for outputs without alpha
+pub fn blend_over_white(channel: u8, alpha: u8) -> u8 {
+    let (channel, alpha) = (channel as u32, alpha as u32);
+    ((channel * alpha + 255 * (255 - alpha) + 127) / 255) as u8
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Checks if both formats are animated, so the conversion should keep all frames
+pub fn is_animated_conversion(input_ext: &str, output_ext: &str) -> bool {
+    input_ext != output_ext
+        && ANIMATED_FORMATS.contains(&input_ext)
+        && ANIMATED_FORMATS.contains(&output_ext)
+}
+
+/// Picks the tool for an animated-to-animated conversion.
+///
+/// ImageMagick handles GIF/WebP/APNG among themselves. FFmpeg handles anything
+/// involving MP4, except animated WebP input, which FFmpeg can't decode.
+pub fn animated_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'static str> {
+    if !is_animated_conversion(input_ext, output_ext) {
+        return None;
+    }
+
+    if input_ext == "webp" || (input_ext != "mp4" && output_ext != "mp4") {
+        Some("imagemagick")
+    } else {
+        Some("ffmpeg")
+    }
+}
+
+/// Builds the ImageMagick operations that keep every frame of an animation and apply
+/// the loop count and frame rate settings.
+pub fn imagemagick_animation_ops(settings: &ConversionSettings) -> Vec<MagickOp> {
+    // Coalesce expands optimized frames so nothing is lost when re-encoding
+    let mut ops = vec![MagickOp::Coalesce];
+    ops.extend(imagemagick_crop_ops(settings));
+
+    if let Some(fps) = settings.frame_rate.filter(|fps| *fps > 0.0) {
+        // Frame delay is in 1/100ths of a second
+        let delay = (100.0 / fps).round().max(1.0) as u32;
+        ops.push(MagickOp::Delay(delay));
+    }
+
+    if let Some(loops) = settings.loop_count {
+        ops.push(MagickOp::Loop(loops));
+    }
+
+    ops
+}
+
+/// Builds the ImageMagick command keeping every frame of an animated input
+pub fn imagemagick_animation_command(
+    input: impl Into<OsString>,
+    output: impl Into<OsString>,
+    output_ext: &str,
+    settings: &ConversionSettings,
+    advanced_options: Option<&str>,
+) -> MagickCommand {
+    let quality = (output_ext == "webp").then_some(MagickOp::Quality(90));
+    MagickCommand::new()
+        .input(input)
+        .ops(imagemagick_animation_ops(settings))
+        .ops(imagemagick_color_ops(output_ext, settings))
+        .ops(quality)
+        .ops(imagemagick_metadata_ops(settings))
+        .extra_args(advanced_options)
+        .output(output)
+}
+
+/// Builds the FFmpeg arguments (placed after the input) for an animated output.
+pub fn ffmpeg_animation_args(output_ext: &str, settings: &ConversionSettings) -> Vec<String> {
+    let mut args = Vec::new();
+
+    // Cropping goes first, so later filters see the final frame
+    let mut frame_filters = ffmpeg_crop_filters(settings);
+    frame_filters.extend(settings.frame_rate.filter(|fps| *fps > 0.0).map(Filter::Fps));
+
+    match output_ext {
+        "gif" => {
+            // Generate a palette from the whole clip for much better GIF colors
+            let mut split = frame_filters;
+            split.push(Filter::Split(2));
+            let graph = FilterGraph::new()
+                .chain(&["0:v"], split, &["a", "b"])
+                .chain(&["a"], vec![Filter::PaletteGen], &["p"])
+                .chain(&["b", "p"], vec![Filter::PaletteUse], &[]);
+            args.push("-filter_complex".to_string());
+            args.push(graph.render());
+            // GIF counts extra repeats: -1 plays once, 0 loops forever
+            let loop_value = match settings.loop_count {
+                Some(0) | None => 0,
+                Some(1) => -1,
+                Some(n) => n as i64 - 1,
+            };
+            args.push("-loop".to_string());
+            args.push(loop_value.to_string());
+        }
+        "webp" => {
+            if !frame_filters.is_empty() {
+                args.push("-vf".to_string());
+                args.push(filter_graph::chain(&frame_filters));
+            }
+            args.push("-c:v".to_string());
+            args.push("libwebp".to_string());
+            args.push("-quality".to_string());
+            args.push("90".to_string());
+            args.push("-loop".to_string());
+            args.push(settings.loop_count.unwrap_or(0).to_string());
+        }
+        "apng" => {
+            if !frame_filters.is_empty() {
+                args.push("-vf".to_string());
+                args.push(filter_graph::chain(&frame_filters));
+            }
+            args.push("-f".to_string());
+            args.push("apng".to_string());
+            args.push("-plays".to_string());
+            args.push(settings.loop_count.unwrap_or(0).to_string());
+        }
+        "mp4" => {
+            // H.264 needs even dimensions, which GIFs often don't have
+            let mut filters = frame_filters;
+            filters.push(Filter::even_dimensions());
+            args.push("-vf".to_string());
+            args.push(filter_graph::chain(&filters));
+            args.push("-pix_fmt".to_string());
+            args.push("yuv420p".to_string());
+            args.push("-movflags".to_string());
+            args.push("+faststart".to_string());
+            args.push("-an".to_string());
+        }
+        _ => {}
+    }
+
+    args
+}
+
+/// Checks if an HTML input can be snapshotted to the given output format
+pub fn supports_html_snapshot(input_ext: &str, output_ext: &str) -> bool {
+    HTML_INPUTS.contains(&input_ext) && SNAPSHOT_OUTPUTS.contains(&output_ext)
+}
+
+/// Converts a local file path into a `file://` URL that Chromium can load, with spaces,
+/// `#`, `?`, `%` and non-ASCII characters percent-encoded
+pub fn file_path_to_url(path: &str) -> String {
+    if let Ok(url) = url::Url::from_file_path(path) {
+        return url.to_string();
+    }
+    // Not a path on this platform (e.g. a Windows drive path elsewhere): the same URL built
+    // by hand. set_path encodes everything but `%`, which it takes as an escape already.
+    let mut url = url::Url::parse("file:///").expect("valid base URL");
+    url.set_path(&path.replace('\\', "/").replace('%', "%25"));
+    url.to_string()
+}
+
+/// Derives an output file name for a URL snapshot from the page's host (e.g. "example.com")
+pub fn snapshot_file_stem(url: &str) -> String {
+    let without_scheme = url.split("://").nth(1).unwrap_or(url);
+    let host = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    let stem: String = host
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+        .collect();
+
+    if stem.is_empty() {
+        "snapshot".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Builds the headless Chromium arguments to render `target` (a URL) into `output_path`.
+///
+/// PNG output takes a screenshot of the viewport, PDF output prints the whole page.
+pub fn chromium_snapshot_args(
+    target: &str,
+    output_path: &str,
+    output_ext: &str,
+    settings: &ConversionSettings,
+) -> Vec<String> {
+    let width = settings.snapshot_width.unwrap_or(DEFAULT_SNAPSHOT_WIDTH);
+    let height = if settings.full_page {
+        FULL_PAGE_MAX_HEIGHT
+    } else {
+        settings.snapshot_height.unwrap_or(DEFAULT_SNAPSHOT_HEIGHT)
+    };
+
+    let mut args = vec![
+        "--headless".to_string(),
+        "--disable-gpu".to_string(),
+        "--hide-scrollbars".to_string(),
+        // Give web fonts and images a moment to load before capturing
+        "--virtual-time-budget=5000".to_string(),
+        format!("--window-size={},{}", width, height),
+    ];
+
+    if output_ext == "pdf" {
+        args.push(format!("--print-to-pdf={}", output_path));
+        args.push("--no-pdf-header-footer".to_string());
+    } else {
+        args.push(format!("--screenshot={}", output_path));
+    }
+
+    args.push(target.to_string());
+    args
+}
+
+/// Characters ImageMagick expands as wildcards or reads as frame/size modifiers in input paths
+const IMAGEMAGICK_SPECIAL_CHARS: &[char] = &['[', ']', '*', '?', '{', '}'];
+
+/// Checks whether ImageMagick reads `path` as a plain file name.
+///
+/// Brackets anywhere in the path are taken as glob patterns (once a `[0]` frame suffix is
+/// added the literal name no longer exists, so ImageMagick starts matching), and a leading
+/// `@` in the file name means "read the list of files inside".
+pub fn is_imagemagick_literal_path(path: &str) -> bool {
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    !path.contains(IMAGEMAGICK_SPECIAL_CHARS) && !file_name.starts_with('@')
+}
+
+/// Builds an ImageMagick input argument, selecting one frame with `[n]` when asked
+pub fn imagemagick_input_spec(path: &str, frame: Option<u32>) -> String {
+    match frame {
+        Some(frame) => format!("{}[{}]", path, frame),
+        None => path.to_string(),
+    }
+}
+
+/// A plain file name for the `index`th input staged into a job directory, keeping the
+/// extension so ImageMagick still picks the right decoder
+pub fn staged_input_name(index: usize, input_ext: &str) -> String {
+    let ext: String = input_ext.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    if ext.is_empty() {
+        format!("input-{}", index)
+    } else {
+        format!("input-{}.{}", index, ext.to_lowercase())
+    }
+}
+
+/// Format-specific ImageMagick output options (quality, ICO sizing, PDF density)
+pub fn imagemagick_format_ops(output_ext: &str) -> Vec<MagickOp> {
+    match output_ext {
+        // ICO files have size limitations (typically max 256x256), so resize to fit
+        // and use extent to make it square
+        "ico" => vec![
+            MagickOp::Resize("256x256".to_string()),
+            MagickOp::Extent { size: "256x256".to_string(), background: "transparent".to_string() },
+        ],
+        // Modern compressed formats
+        "heic" | "heif" | "avif" => vec![MagickOp::Quality(85)],
+        // JPEG XL and WebP benefit from higher quality
+        "jxl" | "webp" => vec![MagickOp::Quality(90)],
+        // Standard lossy formats
+        "jpg" | "jpeg" => vec![MagickOp::Quality(90)],
+        // JPEG 2000 variants
+        "j2k" | "jp2" | "jpc" | "jpf" | "jpx" | "jpm" => vec![MagickOp::Quality(85)],
+        // Professional formats (high quality)
+        "tiff" | "tif" | "exr" | "hdr" | "dpx" => vec![MagickOp::Quality(100)],
+        // PDF output from images - JPEG compression for reasonable file size, displayed at the correct zoom level
+        "pdf" => vec![MagickOp::Compress("jpeg".to_string()), MagickOp::Density(300)],
+        // 300 DPI for vector
+        "svg" | "svgz" => vec![MagickOp::Density(300)],
+        // Everything else uses ImageMagick defaults
+        _ => Vec::new(),
+    }
+}
+
+/// Rotates photos upright from their EXIF orientation (and resets the tag), unless
+/// `keep_orientation` is set
+pub fn imagemagick_orientation_ops(settings: &ConversionSettings) -> Vec<MagickOp> {
+    if settings.keep_orientation { Vec::new() } else { vec![MagickOp::AutoOrient] }
+}
+
+/// Parses an aspect ratio written as `16:9`, `16/9` or `16x9`
+pub fn parse_aspect_ratio(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once([':', '/', 'x'])?;
+    let width: u32 = width.trim().parse().ok()?;
+    let height: u32 = height.trim().parse().ok()?;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// The crop asked for in `settings` (invalid options are rejected by prepare_conversion)
+fn crop_region(settings: &ConversionSettings) -> Option<CropRegion> {
+    settings.crop.as_ref().and_then(|crop| crop.region().ok().flatten())
+}
+
+/// ImageMagick's side of the crop setting
+pub fn imagemagick_crop_ops(settings: &ConversionSettings) -> Vec<MagickOp> {
+    match crop_region(settings) {
+        Some(CropRegion::Rect { x, y, width, height }) => {
+            vec![MagickOp::Crop { geometry: format!("{}x{}+{}+{}", width, height, x, y), centered: false }]
+        }
+        Some(CropRegion::Aspect { width, height }) => {
+            vec![MagickOp::Crop { geometry: format!("{}:{}", width, height), centered: true }]
+        }
+        None => Vec::new(),
+    }
+}
+
+/// FFmpeg's side of the crop setting
+pub fn ffmpeg_crop_filters(settings: &ConversionSettings) -> Vec<Filter> {
+    match crop_region(settings) {
+        Some(CropRegion::Rect { x, y, width, height }) => vec![Filter::crop(width, height, x, y)],
+        Some(CropRegion::Aspect { width, height }) => vec![Filter::crop_to_aspect(width, height)],
+        None => Vec::new(),
+    }
+}
+
+/// Size of the picture a watermark is drawn on: the `width` x `height` input turned upright
+/// from its EXIF `orientation` and cropped as `settings` ask
+pub fn watermark_canvas(width: u32, height: u32, orientation: u16, settings: &ConversionSettings) -> (u32, u32) {
+    let (width, height) = if !settings.keep_orientation && (5..=8).contains(&orientation) {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    match crop_region(settings) {
+        Some(region) => {
+            let (_, _, width, height) = region.rect_within(width, height);
+            (width, height)
+        }
+        None => (width, height),
+    }
+}
+
+/// ImageMagick's side of the watermark setting on a `canvas` sized picture. `image` is the
+/// watermark picture as ImageMagick should read it (see imagemagick_input).
+pub fn imagemagick_watermark_ops(watermark: &Watermark, image: Option<&str>, canvas: (u32, u32)) -> Vec<MagickOp> {
+    let (width, height) = canvas;
+    let gravity = watermark.position.gravity().to_string();
+    let offset = watermark.margin(width, height);
+    match (image, watermark.text()) {
+        (Some(image), _) => vec![MagickOp::Composite {
+            overlay: image.to_string(),
+            width: watermark.image_width(width),
+            opacity: watermark.opacity,
+            gravity,
+            offset,
+        }],
+        (None, Some(text)) => vec![MagickOp::Annotate {
+            text: text.to_string(),
+            point_size: watermark.font_size(height),
+            opacity: watermark.opacity,
+            gravity,
+            offset,
+        }],
+        (None, None) => Vec::new(),
+    }
+}
+
+/// FFmpeg x and y expressions placing an `item_width` x `item_height` item on a `width` x
+/// `height` frame (names of FFmpeg variables, which differ between filters)
+fn watermark_xy(position: WatermarkPosition, width: &str, height: &str, item_width: &str, item_height: &str) -> (String, String) {
+    let margin = format!("min({},{})*{}", width, height, WATERMARK_MARGIN);
+    let place = |cell: u8, size: &str, item: &str| match cell {
+        0 => margin.clone(),
+        1 => format!("({}-{})/2", size, item),
+        _ => format!("{}-{}-{}", size, item, margin),
+    };
+    let (column, row) = position.cell();
+    (place(column, width, item_width), place(row, height, item_height))
+}
+
+/// drawtext filter for a text watermark, sized and placed relative to the frame.
+/// `font_file` is needed where FFmpeg has no fontconfig (Windows).
+pub fn ffmpeg_text_watermark(watermark: &Watermark, text: &str, font_file: Option<String>) -> Filter {
+    let (x, y) = watermark_xy(watermark.position, "w", "h", "text_w", "text_h");
+    Filter::Caption {
+        text: text.to_string(),
+        x,
+        y,
+        font_size: format!("h*{}", watermark.scale_or_default()),
+        opacity: watermark.opacity,
+        font_file,
+    }
+}
+
+/// Adds chains to `graph` that apply `frame_filters` to `input` and then draw a picture
+/// watermark over it, writing `outputs` (none for the end of a `-vf`). The picture is read
+/// with the `movie` source from `image_name`, a plain name in FFmpeg's working directory,
+/// and sized for a `canvas` sized frame.
+pub fn ffmpeg_image_watermark(
+    graph: FilterGraph,
+    input: &str,
+    frame_filters: Vec<Filter>,
+    watermark: &Watermark,
+    image_name: &str,
+    canvas: (u32, u32),
+    outputs: &[&str],
+) -> FilterGraph {
+    let (base, graph) = if frame_filters.is_empty() {
+        (input, graph)
+    } else {
+        ("base", graph.chain(&[input], frame_filters, &["base"]))
+    };
+    let (x, y) = watermark_xy(watermark.position, "main_w", "main_h", "overlay_w", "overlay_h");
+    graph
+        .chain(
+            &[],
+            vec![
+                Filter::Movie(image_name.to_string()),
+                Filter::scale(watermark.image_width(canvas.0).to_string(), "-1"),
+                Filter::Format("rgba".to_string()),
+                Filter::Opacity(watermark.opacity),
+            ],
+            &["watermark"],
+        )
+        .chain(&[base, "watermark"], vec![Filter::Overlay { x, y, shortest: false }], outputs)
+}
+
+/// ImageMagick's side of the color settings. Converting to sRGB reads the profile written by
+/// `icc::ensure_srgb_profile`.
+pub fn imagemagick_color_ops(output_ext: &str, settings: &ConversionSettings) -> Vec<MagickOp> {
+    let color = &settings.color;
+    let mut ops = Vec::new();
+    if color.convert_to_srgb {
+        ops.push(MagickOp::Profile(crate::icc::srgb_profile_path().to_string_lossy().to_string()));
+    }
+    if color.icc_profile == IccProfileMode::Strip {
+        ops.push(MagickOp::RemoveProfile("icc".to_string()));
+    }
+    if color.keep_16_bit && DEEP_COLOR_OUTPUTS.contains(&output_ext) {
+        ops.push(MagickOp::Depth(16));
+    }
+    ops
+}
+
+/// FFmpeg's side of the color settings: a 16-bit pixel format for PNG and TIFF (`alpha` keeps
+/// the alpha channel). FFmpeg can't convert between ICC profiles or drop them.
+pub fn ffmpeg_color_args(output_ext: &str, alpha: bool, settings: &ConversionSettings) -> Vec<String> {
+    if !settings.color.keep_16_bit {
+        return Vec::new();
+    }
+    let pixel_format = match (output_ext, alpha) {
+        ("png", false) => "rgb48be",
+        ("png", true) => "rgba64be",
+        ("tif" | "tiff", false) => "rgb48le",
+        ("tif" | "tiff", true) => "rgba64le",
+        _ => return Vec::new(),
+    };
+    vec!["-pix_fmt".to_string(), pixel_format.to_string()]
+}
+
+/// Builds the LibRaw (`dcraw_emu`) arguments that develop a RAW photo into an sRGB TIFF
+/// at the chosen bit depth, which is then converted like any other image
+pub fn libraw_args(input: &str, output: &str, raw: &RawOptions) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    args.push(match raw.white_balance {
+        RawWhiteBalance::Camera => "-w".to_string(),
+        RawWhiteBalance::Auto => "-a".to_string(),
+    });
+    // Interpolation: 0 = bilinear, 3 = AHD
+    args.extend(["-q".to_string(), if raw.fast_demosaic { "0" } else { "3" }.to_string()]);
+    if raw.exposure != 0.0 {
+        // A linear shift (2^stops), keeping highlights from clipping
+        args.extend(["-aexpo".to_string(), format!("{:.3}", 2f32.powf(raw.exposure)), "1".to_string()]);
+    }
+    if raw.bit_depth == 16 {
+        args.push("-6".to_string());
+    }
+    args.extend(["-o", "1", "-T", "-Z"].map(String::from));
+    args.push(output.to_string());
+    args.push(input.to_string());
+    args
+}
+
+/// ImageMagick's side of the RAW options, for when LibRaw isn't installed: ImageMagick's
+/// own RAW reader takes the white balance and demosaic quality, but not exposure
+pub fn imagemagick_raw_ops(output_ext: &str, settings: &ConversionSettings) -> Vec<MagickOp> {
+    let raw = &settings.raw;
+    let mut ops = vec![match raw.white_balance {
+        RawWhiteBalance::Camera => MagickOp::read_define("dng:use-camera-wb", "true"),
+        RawWhiteBalance::Auto => MagickOp::read_define("dng:use-auto-wb", "true"),
+    }];
+    if raw.fast_demosaic {
+        ops.push(MagickOp::read_define("dng:interpolation-quality", "0"));
+    }
+    // keep_16_bit already adds the same -depth (see imagemagick_color_ops)
+    if raw.bit_depth == 16 && !settings.color.keep_16_bit && DEEP_COLOR_OUTPUTS.contains(&output_ext) {
+        ops.push(MagickOp::Depth(16));
+    }
+    ops
+}
+
+/// ImageMagick's side of `metadata_args`: only stripping needs an option. Deterministic
+/// output also leaves out the modification time PNG keeps outside the profiles.
+pub fn imagemagick_metadata_ops(settings: &ConversionSettings) -> Vec<MagickOp> {
+    let mut ops = Vec::new();
+    if settings.strips_metadata() {
+        ops.push(MagickOp::Strip);
+    }
+    if settings.deterministic {
+        ops.push(MagickOp::define("png:exclude-chunk", "date,time"));
+    }
+    ops
+}
+
+/// Builds the ImageMagick command for a still image. `flatten` composites the image onto
+/// white, for transparent input going to a format without alpha.
+pub fn imagemagick_still_command(
+    input: impl Into<OsString>,
+    output: impl Into<OsString>,
+    output_ext: &str,
+    flatten: bool,
+    settings: &ConversionSettings,
+    advanced_options: Option<&str>,
+) -> MagickCommand {
+    let flatten = flatten.then(|| MagickOp::flatten("white"));
+    MagickCommand::new()
+        .input(input)
+        .ops(imagemagick_orientation_ops(settings))
+        .ops(imagemagick_crop_ops(settings))
+        .ops(flatten)
+        .ops(imagemagick_format_ops(output_ext))
+        .ops(imagemagick_color_ops(output_ext, settings))
+        .ops(imagemagick_metadata_ops(settings))
+        .extra_args(advanced_options)
+        .output(output)
+}
+
+/// Checks if a batch of images can be converted by `magick mogrify` calls instead of one
+/// process per file: every input is a still image ImageMagick reads directly by name,
+/// and no two inputs would write the same output name.
+///
+/// Settings that need a decision per file (naming templates, copying metadata, watermarks
+/// sized to each picture) or work per output (checksums, sidecars) rule it out.
+pub fn can_mogrify_batch(input_paths: &[String], output_ext: &str, settings: &ConversionSettings) -> bool {
+    if input_paths.len() < 2 || settings.preserve_metadata || settings.vector_output || settings.watermark.is_some() || settings.file_by.is_some() {
+        return false;
+    }
+    if settings.checksum || settings.write_checksum_file || settings.write_sidecar {
+        return false;
+    }
+    if settings.name_template.as_deref().is_some_and(|template| template != crate::naming::DEFAULT_NAME_TEMPLATE) {
+        return false;
+    }
+
+    let mut stems = std::collections::HashSet::new();
+    input_paths.iter().all(|path| {
+        let path_ref = Path::new(path);
+        let input_ext = path_ref
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let stem = path_ref
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        determine_conversion_tool(&input_ext, output_ext) == Some("imagemagick")
+            && !prefers_svg_renderer(&input_ext, output_ext)
+            && !needs_ghostscript(&input_ext)
+            && !is_camera_raw(&input_ext)
+            && !ANIMATED_FORMATS.contains(&input_ext.as_str())
+            && !["mng", "apng"].contains(&input_ext.as_str())
+            && is_imagemagick_literal_path(path)
+            && !stem.is_empty()
+            && stems.insert(stem)
+    })
+}
+
+/// Builds `magick mogrify` arguments converting `files` into `output_dir` as `output_ext`
+pub fn mogrify_args(
+    output_dir: &str,
+    output_ext: &str,
+    settings: &ConversionSettings,
+    advanced_options: Option<&str>,
+    files: &[String],
+) -> Vec<String> {
+    // Transparency can't be checked per file here; flattening an opaque image onto white changes nothing
+    let flatten = OPAQUE_OUTPUTS.contains(&output_ext).then(|| MagickOp::flatten("white"));
+    files
+        .iter()
+        .fold(MagickCommand::mogrify(output_dir, output_ext), |command, file| command.input(file))
+        .ops(imagemagick_orientation_ops(settings))
+        .ops(imagemagick_crop_ops(settings))
+        .ops(flatten)
+        .ops(imagemagick_format_ops(output_ext))
+        .ops(imagemagick_color_ops(output_ext, settings))
+        .ops(imagemagick_metadata_ops(settings))
+        .extra_args(advanced_options)
+        .args_lossy()
+}
+
+/// Splits `files` into mogrify calls spread over `workers`, at most MAX_MOGRIFY_FILES each
+pub fn mogrify_chunk_size(files: usize, workers: usize) -> usize {
+    files.div_ceil(workers.max(1)).clamp(1, MAX_MOGRIFY_FILES)
+}
+
+/// Picks the `--convert-to` target for LibreOffice.
+///
+/// Most formats only need the extension. Plain text names its filter so documents are
+/// written as UTF-8 and spreadsheets go through Calc's text export.
+pub fn libreoffice_convert_target(input_ext: &str, output_ext: &str) -> String {
+    match output_ext {
+        "txt" if matches!(input_ext, "xls" | "xlsx" | "ods") => "txt:Text - txt - csv (StarCalc)".to_string(),
+        "txt" => "txt:Text (encoded):UTF8".to_string(),
+        _ => output_ext.to_string(),
+    }
+}
+
+/// Builds the headless LibreOffice arguments to convert `input_path` into `out_dir`.
+///
+/// LibreOffice always names the result after the input (see `libreoffice_output_name`).
+/// `profile_dir` is a user profile of our own, so a LibreOffice window the user has
+/// open doesn't swallow the request.
+pub fn libreoffice_args(input_path: &str, output_ext: &str, out_dir: &str, profile_dir: &str) -> Vec<String> {
+    let input_ext = Path::new(input_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    libreoffice_args_with_target(input_path, &libreoffice_convert_target(&input_ext, output_ext), out_dir, profile_dir)
+}
+
+/// Like `libreoffice_args`, with an explicit `--convert-to` target (format, filter and options)
+pub fn libreoffice_args_with_target(input_path: &str, target: &str, out_dir: &str, profile_dir: &str) -> Vec<String> {
+    vec![
+        format!("-env:UserInstallation={}", file_path_to_url(profile_dir)),
+        "--headless".to_string(),
+        "--norestore".to_string(),
+        "--convert-to".to_string(),
+        target.to_string(),
+        "--outdir".to_string(),
+        out_dir.to_string(),
+        input_path.to_string(),
+    ]
+}
+
+/// Pages (or Pandoc sections) in a document preview when none are asked for
+pub const DEFAULT_PREVIEW_PAGES: u32 = 3;
+
+/// Most pages a preview renders; past this it's no quicker than the real conversion
+pub const MAX_PREVIEW_PAGES: u32 = 20;
+
+/// LibreOffice's PDF export filter for the application that opens `input_ext`
+fn libreoffice_pdf_filter(input_ext: &str) -> &'static str {
+    match input_ext {
+        "xls" | "xlsx" | "ods" | "csv" => "calc_pdf_Export",
+        "ppt" | "pptx" | "odp" => "impress_pdf_Export",
+        "odg" | "vsd" | "vsdx" => "draw_pdf_Export",
+        _ => "writer_pdf_Export",
+    }
+}
+
+/// `--convert-to` target for PDF with the export options, limited to the first `pages`
+/// pages when given. Plain `pdf` when nothing differs from LibreOffice's defaults.
+/// JSON filter options need LibreOffice 7.4 or newer.
+pub fn libreoffice_pdf_target(
+    input_ext: &str,
+    options: &PdfExportOptions,
+    layout: &SpreadsheetLayout,
+    pages: Option<u32>,
+) -> String {
+    let mut filter_options = serde_json::Map::new();
+    let mut set = |name: &str, kind: &str, value: String| {
+        filter_options.insert(name.to_string(), serde_json::json!({ "type": kind, "value": value }));
+    };
+
+    if let Some(pages) = pages {
+        set("PageRange", "string", format!("1-{}", pages.max(1)));
+    }
+    if options.embed_standard_fonts {
+        set("EmbedStandardFonts", "boolean", "true".to_string());
+    }
+    if let Some(part) = options.pdf_a {
+        set("SelectPdfVersion", "long", part.to_string());
+    }
+    if options.lossless_images {
+        set("UseLosslessCompression", "boolean", "true".to_string());
+        set("ReduceImageResolution", "boolean", "false".to_string());
+    }
+    if layout.fit_to_page && libreoffice_pdf_filter(input_ext) == "calc_pdf_Export" {
+        set("SinglePageSheets", "boolean", "true".to_string());
+    }
+    if let Some(password) = &options.password {
+        set("EncryptFile", "boolean", "true".to_string());
+        set("DocumentOpenPassword", "string", password.clone());
+    }
+
+    if filter_options.is_empty() {
+        "pdf".to_string()
+    } else {
+        format!("pdf:{}:{}", libreoffice_pdf_filter(input_ext), serde_json::Value::Object(filter_options))
+    }
+}
+
+/// Masks the PDF open password in a LibreOffice command line, so it doesn't end up in
+/// logs or conversion history
+pub fn redact_pdf_password(command_line: &str) -> String {
+    const KEY: &str = r#""DocumentOpenPassword":{"type":"string","value":""#;
+    let Some(start) = command_line.find(KEY).map(|index| index + KEY.len()) else {
+        return command_line.to_string();
+    };
+    // The value ends at the first quote that isn't escaped
+    let mut escaped = false;
+    let end = command_line[start..]
+        .char_indices()
+        .find(|&(_, c)| {
+            let closes = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            closes
+        })
+        .map_or(command_line.len(), |(offset, _)| start + offset);
+    format!("{}********{}", &command_line[..start], &command_line[end..])
+}
+
+/// The start of an HTML fragment up to the heading that begins section `sections + 1`.
+/// Sections start at the highest heading level used; text before the first heading
+/// belongs to the first section. Returns the whole fragment when it's short enough.
+pub fn html_preview_sections(html: &str, sections: usize) -> &str {
+    let Some(level) = (1..=6).find(|level| html.contains(&format!("<h{}", level))) else {
+        return html;
+    };
+    let open = format!("<h{}", level);
+    let starts: Vec<usize> = html
+        .match_indices(&open)
+        .map(|(index, _)| index)
+        // Skip tags that only share the prefix, e.g. <h1x>
+        .filter(|index| html[index + open.len()..].starts_with(|c: char| c == '>' || c.is_whitespace()))
+        .collect();
+
+    // Leading text is the first section, so the cut comes one heading earlier
+    let has_intro = starts.first().is_some_and(|first| !html[..*first].trim().is_empty());
+    let sections = sections.max(1);
+    let cut = starts.get(if has_intro { sections - 1 } else { sections });
+    match cut {
+        Some(index) => &html[..*index],
+        None => html,
+    }
+}
+
+/// The file name LibreOffice writes for `input_path` converted to `output_ext`
+pub fn libreoffice_output_name(input_path: &str, output_ext: &str) -> String {
+    let stem = Path::new(input_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("output");
+    format!("{}.{}", stem, output_ext)
+}
+
+/// Finds the newest release in the LibreOffice download server's directory listing
+/// (links like `<a href="24.8.2/">`)
+pub fn latest_libreoffice_version(listing: &str) -> Option<String> {
+    listing
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|link| link.split('"').next())
+        .map(|link| link.trim_end_matches('/'))
+        .filter_map(|version| {
+            let parts: Option<Vec<u32>> = version.split('.').map(|part| part.parse().ok()).collect();
+            parts.filter(|parts| parts.len() >= 2).map(|parts| (parts, version.to_string()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, version)| version)
+}
+
+/// Plans which frames to drop so only every `keep_every`th frame remains.
+///
+/// The remaining frames are slowed down so the animation keeps its original
+/// total duration. Returns `None` when nothing would be dropped.
+pub fn plan_frame_drop(delays: &[u32], keep_every: u32) -> Option<FrameDropPlan> {
+    let keep_every = keep_every as usize;
+    if keep_every <= 1 || delays.len() <= 1 {
+        return None;
+    }
+
+    let delete: Vec<usize> = (0..delays.len()).filter(|i| i % keep_every != 0).collect();
+    let kept = delays.len() - delete.len();
+    let total: u32 = delays.iter().sum();
+    // Browsers treat delays under 2 ticks as "as slow as possible", so never go below that
+    let delay = ((total as f64 / kept as f64).round() as u32).max(2);
+
+    Some(FrameDropPlan { delete, delay })
+}
+
+/// Builds the ImageMagick arguments (placed after the input) for an animation optimization pass
+pub fn animation_optimize_args(
+    output_ext: &str,
+    options: &AnimationOptimizeOptions,
+    plan: Option<&FrameDropPlan>,
+) -> Vec<String> {
+    let mut args = vec!["-coalesce".to_string()];
+
+    if let Some(plan) = plan {
+        let indexes: Vec<String> = plan.delete.iter().map(|i| i.to_string()).collect();
+        args.push("-delete".to_string());
+        args.push(indexes.join(","));
+        args.push("-set".to_string());
+        args.push("delay".to_string());
+        args.push(plan.delay.to_string());
+    }
+
+    let lossy = options.lossy.min(100);
+
+    if output_ext == "webp" {
+        args.push("-quality".to_string());
+        args.push((100 - lossy as u32).max(10).to_string());
+    } else {
+        if let Some(colors) = options.colors {
+            args.push("-colors".to_string());
+            args.push(colors.clamp(2, 256).to_string());
+        }
+        // Fuzz lets near-identical pixels between frames become transparent, which compresses much better
+        if lossy > 0 {
+            args.push("-fuzz".to_string());
+            args.push(format!("{}%", lossy as f32 / 10.0));
+        }
+        args.push("-layers".to_string());
+        args.push("Optimize".to_string());
+    }
+
+    args
+}
+
+/// Checks if an image format can be compressed without changing format
+pub fn supports_compression(ext: &str) -> bool {
+    COMPRESSIBLE_FORMATS.contains(&ext)
+}
+
+/// Checks if ImageMagick needs Ghostscript to read this input format
+pub fn needs_ghostscript(input_ext: &str) -> bool {
+    GHOSTSCRIPT_INPUTS.contains(&input_ext)
+}
+
+/// Picks the Ghostscript PDFSETTINGS preset and image resolution for a compression quality.
+///
+/// Quality maps linearly onto 72-300 DPI; the preset decides the rest (JPEG quality, fonts).
+pub fn pdf_compress_settings(quality: u8) -> (&'static str, u32) {
+    let quality = quality.clamp(MIN_COMPRESS_QUALITY, MAX_COMPRESS_QUALITY) as u32;
+    let span = (MAX_COMPRESS_QUALITY - MIN_COMPRESS_QUALITY) as u32;
+    let dpi = MIN_PDF_COMPRESS_DPI
+        + (quality - MIN_COMPRESS_QUALITY as u32) * (MAX_PDF_COMPRESS_DPI - MIN_PDF_COMPRESS_DPI) / span;
+
+    let preset = if quality < 40 {
+        "/screen"
+    } else if quality < 75 {
+        "/ebook"
+    } else {
+        "/printer"
+    };
+    (preset, dpi)
+}
+
+/// Builds the Ghostscript arguments that rewrite a PDF with its images downsampled
+pub fn pdf_compress_args(input: &str, output: &str, quality: u8) -> Vec<String> {
+    let (preset, dpi) = pdf_compress_settings(quality);
+    // Black-and-white scans stay legible only at a higher resolution
+    let mono_dpi = (dpi * 2).min(600);
+
+    vec![
+        "-sDEVICE=pdfwrite".to_string(),
+        "-dCompatibilityLevel=1.5".to_string(),
+        format!("-dPDFSETTINGS={}", preset),
+        "-dNOPAUSE".to_string(),
+        "-dBATCH".to_string(),
+        "-dQUIET".to_string(),
+        "-dSAFER".to_string(),
+        "-dDownsampleColorImages=true".to_string(),
+        "-dDownsampleGrayImages=true".to_string(),
+        "-dDownsampleMonoImages=true".to_string(),
+        "-dColorImageDownsampleType=/Bicubic".to_string(),
+        "-dGrayImageDownsampleType=/Bicubic".to_string(),
+        format!("-dColorImageResolution={}", dpi),
+        format!("-dGrayImageResolution={}", dpi),
+        format!("-dMonoImageResolution={}", mono_dpi),
+        // A bare % in OutputFile is a page-number template
+        format!("-sOutputFile={}", output.replace('%', "%%")),
+        input.to_string(),
+    ]
+}
+
+/// Builds the Ghostscript arguments that join PDFs, in the order given, into one file.
+/// Pages keep the orientation they have in their own document.
+pub fn pdf_merge_args(inputs: &[String], output: &str) -> Vec<String> {
+    let mut args = vec![
+        "-sDEVICE=pdfwrite".to_string(),
+        "-dNOPAUSE".to_string(),
+        "-dBATCH".to_string(),
+        "-dQUIET".to_string(),
+        "-dSAFER".to_string(),
+        "-dAutoRotatePages=/None".to_string(),
+        format!("-sOutputFile={}", output.replace('%', "%%")),
+    ];
+    args.extend(inputs.iter().cloned());
+    args
+}
+
+/// Picks the newest Ghostscript install from folder names like `gs10.04.0` (Windows installs are versioned)
+pub fn latest_ghostscript_dir<'a>(names: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let version = name.strip_prefix("gs")?;
+            let parts: Option<Vec<u32>> = version.split('.').map(|part| part.parse().ok()).collect();
+            parts.filter(|parts| !parts.is_empty()).map(|parts| (parts, name))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, name)| name)
+}
+
+/// Checks if a Ghostscript release asset is the build for `platform` ("windows" or "linux")
+pub fn is_ghostscript_asset(name: &str, platform: &str) -> bool {
+    match platform {
+        // gs10040w64.exe - the 64-bit NSIS installer
+        "windows" => name.starts_with("gs") && name.ends_with("w64.exe"),
+        // ghostscript-10.04.0-linux-x86_64.tgz - a single static binary
+        "linux" => name.starts_with("ghostscript-") && name.ends_with("-linux-x86_64.tgz"),
+        _ => false,
+    }
+}
+
+/// Builds the ImageMagick arguments (placed after the input) to re-encode an image smaller.
+///
+/// PNG is lossless, so quality is ignored and the strongest zlib level is used instead.
+pub fn compress_args(ext: &str, quality: u8, keep_metadata: bool) -> Vec<String> {
+    // Embedded thumbnails and metadata are often a large share of small files
+    let mut args = Vec::new();
+    if !keep_metadata {
+        args.push("-strip".to_string());
+    }
+
+    if ext == "png" {
+        args.push("-define".to_string());
+        args.push("png:compression-level=9".to_string());
+    } else {
+        args.push("-quality".to_string());
+        args.push(quality.clamp(1, 100).to_string());
+    }
+
+    args
+}
+
+/// Searches for the highest quality whose output fits within `max_bytes`.
+///
+/// `encode` writes the output at the given quality and returns its size in bytes.
+/// Returns the chosen quality and whether it actually fits; when nothing fits the
+/// minimum quality is returned so the caller can still keep the smallest result.
+pub fn find_quality_for_size<F>(max_bytes: u64, mut encode: F) -> Result<(u8, bool), String>
+where
+    F: FnMut(u8) -> Result<u64, String>,
+{
+    let mut low = MIN_COMPRESS_QUALITY;
+    let mut high = MAX_COMPRESS_QUALITY;
+    let mut best = None;
+
+    while low <= high {
+        let quality = low + (high - low) / 2;
+        if encode(quality)? <= max_bytes {
+            best = Some(quality);
+            low = quality + 1;
+        } else if quality == MIN_COMPRESS_QUALITY {
+            break;
+        } else {
+            high = quality - 1;
+        }
+    }
+
+    Ok(match best {
+        Some(quality) => (quality, true),
+        None => (MIN_COMPRESS_QUALITY, false),
+    })
+}
+
+/// Parses the duration in seconds from FFmpeg's input banner (`Duration: 00:01:23.45, ...`)
+pub fn parse_ffmpeg_duration(stderr: &str) -> Option<f64> {
+    let start = stderr.find("Duration: ")? + "Duration: ".len();
+    let timestamp = stderr[start..].split(',').next()?.trim();
+
+    let parts: Vec<&str> = timestamp.split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let hours: f64 = parts[0].parse().ok()?;
+    let minutes: f64 = parts[1].parse().ok()?;
+    let seconds: f64 = parts[2].parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Arguments for one output of a shared FFmpeg pass (`-i input out1 out2 ...`), placed
+/// before its file name. None for formats that need a pass of their own.
+fn shared_pass_output_args(output_ext: &str) -> Option<Vec<&'static str>> {
+    // "?" keeps a video without sound from failing the whole pass
+    let video = |codec_args: &[&'static str]| {
+        let mut args = vec!["-map", "0:v:0", "-map", "0:a:0?"];
+        args.extend_from_slice(codec_args);
+        args
+    };
+    let args = match output_ext {
+        "mp4" | "m4v" => video(&["-c:v", "libx264", "-pix_fmt", "yuv420p", "-profile:v", "main", "-c:a", "aac", "-movflags", "+faststart"]),
+        "mov" => video(&["-c:v", "libx264", "-pix_fmt", "yuv420p", "-c:a", "aac"]),
+        "webm" => video(&["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "32", "-row-mt", "1", "-c:a", "libopus"]),
+        "mkv" => video(&[]),
+        // A poster frame from the start of the video
+        "jpg" | "jpeg" => vec!["-map", "0:v:0", "-frames:v", "1", "-update", "1", "-q:v", "2"],
+        "png" | "webp" => vec!["-map", "0:v:0", "-frames:v", "1", "-update", "1"],
+        ext if is_audio_format(ext) => {
+            let mut args = vec!["-map", "0:a:0"];
+            match aac_muxer_args(ext) {
+                muxer if muxer.is_empty() => args.push("-vn"),
+                muxer => args.extend(muxer),
+            }
+            args
+        }
+        _ => return None,
+    };
+    Some(args)
+}
+
+/// Whether all `outputs` can come out of one FFmpeg pass over the input, decoding it once:
+/// a video (or audio, for audio outputs) input, outputs FFmpeg writes with fixed settings,
+/// and no per-file settings that only the regular conversion applies (cropping, watermarks,
+/// subtitles, track selection, target sizes)
+pub fn single_pass_outputs(input_ext: &str, outputs: &[String], settings: &ConversionSettings) -> bool {
+    let input_ok = is_video_format(input_ext) || (is_audio_format(input_ext) && outputs.iter().all(|ext| is_audio_format(ext)));
+    let per_file_settings = settings.crop.is_some()
+        || settings.watermark.is_some()
+        || settings.subtitles.is_some()
+        || settings.stream_selection.is_some()
+        || settings.target_size_mb.is_some()
+        || settings.loudness_two_pass;
+    input_ok && outputs.len() > 1 && !per_file_settings && outputs.iter().all(|ext| shared_pass_output_args(ext).is_some())
+}
+
+/// Output arguments of a shared FFmpeg pass, one `(extension, path)` per output, each with
+/// its codec settings, the audio settings, the metadata handling and `thread_args` (FFmpeg's
+/// thread limits apply per output) before its file name
+pub fn shared_pass_args(outputs: &[(String, String)], thread_args: &[String], settings: &ConversionSettings) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (ext, path) in outputs {
+        let codec_args = shared_pass_output_args(ext)
+            .ok_or_else(|| format!("{} can't be written in a shared pass", ext.to_uppercase()))?;
+        args.extend(codec_args.iter().map(|arg| arg.to_string()));
+        if !is_image_format(ext) {
+            args.extend(audio_processing_args(ext, settings, &AudioAnalysis::default()));
+        }
+        args.extend(metadata_args("ffmpeg", settings).iter().map(|arg| arg.to_string()));
+        args.extend(thread_args.iter().cloned());
+        args.push(path.clone());
+    }
+    Ok(args)
+}
+
+/// FFmpeg log levels that can be chosen in the settings, quietest first
+pub const FFMPEG_LOG_LEVELS: &[&str] = &["quiet", "error", "warning", "info", "verbose"];
+
+/// Shows warnings without the stream banner, so whatever FFmpeg prints is worth reading
+pub const DEFAULT_FFMPEG_LOG_LEVEL: &str = "warning";
+
+/// Distinct warnings kept per conversion; a broken file can print thousands
+pub const MAX_TOOL_WARNINGS: usize = 20;
+
+/// Arguments that go first on FFmpeg conversions: the log level and the progress stats
+pub fn ffmpeg_log_args(level: &str) -> Vec<String> {
+    vec!["-loglevel".to_string(), level.to_string(), "-stats".to_string()]
+}
+
+/// Whether the log at `level` only holds warnings and errors, so all of it can be reported
+pub fn log_level_reports_warnings(level: &str) -> bool {
+    matches!(level, "error" | "warning")
+}
+
+/// `-stats` progress lines: "frame=  240 fps= 60 ... speed=2.1x" or "size=  512kB time=..."
+pub fn is_ffmpeg_stats_line(line: &str) -> bool {
+    line.starts_with("frame=") || line.starts_with("size=") || (line.contains("time=") && line.contains("speed="))
+}
+
+/// Parses the warnings of an FFmpeg run at the warning level: every line but the progress
+/// stats, without the `[component @ 0x...]` prefix, counting repeats (FFmpeg folds them into
+/// "Last message repeated 3 times")
+pub fn parse_ffmpeg_warnings(stderr: &str) -> Vec<ToolWarning> {
+    let mut warnings: Vec<ToolWarning> = Vec::new();
+    let mut last: Option<usize> = None;
+
+    for line in stderr.split(['\n', '\r']).map(str::trim).filter(|line| !line.is_empty()) {
+        if is_ffmpeg_stats_line(line) {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("Last message repeated ") {
+            let repeats = rest.split_whitespace().next().and_then(|n| n.parse::<u32>().ok()).unwrap_or(0);
+            if let Some(index) = last {
+                warnings[index].count += repeats;
+            }
+            continue;
+        }
+
+        // "[mp4 @ 0x7f8a] [vost#0:0 @ 0x7f8b] message": the innermost component printed it
+        let mut message = line;
+        let mut component = None;
+        while let Some((prefix, rest)) = message.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            component = Some(prefix.split(" @ ").next().unwrap_or(prefix).trim().to_string());
+            message = rest.trim_start();
+        }
+        if message.is_empty() {
+            continue;
+        }
+
+        match warnings.iter().position(|w| w.component == component && w.message == message) {
+            Some(index) => {
+                warnings[index].count += 1;
+                last = Some(index);
+            }
+            None if warnings.len() < MAX_TOOL_WARNINGS => {
+                warnings.push(ToolWarning { component, message: message.to_string(), count: 1 });
+                last = Some(warnings.len() - 1);
+            }
+            None => last = None,
+        }
+    }
+    warnings
+}
+
+/// Splits a tool's output into lines as it arrives. FFmpeg ends its progress stats with
+/// `\r` so they overwrite each other in a terminal; those count as lines too.
+#[derive(Debug, Default)]
+pub struct OutputLines {
+    pending: Vec<u8>,
+}
+
+impl OutputLines {
+    /// The lines `chunk` completes, without their line endings and blank lines
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in chunk {
+            if byte == b'\n' || byte == b'\r' {
+                let line = String::from_utf8_lossy(&self.pending).trim_end().to_string();
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+                self.pending.clear();
+            } else {
+                self.pending.push(byte);
+            }
+        }
+        lines
+    }
+
+    /// What's left once the output has ended, if it didn't end with a line break
+    pub fn finish(&mut self) -> Option<String> {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.pending)).trim_end().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// Parses the encoder names from `ffmpeg -hide_banner -encoders` output.
+///
+/// The list follows a legend and a `------` separator, one encoder per line:
+/// ` V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)`
+pub fn parse_ffmpeg_encoders(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            // Flags are a fixed 6 characters starting with the stream type (V/A/S)
+            if flags.len() != 6 || !flags.starts_with(['V', 'A', 'S']) {
+                return None;
+            }
+            parts.next().map(str::to_string)
+        })
+        .collect()
+}
+
+/// Parses the formats ImageMagick can read from `magick -list format` output.
+///
+/// After a `------` separator each format has a line with its name (`*` marks native
+/// blob support), mode and description; long descriptions continue on indented lines:
+/// `     HEIC* rw+   High Efficiency Image Format (1.17.6)`
+///
+/// ImageMagick 7 adds the module the format comes from between the name and the mode:
+/// `     HEIC* HEIC      rw+   High Efficiency Image Format (1.17.6)`
+pub fn parse_magick_read_formats(output: &str) -> Vec<String> {
+    let is_mode = |word: &str| word.len() == 3 && word.chars().all(|c| matches!(c, 'r' | 'w' | '+' | '-'));
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?.trim_end_matches('*');
+            let mode = parts.take(2).find(|word| is_mode(word))?;
+            mode.starts_with('r').then(|| name.to_uppercase())
+        })
+        .collect()
+}
+
+/// Layout of a HEIC picture stored as a grid of HEVC tiles, from FFmpeg's stream information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeicGrid {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Display matrix rotation in degrees (counter-clockwise), 0 if there is none
+    pub rotation: i32,
+}
+
+impl HeicGrid {
+    /// Tiles across and down, covering the picture (edge tiles may overhang)
+    pub fn tiles(&self) -> (u32, u32) {
+        (self.width.div_ceil(self.tile_width), self.height.div_ceil(self.tile_height))
+    }
+}
+
+/// First plausible `WIDTHxHEIGHT` in an FFmpeg stream line (not a 0x... codec tag)
+fn stream_dimensions(line: &str) -> Option<(u32, u32)> {
+    line.split(|c: char| c.is_whitespace() || c == ',').find_map(|word| {
+        if word.starts_with("0x") {
+            return None;
+        }
+        let (w, h) = word.split_once('x')?;
+        let (w, h) = (w.parse::<u32>().ok()?, h.parse::<u32>().ok()?);
+        ((16..100000).contains(&w) && (16..100000).contains(&h)).then_some((w, h))
+    })
+}
+
+/// Reads the tile grid of a HEIC from `ffmpeg -i` output: the default `Tile Grid` stream
+/// group has the full size and the HEVC streams the tile size. None for pictures that
+/// aren't tiled (FFmpeg converts those directly).
+///
+/// ```text
+/// Stream group #0:0[0x31]: Tile Grid: hevc (Main Still Picture) (hvc1 / 0x31637668), yuv420p(pc), 4032x3024 (default)
+/// Stream #0:0[0x1]: Video: hevc (Main Still Picture) (hvc1 / 0x31637668), yuv420p(pc), 512x512, 1 fps
+/// ```
+pub fn parse_heic_grid(ffmpeg_output: &str) -> Option<HeicGrid> {
+    let grid_line = ffmpeg_output
+        .lines()
+        .find(|line| line.contains("Tile Grid:") && line.contains("hevc") && line.contains("default"))?;
+    let (width, height) = stream_dimensions(grid_line)?;
+    // Older iPhones always used 512x512 tiles
+    let (tile_width, tile_height) = ffmpeg_output
+        .lines()
+        .filter(|line| line.trim_start().starts_with("Stream #") && line.contains("Video: hevc"))
+        .find_map(stream_dimensions)
+        .unwrap_or((512, 512));
+
+    let rotation = if ffmpeg_output.contains("rotation of -90") {
+        -90
+    } else if ffmpeg_output.contains("rotation of 90") {
+        90
+    } else if ffmpeg_output.contains("rotation of 180") || ffmpeg_output.contains("rotation of -180") {
+        180
+    } else {
+        0
+    };
+    Some(HeicGrid { width, height, tile_width, tile_height, rotation })
+}
+
+/// Returns the encoders an FFmpeg argument list asks for by name (`-c:v libx265`,
+/// `-acodec libfdk_aac`, ...). Stream copies aren't encoders and are left out.
+pub fn requested_encoders(args: &[String]) -> Vec<String> {
+    let mut encoders: Vec<String> = Vec::new();
+    for pair in args.windows(2) {
+        let flag = pair[0].as_str();
+        let is_codec_flag = matches!(flag, "-c" | "-codec" | "-vcodec" | "-acodec" | "-scodec")
+            || flag.starts_with("-c:")
+            || flag.starts_with("-codec:");
+        if is_codec_flag && pair[1] != "copy" && !encoders.contains(&pair[1]) {
+            encoders.push(pair[1].clone());
+        }
+    }
+    encoders
+}
+
+/// Checks whether a tool path belongs to a Homebrew installation (macOS or Linuxbrew)
+pub fn is_homebrew_path(path: &str) -> bool {
+    ["/opt/homebrew/", "/usr/local/bin/", "/usr/local/Cellar/", "/usr/local/opt/", "/home/linuxbrew/.linuxbrew/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// Returns the (video, audio) encoders used for a target-size encode of the given output,
+/// or `None` if the format is lossless or not a target-size candidate.
+pub fn target_size_codecs(output_ext: &str) -> Option<(Option<&'static str>, &'static str)> {
+    match output_ext {
+        "mp4" | "mov" | "mkv" | "m4v" | "avi" => Some((Some("libx264"), "aac")),
+        "webm" => Some((Some("libvpx-vp9"), "libopus")),
+        "mp3" => Some((None, "libmp3lame")),
+        "m4a" | "aac" => Some((None, "aac")),
+        "ogg" => Some((None, "libvorbis")),
+        _ => None,
+    }
+}
+
+/// Computes the (video, audio) bitrates in kbps needed to hit `target_mb` for a clip of
+/// `duration_secs`. Audio-only outputs spend the whole budget on audio.
+pub fn compute_target_bitrates(
+    target_mb: f64,
+    duration_secs: f64,
+    has_video: bool,
+) -> Result<(Option<u32>, u32), String> {
+    if target_mb <= 0.0 {
+        return Err("Target size must be greater than zero".to_string());
+    }
+    if duration_secs <= 0.0 {
+        return Err("Could not determine the media duration".to_string());
+    }
+
+    // Leave ~3% headroom for container overhead so the result lands under the target
+    let total_kbps = (target_mb * 8.0 * 1024.0 * 0.97 / duration_secs).floor() as u32;
+
+    if !has_video {
+        // Encoders don't go below 8 kbps and gain little above 320 kbps
+        return Ok((None, total_kbps.clamp(8, 320)));
+    }
+
+    let audio_kbps = TARGET_SIZE_AUDIO_KBPS.min(total_kbps / 4);
+    let video_kbps = total_kbps.saturating_sub(audio_kbps);
+    if video_kbps < MIN_TARGET_VIDEO_KBPS {
+        return Err(format!(
+            "{} MB is too small for a {:.0} second video. Try a larger target size or trim the video first.",
+            target_mb, duration_secs
+        ));
+    }
+
+    Ok((Some(video_kbps), audio_kbps))
+}
+
+/// Detects the AAC container from the first bytes of a file
+pub fn detect_aac_container(header: &[u8]) -> AacContainer {
+    // MP4 files have an "ftyp" box right after the 4-byte box size
+    if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        return AacContainer::Mp4;
+    }
+    // ADTS frames start with a 12-bit sync word (0xFFF) and layer bits set to 0
+    if header.len() >= 2 && header[0] == 0xFF && (header[1] & 0xF6) == 0xF0 {
+        return AacContainer::Adts;
+    }
+    AacContainer::Unknown
+}
+
+/// Warns when an AAC/M4A file's extension doesn't match its container, or when raw AAC is picked as output.
+///
+/// `input_header` is the first bytes of the input file (at least 8 for detection).
+pub fn aac_format_warning(input_ext: &str, input_header: &[u8], output_ext: &str) -> Option<String> {
+    match (input_ext, detect_aac_container(input_header)) {
+        ("aac", AacContainer::Mp4) => {
+            return Some("This .aac file is actually an M4A (MP4) file. Renaming it to .m4a will help players open it.".to_string());
+        }
+        ("m4a", AacContainer::Adts) => {
+            return Some("This .m4a file is actually raw AAC. Renaming it to .aac will help players open it.".to_string());
+        }
+        _ => {}
+    }
+
+    if output_ext == "aac" {
+        return Some(
+            "Raw AAC files have no container, so many players and phones can't seek in them or show \
+            their duration. Choose M4A unless a device specifically needs .aac.".to_string()
+        );
+    }
+
+    None
+}
+
+/// Returns the FFmpeg arguments that pick the right muxer for AAC outputs.
+/// Video streams (and cover art) are dropped since neither container holds them here.
+pub fn aac_muxer_args(output_ext: &str) -> Vec<&'static str> {
+    match output_ext {
+        "aac" => vec!["-vn", "-c:a", "aac", "-f", "adts"],
+        "m4a" => vec!["-vn", "-c:a", "aac", "-f", "ipod", "-movflags", "+faststart"],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the FFmpeg arguments (placed right after the input) for a still AVIF image.
+/// Transparent input is written as a second AV1 stream holding the alpha plane.
+/// `video_filter` (the upright, cropped and watermarked frame, as a `-vf` graph) runs on
+/// both streams, so the alpha plane is turned, cropped and marked like the colour one.
+pub fn ffmpeg_avif_args(has_alpha: bool, video_filter: FilterGraph) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    if has_alpha {
+        args.extend(["-map", "0:v", "-map", "0:v", "-filter:v:1"].map(String::from));
+        if video_filter.is_empty() {
+            args.push(Filter::AlphaExtract.render());
+        } else {
+            args.push(video_filter.clone().then(Filter::AlphaExtract).render());
+        }
+    }
+    if !video_filter.is_empty() {
+        args.push("-filter:v:0".to_string());
+        args.push(video_filter.render());
+    }
+    args.extend(
+        ["-frames:v", "1", "-c:v", "libaom-av1", "-still-picture", "1", "-cpu-used", "6", "-crf", "28", "-b:v", "0", "-row-mt", "1"]
+            .map(String::from),
+    );
+    args
+}
+
+/// Formats an audio file can be combined into by concat_audio_files
+pub const CONCAT_AUDIO_OUTPUTS: &[&str] = &["mp3", "m4a"];
+
+/// Builds the list file read by FFmpeg's concat demuxer, one `file '...'` line per input.
+/// Single quotes in paths are closed, escaped and reopened (`'\''`).
+pub fn concat_list(paths: &[String]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("file '{}'\n", path.replace('\'', "'\\''")))
+        .collect()
+}
+
+/// Checks if inputs can be joined without re-encoding: stream copy keeps the codec, so
+/// every input must already hold what the output container expects
+pub fn can_concat_by_copy(input_exts: &[String], output_ext: &str) -> bool {
+    let compatible: &[&str] = match output_ext {
+        "mp3" => &["mp3"],
+        "m4a" => &["m4a", "aac"],
+        _ => &[],
+    };
+    !input_exts.is_empty() && input_exts.iter().all(|ext| compatible.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Builds the FFmpeg arguments that join the files in a concat list into one audio file by
+/// copying the streams: lossless and fast, but needs matching inputs (see can_concat_by_copy)
+pub fn concat_audio_args(list_path: &str, output_ext: &str) -> Vec<String> {
+    // -safe 0 allows absolute paths in the list
+    let mut args: Vec<String> = ["-f", "concat", "-safe", "0", "-i", list_path, "-vn", "-c", "copy"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    if output_ext == "m4a" {
+        args.extend(["-movflags", "+faststart"].map(String::from));
+    }
+    args
+}
+
+/// Builds the FFmpeg arguments that re-encode `inputs` into one audio file. Each input is
+/// opened on its own and joined with the concat filter, which (unlike the concat demuxer)
+/// handles inputs with different codecs, sample rates and channel layouts.
+pub fn concat_audio_reencode_args(inputs: &[String], output_ext: &str) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    for input in inputs {
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+
+    let mut graph = FilterGraph::new();
+    let mut segments: Vec<String> = Vec::new();
+    for index in 0..inputs.len() {
+        let audio_in = format!("{}:a", index);
+        let audio_out = format!("a{}", index);
+        graph = graph.chain(&[audio_in.as_str()], vec![Filter::AResample(48000)], &[audio_out.as_str()]);
+        segments.push(audio_out);
+    }
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let concat = Filter::Concat { segments: inputs.len() as u32, video: 0, audio: 1 };
+    graph = graph.chain(&segments, vec![concat], &["a"]);
+
+    let codec = if output_ext == "mp3" { "libmp3lame" } else { "aac" };
+    args.extend(["-filter_complex".to_string(), graph.render(), "-map".to_string(), "[a]".to_string()]);
+    args.extend(["-c:a", codec, "-b:a", "192k"].map(String::from));
+    if output_ext == "m4a" {
+        args.extend(["-movflags", "+faststart"].map(String::from));
+    }
+    args
+}
+
+/// Video codecs each container takes as they are, for joining clips by stream copy
+/// (any container not listed takes anything)
+fn container_video_codecs(output_ext: &str) -> Option<&'static [&'static str]> {
+    match output_ext {
+        "mp4" | "m4v" | "mov" => Some(&["h264", "hevc", "mpeg4", "av1"]),
+        "webm" => Some(&["vp8", "vp9", "av1"]),
+        _ => None,
+    }
+}
+
+/// Checks if video clips can be joined by stream copy: every clip has the same video (and
+/// audio) codec and shape, and the output container holds that codec. Anything else needs
+/// a re-encode, or players choke at the joins.
+pub fn can_concat_videos_by_copy(clips: &[MediaInfo], output_ext: &str) -> bool {
+    let Some(first) = clips.first() else { return false };
+    let Some(video) = &first.video else { return false };
+    if container_video_codecs(output_ext).is_some_and(|codecs| !codecs.contains(&video.codec.as_str())) {
+        return false;
+    }
+
+    let same_frame_rate = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => (a - b).abs() < 0.01,
+        _ => a.is_none() && b.is_none(),
+    };
+    clips.iter().all(|clip| {
+        let video_matches = clip.video.as_ref().is_some_and(|other| {
+            other.codec == video.codec
+                && other.width == video.width
+                && other.height == video.height
+                && other.pixel_format == video.pixel_format
+                && same_frame_rate(other.frame_rate, video.frame_rate)
+        });
+        let audio_matches = match (&clip.audio, &first.audio) {
+            (Some(a), Some(b)) => a.codec == b.codec && a.sample_rate == b.sample_rate && a.channels == b.channels,
+            (None, None) => true,
+            _ => false,
+        };
+        video_matches && audio_matches
+    })
+}
+
+/// Builds the `-filter_complex` graph that re-encodes `clips` inputs into one: each clip is
+/// fitted onto a `width` x `height` canvas at `fps`, then all are joined into `[v]` (and
+/// `[a]` when every clip has audio).
+pub fn concat_reencode_graph(clips: usize, width: u32, height: u32, fps: f32, with_audio: bool) -> FilterGraph {
+    // Odd sizes don't fit yuv420p
+    let (width, height) = (width.max(2) / 2 * 2, height.max(2) / 2 * 2);
+    let mut graph = FilterGraph::new();
+    let mut segments: Vec<String> = Vec::new();
+    for clip in 0..clips {
+        let video_in = format!("{}:v", clip);
+        let video_out = format!("v{}", clip);
+        let fit = Filter::Scale {
+            width: width.to_string(),
+            height: height.to_string(),
+            fit: Some(filter_graph::ScaleFit::Decrease),
+        };
+        let filters = vec![
+            fit,
+            Filter::pad_centered(width.to_string(), height.to_string(), "black"),
+            Filter::SquarePixels,
+            Filter::Fps(fps),
+            Filter::Format("yuv420p".to_string()),
+        ];
+        graph = graph.chain(&[video_in.as_str()], filters, &[video_out.as_str()]);
+        segments.push(video_out);
+
+        if with_audio {
+            let audio_in = format!("{}:a", clip);
+            let audio_out = format!("a{}", clip);
+            graph = graph.chain(&[audio_in.as_str()], vec![Filter::AResample(48000)], &[audio_out.as_str()]);
+            segments.push(audio_out);
+        }
+    }
+
+    let inputs: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let concat = Filter::Concat { segments: clips as u32, video: 1, audio: u32::from(with_audio) };
+    let outputs: &[&str] = if with_audio { &["v", "a"] } else { &["v"] };
+    graph.chain(&inputs, vec![concat], outputs)
+}
+
+/// Encoder arguments for re-encoding joined clips into `output_ext`
+pub fn concat_video_codec_args(output_ext: &str) -> Vec<&'static str> {
+    match output_ext {
+        "webm" => vec!["-c:v", "libvpx-vp9", "-crf", "32", "-b:v", "0", "-c:a", "libopus", "-b:a", "128k"],
+        _ => vec!["-c:v", "libx264", "-crf", "20", "-preset", "medium", "-c:a", "aac", "-b:a", "192k"],
+    }
+}
+
+/// Reads the output position in seconds from an FFmpeg `-progress` line
+/// (`out_time_us=` and the misnamed `out_time_ms=` are both microseconds)
+pub fn parse_progress_time(line: &str) -> Option<f64> {
+    let (key, value) = line.trim().split_once('=')?;
+    match key {
+        "out_time_us" | "out_time_ms" => value.parse::<i64>().ok().map(|us| us.max(0) as f64 / 1_000_000.0),
+        _ => None,
+    }
+}
+
+/// The clip (0-based) being written `elapsed_secs` into the joined output
+pub fn concat_segment_at(durations: &[f64], elapsed_secs: f64) -> usize {
+    let mut end = 0.0;
+    for (index, duration) in durations.iter().enumerate() {
+        end += duration;
+        if elapsed_secs < end {
+            return index;
+        }
+    }
+    durations.len().saturating_sub(1)
+}
+
+/// Image formats video frames can be extracted as
+pub const FRAME_OUTPUTS: &[&str] = &["png", "jpg"];
+
+/// Highest extraction rate accepted; anything above is almost always a typo for a lower one
+pub const MAX_EXTRACT_FPS: f32 = 120.0;
+
+/// Which frames extract_frames writes
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameSelection {
+    /// Evenly spaced, this many per second of video
+    Fps(f32),
+    /// One frame at each of these times in seconds, sorted without duplicates
+    Timestamps(Vec<f64>),
+}
+
+impl FrameSelection {
+    /// Takes exactly one of a frame rate and a list of times
+    pub fn from_request(fps: Option<f32>, timestamps: Option<Vec<f64>>) -> Result<Self, String> {
+        match (fps, timestamps) {
+            (Some(fps), None) => {
+                if !(fps > 0.0 && fps <= MAX_EXTRACT_FPS) {
+                    return Err(format!("Frame rate must be between 0 and {} frames per second", MAX_EXTRACT_FPS));
+                }
+                Ok(FrameSelection::Fps(fps))
+            }
+            (None, Some(mut timestamps)) => {
+                if timestamps.is_empty() {
+                    return Err("Add at least one time to extract a frame at".to_string());
+                }
+                if let Some(bad) = timestamps.iter().find(|t| !t.is_finite() || **t < 0.0) {
+                    return Err(format!("Invalid frame time: {}", bad));
+                }
+                timestamps.sort_by(f64::total_cmp);
+                // Times closer than a millisecond land on the same frame
+                timestamps.dedup_by(|a, b| (*a - *b).abs() < 0.001);
+                Ok(FrameSelection::Timestamps(timestamps))
+            }
+            (Some(_), Some(_)) => Err("Choose either a frame rate or a list of times, not both".to_string()),
+            (None, None) => Err("Choose a frame rate or a list of times to extract frames at".to_string()),
+        }
+    }
+}
+
+/// File name of the `index`th extracted frame (1-based), e.g. `frame_00042.png`
+pub fn frame_file_name(index: usize, output_ext: &str) -> String {
+    format!("frame_{:05}.{}", index, output_ext)
+}
+
+/// FFmpeg output arguments for extracting frames: evenly spaced at `fps`, or a single frame
+/// when `fps` is None. The numbered output pattern (or single file name) follows.
+pub fn extract_frames_args(fps: Option<f32>, output_ext: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    match fps {
+        Some(fps) => {
+            args.push("-vf".to_string());
+            args.push(Filter::Fps(fps).render());
+        }
+        None => args.extend(["-frames:v".to_string(), "1".to_string()]),
+    }
+    if output_ext == "jpg" {
+        // 2 is visually lossless; 1 doubles the size for no visible gain
+        args.extend(["-q:v".to_string(), "2".to_string()]);
+    }
+    args
+}
+
+/// Largest contact sheet grid side; beyond this the tiles are too small to be useful
+pub const MAX_CONTACT_SHEET_SIDE: u32 = 10;
+
+/// Filters that sample `columns` x `rows` frames evenly across a video of `duration_secs`
+/// and tile them into one image, each `tile_width` pixels wide, optionally stamped with its
+/// time. Frames are taken from the middle of each interval so the first tile isn't the
+/// usual black opening frame.
+pub fn contact_sheet_filters(
+    duration_secs: f64,
+    columns: u32,
+    rows: u32,
+    tile_width: u32,
+    timestamps: bool,
+    font_file: Option<&str>,
+) -> Vec<Filter> {
+    let tiles = (columns * rows).max(1);
+    let interval = (duration_secs / tiles as f64).max(0.001);
+    let select = format!(
+        "gte(t,{offset:.3})*(isnan(prev_selected_t)+gte(t-prev_selected_t,{interval:.3}))",
+        offset = interval / 2.0,
+        interval = interval,
+    );
+
+    let mut filters = vec![Filter::Select(select), Filter::scale(tile_width.to_string(), "-2")];
+    if timestamps {
+        filters.push(Filter::DrawText {
+            text: "%{pts\\:hms}".to_string(),
+            x: "5".to_string(),
+            y: "h-th-5".to_string(),
+            font_size: (tile_width / 16).max(10),
+            font_file: font_file.map(String::from),
+        });
+    }
+    filters.push(Filter::Tile { cols: columns, rows });
+    filters
+}
+
+/// Formats subtitle tracks can be extracted to
+pub const SUBTITLE_OUTPUTS: &[&str] = &["srt", "ass"];
+
+/// Subtitle codecs stored as text, which convert to SRT/ASS. Bitmap subtitles (Blu-ray PGS,
+/// DVD VobSub) would need OCR.
+pub fn is_text_subtitle_codec(codec: &str) -> bool {
+    matches!(codec, "subrip" | "srt" | "ass" | "ssa" | "mov_text" | "webvtt" | "text")
+}
+
+/// File name for an extracted track: `movie.eng.srt`, `movie.eng.2.srt` when several tracks
+/// share a language, or `movie.3.srt` without one
+pub fn subtitle_file_name(stem: &str, track: &SubtitleStreamInfo, same_language_tracks: usize, output_ext: &str) -> String {
+    match &track.language {
+        Some(language) if same_language_tracks <= 1 => format!("{}.{}.{}", stem, language, output_ext),
+        Some(language) => format!("{}.{}.{}.{}", stem, language, track.index + 1, output_ext),
+        None => format!("{}.{}.{}", stem, track.index + 1, output_ext),
+    }
+}
+
+/// FFmpeg output arguments that write subtitle track `index` of the input as `output_ext`
+pub fn extract_subtitle_args(index: u32, output_ext: &str) -> Vec<String> {
+    let codec = if output_ext == "ass" { "ass" } else { "srt" };
+    vec!["-map".to_string(), format!("0:s:{}", index), "-c:s".to_string(), codec.to_string()]
+}
+
+/// Subtitle codec each container stores a muxed text track as
+pub fn mux_subtitle_codec(output_ext: &str) -> Option<&'static str> {
+    match output_ext {
+        "mp4" | "m4v" | "mov" => Some("mov_text"),
+        "mkv" => Some("copy"),
+        "webm" => Some("webvtt"),
+        _ => None,
+    }
+}
+
+/// FFmpeg arguments that add an external subtitle file to a video. For `Mux` they start
+/// with the file as a second input, so they must come right after the main `-i`. For
+/// `Burn`, `subtitle_input` should be a plain file name in the working directory: the
+/// `subtitles` filter's own escaping of drive letters and quotes is too fragile for full paths.
+pub fn subtitle_args(mode: SubtitleMode, subtitle_input: &str, output_ext: &str) -> Result<Vec<String>, String> {
+    match mode {
+        SubtitleMode::Burn => Ok(vec!["-vf".to_string(), format!("subtitles={}", subtitle_input)]),
+        SubtitleMode::Mux => {
+            let codec = mux_subtitle_codec(output_ext).ok_or_else(|| {
+                format!("{} files can't hold subtitle tracks; burn the subtitles in instead", output_ext.to_uppercase())
+            })?;
+            Ok([
+                "-i", subtitle_input,
+                "-map", "0:v?", "-map", "0:a?", "-map", "1:s",
+                "-c:s", codec,
+            ].iter().map(|arg| arg.to_string()).collect())
+        }
+    }
+}
+
+/// FFmpeg `-map` arguments keeping the selected streams of input 0 (plus input 1's
+/// subtitles when an external file is muxed in), with the first selected audio track
+/// marked as the default. Audio outputs keep a single audio track and nothing else.
+pub fn stream_selection_args(
+    selection: &StreamSelection,
+    streams: &[StreamInfo],
+    output_ext: &str,
+    external_subtitles: bool,
+) -> Result<Vec<String>, String> {
+    let find = |index: u32, kind: StreamKind| {
+        streams
+            .iter()
+            .find(|stream| stream.index == index && stream.kind == kind)
+            .ok_or_else(|| format!("Stream {} isn't {} track of this file", index, if kind == StreamKind::Audio { "an audio" } else { "a subtitle" }))
+    };
+    let audio = selection.audio.iter().map(|index| find(*index, StreamKind::Audio)).collect::<Result<Vec<_>, _>>()?;
+    let subtitles = selection.subtitles.iter().map(|index| find(*index, StreamKind::Subtitle)).collect::<Result<Vec<_>, _>>()?;
+
+    if is_audio_format(output_ext) {
+        return match audio.as_slice() {
+            [track] => Ok(vec!["-map".to_string(), format!("0:{}", track.index)]),
+            [] => Err("Choose the audio track to convert".to_string()),
+            _ => Err(format!("{} files hold a single audio track; choose one", output_ext.to_uppercase())),
+        };
+    }
+
+    let mut args: Vec<String> = Vec::new();
+    let mut map = |spec: String| args.extend(["-map".to_string(), spec]);
+
+    // Capital V leaves out cover art, which isn't a real video track
+    map("0:V?".to_string());
+    for track in &audio {
+        map(format!("0:{}", track.index));
+    }
+    for track in &subtitles {
+        map(format!("0:{}", track.index));
+    }
+    if external_subtitles {
+        map("1:s".to_string());
+    }
+
+    for position in 0..audio.len() {
+        let disposition = if position == 0 { "default" } else { "0" };
+        args.extend([format!("-disposition:a:{}", position), disposition.to_string()]);
+    }
+
+    if !subtitles.is_empty() || external_subtitles {
+        let codec = mux_subtitle_codec(output_ext).ok_or_else(|| {
+            format!("{} files can't hold subtitle tracks", output_ext.to_uppercase())
+        })?;
+        if codec != "copy" {
+            if let Some(bitmap) = subtitles.iter().find(|track| !is_text_subtitle_codec(&track.codec)) {
+                return Err(format!(
+                    "Subtitle stream {} is picture-based ({}), which {} can't hold; convert to MKV to keep it",
+                    bitmap.index, bitmap.codec, output_ext.to_uppercase()
+                ));
+            }
+        }
+        args.extend(["-c:s".to_string(), codec.to_string()]);
+    }
+    Ok(args)
+}
+
+/// Joins a program and its arguments into one command line, quoting arguments that
+/// contain spaces or quotes so the line can be copied into a terminal
+pub fn format_command_line(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .map(|part| {
+            if part.is_empty() || part.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+                format!("\"{}\"", part.replace('"', "\\\""))
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the arguments that drop or keep embedded metadata, per `strip_metadata` and
+/// `preserve_metadata`. Stripping wins when both are set.
+///
+/// ImageMagick's `-strip` also removes color profiles and comments; without it ImageMagick
+/// already keeps the EXIF/XMP profiles, so preserving needs no flags. FFmpeg only copies
+/// global tags (ID3, container metadata) when mapped explicitly.
+///
+/// Deterministic output strips metadata too, and has FFmpeg leave out its version and the
+/// encoding time (`bitexact`) so they don't differ between runs.
+pub fn metadata_args(tool: &str, settings: &ConversionSettings) -> Vec<&'static str> {
+    let mut args = match (tool, settings.strips_metadata(), settings.preserve_metadata) {
+        ("imagemagick", true, _) => vec!["-strip"],
+        ("ffmpeg", true, _) => vec!["-map_metadata", "-1"],
+        ("ffmpeg", false, true) => vec!["-map_metadata", "0"],
+        _ => Vec::new(),
+    };
+    if tool == "ffmpeg" && settings.deterministic {
+        args.extend(["-fflags", "+bitexact", "-flags:v", "+bitexact", "-flags:a", "+bitexact"]);
+    }
+    args
+}
+
+/// Timestamp written where a format needs one in deterministic output: 1980-01-01, the
+/// earliest a ZIP entry (and so EPUB and Office files) can hold
+pub const DETERMINISTIC_EPOCH: u64 = 315_532_800;
+
+/// Environment for tools in deterministic output. ImageMagick, Pandoc and Ghostscript take
+/// their timestamps from SOURCE_DATE_EPOCH instead of the clock; LibreOffice, Calibre and
+/// Chromium don't, so their PDFs and e-books still differ by creation date.
+pub fn deterministic_env(settings: &ConversionSettings) -> Vec<(&'static str, String)> {
+    if settings.deterministic {
+        vec![("SOURCE_DATE_EPOCH", DETERMINISTIC_EPOCH.to_string())]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Typical spoken-word bitrate: stereo needs twice the bits of mono for the same quality
+pub fn podcast_bitrate_kbps(channels: u8) -> u32 {
+    if channels >= 2 { 128 } else { 64 }
+}
+
+/// Builds the podcast preset for an output format: -16 LUFS, chapters kept, ready for ID3 tags
+pub fn podcast_preset(output_format: &str, channels: u8) -> AudioPreset {
+    let layout = if channels >= 2 { "stereo" } else { "mono" };
+    AudioPreset {
+        id: format!("podcast-{}-{}", output_format, layout),
+        name: format!("Podcast {} ({})", output_format.to_uppercase(), layout),
+        output_format: output_format.to_string(),
+        settings: ConversionSettings {
+            loudness_lufs: Some(PODCAST_LOUDNESS_LUFS),
+            audio_channels: Some(channels),
+            audio_bitrate_kbps: Some(podcast_bitrate_kbps(channels)),
+            keep_chapters: true,
+            ..Default::default()
+        },
+    }
+}
+
+/// All built-in audio presets, in the order the frontend lists them
+pub fn builtin_audio_presets() -> Vec<AudioPreset> {
+    PODCAST_FORMATS
+        .iter()
+        .flat_map(|format| [podcast_preset(format, 1), podcast_preset(format, 2)])
+        .collect()
+}
+
+/// Quieter than this counts as silence when trimming
+pub const SILENCE_THRESHOLD_DB: f32 = -50.0;
+
+/// Shortest silence cut when trimming the end. Trailing silence can only be told apart from
+/// a pause by its length without buffering the whole stream, so ordinary pauses stay.
+pub const TRAILING_SILENCE_SECS: f64 = 2.0;
+
+/// Longest fade in or out
+pub const MAX_FADE_MS: u32 = 60_000;
+
+/// What a conversion found out about the input's audio before building its arguments
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioAnalysis {
+    /// From the first pass of two-pass loudness normalization
+    pub loudness: Option<LoudnessMeasurement>,
+    /// Length of the input, where a fade-out starts
+    pub duration_secs: Option<f64>,
+    /// The chapters come from a cue sheet, passed as an ffmetadata second input
+    pub cue_chapters: bool,
+}
+
+pub fn validate_audio_edits(settings: &ConversionSettings) -> Result<(), String> {
+    for (name, ms) in [("Fade in", settings.fade_in_ms), ("Fade out", settings.fade_out_ms)] {
+        if ms.is_some_and(|ms| ms > MAX_FADE_MS) {
+            return Err(format!("{} can be at most {} seconds", name, MAX_FADE_MS / 1000));
+        }
+    }
+    Ok(())
+}
+
+/// Filters that trim silence and fade, applied before loudness normalization. The fade-out
+/// starts `duration_secs` (the probed length) minus its own length in, on the input's
+/// timeline, so it's left out when the length isn't known. It comes before trimming, which
+/// shortens the audio by an amount only known once it's decoded.
+pub fn audio_edit_filters(settings: &ConversionSettings, duration_secs: Option<f64>) -> Vec<Filter> {
+    let seconds = |ms: Option<u32>| ms.filter(|ms| *ms > 0).map(|ms| ms as f64 / 1000.0);
+    let fade_in = seconds(settings.fade_in_ms);
+    let fade_out = seconds(settings.fade_out_ms);
+
+    let mut filters = Vec::new();
+    if let (Some(duration), Some(length)) = (fade_out, duration_secs) {
+        filters.push(Filter::AFade { fade_in: false, start: Some((length - duration).max(0.0)), duration });
+    }
+    if settings.trim_silence {
+        filters.push(Filter::SilenceRemove { threshold_db: SILENCE_THRESHOLD_DB });
+    }
+    if let Some(duration) = fade_in {
+        filters.push(Filter::AFade { fade_in: true, start: None, duration });
+    }
+    if settings.trim_silence {
+        filters.push(Filter::SilenceRemoveStops { threshold_db: SILENCE_THRESHOLD_DB, min_secs: TRAILING_SILENCE_SECS });
+    }
+    filters
+}
+
+/// FFmpeg arguments (after the input) for the analysis pass of two-pass loudness
+/// normalization, which decodes the audio and prints loudnorm's measurements to stderr
+pub fn loudness_analysis_args(lufs: f32) -> Vec<String> {
+    let analysis = Filter::LoudnormAnalysis {
+        integrated: lufs,
+        true_peak: LOUDNESS_TRUE_PEAK_DB,
+        range: LOUDNESS_RANGE_LU,
+    };
+    vec!["-vn".to_string(), "-af".to_string(), analysis.render(), "-f".to_string(), "null".to_string(), "-".to_string()]
+}
+
+/// Reads the measurements from the analysis pass (the JSON block loudnorm prints last).
+/// None when they're missing or not numbers, as with silent input ("-inf").
+pub fn parse_loudnorm_json(stderr: &str) -> Option<LoudnessMeasurement> {
+    let start = stderr.rfind("[Parsed_loudnorm").unwrap_or(0);
+    let json_start = start + stderr[start..].find('{')?;
+    let json_end = json_start + stderr[json_start..].find('}')?;
+    let json: serde_json::Value = serde_json::from_str(&stderr[json_start..=json_end]).ok()?;
+    let value = |key: &str| -> Option<f32> {
+        json.get(key)?.as_str()?.trim().parse::<f32>().ok().filter(|v| v.is_finite())
+    };
+    Some(LoudnessMeasurement {
+        input_i: value("input_i")?,
+        input_tp: value("input_tp")?,
+        input_lra: value("input_lra")?,
+        input_thresh: value("input_thresh")?,
+        target_offset: value("target_offset")?,
+    })
+}
+
+/// Returns the FFmpeg arguments for the audio settings: silence trimming and fades (audio
+/// outputs only, so video stays in sync), loudness normalization (as the second pass when
+/// the input was measured), channel layout, bitrate, chapter passthrough and tags.
+pub fn audio_processing_args(output_ext: &str, settings: &ConversionSettings, analysis: &AudioAnalysis) -> Vec<String> {
+    let mut args = Vec::new();
+
+    let mut filters = if is_audio_format(output_ext) {
+        audio_edit_filters(settings, analysis.duration_secs)
+    } else {
+        Vec::new()
+    };
+    if let Some(lufs) = settings.loudness_lufs {
+        filters.push(Filter::Loudnorm {
+            integrated: lufs,
+            true_peak: LOUDNESS_TRUE_PEAK_DB,
+            range: LOUDNESS_RANGE_LU,
+            measured: analysis.loudness,
+        });
+    }
+    if !filters.is_empty() {
+        args.push("-af".to_string());
+        args.push(filter_graph::chain(&filters));
+    }
+    if settings.loudness_lufs.is_some() {
+        // loudnorm upsamples to 192 kHz internally
+        args.push("-ar".to_string());
+        args.push("44100".to_string());
+    }
+
+    if let Some(channels) = settings.audio_channels {
+        args.push("-ac".to_string());
+        args.push(channels.to_string());
+    }
+
+    if let Some(kbps) = settings.audio_bitrate_kbps {
+        args.push("-b:a".to_string());
+        args.push(format!("{}k", kbps));
+    }
+
+    if settings.keep_chapters {
+        args.push("-map_chapters".to_string());
+        args.push(if analysis.cue_chapters { "1" } else { "0" }.to_string());
+    }
+
+    let tags = &settings.audio_tags;
+    let fields = [
+        ("title", &tags.title),
+        ("artist", &tags.artist),
+        ("album", &tags.album),
+        ("track", &tags.track),
+        ("date", &tags.year),
+        ("genre", &tags.genre),
+        ("comment", &tags.comment),
+    ];
+    let mut has_tags = false;
+    for (key, value) in fields {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            args.push("-metadata".to_string());
+            args.push(format!("{}={}", key, value));
+            has_tags = true;
+        }
+    }
+
+    // ID3v2.3 is what most podcast players read, and it carries the chapter (CHAP) frames
+    if output_ext == "mp3" && (has_tags || settings.keep_chapters) {
+        args.push("-id3v2_version".to_string());
+        args.push("3".to_string());
+    }
+
+    args
+}
+
+/// Determines the conversion tool, taking the user's conversion settings into account.
+///
+/// When `vector_output` is requested and both formats are vector formats, Inkscape is
+/// used so the output keeps its paths and text instead of being rasterized.
+///
+/// # Examples
+/// ```
+/// use convertsave_lib::conversion::{determine_conversion_tool_with_settings, ConversionSettings};
+///
+/// let vector = ConversionSettings { vector_output: true, ..Default::default() };
+/// assert_eq!(determine_conversion_tool_with_settings("svg", "pdf", &vector), Some("inkscape"));
+/// assert_eq!(determine_conversion_tool_with_settings("svg", "pdf", &ConversionSettings::default()), Some("imagemagick"));
+/// ```
+pub fn determine_conversion_tool_with_settings(
+    input_ext: &str,
+    output_ext: &str,
+    settings: &ConversionSettings,
+) -> Option<&'static str> {
+    if settings.vector_output && supports_vector_conversion(input_ext, output_ext) {
+        return Some("inkscape");
+    }
+
+    determine_conversion_tool(input_ext, output_ext)
+}
+
+/// Human-readable name of a conversion tool, for messages
+pub fn tool_display_name(tool: &str) -> &str {
+    match tool {
+        "ffmpeg" => "FFmpeg",
+        "imagemagick" => "ImageMagick",
+        "inkscape" => "Inkscape",
+        "chromium" => "Chromium",
+        "pandoc" => "Pandoc",
+        "libreoffice" => "LibreOffice",
+        "calibre" => "Calibre",
+        "ghostscript" => "Ghostscript",
+        "resvg" => "resvg",
+        "builtin" => "Built-in converter",
+        other => other,
+    }
+}
+
+/// Describes a tool standing in for `preferred_tool`, if it did
+pub fn fallback_notes(preferred_tool: Option<&str>, used_tool: &str) -> Vec<String> {
+    match preferred_tool {
+        Some(preferred) if preferred != used_tool => vec![format!(
+            "{} was used because {} isn't installed",
+            tool_display_name(used_tool),
+            tool_display_name(preferred)
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Warnings about what a conversion changed beyond the format, e.g. transparent
+/// pixels filled with white for an output without alpha
+pub fn conversion_warnings(output_ext: &str, input_has_alpha: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if input_has_alpha && OPAQUE_OUTPUTS.contains(&output_ext) {
+        warnings.push(format!(
+            "Transparent areas were filled with white because {} doesn't support transparency",
+            output_ext.to_uppercase()
+        ));
+    }
+    warnings
+}
+
+/// Explains why a conversion pair is unavailable (or confirms it isn't).
+///
+/// `tool_installed` reports whether a tool can be found, and `licensed` whether the
+/// current license allows conversions. Every reason that applies is listed, most
+/// actionable first; the message covers the first one.
+///
+/// # Examples
+/// ```
+/// use convertsave_lib::conversion::{explain_unsupported, UnsupportedReason};
+///
+/// let explanation = explain_unsupported("png", "heic", |_| false, true);
+/// assert_eq!(explanation.reasons, vec![UnsupportedReason::ToolNotInstalled]);
+/// assert!(explain_unsupported("png", "heic", |_| true, true).supported);
+/// ```
+pub fn explain_unsupported(
+    input_ext: &str,
+    output_ext: &str,
+    tool_installed: impl Fn(&str) -> bool,
+    licensed: bool,
+) -> UnsupportedExplanation {
+    let input_ext = input_ext.trim_start_matches('.').to_lowercase();
+    let output_ext = output_ext.trim_start_matches('.').to_lowercase();
+    let input_ext = input_ext.as_str();
+    let output_ext = output_ext.as_str();
+
+    let mut reasons = Vec::new();
+    let mut message = None;
+
+    let tool = match determine_conversion_tool(input_ext, output_ext) {
+        Some(tool) => Some(tool),
+        None if !pandoc_enabled() && DOC_INPUTS.contains(&input_ext) && DOC_OUTPUTS.contains(&output_ext) => {
+            reasons.push(UnsupportedReason::FeatureDisabled);
+            message = Some(format!(
+                "{} to {} needs Pandoc, which is disabled in this version",
+                input_ext.to_uppercase(),
+                output_ext.to_uppercase()
+            ));
+            Some("pandoc")
+        }
+        None => {
+            reasons.push(UnsupportedReason::NotSupported);
+            message = Some(if input_ext == output_ext {
+                format!("The file is already {}", input_ext.to_uppercase())
+            } else {
+                format!(
+                    "Converting {} to {} isn't supported",
+                    input_ext.to_uppercase(),
+                    output_ext.to_uppercase()
+                )
+            });
+            None
+        }
+    };
+
+    // Common image pairs still work without ImageMagick or FFmpeg
+    let tool = if reasons.is_empty() {
+        builtin_fallback(tool, input_ext, output_ext, &tool_installed)
+    } else {
+        tool
+    };
+
+    if let Some(tool) = tool {
+        // Renames and built-in conversions are done in-process, and a disabled backend can't be installed anyway
+        if tool != "rename" && tool != BUILTIN_TOOL && reasons.is_empty() && !tool_installed(tool) {
+            reasons.push(UnsupportedReason::ToolNotInstalled);
+            message = Some(format!(
+                "{} is needed to convert {} to {}. Install it from Settings or set its path.",
+                tool_display_name(tool),
+                input_ext.to_uppercase(),
+                output_ext.to_uppercase()
+            ));
+        }
+    }
+
+    if !licensed {
+        reasons.push(UnsupportedReason::LicenseRequired);
+        message.get_or_insert_with(|| "Activate a license to start converting".to_string());
+    }
+
+    UnsupportedExplanation {
+        supported: reasons.is_empty(),
+        tool: tool.map(str::to_string),
+        reasons,
+        message: message.unwrap_or_else(|| "This conversion is available".to_string()),
+    }
+}
+
+/// Number of worker threads for a batch: one per CPU core, but never more than there are files
+pub fn batch_worker_count(cpus: usize, jobs: usize) -> usize {
+    cpus.min(jobs).max(1)
+}
+
+/// Checks if a file found in a folder should be converted to `output_ext`: its extension is
+/// in `extensions` (compared without case or a leading dot), or, with no filter, there's a
+/// tool that converts it. Files already in the target format are left alone either way.
+pub fn matches_extension_filter(path: &Path, extensions: &[String], output_ext: &str) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if ext.is_empty() || ext == output_ext {
+        return false;
+    }
+    if extensions.is_empty() {
+        return determine_conversion_tool(&ext, output_ext).is_some();
+    }
+    extensions.iter().any(|filter| filter.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+}
+
+/// Where a file from `source_root` goes when the folder structure is mirrored under
+/// `output_root`: the same subfolders, relative to the source folder
+pub fn mirrored_output_dir(source_root: &Path, file: &Path, output_root: &Path) -> std::path::PathBuf {
+    let relative = file
+        .parent()
+        .and_then(|parent| parent.strip_prefix(source_root).ok())
+        .unwrap_or(Path::new(""));
+    output_root.join(relative)
+}
+
+/// Rough duration of a conversion nothing has been recorded for yet: a startup cost plus
+/// a cost per MB of input that depends on what's being converted
+pub fn default_conversion_ms(input_ext: &str, output_ext: &str, input_bytes: u64) -> u64 {
+    let (startup_ms, ms_per_mb) = if is_video_format(output_ext) || (is_video_format(input_ext) && is_image_format(output_ext)) {
+        (2000.0, 1500.0)
+    } else if is_audio_format(output_ext) {
+        (500.0, 300.0)
+    } else if is_document_format(input_ext) || is_document_format(output_ext) {
+        (3000.0, 500.0)
+    } else {
+        (300.0, 150.0)
+    };
+    (startup_ms + ms_per_mb * input_bytes as f64 / 1_000_000.0).round() as u64
+}
+
+/// Wall-clock time for conversions of the given durations on `workers` workers: each file
+/// goes to the worker that frees up first, longest files first
+pub fn batch_wall_time_ms(file_ms: &[u64], workers: usize) -> u64 {
+    let mut sorted = file_ms.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let mut loads = vec![0u64; workers.max(1)];
+    for duration in sorted {
+        if let Some(least) = loads.iter_mut().min() {
+            *least += duration;
+        }
+    }
+    loads.into_iter().max().unwrap_or(0)
+}
+
+/// Most conversions a batch runs at once, whatever the user asks for
+pub const MAX_BATCH_WORKERS: usize = 32;
+
+/// Worker count for a batch when the user may have picked one: `requested` replaces the
+/// CPU count, up to MAX_BATCH_WORKERS, and is still capped by the number of files
+pub fn requested_worker_count(requested: Option<usize>, cpus: usize, jobs: usize) -> usize {
+    match requested.filter(|workers| *workers > 0) {
+        Some(workers) => batch_worker_count(workers.min(MAX_BATCH_WORKERS), jobs),
+        None => batch_worker_count(cpus, jobs),
+    }
+}
+
+/// Checks if an extension is a valid video format
+pub fn is_video_format(ext: &str) -> bool {
+    VIDEO_INPUTS.contains(&ext.to_lowercase().as_str())
+}
+
+/// Checks if an extension is a valid audio format
+pub fn is_audio_format(ext: &str) -> bool {
+    AUDIO_INPUTS.contains(&ext.to_lowercase().as_str())
+}
+
+/// Checks if an extension is a valid image format
+pub fn is_image_format(ext: &str) -> bool {
+    IMAGE_INPUTS.contains(&ext.to_lowercase().as_str())
+}
+
+/// Checks if an extension is a valid document format
+pub fn is_document_format(ext: &str) -> bool {
+    DOC_INPUTS.contains(&ext.to_lowercase().as_str()) 
+        || OFFICE_INPUTS.contains(&ext.to_lowercase().as_str())
+}
+
+/// Checks if files with this extension can be converted to anything
+pub fn is_supported_input(ext: &str) -> bool {
+    let ext = ext.to_lowercase();
+    let ext = ext.as_str();
+    is_video_format(ext)
+        || is_audio_format(ext)
+        || is_image_format(ext)
+        || is_document_format(ext)
+        || VECTOR_INPUTS.contains(&ext)
+        || HTML_INPUTS.contains(&ext)
+        || CALIBRE_INPUTS.contains(&ext)
+        || crate::email::EMAIL_INPUTS.contains(&ext)
+        || crate::pim::PIM_CONVERSIONS.iter().any(|(input, _)| *input == ext)
+}
+
+/// MIME type of an image thumbnail, by extension (JPEG for anything unknown)
+pub fn thumbnail_mime_type(ext: &str) -> &'static str {
+    match ext {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "tiff" | "tif" => "image/tiff",
+        _ => "image/jpeg",
+    }
+}
+
+/// An image file as a data URL the frontend can show directly
+pub fn thumbnail_data_url(ext: &str, data: &[u8]) -> String {
+    let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, data);
+    format!("data:{};base64,{}", thumbnail_mime_type(ext), base64_data)
+}
+
+/// Whether `magick identify -format %[channels]` output ("srgba", "graya 2.0") has an alpha
+/// channel. The alpha is the trailing "a" on the colorspace, so "gray" and "lab" don't count
+pub fn identify_channels_have_alpha(channels: &str) -> bool {
+    channels.split_whitespace().next().is_some_and(|colorspace| colorspace.to_lowercase().ends_with('a'))
+}
+
+/// Whether FFmpeg's stream information (`ffmpeg -i`, stderr) shows a pixel format with alpha
+pub fn ffmpeg_stream_has_alpha(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    // rgba, yuva420p, gbrap, ya8, ya16, ...
+    ["rgba", "yuva", "gbra", "ya8", "ya16"].iter().any(|format| stderr.contains(format))
+}
+
+/// Length of the looping clip shown while hovering over a video, in seconds
+pub const HOVER_PREVIEW_SECONDS: f64 = 2.0;
+
+/// Hover previews are at most 240p
+pub const HOVER_PREVIEW_HEIGHT: u32 = 240;
+
+const HOVER_PREVIEW_FPS: f32 = 12.0;
+
+/// Where a video's hover preview starts: a tenth of the way in, past intros and fades from
+/// black, but early enough for the whole clip to fit
+pub fn hover_preview_start(duration_secs: Option<f64>) -> f64 {
+    match duration_secs {
+        Some(duration) if duration > HOVER_PREVIEW_SECONDS => (duration * 0.1).min(duration - HOVER_PREVIEW_SECONDS),
+        _ => 0.0,
+    }
+}
+
+/// Cache file name of a video's hover preview, which changes whenever the video does
+pub fn hover_preview_file_name(path: &str, size: u64, modified_secs: u64) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    path.hash(&mut hasher);
+    size.hash(&mut hasher);
+    modified_secs.hash(&mut hasher);
+    format!("hover-{:016x}.webp", hasher.finish())
+}
+
+/// FFmpeg arguments for a hover preview: two seconds from `start`, without sound, at a low
+/// frame rate and no more than 240p, as a small WebP that loops forever
+pub fn ffmpeg_hover_preview_args(input: &str, output: &str, start: f64) -> Vec<String> {
+    let filters = [
+        Filter::Fps(HOVER_PREVIEW_FPS),
+        Filter::scale("-2", format!("min({},ih)", HOVER_PREVIEW_HEIGHT)),
+    ];
+    vec![
+        "-ss".to_string(),
+        format!("{:.2}", start),
+        "-t".to_string(),
+        HOVER_PREVIEW_SECONDS.to_string(),
+        "-i".to_string(),
+        input.to_string(),
+        "-an".to_string(),
+        "-vf".to_string(),
+        filter_graph::chain(&filters),
+        "-c:v".to_string(),
+        "libwebp".to_string(),
+        "-quality".to_string(),
+        "60".to_string(),
+        "-loop".to_string(),
+        "0".to_string(),
+        "-y".to_string(),
+        output.to_string(),
+    ]
+}
+
+/// Characters of recognized text shown as a hint for a scanned document
+pub const OCR_PREVIEW_CHARS: usize = 200;
+
+/// Images Tesseract opens itself (through Leptonica); PDFs, HEIC and the rest are
+/// rasterized with ImageMagick first
+pub const TESSERACT_INPUTS: &[&str] = &[
+    "bmp", "gif", "jp2", "jpeg", "jpg", "pbm", "pgm", "png", "pnm", "ppm", "tif", "tiff", "webp",
+];
+
+pub fn tesseract_reads(ext: &str) -> bool {
+    TESSERACT_INPUTS.contains(&ext)
+}
+
+/// Tesseract arguments that print an image's text to stdout, with automatic page
+/// segmentation and the default (English) model
+pub fn tesseract_preview_args(input: &str) -> Vec<String> {
+    vec![input.to_string(), "stdout".to_string(), "--psm".to_string(), "3".to_string()]
+}
+
+/// ImageMagick arguments that render the first page of a document or image into a grayscale
+/// PNG at a resolution Tesseract reads well
+pub fn ocr_raster_args(input: &str, output: &str) -> Vec<String> {
+    vec![
+        "-density".to_string(),
+        "300".to_string(),
+        imagemagick_input_spec(input, Some(0)),
+        "-colorspace".to_string(),
+        "Gray".to_string(),
+        output.to_string(),
+    ]
+}
+
+/// The start of OCR output as a one-line hint: whitespace collapsed, cut at a word boundary
+/// after at most `max_chars` characters. None when nothing was recognized.
+pub fn text_snippet(text: &str, max_chars: usize) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+    if text.chars().count() <= max_chars {
+        return Some(text);
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    Some(format!("{}…", cut.trim_end()))
+}
+
+/// Normalizes a file extension by removing the leading dot and converting to lowercase
+pub fn normalize_extension(ext: &str) -> String {
+    ext.trim_start_matches('.').to_lowercase()
+}
+
+/// Built-in format registry
+pub const BUILTIN_FORMAT_REGISTRY: &str = include_str!("formats.json");
+
+/// File next to the config that extends or overrides the built-in registry
+pub const FORMAT_OVERRIDES_FILE: &str = "formats.json";
+
+/// Name, color and category of a format
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FormatEntry {
+    pub name: String,
+    /// Color category the frontend maps to its palette
+    pub color: String,
+    /// "video", "audio", "image", "vector", "document", "ebook", "email" or "data"
+    pub category: String,
+}
+
+/// Only offer an output when the input allows it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputCondition {
+    /// The input can be converted frame for frame (see animated_conversion_tool)
+    Animated,
+    /// The input can be re-encoded smaller in its own format
+    Compressible,
+}
+
+/// One output offered for the inputs of a group
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct OutputRule {
+    pub format: String,
+    /// Label instead of the format's name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The tool that does the conversion. Routing is determine_conversion_tool's, so a rule
+    /// naming another tool is left out; only "compress" (which isn't routed) needs one.
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Only for these inputs
+    #[serde(default)]
+    pub only_inputs: Vec<String>,
+    /// Not for these inputs (an input is never offered its own format)
+    #[serde(default)]
+    pub except_inputs: Vec<String>,
+    /// Feature flag that must be on
+    #[serde(default)]
+    pub requires_flag: Option<String>,
+    #[serde(default)]
+    pub when: Option<OutputCondition>,
+}
+
+/// Inputs that are offered the same outputs
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FormatGroup {
+    pub id: String,
+    pub inputs: Vec<String>,
+    /// Feature flag the whole group needs
+    #[serde(default)]
+    pub requires_flag: Option<String>,
+    #[serde(default)]
+    pub outputs: Vec<OutputRule>,
+    /// Also offer the vector outputs Inkscape can write without rasterizing
+    #[serde(default)]
+    pub vector_outputs: bool,
+}
+
+/// Every format the app knows and which outputs each input is offered, as data. An
+/// input's options come from the first group listing it whose flag is on; the tool of an
+/// option is the one conversions are routed to, unless the rule names one.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FormatRegistry {
+    #[serde(default)]
+    pub formats: BTreeMap<String, FormatEntry>,
+    #[serde(default)]
+    pub groups: Vec<FormatGroup>,
+}
+
+static FORMAT_REGISTRY: OnceLock<FormatRegistry> = OnceLock::new();
+
+impl FormatRegistry {
+    pub fn builtin() -> Self {
+        serde_json::from_str(BUILTIN_FORMAT_REGISTRY).expect("formats.json is valid")
+    }
+
+    /// This registry with `overrides` (the same JSON shape) applied: formats are replaced
+    /// or added by extension, groups by id
+    pub fn with_overrides(mut self, overrides: &str) -> Result<Self, String> {
+        let overrides: FormatRegistry = serde_json::from_str(overrides)
+            .map_err(|e| format!("Failed to parse format overrides: {}", e))?;
+        self.formats.extend(overrides.formats);
+        for group in overrides.groups {
+            match self.groups.iter_mut().find(|existing| existing.id == group.id) {
+                Some(existing) => *existing = group,
+                None => self.groups.push(group),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn format(&self, format: &str) -> Option<&FormatEntry> {
+        self.formats.get(format)
+    }
+
+    /// The outputs offered for `input_ext` (lowercase, without dot)
+    pub fn options(&self, input_ext: &str) -> Vec<ConversionOption> {
+        let flag_on = |flag: &Option<String>| flag.iter().all(|flag| feature_flags::is_enabled(flag));
+        let Some(group) = self.groups.iter().find(|group| group.inputs.iter().any(|input| input == input_ext) && flag_on(&group.requires_flag)) else {
+            return Vec::new();
+        };
+
+        let mut options = Vec::new();
+        for rule in &group.outputs {
+            let applies = rule.format != input_ext
+                && (rule.only_inputs.is_empty() || rule.only_inputs.iter().any(|input| input == input_ext))
+                && !rule.except_inputs.iter().any(|input| input == input_ext)
+                && flag_on(&rule.requires_flag)
+                && match rule.when {
+                    Some(OutputCondition::Animated) => animated_conversion_tool(input_ext, &rule.format).is_some(),
+                    Some(OutputCondition::Compressible) => supports_compression(input_ext),
+                    None => true,
+                };
+            if !applies {
+                continue;
+            }
+            // Only what the conversion will actually do is offered: a rule naming another
+            // tool than the pair is routed to (or a pair nothing converts) is left out.
+            // Compressing isn't routed, so its rules say which tool does it.
+            let routed = determine_conversion_tool(input_ext, &rule.format);
+            let tool = match (rule.tool.as_deref(), routed) {
+                (Some(tool), _) if rule.format == COMPRESS_TARGET => tool,
+                (Some(tool), Some(routed)) if tool != routed => continue,
+                (_, Some(routed)) => routed,
+                (_, None) => continue,
+            };
+            options.push(self.option(&rule.format, tool, rule.name.as_deref()));
+        }
+
+        // Vector-to-vector exports, unless the format is already offered (e.g. PDF as a raster output)
+        if group.vector_outputs && VECTOR_INPUTS.contains(&input_ext) {
+            for format in ["svg", "pdf", "eps", "emf"] {
+                if supports_vector_conversion(input_ext, format) && !options.iter().any(|option| option.format == format) {
+                    options.push(self.option(format, "inkscape", None));
+                }
+            }
+        }
+        options
+    }
+
+    /// The options of every input, with which of them the installed tools can do.
+    /// `tool_installed` is asked once per tool. Pairs the built-in converter handles stay
+    /// available without ImageMagick and FFmpeg, as in builtin_fallback.
+    pub fn matrix(&self, tool_installed: impl Fn(&str) -> bool) -> ConversionMatrix {
+        let mut inputs: Vec<&str> = Vec::new();
+        for input in self.groups.iter().flat_map(|group| group.inputs.iter()) {
+            if !inputs.contains(&input.as_str()) {
+                inputs.push(input);
+            }
+        }
+        let options: Vec<(&str, Vec<ConversionOption>)> = inputs
+            .into_iter()
+            .map(|input| (input, self.options(input)))
+            .filter(|(_, options)| !options.is_empty())
+            .collect();
+
+        let mut tools = BTreeMap::new();
+        for option in options.iter().flat_map(|(_, options)| options) {
+            if option.tool != "rename" && option.tool != BUILTIN_TOOL && !tools.contains_key(&option.tool) {
+                tools.insert(option.tool.clone(), tool_installed(&option.tool));
+            }
+        }
+        let installed = |tool: &str| tools.get(tool).copied().unwrap_or(false);
+
+        let mut categories: Vec<MatrixCategory> = Vec::new();
+        for (input, options) in options {
+            let outputs = options
+                .into_iter()
+                .map(|mut option| {
+                    let builtin = matches!(option.tool.as_str(), "imagemagick" | "ffmpeg")
+                        && supports_builtin_conversion(input, &option.format)
+                        && !installed("imagemagick")
+                        && !installed("ffmpeg");
+                    if builtin {
+                        option.tool = BUILTIN_TOOL.to_string();
+                    }
+                    let available = option.tool == "rename" || option.tool == BUILTIN_TOOL || installed(&option.tool);
+                    MatrixOutput { option, available }
+                })
+                .collect();
+            let entry = self.format(input);
+            let category = entry.map_or("other", |entry| entry.category.as_str());
+            let matrix_input = MatrixInput {
+                format: input.to_string(),
+                display_name: entry.map_or(UNKNOWN_FORMAT_NAME, |entry| entry.name.as_str()).to_string(),
+                color: entry.map_or(UNKNOWN_FORMAT_COLOR, |entry| entry.color.as_str()).to_string(),
+                outputs,
+            };
+            match categories.iter_mut().find(|existing| existing.category == category) {
+                Some(existing) => existing.inputs.push(matrix_input),
+                None => categories.push(MatrixCategory { category: category.to_string(), inputs: vec![matrix_input] }),
+            }
+        }
+        ConversionMatrix { categories, tools }
+    }
+
+    /// The plan for converting `inputs` (path and format) together. Formats are listed in
+    /// the order the files' options first offer them. A file already in a target format
+    /// counts toward it (and is skipped), so a drop of JPGs and PNGs can still be converted
+    /// to JPG together.
+    pub fn plan_batch(&self, inputs: &[(String, String)]) -> BatchPlan {
+        let mut options_by_format: BTreeMap<&str, Vec<ConversionOption>> = BTreeMap::new();
+        for (_, format) in inputs {
+            options_by_format.entry(format.as_str()).or_insert_with(|| self.options(format));
+        }
+
+        let mut plan = BatchPlan::default();
+        let mut targets: Vec<PlanTarget> = Vec::new();
+        for (input_path, format) in inputs {
+            let options = &options_by_format[format.as_str()];
+            if options.is_empty() {
+                plan.unsupported.push(input_path.clone());
+                continue;
+            }
+            for option in options {
+                match targets.iter_mut().find(|target| target.option.format == option.format) {
+                    Some(target) => {
+                        target.files += 1;
+                        if !target.tools.contains(&option.tool) {
+                            target.tools.push(option.tool.clone());
+                        }
+                    }
+                    None => targets.push(PlanTarget {
+                        option: option.clone(),
+                        files: 1,
+                        already: 0,
+                        tools: vec![option.tool.clone()],
+                    }),
+                }
+            }
+            plan.files.push(PlannedFile {
+                input_path: input_path.clone(),
+                format: format.clone(),
+                tools: options.iter().map(|option| (option.format.clone(), option.tool.clone())).collect(),
+                already_in: Vec::new(),
+            });
+        }
+        for target in targets.iter_mut() {
+            let format = &target.option.format;
+            for file in plan.files.iter_mut() {
+                if !file.tools.contains_key(format) && crate::signature::same_format(&file.format, format) {
+                    target.files += 1;
+                    target.already += 1;
+                    file.already_in.push(format.clone());
+                }
+            }
+        }
+
+        let supported = plan.files.len();
+        let (common, mut partial): (Vec<_>, Vec<_>) = targets.into_iter().partition(|target| target.files == supported);
+        // Stable, so formats offered to as many files keep their order
+        partial.sort_by(|a, b| b.files.cmp(&a.files));
+        plan.common_formats = common;
+        plan.partial_formats = partial;
+        plan
+    }
+
+    fn option(&self, format: &str, tool: &str, name: Option<&str>) -> ConversionOption {
+        let entry = self.format(format);
+        ConversionOption {
+            format: format.to_string(),
+            tool: tool.to_string(),
+            display_name: name.or(entry.map(|entry| entry.name.as_str())).unwrap_or(UNKNOWN_FORMAT_NAME).to_string(),
+            color: entry.map_or(UNKNOWN_FORMAT_COLOR, |entry| entry.color.as_str()).to_string(),
+            category: entry.map(|entry| entry.category.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// An output of the conversion matrix, and whether it can be converted to right now
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatrixOutput {
+    #[serde(flatten)]
+    pub option: ConversionOption,
+    /// The tool is installed, or the conversion doesn't need one
+    pub available: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatrixInput {
+    pub format: String,
+    pub display_name: String,
+    pub color: String,
+    pub outputs: Vec<MatrixOutput>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatrixCategory {
+    pub category: String,
+    pub inputs: Vec<MatrixInput>,
+}
+
+/// Every input the app converts and what each converts to, by the input's category
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ConversionMatrix {
+    pub categories: Vec<MatrixCategory>,
+    /// Each external tool the matrix needs, and whether it's installed
+    pub tools: BTreeMap<String, bool>,
+}
+
+/// A target format for a mixed batch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlanTarget {
+    /// With the tool of the first file that can be converted to it
+    #[serde(flatten)]
+    pub option: ConversionOption,
+    /// How many files can be converted to it, counting those already in it
+    pub files: usize,
+    /// How many of the files are already in it; they're skipped
+    pub already: usize,
+    /// Every tool the batch would use for it
+    pub tools: Vec<String>,
+}
+
+/// One file of a mixed batch, with the tool for each format it can be converted to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlannedFile {
+    pub input_path: String,
+    /// What it's converted as (see signature::check_format)
+    pub format: String,
+    pub tools: BTreeMap<String, String>,
+    /// Target formats it's already in, so converting to them skips it
+    pub already_in: Vec<String>,
+}
+
+/// What a batch of different kinds of files can be converted to together
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BatchPlan {
+    /// Formats every supported file can be converted to: "convert everything to X"
+    pub common_formats: Vec<PlanTarget>,
+    /// Formats only some of them can, most files first
+    pub partial_formats: Vec<PlanTarget>,
+    pub files: Vec<PlannedFile>,
+    /// Files nothing is offered for; they don't narrow the common formats
+    pub unsupported: Vec<String>,
+}
+
+/// Makes `registry` the one in use. Only works before the registry is first used, so call
+/// it at startup.
+pub fn install_format_registry(registry: FormatRegistry) -> Result<(), String> {
+    FORMAT_REGISTRY.set(registry).map_err(|_| "The format registry is already in use".to_string())
+}
+
+/// The registry in use: the installed one, or the built-in one
+pub fn format_registry() -> &'static FormatRegistry {
+    FORMAT_REGISTRY.get_or_init(FormatRegistry::builtin)
+}
+
+const UNKNOWN_FORMAT_NAME: &str = "Unknown Format";
+const UNKNOWN_FORMAT_COLOR: &str = "gray";
+
+/// Returns the display name for a given format
+pub fn get_format_display_name(format: &str) -> &'static str {
+    format_registry().format(format).map_or(UNKNOWN_FORMAT_NAME, |entry| entry.name.as_str())
+}
+
+/// Name and color of a format, as shown in the UI
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FormatDetails {
+    pub format: String,
+    pub display_name: String,
+    pub color: String,
+}
+
+/// Display name and color for an extension, with or without its dot
+pub fn format_details(format: &str) -> FormatDetails {
+    let format = normalize_extension(format);
+    FormatDetails {
+        display_name: get_format_display_name(&format).to_string(),
+        color: get_format_color(&format).to_string(),
+        format,
+    }
+}
+
+/// Returns the color category for a given format (for UI styling). The frontend maps
+/// these names to its palette, so a format only needs a color here.
+pub fn get_format_color(format: &str) -> &'static str {
+    format_registry().format(format).map_or(UNKNOWN_FORMAT_COLOR, |entry| entry.color.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==========================================
+    // VIDEO CONVERSION TESTS
+    // ==========================================
+
+    mod video_conversions {
+        use super::*;
+
+        #[test]
+        fn test_all_video_to_video_conversions() {
+            let video_formats = ["mp4", "mov", "avi", "mkv", "webm"];
+            
+            for input in &video_formats {
+                for output in &video_formats {
+                    let result = determine_conversion_tool(input, output);
+                    assert_eq!(
+                        result, Some("ffmpeg"),
+                        "Video conversion {} -> {} should use ffmpeg",
+                        input, output
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_all_video_inputs_recognized() {
+            for format in VIDEO_INPUTS {
+                assert!(
+                    is_video_format(format),
+                    "Video format {} should be recognized",
+                    format
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_mp4() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "mp4"), Some("ffmpeg"),
+                    "{} -> mp4 should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_mov() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "mov"), Some("ffmpeg"),
+                    "{} -> mov should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_avi() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "avi"), Some("ffmpeg"),
+                    "{} -> avi should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_mkv() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "mkv"), Some("ffmpeg"),
+                    "{} -> mkv should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_webm() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "webm"), Some("ffmpeg"),
+                    "{} -> webm should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_gif() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "gif"), Some("ffmpeg"),
+                    "{} -> gif should use ffmpeg", input
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // VIDEO TO AUDIO EXTRACTION TESTS
+    // ==========================================
+
+    mod video_to_audio_conversions {
+        use super::*;
+
+        #[test]
+        fn test_video_to_mp3() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "mp3"), Some("ffmpeg"),
+                    "{} -> mp3 should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_wav() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "wav"), Some("ffmpeg"),
+                    "{} -> wav should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_flac() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "flac"), Some("ffmpeg"),
+                    "{} -> flac should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_ogg() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "ogg"), Some("ffmpeg"),
+                    "{} -> ogg should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_m4a() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "m4a"), Some("ffmpeg"),
+                    "{} -> m4a should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_video_to_aac() {
+            for input in VIDEO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "aac"), Some("ffmpeg"),
+                    "{} -> aac should use ffmpeg", input
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // AUDIO CONVERSION TESTS
+    // ==========================================
+
+    mod audio_conversions {
+        use super::*;
+
+        #[test]
+        fn test_all_audio_inputs_recognized() {
+            for format in AUDIO_INPUTS {
+                assert!(
+                    is_audio_format(format),
+                    "Audio format {} should be recognized",
+                    format
+                );
+            }
+        }
+
+        #[test]
+        fn test_all_audio_to_audio_conversions() {
+            let audio_outputs = ["mp3", "wav", "flac", "ogg", "m4a", "aac"];
+            
+            for input in AUDIO_INPUTS {
+                for output in &audio_outputs {
+                    let result = determine_conversion_tool(input, output);
+                    assert_eq!(
+                        result, Some("ffmpeg"),
+                        "Audio conversion {} -> {} should use ffmpeg",
+                        input, output
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_audio_to_mp3() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "mp3"), Some("ffmpeg"),
+                    "{} -> mp3 should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_audio_to_wav() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "wav"), Some("ffmpeg"),
+                    "{} -> wav should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_audio_to_flac() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "flac"), Some("ffmpeg"),
+                    "{} -> flac should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_audio_to_ogg() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "ogg"), Some("ffmpeg"),
+                    "{} -> ogg should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_audio_to_m4a() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "m4a"), Some("ffmpeg"),
+                    "{} -> m4a should use ffmpeg", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_audio_to_aac() {
+            for input in AUDIO_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "aac"), Some("ffmpeg"),
+                    "{} -> aac should use ffmpeg", input
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // IMAGE CONVERSION TESTS
+    // ==========================================
+
+    mod image_conversions {
+        use super::*;
+
+        // Common image formats that should always work
+        const COMMON_IMAGE_INPUTS: &[&str] = &[
+            "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"
+        ];
+
+        const COMMON_IMAGE_OUTPUTS: &[&str] = &[
+            "jpg", "png", "gif", "bmp", "tiff", "webp"
+        ];
+
+        #[test]
+        fn test_common_image_inputs_recognized() {
+            for format in COMMON_IMAGE_INPUTS {
+                assert!(
+                    is_image_format(format),
+                    "Image format {} should be recognized",
+                    format
+                );
+            }
+        }
+
+        #[test]
+        fn test_all_image_inputs_recognized() {
+            for format in IMAGE_INPUTS {
+                assert!(
+                    is_image_format(format),
+                    "Image format {} should be recognized",
+                    format
+                );
+            }
+        }
+
+        #[test]
+        fn test_common_image_conversions() {
+            for input in COMMON_IMAGE_INPUTS {
+                for output in COMMON_IMAGE_OUTPUTS {
+                    let result = determine_conversion_tool(input, output);
+                    assert!(
+                        result.is_some(),
+                        "Image conversion {} -> {} should have a tool",
+                        input, output
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_jpg() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "jpg" && *input != "jpeg" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "jpg"), Some("imagemagick"),
+                        "{} -> jpg should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_png() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "png" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "png"), Some("imagemagick"),
+                        "{} -> png should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_webp() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "webp" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "webp"), Some("imagemagick"),
+                        "{} -> webp should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_gif() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "gif" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "gif"), Some("imagemagick"),
+                        "{} -> gif should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_bmp() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "bmp" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "bmp"), Some("imagemagick"),
+                        "{} -> bmp should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_tiff() {
+            for input in COMMON_IMAGE_INPUTS {
+                if *input != "tiff" {
+                    assert_eq!(
+                        determine_conversion_tool(input, "tiff"), Some("imagemagick"),
+                        "{} -> tiff should use imagemagick", input
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_image_to_ico() {
+            for input in COMMON_IMAGE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "ico"), Some("imagemagick"),
+                    "{} -> ico should use imagemagick", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_image_to_avif() {
+            for input in COMMON_IMAGE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "avif"), Some("imagemagick"),
+                    "{} -> avif should use imagemagick", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_jpg_jpeg_rename() {
+            assert_eq!(determine_conversion_tool("jpg", "jpeg"), Some("rename"));
+            assert_eq!(determine_conversion_tool("jpeg", "jpg"), Some("rename"));
+        }
+    }
+
+    // ==========================================
+    // HEIC/HEIF CONVERSION TESTS
+    // ==========================================
+
+    mod heic_conversions {
+        use super::*;
+
+        const HEIC_INPUTS: &[&str] = &["jpg", "jpeg", "png", "bmp", "tiff", "webp", "gif"];
+
+        #[test]
+        fn test_image_to_heic() {
+            for input in HEIC_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "heic"), Some("imagemagick"),
+                    "{} -> heic should use imagemagick", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_image_to_heif() {
+            for input in HEIC_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "heif"), Some("imagemagick"),
+                    "{} -> heif should use imagemagick", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_heic_to_common_formats() {
+            let outputs = ["jpg", "png", "bmp", "tiff", "webp"];
+            for output in &outputs {
+                assert_eq!(
+                    determine_conversion_tool("heic", output), Some("imagemagick"),
+                    "heic -> {} should use imagemagick", output
+                );
+            }
+        }
+
+        #[test]
+        fn test_heif_to_common_formats() {
+            let outputs = ["jpg", "png", "bmp", "tiff", "webp"];
+            for output in &outputs {
+                assert_eq!(
+                    determine_conversion_tool("heif", output), Some("imagemagick"),
+                    "heif -> {} should use imagemagick", output
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // PROFESSIONAL IMAGE FORMAT TESTS
+    // ==========================================
+
+    mod professional_image_conversions {
+        use super::*;
+
+        #[test]
+        fn test_tga_conversions() {
+            assert_eq!(determine_conversion_tool("tga", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("tga", "jpg"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "tga"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_exr_conversions() {
+            assert_eq!(determine_conversion_tool("exr", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("exr", "jpg"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "exr"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_hdr_conversions() {
+            assert_eq!(determine_conversion_tool("hdr", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("hdr", "jpg"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "hdr"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_psd_conversions() {
+            assert_eq!(determine_conversion_tool("psd", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("psd", "jpg"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "psd"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_jpeg2000_conversions() {
+            assert_eq!(determine_conversion_tool("j2k", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("jp2", "png"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "j2k"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("png", "jp2"), Some("imagemagick"));
+        }
+    }
+
+    // ==========================================
+    // X WINDOW SYSTEM FORMAT TESTS
+    // ==========================================
+
+    mod x_window_conversions {
+        use super::*;
+
+        #[test]
+        fn test_xbm_conversions() {
+            assert_eq!(determine_conversion_tool("png", "xbm"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("jpg", "xbm"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("xbm", "png"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_xpm_conversions() {
+            assert_eq!(determine_conversion_tool("png", "xpm"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("jpg", "xpm"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("xpm", "png"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_xwd_conversions() {
+            assert_eq!(determine_conversion_tool("png", "xwd"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("jpg", "xwd"), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool("xwd", "png"), Some("imagemagick"));
+        }
+    }
+
+    // ==========================================
+    // RAW IMAGE FORMAT TESTS
+    // ==========================================
+
+    mod raw_image_conversions {
+        use super::*;
+
+        const RAW_FORMATS: &[&str] = &[
+            "arw", "cr2", "cr3", "dng", "nef", "orf", "raf", "raw", "rw2"
+        ];
+
+        #[test]
+        fn test_raw_to_jpg() {
+            for raw in RAW_FORMATS {
+                assert_eq!(
+                    determine_conversion_tool(raw, "jpg"), Some("imagemagick"),
+                    "{} -> jpg should use imagemagick", raw
+                );
+            }
+        }
+
+        #[test]
+        fn test_raw_to_png() {
+            for raw in RAW_FORMATS {
+                assert_eq!(
+                    determine_conversion_tool(raw, "png"), Some("imagemagick"),
+                    "{} -> png should use imagemagick", raw
+                );
+            }
+        }
+
+        #[test]
+        fn test_raw_to_tiff() {
+            for raw in RAW_FORMATS {
+                assert_eq!(
+                    determine_conversion_tool(raw, "tiff"), Some("imagemagick"),
+                    "{} -> tiff should use imagemagick", raw
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // OFFICE DOCUMENT CONVERSION TESTS
+    // ==========================================
+
+    mod office_conversions {
+        use super::*;
+
+        #[test]
+        fn test_word_to_pdf() {
+            assert_eq!(determine_conversion_tool("doc", "pdf"), Some("libreoffice"));
+            assert_eq!(determine_conversion_tool("docx", "pdf"), Some("libreoffice"));
+        }
+
+        #[test]
+        fn test_excel_to_pdf() {
+            assert_eq!(determine_conversion_tool("xls", "pdf"), Some("libreoffice"));
+            assert_eq!(determine_conversion_tool("xlsx", "pdf"), Some("libreoffice"));
+        }
+
+        #[test]
+        fn test_powerpoint_to_pdf() {
+            assert_eq!(determine_conversion_tool("ppt", "pdf"), Some("libreoffice"));
+            assert_eq!(determine_conversion_tool("pptx", "pdf"), Some("libreoffice"));
+        }
+
+        #[test]
+        fn test_odt_to_pdf() {
+            assert_eq!(determine_conversion_tool("odt", "pdf"), Some("libreoffice"));
+        }
+
+        #[test]
+        fn test_office_to_html() {
+            for input in OFFICE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "html"), Some("libreoffice"),
+                    "{} -> html should use libreoffice", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_office_to_txt() {
+            for input in OFFICE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "txt"), Some("libreoffice"),
+                    "{} -> txt should use libreoffice", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_office_to_docx() {
+            for input in OFFICE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "docx"), Some("libreoffice"),
+                    "{} -> docx should use libreoffice", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_office_to_odt() {
+            for input in OFFICE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "odt"), Some("libreoffice"),
+                    "{} -> odt should use libreoffice", input
+                );
+            }
+        }
+
+        #[test]
+        fn test_office_to_rtf() {
+            for input in OFFICE_INPUTS {
+                assert_eq!(
+                    determine_conversion_tool(input, "rtf"), Some("libreoffice"),
+                    "{} -> rtf should use libreoffice", input
+                );
+            }
+        }
+    }
+
+    // ==========================================
+    // VECTOR CONVERSION TESTS
+    // ==========================================
+
+    mod vector_conversions {
+        use super::*;
+
+        fn vector_settings() -> ConversionSettings {
+            ConversionSettings { vector_output: true, ..Default::default() }
+        }
+
+        #[test]
+        fn test_vector_output_routes_to_inkscape() {
+            let pairs = [("svg", "pdf"), ("svg", "eps"), ("pdf", "svg"), ("eps", "emf"), ("ai", "svg"), ("emf", "pdf")];
+            for (input, output) in &pairs {
+                assert_eq!(
+                    determine_conversion_tool_with_settings(input, output, &vector_settings()), Some("inkscape"),
+                    "{} -> {} with vector output should use inkscape", input, output
+                );
+            }
+        }
+
+        #[test]
+        fn test_default_settings_keep_rasterizing() {
+            let settings = ConversionSettings::default();
+            assert_eq!(determine_conversion_tool_with_settings("svg", "pdf", &settings), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool_with_settings("svg", "png", &settings), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool_with_settings("pdf", "svg", &settings), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_vector_output_ignored_for_raster_targets() {
+            assert_eq!(determine_conversion_tool_with_settings("svg", "png", &vector_settings()), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool_with_settings("png", "svg", &vector_settings()), Some("imagemagick"));
+            assert_eq!(determine_conversion_tool_with_settings("mp4", "mp3", &vector_settings()), Some("ffmpeg"));
+        }
+
+        #[test]
+        fn test_vector_only_pairs_fall_back_to_inkscape() {
+            // No raster tool writes EPS/PS, so these always go through Inkscape
+            assert_eq!(determine_conversion_tool("svg", "eps"), Some("inkscape"));
+            assert_eq!(determine_conversion_tool("pdf", "ps"), Some("inkscape"));
+        }
+
+        #[test]
+        fn test_same_vector_format_not_supported() {
+            assert!(!supports_vector_conversion("svg", "svg"));
+            assert!(!supports_vector_conversion("pdf", "pdf"));
+        }
+    }
+
+    // ==========================================
+    // ANIMATED CONVERSION TESTS
+    // ==========================================
+
+    mod animated_conversions {
+        use super::*;
+
+        #[test]
+        fn test_animated_image_pairs_use_imagemagick() {
+            let pairs = [("gif", "webp"), ("webp", "gif"), ("gif", "apng"), ("apng", "webp"), ("webp", "mp4")];
+            for (input, output) in &pairs {
+                assert_eq!(
+                    determine_conversion_tool(input, output), Some("imagemagick"),
+                    "{} -> {} should use imagemagick", input, output
+                );
+            }
+        }
+
+        #[test]
+        fn test_mp4_pairs_use_ffmpeg() {
+            let pairs = [("gif", "mp4"), ("apng", "mp4"), ("mp4", "gif"), ("mp4", "webp"), ("mp4", "apng")];
+            for (input, output) in &pairs {
+                assert_eq!(
+                    determine_conversion_tool(input, output), Some("ffmpeg"),
+                    "{} -> {} should use ffmpeg", input, output
+                );
+            }
+        }
+
+        #[test]
+        fn test_static_formats_not_animated() {
+            assert!(!is_animated_conversion("png", "gif"));
+            assert!(!is_animated_conversion("gif", "png"));
+            assert!(!is_animated_conversion("gif", "gif"));
+            assert_eq!(animated_conversion_tool("mov", "webp"), None);
+        }
+
+        #[test]
+        fn test_imagemagick_args_default_only_coalesce() {
+            let ops = imagemagick_animation_ops(&ConversionSettings::default());
+            assert_eq!(ops, vec![MagickOp::Coalesce]);
+        }
+
+        #[test]
+        fn test_imagemagick_args_with_loop_and_frame_rate() {
+            let settings = ConversionSettings { loop_count: Some(3), frame_rate: Some(25.0), ..Default::default() };
+            let args = imagemagick_animation_command("in.gif", "out.webp", "webp", &settings, None).args_lossy();
+            assert_eq!(args, vec!["in.gif", "-coalesce", "-set", "delay", "4", "-loop", "3", "-quality", "90", "out.webp"]);
+        }
+
+        #[test]
+        fn test_ffmpeg_gif_loop_values() {
+            let once = ConversionSettings { loop_count: Some(1), ..Default::default() };
+            let args = ffmpeg_animation_args("gif", &once);
+            assert!(args.windows(2).any(|w| w == ["-loop", "-1"]));
+
+            let args = ffmpeg_animation_args("gif", &ConversionSettings::default());
+            assert!(args.windows(2).any(|w| w == ["-loop", "0"]));
+        }
+
+        #[test]
+        fn test_ffmpeg_frame_rate_filter() {
+            let settings = ConversionSettings { frame_rate: Some(12.0), ..Default::default() };
+            let args = ffmpeg_animation_args("mp4", &settings);
+            assert!(args.contains(&"fps=12,scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string()));
+
+            let args = ffmpeg_animation_args("apng", &settings);
+            assert!(args.windows(2).any(|w| w == ["-plays", "0"]));
+            assert!(args.contains(&"fps=12".to_string()));
+        }
+    }
+
+    // ==========================================
+    // HTML SNAPSHOT TESTS
+    // ==========================================
+
+    mod html_snapshots {
+        use super::*;
+
+        #[test]
+        fn test_html_routes_to_chromium() {
+            for input in HTML_INPUTS {
+                for output in SNAPSHOT_OUTPUTS {
+                    assert_eq!(
+                        determine_conversion_tool(input, output), Some("chromium"),
+                        "{} -> {} should use chromium", input, output
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_email_routes_to_chromium() {
+            assert_eq!(determine_conversion_tool("eml", "pdf"), Some("chromium"));
+            assert_eq!(determine_conversion_tool("msg", "pdf"), Some("chromium"));
+            assert_eq!(determine_conversion_tool("eml", "png"), None);
+        }
+
+        #[test]
+        fn test_calendars_and_contacts_use_builtin() {
+            for (input, output) in [("ics", "csv"), ("csv", "ics"), ("vcf", "csv"), ("csv", "vcf")] {
+                assert_eq!(determine_conversion_tool(input, output), Some(BUILTIN_TOOL), "{} -> {}", input, output);
+            }
+            assert_eq!(determine_conversion_tool("ics", "vcf"), None);
+        }
+
+        #[test]
+        fn test_file_path_to_url() {
+            assert_eq!(file_path_to_url("/home/me/email.html"), "file:///home/me/email.html");
+            assert_eq!(file_path_to_url(r"C:\Users\me\My Email.html"), "file:///C:/Users/me/My%20Email.html");
+            assert_eq!(file_path_to_url(r"C:\Users\me\100% #1?.html"), "file:///C:/Users/me/100%25%20%231%3F.html");
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_file_path_to_url_escapes_reserved_characters() {
+            assert_eq!(file_path_to_url("/tmp/100% #1?.html"), "file:///tmp/100%25%20%231%3F.html");
+            assert_eq!(file_path_to_url("/tmp/café.html"), "file:///tmp/caf%C3%A9.html");
+        }
+
+        #[test]
+        fn test_snapshot_file_stem() {
+            assert_eq!(snapshot_file_stem("https://example.com/newsletter?id=4"), "example.com");
+            assert_eq!(snapshot_file_stem("http://localhost:8080/email"), "localhost-8080");
+            assert_eq!(snapshot_file_stem("https://"), "snapshot");
+        }
+
+        #[test]
+        fn test_png_snapshot_uses_viewport_size() {
+            let settings = ConversionSettings { snapshot_width: Some(600), snapshot_height: Some(900), ..Default::default() };
+            let args = chromium_snapshot_args("https://example.com", "out.png", "png", &settings);
+            assert!(args.contains(&"--window-size=600,900".to_string()));
+            assert!(args.contains(&"--screenshot=out.png".to_string()));
+            assert_eq!(args.last().map(String::as_str), Some("https://example.com"));
+        }
+
+        #[test]
+        fn test_full_page_uses_tall_viewport() {
+            let settings = ConversionSettings { full_page: true, snapshot_height: Some(900), ..Default::default() };
+            let args = chromium_snapshot_args("https://example.com", "out.png", "png", &settings);
+            assert!(args.contains(&format!("--window-size={},{}", DEFAULT_SNAPSHOT_WIDTH, FULL_PAGE_MAX_HEIGHT)));
+        }
+
+        #[test]
+        fn test_pdf_snapshot_prints_page() {
+            let args = chromium_snapshot_args("https://example.com", "out.pdf", "pdf", &ConversionSettings::default());
+            assert!(args.contains(&"--print-to-pdf=out.pdf".to_string()));
+            assert!(!args.iter().any(|a| a.starts_with("--screenshot")));
+        }
+    }
+
+    // ==========================================
+    // ANIMATION OPTIMIZATION TESTS
+    // ==========================================
+
+    mod animation_optimization {
+        use super::*;
+
+        #[test]
+        fn test_plan_frame_drop_keeps_duration() {
+            let plan = plan_frame_drop(&[10, 10, 10, 10, 10], 2).unwrap();
+            assert_eq!(plan.delete, vec![1, 3]);
+            // 50 ticks over 3 remaining frames
+            assert_eq!(plan.delay, 17);
+        }
+
+        #[test]
+        fn test_plan_frame_drop_noop() {
+            assert_eq!(plan_frame_drop(&[10, 10, 10], 1), None);
+            assert_eq!(plan_frame_drop(&[10], 3), None);
+        }
+
+        #[test]
+        fn test_gif_optimize_args() {
+            let options = AnimationOptimizeOptions { lossy: 30, colors: Some(64), keep_every: None };
+            let args = animation_optimize_args("gif", &options, None);
+            assert_eq!(args, vec!["-coalesce", "-colors", "64", "-fuzz", "3%", "-layers", "Optimize"]);
+        }
+
+        #[test]
+        fn test_webp_optimize_args_with_plan() {
+            let plan = FrameDropPlan { delete: vec![1, 3], delay: 8 };
+            let options = AnimationOptimizeOptions { lossy: 40, ..Default::default() };
+            let args = animation_optimize_args("webp", &options, Some(&plan));
+            assert_eq!(args, vec!["-coalesce", "-delete", "1,3", "-set", "delay", "8", "-quality", "60"]);
+        }
+
+        #[test]
+        fn test_optimization_report_savings() {
+            let report = OptimizationReport::new("out.gif".to_string(), 2000, 500);
+            assert_eq!(report.saved_bytes, 1500);
+            assert_eq!(report.saved_percent, 75.0);
+
+            let grew = OptimizationReport::new("out.gif".to_string(), 1000, 1100);
+            assert_eq!(grew.saved_bytes, -100);
+            assert_eq!(grew.saved_percent, -10.0);
+        }
+    }
+
+    // ==========================================
+    // IMAGE COMPRESSION TESTS
+    // ==========================================
+
+    mod image_compression {
+        use super::*;
+
+        #[test]
+        fn test_compressible_formats() {
+            assert!(supports_compression("jpg"));
+            assert!(supports_compression("png"));
+            assert!(!supports_compression("gif"));
+            assert!(!supports_compression("bmp"));
+        }
+
+        #[test]
+        fn test_compress_args() {
+            assert_eq!(compress_args("jpg", 70, false), vec!["-strip", "-quality", "70"]);
+            assert_eq!(compress_args("png", 70, false), vec!["-strip", "-define", "png:compression-level=9"]);
+            assert_eq!(compress_args("jpg", 70, true), vec!["-quality", "70"]);
+        }
+
+        #[test]
+        fn test_find_quality_for_size_picks_highest_fit() {
+            // Pretend every quality step adds 10 KB
+            let (quality, fits) = find_quality_for_size(500_000, |q| Ok(q as u64 * 10_000)).unwrap();
+            assert_eq!(quality, 50);
+            assert!(fits);
+        }
+
+        #[test]
+        fn test_find_quality_for_size_when_nothing_fits() {
+            let (quality, fits) = find_quality_for_size(1_000, |_| Ok(50_000)).unwrap();
+            assert_eq!(quality, MIN_COMPRESS_QUALITY);
+            assert!(!fits);
+        }
+
+        #[test]
+        fn test_find_quality_for_size_everything_fits() {
+            let (quality, fits) = find_quality_for_size(u64::MAX, |_| Ok(1)).unwrap();
+            assert_eq!(quality, MAX_COMPRESS_QUALITY);
+            assert!(fits);
+        }
+    }
+
+    // ==========================================
+    // TARGET SIZE TESTS
+    // ==========================================
+
+    mod target_size {
+        use super::*;
+
+        #[test]
+        fn test_parse_ffmpeg_duration() {
+            let stderr = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':\n  Duration: 00:01:23.50, start: 0.000000, bitrate: 1205 kb/s";
+            assert_eq!(parse_ffmpeg_duration(stderr), Some(83.5));
+            assert_eq!(parse_ffmpeg_duration("Duration: N/A, bitrate: N/A"), None);
+            assert_eq!(parse_ffmpeg_duration("no banner"), None);
+        }
+
+        #[test]
+        fn test_parse_ffmpeg_warnings() {
+            let stderr = "[swscaler @ 0x7f8a1c008000] deprecated pixel format used, make sure you did set range correctly\n\
+                          [mp4 @ 0x55d5c4a0] Non-monotonic DTS in output stream 0:1; previous: 1024, current: 1000; changing to 1025.\n\
+                          frame=  120 fps= 60 q=28.0 size=     256kB time=00:00:04.00 bitrate= 524.3kbits/s speed=2.01x    \r\
+                          [mp4 @ 0x55d5c4a0] Non-monotonic DTS in output stream 0:1; previous: 1024, current: 1000; changing to 1025.\n\
+                              Last message repeated 3 times\n\
+                          Guessed Channel Layout for Input Stream #0.0 : mono\n\
+                          [out#0/mp4 @ 0x55d5c4b0] [vost#0:0/libx264 @ 0x55d5c4c0] \n\
+                          frame=  240 fps= 60 q=-1.0 Lsize=     512kB time=00:00:08.00 bitrate= 524.3kbits/s speed=2.05x\n";
+            let warnings = parse_ffmpeg_warnings(stderr);
+            assert_eq!(warnings.len(), 3);
+            assert_eq!(warnings[0].component.as_deref(), Some("swscaler"));
+            assert!(warnings[0].message.starts_with("deprecated pixel format used"));
+            assert_eq!(warnings[1].component.as_deref(), Some("mp4"));
+            assert_eq!(warnings[1].count, 5);
+            assert_eq!(warnings[2], ToolWarning {
+                component: None,
+                message: "Guessed Channel Layout for Input Stream #0.0 : mono".to_string(),
+                count: 1,
+            });
+
+            let flood: String = (0..100).map(|i| format!("[h264 @ 0x1] error while decoding MB {} 0\n", i)).collect();
+            assert_eq!(parse_ffmpeg_warnings(&flood).len(), MAX_TOOL_WARNINGS);
+            assert!(parse_ffmpeg_warnings("").is_empty());
+        }
+
+        #[test]
+        fn test_output_lines() {
+            let mut lines = OutputLines::default();
+            assert_eq!(lines.push(b"Input #0, mov"), Vec::<String>::new());
+            assert_eq!(lines.push(b", from 'clip.mov':\nframe=  120 speed=2x\rframe=  240 speed=2x\r\n"), vec![
+                "Input #0, mov, from 'clip.mov':".to_string(),
+                "frame=  120 speed=2x".to_string(),
+                "frame=  240 speed=2x".to_string(),
+            ]);
+            assert_eq!(lines.push(b"\xff done"), Vec::<String>::new());
+            assert_eq!(lines.finish(), Some("\u{fffd} done".to_string()));
+            assert_eq!(lines.finish(), None);
+        }
+
+        #[test]
+        fn test_single_pass_outputs() {
+            let outputs = |formats: &[&str]| formats.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+            let settings = ConversionSettings::default();
+            assert!(single_pass_outputs("mov", &outputs(&["mp4", "webm", "jpg"]), &settings));
+            assert!(single_pass_outputs("wav", &outputs(&["mp3", "m4a"]), &settings));
+            assert!(!single_pass_outputs("wav", &outputs(&["mp3", "mp4"]), &settings));
+            assert!(!single_pass_outputs("png", &outputs(&["webp", "avif"]), &settings));
+            assert!(!single_pass_outputs("mov", &outputs(&["mp4", "gif"]), &settings));
+            assert!(!single_pass_outputs("mov", &outputs(&["mp4"]), &settings));
+            let target_size = ConversionSettings { target_size_mb: Some(8.0), ..Default::default() };
+            assert!(!single_pass_outputs("mov", &outputs(&["mp4", "webm"]), &target_size));
+        }
+
+        #[test]
+        fn test_shared_pass_args() {
+            let settings = ConversionSettings { audio_bitrate_kbps: Some(128), strip_metadata: true, ..Default::default() };
+            let outputs = vec![
+                ("mp4".to_string(), "/out/clip.mp4".to_string()),
+                ("jpg".to_string(), "/out/clip.jpg".to_string()),
+                ("m4a".to_string(), "/out/clip.m4a".to_string()),
+            ];
+            let threads = vec!["-threads".to_string(), "2".to_string()];
+            let args = shared_pass_args(&outputs, &threads, &settings).unwrap();
+
+            let mp4 = args.iter().position(|arg| arg == "/out/clip.mp4").unwrap();
+            let jpg = args.iter().position(|arg| arg == "/out/clip.jpg").unwrap();
+            assert!(args[..mp4].windows(2).any(|w| w == ["-c:v", "libx264"]));
+            assert!(args[..mp4].windows(2).any(|w| w == ["-b:a", "128k"]));
+            assert!(args[mp4..jpg].windows(2).any(|w| w == ["-frames:v", "1"]));
+            assert!(!args[mp4..jpg].contains(&"-b:a".to_string()));
+            assert!(args[jpg..].windows(2).any(|w| w == ["-f", "ipod"]));
+            assert_eq!(args.iter().filter(|arg| *arg == "-map_metadata").count(), 3);
+            assert_eq!(args.iter().filter(|arg| *arg == "-threads").count(), 3);
+            assert_eq!(args[args.len() - 3..], ["-threads", "2", "/out/clip.m4a"]);
+            assert!(shared_pass_args(&[("gif".to_string(), "a.gif".to_string())], &[], &settings).is_err());
+        }
+
+        #[test]
+        fn test_ffmpeg_log_args() {
+            assert_eq!(ffmpeg_log_args(DEFAULT_FFMPEG_LOG_LEVEL), ["-loglevel", "warning", "-stats"]);
+            assert!(FFMPEG_LOG_LEVELS.contains(&DEFAULT_FFMPEG_LOG_LEVEL));
+            assert!(log_level_reports_warnings("error"));
+            assert!(!log_level_reports_warnings("info"));
+        }
+
+        #[test]
+        fn test_video_bitrates_fit_target() {
+            let (video, audio) = compute_target_bitrates(25.0, 60.0, true).unwrap();
+            let total_kbits = (video.unwrap() + audio) as f64 * 60.0;
+            assert!(total_kbits <= 25.0 * 8.0 * 1024.0);
+            assert_eq!(audio, TARGET_SIZE_AUDIO_KBPS);
+        }
+
+        #[test]
+        fn test_audio_only_uses_whole_budget() {
+            let (video, audio) = compute_target_bitrates(5.0, 300.0, false).unwrap();
+            assert_eq!(video, None);
+            assert_eq!(audio, 132);
+        }
+
+        #[test]
+        fn test_target_too_small_errors() {
+            assert!(compute_target_bitrates(1.0, 600.0, true).is_err());
+            assert!(compute_target_bitrates(0.0, 60.0, true).is_err());
+            assert!(compute_target_bitrates(10.0, 0.0, true).is_err());
+        }
+
+        #[test]
+        fn test_target_size_codecs() {
+            assert_eq!(target_size_codecs("mp4"), Some((Some("libx264"), "aac")));
+            assert_eq!(target_size_codecs("webm"), Some((Some("libvpx-vp9"), "libopus")));
+            assert_eq!(target_size_codecs("mp3"), Some((None, "libmp3lame")));
+            assert_eq!(target_size_codecs("flac"), None);
+            assert_eq!(target_size_codecs("wav"), None);
+        }
+    }
+
+    // ==========================================
+    // AAC / M4A TESTS
+    // ==========================================
+
+    mod aac_containers {
+        use super::*;
+
+        const MP4_HEADER: &[u8] = &[0x00, 0x00, 0x00, 0x20, b'f', b't', b'y', b'p', b'M', b'4', b'A', b' '];
+        const ADTS_HEADER: &[u8] = &[0xFF, 0xF1, 0x50, 0x80, 0x02, 0x1F, 0xFC, 0x21];
+
+        #[test]
+        fn test_detect_aac_container() {
+            assert_eq!(detect_aac_container(MP4_HEADER), AacContainer::Mp4);
+            assert_eq!(detect_aac_container(ADTS_HEADER), AacContainer::Adts);
+            assert_eq!(detect_aac_container(&[0xFF, 0xFB, 0x90, 0x00]), AacContainer::Unknown); // MP3 frame
+            assert_eq!(detect_aac_container(&[]), AacContainer::Unknown);
+        }
+
+        #[test]
+        fn test_mislabeled_inputs_warn() {
+            assert!(aac_format_warning("aac", MP4_HEADER, "mp3").unwrap().contains(".m4a"));
+            assert!(aac_format_warning("m4a", ADTS_HEADER, "mp3").unwrap().contains(".aac"));
+            assert_eq!(aac_format_warning("m4a", MP4_HEADER, "mp3"), None);
+        }
+
+        #[test]
+        fn test_raw_aac_output_warns() {
+            assert!(aac_format_warning("mp3", &[], "aac").is_some());
+            assert_eq!(aac_format_warning("mp3", &[], "m4a"), None);
+        }
+
+        #[test]
+        fn test_aac_muxer_args() {
+            assert!(aac_muxer_args("aac").windows(2).any(|w| w == ["-f", "adts"]));
+            assert!(aac_muxer_args("m4a").windows(2).any(|w| w == ["-f", "ipod"]));
+            assert!(aac_muxer_args("mp3").is_empty());
+        }
+
+        #[test]
+        fn test_concat_list_escapes_quotes() {
+            let paths = vec!["/music/a.mp3".to_string(), "/music/Don't Stop.mp3".to_string()];
+            assert_eq!(concat_list(&paths), "file '/music/a.mp3'\nfile '/music/Don'\\''t Stop.mp3'\n");
+        }
+
+        #[test]
+        fn test_can_concat_by_copy() {
+            let exts = |list: &[&str]| list.iter().map(|ext| ext.to_string()).collect::<Vec<_>>();
+            assert!(can_concat_by_copy(&exts(&["mp3", "MP3"]), "mp3"));
+            assert!(can_concat_by_copy(&exts(&["m4a", "aac"]), "m4a"));
+            assert!(!can_concat_by_copy(&exts(&["mp3", "wav"]), "mp3"));
+            assert!(!can_concat_by_copy(&[], "mp3"));
+        }
+
+        #[test]
+        fn test_concat_audio_args() {
+            let copy = concat_audio_args("list.txt", "mp3");
+            assert_eq!(&copy[..6], ["-f", "concat", "-safe", "0", "-i", "list.txt"]);
+            assert!(copy.windows(2).any(|w| w == ["-c", "copy"]));
+
+            let inputs = ["a.mp3".to_string(), "b.wav".to_string(), "c.flac".to_string()];
+            let reencode = concat_audio_reencode_args(&inputs, "m4a");
+            assert_eq!(&reencode[..6], ["-i", "a.mp3", "-i", "b.wav", "-i", "c.flac"]);
+            assert!(!reencode.iter().any(|arg| arg == "concat"));
+            let graph = &reencode[reencode.iter().position(|arg| arg == "-filter_complex").unwrap() + 1];
+            assert!(graph.ends_with("[a0][a1][a2]concat=n=3:v=0:a=1[a]"), "{}", graph);
+            assert!(reencode.windows(2).any(|w| w == ["-map", "[a]"]));
+            assert!(reencode.windows(2).any(|w| w == ["-c:a", "aac"]));
+            assert!(reencode.windows(2).any(|w| w == ["-movflags", "+faststart"]));
+        }
+
+        fn clip(codec: &str, width: u32, audio: Option<&str>) -> MediaInfo {
+            MediaInfo {
+                video: Some(crate::probe::VideoStreamInfo {
+                    codec: codec.to_string(),
+                    width: Some(width),
+                    height: Some(720),
+                    frame_rate: Some(30.0),
+                    pixel_format: Some("yuv420p".to_string()),
+                    bit_depth: Some(8),
+                }),
+                audio: audio.map(|codec| crate::probe::AudioStreamInfo {
+                    codec: codec.to_string(),
+                    sample_rate: Some(48000),
+                    channels: Some(2),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_concat_videos_by_copy_when_clips_match() {
+            let clips = vec![clip("h264", 1280, Some("aac")), clip("h264", 1280, Some("aac"))];
+            assert!(can_concat_videos_by_copy(&clips, "mp4"));
+            assert!(can_concat_videos_by_copy(&clips, "mkv"));
+            // WebM can't hold H.264
+            assert!(!can_concat_videos_by_copy(&clips, "webm"));
+        }
+
+        #[test]
+        fn test_concat_videos_reencodes_mismatched_clips() {
+            assert!(!can_concat_videos_by_copy(&[clip("h264", 1280, Some("aac")), clip("h264", 1920, Some("aac"))], "mp4"));
+            assert!(!can_concat_videos_by_copy(&[clip("h264", 1280, Some("aac")), clip("hevc", 1280, Some("aac"))], "mp4"));
+            assert!(!can_concat_videos_by_copy(&[clip("h264", 1280, Some("aac")), clip("h264", 1280, None)], "mp4"));
+            assert!(!can_concat_videos_by_copy(&[], "mp4"));
+        }
+
+        #[test]
+        fn test_concat_reencode_graph() {
+            let graph = concat_reencode_graph(2, 1281, 720, 30.0, true).render();
+            assert!(graph.starts_with("[0:v]scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2:color=black,setsar=1,fps=30,format=yuv420p[v0]"));
+            assert!(graph.contains("[1:a]aresample=48000[a1]"));
+            assert!(graph.ends_with("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"));
+            let silent = concat_reencode_graph(2, 640, 480, 25.0, false).render();
+            assert!(silent.ends_with("[v0][v1]concat=n=2:v=1:a=0[v]"));
+        }
+
+        #[test]
+        fn test_parse_progress_time() {
+            assert_eq!(parse_progress_time("out_time_us=2500000"), Some(2.5));
+            assert_eq!(parse_progress_time("out_time_ms=1000000\n"), Some(1.0));
+            assert_eq!(parse_progress_time("frame=10"), None);
+            assert_eq!(parse_progress_time("out_time_us=N/A"), None);
+        }
+
+        #[test]
+        fn test_concat_segment_at() {
+            let durations = [10.0, 5.0, 20.0];
+            assert_eq!(concat_segment_at(&durations, 0.0), 0);
+            assert_eq!(concat_segment_at(&durations, 12.0), 1);
+            assert_eq!(concat_segment_at(&durations, 15.0), 2);
+            assert_eq!(concat_segment_at(&durations, 99.0), 2);
+            assert_eq!(concat_segment_at(&[], 1.0), 0);
+        }
+
+        #[test]
+        fn test_frame_selection_from_request() {
+            assert_eq!(FrameSelection::from_request(Some(2.0), None), Ok(FrameSelection::Fps(2.0)));
+            assert_eq!(
+                FrameSelection::from_request(None, Some(vec![5.0, 1.5, 5.0004])),
+                Ok(FrameSelection::Timestamps(vec![1.5, 5.0]))
+            );
+            assert!(FrameSelection::from_request(Some(0.0), None).is_err());
+            assert!(FrameSelection::from_request(Some(500.0), None).is_err());
+            assert!(FrameSelection::from_request(None, Some(vec![-1.0])).is_err());
+            assert!(FrameSelection::from_request(None, Some(Vec::new())).is_err());
+            assert!(FrameSelection::from_request(Some(1.0), Some(vec![1.0])).is_err());
+            assert!(FrameSelection::from_request(None, None).is_err());
+        }
+
+        #[test]
+        fn test_extract_frames_args() {
+            assert_eq!(frame_file_name(42, "png"), "frame_00042.png");
+            assert_eq!(extract_frames_args(Some(0.5), "png"), vec!["-vf", "fps=0.5"]);
+            assert_eq!(extract_frames_args(None, "jpg"), vec!["-frames:v", "1", "-q:v", "2"]);
+        }
+
+        #[test]
+        fn test_libreoffice_pdf_preview_target() {
+            let defaults = PdfExportOptions::default();
+            assert_eq!(
+                libreoffice_pdf_target("docx", &defaults, &SpreadsheetLayout::default(), Some(3)),
+                r#"pdf:writer_pdf_Export:{"PageRange":{"type":"string","value":"1-3"}}"#
+            );
+            assert!(libreoffice_pdf_target("xlsx", &defaults, &SpreadsheetLayout::default(), Some(1)).starts_with("pdf:calc_pdf_Export:"));
+            assert!(libreoffice_pdf_target("pptx", &defaults, &SpreadsheetLayout::default(), Some(0)).ends_with(r#""value":"1-1"}}"#));
+        }
+
+        #[test]
+        fn test_libreoffice_pdf_target_options() {
+            assert_eq!(libreoffice_pdf_target("docx", &PdfExportOptions::default(), &SpreadsheetLayout::default(), None), "pdf");
+
+            let archival = PdfExportOptions { embed_standard_fonts: true, pdf_a: Some(2), ..Default::default() };
+            assert_eq!(
+                libreoffice_pdf_target("docx", &archival, &SpreadsheetLayout::default(), None),
+                r#"pdf:writer_pdf_Export:{"EmbedStandardFonts":{"type":"boolean","value":"true"},"SelectPdfVersion":{"type":"long","value":"2"}}"#
+            );
+
+            let locked = PdfExportOptions { password: Some(r#"a"b"#.to_string()), lossless_images: true, ..Default::default() };
+            let target = libreoffice_pdf_target("docx", &locked, &SpreadsheetLayout::default(), None);
+            assert!(target.contains(r#""DocumentOpenPassword":{"type":"string","value":"a\"b"}"#));
+            assert!(target.contains(r#""UseLosslessCompression":{"type":"boolean","value":"true"}"#));
+            assert!(target.contains(r#""ReduceImageResolution":{"type":"boolean","value":"false"}"#));
+        }
+
+        #[test]
+        fn test_libreoffice_pdf_target_fits_sheets() {
+            let fit = SpreadsheetLayout { fit_to_page: true, ..Default::default() };
+            assert_eq!(
+                libreoffice_pdf_target("xlsx", &PdfExportOptions::default(), &fit, None),
+                r#"pdf:calc_pdf_Export:{"SinglePageSheets":{"type":"boolean","value":"true"}}"#
+            );
+            // Only spreadsheets have sheets to fit
+            assert_eq!(libreoffice_pdf_target("docx", &PdfExportOptions::default(), &fit, None), "pdf");
+        }
+
+        #[test]
+        fn test_redact_pdf_password() {
+            let locked = PdfExportOptions { password: Some(r#"sw0rd"fi\sh"#.to_string()), ..Default::default() };
+            let line = format!("soffice --convert-to '{}' a.docx", libreoffice_pdf_target("docx", &locked, &SpreadsheetLayout::default(), None));
+            let redacted = redact_pdf_password(&line);
+            assert!(!redacted.contains("sw0rd") && !redacted.contains(r"\\sh"));
+            assert!(redacted.contains(r#""value":"********"}"#));
+            assert!(redacted.ends_with("' a.docx"));
+            assert_eq!(redact_pdf_password("soffice --convert-to pdf a.docx"), "soffice --convert-to pdf a.docx");
+        }
+
+        #[test]
+        fn test_pdf_export_options_validate() {
+            assert!(PdfExportOptions::default().validate().is_ok());
+            assert!(PdfExportOptions { pdf_a: Some(4), ..Default::default() }.validate().is_err());
+            assert!(PdfExportOptions { pdf_a: Some(1), password: Some("x".to_string()), ..Default::default() }.validate().is_err());
+            assert!(PdfExportOptions { password: Some(String::new()), ..Default::default() }.validate().is_err());
+        }
+
+        #[test]
+        fn test_html_preview_sections() {
+            let html = "<h1 id=\"a\">A</h1><p>1</p><h2>A.1</h2><h1>B</h1><p>2</p><h1>C</h1>";
+            assert_eq!(html_preview_sections(html, 1), "<h1 id=\"a\">A</h1><p>1</p><h2>A.1</h2>");
+            assert_eq!(html_preview_sections(html, 2), "<h1 id=\"a\">A</h1><p>1</p><h2>A.1</h2><h1>B</h1><p>2</p>");
+            assert_eq!(html_preview_sections(html, 5), html);
+        }
+
+        #[test]
+        fn test_html_preview_sections_with_intro() {
+            let html = "<p>intro</p><h2>A</h2><p>1</p><h2>B</h2>";
+            assert_eq!(html_preview_sections(html, 1), "<p>intro</p>");
+            assert_eq!(html_preview_sections(html, 2), "<p>intro</p><h2>A</h2><p>1</p>");
+            assert_eq!(html_preview_sections("<p>no headings</p>", 1), "<p>no headings</p>");
+        }
+
+        #[test]
+        fn test_contact_sheet_filters() {
+            let filters = contact_sheet_filters(160.0, 4, 4, 320, false, None);
+            assert_eq!(
+                filter_graph::chain(&filters),
+                "select='gte(t,5.000)*(isnan(prev_selected_t)+gte(t-prev_selected_t,10.000))',scale=320:-2,tile=4x4"
+            );
+
+            let stamped = filter_graph::chain(&contact_sheet_filters(60.0, 3, 2, 480, true, None));
+            assert!(stamped.contains("scale=480:-2,drawtext=text='%{pts\\:hms}':x=5:y=h-th-5:fontsize=30"));
+            assert!(stamped.ends_with("tile=3x2"));
+        }
+
+        #[test]
+        fn test_subtitle_file_name() {
+            let english = SubtitleStreamInfo { index: 2, language: Some("eng".to_string()), ..Default::default() };
+            assert_eq!(subtitle_file_name("movie", &english, 1, "srt"), "movie.eng.srt");
+            assert_eq!(subtitle_file_name("movie", &english, 2, "srt"), "movie.eng.3.srt");
+            let unlabeled = SubtitleStreamInfo { index: 0, ..Default::default() };
+            assert_eq!(subtitle_file_name("movie", &unlabeled, 1, "ass"), "movie.1.ass");
+        }
+
+        #[test]
+        fn test_extract_subtitle_args() {
+            assert_eq!(extract_subtitle_args(1, "srt"), vec!["-map", "0:s:1", "-c:s", "srt"]);
+            assert_eq!(extract_subtitle_args(0, "ass"), vec!["-map", "0:s:0", "-c:s", "ass"]);
+            assert!(is_text_subtitle_codec("mov_text"));
+            assert!(!is_text_subtitle_codec("hdmv_pgs_subtitle"));
+        }
+
+        #[test]
+        fn test_subtitle_args() {
+            assert_eq!(
+                subtitle_args(SubtitleMode::Mux, "/subs/film.srt", "mp4").unwrap(),
+                vec!["-i", "/subs/film.srt", "-map", "0:v?", "-map", "0:a?", "-map", "1:s", "-c:s", "mov_text"]
+            );
+            assert!(subtitle_args(SubtitleMode::Mux, "film.srt", "mkv").unwrap().ends_with(&["-c:s".to_string(), "copy".to_string()]));
+            assert!(subtitle_args(SubtitleMode::Mux, "film.srt", "avi").is_err());
+            assert_eq!(subtitle_args(SubtitleMode::Burn, "input-0.srt", "avi").unwrap(), vec!["-vf", "subtitles=input-0.srt"]);
+        }
+
+        fn film_streams() -> Vec<StreamInfo> {
+            let stream = |index, kind, codec: &str| StreamInfo { index, kind, codec: codec.to_string(), ..Default::default() };
+            vec![
+                stream(0, StreamKind::Video, "h264"),
+                stream(1, StreamKind::Audio, "ac3"),
+                stream(2, StreamKind::Audio, "aac"),
+                stream(3, StreamKind::Subtitle, "subrip"),
+                stream(4, StreamKind::Subtitle, "hdmv_pgs_subtitle"),
+            ]
+        }
+
+        #[test]
+        fn test_stream_selection_args() {
+            let selection = StreamSelection { audio: vec![2, 1], subtitles: vec![3] };
+            assert_eq!(
+                stream_selection_args(&selection, &film_streams(), "mp4", false).unwrap(),
+                vec![
+                    "-map", "0:V?", "-map", "0:2", "-map", "0:1", "-map", "0:3",
+                    "-disposition:a:0", "default", "-disposition:a:1", "0",
+                    "-c:s", "mov_text",
+                ]
+            );
+
+            // Nothing selected keeps just the video
+            let none = StreamSelection::default();
+            assert_eq!(stream_selection_args(&none, &film_streams(), "mp4", false).unwrap(), vec!["-map", "0:V?"]);
+
+            // An external file muxed in alongside
+            let args = stream_selection_args(&none, &film_streams(), "mkv", true).unwrap();
+            assert_eq!(args, vec!["-map", "0:V?", "-map", "1:s", "-c:s", "copy"]);
+        }
+
+        #[test]
+        fn test_stream_selection_rejects_bad_picks() {
+            let streams = film_streams();
+            let wrong_kind = StreamSelection { audio: vec![3], subtitles: vec![] };
+            assert!(stream_selection_args(&wrong_kind, &streams, "mkv", false).is_err());
+            let bitmap = StreamSelection { audio: vec![], subtitles: vec![4] };
+            assert!(stream_selection_args(&bitmap, &streams, "mp4", false).unwrap_err().contains("MKV"));
+            assert!(stream_selection_args(&bitmap, &streams, "mkv", false).is_ok());
+            assert!(stream_selection_args(&bitmap, &streams, "avi", false).is_err());
+        }
+
+        #[test]
+        fn test_stream_selection_for_audio_outputs() {
+            let streams = film_streams();
+            let one = StreamSelection { audio: vec![2], subtitles: vec![3] };
+            assert_eq!(stream_selection_args(&one, &streams, "mp3", false).unwrap(), vec!["-map", "0:2"]);
+            let two = StreamSelection { audio: vec![1, 2], subtitles: vec![] };
+            assert!(stream_selection_args(&two, &streams, "mp3", false).is_err());
+        }
+
+        #[test]
+        fn test_ffmpeg_avif_args() {
+            let opaque = ffmpeg_avif_args(false, FilterGraph::new());
+            assert_eq!(&opaque[..4], ["-frames:v", "1", "-c:v", "libaom-av1"]);
+            let transparent = ffmpeg_avif_args(true, FilterGraph::new());
+            assert_eq!(&transparent[..5], ["-map", "0:v", "-map", "0:v", "-filter:v:1"]);
+            assert_eq!(transparent.len(), opaque.len() + 6);
+        }
+
+        #[test]
+        fn test_ffmpeg_avif_args_turn_the_picture_upright() {
+            let upright = || FilterGraph::new().chain(&[], filter_graph::exif_orientation(6), &[]);
+            let opaque = ffmpeg_avif_args(false, upright());
+            assert_eq!(&opaque[..2], ["-filter:v:0", "transpose=1"]);
+            let transparent = ffmpeg_avif_args(true, upright());
+            assert_eq!(&transparent[..7], ["-map", "0:v", "-map", "0:v", "-filter:v:1", "transpose=1,alphaextract", "-filter:v:0"]);
+            assert_eq!(transparent[7], "transpose=1");
+        }
+
+        #[test]
+        fn test_ffmpeg_avif_args_crop() {
+            let settings = ConversionSettings {
+                crop: Some(CropOptions { x: 10, y: 20, width: Some(300), height: Some(200), aspect: None }),
+                ..Default::default()
+            };
+            let cropped = FilterGraph::new().chain(&[], ffmpeg_crop_filters(&settings), &[]);
+            let args = ffmpeg_avif_args(true, cropped);
+            assert_eq!(args[5], "crop=300:200:10:20,alphaextract");
+            assert_eq!(&args[6..8], ["-filter:v:0", "crop=300:200:10:20"]);
+        }
+
+        #[test]
+        fn test_ffmpeg_avif_args_watermark() {
+            let logo = Watermark { image_path: Some("logo.png".to_string()), ..Default::default() };
+            let marked = ffmpeg_image_watermark(FilterGraph::new(), "in", Vec::new(), &logo, "input-0.png", (1920, 1080), &[]);
+            let args = ffmpeg_avif_args(true, marked.clone());
+            assert_eq!(args[5], format!("{},alphaextract", marked.render()));
+            assert_eq!(args[6..8], ["-filter:v:0".to_string(), marked.render()]);
+
+            let text = Watermark { text: Some("Draft".to_string()), ..Default::default() };
+            let drawn = FilterGraph::new().chain(&[], vec![ffmpeg_text_watermark(&text, "Draft", None)], &[]);
+            let args = ffmpeg_avif_args(false, drawn);
+            assert_eq!(args[0], "-filter:v:0");
+            assert!(args[1].starts_with("drawtext=text=Draft"));
+        }
+    }
 
     // ==========================================
-    // VIDEO CONVERSION TESTS
+    // METADATA STRIP / PRESERVE TESTS
     // ==========================================
 
-    mod video_conversions {
+    mod metadata_handling {
         use super::*;
 
         #[test]
-        fn test_all_video_to_video_conversions() {
-            let video_formats = ["mp4", "mov", "avi", "mkv", "webm"];
-            
-            for input in &video_formats {
-                for output in &video_formats {
-                    let result = determine_conversion_tool(input, output);
-                    assert_eq!(
-                        result, Some("ffmpeg"),
-                        "Video conversion {} -> {} should use ffmpeg",
-                        input, output
-                    );
-                }
-            }
+        fn test_metadata_args() {
+            let settings = ConversionSettings { strip_metadata: true, ..Default::default() };
+            assert_eq!(metadata_args("imagemagick", &settings), vec!["-strip"]);
+            assert_eq!(metadata_args("ffmpeg", &settings), vec!["-map_metadata", "-1"]);
+            assert!(metadata_args("pandoc", &settings).is_empty());
         }
 
         #[test]
-        fn test_all_video_inputs_recognized() {
-            for format in VIDEO_INPUTS {
-                assert!(
-                    is_video_format(format),
-                    "Video format {} should be recognized",
-                    format
-                );
-            }
+        fn test_preserve_metadata_args() {
+            let settings = ConversionSettings { preserve_metadata: true, ..Default::default() };
+            assert_eq!(metadata_args("ffmpeg", &settings), vec!["-map_metadata", "0"]);
+            assert!(metadata_args("imagemagick", &settings).is_empty());
+
+            // Stripping wins when both are requested
+            let both = ConversionSettings { preserve_metadata: true, strip_metadata: true, ..Default::default() };
+            assert_eq!(metadata_args("ffmpeg", &both), vec!["-map_metadata", "-1"]);
         }
 
         #[test]
-        fn test_video_to_mp4() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "mp4"), Some("ffmpeg"),
-                    "{} -> mp4 should use ffmpeg", input
-                );
-            }
+        fn test_deterministic_args() {
+            let settings = ConversionSettings { deterministic: true, preserve_metadata: true, ..Default::default() };
+            assert!(!settings.keeps_metadata());
+            assert_eq!(
+                metadata_args("ffmpeg", &settings),
+                vec!["-map_metadata", "-1", "-fflags", "+bitexact", "-flags:v", "+bitexact", "-flags:a", "+bitexact"]
+            );
+            assert_eq!(
+                imagemagick_metadata_ops(&settings),
+                vec![MagickOp::Strip, MagickOp::define("png:exclude-chunk", "date,time")]
+            );
+            assert_eq!(deterministic_env(&settings), vec![("SOURCE_DATE_EPOCH", "315532800".to_string())]);
+            assert!(deterministic_env(&ConversionSettings::default()).is_empty());
         }
 
         #[test]
-        fn test_video_to_mov() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "mov"), Some("ffmpeg"),
-                    "{} -> mov should use ffmpeg", input
-                );
+        fn test_metadata_kept_by_default() {
+            let settings = ConversionSettings::default();
+            assert!(metadata_args("imagemagick", &settings).is_empty());
+            assert!(metadata_args("ffmpeg", &settings).is_empty());
+        }
+    }
+
+    // ==========================================
+    // AUDIO PRESET TESTS
+    // ==========================================
+
+    mod audio_presets {
+        use super::*;
+
+        #[test]
+        fn test_builtin_podcast_presets() {
+            let presets = builtin_audio_presets();
+            let ids: Vec<&str> = presets.iter().map(|p| p.id.as_str()).collect();
+            assert_eq!(ids, vec!["podcast-mp3-mono", "podcast-mp3-stereo", "podcast-m4a-mono", "podcast-m4a-stereo"]);
+            for preset in &presets {
+                assert_eq!(preset.settings.loudness_lufs, Some(-16.0));
+                assert!(preset.settings.keep_chapters);
             }
+            assert_eq!(presets[0].settings.audio_bitrate_kbps, Some(64));
+            assert_eq!(presets[1].settings.audio_bitrate_kbps, Some(128));
         }
 
         #[test]
-        fn test_video_to_avi() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "avi"), Some("ffmpeg"),
-                    "{} -> avi should use ffmpeg", input
-                );
+        fn test_podcast_args() {
+            let mut settings = podcast_preset("mp3", 1).settings;
+            settings.audio_tags.title = Some("Episode 12".to_string());
+            settings.audio_tags.artist = Some("  ".to_string());
+            let args = audio_processing_args("mp3", &settings, &AudioAnalysis::default());
+
+            assert!(args.windows(2).any(|w| w == ["-af", "loudnorm=I=-16:TP=-1.5:LRA=11"]));
+            assert!(args.windows(2).any(|w| w == ["-ac", "1"]));
+            assert!(args.windows(2).any(|w| w == ["-b:a", "64k"]));
+            assert!(args.windows(2).any(|w| w == ["-map_chapters", "0"]));
+            let from_cue = audio_processing_args("mp3", &settings, &AudioAnalysis { cue_chapters: true, ..Default::default() });
+            assert!(from_cue.windows(2).any(|w| w == ["-map_chapters", "1"]));
+            assert!(args.windows(2).any(|w| w == ["-metadata", "title=Episode 12"]));
+            assert!(args.windows(2).any(|w| w == ["-id3v2_version", "3"]));
+            // Blank tags are skipped
+            assert!(!args.iter().any(|a| a.starts_with("artist=")));
+        }
+
+        #[test]
+        fn test_audio_args_empty_by_default() {
+            assert!(audio_processing_args("mp3", &ConversionSettings::default(), &AudioAnalysis::default()).is_empty());
+            // ID3 version is MP3 only
+            let settings = podcast_preset("m4a", 2).settings;
+            assert!(!audio_processing_args("m4a", &settings, &AudioAnalysis::default()).contains(&"-id3v2_version".to_string()));
+        }
+
+        #[test]
+        fn test_silence_trim_and_fades() {
+            let mut settings = ConversionSettings { fade_in_ms: Some(500), fade_out_ms: Some(2000), ..Default::default() };
+            // With the length known, the fade-out starts two seconds before the end
+            assert_eq!(
+                filter_graph::chain(&audio_edit_filters(&settings, Some(63.25))),
+                "afade=t=out:st=61.250:d=2.000,afade=t=in:d=0.500"
+            );
+            // Without the length there's no fade-out, rather than buffering the whole stream
+            assert_eq!(filter_graph::chain(&audio_edit_filters(&settings, None)), "afade=t=in:d=0.500");
+
+            settings.trim_silence = true;
+            settings.fade_in_ms = None;
+            settings.loudness_lufs = Some(-16.0);
+            let analysis = AudioAnalysis { duration_secs: Some(63.25), ..Default::default() };
+            let args = audio_processing_args("m4a", &settings, &analysis);
+            assert!(args.windows(2).any(|w| w == [
+                "-af",
+                "afade=t=out:st=61.250:d=2.000,silenceremove=start_periods=1:start_threshold=-50dB,\
+                 silenceremove=stop_periods=-1:stop_duration=2.000:stop_threshold=-50dB,loudnorm=I=-16:TP=-1.5:LRA=11"
+            ]));
+            // Video keeps its audio in sync: only the loudness changes
+            assert!(audio_processing_args("mp4", &settings, &analysis).windows(2).any(|w| w == ["-af", "loudnorm=I=-16:TP=-1.5:LRA=11"]));
+
+            settings.fade_out_ms = Some(MAX_FADE_MS + 1);
+            assert!(validate_audio_edits(&settings).is_err());
+            assert!(audio_edit_filters(&ConversionSettings::default(), Some(10.0)).is_empty());
+        }
+
+        #[test]
+        fn test_two_pass_loudness() {
+            let stderr = "size=N/A time=00:42:10.01 bitrate=N/A speed= 512x
+[Parsed_loudnorm_0 @ 0x600001234000] 
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "6.80",
+	"input_thresh" : "-38.11",
+	"output_i" : "-16.58",
+	"output_tp" : "-1.50",
+	"output_lra" : "5.20",
+	"output_thresh" : "-27.02",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.58"
+}
+";
+            let measured = parse_loudnorm_json(stderr).unwrap();
+            assert_eq!(measured.input_i, -27.61);
+            assert_eq!(measured.target_offset, 0.58);
+            assert_eq!(parse_loudnorm_json(&stderr.replace("-27.61", "-inf")), None);
+            assert_eq!(parse_loudnorm_json("no loudnorm here"), None);
+
+            assert_eq!(loudness_analysis_args(-16.0)[2], "loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json");
+            let settings = podcast_preset("mp3", 1).settings;
+            let analysis = AudioAnalysis { loudness: Some(measured), ..Default::default() };
+            let args = audio_processing_args("mp3", &settings, &analysis);
+            assert!(args.iter().any(|arg| arg.contains("measured_I=-27.61") && arg.ends_with(":linear=true")));
+        }
+    }
+
+    // ==========================================
+    // CONVERSION RESULT TESTS
+    // ==========================================
+
+    mod conversion_results {
+        use super::*;
+
+        #[test]
+        fn test_compression_ratio() {
+            let result = ConversionResult::new("out.webp".to_string(), 420, 4000, 1000, None);
+            assert_eq!(result.compression_ratio, 0.25);
+            assert_eq!(ConversionResult::new("out.png".to_string(), 0, 0, 10, None).compression_ratio, 0.0);
+        }
+
+        #[test]
+        fn test_result_with_notes() {
+            let result = ConversionResult::new("out.jpg".to_string(), 10, 100, 50, None)
+                .with_notes("ffmpeg", conversion_warnings("jpg", true), fallback_notes(Some("imagemagick"), "ffmpeg"));
+            assert_eq!(result.tool.as_deref(), Some("ffmpeg"));
+            assert_eq!(result.warnings.len(), 1);
+            assert!(result.warnings[0].contains("JPG"));
+            assert_eq!(result.fallbacks, vec!["FFmpeg was used because ImageMagick isn't installed"]);
+        }
+
+        #[test]
+        fn test_no_notes_for_plain_conversion() {
+            assert!(conversion_warnings("png", true).is_empty());
+            assert!(conversion_warnings("jpg", false).is_empty());
+            assert!(fallback_notes(Some("ffmpeg"), "ffmpeg").is_empty());
+            assert!(fallback_notes(None, "builtin").is_empty());
+        }
+
+        #[test]
+        fn test_result_without_notes_deserializes() {
+            let json = r#"{"output_path":"a.png","elapsed_ms":1,"input_size":2,"output_size":3,"compression_ratio":1.5,"command_line":null}"#;
+            let result: ConversionResult = serde_json::from_str(json).unwrap();
+            assert!(result.tool.is_none() && result.warnings.is_empty() && result.fallbacks.is_empty());
+        }
+
+        #[test]
+        fn test_format_command_line() {
+            let args: Vec<String> = ["-i", "/My Videos/clip.mov", "-vf", "scale=\"iw/2\":-2", "out.mp4"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+            assert_eq!(
+                format_command_line("ffmpeg", &args),
+                r#"ffmpeg -i "/My Videos/clip.mov" -vf "scale=\"iw/2\":-2" out.mp4"#
+            );
+            assert_eq!(format_command_line("magick", &["".to_string()]), r#"magick """#);
+        }
+    }
+
+    // ==========================================
+    // UNSUPPORTED REASON TESTS
+    // ==========================================
+
+    mod unsupported_reasons {
+        use super::*;
+
+        #[test]
+        fn test_available_pair() {
+            let explanation = explain_unsupported("mp4", "mp3", |_| true, true);
+            assert!(explanation.supported);
+            assert_eq!(explanation.tool.as_deref(), Some("ffmpeg"));
+            assert!(explanation.reasons.is_empty());
+        }
+
+        #[test]
+        fn test_missing_tool() {
+            let explanation = explain_unsupported("png", "jpg", |tool| tool != "imagemagick", true);
+            assert!(!explanation.supported);
+            assert_eq!(explanation.reasons, vec![UnsupportedReason::ToolNotInstalled]);
+            assert!(explanation.message.contains("ImageMagick"));
+        }
+
+        #[test]
+        fn test_rename_needs_no_tool() {
+            assert!(explain_unsupported("jpg", "jpeg", |_| false, true).supported);
+        }
+
+        #[test]
+        fn test_no_backend() {
+            let explanation = explain_unsupported("mp3", "png", |_| true, true);
+            assert_eq!(explanation.tool, None);
+            assert_eq!(explanation.reasons, vec![UnsupportedReason::NotSupported]);
+            assert!(explanation.message.contains("MP3 to PNG"));
+        }
+
+        #[test]
+        fn test_pandoc_disabled() {
+            if pandoc_enabled() {
+                return;
             }
+            let explanation = explain_unsupported("md", "html", |_| false, true);
+            assert_eq!(explanation.tool.as_deref(), Some("pandoc"));
+            assert_eq!(explanation.reasons, vec![UnsupportedReason::FeatureDisabled]);
+        }
+
+        #[test]
+        fn test_builtin_needs_no_tool() {
+            let explanation = explain_unsupported("png", "jpg", |_| false, true);
+            assert!(explanation.supported);
+            assert_eq!(explanation.tool.as_deref(), Some(BUILTIN_TOOL));
+        }
+
+        #[test]
+        fn test_license_listed_after_other_reasons() {
+            let explanation = explain_unsupported("png", "heic", |_| false, false);
+            assert_eq!(
+                explanation.reasons,
+                vec![UnsupportedReason::ToolNotInstalled, UnsupportedReason::LicenseRequired]
+            );
+            assert!(explanation.message.contains("ImageMagick"));
+
+            let explanation = explain_unsupported("png", "jpg", |_| true, false);
+            assert_eq!(explanation.reasons, vec![UnsupportedReason::LicenseRequired]);
+            assert!(explanation.message.contains("license"));
+        }
+
+        #[test]
+        fn test_extension_normalized() {
+            assert_eq!(explain_unsupported(".PNG", "JPG", |_| true, true).tool.as_deref(), Some("imagemagick"));
+        }
+    }
+
+    // ==========================================
+    // LIBREOFFICE TESTS
+    // ==========================================
+
+    mod libreoffice {
+        use super::*;
+
+        #[test]
+        fn test_office_pairs_use_libreoffice() {
+            assert_eq!(determine_conversion_tool("docx", "pdf"), Some("libreoffice"));
+            assert_eq!(determine_conversion_tool("pptx", "pdf"), Some("libreoffice"));
+            assert_eq!(determine_conversion_tool("doc", "docx"), Some("libreoffice"));
+        }
+
+        #[test]
+        fn test_convert_target() {
+            assert_eq!(libreoffice_convert_target("docx", "pdf"), "pdf");
+            assert_eq!(libreoffice_convert_target("docx", "txt"), "txt:Text (encoded):UTF8");
+            assert_eq!(libreoffice_convert_target("xlsx", "txt"), "txt:Text - txt - csv (StarCalc)");
+        }
+
+        #[test]
+        fn test_args() {
+            let args = libreoffice_args("/docs/Report.DOCX", "txt", "/tmp/out", "/data/lo-profile");
+            assert_eq!(
+                args,
+                vec![
+                    "-env:UserInstallation=file:///data/lo-profile",
+                    "--headless",
+                    "--norestore",
+                    "--convert-to",
+                    "txt:Text (encoded):UTF8",
+                    "--outdir",
+                    "/tmp/out",
+                    "/docs/Report.DOCX",
+                ]
+            );
+        }
+
+        #[test]
+        fn test_profile_url_on_windows() {
+            let args = libreoffice_args(r"C:\docs\a.docx", "pdf", r"C:\tmp", r"C:\Users\Me\AppData\lo profile");
+            assert_eq!(args[0], "-env:UserInstallation=file:///C:/Users/Me/AppData/lo%20profile");
+        }
+
+        #[test]
+        fn test_latest_version() {
+            let listing = r#"<a href="../">Parent Directory</a>
+                <a href="24.2.7/">24.2.7/</a>
+                <a href="24.8.10/">24.8.10/</a>
+                <a href="24.8.2/">24.8.2/</a>
+                <a href="?C=M;O=A">Last modified</a>"#;
+            assert_eq!(latest_libreoffice_version(listing), Some("24.8.10".to_string()));
+            assert_eq!(latest_libreoffice_version("<html>nothing here</html>"), None);
+        }
+
+        #[test]
+        fn test_output_name() {
+            assert_eq!(libreoffice_output_name("/docs/Q3 Report.docx", "pdf"), "Q3 Report.pdf");
+            assert_eq!(libreoffice_output_name("/docs/notes.v2.odt", "docx"), "notes.v2.docx");
+        }
+    }
+
+    // ==========================================
+    // ENCODER CAPABILITY TESTS
+    // ==========================================
+
+    mod encoder_capabilities {
+        use super::*;
+
+        const ENCODERS_OUTPUT: &str = "Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ..S... = Slice-level multithreading
+ ...X.. = Codec is experimental
+ ....B. = Supports draw_horiz_band
+ .....D = Supports direct rendering method 1
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ V....D libjxl               libjxl JPEG XL (codec jpegxl)
+ A....D aac                  AAC (Advanced Audio Coding)
+ S..... srt                  SubRip subtitle
+";
+
+        #[test]
+        fn test_parse_encoders_skips_legend() {
+            assert_eq!(parse_ffmpeg_encoders(ENCODERS_OUTPUT), vec!["libx264", "libjxl", "aac", "srt"]);
+        }
+
+        #[test]
+        fn test_parse_encoders_without_list() {
+            assert!(parse_ffmpeg_encoders("ffmpeg: unrecognized option").is_empty());
+        }
+
+        #[test]
+        fn test_requested_encoders() {
+            let args: Vec<String> = ["-y", "-i", "in.mov", "-c:v", "libx265", "-acodec", "libfdk_aac", "-c:s", "copy", "-codec:a", "libfdk_aac", "out.mp4"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+            assert_eq!(requested_encoders(&args), vec!["libx265", "libfdk_aac"]);
+        }
+
+        #[test]
+        fn test_requested_encoders_none() {
+            let args: Vec<String> = ["-i", "in.wav", "out.mp3"].iter().map(|s| s.to_string()).collect();
+            assert!(requested_encoders(&args).is_empty());
+        }
+
+        #[test]
+        fn test_is_homebrew_path() {
+            assert!(is_homebrew_path("/opt/homebrew/bin/ffmpeg"));
+            assert!(is_homebrew_path("/usr/local/bin/ffmpeg"));
+            assert!(is_homebrew_path("/home/linuxbrew/.linuxbrew/bin/ffmpeg"));
+            assert!(!is_homebrew_path("/Users/me/Library/Application Support/com.convertsave/ffmpeg/ffmpeg"));
+            assert!(!is_homebrew_path("/usr/bin/ffmpeg"));
+        }
+    }
+
+    // ==========================================
+    // IMAGEMAGICK INPUT PATH TESTS
+    // ==========================================
+
+    mod imagemagick_inputs {
+        use super::*;
+
+        #[test]
+        fn test_plain_paths_are_literal() {
+            assert!(is_imagemagick_literal_path("/Users/me/Photos/IMG_0001.gif"));
+            assert!(is_imagemagick_literal_path(r"C:\Users\me\My Photos\scan (1).png"));
+            assert!(is_imagemagick_literal_path("/photos/me@home.jpg"));
+        }
+
+        #[test]
+        fn test_brackets_and_wildcards_are_not_literal() {
+            assert!(!is_imagemagick_literal_path("/photos/[2024] trip/beach.gif"));
+            assert!(!is_imagemagick_literal_path("/photos/frame[1].png"));
+            assert!(!is_imagemagick_literal_path("/photos/what?.jpg"));
+            assert!(!is_imagemagick_literal_path("/photos/{a,b}.jpg"));
+            assert!(!is_imagemagick_literal_path("/photos/all*.jpg"));
+        }
+
+        #[test]
+        fn test_leading_at_is_not_literal() {
+            assert!(!is_imagemagick_literal_path("/photos/@list.txt"));
+            assert!(!is_imagemagick_literal_path(r"C:\photos\@list.png"));
+        }
+
+        #[test]
+        fn test_input_spec() {
+            assert_eq!(imagemagick_input_spec("input-0.gif", Some(0)), "input-0.gif[0]");
+            assert_eq!(imagemagick_input_spec("/a/b.gif", None), "/a/b.gif");
+        }
+
+        #[test]
+        fn test_staged_input_name() {
+            assert_eq!(staged_input_name(0, "GIF"), "input-0.gif");
+            assert_eq!(staged_input_name(2, "we[b]p"), "input-2.webp");
+            assert_eq!(staged_input_name(1, ""), "input-1");
+        }
+    }
+
+    // ==========================================
+    // E-BOOK CONVERSION TESTS
+    // ==========================================
+
+    mod ebook_conversions {
+        use super::*;
+
+        #[test]
+        fn test_kindle_formats_use_calibre() {
+            assert_eq!(determine_conversion_tool("epub", "mobi"), Some("calibre"));
+            assert_eq!(determine_conversion_tool("epub", "azw3"), Some("calibre"));
+            assert_eq!(determine_conversion_tool("mobi", "epub"), Some("calibre"));
+            assert_eq!(determine_conversion_tool("azw3", "pdf"), Some("calibre"));
         }
 
         #[test]
-        fn test_video_to_mkv() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "mkv"), Some("ffmpeg"),
-                    "{} -> mkv should use ffmpeg", input
-                );
-            }
+        fn test_documents_to_epub() {
+            let expected = if pandoc_enabled() { Some("pandoc") } else { Some("calibre") };
+            assert_eq!(determine_conversion_tool("docx", "epub"), expected);
+            assert_eq!(determine_conversion_tool("md", "epub"), expected);
+            // Pandoc can't write Kindle formats
+            assert_eq!(determine_conversion_tool("docx", "mobi"), Some("calibre"));
         }
 
         #[test]
-        fn test_video_to_webm() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "webm"), Some("ffmpeg"),
-                    "{} -> webm should use ffmpeg", input
-                );
-            }
+        fn test_office_pairs_unchanged() {
+            // No e-book format involved, so LibreOffice keeps these
+            assert_eq!(determine_conversion_tool("docx", "pdf"), Some("libreoffice"));
+            assert_eq!(ebook_conversion_tool("docx", "pdf"), None);
         }
 
         #[test]
-        fn test_video_to_gif() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "gif"), Some("ffmpeg"),
-                    "{} -> gif should use ffmpeg", input
-                );
-            }
+        fn test_unsupported_ebook_pairs() {
+            assert_eq!(ebook_conversion_tool("epub", "epub"), None);
+            assert_eq!(determine_conversion_tool("mp4", "epub"), None);
+            assert_eq!(determine_conversion_tool("epub", "png"), None);
         }
     }
 
     // ==========================================
-    // VIDEO TO AUDIO EXTRACTION TESTS
+    // GHOSTSCRIPT TESTS
     // ==========================================
 
-    mod video_to_audio_conversions {
+    mod ghostscript {
         use super::*;
 
         #[test]
-        fn test_video_to_mp3() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "mp3"), Some("ffmpeg"),
-                    "{} -> mp3 should use ffmpeg", input
-                );
-            }
+        fn test_needs_ghostscript() {
+            assert!(needs_ghostscript("pdf"));
+            assert!(needs_ghostscript("eps"));
+            assert!(!needs_ghostscript("svg"));
+            assert!(!needs_ghostscript("png"));
         }
 
         #[test]
-        fn test_video_to_wav() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "wav"), Some("ffmpeg"),
-                    "{} -> wav should use ffmpeg", input
-                );
-            }
+        fn test_pdf_compress_settings() {
+            assert_eq!(pdf_compress_settings(MIN_COMPRESS_QUALITY), ("/screen", MIN_PDF_COMPRESS_DPI));
+            assert_eq!(pdf_compress_settings(MAX_COMPRESS_QUALITY), ("/printer", MAX_PDF_COMPRESS_DPI));
+            assert_eq!(pdf_compress_settings(60).0, "/ebook");
+            // Out-of-range qualities are clamped
+            assert_eq!(pdf_compress_settings(0), pdf_compress_settings(MIN_COMPRESS_QUALITY));
+            assert_eq!(pdf_compress_settings(100), pdf_compress_settings(MAX_COMPRESS_QUALITY));
         }
 
         #[test]
-        fn test_video_to_flac() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "flac"), Some("ffmpeg"),
-                    "{} -> flac should use ffmpeg", input
-                );
-            }
+        fn test_pdf_compress_dpi_increases_with_quality() {
+            let dpis: Vec<u32> = (MIN_COMPRESS_QUALITY..=MAX_COMPRESS_QUALITY)
+                .map(|quality| pdf_compress_settings(quality).1)
+                .collect();
+            assert!(dpis.windows(2).all(|pair| pair[0] <= pair[1]));
         }
 
         #[test]
-        fn test_video_to_ogg() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "ogg"), Some("ffmpeg"),
-                    "{} -> ogg should use ffmpeg", input
-                );
-            }
+        fn test_pdf_compress_args() {
+            let args = pdf_compress_args("in.pdf", "out.pdf", MIN_COMPRESS_QUALITY);
+            assert_eq!(args[0], "-sDEVICE=pdfwrite");
+            assert!(args.contains(&"-dPDFSETTINGS=/screen".to_string()));
+            assert!(args.contains(&"-dColorImageResolution=72".to_string()));
+            assert!(args.contains(&"-dMonoImageResolution=144".to_string()));
+            // The output option comes before the input, which is last
+            assert_eq!(&args[args.len() - 2..], ["-sOutputFile=out.pdf", "in.pdf"]);
         }
 
         #[test]
-        fn test_video_to_m4a() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "m4a"), Some("ffmpeg"),
-                    "{} -> m4a should use ffmpeg", input
-                );
-            }
+        fn test_pdf_compress_escapes_percent() {
+            let args = pdf_compress_args("100% done.pdf", "100% done-compressed.pdf", 80);
+            assert!(args.contains(&"-sOutputFile=100%% done-compressed.pdf".to_string()));
+            assert_eq!(args.last().unwrap(), "100% done.pdf");
         }
 
         #[test]
-        fn test_video_to_aac() {
-            for input in VIDEO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "aac"), Some("ffmpeg"),
-                    "{} -> aac should use ffmpeg", input
-                );
-            }
+        fn test_pdf_merge_args() {
+            let inputs = vec!["part-001.pdf".to_string(), "/docs/b.pdf".to_string()];
+            let args = pdf_merge_args(&inputs, "/out/a_merged 100%.pdf");
+            assert!(args.contains(&"-dAutoRotatePages=/None".to_string()));
+            assert_eq!(&args[args.len() - 3..], ["-sOutputFile=/out/a_merged 100%%.pdf", "part-001.pdf", "/docs/b.pdf"]);
+        }
+
+        #[test]
+        fn test_latest_ghostscript_dir() {
+            assert_eq!(latest_ghostscript_dir(["gs9.56.1", "gs10.04.0", "gs10.02.1"]), Some("gs10.04.0"));
+            assert_eq!(latest_ghostscript_dir(["fonts", "gs10.03.0"]), Some("gs10.03.0"));
+            assert_eq!(latest_ghostscript_dir(["fonts", "gsview"]), None);
+        }
+
+        #[test]
+        fn test_ghostscript_assets() {
+            assert!(is_ghostscript_asset("gs10040w64.exe", "windows"));
+            assert!(!is_ghostscript_asset("gs10040w32.exe", "windows"));
+            assert!(is_ghostscript_asset("ghostscript-10.04.0-linux-x86_64.tgz", "linux"));
+            assert!(!is_ghostscript_asset("ghostscript-10.04.0.tar.gz", "linux"));
+            assert!(!is_ghostscript_asset("gs10040w64.exe", "macos"));
+        }
+
+        #[test]
+        fn test_ghostscript_display_name() {
+            assert_eq!(tool_display_name("ghostscript"), "Ghostscript");
         }
     }
 
     // ==========================================
-    // AUDIO CONVERSION TESTS
+    // SVG RASTERIZATION TESTS
     // ==========================================
 
-    mod audio_conversions {
+    mod svg_rasterization {
         use super::*;
 
         #[test]
-        fn test_all_audio_inputs_recognized() {
-            for format in AUDIO_INPUTS {
-                assert!(
-                    is_audio_format(format),
-                    "Audio format {} should be recognized",
-                    format
-                );
-            }
+        fn test_prefers_svg_renderer_for_raster_outputs() {
+            assert!(prefers_svg_renderer("svg", "png"));
+            assert!(prefers_svg_renderer("svgz", "jpg"));
+            assert!(prefers_svg_renderer("svg", "webp"));
         }
 
         #[test]
-        fn test_all_audio_to_audio_conversions() {
-            let audio_outputs = ["mp3", "wav", "flac", "ogg", "m4a", "aac"];
-            
-            for input in AUDIO_INPUTS {
-                for output in &audio_outputs {
-                    let result = determine_conversion_tool(input, output);
-                    assert_eq!(
-                        result, Some("ffmpeg"),
-                        "Audio conversion {} -> {} should use ffmpeg",
-                        input, output
-                    );
-                }
-            }
+        fn test_svg_renderer_skips_vector_outputs() {
+            assert!(!prefers_svg_renderer("svg", "pdf"));
+            assert!(!prefers_svg_renderer("svg", "svgz"));
+            assert!(!prefers_svg_renderer("svgz", "svg"));
+            assert!(!prefers_svg_renderer("png", "jpg"));
         }
 
         #[test]
-        fn test_audio_to_mp3() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "mp3"), Some("ffmpeg"),
-                    "{} -> mp3 should use ffmpeg", input
-                );
-            }
+        fn test_svg_raster_size_defaults_to_svg_size() {
+            assert_eq!(svg_raster_size(200.0, 100.0, None, None, None), (200, 100));
         }
 
         #[test]
-        fn test_audio_to_wav() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "wav"), Some("ffmpeg"),
-                    "{} -> wav should use ffmpeg", input
-                );
-            }
+        fn test_svg_raster_size_scales_with_dpi() {
+            assert_eq!(svg_raster_size(200.0, 100.0, None, None, Some(192.0)), (400, 200));
+            // Nonsense DPI falls back to the SVG's own size
+            assert_eq!(svg_raster_size(200.0, 100.0, None, None, Some(0.0)), (200, 100));
         }
 
         #[test]
-        fn test_audio_to_flac() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "flac"), Some("ffmpeg"),
-                    "{} -> flac should use ffmpeg", input
-                );
-            }
+        fn test_svg_raster_size_keeps_aspect_ratio() {
+            assert_eq!(svg_raster_size(200.0, 100.0, Some(1000), None, None), (1000, 500));
+            assert_eq!(svg_raster_size(200.0, 100.0, None, Some(50), None), (100, 50));
         }
 
         #[test]
-        fn test_audio_to_ogg() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "ogg"), Some("ffmpeg"),
-                    "{} -> ogg should use ffmpeg", input
-                );
-            }
+        fn test_svg_raster_size_explicit_dimensions_win() {
+            assert_eq!(svg_raster_size(200.0, 100.0, Some(64), Some(64), Some(300.0)), (64, 64));
         }
 
         #[test]
-        fn test_audio_to_m4a() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "m4a"), Some("ffmpeg"),
-                    "{} -> m4a should use ffmpeg", input
-                );
-            }
+        fn test_svg_raster_size_is_clamped() {
+            assert_eq!(svg_raster_size(200.0, 100.0, Some(100_000), Some(100), None), (16384, 16));
+            assert_eq!(svg_raster_size(0.0, 0.0, None, None, None), (1, 1));
+        }
+        
+        #[test]
+        fn test_svg_raster_size_keeps_aspect_ratio_when_clamped() {
+            let (width, height) = svg_raster_size(200.0, 100.0, Some(100_000), None, None);
+            assert!(width <= MAX_RASTER_DIMENSION && height <= MAX_RASTER_DIMENSION);
+            assert!(width as u64 * height as u64 <= MAX_RASTER_PIXELS);
+            assert!((width as f64 / height as f64 - 2.0).abs() < 0.01);
+            
+            let (width, height) = svg_raster_size(100.0, 100.0, None, None, Some(96_000.0));
+            assert_eq!(width, height);
+            assert!(width as u64 * height as u64 <= MAX_RASTER_PIXELS);
         }
+    }
+
+    // ==========================================
+    // BUILT-IN CONVERTER TESTS
+    // ==========================================
+
+    mod builtin_converter {
+        use super::*;
 
         #[test]
-        fn test_audio_to_aac() {
-            for input in AUDIO_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "aac"), Some("ffmpeg"),
-                    "{} -> aac should use ffmpeg", input
-                );
-            }
+        fn test_supports_builtin_conversion() {
+            assert!(supports_builtin_conversion("png", "jpg"));
+            assert!(supports_builtin_conversion("tif", "webp"));
+            assert!(!supports_builtin_conversion("heic", "jpg"));
+            assert!(!supports_builtin_conversion("png", "avif"));
+            // Animated pairs keep every frame, which needs ImageMagick or FFmpeg
+            assert!(!supports_builtin_conversion("gif", "webp"));
+        }
+
+        #[test]
+        fn test_builtin_fallback_without_tools() {
+            assert_eq!(builtin_fallback(Some("imagemagick"), "png", "jpg", |_| false), Some(BUILTIN_TOOL));
+            assert_eq!(builtin_fallback(Some("ffmpeg"), "bmp", "png", |_| false), Some(BUILTIN_TOOL));
+        }
+
+        #[test]
+        fn test_builtin_fallback_prefers_installed_tools() {
+            assert_eq!(builtin_fallback(Some("imagemagick"), "png", "jpg", |_| true), Some("imagemagick"));
+            // FFmpeg stands in for ImageMagick when only it is installed
+            assert_eq!(builtin_fallback(Some("imagemagick"), "png", "jpg", |tool| tool == "ffmpeg"), Some("imagemagick"));
+        }
+
+        #[test]
+        fn test_builtin_fallback_leaves_other_pairs() {
+            assert_eq!(builtin_fallback(Some("imagemagick"), "png", "heic", |_| false), Some("imagemagick"));
+            assert_eq!(builtin_fallback(Some("ffmpeg"), "mp4", "mp3", |_| false), Some("ffmpeg"));
+            assert_eq!(builtin_fallback(Some("rename"), "jpg", "jpeg", |_| false), Some("rename"));
+            assert_eq!(builtin_fallback(None, "png", "jpg", |_| false), None);
+        }
+
+        #[test]
+        fn test_blend_over_white() {
+            assert_eq!(blend_over_white(0, 255), 0);
+            assert_eq!(blend_over_white(0, 0), 255);
+            assert_eq!(blend_over_white(0, 128), 127);
+            assert_eq!(blend_over_white(200, 0), 255);
         }
     }
 
     // ==========================================
-    // IMAGE CONVERSION TESTS
+    // BATCH CONVERSION TESTS
     // ==========================================
 
-    mod image_conversions {
+    mod batch {
         use super::*;
 
-        // Common image formats that should always work
-        const COMMON_IMAGE_INPUTS: &[&str] = &[
-            "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"
-        ];
+        fn paths(names: &[&str]) -> Vec<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        }
 
-        const COMMON_IMAGE_OUTPUTS: &[&str] = &[
-            "jpg", "png", "gif", "bmp", "tiff", "webp"
-        ];
+        #[test]
+        fn test_can_mogrify_still_images() {
+            let inputs = paths(&["/photos/a.png", "/photos/b.jpg", "/photos/c.tiff"]);
+            assert!(can_mogrify_batch(&inputs, "webp", &ConversionSettings::default()));
+        }
 
         #[test]
-        fn test_common_image_inputs_recognized() {
-            for format in COMMON_IMAGE_INPUTS {
-                assert!(
-                    is_image_format(format),
-                    "Image format {} should be recognized",
-                    format
-                );
-            }
+        fn test_cannot_mogrify_single_file() {
+            assert!(!can_mogrify_batch(&paths(&["/photos/a.png"]), "webp", &ConversionSettings::default()));
         }
 
         #[test]
-        fn test_all_image_inputs_recognized() {
-            for format in IMAGE_INPUTS {
-                assert!(
-                    is_image_format(format),
-                    "Image format {} should be recognized",
-                    format
-                );
-            }
+        fn test_cannot_mogrify_colliding_stems() {
+            // Both would be written as a.webp
+            let inputs = paths(&["/photos/a.png", "/other/A.jpg"]);
+            assert!(!can_mogrify_batch(&inputs, "webp", &ConversionSettings::default()));
         }
 
         #[test]
-        fn test_common_image_conversions() {
-            for input in COMMON_IMAGE_INPUTS {
-                for output in COMMON_IMAGE_OUTPUTS {
-                    let result = determine_conversion_tool(input, output);
-                    assert!(
-                        result.is_some(),
-                        "Image conversion {} -> {} should have a tool",
-                        input, output
-                    );
-                }
-            }
+        fn test_cannot_mogrify_special_inputs() {
+            let settings = ConversionSettings::default();
+            assert!(!can_mogrify_batch(&paths(&["/a.png", "/b.gif"]), "png", &settings));
+            assert!(!can_mogrify_batch(&paths(&["/a.png", "/b.pdf"]), "png", &settings));
+            assert!(!can_mogrify_batch(&paths(&["/a.png", "/b.svg"]), "png", &settings));
+            assert!(!can_mogrify_batch(&paths(&["/a.png", "/b[1].png"]), "jpg", &settings));
         }
 
         #[test]
-        fn test_image_to_jpg() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "jpg" && *input != "jpeg" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "jpg"), Some("imagemagick"),
-                        "{} -> jpg should use imagemagick", input
-                    );
-                }
-            }
+        fn test_cannot_mogrify_with_per_file_settings() {
+            let inputs = paths(&["/a.png", "/b.png"]);
+            let templated = ConversionSettings { name_template: Some("{name}-{counter}".to_string()), ..Default::default() };
+            assert!(!can_mogrify_batch(&inputs, "jpg", &templated));
+            let preserved = ConversionSettings { preserve_metadata: true, ..Default::default() };
+            assert!(!can_mogrify_batch(&inputs, "jpg", &preserved));
         }
 
         #[test]
-        fn test_image_to_png() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "png" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "png"), Some("imagemagick"),
-                        "{} -> png should use imagemagick", input
-                    );
-                }
-            }
+        fn test_cannot_mogrify_with_per_output_settings() {
+            let inputs = paths(&["/a.png", "/b.png"]);
+            let checksum = ConversionSettings { checksum: true, ..Default::default() };
+            assert!(!can_mogrify_batch(&inputs, "jpg", &checksum));
+            let checksum_file = ConversionSettings { write_checksum_file: true, ..Default::default() };
+            assert!(!can_mogrify_batch(&inputs, "jpg", &checksum_file));
+            let sidecar = ConversionSettings { write_sidecar: true, ..Default::default() };
+            assert!(!can_mogrify_batch(&inputs, "jpg", &sidecar));
         }
 
         #[test]
-        fn test_image_to_webp() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "webp" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "webp"), Some("imagemagick"),
-                        "{} -> webp should use imagemagick", input
-                    );
-                }
-            }
+        fn test_mogrify_args() {
+            let args = mogrify_args("/out", "jpg", &ConversionSettings::default(), Some("-resize 50%"), &paths(&["a.png", "b.png"]));
+            assert_eq!(&args[..5], ["mogrify", "-path", "/out", "-format", "jpg"]);
+            assert!(args.windows(3).any(|w| w == ["-background", "white", "-flatten"]));
+            assert!(args.windows(2).any(|w| w == ["-quality", "90"]));
+            // Advanced options come right before the files
+            assert_eq!(&args[args.len() - 4..], ["-resize", "50%", "a.png", "b.png"]);
         }
 
         #[test]
-        fn test_image_to_gif() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "gif" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "gif"), Some("imagemagick"),
-                        "{} -> gif should use imagemagick", input
-                    );
-                }
-            }
+        fn test_mogrify_args_keep_alpha_for_png() {
+            let args = mogrify_args("/out", "png", &ConversionSettings::default(), None, &paths(&["a.webp"]));
+            assert!(!args.contains(&"-flatten".to_string()));
         }
 
         #[test]
-        fn test_image_to_bmp() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "bmp" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "bmp"), Some("imagemagick"),
-                        "{} -> bmp should use imagemagick", input
-                    );
-                }
-            }
+        fn test_mogrify_chunk_size() {
+            assert_eq!(mogrify_chunk_size(10, 4), 3);
+            assert_eq!(mogrify_chunk_size(10_000, 4), MAX_MOGRIFY_FILES);
+            assert_eq!(mogrify_chunk_size(0, 0), 1);
         }
 
         #[test]
-        fn test_image_to_tiff() {
-            for input in COMMON_IMAGE_INPUTS {
-                if *input != "tiff" {
-                    assert_eq!(
-                        determine_conversion_tool(input, "tiff"), Some("imagemagick"),
-                        "{} -> tiff should use imagemagick", input
-                    );
-                }
-            }
+        fn test_imagemagick_format_ops() {
+            assert_eq!(imagemagick_format_ops("jpg"), vec![MagickOp::Quality(90)]);
+            assert_eq!(imagemagick_format_ops("pdf"), vec![MagickOp::Compress("jpeg".to_string()), MagickOp::Density(300)]);
+            assert!(imagemagick_format_ops("png").is_empty());
+        }
+
+        #[test]
+        fn test_imagemagick_still_command_order() {
+            let settings = ConversionSettings { strip_metadata: true, ..Default::default() };
+            let args = imagemagick_still_command("in.png", "out.ico", "ico", false, &settings, Some("-quality 80")).args_lossy();
+            assert_eq!(args, vec![
+                "in.png", "-auto-orient", "-resize", "256x256", "-gravity", "center", "-background", "transparent",
+                "-extent", "256x256", "-strip", "-quality", "80", "out.ico",
+            ]);
         }
 
         #[test]
-        fn test_image_to_ico() {
-            for input in COMMON_IMAGE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "ico"), Some("imagemagick"),
-                    "{} -> ico should use imagemagick", input
-                );
-            }
+        fn test_imagemagick_still_command_flattens_before_quality() {
+            let args = imagemagick_still_command("in.png", "out.jpg", "jpg", true, &ConversionSettings::default(), None).args_lossy();
+            assert_eq!(args, vec!["in.png", "-auto-orient", "-background", "white", "-flatten", "-quality", "90", "out.jpg"]);
         }
 
         #[test]
-        fn test_image_to_avif() {
-            for input in COMMON_IMAGE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "avif"), Some("imagemagick"),
-                    "{} -> avif should use imagemagick", input
-                );
-            }
+        fn test_keep_orientation_skips_auto_orient() {
+            let settings = ConversionSettings { keep_orientation: true, ..Default::default() };
+            let args = imagemagick_still_command("in.jpg", "out.png", "png", false, &settings, None).args_lossy();
+            assert_eq!(args, vec!["in.jpg", "out.png"]);
+            assert!(mogrify_args("/out", "png", &ConversionSettings::default(), None, &paths(&["a.jpg"])).contains(&"-auto-orient".to_string()));
         }
 
         #[test]
-        fn test_jpg_jpeg_rename() {
-            assert_eq!(determine_conversion_tool("jpg", "jpeg"), Some("rename"));
-            assert_eq!(determine_conversion_tool("jpeg", "jpg"), Some("rename"));
+        fn test_crop_options() {
+            let rect = CropOptions { x: 10, y: 20, width: Some(100), height: Some(50), aspect: Some("1:1".to_string()) };
+            assert_eq!(rect.region(), Ok(Some(CropRegion::Rect { x: 10, y: 20, width: 100, height: 50 })));
+            let aspect = CropOptions { aspect: Some(" 16:9 ".to_string()), ..Default::default() };
+            assert_eq!(aspect.region(), Ok(Some(CropRegion::Aspect { width: 16, height: 9 })));
+            assert_eq!(CropOptions::default().region(), Ok(None));
+            assert!(CropOptions { width: Some(100), ..Default::default() }.region().is_err());
+            assert!(CropOptions { aspect: Some("wide".to_string()), ..Default::default() }.region().is_err());
+            assert_eq!(parse_aspect_ratio("9/16"), Some((9, 16)));
+            assert_eq!(parse_aspect_ratio("4x0"), None);
         }
-    }
 
-    // ==========================================
-    // HEIC/HEIF CONVERSION TESTS
-    // ==========================================
+        #[test]
+        fn test_crop_rect_within() {
+            // 16:9 out of a 4:3 photo keeps the full width
+            assert_eq!(CropRegion::Aspect { width: 16, height: 9 }.rect_within(4000, 3000), (0, 375, 4000, 2250));
+            assert_eq!(CropRegion::Aspect { width: 1, height: 1 }.rect_within(1920, 1080), (420, 0, 1080, 1080));
+            // Rectangles reaching past the edge are cut at it
+            assert_eq!(CropRegion::Rect { x: 900, y: 0, width: 500, height: 500 }.rect_within(1000, 400), (900, 0, 100, 400));
+        }
 
-    mod heic_conversions {
-        use super::*;
+        #[test]
+        fn test_crop_in_commands() {
+            let settings = ConversionSettings {
+                crop: Some(CropOptions { aspect: Some("4:5".to_string()), ..Default::default() }),
+                ..Default::default()
+            };
+            let args = imagemagick_still_command("in.jpg", "out.jpg", "jpg", false, &settings, None).args_lossy();
+            assert_eq!(args[..7], ["in.jpg", "-auto-orient", "-gravity", "center", "-crop", "4:5", "+repage"]);
 
-        const HEIC_INPUTS: &[&str] = &["jpg", "jpeg", "png", "bmp", "tiff", "webp", "gif"];
+            let settings = ConversionSettings {
+                crop: Some(CropOptions { x: 0, y: 10, width: Some(640), height: Some(360), aspect: None }),
+                frame_rate: Some(10.0),
+                ..Default::default()
+            };
+            let args = ffmpeg_animation_args("webp", &settings);
+            assert_eq!(args[..2], ["-vf", "crop=640:360:0:10,fps=10"]);
+        }
 
         #[test]
-        fn test_image_to_heic() {
-            for input in HEIC_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "heic"), Some("imagemagick"),
-                    "{} -> heic should use imagemagick", input
-                );
-            }
+        fn test_watermark_validation() {
+            let text = Watermark { text: Some("© Studio".to_string()), ..Default::default() };
+            assert_eq!(text.validate(), Ok(()));
+            assert!(Watermark::default().validate().is_err());
+            assert!(Watermark { image_path: Some("logo.png".to_string()), ..text.clone() }.validate().is_err());
+            assert!(Watermark { opacity: 1.5, ..text.clone() }.validate().is_err());
+            assert!(Watermark { scale: Some(0.0), ..text.clone() }.validate().is_err());
+            assert!(Watermark { text: Some("  ".to_string()), image_path: Some(" ".to_string()), ..Default::default() }.validate().is_err());
         }
 
         #[test]
-        fn test_image_to_heif() {
-            for input in HEIC_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "heif"), Some("imagemagick"),
-                    "{} -> heif should use imagemagick", input
-                );
-            }
+        fn test_watermark_origin() {
+            let watermark = Watermark { image_path: Some("logo.png".to_string()), ..Default::default() };
+            assert_eq!(watermark.origin(1000, 500, 200, 100), (785, 385));
+            let centered = Watermark { position: WatermarkPosition::Center, ..watermark.clone() };
+            assert_eq!(centered.origin(1000, 500, 200, 100), (400, 200));
+            // A watermark bigger than the frame starts at its corner
+            assert_eq!(Watermark { position: WatermarkPosition::TopLeft, ..watermark }.origin(100, 100, 300, 50), (0, 3));
         }
 
         #[test]
-        fn test_heic_to_common_formats() {
-            let outputs = ["jpg", "png", "bmp", "tiff", "webp"];
-            for output in &outputs {
-                assert_eq!(
-                    determine_conversion_tool("heic", output), Some("imagemagick"),
-                    "heic -> {} should use imagemagick", output
-                );
-            }
+        fn test_watermark_canvas() {
+            let settings = ConversionSettings::default();
+            // Orientation 6 is stored sideways
+            assert_eq!(watermark_canvas(4000, 3000, 6, &settings), (3000, 4000));
+            assert_eq!(watermark_canvas(4000, 3000, 6, &ConversionSettings { keep_orientation: true, ..Default::default() }), (4000, 3000));
+            let cropped = ConversionSettings {
+                crop: Some(CropOptions { aspect: Some("1:1".to_string()), ..Default::default() }),
+                ..Default::default()
+            };
+            assert_eq!(watermark_canvas(1920, 1080, 1, &cropped), (1080, 1080));
         }
 
         #[test]
-        fn test_heif_to_common_formats() {
-            let outputs = ["jpg", "png", "bmp", "tiff", "webp"];
-            for output in &outputs {
-                assert_eq!(
-                    determine_conversion_tool("heif", output), Some("imagemagick"),
-                    "heif -> {} should use imagemagick", output
-                );
-            }
+        fn test_imagemagick_watermark_ops() {
+            let logo = Watermark { image_path: Some("/logos/logo.png".to_string()), opacity: 0.4, ..Default::default() };
+            assert_eq!(imagemagick_watermark_ops(&logo, Some("/logos/logo.png"), (2000, 1000)), vec![MagickOp::Composite {
+                overlay: "/logos/logo.png".to_string(),
+                width: 400,
+                opacity: 0.4,
+                gravity: "southeast".to_string(),
+                offset: 30,
+            }]);
+            let text = Watermark { text: Some("Draft".to_string()), position: WatermarkPosition::Top, ..Default::default() };
+            assert_eq!(imagemagick_watermark_ops(&text, None, (2000, 1000)), vec![MagickOp::Annotate {
+                text: "Draft".to_string(),
+                point_size: 50,
+                opacity: 0.5,
+                gravity: "north".to_string(),
+                offset: 30,
+            }]);
         }
-    }
 
-    // ==========================================
-    // PROFESSIONAL IMAGE FORMAT TESTS
-    // ==========================================
+        #[test]
+        fn test_ffmpeg_watermarks() {
+            let text = Watermark { text: Some("Draft".to_string()), position: WatermarkPosition::TopLeft, scale: Some(0.1), ..Default::default() };
+            assert_eq!(
+                ffmpeg_text_watermark(&text, "Draft", None).render(),
+                "drawtext=text=Draft:expansion=none:x='min(w,h)*0.03':y='min(w,h)*0.03':fontsize=h*0.1:fontcolor=white@0.5:shadowcolor=black@0.5:shadowx=2:shadowy=2"
+            );
 
-    mod professional_image_conversions {
-        use super::*;
+            let logo = Watermark { image_path: Some("logo.png".to_string()), position: WatermarkPosition::Center, ..Default::default() };
+            let graph = ffmpeg_image_watermark(FilterGraph::new(), "in", Vec::new(), &logo, "input-0.png", (1920, 1080), &[]);
+            assert_eq!(
+                graph.render(),
+                "movie=input-0.png,scale=384:-1,format=rgba,colorchannelmixer=aa=0.5[watermark];\
+                 [in][watermark]overlay=x=(main_w-overlay_w)/2:y=(main_h-overlay_h)/2"
+            );
+            let cropped = ffmpeg_image_watermark(FilterGraph::new(), "in", vec![Filter::crop_to_aspect(1, 1)], &logo, "input-0.png", (1080, 1080), &["out"]);
+            assert!(cropped.render().starts_with("[in]crop="));
+            assert!(cropped.render().ends_with("[base][watermark]overlay=x=(main_w-overlay_w)/2:y=(main_h-overlay_h)/2[out]"));
+        }
 
         #[test]
-        fn test_tga_conversions() {
-            assert_eq!(determine_conversion_tool("tga", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("tga", "jpg"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "tga"), Some("imagemagick"));
+        fn test_watermark_rules_out_mogrify() {
+            let settings = ConversionSettings {
+                watermark: Some(Watermark { text: Some("Draft".to_string()), ..Default::default() }),
+                ..Default::default()
+            };
+            assert!(!can_mogrify_batch(&paths(&["/a.png", "/b.png"]), "jpg", &settings));
         }
 
         #[test]
-        fn test_exr_conversions() {
-            assert_eq!(determine_conversion_tool("exr", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("exr", "jpg"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "exr"), Some("imagemagick"));
+        fn test_color_ops() {
+            let settings = ConversionSettings {
+                color: ColorOptions { convert_to_srgb: true, icc_profile: IccProfileMode::Strip, keep_16_bit: true },
+                strip_metadata: true,
+                ..Default::default()
+            };
+            let profile = crate::icc::srgb_profile_path().to_string_lossy().to_string();
+            let args = imagemagick_still_command("in.tif", "out.tif", "tif", false, &settings, None).args_lossy();
+            // Colors are converted before anything else, and before -strip drops the profile
+            assert_eq!(args, vec![
+                "in.tif", "-profile", profile.as_str(), "-auto-orient", "-quality", "100", "+profile", "icc", "-depth", "16", "-strip", "out.tif",
+            ]);
+            // 8-bit formats don't get -depth
+            assert_eq!(imagemagick_color_ops("jpg", &settings).len(), 2);
+            assert!(imagemagick_color_ops("png", &ConversionSettings::default()).is_empty());
+            assert!(!ColorOptions::default().is_active());
         }
 
         #[test]
-        fn test_hdr_conversions() {
-            assert_eq!(determine_conversion_tool("hdr", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("hdr", "jpg"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "hdr"), Some("imagemagick"));
+        fn test_ffmpeg_color_args() {
+            let deep = ConversionSettings { color: ColorOptions { keep_16_bit: true, ..Default::default() }, ..Default::default() };
+            assert_eq!(ffmpeg_color_args("png", true, &deep), vec!["-pix_fmt", "rgba64be"]);
+            assert_eq!(ffmpeg_color_args("tiff", false, &deep), vec!["-pix_fmt", "rgb48le"]);
+            assert!(ffmpeg_color_args("jpg", false, &deep).is_empty());
+            assert!(ffmpeg_color_args("png", false, &ConversionSettings::default()).is_empty());
         }
 
         #[test]
-        fn test_psd_conversions() {
-            assert_eq!(determine_conversion_tool("psd", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("psd", "jpg"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "psd"), Some("imagemagick"));
+        fn test_raw_options() {
+            assert_eq!(RawOptions::default().validate(), Ok(()));
+            assert!(RawOptions { exposure: 4.0, ..Default::default() }.validate().is_err());
+            assert!(RawOptions { bit_depth: 12, ..Default::default() }.validate().is_err());
+            assert!(is_camera_raw("cr3"));
+            assert!(!is_camera_raw("jpg"));
+            // Developed one by one, by LibRaw when it's installed
+            let photos = ["a.nef".to_string(), "b.nef".to_string()];
+            assert!(!can_mogrify_batch(&photos, "jpg", &ConversionSettings::default()));
         }
 
         #[test]
-        fn test_jpeg2000_conversions() {
-            assert_eq!(determine_conversion_tool("j2k", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("jp2", "png"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "j2k"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("png", "jp2"), Some("imagemagick"));
+        fn test_libraw_args() {
+            assert_eq!(
+                libraw_args("/photos/a.nef", "/tmp/job/developed.tiff", &RawOptions::default()),
+                vec!["-w", "-q", "3", "-o", "1", "-T", "-Z", "/tmp/job/developed.tiff", "/photos/a.nef"]
+            );
+            let brighter = RawOptions { white_balance: RawWhiteBalance::Auto, exposure: 1.0, bit_depth: 16, fast_demosaic: true };
+            assert_eq!(
+                libraw_args("a.cr2", "b.tiff", &brighter),
+                vec!["-a", "-q", "0", "-aexpo", "2.000", "1", "-6", "-o", "1", "-T", "-Z", "b.tiff", "a.cr2"]
+            );
         }
-    }
-
-    // ==========================================
-    // X WINDOW SYSTEM FORMAT TESTS
-    // ==========================================
-
-    mod x_window_conversions {
-        use super::*;
 
         #[test]
-        fn test_xbm_conversions() {
-            assert_eq!(determine_conversion_tool("png", "xbm"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("jpg", "xbm"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("xbm", "png"), Some("imagemagick"));
+        fn test_imagemagick_raw_ops() {
+            let mut settings = ConversionSettings::default();
+            settings.raw = RawOptions { bit_depth: 16, fast_demosaic: true, ..Default::default() };
+            let args = imagemagick_still_command("in.dng", "out.tif", "tif", false, &settings, None)
+                .ops(imagemagick_raw_ops("tif", &settings))
+                .args_lossy();
+            assert_eq!(args, vec![
+                "-define", "dng:use-camera-wb=true", "-define", "dng:interpolation-quality=0", "in.dng",
+                "-auto-orient", "-quality", "100", "-depth", "16", "out.tif",
+            ]);
+            assert_eq!(imagemagick_raw_ops("jpg", &settings).len(), 2);
+            settings.color.keep_16_bit = true;
+            assert_eq!(imagemagick_raw_ops("tif", &settings).len(), 2);
         }
 
         #[test]
-        fn test_xpm_conversions() {
-            assert_eq!(determine_conversion_tool("png", "xpm"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("jpg", "xpm"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("xpm", "png"), Some("imagemagick"));
+        fn test_thumbnail_data_url() {
+            assert_eq!(thumbnail_data_url("png", b"abc"), "data:image/png;base64,YWJj");
+            assert_eq!(thumbnail_mime_type("heic"), "image/jpeg");
         }
 
         #[test]
-        fn test_xwd_conversions() {
-            assert_eq!(determine_conversion_tool("png", "xwd"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("jpg", "xwd"), Some("imagemagick"));
-            assert_eq!(determine_conversion_tool("xwd", "png"), Some("imagemagick"));
-        }
-    }
+        fn test_hover_preview() {
+            assert_eq!(hover_preview_start(Some(60.0)), 6.0);
+            // Just over two seconds: starts early enough to play the whole clip
+            assert!((hover_preview_start(Some(2.2)) - 0.2).abs() < 1e-9);
+            assert_eq!(hover_preview_start(Some(1.5)), 0.0);
+            assert_eq!(hover_preview_start(None), 0.0);
 
-    // ==========================================
-    // RAW IMAGE FORMAT TESTS
-    // ==========================================
+            let name = hover_preview_file_name("/videos/clip.mp4", 1024, 1_700_000_000);
+            assert!(name.starts_with("hover-") && name.ends_with(".webp"));
+            assert_ne!(name, hover_preview_file_name("/videos/clip.mp4", 1024, 1_700_000_001));
 
-    mod raw_image_conversions {
-        use super::*;
+            let args = ffmpeg_hover_preview_args("clip.mp4", "hover.webp", 6.0);
+            assert_eq!(&args[..4], ["-ss", "6.00", "-t", "2"]);
+            assert!(args.contains(&"fps=12,scale=-2:'min(240,ih)'".to_string()));
+        }
 
-        const RAW_FORMATS: &[&str] = &[
-            "arw", "cr2", "cr3", "dng", "nef", "orf", "raf", "raw", "rw2"
-        ];
+        #[test]
+        fn test_text_snippet() {
+            assert_eq!(text_snippet("  INVOICE\n\n No. 1042 \x0c", 200), Some("INVOICE No. 1042".to_string()));
+            assert_eq!(text_snippet(" \n\x0c", 200), None);
+            assert_eq!(text_snippet("Dear customer, thank you", 16), Some("Dear customer,…".to_string()));
+            assert_eq!(text_snippet("Überweisungsträger", 5), Some("Überw…".to_string()));
+            assert!(tesseract_reads("tiff"));
+            assert!(!tesseract_reads("pdf"));
+            assert_eq!(ocr_raster_args("scan.pdf", "page.png")[2], "scan.pdf[0]");
+        }
 
         #[test]
-        fn test_raw_to_jpg() {
-            for raw in RAW_FORMATS {
-                assert_eq!(
-                    determine_conversion_tool(raw, "jpg"), Some("imagemagick"),
-                    "{} -> jpg should use imagemagick", raw
-                );
-            }
+        fn test_transparency_detection() {
+            assert!(identify_channels_have_alpha("srgba"));
+            assert!(!identify_channels_have_alpha("srgb"));
+            assert!(identify_channels_have_alpha("graya"));
+            assert!(identify_channels_have_alpha("cmyka 5.0"));
+            assert!(!identify_channels_have_alpha("gray"));
+            assert!(!identify_channels_have_alpha("gray 1.0"));
+            assert!(!identify_channels_have_alpha("lab"));
+            assert!(!identify_channels_have_alpha(""));
+            assert!(ffmpeg_stream_has_alpha("Stream #0:0: Video: png, rgba(pc), 640x480"));
+            assert!(ffmpeg_stream_has_alpha("Stream #0:0: Video: vp9, yuva420p(tv), 1920x1080"));
+            assert!(!ffmpeg_stream_has_alpha("Stream #0:0: Video: mjpeg, yuvj420p(pc), 640x480"));
         }
 
         #[test]
-        fn test_raw_to_png() {
-            for raw in RAW_FORMATS {
-                assert_eq!(
-                    determine_conversion_tool(raw, "png"), Some("imagemagick"),
-                    "{} -> png should use imagemagick", raw
-                );
-            }
+        fn test_parse_magick_read_formats() {
+            let output = "   Format  Mode  Description
+-------------------------------------------------------------------------------
+      GIF* rw+   CompuServe graphics interchange format
+     HEIC* rw+   High Efficiency Image Format (1.17.6)
+      PDF* rw+   Portable Document Format
+                 See https://imagemagick.org/script/formats.php
+     XPS   r--   Microsoft XML Paper Specification
+     EPSI* -w-   Encapsulated PostScript Interchange format
+";
+            assert_eq!(parse_magick_read_formats(output), vec!["GIF", "HEIC", "PDF", "XPS"]);
+            assert!(parse_magick_read_formats("magick: not a list").is_empty());
         }
 
         #[test]
-        fn test_raw_to_tiff() {
-            for raw in RAW_FORMATS {
-                assert_eq!(
-                    determine_conversion_tool(raw, "tiff"), Some("imagemagick"),
-                    "{} -> tiff should use imagemagick", raw
-                );
-            }
+        fn test_parse_magick_7_read_formats() {
+            let output = "   Format  Module    Mode  Description
+-------------------------------------------------------------------------------
+      3FR  DNG       r--   Hasselblad CFV/H3D39II Raw Format (0.21.2)
+      GIF* GIF       rw+   CompuServe graphics interchange format
+     HEIC* HEIC      rw+   High Efficiency Image Format (1.17.6)
+      PDF* PDF       rw+   Portable Document Format
+                           See https://imagemagick.org/script/formats.php
+     EPSI* PS        -w-   Encapsulated PostScript Interchange format
+      XPS  XPS       r--   Microsoft XML Paper Specification
+
+* native blob support
+r read support
+w write support
++ support for multiple images
+";
+            assert_eq!(parse_magick_read_formats(output), vec!["3FR", "GIF", "HEIC", "PDF", "XPS"]);
         }
-    }
 
-    // ==========================================
-    // OFFICE DOCUMENT CONVERSION TESTS
-    // ==========================================
+        #[test]
+        fn test_parse_heic_grid() {
+            let output = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'IMG_0001.HEIC':
+  Stream group #0:0[0x31]: Tile Grid: hevc (Main Still Picture) (hvc1 / 0x31637668), yuv420p(pc, smpte170m), 4032x3024 (default)
+  Stream #0:0[0x1]: Video: hevc (Main Still Picture) (hvc1 / 0x31637668), yuv420p(pc, smpte170m), 512x512, 1 fps, 1 tbr, 1 tbn
+    Side data:
+      displaymatrix: rotation of -90.00 degrees
+";
+            let grid = parse_heic_grid(output).unwrap();
+            assert_eq!(grid, HeicGrid { width: 4032, height: 3024, tile_width: 512, tile_height: 512, rotation: -90 });
+            assert_eq!(grid.tiles(), (8, 6));
 
-    mod office_conversions {
-        use super::*;
+            // Newer phones use other tile sizes
+            let wide_tiles = output.replace("512x512", "1024x768").replace("rotation of -90.00", "rotation of 0.00");
+            let grid = parse_heic_grid(&wide_tiles).unwrap();
+            assert_eq!((grid.tile_width, grid.tile_height, grid.rotation), (1024, 768, 0));
+            assert_eq!(grid.tiles(), (4, 4));
+
+            // A single picture, not tiled
+            assert_eq!(parse_heic_grid("  Stream #0:0[0x1]: Video: hevc (Main 10), yuv420p10le(tv), 1920x1080, 1 fps"), None);
+        }
 
         #[test]
-        fn test_word_to_pdf() {
-            assert_eq!(determine_conversion_tool("doc", "pdf"), Some("libreoffice"));
-            assert_eq!(determine_conversion_tool("docx", "pdf"), Some("libreoffice"));
+        fn test_batch_worker_count_uses_every_core() {
+            assert_eq!(batch_worker_count(8, 100), 8);
         }
 
         #[test]
-        fn test_excel_to_pdf() {
-            assert_eq!(determine_conversion_tool("xls", "pdf"), Some("libreoffice"));
-            assert_eq!(determine_conversion_tool("xlsx", "pdf"), Some("libreoffice"));
+        fn test_batch_worker_count_capped_by_files() {
+            assert_eq!(batch_worker_count(8, 3), 3);
         }
 
         #[test]
-        fn test_powerpoint_to_pdf() {
-            assert_eq!(determine_conversion_tool("ppt", "pdf"), Some("libreoffice"));
-            assert_eq!(determine_conversion_tool("pptx", "pdf"), Some("libreoffice"));
+        fn test_batch_worker_count_at_least_one() {
+            assert_eq!(batch_worker_count(0, 5), 1);
+            assert_eq!(batch_worker_count(4, 0), 1);
         }
 
         #[test]
-        fn test_odt_to_pdf() {
-            assert_eq!(determine_conversion_tool("odt", "pdf"), Some("libreoffice"));
+        fn test_requested_worker_count() {
+            assert_eq!(requested_worker_count(Some(2), 8, 100), 2);
+            assert_eq!(requested_worker_count(Some(2), 8, 1), 1);
+            assert_eq!(requested_worker_count(Some(1000), 8, 100), MAX_BATCH_WORKERS);
         }
 
         #[test]
-        fn test_office_to_html() {
-            for input in OFFICE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "html"), Some("libreoffice"),
-                    "{} -> html should use libreoffice", input
-                );
-            }
+        fn test_batch_wall_time() {
+            assert_eq!(batch_wall_time_ms(&[100, 100, 100, 100], 2), 200);
+            // One long file can't be split across workers
+            assert_eq!(batch_wall_time_ms(&[1000, 10, 10], 4), 1000);
+            assert_eq!(batch_wall_time_ms(&[30, 20, 10], 0), 60);
+            assert_eq!(batch_wall_time_ms(&[], 4), 0);
         }
 
         #[test]
-        fn test_office_to_txt() {
-            for input in OFFICE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "txt"), Some("libreoffice"),
-                    "{} -> txt should use libreoffice", input
-                );
-            }
+        fn test_default_conversion_ms_grows_with_size() {
+            assert_eq!(default_conversion_ms("png", "jpg", 0), 300);
+            assert!(default_conversion_ms("mov", "mp4", 100_000_000) > default_conversion_ms("png", "jpg", 100_000_000));
         }
 
         #[test]
-        fn test_office_to_docx() {
-            for input in OFFICE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "docx"), Some("libreoffice"),
-                    "{} -> docx should use libreoffice", input
-                );
-            }
+        fn test_extension_filter() {
+            let filter = vec![".PNG".to_string(), "webp".to_string()];
+            assert!(matches_extension_filter(Path::new("/a/b.png"), &filter, "jpg"));
+            assert!(matches_extension_filter(Path::new("/a/b.WEBP"), &filter, "jpg"));
+            assert!(!matches_extension_filter(Path::new("/a/b.gif"), &filter, "jpg"));
+            assert!(!matches_extension_filter(Path::new("/a/b.png"), &filter, "png"));
         }
 
         #[test]
-        fn test_office_to_odt() {
-            for input in OFFICE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "odt"), Some("libreoffice"),
-                    "{} -> odt should use libreoffice", input
-                );
-            }
+        fn test_empty_filter_takes_convertible_files() {
+            assert!(matches_extension_filter(Path::new("/a/b.png"), &[], "jpg"));
+            assert!(!matches_extension_filter(Path::new("/a/notes"), &[], "jpg"));
+            assert!(!matches_extension_filter(Path::new("/a/b.jpg"), &[], "jpg"));
         }
 
         #[test]
-        fn test_office_to_rtf() {
-            for input in OFFICE_INPUTS {
-                assert_eq!(
-                    determine_conversion_tool(input, "rtf"), Some("libreoffice"),
-                    "{} -> rtf should use libreoffice", input
-                );
-            }
+        fn test_mirrored_output_dir() {
+            let root = Path::new("/photos");
+            let out = Path::new("/converted");
+            assert_eq!(mirrored_output_dir(root, Path::new("/photos/2024/june/a.heic"), out), Path::new("/converted/2024/june"));
+            assert_eq!(mirrored_output_dir(root, Path::new("/photos/a.heic"), out), Path::new("/converted"));
+        }
+
+        #[test]
+        fn test_requested_worker_count_defaults_to_cpus() {
+            assert_eq!(requested_worker_count(None, 8, 100), 8);
+            assert_eq!(requested_worker_count(Some(0), 8, 100), 8);
         }
     }
 
@@ -1005,6 +6709,170 @@ mod tests {
         }
     }
 
+    // ==========================================
+    // FORMAT REGISTRY TESTS
+    // ==========================================
+
+    mod format_registry {
+        use super::*;
+
+        fn formats(input: &str) -> Vec<String> {
+            FormatRegistry::builtin().options(input).into_iter().map(|option| option.format).collect()
+        }
+
+        #[test]
+        fn test_builtin_options() {
+            let mp4 = formats("mp4");
+            assert!(mp4.contains(&"mp3".to_string()));
+            assert!(!mp4.contains(&"mp4".to_string()));
+            assert!(formats("jpg").contains(&"jpeg".to_string()));
+            assert!(!formats("doc").contains(&"epub".to_string()));
+            // Behind the pandoc flag, which is off by default
+            assert!(formats("md").is_empty());
+            assert!(formats("xyz").is_empty());
+        }
+
+        #[test]
+        fn test_options_are_routed() {
+            let registry = FormatRegistry::builtin();
+            for input in registry.groups.iter().flat_map(|group| group.inputs.iter()) {
+                for option in registry.options(input) {
+                    assert!(!option.tool.is_empty(), "{} -> {} has no tool", input, option.format);
+                    assert_ne!(option.display_name, UNKNOWN_FORMAT_NAME, "{} has no name", option.format);
+                }
+            }
+        }
+
+        #[test]
+        fn test_matrix() {
+            let output = |matrix: &ConversionMatrix, input: &str, format: &str| {
+                matrix
+                    .categories
+                    .iter()
+                    .flat_map(|category| &category.inputs)
+                    .find(|matrix_input| matrix_input.format == input)
+                    .and_then(|matrix_input| matrix_input.outputs.iter().find(|output| output.option.format == format))
+                    .cloned()
+                    .unwrap()
+            };
+
+            // Nothing installed
+            let matrix = FormatRegistry::builtin().matrix(|_| false);
+            assert_eq!(matrix.categories[0].category, "video");
+            assert_eq!(matrix.tools.get("ffmpeg"), Some(&false));
+            assert_eq!(matrix.tools.get("imagemagick"), Some(&false));
+            assert!(!matrix.tools.contains_key("rename"));
+            assert!(!output(&matrix, "mp4", "mp3").available);
+            assert!(output(&matrix, "jpg", "jpeg").available);
+            assert!(!output(&matrix, "png", "heic").available);
+            // ImageMagick's pair, done by the built-in converter since neither tool is there
+            let png_to_jpg = output(&matrix, "png", "jpg");
+            assert!(png_to_jpg.available);
+            assert_eq!(png_to_jpg.option.tool, BUILTIN_TOOL);
+
+            // FFmpeg only: the built-in converter doesn't stand in, and ImageMagick's pairs are missing
+            let matrix = FormatRegistry::builtin().matrix(|tool| tool == "ffmpeg");
+            assert_eq!(matrix.tools.get("ffmpeg"), Some(&true));
+            assert!(output(&matrix, "mp4", "mp3").available);
+            let png_to_jpg = output(&matrix, "png", "jpg");
+            assert!(!png_to_jpg.available);
+            assert_eq!(png_to_jpg.option.tool, "imagemagick");
+        }
+
+        #[test]
+        fn test_plan_batch() {
+            let inputs: Vec<(String, String)> = [("a.jpg", "jpg"), ("b.mp4", "mp4"), ("c.png", "png"), ("notes.xyz", "xyz")]
+                .iter()
+                .map(|(path, format)| (path.to_string(), format.to_string()))
+                .collect();
+            let plan = FormatRegistry::builtin().plan_batch(&inputs);
+            assert_eq!(plan.unsupported, vec!["notes.xyz".to_string()]);
+            assert_eq!(plan.files.len(), 3);
+
+            // Images and videos meet at animated and still image formats FFmpeg writes
+            let common: Vec<&str> = plan.common_formats.iter().map(|target| target.option.format.as_str()).collect();
+            assert!(common.contains(&"gif"));
+            assert!(!common.contains(&"mp3"));
+            let gif = plan.common_formats.iter().find(|target| target.option.format == "gif").unwrap();
+            assert_eq!(gif.files, 3);
+            assert!(gif.tools.contains(&plan.files[1].tools["gif"]));
+
+            let mp3 = plan.partial_formats.iter().find(|target| target.option.format == "mp3").unwrap();
+            assert_eq!((mp3.files, mp3.tools.as_slice()), (1, ["ffmpeg".to_string()].as_slice()));
+            assert!(plan.partial_formats.windows(2).all(|pair| pair[0].files >= pair[1].files));
+        }
+
+        #[test]
+        fn test_plan_batch_counts_files_already_in_the_format() {
+            let inputs: Vec<(String, String)> = [("a.jpg", "jpg"), ("b.png", "png"), ("c.heic", "heic")]
+                .iter()
+                .map(|(path, format)| (path.to_string(), format.to_string()))
+                .collect();
+            let plan = FormatRegistry::builtin().plan_batch(&inputs);
+            let common: Vec<&str> = plan.common_formats.iter().map(|target| target.option.format.as_str()).collect();
+            assert!(common.contains(&"jpg"));
+            assert!(common.contains(&"png"));
+
+            let jpg = plan.common_formats.iter().find(|target| target.option.format == "jpg").unwrap();
+            assert_eq!((jpg.files, jpg.already), (3, 1));
+            assert_eq!(plan.files[0].already_in, vec!["jpg".to_string()]);
+            assert!(!plan.files[0].tools.contains_key("jpg"));
+            assert_eq!(plan.files[1].already_in, vec!["png".to_string()]);
+            assert!(plan.files[2].already_in.is_empty());
+        }
+
+        #[test]
+        fn test_with_overrides() {
+            let registry = FormatRegistry::builtin()
+                .with_overrides(
+                    r#"{
+                        "formats": { "mp4": { "name": "MPEG-4", "color": "purple", "category": "video" } },
+                        "groups": [{ "id": "notes", "inputs": ["note"], "outputs": [{ "format": "txt", "tool": "builtin" }] }]
+                    }"#,
+                )
+                .unwrap();
+            assert_eq!(registry.format("mp4").unwrap().name, "MPEG-4");
+            assert_eq!(registry.groups.iter().filter(|group| group.id == "video").count(), 1);
+            // Nothing converts notes, so the override's rule isn't offered
+            assert!(registry.options("note").is_empty());
+            assert!(FormatRegistry::builtin().with_overrides("[]").is_err());
+
+            let registry = FormatRegistry::builtin()
+                .with_overrides(
+                    r#"{
+                        "groups": [{ "id": "audio", "inputs": ["wav", "flac"], "outputs": [
+                            { "format": "mp3", "name": "MP3 (small)" },
+                            { "format": "ogg", "tool": "imagemagick" },
+                            { "format": "docx" }
+                        ] }]
+                    }"#,
+                )
+                .unwrap();
+            let wav = registry.options("wav");
+            assert_eq!(wav.len(), 1);
+            assert_eq!((wav[0].format.as_str(), wav[0].tool.as_str(), wav[0].display_name.as_str()), ("mp3", "ffmpeg", "MP3 (small)"));
+        }
+
+        #[test]
+        fn test_options_with_pandoc() {
+            crate::feature_flags::with_flag(crate::feature_flags::PANDOC, true, || {
+                let registry = FormatRegistry::builtin();
+                let doc: Vec<(String, String)> = registry.options("doc").into_iter().map(|option| (option.format, option.tool)).collect();
+                assert!(doc.contains(&("epub".to_string(), "libreoffice".to_string())));
+                assert!(!doc.iter().any(|(format, _)| format == "azw3"));
+                let docx = registry.options("docx");
+                assert!(docx.iter().any(|option| option.format == "epub" && option.tool == "pandoc"));
+                assert!(docx.iter().any(|option| option.format == "txt" && option.tool == "pandoc"));
+                assert!(!registry.options("md").is_empty());
+                for input in registry.groups.iter().flat_map(|group| group.inputs.iter()) {
+                    for option in registry.options(input) {
+                        assert!(!option.tool.is_empty(), "{} -> {} has no tool", input, option.format);
+                    }
+                }
+            });
+        }
+    }
+
     // ==========================================
     // FORMAT DETECTION TESTS
     // ==========================================
@@ -1083,6 +6951,15 @@ mod tests {
             assert!(!is_document_format("mp3"));
             assert!(!is_document_format("png"));
         }
+
+        #[test]
+        fn test_is_supported_input() {
+            for format in ["MOV", "flac", "heic", "docx", "epub", "eml", "vcf", "csv"] {
+                assert!(is_supported_input(format), "{} should be supported", format);
+            }
+            assert!(!is_supported_input("exe"));
+            assert!(!is_supported_input(""));
+        }
     }
 
     // ==========================================
@@ -1190,6 +7067,15 @@ mod tests {
             // Unknown
             assert_eq!(get_format_color("unknown"), "gray");
         }
+
+        #[test]
+        fn test_format_details() {
+            assert_eq!(
+                format_details(".EXR"),
+                FormatDetails { format: "exr".to_string(), display_name: "OpenEXR (HDR)".to_string(), color: "aquamarine".to_string() }
+            );
+            assert_eq!(format_details("xyz").display_name, "Unknown Format");
+        }
     }
 
     // ==========================================
@@ -0,0 +1,357 @@
+//! Email messages - Saved .eml and Outlook .msg files laid out as printable HTML
+//!
+//! A message is read into an `EmailMessage` (headers, one body, attachments), which
+//! `render_html` lays out like a printed email: the header block, the body, and an
+//! appendix listing the attachments. Headless Chromium prints that page to PDF.
+//! Images embedded in the message (`cid:` references) are inlined as data URIs, and the
+//! page forbids scripts and remote content so opening it never contacts a tracker.
+
+use base64::Engine;
+use mail_parser::{Address, MessageParser, MimeHeaders};
+use std::path::Path;
+
+/// Email inputs and the outputs they can be printed to
+pub const EMAIL_INPUTS: &[&str] = &["eml", "msg"];
+pub const EMAIL_OUTPUTS: &[&str] = &["pdf"];
+
+/// Checks if an email file can be converted to the given output format
+pub fn supports_email_conversion(input_ext: &str, output_ext: &str) -> bool {
+    EMAIL_INPUTS.contains(&input_ext) && EMAIL_OUTPUTS.contains(&output_ext)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailBody {
+    Html(String),
+    Text(String),
+}
+
+/// A file attached to a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailAttachment {
+    pub name: String,
+    pub size: u64,
+    /// Content-ID the HTML body refers to it by (`cid:...`), for embedded images
+    pub content_id: Option<String>,
+    pub mime_type: Option<String>,
+    /// Kept only for embedded images, which are inlined into the page
+    pub data: Option<Vec<u8>>,
+}
+
+/// The parts of a message that are printed
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub date: Option<String>,
+    pub body: EmailBody,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// `Name <address>`, or whichever of the two is known
+pub fn format_mailbox(name: Option<&str>, address: Option<&str>) -> String {
+    let name = name.map(str::trim).filter(|name| !name.is_empty());
+    let address = address.map(str::trim).filter(|address| !address.is_empty());
+    match (name, address) {
+        (Some(name), Some(address)) if name != address => format!("{} <{}>", name, address),
+        (Some(name), _) => name.to_string(),
+        (None, Some(address)) => address.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+fn mailboxes(address: Option<&Address>) -> Vec<String> {
+    address
+        .map(|address| {
+            address
+                .iter()
+                .map(|mailbox| format_mailbox(mailbox.name(), mailbox.address()))
+                .filter(|mailbox| !mailbox.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses a MIME message (.eml). The HTML body is preferred; plain text bodies are
+/// converted to HTML by the parser.
+pub fn parse_eml(bytes: &[u8]) -> Result<EmailMessage, String> {
+    let message = MessageParser::default()
+        .parse(bytes)
+        .ok_or("This file isn't a valid email message")?;
+
+    let body = match message.body_html(0) {
+        Some(html) => EmailBody::Html(html.to_string()),
+        None => EmailBody::Text(message.body_text(0).map(|text| text.to_string()).unwrap_or_default()),
+    };
+
+    let attachments = message
+        .attachments()
+        .enumerate()
+        .map(|(index, part)| {
+            let mime_type = part
+                .content_type()
+                .map(|content_type| match content_type.subtype() {
+                    Some(subtype) => format!("{}/{}", content_type.ctype(), subtype),
+                    None => content_type.ctype().to_string(),
+                });
+            let content_id = part
+                .content_id()
+                .map(|id| id.trim_matches(|c| c == '<' || c == '>').to_string());
+            let is_image = mime_type.as_deref().is_some_and(|mime| mime.starts_with("image/"));
+            EmailAttachment {
+                name: part
+                    .attachment_name()
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("attachment-{}", index + 1)),
+                size: part.contents().len() as u64,
+                data: (is_image && content_id.is_some()).then(|| part.contents().to_vec()),
+                content_id,
+                mime_type,
+            }
+        })
+        .collect();
+
+    Ok(EmailMessage {
+        subject: message.subject().unwrap_or_default().to_string(),
+        from: mailboxes(message.from()).join(", "),
+        to: mailboxes(message.to()),
+        cc: mailboxes(message.cc()),
+        date: message.date().map(|date| date.to_rfc822()),
+        body,
+        attachments,
+    })
+}
+
+/// Reads an Outlook message (.msg). Outlook keeps the formatted body as compressed RTF,
+/// so the plain text body is printed.
+pub fn parse_msg(path: &Path) -> Result<EmailMessage, String> {
+    let outlook = msg_parser::Outlook::from_path(path)
+        .map_err(|e| format!("This file isn't a valid Outlook message: {}", e))?;
+    let person = |person: &msg_parser::Person| format_mailbox(Some(&person.name), Some(&person.email));
+
+    Ok(EmailMessage {
+        subject: outlook.subject.clone(),
+        from: person(&outlook.sender),
+        to: outlook.to.iter().map(person).filter(|to| !to.is_empty()).collect(),
+        cc: outlook.cc.iter().map(person).filter(|cc| !cc.is_empty()).collect(),
+        date: Some(outlook.headers.date.clone()).filter(|date| !date.is_empty()),
+        body: EmailBody::Text(outlook.body.clone()),
+        attachments: outlook
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(index, attachment)| EmailAttachment {
+                name: [&attachment.display_name, &attachment.file_name]
+                    .into_iter()
+                    .find(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| format!("attachment-{}", index + 1)),
+                // The payload is hex encoded
+                size: attachment.payload.len() as u64 / 2,
+                content_id: None,
+                mime_type: Some(attachment.mime_tag.clone()).filter(|mime| !mime.is_empty()),
+                data: None,
+            })
+            .collect(),
+    })
+}
+
+/// Parses an email file by its extension
+pub fn read_email(path: &Path) -> Result<EmailMessage, String> {
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "msg" => parse_msg(path),
+        _ => {
+            let bytes = std::fs::read(path).map_err(|e| format!("Failed to read email: {}", e))?;
+            parse_eml(&bytes)
+        }
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Size for the attachment list, e.g. "12 KB"
+pub fn format_attachment_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} bytes", bytes),
+        1024..=1_048_575 => format!("{} KB", (bytes + 512) / 1024),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Blocks scripts and anything loaded from the network; embedded images are data URIs
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src data:; style-src 'unsafe-inline'; font-src data:";
+
+const PAGE_STYLE: &str = "body { font-family: Arial, Helvetica, sans-serif; font-size: 11pt; margin: 0; } \
+    table.email-headers { border-collapse: collapse; margin-bottom: 12px; } \
+    table.email-headers th { text-align: left; vertical-align: top; padding: 2px 12px 2px 0; color: #555; } \
+    h1.email-subject { font-size: 16pt; margin: 0 0 8px; } \
+    pre.email-text { white-space: pre-wrap; font-family: inherit; } \
+    section.email-attachments { page-break-inside: avoid; border-top: 1px solid #ccc; margin-top: 24px; }";
+
+/// Lays a message out as a standalone HTML page for printing
+pub fn render_html(message: &EmailMessage) -> String {
+    let mut body = match &message.body {
+        EmailBody::Html(html) => html.clone(),
+        EmailBody::Text(text) => format!("<pre class=\"email-text\">{}</pre>", escape_html(text)),
+    };
+    // Embedded images are printed where the body shows them, not listed as attachments
+    let mut listed = Vec::new();
+    for attachment in &message.attachments {
+        match (&attachment.content_id, &attachment.data, &attachment.mime_type) {
+            (Some(id), Some(data), Some(mime)) if body.contains(&format!("cid:{}", id)) => {
+                let uri = format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(data));
+                body = body.replace(&format!("cid:{}", id), &uri);
+            }
+            _ => listed.push(attachment),
+        }
+    }
+
+    let mut headers = vec![("From", message.from.clone())];
+    if let Some(date) = &message.date {
+        headers.push(("Date", date.clone()));
+    }
+    headers.push(("To", message.to.join(", ")));
+    if !message.cc.is_empty() {
+        headers.push(("Cc", message.cc.join(", ")));
+    }
+    let header_rows: String = headers
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(label, value)| format!("<tr><th>{}</th><td>{}</td></tr>", label, escape_html(value)))
+        .collect();
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+        <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\
+        <title>{}</title><style>{}</style></head><body>\n\
+        <h1 class=\"email-subject\">{}</h1>\n<table class=\"email-headers\">{}</table>\n<hr>\n<div class=\"email-body\">\n{}\n</div>\n",
+        CONTENT_SECURITY_POLICY,
+        escape_html(&message.subject),
+        PAGE_STYLE,
+        escape_html(&message.subject),
+        header_rows,
+        body
+    );
+    if !listed.is_empty() {
+        page.push_str(&format!("<section class=\"email-attachments\"><h2>Attachments ({})</h2><ul>", listed.len()));
+        for attachment in listed {
+            page.push_str(&format!(
+                "<li>{} ({})</li>",
+                escape_html(&attachment.name),
+                format_attachment_size(attachment.size)
+            ));
+        }
+        page.push_str("</ul></section>\n");
+    }
+    page.push_str("</body></html>\n");
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "From: Ada Lovelace <ada@example.com>\r\n\
+        To: Charles <charles@example.com>, bob@example.com\r\n\
+        Subject: Notes on the engine\r\n\
+        Date: Mon, 7 Oct 2024 09:30:00 +0000\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+        \r\n\
+        --b1\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        See the attached table.\r\n\
+        --b1\r\n\
+        Content-Type: text/csv\r\n\
+        Content-Disposition: attachment; filename=\"table.csv\"\r\n\
+        \r\n\
+        a,b\r\n\
+        --b1--\r\n";
+
+    #[test]
+    fn test_parse_eml() {
+        let message = parse_eml(SAMPLE.as_bytes()).unwrap();
+        assert_eq!(message.subject, "Notes on the engine");
+        assert_eq!(message.from, "Ada Lovelace <ada@example.com>");
+        assert_eq!(message.to, vec!["Charles <charles@example.com>", "bob@example.com"]);
+        assert!(message.date.is_some());
+        assert_eq!(message.attachments.len(), 1);
+        assert_eq!(message.attachments[0].name, "table.csv");
+        assert!(message.attachments[0].data.is_none());
+    }
+
+    #[test]
+    fn test_format_mailbox() {
+        assert_eq!(format_mailbox(Some("Ada"), Some("ada@example.com")), "Ada <ada@example.com>");
+        assert_eq!(format_mailbox(Some("ada@example.com"), Some("ada@example.com")), "ada@example.com");
+        assert_eq!(format_mailbox(Some(" "), Some("ada@example.com")), "ada@example.com");
+        assert_eq!(format_mailbox(None, None), "");
+    }
+
+    fn message(body: EmailBody, attachments: Vec<EmailAttachment>) -> EmailMessage {
+        EmailMessage {
+            subject: "Q3 <draft>".to_string(),
+            from: "Ada <ada@example.com>".to_string(),
+            to: vec!["bob@example.com".to_string()],
+            cc: Vec::new(),
+            date: None,
+            body,
+            attachments,
+        }
+    }
+
+    #[test]
+    fn test_render_escapes_headers_and_text() {
+        let page = render_html(&message(EmailBody::Text("1 < 2".to_string()), Vec::new()));
+        assert!(page.contains("<h1 class=\"email-subject\">Q3 &lt;draft&gt;</h1>"));
+        assert!(page.contains("<tr><th>From</th><td>Ada &lt;ada@example.com&gt;</td></tr>"));
+        assert!(page.contains("<pre class=\"email-text\">1 &lt; 2</pre>"));
+        // Empty headers are left out, and so is the appendix without attachments
+        assert!(!page.contains("<th>Cc</th>"));
+        assert!(!page.contains("Attachments"));
+        assert!(page.contains("default-src 'none'"));
+    }
+
+    #[test]
+    fn test_render_inlines_embedded_images() {
+        let logo = EmailAttachment {
+            name: "logo.png".to_string(),
+            size: 3,
+            content_id: Some("logo@1".to_string()),
+            mime_type: Some("image/png".to_string()),
+            data: Some(vec![1, 2, 3]),
+        };
+        let report = EmailAttachment {
+            name: "report.pdf".to_string(),
+            size: 2048,
+            content_id: None,
+            mime_type: Some("application/pdf".to_string()),
+            data: None,
+        };
+        let page = render_html(&message(EmailBody::Html("<img src=\"cid:logo@1\">".to_string()), vec![logo, report]));
+        assert!(page.contains("<img src=\"data:image/png;base64,AQID\">"));
+        assert!(page.contains("<h2>Attachments (1)</h2><ul><li>report.pdf (2 KB)</li></ul>"));
+    }
+
+    #[test]
+    fn test_format_attachment_size() {
+        assert_eq!(format_attachment_size(900), "900 bytes");
+        assert_eq!(format_attachment_size(1536), "2 KB");
+        assert_eq!(format_attachment_size(5 * 1_048_576), "5.0 MB");
+    }
+
+    #[test]
+    fn test_supports_email_conversion() {
+        assert!(supports_email_conversion("eml", "pdf"));
+        assert!(supports_email_conversion("msg", "pdf"));
+        assert!(!supports_email_conversion("eml", "docx"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Error summaries - Short, plain-language versions of conversion errors
+//!
+//! Errors reach the frontend as the strings the backend built, which often run to several
+//! paragraphs of advice and tool output. `summarize` sorts one into a kind (a stable code
+//! the UI can translate), a one-sentence summary of at most MAX_SUMMARY_CHARS, and the
+//! full text as the detail to show behind a "Details" toggle.
+
+use crate::input_state::FILE_IN_USE_PREFIX;
+use crate::job_log;
+use crate::watchdog::TIMEOUT_PREFIX;
+use serde::{Deserialize, Serialize};
+
+/// Longest summary, so it fits a toast or a batch list row
+pub const MAX_SUMMARY_CHARS: usize = 120;
+
+/// What went wrong, for the UI to pick a translated message and an action
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The input is still downloading or open in another program
+    FileInUse,
+    InputMissing,
+    /// A tool the conversion needs isn't installed
+    ToolMissing,
+    /// The tool is installed but couldn't run
+    ToolFailedToStart,
+    /// The installed tools can't write the output format
+    FormatUnsupported,
+    CodecUnavailable,
+    /// The input has no audio for an audio output
+    NoAudioStream,
+    OutputNotWritable,
+    /// The tool hung or ran past the time limit and was stopped
+    TimedOut,
+    /// The conversion ran but the tool failed
+    ConversionFailed,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ErrorSummary {
+    pub kind: ErrorKind,
+    pub summary: String,
+    /// The whole error, without the job log line
+    pub detail: String,
+    /// The job log of a failed tool run (see job_log)
+    pub log_path: Option<String>,
+}
+
+/// Cuts `text` to `max` characters at a word boundary, ending with "…" when cut
+pub fn truncate_summary(text: &str, max: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut chars = text.chars();
+    let cut: String = chars.by_ref().take(max.saturating_sub(1)).collect();
+    let ends_word = chars.next().is_some_and(char::is_whitespace);
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if !ends_word && space > cut.len() / 2 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches(|c: char| c.is_whitespace() || c == ',' || c == ':' || c == '.'))
+}
+
+/// The first sentence of the first line, which is what most messages lead with
+fn first_sentence(text: &str) -> &str {
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    match line.find(". ") {
+        Some(end) => &line[..=end],
+        None => line,
+    }
+}
+
+/// "Calibre is required for ..." -> "Calibre"
+fn required_tool(text: &str) -> Option<&str> {
+    text.split_once(" is required").map(|(tool, _)| tool.trim()).filter(|tool| !tool.is_empty() && tool.len() < 30)
+}
+
+pub fn summarize(error: &str) -> ErrorSummary {
+    let log_path = job_log::log_path_from_error(error).map(str::to_string);
+    let detail = match error.rsplit_once(job_log::LOG_PATH_PREFIX) {
+        Some((before, _)) if log_path.is_some() => before.trim_end().to_string(),
+        _ => error.trim().to_string(),
+    };
+
+    let (kind, summary) = if let Some(reason) = detail.strip_prefix(FILE_IN_USE_PREFIX) {
+        (ErrorKind::FileInUse, first_sentence(reason).to_string())
+    } else if let Some(reason) = detail.strip_prefix(TIMEOUT_PREFIX) {
+        (ErrorKind::TimedOut, first_sentence(reason).to_string())
+    } else if detail.contains("is required") && (detail.contains("not found") || detail.contains("not installed")) {
+        let tool = required_tool(&detail).unwrap_or("A conversion tool");
+        (ErrorKind::ToolMissing, format!("{} is needed for this. Install it in Settings.", tool))
+    } else if detail.starts_with("Input file not found") || detail.starts_with("File not found") {
+        (ErrorKind::InputMissing, "The file was moved or deleted.".to_string())
+    } else if detail.contains("has no audio stream") {
+        (ErrorKind::NoAudioStream, "This video has no sound to convert.".to_string())
+    } else if detail.contains("failed to start") || detail.contains("killed by macOS") || detail.starts_with("Failed to execute") {
+        (ErrorKind::ToolFailedToStart, "The conversion tool couldn't start.".to_string())
+    } else if detail.contains("format is not supported") || detail.contains("format encoding is not supported") {
+        (ErrorKind::FormatUnsupported, "The installed tools can't save this format.".to_string())
+    } else if detail.contains("required codec is not available") {
+        (ErrorKind::CodecUnavailable, "The installed tools can't encode this format.".to_string())
+    } else if detail.starts_with("Cannot write to the output location") || detail.starts_with("Failed to create output directory") {
+        (ErrorKind::OutputNotWritable, "Couldn't save to this folder.".to_string())
+    } else if detail.starts_with("Conversion failed") {
+        (ErrorKind::ConversionFailed, "The conversion failed.".to_string())
+    } else {
+        (ErrorKind::Other, first_sentence(&detail).to_string())
+    };
+
+    ErrorSummary { kind, summary: truncate_summary(&summary, MAX_SUMMARY_CHARS), detail, log_path }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_truncate_summary() {
+        assert_eq!(truncate_summary("  Short enough. ", 20), "Short enough.");
+        assert_eq!(truncate_summary("The conversion tool could not start at all", 20), "The conversion tool…");
+        assert_eq!(truncate_summary("The conversion tool could not start at all", 22), "The conversion tool…");
+        assert_eq!(truncate_summary("Supercalifragilistic", 10), "Supercali…");
+    }
+
+    #[test]
+    fn test_summarize() {
+        let ffmpeg = "The required codec is not available in this ffmpeg build.\n\nTry converting to a different format like JPG, PNG, or WebP.";
+        let error = job_log::with_log_path(ffmpeg, Path::new("/logs/jobs/a.log"));
+        let summary = summarize(&error);
+        assert_eq!(summary.kind, ErrorKind::CodecUnavailable);
+        assert_eq!(summary.detail, ffmpeg);
+        assert_eq!(summary.log_path.as_deref(), Some("/logs/jobs/a.log"));
+
+        let calibre = summarize("Calibre is required for this e-book conversion but was not found.\n\nPlease install Calibre ...");
+        assert_eq!(calibre.kind, ErrorKind::ToolMissing);
+        assert_eq!(calibre.summary, "Calibre is needed for this. Install it in Settings.");
+
+        let busy = summarize("File in use: \"clip.mp4\" is still downloading. Convert it once the download has finished.");
+        assert_eq!(busy.kind, ErrorKind::FileInUse);
+        assert_eq!(busy.summary, "\"clip.mp4\" is still downloading.");
+
+        let stalled = summarize("Timed out: ffmpeg made no progress for 10 minutes and was stopped. The file may be damaged.");
+        assert_eq!(stalled.kind, ErrorKind::TimedOut);
+        assert_eq!(stalled.summary, "ffmpeg made no progress for 10 minutes and was stopped.");
+
+        let other = summarize(&format!("Something odd happened. {}", "x".repeat(300)));
+        assert_eq!(other.kind, ErrorKind::Other);
+        assert_eq!(other.summary, "Something odd happened.");
+        assert!(summarize(&"y".repeat(300)).summary.chars().count() <= MAX_SUMMARY_CHARS);
+    }
+}
@@ -0,0 +1,149 @@
+//! Feature flags - Experimental backends switched on and off at runtime
+//!
+//! Each flag has a built-in default. A manifest fetched with the app update check can
+//! change the default for everyone (dark launches, or turning a broken backend off
+//! without a release), and a value the user set in the settings wins over both. New
+//! toggles belong here rather than in compile-time constants.
+//!
+//! The flags in effect are kept for the whole process, so code that doesn't see the
+//! config (like `conversion::determine_conversion_tool`) can ask `is_enabled`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// Pandoc document conversions (Markdown, HTML, TXT)
+pub const PANDOC: &str = "pandoc";
+/// libvips as an image backend next to ImageMagick
+pub const LIBVIPS: &str = "libvips";
+/// Long videos split into chunks that are encoded in parallel
+pub const CHUNKED_ENCODING: &str = "chunked_encoding";
+/// Conversion backends from third-party plugins
+pub const PLUGINS: &str = "plugins";
+
+/// Every flag: name, description and built-in default
+pub const FLAGS: &[(&str, &str, bool)] = &[
+    (PANDOC, "Pandoc document conversions (Markdown, HTML, TXT)", false),
+    (LIBVIPS, "libvips image backend", false),
+    (CHUNKED_ENCODING, "Encode long videos in parallel chunks", false),
+    (PLUGINS, "Conversion backends from plugins", false),
+];
+
+/// Where the remote manifest is published, next to the app updates
+pub const REMOTE_MANIFEST_URL: &str = "https://github.com/Hunter-Boone/ConvertSave-Support/releases/latest/download/feature-flags.json";
+
+/// Where a flag's value came from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Default,
+    Remote,
+    User,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    pub source: FlagSource,
+}
+
+/// The remote manifest: `{"flags": {"libvips": true}}`. Flags this version doesn't know
+/// are ignored.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RemoteManifest {
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+}
+
+/// Flags in effect, by name (None until `apply` is first called)
+static ACTIVE: RwLock<Option<BTreeMap<String, bool>>> = RwLock::new(None);
+
+pub fn is_known(name: &str) -> bool {
+    FLAGS.iter().any(|(flag, _, _)| *flag == name)
+}
+
+/// Every flag with the value in effect: the user's, else the remote manifest's, else the default
+pub fn resolve(user: &BTreeMap<String, bool>, remote: &BTreeMap<String, bool>) -> Vec<FeatureFlag> {
+    FLAGS
+        .iter()
+        .map(|(name, description, default)| {
+            let (enabled, source) = match (user.get(*name), remote.get(*name)) {
+                (Some(enabled), _) => (*enabled, FlagSource::User),
+                (None, Some(enabled)) => (*enabled, FlagSource::Remote),
+                (None, None) => (*default, FlagSource::Default),
+            };
+            FeatureFlag { name: name.to_string(), description: description.to_string(), enabled, source }
+        })
+        .collect()
+}
+
+/// Makes `flags` the ones in effect
+pub fn apply(flags: &[FeatureFlag]) {
+    let active = flags.iter().map(|flag| (flag.name.clone(), flag.enabled)).collect();
+    if let Ok(mut guard) = ACTIVE.write() {
+        *guard = Some(active);
+    }
+}
+
+/// Whether a flag is on: as last applied, or its default before that
+pub fn is_enabled(name: &str) -> bool {
+    #[cfg(test)]
+    if let Some(enabled) = TEST_OVERRIDES.with(|overrides| overrides.borrow().get(name).copied()) {
+        return enabled;
+    }
+    let applied = ACTIVE.read().ok().and_then(|guard| guard.as_ref().and_then(|active| active.get(name).copied()));
+    applied.unwrap_or_else(|| FLAGS.iter().any(|(flag, _, default)| *flag == name && *default))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Flags switched for the current test only, so tests running side by side don't see them
+    static TEST_OVERRIDES: std::cell::RefCell<BTreeMap<String, bool>> = const { std::cell::RefCell::new(BTreeMap::new()) };
+}
+
+/// Runs `f` with `name` switched to `enabled` on this thread
+#[cfg(test)]
+pub fn with_flag<T>(name: &str, enabled: bool, f: impl FnOnce() -> T) -> T {
+    let previous = TEST_OVERRIDES.with(|overrides| overrides.borrow_mut().insert(name.to_string(), enabled));
+    let result = f();
+    TEST_OVERRIDES.with(|overrides| match previous {
+        Some(previous) => overrides.borrow_mut().insert(name.to_string(), previous),
+        None => overrides.borrow_mut().remove(name),
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let user = BTreeMap::from([(LIBVIPS.to_string(), false)]);
+        let remote = BTreeMap::from([(LIBVIPS.to_string(), true), (PLUGINS.to_string(), true), ("teleport".to_string(), true)]);
+        let flags = resolve(&user, &remote);
+
+        assert_eq!(flags.len(), FLAGS.len());
+        let flag = |name: &str| flags.iter().find(|flag| flag.name == name).unwrap();
+        assert_eq!((flag(LIBVIPS).enabled, flag(LIBVIPS).source), (false, FlagSource::User));
+        assert_eq!((flag(PLUGINS).enabled, flag(PLUGINS).source), (true, FlagSource::Remote));
+        assert_eq!((flag(PANDOC).enabled, flag(PANDOC).source), (false, FlagSource::Default));
+        assert!(!flags.iter().any(|flag| flag.name == "teleport"));
+    }
+
+    #[test]
+    fn test_with_flag() {
+        assert!(!is_enabled(PANDOC));
+        assert!(with_flag(PANDOC, true, || is_enabled(PANDOC)));
+        assert!(!is_enabled(PANDOC));
+    }
+
+    #[test]
+    fn test_remote_manifest() {
+        let manifest: RemoteManifest = serde_json::from_str(r#"{"flags": {"chunked_encoding": true}, "note": "x"}"#).unwrap();
+        assert_eq!(manifest.flags.get(CHUNKED_ENCODING), Some(&true));
+        assert_eq!(serde_json::from_str::<RemoteManifest>("{}").unwrap(), RemoteManifest::default());
+    }
+}
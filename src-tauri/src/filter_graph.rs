@@ -0,0 +1,534 @@
+//! FFmpeg filter graphs - Typed filters rendered into `-vf`, `-af` and `-filter_complex` values
+//!
+//! A simple chain (`fps=12,scale=...`) is a slice of filters joined with commas; graphs
+//! with several inputs or outputs are built from labeled chains with `FilterGraph`.
+
+/// How `scale` treats the aspect ratio when both sides are given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaleFit {
+    /// Shrink to fit inside the box
+    Decrease,
+    /// Grow to cover the box
+    Increase,
+}
+
+/// Rotations and flips done by `transpose`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transpose {
+    /// 90° counter-clockwise and vertical flip
+    CounterClockwiseFlip = 0,
+    /// 90° clockwise
+    Clockwise = 1,
+    /// 90° counter-clockwise
+    CounterClockwise = 2,
+    /// 90° clockwise and vertical flip
+    ClockwiseFlip = 3,
+}
+
+/// What an analysis pass of `loudnorm` measured, handed to the second pass so it can
+/// normalize linearly instead of adjusting the gain as it goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessMeasurement {
+    /// Integrated loudness (LUFS)
+    pub input_i: f32,
+    /// True peak (dBTP)
+    pub input_tp: f32,
+    /// Loudness range (LU)
+    pub input_lra: f32,
+    /// Gating threshold (LUFS)
+    pub input_thresh: f32,
+    /// Gain offset for the second pass (LU)
+    pub target_offset: f32,
+}
+
+/// Tone mapping curves for HDR to SDR conversion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TonemapAlgorithm {
+    Hable,
+    Mobius,
+    Reinhard,
+    Clip,
+}
+
+impl TonemapAlgorithm {
+    fn name(self) -> &'static str {
+        match self {
+            TonemapAlgorithm::Hable => "hable",
+            TonemapAlgorithm::Mobius => "mobius",
+            TonemapAlgorithm::Reinhard => "reinhard",
+            TonemapAlgorithm::Clip => "clip",
+        }
+    }
+}
+
+/// One FFmpeg filter. Sizes and positions are FFmpeg expressions (`iw/2`, `min(256,iw)`, `-2`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Scale { width: String, height: String, fit: Option<ScaleFit> },
+    Crop { width: String, height: String, x: String, y: String },
+    Pad { width: String, height: String, x: String, y: String, color: String },
+    Overlay { x: String, y: String, shortest: bool },
+    Fps(f32),
+    Transpose(Transpose),
+    HFlip,
+    VFlip,
+    /// Pixel format conversion, e.g. `rgb24`
+    Format(String),
+    /// Lay frames out in a `cols` x `rows` grid
+    Tile { cols: u32, rows: u32 },
+    /// zimg scaler options such as `t=linear:npl=100`, used for color space changes
+    ZScale(String),
+    Tonemap(TonemapAlgorithm),
+    /// EBU R128 loudness normalization to `integrated` LUFS, in one pass or as the second
+    /// pass after a `LoudnormAnalysis` that `measured` the input
+    Loudnorm { integrated: f32, true_peak: f32, range: f32, measured: Option<LoudnessMeasurement> },
+    /// First pass of two-pass loudness normalization: prints the measurements as JSON
+    LoudnormAnalysis { integrated: f32, true_peak: f32, range: f32 },
+    /// Join `segments` inputs one after another, each with `video` and `audio` streams
+    Concat { segments: u32, video: u32, audio: u32 },
+    Split(u32),
+    /// Square pixels (`setsar=1`), so clips of different shapes can be joined
+    SquarePixels,
+    /// Resample audio to a sample rate in Hz
+    AResample(u32),
+    /// Cut audio quieter than `threshold_db` from the start
+    SilenceRemove { threshold_db: f32 },
+    /// Cut every stretch quieter than `threshold_db` lasting over `min_secs`, trailing
+    /// silence included, without buffering the stream
+    SilenceRemoveStops { threshold_db: f32, min_secs: f64 },
+    /// Fade in (or out) over `duration` seconds, starting at `start` (the beginning if None)
+    AFade { fade_in: bool, start: Option<f64>, duration: f64 },
+    /// Keep only frames for which the expression is non-zero
+    Select(String),
+    /// Burn `text` in at `x`, `y`, white on a translucent box. `text` may use expansions
+    /// such as `%{pts\:hms}`; `font_file` is needed where FFmpeg has no fontconfig (Windows).
+    DrawText { text: String, x: String, y: String, font_size: u32, font_file: Option<String> },
+    /// Draw `text` as is (no expansions) in white with a soft shadow, both at `opacity`.
+    /// `font_size` is an expression, so it can follow the frame (`h*0.05`).
+    Caption { text: String, x: String, y: String, font_size: String, opacity: f32, font_file: Option<String> },
+    /// Read a picture or video file as a source (`movie=logo.png`)
+    Movie(String),
+    /// Multiply the alpha channel by `opacity` (needs an alpha pixel format such as rgba)
+    Opacity(f32),
+    PaletteGen,
+    PaletteUse,
+    AlphaExtract,
+}
+
+impl Filter {
+    /// Scale to `width` x `height` (either may be -1/-2 to follow the aspect ratio)
+    pub fn scale(width: impl Into<String>, height: impl Into<String>) -> Self {
+        Filter::Scale { width: width.into(), height: height.into(), fit: None }
+    }
+
+    /// Round both sides down to even numbers, which H.264 and yuv420p require
+    pub fn even_dimensions() -> Self {
+        Filter::scale("trunc(iw/2)*2", "trunc(ih/2)*2")
+    }
+
+    /// Shrink to fit within `max_width` x `max_height`, never enlarging and keeping the aspect ratio
+    pub fn fit_within(max_width: u32, max_height: u32) -> Self {
+        Filter::Scale {
+            width: format!("min({},iw)", max_width),
+            height: format!("min({},ih)", max_height),
+            fit: Some(ScaleFit::Decrease),
+        }
+    }
+
+    /// Crop a `width` x `height` region with its top-left corner at `x`, `y`
+    pub fn crop(width: u32, height: u32, x: u32, y: u32) -> Self {
+        Filter::Crop {
+            width: width.to_string(),
+            height: height.to_string(),
+            x: x.to_string(),
+            y: y.to_string(),
+        }
+    }
+
+    /// Crop the largest centered area with a `width`:`height` aspect ratio, rounded down to
+    /// even sides for H.264
+    pub fn crop_to_aspect(width: u32, height: u32) -> Self {
+        Filter::Crop {
+            width: format!("trunc(min(iw,ih*{}/{})/2)*2", width, height),
+            height: format!("trunc(min(ih,iw*{}/{})/2)*2", height, width),
+            x: "(iw-ow)/2".to_string(),
+            y: "(ih-oh)/2".to_string(),
+        }
+    }
+
+    /// Place the frame centered on a `width` x `height` canvas of `color`
+    pub fn pad_centered(width: impl Into<String>, height: impl Into<String>, color: impl Into<String>) -> Self {
+        Filter::Pad {
+            width: width.into(),
+            height: height.into(),
+            x: "(ow-iw)/2".to_string(),
+            y: "(oh-ih)/2".to_string(),
+            color: color.into(),
+        }
+    }
+
+    /// Overlay the second input at the top-left of the first
+    pub fn overlay() -> Self {
+        Filter::Overlay { x: "0".to_string(), y: "0".to_string(), shortest: false }
+    }
+
+    /// Renders the filter as it appears inside a chain
+    pub fn render(&self) -> String {
+        match self {
+            Filter::Scale { width, height, fit } => {
+                let mut value = format!("scale={}:{}", escape(width), escape(height));
+                match fit {
+                    Some(ScaleFit::Decrease) => value.push_str(":force_original_aspect_ratio=decrease"),
+                    Some(ScaleFit::Increase) => value.push_str(":force_original_aspect_ratio=increase"),
+                    None => {}
+                }
+                value
+            }
+            Filter::Crop { width, height, x, y } => {
+                format!("crop={}:{}:{}:{}", escape(width), escape(height), escape(x), escape(y))
+            }
+            Filter::Pad { width, height, x, y, color } => format!(
+                "pad={}:{}:{}:{}:color={}",
+                escape(width), escape(height), escape(x), escape(y), escape(color)
+            ),
+            Filter::Overlay { x, y, shortest } => {
+                let mut options = Vec::new();
+                if x != "0" || y != "0" {
+                    options.push(format!("x={}", escape(x)));
+                    options.push(format!("y={}", escape(y)));
+                }
+                if *shortest {
+                    options.push("shortest=1".to_string());
+                }
+                if options.is_empty() {
+                    "overlay".to_string()
+                } else {
+                    format!("overlay={}", options.join(":"))
+                }
+            }
+            Filter::Fps(fps) => format!("fps={}", fps),
+            Filter::Transpose(direction) => format!("transpose={}", *direction as u8),
+            Filter::HFlip => "hflip".to_string(),
+            Filter::VFlip => "vflip".to_string(),
+            Filter::Format(pix_fmt) => format!("format={}", escape(pix_fmt)),
+            Filter::Tile { cols, rows } => format!("tile={}x{}", cols, rows),
+            Filter::ZScale(options) => format!("zscale={}", options),
+            Filter::Tonemap(algorithm) => format!("tonemap=tonemap={}:desat=0", algorithm.name()),
+            Filter::Loudnorm { integrated, true_peak, range, measured } => {
+                let mut value = format!("loudnorm=I={}:TP={}:LRA={}", integrated, true_peak, range);
+                if let Some(measured) = measured {
+                    value.push_str(&format!(
+                        ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+                        measured.input_i, measured.input_tp, measured.input_lra, measured.input_thresh, measured.target_offset
+                    ));
+                }
+                value
+            }
+            Filter::LoudnormAnalysis { integrated, true_peak, range } => {
+                format!("loudnorm=I={}:TP={}:LRA={}:print_format=json", integrated, true_peak, range)
+            }
+            Filter::Concat { segments, video, audio } => format!("concat=n={}:v={}:a={}", segments, video, audio),
+            Filter::Split(outputs) if *outputs == 2 => "split".to_string(),
+            Filter::Split(outputs) => format!("split={}", outputs),
+            Filter::SquarePixels => "setsar=1".to_string(),
+            Filter::AResample(rate) => format!("aresample={}", rate),
+            Filter::SilenceRemove { threshold_db } => {
+                format!("silenceremove=start_periods=1:start_threshold={}dB", threshold_db)
+            }
+            Filter::SilenceRemoveStops { threshold_db, min_secs } => {
+                format!("silenceremove=stop_periods=-1:stop_duration={:.3}:stop_threshold={}dB", min_secs, threshold_db)
+            }
+            Filter::AFade { fade_in, start, duration } => {
+                let mut value = format!("afade=t={}", if *fade_in { "in" } else { "out" });
+                if let Some(start) = start {
+                    value.push_str(&format!(":st={:.3}", start));
+                }
+                value.push_str(&format!(":d={:.3}", duration));
+                value
+            }
+            Filter::Select(expression) => format!("select={}", escape(expression)),
+            Filter::DrawText { text, x, y, font_size, font_file } => {
+                let mut value = format!(
+                    "drawtext=text={}:x={}:y={}:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4",
+                    escape(text), escape(x), escape(y), font_size
+                );
+                if let Some(font_file) = font_file {
+                    value.push_str(&format!(":fontfile={}", escape(font_file)));
+                }
+                value
+            }
+            Filter::Caption { text, x, y, font_size, opacity, font_file } => {
+                let mut value = format!(
+                    "drawtext=text={}:expansion=none:x={}:y={}:fontsize={}:fontcolor=white@{}:shadowcolor=black@{}:shadowx=2:shadowy=2",
+                    escape(text), escape(x), escape(y), escape(font_size), opacity, opacity
+                );
+                if let Some(font_file) = font_file {
+                    value.push_str(&format!(":fontfile={}", escape(font_file)));
+                }
+                value
+            }
+            Filter::Movie(file) => format!("movie={}", escape(file)),
+            Filter::Opacity(opacity) => format!("colorchannelmixer=aa={}", opacity),
+            Filter::PaletteGen => "palettegen".to_string(),
+            Filter::PaletteUse => "paletteuse".to_string(),
+            Filter::AlphaExtract => "alphaextract".to_string(),
+        }
+    }
+}
+
+/// Quotes an option value that contains filter graph syntax (`min(256,iw)` -> `'min(256,iw)'`)
+fn escape(value: &str) -> String {
+    if value.contains([',', ';', '[', ']', '\'', ':', '=']) {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Joins filters into a simple chain for `-vf` / `-af`
+pub fn chain(filters: &[Filter]) -> String {
+    filters.iter().map(Filter::render).collect::<Vec<_>>().join(",")
+}
+
+/// Filters that convert HDR (PQ/HLG) video to SDR BT.709 with the given tone mapping curve.
+/// Needs an FFmpeg built with zimg (zscale), which the static builds include.
+pub fn hdr_to_sdr(algorithm: TonemapAlgorithm) -> Vec<Filter> {
+    vec![
+        Filter::ZScale("t=linear:npl=100".to_string()),
+        Filter::Format("gbrpf32le".to_string()),
+        Filter::ZScale("p=bt709".to_string()),
+        Filter::Tonemap(algorithm),
+        Filter::ZScale("t=bt709:m=bt709:r=tv".to_string()),
+        Filter::Format("yuv420p".to_string()),
+    ]
+}
+
+/// A chain in a filter graph, reading and writing labeled pads
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabeledChain {
+    pub inputs: Vec<String>,
+    pub filters: Vec<Filter>,
+    pub outputs: Vec<String>,
+}
+
+/// A `-filter_complex` graph: labeled chains separated by `;`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterGraph {
+    chains: Vec<LabeledChain>,
+}
+
+impl FilterGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chain reading `inputs` (e.g. `0:v`, `bg`) and writing `outputs`
+    pub fn chain(mut self, inputs: &[&str], filters: Vec<Filter>, outputs: &[&str]) -> Self {
+        self.chains.push(LabeledChain {
+            inputs: inputs.iter().map(|label| label.to_string()).collect(),
+            filters,
+            outputs: outputs.iter().map(|label| label.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Appends a filter to the end of the last chain
+    pub fn then(mut self, filter: Filter) -> Self {
+        if let Some(last) = self.chains.last_mut() {
+            last.filters.push(filter);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.iter().all(|chain| chain.filters.is_empty())
+    }
+
+    pub fn render(&self) -> String {
+        let label = |labels: &[String]| labels.iter().map(|l| format!("[{}]", l)).collect::<String>();
+        self.chains
+            .iter()
+            .filter(|c| !c.filters.is_empty())
+            .map(|c| format!("{}{}{}", label(&c.inputs), chain(&c.filters), label(&c.outputs)))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+/// Rotation filters for a clockwise rotation in degrees (multiples of 90; others are ignored)
+pub fn rotation(degrees: i32) -> Vec<Filter> {
+    match degrees.rem_euclid(360) {
+        90 => vec![Filter::Transpose(Transpose::Clockwise)],
+        180 => vec![Filter::HFlip, Filter::VFlip],
+        270 => vec![Filter::Transpose(Transpose::CounterClockwise)],
+        _ => Vec::new(),
+    }
+}
+
+/// Filters that turn a picture stored with EXIF orientation `orientation` (1-8) upright.
+/// FFmpeg doesn't apply the tag to still images itself.
+pub fn exif_orientation(orientation: u16) -> Vec<Filter> {
+    match orientation {
+        2 => vec![Filter::HFlip],
+        3 => vec![Filter::HFlip, Filter::VFlip],
+        4 => vec![Filter::VFlip],
+        5 => vec![Filter::Transpose(Transpose::CounterClockwiseFlip)],
+        6 => vec![Filter::Transpose(Transpose::Clockwise)],
+        7 => vec![Filter::Transpose(Transpose::ClockwiseFlip)],
+        8 => vec![Filter::Transpose(Transpose::CounterClockwise)],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(Filter::scale("320", "-1").render(), "scale=320:-1");
+        assert_eq!(Filter::even_dimensions().render(), "scale=trunc(iw/2)*2:trunc(ih/2)*2");
+    }
+
+    #[test]
+    fn test_fit_within_quotes_expressions() {
+        assert_eq!(
+            Filter::fit_within(256, 256).render(),
+            "scale='min(256,iw)':'min(256,ih)':force_original_aspect_ratio=decrease"
+        );
+    }
+
+    #[test]
+    fn test_crop_and_pad() {
+        assert_eq!(Filter::crop(4032, 3024, 0, 0).render(), "crop=4032:3024:0:0");
+        assert_eq!(
+            Filter::crop_to_aspect(16, 9).render(),
+            "crop='trunc(min(iw,ih*16/9)/2)*2':'trunc(min(ih,iw*9/16)/2)*2':(iw-ow)/2:(ih-oh)/2"
+        );
+        assert_eq!(Filter::pad_centered("1920", "1080", "black").render(), "pad=1920:1080:(ow-iw)/2:(oh-ih)/2:color=black");
+    }
+
+    #[test]
+    fn test_overlay() {
+        assert_eq!(Filter::overlay().render(), "overlay");
+        let overlay = Filter::Overlay { x: "10".to_string(), y: "main_h-overlay_h".to_string(), shortest: true };
+        assert_eq!(overlay.render(), "overlay=x=10:y=main_h-overlay_h:shortest=1");
+    }
+
+    #[test]
+    fn test_transpose_and_rotation() {
+        assert_eq!(Filter::Transpose(Transpose::Clockwise).render(), "transpose=1");
+        assert_eq!(chain(&rotation(90)), "transpose=1");
+        assert_eq!(chain(&rotation(-90)), "transpose=2");
+        assert_eq!(chain(&rotation(180)), "hflip,vflip");
+        assert!(rotation(45).is_empty());
+    }
+
+    #[test]
+    fn test_audio_filters() {
+        let loudnorm = Filter::Loudnorm { integrated: -16.0, true_peak: -1.5, range: 11.0, measured: None };
+        assert_eq!(loudnorm.render(), "loudnorm=I=-16:TP=-1.5:LRA=11");
+        let analysis = Filter::LoudnormAnalysis { integrated: -16.0, true_peak: -1.5, range: 11.0 };
+        assert_eq!(analysis.render(), "loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json");
+        let measured = LoudnessMeasurement { input_i: -27.6, input_tp: -4.5, input_lra: 6.8, input_thresh: -38.1, target_offset: 0.4 };
+        let second_pass = Filter::Loudnorm { integrated: -16.0, true_peak: -1.5, range: 11.0, measured: Some(measured) };
+        assert_eq!(
+            second_pass.render(),
+            "loudnorm=I=-16:TP=-1.5:LRA=11:measured_I=-27.6:measured_TP=-4.5:measured_LRA=6.8:measured_thresh=-38.1:offset=0.4:linear=true"
+        );
+        assert_eq!(Filter::Concat { segments: 3, video: 0, audio: 1 }.render(), "concat=n=3:v=0:a=1");
+        assert_eq!(Filter::AResample(48000).render(), "aresample=48000");
+        assert_eq!(Filter::SilenceRemove { threshold_db: -50.0 }.render(), "silenceremove=start_periods=1:start_threshold=-50dB");
+        assert_eq!(
+            Filter::SilenceRemoveStops { threshold_db: -50.0, min_secs: 2.0 }.render(),
+            "silenceremove=stop_periods=-1:stop_duration=2.000:stop_threshold=-50dB"
+        );
+        assert_eq!(Filter::AFade { fade_in: true, start: None, duration: 0.5 }.render(), "afade=t=in:d=0.500");
+        assert_eq!(Filter::AFade { fade_in: false, start: Some(61.25), duration: 2.0 }.render(), "afade=t=out:st=61.250:d=2.000");
+    }
+
+    #[test]
+    fn test_select_and_drawtext() {
+        assert_eq!(Filter::Select("gte(t,5)".to_string()).render(), "select='gte(t,5)'");
+        let text = Filter::DrawText {
+            text: "%{pts\\:hms}".to_string(),
+            x: "5".to_string(),
+            y: "h-th-5".to_string(),
+            font_size: 18,
+            font_file: Some("C:/Windows/Fonts/arial.ttf".to_string()),
+        };
+        assert_eq!(
+            text.render(),
+            "drawtext=text='%{pts\\:hms}':x=5:y=h-th-5:fontsize=18:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4:fontfile='C:/Windows/Fonts/arial.ttf'"
+        );
+        let caption = Filter::Caption {
+            text: "© Studio: 2026".to_string(),
+            x: "w-text_w-10".to_string(),
+            y: "10".to_string(),
+            font_size: "h*0.05".to_string(),
+            opacity: 0.5,
+            font_file: None,
+        };
+        assert_eq!(
+            caption.render(),
+            "drawtext=text='© Studio: 2026':expansion=none:x=w-text_w-10:y=10:fontsize=h*0.05:fontcolor=white@0.5:shadowcolor=black@0.5:shadowx=2:shadowy=2"
+        );
+        assert_eq!(Filter::Movie("wm0.png".to_string()).render(), "movie=wm0.png");
+        assert_eq!(Filter::Opacity(0.25).render(), "colorchannelmixer=aa=0.25");
+        assert_eq!(Filter::SquarePixels.render(), "setsar=1");
+    }
+
+    #[test]
+    fn test_tonemap_chain() {
+        assert_eq!(
+            chain(&hdr_to_sdr(TonemapAlgorithm::Hable)),
+            "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p"
+        );
+    }
+
+    #[test]
+    fn test_chain() {
+        assert_eq!(chain(&[Filter::Fps(12.0), Filter::even_dimensions()]), "fps=12,scale=trunc(iw/2)*2:trunc(ih/2)*2");
+        assert_eq!(chain(&[]), "");
+    }
+
+    #[test]
+    fn test_palette_graph() {
+        let graph = FilterGraph::new()
+            .chain(&["0:v"], vec![Filter::Fps(10.0), Filter::Split(2)], &["a", "b"])
+            .chain(&["a"], vec![Filter::PaletteGen], &["p"])
+            .chain(&["b", "p"], vec![Filter::PaletteUse], &[]);
+        assert_eq!(graph.render(), "[0:v]fps=10,split[a][b];[a]palettegen[p];[b][p]paletteuse");
+    }
+
+    #[test]
+    fn test_then_appends_to_last_chain() {
+        let graph = FilterGraph::new()
+            .chain(&["1", "0"], vec![Filter::scale("rw", "rh")], &["bg"])
+            .chain(&["bg", "0"], vec![Filter::Overlay { x: "0".to_string(), y: "0".to_string(), shortest: true }], &[])
+            .then(Filter::Format("rgb24".to_string()));
+        assert_eq!(graph.render(), "[1][0]scale=rw:rh[bg];[bg][0]overlay=shortest=1,format=rgb24");
+    }
+
+    #[test]
+    fn test_empty_graph() {
+        assert!(FilterGraph::new().is_empty());
+        assert_eq!(FilterGraph::new().render(), "");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("iw/2"), "iw/2");
+        assert_eq!(escape("a,b"), "'a,b'");
+        assert_eq!(escape("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_exif_orientation() {
+        assert!(exif_orientation(1).is_empty());
+        assert_eq!(chain(&exif_orientation(3)), "hflip,vflip");
+        assert_eq!(chain(&exif_orientation(6)), "transpose=1");
+        assert_eq!(chain(&exif_orientation(8)), "transpose=2");
+        assert!(exif_orientation(9).is_empty());
+    }
+}
@@ -0,0 +1,404 @@
+//! Test fixtures - Synthesizes the sample files the integration tests convert
+//!
+//! Nothing is read from disk: ImageMagick draws solid and gradient images, FFmpeg's lavfi
+//! sources make tones, sweeps and color bars, and the documents are written from the text
+//! below. Running the tools is left to the caller (main.rs uses the Tools Manager paths,
+//! the integration tests use PATH), so a missing tool only skips its fixtures.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Subfolders of the fixtures directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureKind {
+    Images,
+    Video,
+    Audio,
+    Documents,
+}
+
+impl FixtureKind {
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            FixtureKind::Images => "images",
+            FixtureKind::Video => "video",
+            FixtureKind::Audio => "audio",
+            FixtureKind::Documents => "documents",
+        }
+    }
+}
+
+/// How a fixture is made
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipe {
+    /// `magick` arguments; the output path is appended
+    Magick(Vec<String>),
+    /// `ffmpeg` arguments after `-y`; the output path is appended
+    Ffmpeg(Vec<String>),
+    Text(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    pub kind: FixtureKind,
+    pub file_name: String,
+    pub recipe: Recipe,
+}
+
+impl Fixture {
+    pub fn path(&self, fixtures_dir: &Path) -> PathBuf {
+        fixtures_dir.join(self.kind.dir_name()).join(&self.file_name)
+    }
+
+    /// The tool that makes this fixture (None for text)
+    pub fn tool(&self) -> Option<&'static str> {
+        match self.recipe {
+            Recipe::Magick(_) => Some("imagemagick"),
+            Recipe::Ffmpeg(_) => Some("ffmpeg"),
+            Recipe::Text(_) => None,
+        }
+    }
+
+    /// Full argument list for the tool, ending with the output path
+    pub fn args(&self, fixtures_dir: &Path) -> Vec<OsString> {
+        let (prefix, args): (&[&str], &Vec<String>) = match &self.recipe {
+            Recipe::Magick(args) => (&[], args),
+            Recipe::Ffmpeg(args) => (&["-hide_banner", "-loglevel", "error", "-y"], args),
+            Recipe::Text(_) => return Vec::new(),
+        };
+        prefix
+            .iter()
+            .map(OsString::from)
+            .chain(args.iter().map(OsString::from))
+            .chain(std::iter::once(self.path(fixtures_dir).into_os_string()))
+            .collect()
+    }
+}
+
+/// What a generation run did, by file name relative to the fixtures directory
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FixtureReport {
+    pub directory: String,
+    pub created: Vec<String>,
+    /// Already there and kept
+    pub existing: Vec<String>,
+    /// Needs a tool that isn't installed
+    pub skipped: Vec<String>,
+    /// "name: error"
+    pub failed: Vec<String>,
+}
+
+/// The test pattern every image fixture starts from: a gradient with a frame and a circle
+const PATTERN_SIZE: &str = "800x600";
+
+fn pattern(extra: &[&str]) -> Recipe {
+    let mut args: Vec<String> = [
+        "-size", PATTERN_SIZE, "gradient:#3562e3-#8e44ad",
+        "-fill", "none", "-stroke", "white", "-strokewidth", "3",
+        "-draw", "rectangle 100,100 700,500",
+        "-draw", "circle 400,300 400,150",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.extend(extra.iter().map(|arg| arg.to_string()));
+    Recipe::Magick(args)
+}
+
+fn magick(args: &[&str]) -> Recipe {
+    Recipe::Magick(args.iter().map(|arg| arg.to_string()).collect())
+}
+
+fn ffmpeg(args: &[&str]) -> Recipe {
+    Recipe::Ffmpeg(args.iter().map(|arg| arg.to_string()).collect())
+}
+
+/// Color bars with a 440 Hz tone, followed by the encoder arguments
+fn color_bars(seconds: u32, codecs: &[&str]) -> Recipe {
+    let video = format!("smptebars=duration={}:size=640x480:rate=30", seconds);
+    let audio = format!("sine=frequency=440:duration={}", seconds);
+    let mut args = vec!["-f", "lavfi", "-i", video.as_str(), "-f", "lavfi", "-i", audio.as_str(), "-pix_fmt", "yuv420p"];
+    args.extend(codecs);
+    ffmpeg(&args)
+}
+
+/// A 440 Hz tone, 5 seconds, followed by the encoder arguments
+fn tone(codecs: &[&str]) -> Recipe {
+    let mut args = vec!["-f", "lavfi", "-i", "sine=frequency=440:duration=5"];
+    args.extend(codecs);
+    ffmpeg(&args)
+}
+
+fn fixture(kind: FixtureKind, file_name: &str, recipe: Recipe) -> Fixture {
+    Fixture { kind, file_name: file_name.to_string(), recipe }
+}
+
+/// Every fixture, in the order they're generated
+pub fn fixture_plan() -> Vec<Fixture> {
+    use FixtureKind::*;
+    let mut plan = vec![
+        fixture(Images, "sample.png", pattern(&[])),
+        fixture(Images, "sample2.png", pattern(&["-rotate", "90"])),
+        fixture(Images, "sample3.png", pattern(&["-negate"])),
+        fixture(Images, "solid.png", magick(&["-size", PATTERN_SIZE, "xc:#3562e3"])),
+        fixture(Images, "tiny.png", pattern(&["-resize", "16x16"])),
+        fixture(Images, "small_100x100.png", pattern(&["-resize", "100x100"])),
+        fixture(Images, "large_4k.png", pattern(&["-resize", "3840x2160"])),
+        fixture(Images, "wide_1920x200.png", pattern(&["-resize", "1920x200!"])),
+        fixture(Images, "tall_200x1920.png", pattern(&["-resize", "200x1920!"])),
+        fixture(Images, "grayscale.png", pattern(&["-colorspace", "gray"])),
+        fixture(Images, "transparent.png", pattern(&["-alpha", "on", "-channel", "A", "-evaluate", "set", "50%", "+channel"])),
+        fixture(Images, "sample.ico", pattern(&["-resize", "256x256"])),
+        fixture(Images, "sample.pgm", pattern(&["-colorspace", "gray"])),
+        fixture(Images, "sample.pbm", pattern(&["-colorspace", "gray", "-threshold", "50%"])),
+        fixture(
+            Images,
+            "animated.gif",
+            magick(&["-size", "200x150", "-delay", "50", "-loop", "0", "xc:#3562e3", "xc:#8e44ad", "xc:#27ae60", "xc:#f39c12", "xc:#c0392b"]),
+        ),
+    ];
+
+    // The pattern in every other image format the tests read
+    let formats: &[(&str, &[&str])] = &[
+        ("jpg", &["-quality", "90"]),
+        ("jpeg", &["-quality", "90"]),
+        ("gif", &[]),
+        ("bmp", &[]),
+        ("tiff", &[]),
+        ("webp", &["-quality", "85"]),
+        ("tga", &[]),
+        ("ppm", &[]),
+        ("pcx", &[]),
+        ("avif", &[]),
+        ("heic", &[]),
+        ("jp2", &[]),
+        ("psd", &[]),
+    ];
+    for (ext, extra) in formats {
+        plan.push(fixture(Images, &format!("sample.{}", ext), pattern(extra)));
+    }
+
+    plan.extend([
+        fixture(Video, "sample.mp4", color_bars(5, &["-c:v", "libx264", "-preset", "ultrafast", "-crf", "23", "-c:a", "aac", "-b:a", "128k"])),
+        fixture(Video, "sample_short.mp4", color_bars(2, &["-c:v", "libx264", "-preset", "ultrafast", "-c:a", "aac"])),
+        fixture(Video, "sample.mov", color_bars(5, &["-c:v", "libx264", "-preset", "ultrafast", "-c:a", "aac"])),
+        fixture(Video, "sample.avi", color_bars(5, &["-c:v", "mpeg4", "-c:a", "libmp3lame"])),
+        fixture(Video, "sample.mkv", color_bars(5, &["-c:v", "libx264", "-preset", "ultrafast", "-c:a", "aac"])),
+        fixture(Video, "sample.webm", color_bars(5, &["-c:v", "libvpx-vp9", "-crf", "30", "-b:v", "0", "-deadline", "realtime", "-c:a", "libopus"])),
+        fixture(Audio, "sample.mp3", tone(&["-c:a", "libmp3lame", "-b:a", "192k"])),
+        fixture(Audio, "sample.wav", tone(&["-c:a", "pcm_s16le"])),
+        fixture(Audio, "sample.flac", tone(&["-c:a", "flac"])),
+        fixture(Audio, "sample.ogg", tone(&["-c:a", "libvorbis", "-q:a", "5"])),
+        fixture(Audio, "sample.m4a", tone(&["-c:a", "aac", "-b:a", "192k"])),
+        fixture(Audio, "sample.aac", tone(&["-c:a", "aac", "-b:a", "192k"])),
+        fixture(Audio, "sample_stereo.mp3", tone(&["-ac", "2", "-c:a", "libmp3lame"])),
+        fixture(Audio, "sample_mono.mp3", tone(&["-ac", "1", "-c:a", "libmp3lame"])),
+        // 200 Hz rising to 2 kHz over 5 seconds
+        fixture(Audio, "sweep.wav", ffmpeg(&["-f", "lavfi", "-i", "aevalsrc=sin(2*PI*(200*t+180*t*t)):s=44100:d=5", "-c:a", "pcm_s16le"])),
+        fixture(Documents, "sample.md", Recipe::Text(SAMPLE_MD)),
+        fixture(Documents, "sample.html", Recipe::Text(SAMPLE_HTML)),
+        fixture(Documents, "sample.txt", Recipe::Text(SAMPLE_TXT)),
+        fixture(Documents, "sample.rtf", Recipe::Text(SAMPLE_RTF)),
+    ]);
+    plan
+}
+
+/// Generates every missing fixture (or all of them with `overwrite`) under `fixtures_dir`.
+///
+/// `installed` lists the tools that can run ("imagemagick", "ffmpeg"); `run` runs one with
+/// the given arguments.
+pub fn generate<F>(fixtures_dir: &Path, installed: &[&str], overwrite: bool, mut run: F) -> FixtureReport
+where
+    F: FnMut(&str, &[OsString]) -> Result<(), String>,
+{
+    let mut report = FixtureReport { directory: fixtures_dir.to_string_lossy().to_string(), ..Default::default() };
+
+    for fixture in fixture_plan() {
+        let name = format!("{}/{}", fixture.kind.dir_name(), fixture.file_name);
+        let path = fixture.path(fixtures_dir);
+        if path.exists() && !overwrite {
+            report.existing.push(name);
+            continue;
+        }
+        if fixture.tool().is_some_and(|tool| !installed.contains(&tool)) {
+            report.skipped.push(name);
+            continue;
+        }
+        if let Some(dir) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                report.failed.push(format!("{}: {}", name, e));
+                continue;
+            }
+        }
+
+        let result = match (&fixture.recipe, fixture.tool()) {
+            (Recipe::Text(text), _) => std::fs::write(&path, text).map_err(|e| e.to_string()),
+            (_, Some(tool)) => run(tool, &fixture.args(fixtures_dir)),
+            (_, None) => unreachable!("only text fixtures have no tool"),
+        };
+        match result {
+            Ok(()) => report.created.push(name),
+            Err(e) => {
+                // Don't leave a half-written file that would count as existing next time
+                let _ = std::fs::remove_file(&path);
+                report.failed.push(format!("{}: {}", name, e));
+            }
+        }
+    }
+
+    report
+}
+
+const SAMPLE_MD: &str = r#"# ConvertSave Test Document
+
+This is a **sample markdown** document for testing file conversions.
+
+## Features
+
+- Bullet point 1
+- Bullet point 2
+- Bullet point 3
+
+## Code Example
+
+```rust
+fn main() {
+    println!("Hello, ConvertSave!");
+}
+```
+
+## Table
+
+| Format | Tool | Status |
+|--------|------|--------|
+| PNG | ImageMagick | ✓ |
+| MP4 | FFmpeg | ✓ |
+| PDF | LibreOffice | ✓ |
+
+---
+
+*Generated for ConvertSave testing*
+"#;
+
+const SAMPLE_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>ConvertSave Test Document</title>
+    <style>
+        body { font-family: Arial, sans-serif; max-width: 800px; margin: 0 auto; padding: 20px; }
+        h1 { color: #3562e3; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { border: 1px solid #ddd; padding: 8px; text-align: left; }
+    </style>
+</head>
+<body>
+    <h1>ConvertSave Test Document</h1>
+    <p>This is a <strong>sample HTML</strong> document for testing file conversions.</p>
+    <ul>
+        <li>Bullet point 1</li>
+        <li>Bullet point 2</li>
+        <li>Bullet point 3</li>
+    </ul>
+    <table>
+        <tr><th>Format</th><th>Tool</th></tr>
+        <tr><td>PNG</td><td>ImageMagick</td></tr>
+        <tr><td>MP4</td><td>FFmpeg</td></tr>
+        <tr><td>PDF</td><td>LibreOffice</td></tr>
+    </table>
+    <p><em>Generated for ConvertSave testing</em></p>
+</body>
+</html>
+"#;
+
+const SAMPLE_TXT: &str = "ConvertSave Test Document
+=========================
+
+This is a sample plain text document for testing file conversions.
+
+Features:
+- Bullet point 1
+- Bullet point 2
+- Bullet point 3
+
+---
+Generated for ConvertSave testing
+";
+
+const SAMPLE_RTF: &str = r#"{\rtf1\ansi\deff0
+{\fonttbl{\f0 Arial;}}
+{\colortbl;\red53\green98\blue227;}
+\f0\fs24
+\cf1\b ConvertSave Test Document\b0\cf0\par
+\par
+This is a \b sample RTF\b0  document for testing file conversions.\par
+\par
+\i Generated for ConvertSave testing\i0\par
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("convertsave-fixtures-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_plan_names_are_unique() {
+        let plan = fixture_plan();
+        let mut names: Vec<String> = plan.iter().map(|fixture| format!("{}/{}", fixture.kind.dir_name(), fixture.file_name)).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), plan.len());
+        assert!(names.contains(&"images/sample.png".to_string()));
+        assert!(names.contains(&"video/sample.mp4".to_string()));
+    }
+
+    #[test]
+    fn test_args_end_with_output() {
+        let dir = Path::new("/fixtures");
+        let plan = fixture_plan();
+        let mp3 = plan.iter().find(|fixture| fixture.file_name == "sample.mp3").unwrap();
+        let args = mp3.args(dir);
+        assert_eq!(args[3], "-y");
+        assert_eq!(args.last().unwrap(), &dir.join("audio").join("sample.mp3").into_os_string());
+        assert_eq!(mp3.tool(), Some("ffmpeg"));
+    }
+
+    #[test]
+    fn test_generate_skips_missing_tools() {
+        let dir = scratch_dir("skip");
+        let mut calls = Vec::new();
+        let report = generate(&dir, &["imagemagick"], false, |tool, args| {
+            calls.push(tool.to_string());
+            std::fs::write(args.last().unwrap(), b"x").map_err(|e| e.to_string())
+        });
+        assert!(report.failed.is_empty());
+        assert!(report.created.contains(&"documents/sample.md".to_string()));
+        assert!(report.created.contains(&"images/sample.png".to_string()));
+        assert!(report.skipped.contains(&"video/sample.mp4".to_string()));
+        assert!(calls.iter().all(|tool| tool == "imagemagick"));
+        assert!(dir.join("documents").join("sample.rtf").exists());
+
+        // A second run keeps what's there
+        let again = generate(&dir, &["imagemagick"], false, |_, _| Err("should not run".to_string()));
+        assert!(again.created.is_empty());
+        assert_eq!(again.existing.len(), report.created.len());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_generate_records_failures() {
+        let dir = scratch_dir("fail");
+        let report = generate(&dir, &["ffmpeg"], false, |_, _| Err("encoder missing".to_string()));
+        assert!(report.failed.contains(&"audio/sample.wav: encoder missing".to_string()));
+        assert!(!dir.join("audio").join("sample.wav").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
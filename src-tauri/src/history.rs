@@ -0,0 +1,357 @@
+//! Conversion history - Persists finished and queued jobs with user notes and color labels
+//!
+//! main.rs records every conversion here; the frontend attaches notes/labels to entries
+//! (or to queue jobs before they run) and searches them later. How long recent conversions
+//! took is kept per tool and format pair, for estimating how long a batch will take.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Oldest entries are dropped past this many
+pub const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// Recent conversions kept per tool and format pair for throughput estimates
+pub const MAX_THROUGHPUT_SAMPLES: usize = 50;
+
+/// Color labels a job can be tagged with
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl JobLabel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobLabel::Red => "red",
+            JobLabel::Orange => "orange",
+            JobLabel::Yellow => "yellow",
+            JobLabel::Green => "green",
+            JobLabel::Blue => "blue",
+            JobLabel::Purple => "purple",
+            JobLabel::Gray => "gray",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Labeled in the queue but not converted yet
+    #[default]
+    Queued,
+    Completed,
+    Failed,
+}
+
+/// One conversion job
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct HistoryEntry {
+    /// History entry id, or the frontend's queue job id when one was given
+    pub id: String,
+    pub created_at: String,
+    pub input_path: String,
+    pub output_path: Option<String>,
+    pub output_format: String,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub note: Option<String>,
+    pub label: Option<JobLabel>,
+}
+
+impl HistoryEntry {
+    /// Checks if every word of a lowercase query appears in the file names, format, note or label
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = [
+            self.input_path.as_str(),
+            self.output_path.as_deref().unwrap_or(""),
+            self.output_format.as_str(),
+            self.note.as_deref().unwrap_or(""),
+            self.label.map(|label| label.name()).unwrap_or(""),
+        ]
+        .join("\n")
+        .to_lowercase();
+
+        query.split_whitespace().all(|word| haystack.contains(word))
+    }
+}
+
+/// How long one conversion took for its input size
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ThroughputSample {
+    pub input_bytes: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Key for the throughput samples of a tool and format pair, e.g. "ffmpeg mov->mp4"
+pub fn throughput_key(tool: &str, input_ext: &str, output_ext: &str) -> String {
+    format!("{} {}->{}", tool, input_ext.to_lowercase(), output_ext.to_lowercase())
+}
+
+/// Fits `elapsed = overhead + bytes * rate` to the samples (least squares), returning the
+/// overhead in ms and the rate in ms per byte. Neither is allowed to go negative: with a
+/// negative overhead the line is forced through zero instead.
+fn fit_throughput(samples: &[ThroughputSample]) -> Option<(f64, f64)> {
+    if samples.is_empty() {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|s| s.input_bytes as f64).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|s| s.elapsed_ms as f64).sum::<f64>() / n;
+    let var_x: f64 = samples.iter().map(|s| (s.input_bytes as f64 - mean_x).powi(2)).sum();
+    let cov: f64 = samples.iter()
+        .map(|s| (s.input_bytes as f64 - mean_x) * (s.elapsed_ms as f64 - mean_y))
+        .sum();
+
+    // All samples the same size: there's no telling overhead from rate, so scale by size
+    if var_x < 1.0 {
+        return Some(if mean_x > 0.0 { (0.0, mean_y / mean_x) } else { (mean_y, 0.0) });
+    }
+
+    let rate = (cov / var_x).max(0.0);
+    let overhead = mean_y - rate * mean_x;
+    if overhead >= 0.0 {
+        return Some((overhead, rate));
+    }
+    let sum_xy: f64 = samples.iter().map(|s| s.input_bytes as f64 * s.elapsed_ms as f64).sum();
+    let sum_xx: f64 = samples.iter().map(|s| (s.input_bytes as f64).powi(2)).sum();
+    Some((0.0, sum_xy / sum_xx))
+}
+
+/// All recorded jobs, oldest first
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct JobHistory {
+    pub entries: Vec<HistoryEntry>,
+    /// Recent conversion timings by throughput_key, oldest first
+    pub throughput: BTreeMap<String, Vec<ThroughputSample>>,
+}
+
+impl JobHistory {
+    /// Loads the history, starting empty if the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read history: {}", e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse history: {}", e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize history: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write history: {}", e))
+    }
+
+    /// Adds a finished job. If the job was labeled while queued, its note and label are kept.
+    pub fn record(&mut self, entry: HistoryEntry) {
+        match self.entries.iter_mut().find(|existing| existing.id == entry.id) {
+            Some(existing) => {
+                let note = existing.note.take();
+                let label = existing.label.take();
+                *existing = HistoryEntry {
+                    note: entry.note.or(note),
+                    label: entry.label.or(label),
+                    ..entry
+                };
+            }
+            None => self.entries.push(entry),
+        }
+
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            let excess = self.entries.len() - MAX_HISTORY_ENTRIES;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Sets (or clears, with None) the note and label of a job. Unknown ids are queue jobs
+    /// that haven't run yet, so a queued placeholder is created for them.
+    pub fn set_label(&mut self, id: &str, note: Option<String>, label: Option<JobLabel>, created_at: &str) {
+        let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.note = note;
+                entry.label = label;
+            }
+            None => self.record(HistoryEntry {
+                id: id.to_string(),
+                created_at: created_at.to_string(),
+                note,
+                label,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Adds a conversion timing, dropping the oldest past MAX_THROUGHPUT_SAMPLES
+    pub fn record_throughput(&mut self, key: &str, sample: ThroughputSample) {
+        let samples = self.throughput.entry(key.to_string()).or_default();
+        samples.push(sample);
+        if samples.len() > MAX_THROUGHPUT_SAMPLES {
+            let excess = samples.len() - MAX_THROUGHPUT_SAMPLES;
+            samples.drain(..excess);
+        }
+    }
+
+    /// Expected duration of converting `input_bytes` by the recorded timings for `key`
+    /// (None when nothing was recorded for it yet)
+    pub fn estimate_ms(&self, key: &str, input_bytes: u64) -> Option<u64> {
+        let (overhead, rate) = fit_throughput(self.throughput.get(key)?)?;
+        Some((overhead + rate * input_bytes as f64).round().max(0.0) as u64)
+    }
+
+    /// The last `count` jobs that ran, newest first (labels on queued jobs aren't included)
+    pub fn recent(&self, count: usize) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.status != JobStatus::Queued)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// Entries matching the query, newest first (an empty query returns everything)
+    pub fn search(&self, query: &str) -> Vec<HistoryEntry> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.matches(&query))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(id: &str, input: &str, format: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            input_path: input.to_string(),
+            output_path: Some(format!("{}.{}", input, format)),
+            output_format: format.to_string(),
+            status: JobStatus::Completed,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queued_label_survives_conversion() {
+        let mut history = JobHistory::default();
+        history.set_label("job-1", Some(" Acme rebrand ".to_string()), Some(JobLabel::Blue), "2024-01-01T00:00:00Z");
+        assert_eq!(history.entries[0].status, JobStatus::Queued);
+
+        history.record(completed("job-1", "/clients/acme/logo.png", "webp"));
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].status, JobStatus::Completed);
+        assert_eq!(history.entries[0].note.as_deref(), Some("Acme rebrand"));
+        assert_eq!(history.entries[0].label, Some(JobLabel::Blue));
+    }
+
+    #[test]
+    fn test_set_label_clears() {
+        let mut history = JobHistory::default();
+        history.record(completed("1", "/a/photo.heic", "jpg"));
+        history.set_label("1", Some("draft".to_string()), Some(JobLabel::Red), "");
+        history.set_label("1", Some("   ".to_string()), None, "");
+        assert_eq!(history.entries[0].note, None);
+        assert_eq!(history.entries[0].label, None);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut history = JobHistory::default();
+        history.record(completed("1", "/clients/acme/logo.png", "webp"));
+        history.record(completed("2", "/clients/globex/intro.mov", "mp4"));
+        history.set_label("2", Some("Globex launch video".to_string()), Some(JobLabel::Green), "");
+
+        let ids = |results: Vec<HistoryEntry>| results.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(history.search("")), vec!["2", "1"]);
+        assert_eq!(ids(history.search("ACME")), vec!["1"]);
+        assert_eq!(ids(history.search("launch green")), vec!["2"]);
+        assert!(history.search("acme green").is_empty());
+    }
+
+    #[test]
+    fn test_recent() {
+        let mut history = JobHistory::default();
+        for id in ["1", "2", "3"] {
+            history.record(completed(id, "/in/a.png", "webp"));
+        }
+        history.set_label("queued", Some("Later".to_string()), None, "");
+
+        let ids: Vec<String> = history.recent(2).into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["3", "2"]);
+        assert_eq!(history.recent(10).len(), 3);
+    }
+
+    fn sample(input_bytes: u64, elapsed_ms: u64) -> ThroughputSample {
+        ThroughputSample { input_bytes, elapsed_ms }
+    }
+
+    #[test]
+    fn test_estimate_fits_overhead_and_rate() {
+        let mut history = JobHistory::default();
+        let key = throughput_key("ffmpeg", "MOV", "mp4");
+        assert_eq!(key, "ffmpeg mov->mp4");
+        assert_eq!(history.estimate_ms(&key, 1000), None);
+
+        // 100 ms to start plus 1 ms per KB
+        for kb in [10, 50, 200] {
+            history.record_throughput(&key, sample(kb * 1000, 100 + kb));
+        }
+        assert_eq!(history.estimate_ms(&key, 1_000_000), Some(1100));
+    }
+
+    #[test]
+    fn test_estimate_with_one_size_scales_by_size() {
+        let mut history = JobHistory::default();
+        history.record_throughput("k", sample(1000, 500));
+        assert_eq!(history.estimate_ms("k", 2000), Some(1000));
+    }
+
+    #[test]
+    fn test_estimate_never_negative_overhead() {
+        let mut history = JobHistory::default();
+        history.record_throughput("k", sample(1000, 10));
+        history.record_throughput("k", sample(2000, 1000));
+        assert_eq!(history.estimate_ms("k", 0), Some(0));
+        assert!(history.estimate_ms("k", 4000).unwrap() > 1000);
+    }
+
+    #[test]
+    fn test_throughput_samples_are_capped() {
+        let mut history = JobHistory::default();
+        for i in 0..MAX_THROUGHPUT_SAMPLES as u64 + 3 {
+            history.record_throughput("k", sample(i, i));
+        }
+        assert_eq!(history.throughput["k"].len(), MAX_THROUGHPUT_SAMPLES);
+        assert_eq!(history.throughput["k"][0].input_bytes, 3);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = JobHistory::default();
+        for i in 0..MAX_HISTORY_ENTRIES + 5 {
+            history.record(completed(&i.to_string(), "in.png", "jpg"));
+        }
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.entries[0].id, "5");
+    }
+}
@@ -0,0 +1,235 @@
+//! ICC profiles - A built-in sRGB profile for color conversion
+//!
+//! ImageMagick converts colors between ICC profiles with `-profile`, which needs the target
+//! profile as a file. Rather than shipping one, the standard sRGB profile (ICC v2, D50
+//! adapted colorants and the sRGB tone curve) is generated and written next to the job files.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// Entries in each tone curve table
+const CURVE_POINTS: usize = 1024;
+
+/// D50, the profile connection space white point
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// sRGB primaries adapted to D50 (Bradford), from the sRGB specification
+const RED: [f64; 3] = [0.4360747, 0.2225045, 0.0139322];
+const GREEN: [f64; 3] = [0.3850649, 0.7168786, 0.0971045];
+const BLUE: [f64; 3] = [0.1430804, 0.0606169, 0.7141733];
+
+const DESCRIPTION: &str = "sRGB IEC61966-2.1";
+const COPYRIGHT: &str = "No copyright, use freely";
+
+/// The sRGB transfer function: encoded value (0-1) to linear light
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn s15_fixed16(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+    let mut data = b"XYZ \0\0\0\0".to_vec();
+    for value in xyz {
+        data.extend(s15_fixed16(value));
+    }
+    data
+}
+
+fn curve_tag() -> Vec<u8> {
+    let mut data = b"curv\0\0\0\0".to_vec();
+    data.extend((CURVE_POINTS as u32).to_be_bytes());
+    for i in 0..CURVE_POINTS {
+        let linear = srgb_to_linear(i as f64 / (CURVE_POINTS - 1) as f64);
+        data.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    data
+}
+
+/// ICC v2 textDescriptionType: ASCII, then empty Unicode and ScriptCode descriptions
+fn description_tag(text: &str) -> Vec<u8> {
+    let mut data = b"desc\0\0\0\0".to_vec();
+    data.extend((text.len() as u32 + 1).to_be_bytes());
+    data.extend(text.as_bytes());
+    data.push(0);
+    // Unicode language and count, ScriptCode code and count, then the 67 byte ScriptCode field
+    data.extend([0u8; 4 + 4 + 2 + 1 + 67]);
+    data
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut data = b"text\0\0\0\0".to_vec();
+    data.extend(text.as_bytes());
+    data.push(0);
+    data
+}
+
+/// The sRGB display profile as ICC v2 bytes
+pub fn srgb_profile() -> Vec<u8> {
+    let curve = curve_tag();
+    // The three tone curves share one table
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", description_tag(DESCRIPTION)),
+        (b"cprt", text_tag(COPYRIGHT)),
+        (b"wtpt", xyz_tag(D50)),
+        (b"rXYZ", xyz_tag(RED)),
+        (b"gXYZ", xyz_tag(GREEN)),
+        (b"bXYZ", xyz_tag(BLUE)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let table_size = 4 + tags.len() * 12;
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data: Vec<u8> = Vec::new();
+    let mut shared_curve: Option<(u32, u32)> = None;
+    for (signature, tag) in &tags {
+        let is_curve = tag.starts_with(b"curv");
+        let (offset, size) = match shared_curve.filter(|_| is_curve) {
+            Some(entry) => entry,
+            None => {
+                let entry = ((128 + table_size + data.len()) as u32, tag.len() as u32);
+                data.extend(tag);
+                // Tag data starts on 4 byte boundaries
+                data.resize(data.len().div_ceil(4) * 4, 0);
+                if is_curve {
+                    shared_curve = Some(entry);
+                }
+                entry
+            }
+        };
+        table.extend(*signature);
+        table.extend(offset.to_be_bytes());
+        table.extend(size.to_be_bytes());
+    }
+
+    let size = 128 + table_size + data.len();
+    let mut header = vec![0u8; 128];
+    header[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+    // Version 2.1
+    header[8..12].copy_from_slice(&[2, 0x10, 0, 0]);
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    // Creation date: 2015-02-15 (year, month, day, hour, minute, second)
+    for (i, value) in [2015u16, 2, 15, 0, 0, 0].iter().enumerate() {
+        header[24 + i * 2..26 + i * 2].copy_from_slice(&value.to_be_bytes());
+    }
+    header[36..40].copy_from_slice(b"acsp");
+    for (i, value) in D50.iter().enumerate() {
+        header[68 + i * 4..72 + i * 4].copy_from_slice(&s15_fixed16(*value));
+    }
+
+    let mut profile = header;
+    profile.extend(table);
+    profile.extend(data);
+    profile
+}
+
+/// Where the sRGB profile is written for ImageMagick
+pub fn srgb_profile_path() -> PathBuf {
+    std::env::temp_dir().join("convertsave-sRGB-v2.icc")
+}
+
+/// Writes the sRGB profile to srgb_profile_path unless it's already there. The profile is
+/// written under a unique name and renamed into place, so a conversion running in another
+/// thread or process never reads it half written; after that it's only checked once per run.
+pub fn ensure_srgb_profile() -> Result<PathBuf, String> {
+    static WRITTEN: OnceLock<PathBuf> = OnceLock::new();
+    static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+    if let Some(path) = WRITTEN.get() {
+        return Ok(path.clone());
+    }
+
+    let path = srgb_profile_path();
+    let profile = srgb_profile();
+    let is_current = |path: &PathBuf| std::fs::read(path).map(|bytes| bytes == profile).unwrap_or(false);
+    if !is_current(&path) {
+        let temp_path = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = std::fs::write(&temp_path, &profile).and_then(|_| std::fs::rename(&temp_path, &path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&temp_path);
+            // Someone else may have put it in place first (Windows won't replace a file in use)
+            if !is_current(&path) {
+                return Err(format!("Failed to write the sRGB color profile: {}", e));
+            }
+        }
+    }
+    Ok(WRITTEN.get_or_init(|| path).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_ensure_srgb_profile() {
+        let first = ensure_srgb_profile().unwrap();
+        let second = ensure_srgb_profile().unwrap();
+        assert_eq!(first, second);
+        assert_eq!(std::fs::read(&first).unwrap(), srgb_profile());
+    }
+
+    #[test]
+    fn test_header() {
+        let profile = srgb_profile();
+        assert_eq!(u32_at(&profile, 0) as usize, profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+        assert_eq!(&profile[12..24], b"mntrRGB XYZ ");
+        assert_eq!(profile[8], 2);
+    }
+
+    #[test]
+    fn test_tag_table() {
+        let profile = srgb_profile();
+        let count = u32_at(&profile, 128) as usize;
+        assert_eq!(count, 9);
+        let mut curve_offsets = Vec::new();
+        for i in 0..count {
+            let entry = 132 + i * 12;
+            let (offset, size) = (u32_at(&profile, entry + 4) as usize, u32_at(&profile, entry + 8) as usize);
+            assert_eq!(offset % 4, 0);
+            assert!(offset + size <= profile.len());
+            if profile[entry..entry + 4].ends_with(b"TRC") {
+                assert_eq!(&profile[offset..offset + 4], b"curv");
+                curve_offsets.push(offset);
+            }
+        }
+        // One table for all three channels
+        assert_eq!(curve_offsets.len(), 3);
+        assert!(curve_offsets.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn test_curve_follows_srgb() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-9);
+        // Mid grey (128) is about 21.6% linear light
+        assert!((srgb_to_linear(128.0 / 255.0) - 0.2158).abs() < 0.001);
+        let curve = curve_tag();
+        assert_eq!(curve.len(), 12 + CURVE_POINTS * 2);
+        assert_eq!(&curve[curve.len() - 2..], &[0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        assert_eq!(s15_fixed16(1.0), [0, 1, 0, 0]);
+        assert_eq!(s15_fixed16(0.9642), [0, 0, 0xf6, 0xd6]);
+    }
+}
@@ -0,0 +1,312 @@
+//! Icon sets - App icons for macOS, Windows, iOS and Android from one large picture
+//!
+//! ImageMagick renders every size as a PNG. The .icns and .ico containers are put together
+//! here, since both are a small index in front of those PNGs, and so is the Contents.json
+//! Xcode expects next to an AppIcon set.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Sources should be at least this big; smaller ones are scaled up for the largest icons
+pub const RECOMMENDED_SOURCE_SIZE: u32 = 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IconPlatform {
+    Macos,
+    Windows,
+    Ios,
+    Android,
+}
+
+pub const ALL_PLATFORMS: &[IconPlatform] = &[IconPlatform::Macos, IconPlatform::Windows, IconPlatform::Ios, IconPlatform::Android];
+
+/// .icns entries that hold PNGs: OSType and pixel size. The @2x types (ic11-ic14) share
+/// their pixels with the next size up.
+const ICNS_ENTRIES: &[(&[u8; 4], u32)] = &[
+    (b"icp4", 16),
+    (b"ic11", 32),
+    (b"icp5", 32),
+    (b"ic12", 64),
+    (b"icp6", 64),
+    (b"ic07", 128),
+    (b"ic13", 256),
+    (b"ic08", 256),
+    (b"ic14", 512),
+    (b"ic09", 512),
+    (b"ic10", 1024),
+];
+
+/// Sizes in a Windows .ico, covering small icons to the 256px Explorer tiles
+const ICO_SIZES: &[u32] = &[16, 24, 32, 48, 64, 128, 256];
+
+/// iPhone, iPad and App Store icons: idiom, size in points, scale and size in pixels
+const IOS_ICONS: &[(&str, &str, u32, u32)] = &[
+    ("iphone", "20", 2, 40),
+    ("iphone", "20", 3, 60),
+    ("iphone", "29", 2, 58),
+    ("iphone", "29", 3, 87),
+    ("iphone", "40", 2, 80),
+    ("iphone", "40", 3, 120),
+    ("iphone", "60", 2, 120),
+    ("iphone", "60", 3, 180),
+    ("ipad", "20", 1, 20),
+    ("ipad", "20", 2, 40),
+    ("ipad", "29", 1, 29),
+    ("ipad", "29", 2, 58),
+    ("ipad", "40", 1, 40),
+    ("ipad", "40", 2, 80),
+    ("ipad", "76", 1, 76),
+    ("ipad", "76", 2, 152),
+    ("ipad", "83.5", 2, 167),
+    ("ios-marketing", "1024", 1, 1024),
+];
+
+/// Android launcher icon sizes per screen density
+const ANDROID_DENSITIES: &[(&str, u32)] = &[("mdpi", 48), ("hdpi", 72), ("xhdpi", 96), ("xxhdpi", 144), ("xxxhdpi", 192)];
+
+/// Size of the Play Store listing icon
+const PLAY_STORE_SIZE: u32 = 512;
+
+/// What generate_icon_set wrote
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IconSetResult {
+    pub output_directory: String,
+    /// Files relative to `output_directory`
+    pub files: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// One file of an icon set, relative to the set's folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconFile {
+    /// A PNG of `size` pixels square; `opaque` ones are flattened, as the App Store rejects
+    /// icons with transparency
+    Png { path: String, size: u32, opaque: bool },
+    Icns { path: String },
+    Ico { path: String },
+    /// Xcode's description of an AppIcon set
+    IosContents { path: String },
+}
+
+impl IconFile {
+    pub fn path(&self) -> &str {
+        match self {
+            IconFile::Png { path, .. } | IconFile::Icns { path } | IconFile::Ico { path } | IconFile::IosContents { path } => path,
+        }
+    }
+}
+
+fn ios_file_name(points: &str, scale: u32) -> String {
+    if scale == 1 {
+        format!("Icon-{}.png", points)
+    } else {
+        format!("Icon-{}@{}x.png", points, scale)
+    }
+}
+
+/// Every file of the icon set for `platforms`, with `name` used for the .icns and .ico
+pub fn icon_files(platforms: &[IconPlatform], name: &str) -> Vec<IconFile> {
+    let mut files = Vec::new();
+    for platform in ALL_PLATFORMS.iter().filter(|platform| platforms.contains(platform)) {
+        match platform {
+            IconPlatform::Macos => files.push(IconFile::Icns { path: format!("macos/{}.icns", name) }),
+            IconPlatform::Windows => files.push(IconFile::Ico { path: format!("windows/{}.ico", name) }),
+            IconPlatform::Ios => {
+                let mut seen = Vec::new();
+                for (_, points, scale, size) in IOS_ICONS {
+                    let path = format!("ios/AppIcon.appiconset/{}", ios_file_name(points, *scale));
+                    if !seen.contains(&path) {
+                        seen.push(path.clone());
+                        files.push(IconFile::Png { path, size: *size, opaque: true });
+                    }
+                }
+                files.push(IconFile::IosContents { path: "ios/AppIcon.appiconset/Contents.json".to_string() });
+            }
+            IconPlatform::Android => {
+                for (density, size) in ANDROID_DENSITIES {
+                    files.push(IconFile::Png { path: format!("android/res/mipmap-{}/ic_launcher.png", density), size: *size, opaque: false });
+                }
+                files.push(IconFile::Png { path: "android/playstore-icon.png".to_string(), size: PLAY_STORE_SIZE, opaque: false });
+            }
+        }
+    }
+    files
+}
+
+/// The PNG sizes to render for `files`, as (size, opaque) pairs without repeats
+pub fn render_sizes(files: &[IconFile]) -> Vec<(u32, bool)> {
+    let mut sizes: Vec<(u32, bool)> = Vec::new();
+    for file in files {
+        match file {
+            IconFile::Png { size, opaque, .. } => sizes.push((*size, *opaque)),
+            IconFile::Icns { .. } => sizes.extend(ICNS_ENTRIES.iter().map(|(_, size)| (*size, false))),
+            IconFile::Ico { .. } => sizes.extend(ICO_SIZES.iter().map(|size| (*size, false))),
+            IconFile::IosContents { .. } => {}
+        }
+    }
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
+
+fn png_for(pngs: &BTreeMap<u32, Vec<u8>>, size: u32) -> Result<&[u8], String> {
+    pngs.get(&size).map(Vec::as_slice).ok_or_else(|| format!("The {0}x{0} icon is missing", size))
+}
+
+/// A macOS .icns holding the transparent PNGs in `pngs` (pixel size -> PNG data)
+pub fn build_icns(pngs: &BTreeMap<u32, Vec<u8>>) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    for (kind, size) in ICNS_ENTRIES {
+        let png = png_for(pngs, *size)?;
+        body.extend_from_slice(*kind);
+        body.extend_from_slice(&(png.len() as u32 + 8).to_be_bytes());
+        body.extend_from_slice(png);
+    }
+    let mut icns = b"icns".to_vec();
+    icns.extend_from_slice(&(body.len() as u32 + 8).to_be_bytes());
+    icns.extend(body);
+    Ok(icns)
+}
+
+/// A Windows .ico holding the transparent PNGs in `pngs` (pixel size -> PNG data), which
+/// Windows Vista and later read directly
+pub fn build_ico(pngs: &BTreeMap<u32, Vec<u8>>) -> Result<Vec<u8>, String> {
+    let images = ICO_SIZES.iter().map(|size| Ok((*size, png_for(pngs, *size)?))).collect::<Result<Vec<_>, String>>()?;
+
+    let mut ico = Vec::new();
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    // Type 1 is an icon (2 would be a cursor)
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&(images.len() as u16).to_le_bytes());
+
+    let mut offset = 6 + 16 * images.len();
+    for (size, png) in &images {
+        // 0 stands for 256
+        let dimension = if *size >= 256 { 0 } else { *size as u8 };
+        ico.extend_from_slice(&[dimension, dimension, 0, 0]);
+        ico.extend_from_slice(&1u16.to_le_bytes());
+        ico.extend_from_slice(&32u16.to_le_bytes());
+        ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    for (_, png) in images {
+        ico.extend_from_slice(png);
+    }
+    Ok(ico)
+}
+
+/// Contents.json for the iOS AppIcon set
+pub fn ios_contents_json() -> serde_json::Value {
+    let images: Vec<serde_json::Value> = IOS_ICONS
+        .iter()
+        .map(|(idiom, points, scale, _)| {
+            serde_json::json!({
+                "size": format!("{0}x{0}", points),
+                "idiom": idiom,
+                "filename": ios_file_name(points, *scale),
+                "scale": format!("{}x", scale),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "images": images,
+        "info": { "version": 1, "author": "ConvertSave" },
+    })
+}
+
+/// ImageMagick arguments rendering a `size` pixel icon: fitted into the square, centered on
+/// a transparent (or, for `opaque` icons, white) background
+pub fn icon_render_args(input: &str, output: &str, size: u32, opaque: bool) -> Vec<String> {
+    let geometry = format!("{0}x{0}", size);
+    let mut args = vec![
+        input.to_string(),
+        "-background".to_string(),
+        if opaque { "white" } else { "none" }.to_string(),
+        "-resize".to_string(),
+        geometry.clone(),
+        "-gravity".to_string(),
+        "center".to_string(),
+        "-extent".to_string(),
+        geometry,
+    ];
+    if opaque {
+        args.extend(["-alpha".to_string(), "remove".to_string(), "-alpha".to_string(), "off".to_string()]);
+    }
+    // Force RGB(A) PNGs: ImageMagick would otherwise write grayscale or palette PNGs for
+    // simple logos, which some asset catalogs refuse
+    args.push(format!("{}:{}", if opaque { "PNG24" } else { "PNG32" }, output));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pngs(sizes: &[u32]) -> BTreeMap<u32, Vec<u8>> {
+        sizes.iter().map(|size| (*size, vec![*size as u8; 3])).collect()
+    }
+
+    #[test]
+    fn test_icon_files() {
+        let files = icon_files(&[IconPlatform::Android, IconPlatform::Macos], "app");
+        assert_eq!(files[0], IconFile::Icns { path: "macos/app.icns".to_string() });
+        assert_eq!(files[1].path(), "android/res/mipmap-mdpi/ic_launcher.png");
+        assert_eq!(files.len(), 7);
+
+        // iPhone and iPad share the 20@2x, 29@2x and 40@2x files
+        let ios = icon_files(&[IconPlatform::Ios], "app");
+        assert_eq!(ios.len(), 16);
+        assert!(ios.contains(&IconFile::Png { path: "ios/AppIcon.appiconset/Icon-83.5@2x.png".to_string(), size: 167, opaque: true }));
+    }
+
+    #[test]
+    fn test_render_sizes() {
+        let sizes = render_sizes(&icon_files(&[IconPlatform::Windows, IconPlatform::Macos], "app"));
+        assert_eq!(sizes.iter().map(|(size, _)| *size).collect::<Vec<_>>(), vec![16, 24, 32, 48, 64, 128, 256, 512, 1024]);
+        assert!(render_sizes(&icon_files(&[IconPlatform::Ios], "app")).contains(&(1024, true)));
+    }
+
+    #[test]
+    fn test_build_icns() {
+        let icns = build_icns(&pngs(&[16, 32, 64, 128, 256, 512, 1024])).unwrap();
+        assert_eq!(&icns[..4], b"icns");
+        assert_eq!(u32::from_be_bytes(icns[4..8].try_into().unwrap()) as usize, icns.len());
+        // Each entry: type, length (with its 8 byte header), PNG
+        assert_eq!(&icns[8..12], b"icp4");
+        assert_eq!(u32::from_be_bytes(icns[12..16].try_into().unwrap()), 11);
+        assert_eq!(icns.len(), 8 + ICNS_ENTRIES.len() * 11);
+        assert!(build_icns(&pngs(&[16, 32])).is_err());
+    }
+
+    #[test]
+    fn test_build_ico() {
+        let ico = build_ico(&pngs(ICO_SIZES)).unwrap();
+        assert_eq!(&ico[..6], &[0, 0, 1, 0, 7, 0]);
+        // First entry: 16x16, 32 bits, 3 bytes at the end of the directory
+        assert_eq!(&ico[6..10], &[16, 16, 0, 0]);
+        assert_eq!(u32::from_le_bytes(ico[18..22].try_into().unwrap()), 6 + 16 * 7);
+        // The 256px entry is written as 0
+        assert_eq!(ico[6 + 16 * 6], 0);
+        assert_eq!(ico.len(), 6 + 16 * 7 + 3 * 7);
+    }
+
+    #[test]
+    fn test_ios_contents_json() {
+        let contents = ios_contents_json();
+        let images = contents["images"].as_array().unwrap();
+        assert_eq!(images.len(), IOS_ICONS.len());
+        assert_eq!(images[0]["filename"], "Icon-20@2x.png");
+        assert_eq!(images[16]["size"], "83.5x83.5");
+        assert_eq!(images[17]["idiom"], "ios-marketing");
+    }
+
+    #[test]
+    fn test_icon_render_args() {
+        let args = icon_render_args("logo.png", "16.png", 16, false);
+        assert_eq!(args, vec!["logo.png", "-background", "none", "-resize", "16x16", "-gravity", "center", "-extent", "16x16", "PNG32:16.png"]);
+        let args = icon_render_args("logo.png", "1024.png", 1024, true);
+        assert_eq!(&args[9..], ["-alpha", "remove", "-alpha", "off", "PNG24:1024.png"]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Photo ingest reports - What each automatic run over a photo folder did
+//!
+//! A rule that converts whatever lands in a photo folder (a watch folder) runs unattended,
+//! often overnight. Each run gets a report: how many photos were converted, how many were
+//! skipped as duplicates of photos the rule already ingested, how many were turned upright
+//! from their EXIF orientation, and which failed and why. Reports are kept per rule with
+//! the checksums of everything it ingested, which is what duplicates are checked against.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Reports kept per rule, newest last
+pub const MAX_REPORTS: usize = 30;
+
+/// A file the run couldn't convert
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IngestFailure {
+    pub input_path: String,
+    pub reason: String,
+}
+
+/// A file skipped because the rule already ingested the same photo
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IngestDuplicate {
+    pub input_path: String,
+    /// The earlier file with the same content, when it was seen in this run
+    pub duplicate_of: Option<String>,
+}
+
+/// One run of a rule, emitted as "ingest-report" when it finishes
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct IngestReport {
+    pub rule_id: String,
+    /// RFC 3339
+    pub started_at: String,
+    pub finished_at: String,
+    pub converted: usize,
+    /// Converted photos whose EXIF orientation was applied to the pixels
+    pub auto_rotated: usize,
+    pub duplicates: Vec<IngestDuplicate>,
+    pub failures: Vec<IngestFailure>,
+    /// Output of each converted file
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+impl IngestReport {
+    pub fn skipped_duplicates(&self) -> usize {
+        self.duplicates.len()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failures.len()
+    }
+
+    /// "12 converted (3 rotated), 2 duplicates skipped, 1 failed"
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} converted", self.converted);
+        if self.auto_rotated > 0 {
+            summary.push_str(&format!(" ({} rotated)", self.auto_rotated));
+        }
+        if !self.duplicates.is_empty() {
+            summary.push_str(&format!(", {} duplicate{} skipped", self.duplicates.len(), if self.duplicates.len() == 1 { "" } else { "s" }));
+        }
+        if !self.failures.is_empty() {
+            summary.push_str(&format!(", {} failed", self.failures.len()));
+        }
+        summary
+    }
+}
+
+/// Everything kept for one rule
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct IngestHistory {
+    /// SHA-256 of every photo the rule converted
+    #[serde(default)]
+    pub seen: BTreeSet<String>,
+    #[serde(default)]
+    pub reports: Vec<IngestReport>,
+}
+
+impl IngestHistory {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read ingest reports: {}", e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse ingest reports: {}", e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize ingest reports: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write ingest reports: {}", e))
+    }
+
+    /// Adds a finished run, dropping the oldest beyond MAX_REPORTS
+    pub fn push_report(&mut self, report: IngestReport) {
+        self.reports.push(report);
+        let excess = self.reports.len().saturating_sub(MAX_REPORTS);
+        self.reports.drain(..excess);
+    }
+}
+
+/// Whether a photo with this EXIF orientation is turned upright by the conversion
+pub fn is_auto_rotated(orientation: u16, keep_orientation: bool) -> bool {
+    !keep_orientation && (2..=8).contains(&orientation)
+}
+
+/// File holding a rule's reports: "ingest-<rule>.json", with the id reduced to safe characters
+pub fn history_file_name(rule_id: &str) -> String {
+    let id: String = rule_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(64)
+        .collect();
+    format!("ingest-{}.json", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut report = IngestReport { converted: 12, auto_rotated: 3, ..Default::default() };
+        assert_eq!(report.summary(), "12 converted (3 rotated)");
+        report.duplicates.push(IngestDuplicate { input_path: "/in/a.heic".to_string(), duplicate_of: None });
+        report.failures.push(IngestFailure { input_path: "/in/b.heic".to_string(), reason: "damaged".to_string() });
+        assert_eq!(report.summary(), "12 converted (3 rotated), 1 duplicate skipped, 1 failed");
+        assert_eq!((report.skipped_duplicates(), report.failed()), (1, 1));
+    }
+
+    #[test]
+    fn test_push_report_keeps_the_newest() {
+        let mut history = IngestHistory::default();
+        for i in 0..MAX_REPORTS + 5 {
+            history.push_report(IngestReport { converted: i, ..Default::default() });
+        }
+        assert_eq!(history.reports.len(), MAX_REPORTS);
+        assert_eq!(history.reports[0].converted, 5);
+    }
+
+    #[test]
+    fn test_is_auto_rotated() {
+        assert!(is_auto_rotated(6, false));
+        assert!(!is_auto_rotated(6, true));
+        assert!(!is_auto_rotated(1, false));
+        assert!(!is_auto_rotated(0, false));
+    }
+
+    #[test]
+    fn test_history_file_name() {
+        assert_eq!(history_file_name("camera-roll"), "ingest-camera-roll.json");
+        assert_eq!(history_file_name("../Photos/2026"), "ingest-___Photos_2026.json");
+    }
+}
@@ -0,0 +1,134 @@
+//! Busy inputs - Files that are still downloading or being written
+//!
+//! Browsers write a download under a temporary name (`report.pdf.crdownload`, Firefox's
+//! `.part` next to an empty `report.pdf`) and rename it at the end, and a copy from a slow
+//! drive grows for a while. Converting such a file reads partial data, so conversions check
+//! the input first, wait for it to settle and otherwise fail with a "file in use" error.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Extensions browsers and download managers give files they are still writing
+pub const PARTIAL_DOWNLOAD_EXTENSIONS: &[&str] = &["crdownload", "part", "partial", "download", "opdownload", "!ut", "!qb"];
+
+/// Errors about busy inputs start with this, so the frontend can offer to retry
+pub const FILE_IN_USE_PREFIX: &str = "File in use: ";
+
+/// A file changed this recently may still be written to, so its size is watched first
+pub const RECENT_WRITE: Duration = Duration::from_secs(3);
+
+/// How long the size has to stay the same
+pub const SETTLE_INTERVAL: Duration = Duration::from_millis(750);
+
+/// How long a conversion waits for a busy input by default
+pub const DEFAULT_INPUT_WAIT_SECS: u32 = 30;
+
+/// Whether an input can be converted now
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InputState {
+    Ready,
+    /// A browser's partial download, or the file it will become
+    Downloading,
+    /// The size or modification time changed while it was watched
+    Growing,
+    /// Another program has it open for writing (Windows)
+    Locked,
+}
+
+/// Size and modification time of a file at one moment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSample {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Whether `path` is itself a partial download (`report.pdf.crdownload`)
+pub fn is_partial_download(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PARTIAL_DOWNLOAD_EXTENSIONS.iter().any(|partial| ext.eq_ignore_ascii_case(partial)))
+}
+
+/// The names a partial download of `path` would have while it's being written
+pub fn partial_download_names(path: &Path) -> Vec<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Vec::new();
+    };
+    PARTIAL_DOWNLOAD_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            let mut partial = name.to_os_string();
+            partial.push(".");
+            partial.push(ext);
+            path.with_file_name(partial)
+        })
+        .collect()
+}
+
+/// Whether a file last written at `modified` may still be written to at `now`
+pub fn recently_modified(modified: Option<SystemTime>, now: SystemTime) -> bool {
+    // A time in the future (clock skew on a network share) counts as recent too
+    modified.is_some_and(|modified| !now.duration_since(modified).is_ok_and(|age| age >= RECENT_WRITE))
+}
+
+/// Whether two samples taken SETTLE_INTERVAL apart show the file is no longer growing
+pub fn is_settled(before: &FileSample, after: &FileSample) -> bool {
+    before == after
+}
+
+/// The "file in use" error for an input that didn't become ready
+pub fn busy_message(path: &Path, state: InputState, waited_secs: u32) -> String {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let reason = match state {
+        InputState::Downloading => format!("\"{}\" is still downloading. Convert it once the download has finished.", name),
+        InputState::Growing if waited_secs > 0 => format!("\"{}\" was still being written after {} seconds. Try again once it's complete.", name, waited_secs),
+        InputState::Growing => format!("\"{}\" is still being written. Try again once it's complete.", name),
+        InputState::Locked => format!("\"{}\" is open in another program. Close it there and try again.", name),
+        InputState::Ready => format!("\"{}\" is ready.", name),
+    };
+    format!("{}{}", FILE_IN_USE_PREFIX, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_downloads() {
+        assert!(is_partial_download(Path::new("/downloads/report.pdf.crdownload")));
+        assert!(is_partial_download(Path::new("/downloads/clip.mp4.PART")));
+        assert!(!is_partial_download(Path::new("/downloads/report.pdf")));
+
+        let names = partial_download_names(Path::new("/downloads/report.pdf"));
+        assert_eq!(names[0], Path::new("/downloads/report.pdf.crdownload"));
+        assert!(names.contains(&PathBuf::from("/downloads/report.pdf.part")));
+    }
+
+    #[test]
+    fn test_recently_modified() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert!(recently_modified(Some(now - Duration::from_secs(1)), now));
+        assert!(recently_modified(Some(now + Duration::from_secs(5)), now));
+        assert!(!recently_modified(Some(now - Duration::from_secs(60)), now));
+        assert!(!recently_modified(None, now));
+    }
+
+    #[test]
+    fn test_is_settled() {
+        let modified = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(10));
+        let sample = FileSample { len: 1024, modified };
+        assert!(is_settled(&sample, &sample));
+        assert!(!is_settled(&sample, &FileSample { len: 2048, ..sample }));
+        assert!(!is_settled(&sample, &FileSample { modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(11)), ..sample }));
+    }
+
+    #[test]
+    fn test_busy_message() {
+        let message = busy_message(Path::new("/downloads/clip.mp4"), InputState::Growing, 30);
+        assert!(message.starts_with(FILE_IN_USE_PREFIX));
+        assert!(message.contains("\"clip.mp4\" was still being written after 30 seconds"));
+        assert!(busy_message(Path::new("a.docx"), InputState::Locked, 0).contains("open in another program"));
+    }
+}
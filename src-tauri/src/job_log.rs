@@ -0,0 +1,159 @@
+//! Job logs - One file per failed conversion, with everything needed to reproduce it
+//!
+//! When a tool fails, its command line, the environment variables ConvertSave set for it,
+//! the timings and its full output are written to `jobs/` in the app log directory. The
+//! error message ends with the file's path, so a bug report can attach it instead of
+//! asking the user to reproduce the failure with a debug build.
+
+use std::path::Path;
+
+/// Folder inside the app log directory
+pub const JOB_LOG_DIR: &str = "jobs";
+
+/// Job logs kept; older ones are deleted when a new one is written
+pub const MAX_JOB_LOGS: usize = 50;
+
+/// Starts the line of an error message that holds the job log's path
+pub const LOG_PATH_PREFIX: &str = "Log file: ";
+
+/// What one tool run did
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobLog {
+    pub tool: String,
+    pub input_path: String,
+    pub output_path: String,
+    pub command_line: String,
+    /// Variables set (or removed, with None) on the command, on top of the app's environment
+    pub env: Vec<(String, Option<String>)>,
+    /// RFC 3339
+    pub started_at: String,
+    pub duration_secs: f64,
+    pub exit_status: String,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl JobLog {
+    pub fn render(&self) -> String {
+        let mut text = format!("ConvertSave {} job log\n\n", env!("CARGO_PKG_VERSION"));
+        text.push_str(&format!("Tool:     {}\n", self.tool));
+        text.push_str(&format!("Input:    {}\n", self.input_path));
+        text.push_str(&format!("Output:   {}\n", self.output_path));
+        text.push_str(&format!("Started:  {}\n", self.started_at));
+        text.push_str(&format!("Duration: {:.2}s\n", self.duration_secs));
+        text.push_str(&format!("Exit:     {}\n", self.exit_status));
+        text.push_str(&format!("OS:       {} {}\n", std::env::consts::OS, std::env::consts::ARCH));
+
+        text.push_str("\n== Command ==\n");
+        text.push_str(&self.command_line);
+        text.push('\n');
+
+        text.push_str("\n== Environment ==\n");
+        if self.env.is_empty() {
+            text.push_str("(inherited)\n");
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => text.push_str(&format!("{}={}\n", key, value)),
+                None => text.push_str(&format!("{} (removed)\n", key)),
+            }
+        }
+
+        for (title, output) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            text.push_str(&format!("\n== {} ==\n", title));
+            text.push_str(if output.trim().is_empty() { "(empty)\n" } else { output });
+            if !output.ends_with('\n') && !output.trim().is_empty() {
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// "20261016-153012-0003-ffmpeg-clip.log": sorts by time, and says what ran on what. `seq`
+/// keeps two failures in the same second from overwriting each other
+pub fn job_log_file_name(stamp: &str, seq: u64, tool: &str, input: &Path) -> String {
+    let stem = input.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(40)
+        .collect();
+    format!("{}-{:04}-{}-{}.log", stamp, seq, tool, stem)
+}
+
+/// `error` with the job log's path on its own line at the end
+pub fn with_log_path(error: &str, log_path: &Path) -> String {
+    format!("{}\n\n{}{}", error, LOG_PATH_PREFIX, log_path.display())
+}
+
+/// The job log path at the end of an error message, if it has one
+pub fn log_path_from_error(error: &str) -> Option<&str> {
+    let (_, path) = error.rsplit_once(LOG_PATH_PREFIX)?;
+    let path = path.trim();
+    (!path.is_empty() && !path.contains('\n')).then_some(path)
+}
+
+/// The logs to delete from `names` so `keep` remain, oldest first (the names sort by time)
+pub fn logs_to_prune(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.retain(|name| name.ends_with(".log"));
+    names.sort();
+    let excess = names.len().saturating_sub(keep);
+    names.truncate(excess);
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job_log() -> JobLog {
+        JobLog {
+            tool: "ffmpeg".to_string(),
+            input_path: "/in/clip.mov".to_string(),
+            output_path: "/out/clip.mp4".to_string(),
+            command_line: "ffmpeg -i /in/clip.mov /out/clip.mp4".to_string(),
+            env: vec![("FONTCONFIG_FILE".to_string(), Some("/app/fonts.conf".to_string())), ("DYLD_LIBRARY_PATH".to_string(), None)],
+            started_at: "2026-10-16T15:30:12+00:00".to_string(),
+            duration_secs: 1.5,
+            exit_status: "exit status: 1".to_string(),
+            stdout: String::new(),
+            stderr: "Unknown encoder 'libx265'".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let text = job_log().render();
+        assert!(text.contains("Tool:     ffmpeg\n"));
+        assert!(text.contains("Duration: 1.50s\n"));
+        assert!(text.contains("== Command ==\nffmpeg -i /in/clip.mov /out/clip.mp4\n"));
+        assert!(text.contains("FONTCONFIG_FILE=/app/fonts.conf\nDYLD_LIBRARY_PATH (removed)\n"));
+        assert!(text.contains("== stdout ==\n(empty)\n"));
+        assert!(text.ends_with("== stderr ==\nUnknown encoder 'libx265'\n"));
+    }
+
+    #[test]
+    fn test_job_log_file_name() {
+        assert_eq!(job_log_file_name("20261016-153012", 3, "ffmpeg", Path::new("/in/my clip (1).mov")), "20261016-153012-0003-ffmpeg-my_clip__1_.log");
+        assert_ne!(
+            job_log_file_name("20261016-153012", 3, "ffmpeg", Path::new("/in/clip.mov")),
+            job_log_file_name("20261016-153012", 4, "ffmpeg", Path::new("/in/clip.mov"))
+        );
+    }
+
+    #[test]
+    fn test_log_path_in_error() {
+        let error = with_log_path("Conversion failed.", Path::new("/logs/jobs/a.log"));
+        assert_eq!(error, "Conversion failed.\n\nLog file: /logs/jobs/a.log");
+        assert_eq!(log_path_from_error(&error), Some("/logs/jobs/a.log"));
+        assert_eq!(log_path_from_error("Conversion failed."), None);
+    }
+
+    #[test]
+    fn test_logs_to_prune() {
+        let names = vec!["20261016-b.log".to_string(), "20261015-a.log".to_string(), "20261017-c.log".to_string(), "notes.txt".to_string()];
+        assert_eq!(logs_to_prune(names.clone(), 2), vec!["20261015-a.log".to_string()]);
+        assert!(logs_to_prune(names, 5).is_empty());
+    }
+}
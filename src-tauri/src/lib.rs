@@ -1,4 +1,106 @@
 pub use tauri;
 
+// Archive entry paths for tool downloads
+pub mod archive;
+
 // Conversion module with testable logic
 pub mod conversion;
+
+// Typed FFmpeg filter graphs
+pub mod filter_graph;
+
+// Typed ImageMagick command lines
+pub mod magick;
+
+// Conversion slots and foreground/background priority
+pub mod scheduler;
+
+// Conversion history with job notes and labels
+pub mod history;
+
+// HEIC migration planning, manifests and rollback
+pub mod migration;
+
+// Folder audit: formats, sizes and projected savings
+pub mod audit;
+
+// Image metadata (EXIF/IPTC/XMP) parsing
+pub mod metadata;
+
+// Built-in sRGB ICC profile for color conversion
+pub mod icc;
+
+// Output file naming templates
+pub mod naming;
+
+// XLSX page setup for PDF export
+pub mod spreadsheet;
+
+// Email (.eml/.msg) parsing and print layout
+pub mod email;
+
+// Calendar (ICS) and contact (VCF) conversion to and from CSV
+pub mod pim;
+
+// Synthesized fixtures for the integration tests
+pub mod fixtures;
+
+// Tools bundled into the installer, and tool folder layouts
+pub mod bundled_tools;
+
+// Live Photo and motion photo videos
+pub mod live_photo;
+
+// App icon sets (.icns, .ico, iOS and Android) from one picture
+pub mod icon_set;
+
+// .convertsave.json sidecars recording how an output was made
+pub mod sidecar;
+
+// Media inspection (ffprobe / ffmpeg banner parsing)
+pub mod probe;
+
+// Chapters from media and cue sheets, and splitting audiobooks by chapter
+pub mod chapters;
+
+// Inputs that are still downloading or being written
+pub mod input_state;
+
+// SHA-256 checksums of outputs and .sha256 files
+pub mod checksum;
+
+// srcset-ready sets of one picture at several widths and formats
+pub mod responsive;
+
+// Per-conversion log files for failed tool runs
+pub mod job_log;
+
+// Runtime feature flags for experimental backends
+pub mod feature_flags;
+
+// Short, plain-language summaries of error messages
+pub mod error_summary;
+
+// Time limits and stall detection for external tools
+pub mod watchdog;
+
+// Reports of automatic photo folder runs
+pub mod ingest;
+
+// Retry policies with backoff for network calls
+pub mod retry;
+
+// Sleep inhibition while converting, and sleep or shutdown afterwards
+pub mod power;
+
+// Machine-wide configuration layered under the user's
+pub mod config;
+
+// Queue journal for resuming batches after a crash
+pub mod resume;
+
+// Where tools are looked for, and which copy is used
+pub mod tool_search;
+
+// File format detection by signature
+pub mod signature;
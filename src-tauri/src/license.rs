@@ -2,7 +2,7 @@
 //!
 //! Handles the complete licensing flow:
 //! 1. Check for local license file
-//! 2. Validate license (MAC address, expiration)
+//! 2. Validate license (device ID, expiration)
 //! 3. Refresh from server if needed
 //! 4. Activate new devices with product key
 
@@ -11,11 +11,14 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use scrypt::{scrypt, Params};
 use serde::{Deserialize, Serialize};
+use convertsave_lib::retry::RetryPolicy;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 /// The encryption key - MUST match LICENSE_ENCRYPTION_KEY from the website
 const ENCRYPTION_KEY: &str = match option_env!("LICENSE_ENCRYPTION_KEY") {
@@ -41,6 +44,16 @@ const APP_IDENTIFIER: &str = "com.convertsave";
 /// Grace period in days before locking app after subscription expires
 const GRACE_PERIOD_DAYS: i64 = 2;
 
+/// How far the system clock may go back (NTP corrections, a mis-set time zone) before
+/// the last time seen is trusted over it
+const CLOCK_ROLLBACK_TOLERANCE_HOURS: i64 = 24;
+
+/// How long a license check is reused before asking the server again
+pub const LICENSE_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Event emitted to the frontend whenever the license status changes
+pub const LICENSE_STATUS_EVENT: &str = "license-status-changed";
+
 /// Decrypted license data structure (matches server's LicenseData)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -74,6 +87,18 @@ pub struct LicenseStatus {
     pub product_key: Option<String>,
 }
 
+impl LicenseStatus {
+    /// Checks if two statuses differ in a way the UI cares about (not just days remaining)
+    pub fn differs_from(&self, other: &LicenseStatus) -> bool {
+        self.is_valid != other.is_valid
+            || self.is_activated != other.is_activated
+            || self.plan_type != other.plan_type
+            || self.in_grace_period != other.in_grace_period
+            || self.requires_activation != other.requires_activation
+            || self.product_key != other.product_key
+    }
+}
+
 impl Default for LicenseStatus {
     fn default() -> Self {
         Self {
@@ -89,6 +114,54 @@ impl Default for LicenseStatus {
     }
 }
 
+/// Response from /api/license/checkout
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckoutResponse {
+    success: bool,
+    checkout_url: Option<String>,
+    token: Option<String>,
+    error: Option<String>,
+}
+
+/// Response from /api/license/checkout/status
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckoutStatusResponse {
+    success: bool,
+    status: Option<CheckoutState>,
+    product_key: Option<String>,
+    error: Option<String>,
+}
+
+/// A checkout started from the app. The token is bound to this device on the server,
+/// so only this device can pick up the key it produces.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckoutSession {
+    pub checkout_url: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckoutState {
+    /// Waiting for the user to finish paying in the browser
+    Pending,
+    Completed,
+    /// Abandoned or timed out on the server
+    Expired,
+}
+
+/// Result of polling a checkout, returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckoutStatus {
+    pub state: CheckoutState,
+    /// Set once the purchased key has been activated on this device
+    pub license_status: Option<LicenseStatus>,
+}
+
 /// Response from /api/license/lookup
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -199,58 +272,212 @@ pub fn decrypt_license(encrypted_license: &str) -> Result<LicenseData, String> {
     serde_json::from_slice(&decrypted).map_err(|e| format!("JSON parse error: {}", e))
 }
 
-/// Get the current device's MAC address
+/// Get the path to the saved device ID
+fn get_device_id_path() -> Result<PathBuf, String> {
+    Ok(get_license_path()?.with_file_name("device-id"))
+}
+
+/// Get the ID this device is licensed under (sent to the server as `macAddress`).
+///
+/// The first time it's needed the first available identifier is picked - MAC address,
+/// then the platform machine GUID, then a generated UUID - and saved, so a VPN adapter
+/// or a MAC that later goes missing doesn't turn into a different device. A license saved
+/// earlier keeps the identifier it was activated under while this device still has it,
+/// e.g. a locally administered MAC that's no longer picked.
+pub fn get_device_id() -> Result<String, String> {
+    let path = get_device_id_path()?;
+    if let Ok(saved) = fs::read_to_string(&path) {
+        let saved = saved.trim();
+        if !saved.is_empty() {
+            return Ok(saved.to_string());
+        }
+    }
+
+    let licensed_id = load_license()
+        .and_then(|license| decrypt_license(&license))
+        .ok()
+        .map(|license| license.mac_address);
+    let device_id = licensed_id
+        .filter(|licensed_id| matches_identifier(licensed_id, &device_identifiers()))
+        .or_else(|| get_mac_address().ok())
+        .or_else(|| get_machine_guid().ok())
+        .unwrap_or_else(generate_uuid);
+    fs::write(&path, &device_id).map_err(|e| format!("Failed to save device ID: {}", e))?;
+    Ok(device_id)
+}
+
+/// Checks if a license issued to `licensed_id` belongs to this device. Licenses activated
+/// before the device ID was saved are tied to whichever identifier was current then.
+fn is_this_device(licensed_id: &str, device_id: &str) -> bool {
+    licensed_id.eq_ignore_ascii_case(device_id) || matches_identifier(licensed_id, &device_identifiers())
+}
+
+/// Every identifier a license may have been activated under on this device: all of its
+/// MAC addresses (locally administered ones included) and the platform machine GUID
+fn device_identifiers() -> Vec<String> {
+    let mut identifiers = mac_addresses();
+    identifiers.extend(get_machine_guid().ok());
+    identifiers
+}
+
+/// Checks if `licensed_id` is one of `identifiers`, ignoring case and MAC separators
+fn matches_identifier(licensed_id: &str, identifiers: &[String]) -> bool {
+    let normalize = |id: &str| id.trim().replace('-', ":").to_uppercase();
+    let licensed_id = normalize(licensed_id);
+    !licensed_id.is_empty() && identifiers.iter().any(|id| normalize(id) == licensed_id)
+}
+
+/// Generate a random UUID (v4) for devices with no usable hardware identifier
+fn generate_uuid() -> String {
+    use aes_gcm::aead::{rand_core::RngCore, OsRng};
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]).to_uppercase()
+}
+
+/// Checks if a MAC address is well formed and not all zeros
+fn is_mac(mac: &str) -> bool {
+    let digits: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    digits.len() == 12 && !digits.chars().all(|c| c == '0')
+}
+
+/// Checks if a MAC address identifies real hardware. VPN and virtual adapters use
+/// locally administered (often randomized) addresses, which change between sessions.
+fn is_usable_mac(mac: &str) -> bool {
+    if !is_mac(mac) {
+        return false;
+    }
+    let digits: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    match u8::from_str_radix(&digits[0..2], 16) {
+        Ok(first_octet) => first_octet & 0x02 == 0,
+        Err(_) => false,
+    }
+}
+
+/// Get the platform's machine GUID (set when the OS is installed)
 #[cfg(target_os = "windows")]
-pub fn get_mac_address() -> Result<String, String> {
+pub fn get_machine_guid() -> Result<String, String> {
     use std::process::Command;
     use std::os::windows::process::CommandExt;
-    
+
     const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-    let output = Command::new("getmac")
-        .args(["/fo", "csv", "/nh"])
+    let output = Command::new("reg")
+        .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
         .creation_flags(CREATE_NO_WINDOW)
         .output()
-        .map_err(|e| format!("Failed to get MAC address: {}", e))?;
+        .map_err(|e| format!("Failed to get machine GUID: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
 
+    // "    MachineGuid    REG_SZ    1c2d..."
     for line in stdout.lines() {
-        if let Some(mac) = line.split(',').next() {
-            let mac = mac.trim_matches('"').replace('-', ":");
-            if !mac.is_empty() && mac != "N/A" && !mac.contains("Media") {
-                return Ok(mac.to_uppercase());
+        if line.contains("MachineGuid") {
+            if let Some(guid) = line.split_whitespace().last() {
+                return Ok(guid.to_uppercase());
             }
         }
     }
 
-    Err("Could not determine MAC address".to_string())
+    Err("Could not determine machine GUID".to_string())
 }
 
 #[cfg(target_os = "macos")]
-pub fn get_mac_address() -> Result<String, String> {
+pub fn get_machine_guid() -> Result<String, String> {
     use std::process::Command;
 
-    let output = Command::new("ifconfig")
-        .arg("en0")
+    let output = Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
         .output()
-        .map_err(|e| format!("Failed to get MAC address: {}", e))?;
+        .map_err(|e| format!("Failed to get machine GUID: {}", e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
 
+    // "IOPlatformUUID" = "564D....-...."
     for line in stdout.lines() {
-        if line.contains("ether ") {
-            if let Some(mac) = line.split_whitespace().nth(1) {
-                return Ok(mac.to_uppercase());
+        if line.contains("IOPlatformUUID") {
+            if let Some(guid) = line.split('"').nth(3) {
+                return Ok(guid.to_uppercase());
             }
         }
     }
 
-    Err("Could not determine MAC address".to_string())
+    Err("Could not determine machine GUID".to_string())
 }
 
 #[cfg(target_os = "linux")]
+pub fn get_machine_guid() -> Result<String, String> {
+    for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+        if let Ok(id) = fs::read_to_string(path) {
+            let id = id.trim();
+            if !id.is_empty() {
+                return Ok(id.to_uppercase());
+            }
+        }
+    }
+
+    Err("Could not determine machine GUID".to_string())
+}
+
+/// Get the current device's MAC address: the first one that identifies real hardware
 pub fn get_mac_address() -> Result<String, String> {
+    mac_addresses()
+        .into_iter()
+        .find(|mac| is_usable_mac(mac))
+        .ok_or_else(|| "Could not determine MAC address".to_string())
+}
+
+/// The device's MAC addresses in adapter order, upper case, locally administered ones included
+#[cfg(target_os = "windows")]
+fn mac_addresses() -> Vec<String> {
+    use std::process::Command;
+    use std::os::windows::process::CommandExt;
+    
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let Ok(output) = Command::new("getmac")
+        .args(["/fo", "csv", "/nh"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .filter_map(|line| line.split(',').next())
+        .map(|mac| mac.trim_matches('"').replace('-', ":").to_uppercase())
+        .filter(|mac| is_mac(mac))
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn mac_addresses() -> Vec<String> {
+    use std::process::Command;
+
+    let Ok(output) = Command::new("ifconfig").arg("en0").output() else {
+        return Vec::new();
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .filter(|line| line.contains("ether "))
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|mac| mac.to_uppercase())
+        .filter(|mac| is_mac(mac))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn mac_addresses() -> Vec<String> {
+    let mut macs = Vec::new();
     let net_dir = "/sys/class/net";
     if let Ok(entries) = fs::read_dir(net_dir) {
         for entry in entries.flatten() {
@@ -268,20 +495,109 @@ pub fn get_mac_address() -> Result<String, String> {
             let address_path = entry.path().join("address");
             if let Ok(mac) = fs::read_to_string(&address_path) {
                 let mac = mac.trim().to_uppercase();
-                if !mac.is_empty() && mac != "00:00:00:00:00:00" {
-                    return Ok(mac);
+                if is_mac(&mac) {
+                    macs.push(mac);
                 }
             }
         }
     }
+    macs
+}
 
-    Err("Could not determine MAC address".to_string())
+/// Parse a date from the license. Dates with an offset are converted to UTC; dates without
+/// one are taken as UTC, and a bare date means the end of that day so no time zone loses it.
+fn parse_license_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(date) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(parsed) = NaiveDateTime::parse_from_str(date, format) {
+            return Some(Utc.from_utc_datetime(&parsed));
+        }
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(23, 59, 59))
+        .map(|end_of_day| Utc.from_utc_datetime(&end_of_day))
+}
+
+/// Get the path to the file holding the latest time seen
+fn get_last_seen_path() -> Result<PathBuf, String> {
+    Ok(get_license_path()?.with_file_name("last-seen"))
+}
+
+/// The current time for expiry checks, hardened against the clock being turned back.
+///
+/// UTC is used throughout, so travelling between time zones changes nothing. The result is
+/// never earlier than: when the license was issued, the latest time seen on a previous check
+/// (minus a day of tolerance), or the app's start time plus the monotonic time since.
+fn trusted_now(license: &LicenseData) -> DateTime<Utc> {
+    static SESSION_START: OnceLock<(Instant, DateTime<Utc>)> = OnceLock::new();
+
+    let now = Utc::now();
+    let (started, started_at) = SESSION_START.get_or_init(|| (Instant::now(), now));
+    let session_now = chrono::Duration::from_std(started.elapsed()).ok().map(|elapsed| *started_at + elapsed);
+    let last_seen = load_last_seen();
+    let trusted = trusted_time(now, parse_license_date(&license.issued_at), session_now, last_seen);
+
+    // Only ever move the high-water mark forward here; the server's clock moves it back
+    if last_seen.map(|last_seen| trusted > last_seen).unwrap_or(true) {
+        save_last_seen(trusted);
+    }
+
+    trusted
+}
+
+/// The latest of the system time, the issue time, the session's monotonic time and the
+/// time last seen minus the tolerance
+fn trusted_time(
+    now: DateTime<Utc>,
+    issued_at: Option<DateTime<Utc>>,
+    session_now: Option<DateTime<Utc>>,
+    last_seen: Option<DateTime<Utc>>,
+) -> DateTime<Utc> {
+    let tolerated = last_seen.map(|last_seen| last_seen - chrono::Duration::hours(CLOCK_ROLLBACK_TOLERANCE_HOURS));
+    [issued_at, session_now, tolerated].into_iter().flatten().fold(now, DateTime::max)
+}
+
+fn load_last_seen() -> Option<DateTime<Utc>> {
+    get_last_seen_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|saved| parse_license_date(&saved))
+}
+
+fn save_last_seen(time: DateTime<Utc>) {
+    if let Ok(path) = get_last_seen_path() {
+        let _ = fs::write(path, time.to_rfc3339());
+    }
+}
+
+/// The high-water mark once the server has answered at `server_now`: a mark ahead of the
+/// server's clock came from a clock that ran fast, and is brought back to it
+fn corrected_last_seen(last_seen: Option<DateTime<Utc>>, server_now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    last_seen.filter(|last_seen| *last_seen > server_now).map(|_| server_now)
+}
+
+/// The time in a server response's Date header
+fn server_date(response: &reqwest::Response) -> Option<DateTime<Utc>> {
+    let date = response.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(date).ok().map(|date| date.with_timezone(&Utc))
+}
+
+/// Bounds the high-water mark by the server's time after a successful validation, so a
+/// clock that was once set ahead doesn't keep a subscription expired for good
+fn correct_last_seen(server_now: Option<DateTime<Utc>>) {
+    if let Some(corrected) = server_now.and_then(|server_now| corrected_last_seen(load_last_seen(), server_now)) {
+        save_last_seen(corrected);
+    }
 }
 
 /// Validate a decrypted license
 fn validate_license_data(license: &LicenseData, mac_address: &str) -> LicenseStatus {
-    // Check MAC address
-    if license.mac_address != mac_address {
+    // Check the license belongs to this device
+    if !is_this_device(&license.mac_address, mac_address) {
         return LicenseStatus {
             is_valid: false,
             error: Some("License not valid for this device".to_string()),
@@ -307,8 +623,8 @@ fn validate_license_data(license: &LicenseData, mac_address: &str) -> LicenseSta
 
     // Check subscription expiration
     if let Some(end_date_str) = &license.subscription_end_date {
-        if let Ok(end_date) = end_date_str.parse::<DateTime<Utc>>() {
-            let now = Utc::now();
+        if let Some(end_date) = parse_license_date(end_date_str) {
+            let now = trusted_now(license);
             let days_remaining = (end_date - now).num_days();
 
             if days_remaining < -GRACE_PERIOD_DAYS {
@@ -369,7 +685,7 @@ fn validate_license_data(license: &LicenseData, mac_address: &str) -> LicenseSta
 /// Check license status - main entry point for the app
 /// This implements the full flow described
 pub async fn check_license_status() -> LicenseStatus {
-    let mac_address = match get_mac_address() {
+    let mac_address = match get_device_id() {
         Ok(mac) => mac,
         Err(e) => {
             return LicenseStatus {
@@ -442,14 +758,15 @@ pub async fn check_license_status() -> LicenseStatus {
 
 /// Look up license by MAC address from server
 async fn lookup_license_by_mac(mac_address: &str) -> Result<Option<String>, String> {
-    let client = reqwest::Client::new();
+    let client = crate::create_api_client()?;
 
-    let response = client
-        .post(format!("{}/lookup", API_BASE_URL))
-        .json(&serde_json::json!({ "macAddress": mac_address }))
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = crate::send_with_retry("Looking up the license", RetryPolicy::LICENSE, || {
+        client
+            .post(format!("{}/lookup", API_BASE_URL))
+            .json(&serde_json::json!({ "macAddress": mac_address }))
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
 
     let data: LookupResponse = response
         .json()
@@ -468,18 +785,20 @@ async fn refresh_license_from_server(
     encrypted_license: &str,
     mac_address: &str,
 ) -> Result<LicenseStatus, String> {
-    let client = reqwest::Client::new();
-
-    let response = client
-        .post(format!("{}/refresh", API_BASE_URL))
-        .json(&serde_json::json!({
-            "license": encrypted_license,
-            "macAddress": mac_address
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
-
+    let client = crate::create_api_client()?;
+
+    let response = crate::send_with_retry("Refreshing the license", RetryPolicy::LICENSE, || {
+        client
+            .post(format!("{}/refresh", API_BASE_URL))
+            .json(&serde_json::json!({
+                "license": encrypted_license,
+                "macAddress": mac_address
+            }))
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
+
+    let server_now = server_date(&response);
     let data: RefreshResponse = response
         .json()
         .await
@@ -487,6 +806,7 @@ async fn refresh_license_from_server(
 
     if data.success {
         if let Some(new_license) = data.license {
+            correct_last_seen(server_now);
             // Save the new license
             save_license(&new_license)?;
 
@@ -513,8 +833,8 @@ pub async fn activate_with_product_key(
     product_key: &str,
     device_name: Option<&str>,
 ) -> Result<LicenseStatus, String> {
-    let mac_address = get_mac_address()?;
-    let client = reqwest::Client::new();
+    let mac_address = get_device_id()?;
+    let client = crate::create_api_client()?;
 
     let mut body = serde_json::json!({
         "productKey": product_key,
@@ -525,14 +845,16 @@ pub async fn activate_with_product_key(
         body["deviceName"] = serde_json::Value::String(name.to_string());
     }
 
-    let response = client
-        .post(format!("{}/validate", API_BASE_URL))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = crate::send_with_retry("Activating the license", RetryPolicy::LICENSE_CHANGE, || {
+        client
+            .post(format!("{}/validate", API_BASE_URL))
+            .json(&body)
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
 
     let status_code = response.status();
+    let server_now = server_date(&response);
     let data: ValidateResponse = response
         .json()
         .await
@@ -540,6 +862,7 @@ pub async fn activate_with_product_key(
 
     if data.success {
         if let Some(license) = data.license {
+            correct_last_seen(server_now);
             // Save the license locally
             save_license(&license)?;
 
@@ -560,20 +883,25 @@ pub async fn activate_with_product_key(
 
 /// Deactivate this device
 pub async fn deactivate_device() -> Result<(), String> {
-    let mac_address = get_mac_address()?;
     let encrypted_license = load_license()?;
+    // Deactivate the ID the license was issued to, even if this device's ID has changed since
+    let mac_address = match decrypt_license(&encrypted_license) {
+        Ok(license_data) => license_data.mac_address,
+        Err(_) => get_device_id()?,
+    };
 
-    let client = reqwest::Client::new();
+    let client = crate::create_api_client()?;
 
-    let response = client
-        .post(format!("{}/deactivate", API_BASE_URL))
-        .json(&serde_json::json!({
-            "license": encrypted_license,
-            "macAddress": mac_address
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = crate::send_with_retry("Deactivating the license", RetryPolicy::LICENSE_CHANGE, || {
+        client
+            .post(format!("{}/deactivate", API_BASE_URL))
+            .json(&serde_json::json!({
+                "license": encrypted_license,
+                "macAddress": mac_address
+            }))
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
 
     let data: serde_json::Value = response
         .json()
@@ -617,8 +945,8 @@ pub async fn change_product_key(
     new_product_key: &str,
     device_name: Option<&str>,
 ) -> Result<LicenseStatus, String> {
-    let mac_address = get_mac_address()?;
-    let client = reqwest::Client::new();
+    let mac_address = get_device_id()?;
+    let client = crate::create_api_client()?;
 
     let mut body = serde_json::json!({
         "newProductKey": new_product_key,
@@ -629,12 +957,13 @@ pub async fn change_product_key(
         body["deviceName"] = serde_json::Value::String(name.to_string());
     }
 
-    let response = client
-        .post(format!("{}/change", API_BASE_URL))
-        .json(&body)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    let response = crate::send_with_retry("Changing the product key", RetryPolicy::LICENSE_CHANGE, || {
+        client
+            .post(format!("{}/change", API_BASE_URL))
+            .json(&body)
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
 
     let status_code = response.status();
     let data: ChangeResponse = response
@@ -660,4 +989,182 @@ pub async fn change_product_key(
             "Failed to change product key".to_string()
         }
     }))
-}
\ No newline at end of file
+}
+
+/// Start a checkout for `plan` bound to this device
+pub async fn create_checkout_session(plan: &PlanType) -> Result<CheckoutSession, String> {
+    let mac_address = get_device_id()?;
+    let client = crate::create_api_client()?;
+
+    let response = crate::send_with_retry("Starting the checkout", RetryPolicy::LICENSE_CHANGE, || {
+        client
+            .post(format!("{}/checkout", API_BASE_URL))
+            .json(&serde_json::json!({
+                "planType": plan,
+                "macAddress": mac_address
+            }))
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
+
+    let data: CheckoutResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Parse error: {}", e))?;
+
+    match (data.success, data.checkout_url, data.token) {
+        (true, Some(checkout_url), Some(token)) => Ok(CheckoutSession { checkout_url, token }),
+        _ => Err(data.error.unwrap_or_else(|| "Failed to start checkout".to_string())),
+    }
+}
+
+/// Check on a checkout. Once it's paid, the new product key is activated on this device.
+pub async fn poll_checkout(token: &str) -> Result<CheckoutStatus, String> {
+    let mac_address = get_device_id()?;
+    let client = crate::create_api_client()?;
+
+    let response = crate::send_with_retry("Checking the checkout", RetryPolicy::LICENSE, || {
+        client
+            .post(format!("{}/checkout/status", API_BASE_URL))
+            .json(&serde_json::json!({
+                "token": token,
+                "macAddress": mac_address
+            }))
+    })
+    .await
+    .map_err(|e| format!("Network error: {}", e))?;
+
+    let data: CheckoutStatusResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Parse error: {}", e))?;
+
+    if !data.success {
+        return Err(data.error.unwrap_or_else(|| "Failed to check purchase status".to_string()));
+    }
+
+    match (data.status, data.product_key) {
+        (Some(CheckoutState::Completed), Some(product_key)) => {
+            let license_status = activate_with_product_key(&product_key, None).await?;
+            Ok(CheckoutStatus {
+                state: CheckoutState::Completed,
+                license_status: Some(license_status),
+            })
+        }
+        // Paid but the key isn't issued yet - keep polling
+        (Some(CheckoutState::Completed), None) | (Some(CheckoutState::Pending), _) | (None, _) => Ok(CheckoutStatus {
+            state: CheckoutState::Pending,
+            license_status: None,
+        }),
+        (Some(CheckoutState::Expired), _) => Ok(CheckoutStatus {
+            state: CheckoutState::Expired,
+            license_status: None,
+        }),
+    }
+}
+
+/// Last license check, shared by every window and startup path (kept in Tauri managed state).
+///
+/// The lock is held while a check runs, so concurrent callers wait for the one in-flight
+/// check instead of each going to the network.
+#[derive(Default)]
+pub struct LicenseCache {
+    last: tokio::sync::Mutex<Option<(Instant, LicenseStatus)>>,
+}
+
+impl LicenseCache {
+    /// Returns the cached status, checking again once it's older than `LICENSE_CACHE_TTL`
+    /// (or always, with `force`). The second value is the previous status if it changed.
+    pub async fn get(&self, force: bool) -> (LicenseStatus, Option<LicenseStatus>) {
+        let mut last = self.last.lock().await;
+
+        if let Some((checked_at, status)) = last.as_ref() {
+            if !force && checked_at.elapsed() < LICENSE_CACHE_TTL {
+                return (status.clone(), None);
+            }
+        }
+
+        let status = check_license_status().await;
+        let previous = last.take().map(|(_, previous)| previous);
+        *last = Some((Instant::now(), status.clone()));
+
+        let changed = previous.filter(|previous| previous.differs_from(&status));
+        (status, changed)
+    }
+
+    /// Stores a status obtained elsewhere (activation, deactivation). Returns true if it changed.
+    pub async fn set(&self, status: LicenseStatus) -> bool {
+        let mut last = self.last.lock().await;
+        let changed = match last.as_ref() {
+            Some((_, previous)) => previous.differs_from(&status),
+            None => true,
+        };
+        *last = Some((Instant::now(), status));
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> DateTime<Utc> {
+        parse_license_date(date).unwrap()
+    }
+
+    #[test]
+    fn test_is_usable_mac() {
+        assert!(is_usable_mac("00:1A:2B:3C:4D:5E"));
+        assert!(is_usable_mac("00-1a-2b-3c-4d-5e"));
+        // Locally administered (VPN, randomized Wi-Fi) and malformed addresses
+        assert!(!is_usable_mac("02:1A:2B:3C:4D:5E"));
+        assert!(!is_usable_mac("00:00:00:00:00:00"));
+        assert!(!is_usable_mac("00:1A:2B"));
+        assert!(is_mac("02:1A:2B:3C:4D:5E"));
+    }
+
+    #[test]
+    fn test_matches_identifier() {
+        let identifiers = vec!["00:1A:2B:3C:4D:5E".to_string(), "02:AA:BB:CC:DD:EE".to_string()];
+        assert!(matches_identifier("00:1a:2b:3c:4d:5e", &identifiers));
+        // A license bound to a locally administered MAC before those were skipped
+        assert!(matches_identifier("02-AA-BB-CC-DD-EE", &identifiers));
+        assert!(!matches_identifier("00:11:22:33:44:55", &identifiers));
+        assert!(!matches_identifier("", &identifiers));
+        assert!(!matches_identifier("00:1A:2B:3C:4D:5E", &[]));
+    }
+
+    #[test]
+    fn test_parse_license_date() {
+        assert_eq!(at("2026-03-01T10:00:00+02:00"), Utc.with_ymd_and_hms(2026, 3, 1, 8, 0, 0).unwrap());
+        assert_eq!(at("2026-03-01T10:00:00.250Z").timestamp_millis() % 1000, 250);
+        assert_eq!(at("2026-03-01T10:00:00"), Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap());
+        assert_eq!(at("2026-03-01 10:00:00"), Utc.with_ymd_and_hms(2026, 3, 1, 10, 0, 0).unwrap());
+        // A bare date lasts the whole day
+        assert_eq!(at(" 2026-03-01 "), Utc.with_ymd_and_hms(2026, 3, 1, 23, 59, 59).unwrap());
+        assert_eq!(parse_license_date("March 1st"), None);
+        assert_eq!(parse_license_date(""), None);
+    }
+
+    #[test]
+    fn test_trusted_time() {
+        let now = at("2026-03-10T12:00:00Z");
+        assert_eq!(trusted_time(now, None, None, None), now);
+        // The clock turned back before the license was issued
+        assert_eq!(trusted_time(now, Some(at("2026-03-11T00:00:00Z")), None, None), at("2026-03-11T00:00:00Z"));
+        assert_eq!(trusted_time(now, None, Some(at("2026-03-10T13:00:00Z")), None), at("2026-03-10T13:00:00Z"));
+        // Within the tolerance the system clock wins, beyond it the time last seen does
+        assert_eq!(trusted_time(now, None, None, Some(at("2026-03-11T06:00:00Z"))), now);
+        assert_eq!(trusted_time(now, None, None, Some(at("2026-03-20T12:00:00Z"))), at("2026-03-19T12:00:00Z"));
+        assert_eq!(trusted_time(now, Some(at("2026-01-01T00:00:00Z")), None, Some(at("2026-03-01T00:00:00Z"))), now);
+    }
+
+    #[test]
+    fn test_corrected_last_seen() {
+        let server_now = at("2026-03-10T12:00:00Z");
+        // Set by a clock that ran a year ahead
+        assert_eq!(corrected_last_seen(Some(at("2027-03-10T12:00:00Z")), server_now), Some(server_now));
+        assert_eq!(corrected_last_seen(Some(at("2026-03-09T12:00:00Z")), server_now), None);
+        assert_eq!(corrected_last_seen(None, server_now), None);
+    }
+}
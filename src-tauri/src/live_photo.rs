@@ -0,0 +1,268 @@
+//! Live Photos and motion photos - Finding the video that goes with a still
+//!
+//! iOS keeps a Live Photo as two files with the same name (IMG_0001.HEIC and IMG_0001.MOV).
+//! Android motion photos (Google, Samsung) append an MP4 to the JPEG or HEIC itself and
+//! record where it starts in the XMP metadata, behind a marker or in an `mpvd` box.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Outputs a Live Photo's video can be converted to
+pub const LIVE_PHOTO_OUTPUTS: &[&str] = &["gif", "mp4"];
+
+/// Extensions of the video half of an iOS Live Photo, as the Photos app exports them
+const PAIRED_VIDEO_EXTENSIONS: &[&str] = &["MOV", "mov", "MP4", "mp4"];
+
+/// Samsung writes this marker right before the embedded MP4
+const SAMSUNG_MARKER: &[u8] = b"MotionPhoto_Data";
+
+/// MP4/QuickTime major brands an embedded video can start with
+const VIDEO_BRANDS: &[&[u8; 4]] = &[b"mp41", b"mp42", b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"avc1", b"qt  "];
+
+/// Where the video of a Live Photo or motion photo is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveVideo {
+    /// A separate file next to the photo
+    Paired(PathBuf),
+    /// Bytes inside the photo file
+    Embedded(Range<usize>),
+}
+
+/// The video saved next to an iOS Live Photo: same folder, same name, .MOV (or .MP4)
+pub fn paired_video(photo: &Path) -> Option<PathBuf> {
+    let stem = photo.file_stem()?;
+    let dir = photo.parent().unwrap_or(Path::new(""));
+    PAIRED_VIDEO_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            let mut name = stem.to_os_string();
+            name.push(".");
+            name.push(ext);
+            dir.join(name)
+        })
+        .find(|candidate| candidate != photo && candidate.is_file())
+}
+
+/// Length of the MP4 at the start of `data`: the top-level boxes (ftyp, moov, mdat, ...)
+/// up to the first that doesn't fit, so trailing data from the photo is left out
+pub fn mp4_length(data: &[u8]) -> usize {
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        if !kind.iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ') || (pos == 0 && kind != b"ftyp") {
+            break;
+        }
+        let size = match size {
+            // Box runs to the end of the file
+            0 => data.len() - pos,
+            // 64-bit size follows the type
+            1 if pos + 16 <= data.len() => u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize,
+            _ => size,
+        };
+        if size < 8 || size > data.len() - pos {
+            break;
+        }
+        pos += size;
+    }
+    pos
+}
+
+/// The MP4 starting at `start`, if there is a whole one there
+fn video_at(data: &[u8], start: usize) -> Option<Range<usize>> {
+    let length = mp4_length(data.get(start..)?);
+    // At least the ftyp box and one more
+    (length > 8 && data.len() - start >= 16 && length > u32::from_be_bytes(data[start..start + 4].try_into().ok()?) as usize)
+        .then_some(start..start + length)
+}
+
+/// Value of an XMP attribute (`name="123"`) or element (`<name>123</name>`)
+fn xmp_number(xmp: &str, name: &str) -> Option<usize> {
+    let (_, rest) = xmp.split_once(name)?;
+    let rest = rest.trim_start_matches(['=', '"', '>', ' ']);
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Google motion photos: the video is the last N bytes, N given by the XMP either as
+/// `GCamera:MicroVideoOffset` (older) or the MotionPhoto item of the `Container` directory
+fn xmp_video_length(data: &[u8]) -> Option<usize> {
+    let start = find(data, b"<x:xmpmeta")?;
+    let end = find(&data[start..], b"</x:xmpmeta>").map_or(data.len(), |end| start + end);
+    let xmp = String::from_utf8_lossy(&data[start..end]);
+
+    if let Some(length) = xmp_number(&xmp, "GCamera:MicroVideoOffset") {
+        return Some(length);
+    }
+    let item = xmp
+        .split("<Container:Item")
+        .skip(1)
+        .find(|item| item.contains("Item:Semantic=\"MotionPhoto\""))?;
+    let item = item.split('>').next().unwrap_or(item);
+    xmp_number(item, "Item:Length")
+}
+
+/// Payload of the top-level `mpvd` box Samsung uses in motion HEICs
+fn mpvd_box(data: &[u8]) -> Option<Range<usize>> {
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        if &data[pos + 4..pos + 8] == b"mpvd" {
+            return video_at(data, pos + 8);
+        }
+        if size < 8 {
+            return None;
+        }
+        pos += size;
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Finds the MP4 inside a motion photo (JPEG or HEIC), trying the XMP offsets, the HEIC
+/// `mpvd` box and Samsung's marker, then any MP4 header after the start of the file
+pub fn embedded_video(data: &[u8]) -> Option<Range<usize>> {
+    if let Some(length) = xmp_video_length(data).filter(|length| *length > 0 && *length <= data.len()) {
+        if let Some(video) = video_at(data, data.len() - length) {
+            return Some(video);
+        }
+    }
+    if data.get(4..8) == Some(b"ftyp".as_slice()) {
+        if let Some(video) = mpvd_box(data) {
+            return Some(video);
+        }
+    }
+    if let Some(marker) = find(data, SAMSUNG_MARKER) {
+        if let Some(video) = video_at(data, marker + SAMSUNG_MARKER.len()) {
+            return Some(video);
+        }
+    }
+
+    // A HEIC starts with its own ftyp box, so the search starts past it
+    let mut from = 8;
+    while let Some(offset) = data.get(from..).and_then(|rest| find(rest, b"ftyp")) {
+        let ftyp = from + offset;
+        let is_video = data.get(ftyp + 4..ftyp + 8).is_some_and(|brand| VIDEO_BRANDS.iter().any(|b| b.as_slice() == brand));
+        if is_video {
+            if let Some(video) = video_at(data, ftyp - 4) {
+                return Some(video);
+            }
+        }
+        from = ftyp + 4;
+    }
+    None
+}
+
+/// The video of a Live Photo or motion photo: the paired file first, then one embedded
+/// in the photo. None for an ordinary photo.
+pub fn find_live_video(photo: &Path) -> Result<Option<LiveVideo>, String> {
+    if let Some(video) = paired_video(photo) {
+        return Ok(Some(LiveVideo::Paired(video)));
+    }
+    let data = std::fs::read(photo).map_err(|e| format!("Failed to read {}: {}", photo.display(), e))?;
+    Ok(embedded_video(&data).map(LiveVideo::Embedded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend(kind);
+        data.extend(payload);
+        data
+    }
+
+    fn sample_mp4() -> Vec<u8> {
+        let mut video = mp4_box(b"ftyp", b"mp42\0\0\0\0mp42isom");
+        video.extend(mp4_box(b"moov", &[1; 20]));
+        video.extend(mp4_box(b"mdat", &[2; 40]));
+        video
+    }
+
+    fn jpeg_with_xmp(xmp: &str) -> Vec<u8> {
+        let mut data = vec![0xff, 0xd8, 0xff, 0xe1];
+        data.extend(format!("<x:xmpmeta>{}</x:xmpmeta>", xmp).as_bytes());
+        data.extend([0xff, 0xd9]);
+        data
+    }
+
+    #[test]
+    fn test_mp4_length_stops_at_trailing_data() {
+        let mut data = sample_mp4();
+        let length = data.len();
+        data.extend(b"SEFHjunk after the video");
+        assert_eq!(mp4_length(&data), length);
+        assert_eq!(mp4_length(b"not a video at all"), 0);
+    }
+
+    #[test]
+    fn test_google_micro_video_offset() {
+        let video = sample_mp4();
+        let mut data = jpeg_with_xmp(&format!("<rdf:Description GCamera:MicroVideo=\"1\" GCamera:MicroVideoOffset=\"{}\"/>", video.len()));
+        let start = data.len();
+        data.extend(&video);
+        assert_eq!(embedded_video(&data), Some(start..data.len()));
+    }
+
+    #[test]
+    fn test_google_container_directory() {
+        let video = sample_mp4();
+        let mut data = jpeg_with_xmp(&format!(
+            "<Container:Directory><rdf:Seq>\
+             <rdf:li><Container:Item Item:Mime=\"image/jpeg\" Item:Semantic=\"Primary\" Item:Length=\"0\" Item:Padding=\"0\"/></rdf:li>\
+             <rdf:li><Container:Item Item:Mime=\"video/mp4\" Item:Semantic=\"MotionPhoto\" Item:Length=\"{}\"/></rdf:li>\
+             </rdf:Seq></Container:Directory>",
+            video.len()
+        ));
+        let start = data.len();
+        data.extend(&video);
+        assert_eq!(embedded_video(&data), Some(start..data.len()));
+    }
+
+    #[test]
+    fn test_samsung_marker() {
+        let mut data = vec![0xff, 0xd8, 0xff, 0xd9];
+        data.extend(SAMSUNG_MARKER);
+        let start = data.len();
+        data.extend(sample_mp4());
+        let end = data.len();
+        data.extend(b"SEFT trailer");
+        assert_eq!(embedded_video(&data), Some(start..end));
+    }
+
+    #[test]
+    fn test_motion_heic_mpvd_box() {
+        let mut data = mp4_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        data.extend(mp4_box(b"meta", &[0; 32]));
+        let video = sample_mp4();
+        let start = data.len() + 8;
+        data.extend(mp4_box(b"mpvd", &video));
+        assert_eq!(embedded_video(&data), Some(start..start + video.len()));
+    }
+
+    #[test]
+    fn test_ordinary_photos_have_no_video() {
+        assert_eq!(embedded_video(&[0xff, 0xd8, 0xff, 0xd9]), None);
+        let mut heic = mp4_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        heic.extend(mp4_box(b"mdat", &[0; 64]));
+        assert_eq!(embedded_video(&heic), None);
+    }
+
+    #[test]
+    fn test_paired_video() {
+        let dir = std::env::temp_dir().join(format!("convertsave-live-photo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let photo = dir.join("IMG_0001.HEIC");
+        std::fs::write(&photo, b"photo").unwrap();
+        assert_eq!(paired_video(&photo), None);
+
+        std::fs::write(dir.join("IMG_0001.MOV"), b"video").unwrap();
+        assert_eq!(paired_video(&photo), Some(dir.join("IMG_0001.MOV")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+//! ImageMagick command lines - Typed operations rendered in the order ImageMagick needs them
+//!
+//! ImageMagick 7 applies arguments left to right: read settings must come before the file
+//! they apply to, orientation must be fixed before anything measures the image, and a
+//! background color only affects the operators after it. Each `MagickOp` belongs to a
+//! `Stage`, and `MagickCommand` renders the stages in order no matter when an option was added.
+
+use std::ffi::OsString;
+
+/// Where an operation goes on the command line, in rendering order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Settings for decoding the next file, placed before the inputs (`-density` for PDF/SVG)
+    Read,
+    /// Frame handling right after reading (`-coalesce`, frame delays)
+    Frames,
+    /// Color profile conversion, so everything after works in the output's colors
+    Color,
+    /// `-auto-orient`, before anything that depends on width and height
+    Orient,
+    /// Cropping, on the upright picture and before it's resized
+    Crop,
+    /// Watermarks, drawn on the cropped picture so they're resized along with it
+    Overlay,
+    /// Resizing and padding
+    Geometry,
+    /// Compositing onto a background, after geometry so padding is filled too
+    Flatten,
+    /// Settings read by the encoder (`-quality`, `-define`, `-strip`)
+    Output,
+}
+
+/// One ImageMagick option
+#[derive(Debug, Clone, PartialEq)]
+pub enum MagickOp {
+    /// Resolution used to rasterize vector input, in DPI
+    ReadDensity(u32),
+    /// Coder option for reading the input, such as `dng:use-camera-wb=true`
+    ReadDefine { key: String, value: String },
+    /// Expand optimized animation frames to full frames
+    Coalesce,
+    /// Frame delay in 1/100ths of a second
+    Delay(u32),
+    /// Convert to the ICC profile in this file (or assign it, for images without a profile)
+    Profile(String),
+    AutoOrient,
+    /// Cut out `geometry` (`WxH+X+Y`, or an aspect ratio such as `16:9` when `centered`)
+    Crop { geometry: String, centered: bool },
+    /// Draw `overlay` (an image ImageMagick can read by name) `width` pixels wide at
+    /// `opacity`, `offset` pixels in from the `gravity` edge
+    Composite { overlay: String, width: u32, opacity: f32, gravity: String, offset: u32 },
+    /// Draw `text` in white at `opacity`, `point_size` pixels tall, `offset` pixels in from
+    /// the `gravity` edge. `%` escapes and a leading `@` are taken literally.
+    Annotate { text: String, point_size: u32, opacity: f32, gravity: String, offset: u32 },
+    /// Geometry such as `256x256` or `50%`
+    Resize(String),
+    /// Pad or crop to `size` around the center, filling with `background`
+    Extent { size: String, background: String },
+    /// Merge layers onto a solid background, dropping the alpha channel
+    Flatten { background: String },
+    Quality(u8),
+    Compress(String),
+    /// Resolution written into the output, in DPI
+    Density(u32),
+    /// Coder option such as `heic:speed=6`
+    Define { key: String, value: String },
+    /// Animation loop count (0 = forever)
+    Loop(u32),
+    /// Drop profiles and comments (EXIF, IPTC, XMP, ICC)
+    Strip,
+    /// Drop one kind of profile, such as `icc`
+    RemoveProfile(String),
+    /// Bits per channel written to the output
+    Depth(u32),
+}
+
+impl MagickOp {
+    pub fn define(key: &str, value: &str) -> Self {
+        MagickOp::Define { key: key.to_string(), value: value.to_string() }
+    }
+
+    pub fn read_define(key: &str, value: &str) -> Self {
+        MagickOp::ReadDefine { key: key.to_string(), value: value.to_string() }
+    }
+
+    pub fn flatten(background: &str) -> Self {
+        MagickOp::Flatten { background: background.to_string() }
+    }
+
+    pub fn stage(&self) -> Stage {
+        match self {
+            MagickOp::ReadDensity(_) | MagickOp::ReadDefine { .. } => Stage::Read,
+            MagickOp::Coalesce | MagickOp::Delay(_) => Stage::Frames,
+            MagickOp::Profile(_) => Stage::Color,
+            MagickOp::AutoOrient => Stage::Orient,
+            MagickOp::Crop { .. } => Stage::Crop,
+            MagickOp::Composite { .. } | MagickOp::Annotate { .. } => Stage::Overlay,
+            MagickOp::Resize(_) | MagickOp::Extent { .. } => Stage::Geometry,
+            MagickOp::Flatten { .. } => Stage::Flatten,
+            MagickOp::Quality(_)
+            | MagickOp::Compress(_)
+            | MagickOp::Density(_)
+            | MagickOp::Define { .. }
+            | MagickOp::Loop(_)
+            | MagickOp::Strip
+            | MagickOp::RemoveProfile(_)
+            | MagickOp::Depth(_) => Stage::Output,
+        }
+    }
+
+    /// Renders the operation as command line arguments
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            MagickOp::ReadDensity(dpi) | MagickOp::Density(dpi) => vec!["-density".into(), dpi.to_string()],
+            MagickOp::Coalesce => vec!["-coalesce".into()],
+            MagickOp::Delay(delay) => vec!["-set".into(), "delay".into(), delay.to_string()],
+            MagickOp::Profile(file) => vec!["-profile".into(), file.clone()],
+            MagickOp::AutoOrient => vec!["-auto-orient".into()],
+            // +repage drops the virtual canvas, or formats like PNG and GIF keep the old offset
+            MagickOp::Crop { geometry, centered } => {
+                let mut args: Vec<String> = if *centered { vec!["-gravity".into(), "center".into()] } else { Vec::new() };
+                args.extend(["-crop".into(), geometry.clone(), "+repage".into()]);
+                args
+            }
+            // The overlay is loaded and faded in parentheses so nothing touches the picture
+            // under it; +gravity puts the default back for the operations after it
+            MagickOp::Composite { overlay, width, opacity, gravity, offset } => vec![
+                "(".into(), overlay.clone(),
+                "-resize".into(), format!("{}x", width),
+                "-alpha".into(), "set".into(),
+                "-channel".into(), "A".into(), "-evaluate".into(), "multiply".into(), opacity.to_string(), "+channel".into(),
+                ")".into(),
+                "-gravity".into(), gravity.clone(),
+                "-geometry".into(), format!("+{}+{}", offset, offset),
+                "-compose".into(), "over".into(), "-composite".into(),
+                "+gravity".into(),
+            ],
+            MagickOp::Annotate { text, point_size, opacity, gravity, offset } => vec![
+                "-gravity".into(), gravity.clone(),
+                "-fill".into(), format!("rgba(255,255,255,{})", opacity),
+                "-pointsize".into(), point_size.to_string(),
+                "-annotate".into(), format!("+{}+{}", offset, offset), literal_text(text),
+                "+gravity".into(),
+            ],
+            MagickOp::Resize(geometry) => vec!["-resize".into(), geometry.clone()],
+            // -background is a setting, so it has to come before the -extent that uses it
+            MagickOp::Extent { size, background } => vec![
+                "-gravity".into(), "center".into(),
+                "-background".into(), background.clone(),
+                "-extent".into(), size.clone(),
+            ],
+            MagickOp::Flatten { background } => vec!["-background".into(), background.clone(), "-flatten".into()],
+            MagickOp::Quality(quality) => vec!["-quality".into(), quality.to_string()],
+            MagickOp::Compress(method) => vec!["-compress".into(), method.clone()],
+            MagickOp::Define { key, value } | MagickOp::ReadDefine { key, value } => {
+                vec!["-define".into(), format!("{}={}", key, value)]
+            }
+            MagickOp::Loop(loops) => vec!["-loop".into(), loops.to_string()],
+            MagickOp::Strip => vec!["-strip".into()],
+            MagickOp::RemoveProfile(name) => vec!["+profile".into(), name.clone()],
+            MagickOp::Depth(bits) => vec!["-depth".into(), bits.to_string()],
+        }
+    }
+}
+
+/// Escapes text for `-annotate`, which expands `%` escapes and reads `@file` names
+fn literal_text(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "%%");
+    if escaped.starts_with('@') { format!("\\{}", escaped) } else { escaped }
+}
+
+/// A `magick` (or `magick mogrify`) command line
+#[derive(Debug, Clone, Default)]
+pub struct MagickCommand {
+    /// Leading arguments, e.g. `mogrify -path out -format jpg`
+    prefix: Vec<OsString>,
+    inputs: Vec<OsString>,
+    ops: Vec<MagickOp>,
+    extra: Vec<String>,
+    output: Option<OsString>,
+    /// mogrify takes its files last, after every option
+    inputs_last: bool,
+}
+
+impl MagickCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `magick mogrify`, writing each file into `output_dir` as `output_ext`
+    pub fn mogrify(output_dir: impl Into<OsString>, output_ext: &str) -> Self {
+        MagickCommand {
+            prefix: vec!["mogrify".into(), "-path".into(), output_dir.into(), "-format".into(), output_ext.into()],
+            inputs_last: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn input(mut self, input: impl Into<OsString>) -> Self {
+        self.inputs.push(input.into());
+        self
+    }
+
+    pub fn op(mut self, op: MagickOp) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    pub fn ops(mut self, ops: impl IntoIterator<Item = MagickOp>) -> Self {
+        self.ops.extend(ops);
+        self
+    }
+
+    /// Raw options typed by the user. They go after every built-in option so they can
+    /// override the defaults, but before the output.
+    pub fn extra_args(mut self, options: Option<&str>) -> Self {
+        if let Some(options) = options {
+            self.extra.extend(options.split_whitespace().map(String::from));
+        }
+        self
+    }
+
+    pub fn output(mut self, output: impl Into<OsString>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Renders the arguments: read settings, inputs, operations by stage, user options, output
+    pub fn args(&self) -> Vec<OsString> {
+        let mut ops: Vec<&MagickOp> = self.ops.iter().collect();
+        // Stable, so operations within a stage keep the order they were added in
+        ops.sort_by_key(|op| op.stage());
+        let (read, rest): (Vec<&MagickOp>, Vec<&MagickOp>) = ops.into_iter().partition(|op| op.stage() == Stage::Read);
+
+        let mut args = self.prefix.clone();
+        args.extend(read.iter().flat_map(|op| op.args()).map(OsString::from));
+        if !self.inputs_last {
+            args.extend(self.inputs.iter().cloned());
+        }
+        args.extend(rest.iter().flat_map(|op| op.args()).map(OsString::from));
+        args.extend(self.extra.iter().map(OsString::from));
+        if self.inputs_last {
+            args.extend(self.inputs.iter().cloned());
+        }
+        args.extend(self.output.iter().cloned());
+        args
+    }
+
+    /// The arguments as strings, for previews and tests
+    pub fn args_lossy(&self) -> Vec<String> {
+        self.args().iter().map(|arg| arg.to_string_lossy().to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_args() {
+        assert_eq!(MagickOp::Quality(90).args(), vec!["-quality", "90"]);
+        assert_eq!(MagickOp::define("heic:speed", "6").args(), vec!["-define", "heic:speed=6"]);
+        assert_eq!(MagickOp::Delay(4).args(), vec!["-set", "delay", "4"]);
+    }
+
+    #[test]
+    fn test_extent_sets_background_first() {
+        let op = MagickOp::Extent { size: "256x256".to_string(), background: "transparent".to_string() };
+        let args = op.args();
+        let background = args.iter().position(|arg| arg == "-background").unwrap();
+        let extent = args.iter().position(|arg| arg == "-extent").unwrap();
+        assert!(background < extent);
+    }
+
+    #[test]
+    fn test_read_settings_before_input() {
+        let command = MagickCommand::new()
+            .input("doc.pdf")
+            .op(MagickOp::Quality(90))
+            .op(MagickOp::ReadDensity(150))
+            .output("doc.png");
+        assert_eq!(command.args_lossy(), vec!["-density", "150", "doc.pdf", "-quality", "90", "doc.png"]);
+    }
+
+    #[test]
+    fn test_auto_orient_before_resize() {
+        let command = MagickCommand::new()
+            .input("a.jpg")
+            .op(MagickOp::Resize("50%".to_string()))
+            .op(MagickOp::AutoOrient)
+            .output("b.jpg");
+        assert_eq!(command.args_lossy(), vec!["a.jpg", "-auto-orient", "-resize", "50%", "b.jpg"]);
+    }
+
+    #[test]
+    fn test_crop_between_orient_and_resize() {
+        let command = MagickCommand::new()
+            .input("a.jpg")
+            .op(MagickOp::Resize("1080x".to_string()))
+            .op(MagickOp::Crop { geometry: "1:1".to_string(), centered: true })
+            .op(MagickOp::AutoOrient)
+            .output("b.jpg");
+        assert_eq!(
+            command.args_lossy(),
+            vec!["a.jpg", "-auto-orient", "-gravity", "center", "-crop", "1:1", "+repage", "-resize", "1080x", "b.jpg"]
+        );
+        let rect = MagickOp::Crop { geometry: "100x50+10+20".to_string(), centered: false };
+        assert_eq!(rect.args(), vec!["-crop", "100x50+10+20", "+repage"]);
+    }
+
+    #[test]
+    fn test_watermark_after_crop_and_before_resize() {
+        let command = MagickCommand::new()
+            .input("a.jpg")
+            .op(MagickOp::Resize("50%".to_string()))
+            .op(MagickOp::Composite { overlay: "logo.png".to_string(), width: 200, opacity: 0.5, gravity: "southeast".to_string(), offset: 30 })
+            .op(MagickOp::Crop { geometry: "1:1".to_string(), centered: true })
+            .output("b.jpg");
+        assert_eq!(command.args_lossy(), vec![
+            "a.jpg", "-gravity", "center", "-crop", "1:1", "+repage",
+            "(", "logo.png", "-resize", "200x", "-alpha", "set", "-channel", "A", "-evaluate", "multiply", "0.5", "+channel", ")",
+            "-gravity", "southeast", "-geometry", "+30+30", "-compose", "over", "-composite", "+gravity",
+            "-resize", "50%", "b.jpg",
+        ]);
+
+        let text = MagickOp::Annotate { text: "@me 100%".to_string(), point_size: 40, opacity: 0.8, gravity: "north".to_string(), offset: 10 };
+        assert_eq!(text.args(), vec![
+            "-gravity", "north", "-fill", "rgba(255,255,255,0.8)", "-pointsize", "40", "-annotate", "+10+10", "\\@me 100%%", "+gravity",
+        ]);
+    }
+
+    #[test]
+    fn test_profile_conversion_first() {
+        let command = MagickCommand::new()
+            .input("a.tif")
+            .op(MagickOp::Depth(16))
+            .op(MagickOp::AutoOrient)
+            .op(MagickOp::Profile("/tmp/sRGB.icc".to_string()))
+            .op(MagickOp::RemoveProfile("icc".to_string()))
+            .output("b.tif");
+        assert_eq!(
+            command.args_lossy(),
+            vec!["a.tif", "-profile", "/tmp/sRGB.icc", "-auto-orient", "-depth", "16", "+profile", "icc", "b.tif"]
+        );
+    }
+
+    #[test]
+    fn test_flatten_after_geometry_and_before_output_settings() {
+        let command = MagickCommand::new()
+            .input("a.png")
+            .op(MagickOp::Strip)
+            .op(MagickOp::flatten("white"))
+            .op(MagickOp::Resize("800x".to_string()))
+            .output("a.jpg");
+        assert_eq!(
+            command.args_lossy(),
+            vec!["a.png", "-resize", "800x", "-background", "white", "-flatten", "-strip", "a.jpg"]
+        );
+    }
+
+    #[test]
+    fn test_stage_keeps_insertion_order() {
+        let command = MagickCommand::new()
+            .op(MagickOp::Compress("jpeg".to_string()))
+            .op(MagickOp::Density(300));
+        assert_eq!(command.args_lossy(), vec!["-compress", "jpeg", "-density", "300"]);
+    }
+
+    #[test]
+    fn test_extra_args_before_output() {
+        let command = MagickCommand::new()
+            .input("a.png")
+            .extra_args(Some("-quality  50"))
+            .op(MagickOp::Quality(90))
+            .output("a.webp");
+        assert_eq!(command.args_lossy(), vec!["a.png", "-quality", "90", "-quality", "50", "a.webp"]);
+    }
+
+    #[test]
+    fn test_mogrify_files_last() {
+        let command = MagickCommand::mogrify("/out", "jpg")
+            .input("a.png")
+            .input("b.png")
+            .op(MagickOp::Quality(90))
+            .extra_args(Some("-resize 50%"));
+        assert_eq!(
+            command.args_lossy(),
+            vec!["mogrify", "-path", "/out", "-format", "jpg", "-quality", "90", "-resize", "50%", "a.png", "b.png"]
+        );
+    }
+}
@@ -6,25 +6,57 @@
 )]
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
 use dirs;
 use serde_json;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_updater::UpdaterExt;
 use log::{info, error, warn, debug};
+use convertsave_lib::archive;
+use convertsave_lib::audit::{self, FolderReport};
+use convertsave_lib::email;
+use convertsave_lib::fixtures::{self, FixtureReport};
+use convertsave_lib::pim;
+use convertsave_lib::icc;
+use convertsave_lib::conversion::{self, AnimationOptimizeOptions, AudioAnalysis, ArchiveConversionResult, AudioPreset, BatchConversionResult, BatchEstimate, BatchItemResult, BatchPlan, ConversionPreview, ConversionMatrix, ConversionOption, ConversionResult, ConversionSettings, DocumentPreview, FormatDetails, FormatItemResult, FrameExtractionResult, FrameSelection, IccProfileMode, OptimizationReport, SubtitleMode, ToolWarning, UnsupportedExplanation, Watermark};
+use convertsave_lib::filter_graph::{self, Filter, FilterGraph, LoudnessMeasurement};
+use convertsave_lib::magick::{MagickCommand, MagickOp};
+use convertsave_lib::history::{self, HistoryEntry, JobHistory, JobLabel, JobStatus, ThroughputSample};
+use convertsave_lib::naming::{self, NamingContext};
+use convertsave_lib::probe::{self, Chapter, MediaInfo, StreamInfo, SubtitleStreamInfo};
+use convertsave_lib::chapters;
+use convertsave_lib::input_state::{self, FileSample, InputState};
+use convertsave_lib::checksum;
+use convertsave_lib::tool_search::{self, ToolSearch, ToolSource};
+use convertsave_lib::signature::{self, FormatCheck};
+use convertsave_lib::config::{self, MachineConfig};
+use convertsave_lib::job_log::{self, JobLog};
+use convertsave_lib::feature_flags::{self, FeatureFlag, RemoteManifest};
+use convertsave_lib::error_summary::{self, ErrorSummary};
+use convertsave_lib::watchdog::{self, Limits};
+use convertsave_lib::ingest::{self, IngestDuplicate, IngestFailure, IngestHistory, IngestReport};
+use convertsave_lib::live_photo::{self, LiveVideo};
+use convertsave_lib::bundled_tools;
+use convertsave_lib::icon_set::{self, IconFile, IconPlatform, IconSetResult};
+use convertsave_lib::responsive::{self, ResponsiveImage, ResponsiveSet};
+use convertsave_lib::sidecar::{self, Sidecar};
+use convertsave_lib::scheduler::{self, JobPriority, QueueStatus, SlotQueue};
+use convertsave_lib::retry::{self, RetryEvent, RetryPolicy};
+use convertsave_lib::power::{self, AfterQueue, PowerSettings};
+use convertsave_lib::resume::{self, InterruptedBatch, QueueJournal, QueuedBatch, QueuedJob};
+use convertsave_lib::spreadsheet::{self, SpreadsheetLayout};
+use convertsave_lib::metadata::{self, FileBy, ImageMetadata};
+use convertsave_lib::migration::{self, MigrationEntry, MigrationManifest, MigrationResult, MigrationScan, RollbackReport};
 
 // License management module
 mod license;
 
-// ═══════════════════════════════════════════════════════════════════════════
-// FEATURE TOGGLES - Set to `true` to enable, `false` to disable
-// ═══════════════════════════════════════════════════════════════════════════
-/// Enable Pandoc document conversion support (Markdown, HTML, TXT conversions)
-/// Change this to `true` when you want to re-enable Pandoc functionality
-const ENABLE_PANDOC: bool = false;
-// ═══════════════════════════════════════════════════════════════════════════
-
 // ═══════════════════════════════════════════════════════════════════════════
 // APP IDENTIFIER - Different for dev and production builds
 // ═══════════════════════════════════════════════════════════════════════════
@@ -43,13 +75,17 @@ use std::os::windows::process::CommandExt;
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ConversionOption {
-    format: String,
-    tool: String,
-    display_name: String,
-    color: String,
-}
+const CHROMIUM_NOT_FOUND: &str = "A headless browser is required for HTML snapshots but none was found.\n\n\
+    Please install Chromium from the Tools Manager in Settings, or install Google Chrome or Microsoft Edge.";
+
+const CALIBRE_NOT_FOUND: &str = "Calibre is required for this e-book conversion but was not found.\n\n\
+    Please install Calibre from calibre-ebook.com, or set the location of ebook-convert in the Tools Manager in Settings.";
+
+const LIBREOFFICE_NOT_FOUND: &str = "LibreOffice is required for office documents but was not found.\n\n\
+    Please install LibreOffice from the Tools Manager in Settings, or from libreoffice.org.";
+
+const GHOSTSCRIPT_NOT_FOUND: &str = "Ghostscript is required to read PDF and PostScript files but was not found.\n\n\
+    Please install Ghostscript from the Tools Manager in Settings, or from ghostscript.com.";
 
 #[derive(Serialize, Clone)]
 struct DownloadProgress {
@@ -57,11 +93,90 @@ struct DownloadProgress {
     message: String,
 }
 
+/// Emitted as "batch-progress" each time a file in convert_batch or convert_image_batch finishes
+#[derive(Serialize, Clone)]
+struct BatchProgress {
+    completed: usize,
+    total: usize,
+    input_path: String,
+    success: bool,
+}
+
+/// Emitted as "archive-progress" while convert_zip extracts and packs files (the
+/// conversions in between report through "batch-progress")
+#[derive(Serialize, Clone)]
+struct ArchiveProgress {
+    /// "extracting" or "packing"
+    stage: String,
+    percent: u32,
+}
+
+/// Emitted as "concat-progress" when concat_videos starts writing each clip
+#[derive(Serialize, Clone)]
+struct ConcatProgress {
+    /// 1-based clip number
+    segment: usize,
+    total: usize,
+    input_path: String,
+    /// Whether the clips are joined without re-encoding
+    stream_copy: bool,
+}
+
+/// Emitted as "merge-progress" when merge_documents starts on each document
+#[derive(Serialize, Clone)]
+struct MergeProgress {
+    /// 1-based document number
+    current: usize,
+    total: usize,
+    input_path: String,
+}
+
+/// Emitted as "tool-output" for each line a conversion tool prints, so the log viewer can
+/// follow long conversions live
+#[derive(Serialize, Clone)]
+struct ToolOutputLine {
+    /// The file being written, to tell concurrent conversions apart
+    output_path: String,
+    tool: String,
+    /// "stdout" or "stderr"
+    stream: String,
+    line: String,
+    /// An FFmpeg progress line, which replaces the previous one rather than adding to the log
+    progress: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 struct ToolConfig {
     ffmpeg_path: Option<String>,
     pandoc_path: Option<String>,
     imagemagick_path: Option<String>,
+    inkscape_path: Option<String>,
+    chromium_path: Option<String>,
+    libreoffice_path: Option<String>,
+    calibre_path: Option<String>,
+    ghostscript_path: Option<String>,
+    /// Default output naming template (None = keep the input name)
+    name_template: Option<String>,
+    /// FFmpeg's -loglevel for conversions (None = warning)
+    #[serde(default)]
+    ffmpeg_log_level: Option<String>,
+    /// Feature flags the user switched on or off
+    #[serde(default)]
+    feature_flags: BTreeMap<String, bool>,
+    /// Flags from the remote manifest at the last update check, so they apply offline too
+    #[serde(default)]
+    remote_feature_flags: BTreeMap<String, bool>,
+    /// Keep the machine awake while converting (None = yes)
+    #[serde(default)]
+    prevent_sleep: Option<bool>,
+    /// Locked-down profile for shared machines (labs, kiosks): conversions work with the
+    /// tools already installed, but tools can't be downloaded or pointed elsewhere and
+    /// settings can't be changed. Only set by editing the config file.
+    #[serde(default)]
+    kiosk: bool,
+    /// Proxy for every network call, e.g. "http://proxy.corp:3128" (None = direct)
+    #[serde(default)]
+    proxy: Option<String>,
 }
 
 /// Get the path to the config file
@@ -72,29 +187,604 @@ fn get_config_path() -> Result<PathBuf, String> {
     Ok(config_dir.join("config.json"))
 }
 
-/// Load the tool configuration from disk
+/// The ProgramData folder, from the shell rather than %ProgramData%, which any user can
+/// point somewhere else to get around the machine config
+#[cfg(target_os = "windows")]
+fn program_data_dir() -> Option<String> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStringExt;
+    
+    #[repr(C)]
+    struct Guid {
+        data1: u32,
+        data2: u16,
+        data3: u16,
+        data4: [u8; 8],
+    }
+    
+    // FOLDERID_ProgramData, {62AB5D82-FDC1-4DC3-A9DD-070D1D495D97}
+    const FOLDERID_PROGRAM_DATA: Guid = Guid {
+        data1: 0x62AB5D82,
+        data2: 0xFDC1,
+        data3: 0x4DC3,
+        data4: [0xA9, 0xDD, 0x07, 0x0D, 0x1D, 0x49, 0x5D, 0x97],
+    };
+    
+    #[link(name = "shell32")]
+    extern "system" {
+        fn SHGetKnownFolderPath(rfid: *const Guid, flags: u32, token: *mut c_void, path: *mut *mut u16) -> i32;
+    }
+    #[link(name = "ole32")]
+    extern "system" {
+        fn CoTaskMemFree(pv: *mut c_void);
+    }
+    
+    let mut path: *mut u16 = std::ptr::null_mut();
+    // SAFETY: `path` receives a NUL-terminated string the shell allocates, which is read
+    // up to its terminator and then freed with CoTaskMemFree as the API requires
+    unsafe {
+        let result = SHGetKnownFolderPath(&FOLDERID_PROGRAM_DATA, 0, std::ptr::null_mut(), &mut path);
+        let dir = if result == 0 && !path.is_null() {
+            let len = (0..).take_while(|&i| *path.add(i) != 0).count();
+            let wide = std::slice::from_raw_parts(path, len);
+            Some(std::ffi::OsString::from_wide(wide).to_string_lossy().to_string())
+        } else {
+            warn!("Could not find the ProgramData folder (error {:#x})", result);
+            None
+        };
+        CoTaskMemFree(path as *mut c_void);
+        dir
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn program_data_dir() -> Option<String> {
+    None
+}
+
+/// Load the machine-wide config set up by IT, if there is one. A broken one is ignored
+/// (and logged) rather than keeping the app from starting.
+fn load_machine_config() -> MachineConfig {
+    let program_data = program_data_dir();
+    let Some(dir) = config::machine_config_dir(std::env::consts::OS, program_data.as_deref()) else {
+        return MachineConfig::default();
+    };
+    let path = dir.join(config::MACHINE_CONFIG_FILE);
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return MachineConfig::default();
+    };
+    match MachineConfig::parse(&contents) {
+        Ok(machine) => {
+            debug!("Loaded machine config from {}: {:?}", path.display(), machine);
+            machine
+        }
+        Err(e) => {
+            warn!("Ignoring the machine config at {}: {}", path.display(), e);
+            MachineConfig::default()
+        }
+    }
+}
+
+/// Install the format registry, with the overrides in formats.json next to the config
+/// if there are any. Broken overrides are ignored (and logged), as for the machine config.
+fn load_format_registry() {
+    let Ok(path) = get_config_path().map(|path| path.with_file_name(conversion::FORMAT_OVERRIDES_FILE)) else {
+        return;
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    match conversion::FormatRegistry::builtin().with_overrides(&contents) {
+        Ok(registry) => {
+            info!("Loaded format overrides from {}", path.display());
+            if let Err(e) = conversion::install_format_registry(registry) {
+                warn!("Could not use the format overrides: {}", e);
+            }
+        }
+        Err(e) => warn!("Ignoring the format overrides at {}: {}", path.display(), e),
+    }
+}
+
+/// Load the tool configuration from disk: the user's config over the machine's
 fn load_config() -> Result<ToolConfig, String> {
     let config_path = get_config_path()?;
-    if config_path.exists() {
+    let user = if config_path.exists() {
         let contents = std::fs::read_to_string(&config_path).map_err(|e| e.to_string())?;
-        let config: ToolConfig = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
-        debug!("Loaded config from {}: {:?}", config_path.display(), config);
-        Ok(config)
+        serde_json::from_str(&contents).map_err(|e| e.to_string())?
     } else {
         debug!("No config file found at {}, using defaults", config_path.display());
-        Ok(ToolConfig::default())
+        serde_json::Value::Object(Default::default())
+    };
+    let config: ToolConfig = serde_json::from_value(load_machine_config().resolve(&user)).map_err(|e| e.to_string())?;
+    debug!("Loaded config from {}: {:?}", config_path.display(), config);
+    Ok(config)
+}
+
+/// Settings fixed by the machine config, so the UI can show them as set by the administrator
+#[tauri::command]
+fn get_locked_settings() -> Vec<String> {
+    load_machine_config().locked.into_iter().collect()
+}
+
+/// Whether the app runs in the locked-down kiosk profile. The machine config decides first,
+/// so a user config.json can't turn it off, and a config that can't be read counts as locked.
+fn kiosk_mode() -> bool {
+    if load_machine_config().values.get("kiosk").and_then(serde_json::Value::as_bool) == Some(true) {
+        return true;
     }
+    match load_config() {
+        Ok(config) => config.kiosk,
+        Err(e) => {
+            warn!("Could not read the config, treating kiosk mode as on: {}", e);
+            true
+        }
+    }
+}
+
+/// Fails with an explanation when `action` is locked by the kiosk profile
+fn ensure_unlocked(action: &str) -> Result<(), String> {
+    if kiosk_mode() {
+        warn!("{} refused: kiosk mode is on", action);
+        return Err(format!("{} is turned off on this computer. Ask your administrator if you need it.", action));
+    }
+    Ok(())
+}
+
+/// Whether settings, tool downloads and custom tool paths are locked, so the UI can hide them
+#[tauri::command]
+fn get_kiosk_mode() -> bool {
+    kiosk_mode()
 }
 
-/// Save the tool configuration to disk
+/// Save the tool configuration to disk. Only what differs from the machine config is
+/// written, and changing a setting the machine config locks is refused.
 fn save_config(config: &ToolConfig) -> Result<(), String> {
     let config_path = get_config_path()?;
-    let contents = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    let machine = load_machine_config();
+    let effective = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    if let Some(key) = machine.locked_changes(&effective).first() {
+        warn!("Refusing to save config: {} is locked by the machine config", key);
+        return Err(format!("The setting \"{}\" is managed by your administrator and can't be changed.", key));
+    }
+    let contents = serde_json::to_string_pretty(&machine.user_layer(&effective)).map_err(|e| e.to_string())?;
     std::fs::write(&config_path, &contents).map_err(|e| e.to_string())?;
     info!("Config saved to {}: {}", config_path.display(), contents);
     Ok(())
 }
 
+/// Serializes read-modify-write access to the history file across concurrent conversions
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Serializes LibreOffice runs, which share one profile directory
+static LIBREOFFICE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Numbers job directories so concurrent conversions never share one
+static NEXT_JOB_DIR: AtomicU64 = AtomicU64::new(0);
+
+/// Numbers job logs so failures in the same second get separate files
+static NEXT_JOB_LOG: AtomicU64 = AtomicU64::new(0);
+
+/// Encoders each FFmpeg binary was built with, probed once per path
+static FFMPEG_ENCODERS: Mutex<BTreeMap<PathBuf, Vec<String>>> = Mutex::new(BTreeMap::new());
+
+/// Formats each ImageMagick binary can read (HEIC needs libheif), probed once per path
+static MAGICK_READ_FORMATS: Mutex<BTreeMap<PathBuf, Vec<String>>> = Mutex::new(BTreeMap::new());
+
+/// Version of each tool binary, read once per path for sidecars
+static TOOL_VERSIONS: Mutex<BTreeMap<PathBuf, Option<String>>> = Mutex::new(BTreeMap::new());
+
+/// Output paths picked by conversions that haven't written their file yet, so
+/// conversions running side by side never pick the same "name (1)"
+static RESERVED_OUTPUTS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Tool paths resolved while a batch is running (count of running batches, paths),
+/// so every file doesn't re-read the config and re-check each install location
+static TOOL_PATH_CACHE: Mutex<(usize, BTreeMap<String, PathBuf>)> = Mutex::new((0, BTreeMap::new()));
+
+/// Conversion slots shared by every convert_file call, created on first use (see scheduler)
+static CONVERSION_SLOTS: Mutex<Option<SlotQueue>> = Mutex::new(None);
+
+/// Signaled whenever a slot is taken or freed, so waiting conversions check if it's their turn
+static SLOTS_CHANGED: Condvar = Condvar::new();
+
+/// The running app, set once at startup, for events from code that isn't given an AppHandle
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// The sleep inhibitor process while conversions are queued (see power), and a counter
+/// bumped whenever the queue gets busy, so a pending "queue finished" check can tell
+static SLEEP_INHIBITOR: Mutex<(Option<std::process::Child>, u64)> = Mutex::new((None, 0));
+
+/// What to do when the queue finishes; reset once it has been done
+static AFTER_QUEUE: Mutex<AfterQueue> = Mutex::new(AfterQueue::Nothing);
+
+/// Serializes updates to the queue journal (queue.json)
+static QUEUE_JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+/// Numbers batches started within the same millisecond apart
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Jobs running outside the conversion slots (mogrify batches, concatenations, merges),
+/// which keep the queue busy all the same
+static BUSY_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+/// A running conversion's slot, given back when dropped
+struct ConversionSlot;
+
+impl ConversionSlot {
+    /// Wait for a free slot. One slot per CPU core; foreground jobs are let in before
+    /// background jobs that were queued earlier.
+    fn acquire(priority: JobPriority) -> Self {
+        queue_busy();
+        let mut slots = CONVERSION_SLOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let queue = slots.get_or_insert_with(|| {
+            SlotQueue::new(std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        });
+        let ticket = queue.enqueue(priority);
+        while !slots.as_mut().is_some_and(|queue| queue.try_start(ticket)) {
+            slots = SLOTS_CHANGED.wait(slots).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        // Another slot may still be free for whoever is next in line
+        SLOTS_CHANGED.notify_all();
+        ConversionSlot
+    }
+
+    /// `acquire` for async commands: the wait happens on a blocking thread, so it doesn't
+    /// hold up the async runtime's workers
+    async fn acquire_async(priority: JobPriority) -> Result<Self, String> {
+        tauri::async_runtime::spawn_blocking(move || Self::acquire(priority))
+            .await
+            .map_err(|e| format!("Could not wait for a conversion slot: {}", e))
+    }
+}
+
+impl Drop for ConversionSlot {
+    fn drop(&mut self) {
+        let mut slots = CONVERSION_SLOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(queue) = slots.as_mut() {
+            queue.finish();
+        }
+        SLOTS_CHANGED.notify_all();
+        drop(slots);
+        if queue_is_idle() {
+            queue_idle();
+        }
+    }
+}
+
+/// A job that doesn't take a conversion slot but should still hold off sleep and the
+/// after-queue action while it runs
+struct BusyGuard;
+
+impl BusyGuard {
+    fn start() -> Self {
+        BUSY_TASKS.fetch_add(1, Ordering::SeqCst);
+        queue_busy();
+        BusyGuard
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        BUSY_TASKS.fetch_sub(1, Ordering::SeqCst);
+        if queue_is_idle() {
+            queue_idle();
+        }
+    }
+}
+
+/// Nothing running or waiting, in the slots or outside them
+fn queue_is_idle() -> bool {
+    let slots = CONVERSION_SLOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let status = slots.as_ref().map(SlotQueue::status).unwrap_or_default();
+    status.running == 0 && status.waiting_foreground == 0 && status.waiting_background == 0 && BUSY_TASKS.load(Ordering::SeqCst) == 0
+}
+
+/// Whether conversions keep the machine awake (on unless switched off)
+fn prevent_sleep_enabled() -> bool {
+    load_config().ok().and_then(|config| config.prevent_sleep).unwrap_or(true)
+}
+
+/// A conversion was queued: cancels a pending "queue finished" and holds off sleep
+fn queue_busy() {
+    let mut inhibitor = SLEEP_INHIBITOR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    inhibitor.1 += 1;
+    if inhibitor.0.is_some() || !prevent_sleep_enabled() {
+        return;
+    }
+    let Some((program, args)) = power::inhibitor_command(std::env::consts::OS, std::process::id()) else {
+        return;
+    };
+    let mut command = create_command(&program);
+    command.args(&args);
+    // In a group of its own, so the shell loop systemd-inhibit runs is stopped with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    match command.spawn() {
+        Ok(child) => {
+            info!("Keeping the system awake while converting ({})", program);
+            inhibitor.0 = Some(child);
+        }
+        Err(e) => warn!("Could not keep the system awake ({}): {}", program, e),
+    }
+}
+
+/// The queue is empty. If nothing new is queued within IDLE_GRACE, the machine may sleep
+/// again, and the after-queue action (if any) runs.
+fn queue_idle() {
+    let generation = SLEEP_INHIBITOR.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).1;
+    std::thread::spawn(move || {
+        std::thread::sleep(power::IDLE_GRACE);
+        {
+            let mut inhibitor = SLEEP_INHIBITOR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if inhibitor.1 != generation {
+                return;
+            }
+            if let Some(child) = inhibitor.0.take() {
+                stop_inhibitor(child);
+                info!("Conversions finished, the system may sleep again");
+            }
+        }
+        let action = std::mem::take(&mut *AFTER_QUEUE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        if let Some((program, args)) = power::after_queue_command(std::env::consts::OS, action) {
+            info!("Conversions finished, running {:?}: {} {}", action, program, args.join(" "));
+            if let Some(app) = APP_HANDLE.get() {
+                app.emit("after-queue-action", action).ok();
+            }
+            if let Err(e) = create_command(&program).args(&args).spawn() {
+                error!("Could not run {}: {}", program, e);
+            }
+        }
+    });
+}
+
+/// Stops the sleep inhibitor and, outside Windows, everything it started
+fn stop_inhibitor(mut child: std::process::Child) {
+    #[cfg(unix)]
+    {
+        let _ = create_command("kill").args(["-TERM", "--", &format!("-{}", child.id())]).status();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Whether conversions keep the machine awake, and what happens when the queue finishes
+#[tauri::command]
+fn get_power_settings() -> PowerSettings {
+    PowerSettings {
+        prevent_sleep: prevent_sleep_enabled(),
+        after_queue: *AFTER_QUEUE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()),
+    }
+}
+
+/// Keep the machine awake while conversions are queued (saved). Takes effect with the next
+/// conversion; switching it off releases a held inhibitor right away.
+#[tauri::command]
+fn set_prevent_sleep(enabled: bool) -> Result<(), String> {
+    ensure_unlocked("Changing settings")?;
+    let mut config = load_config()?;
+    config.prevent_sleep = Some(enabled);
+    save_config(&config)?;
+    if !enabled {
+        let mut inhibitor = SLEEP_INHIBITOR.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(child) = inhibitor.0.take() {
+            stop_inhibitor(child);
+        }
+    }
+    Ok(())
+}
+
+/// Sleep or shut down the machine once every queued conversion has finished. Only for this
+/// session, and only once: it goes back to "nothing" after it has run.
+#[tauri::command]
+fn set_after_queue_action(action: AfterQueue) -> Result<(), String> {
+    ensure_unlocked("Sleeping or shutting down after converting")?;
+    info!("After the queue finishes: {:?}", action);
+    *AFTER_QUEUE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = action;
+    Ok(())
+}
+
+/// How many conversions are running and waiting, by priority
+#[tauri::command]
+fn get_queue_status() -> QueueStatus {
+    let slots = CONVERSION_SLOTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    slots.as_ref().map(SlotQueue::status).unwrap_or_default()
+}
+
+/// Get the path to the conversion history file
+fn get_history_path() -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    Ok(config_path.with_file_name("history.json"))
+}
+
+/// Get the path to the queue journal, which outlives a crash so a batch can be resumed
+fn get_queue_journal_path() -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    Ok(config_path.with_file_name(resume::QUEUE_FILE))
+}
+
+/// Load the queue journal, apply `update` and save it again
+fn update_queue_journal<T>(update: impl FnOnce(&mut QueueJournal) -> T) -> Result<T, String> {
+    let _guard = QUEUE_JOURNAL_LOCK.lock().map_err(|_| "The queue is unavailable".to_string())?;
+    let journal_path = get_queue_journal_path()?;
+    let mut journal = QueueJournal::load(&journal_path)?;
+    let value = update(&mut journal);
+    journal.save(&journal_path)?;
+    Ok(value)
+}
+
+/// Whether the process `pid` is still running, so another window's batch or output isn't
+/// taken for an interrupted one. When it can't be told, the process counts as running.
+fn process_is_running(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        std::path::Path::new(&format!("/proc/{}", pid)).exists()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        create_command("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(true)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        create_command("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+            .unwrap_or(true)
+    }
+}
+
+/// Removes outputs a previous run of the app was writing when it crashed or was closed
+fn remove_partial_outputs() {
+    match update_queue_journal(|journal| journal.take_partial_outputs(process_is_running)) {
+        Ok(partial) => {
+            for output in partial.iter().map(PathBuf::from).filter(|output| output.exists()) {
+                info!("Removing partial output left by an interrupted conversion: {}", output.display());
+                let removed = if output.is_dir() { std::fs::remove_dir_all(&output) } else { std::fs::remove_file(&output) };
+                if let Err(e) = removed {
+                    warn!("Could not remove {}: {}", output.display(), e);
+                }
+            }
+        }
+        Err(e) => warn!("Could not check for partial outputs: {}", e),
+    }
+}
+
+/// Batches a previous run of the app didn't finish, to offer resuming at launch
+#[tauri::command]
+fn get_interrupted_batches() -> Result<Vec<InterruptedBatch>, String> {
+    let _guard = QUEUE_JOURNAL_LOCK.lock().map_err(|_| "The queue is unavailable".to_string())?;
+    Ok(QueueJournal::load(&get_queue_journal_path()?)?.interrupted(process_is_running))
+}
+
+/// Converts the files an interrupted batch didn't get to, with the settings it was started with.
+/// A batch that made password-protected PDFs needs the password again, since it isn't journaled.
+#[tauri::command]
+async fn resume_batch(app: AppHandle, id: String, pdf_password: Option<String>) -> Result<BatchConversionResult, String> {
+    let mut batch = update_queue_journal(|journal| {
+        let needs_password = journal.batches.iter().any(|batch| batch.id == id && batch.needs_pdf_password);
+        if needs_password && pdf_password.is_none() {
+            return Err("This batch made password-protected PDFs; enter the password to resume it".to_string());
+        }
+        journal.remove(&id).ok_or_else(|| "This batch has already been resumed or dismissed".to_string())
+    })??;
+    if batch.needs_pdf_password {
+        batch.settings.pdf_export.password = pdf_password;
+    }
+    let jobs = batch.remaining_jobs();
+    info!("Resuming batch {}: {} of {} files left", id, jobs.len(), batch.jobs.len());
+    let input_paths = jobs.iter().map(|job| job.input_path.clone()).collect();
+    let output_directories = jobs.into_iter().map(|job| job.output_directory).collect();
+    run_batch(app, input_paths, batch.output_format, output_directories, batch.advanced_options, Some(batch.settings), batch.workers, batch.images_only).await
+}
+
+/// Forgets an interrupted batch the user doesn't want to resume
+#[tauri::command]
+fn dismiss_interrupted_batch(id: String) -> Result<(), String> {
+    info!("Dismissing interrupted batch {}", id);
+    update_queue_journal(|journal| journal.remove(&id)).map(|_| ())
+}
+
+/// Load the history, apply `update` and save it again
+fn update_history<T>(update: impl FnOnce(&mut JobHistory) -> T) -> Result<T, String> {
+    let _guard = HISTORY_LOCK.lock().map_err(|_| "History is unavailable".to_string())?;
+    let history_path = get_history_path()?;
+    let mut history = JobHistory::load(&history_path)?;
+    let value = update(&mut history);
+    history.save(&history_path)?;
+    Ok(value)
+}
+
+/// Record a finished conversion in the history. Failing to write history never fails the conversion.
+fn record_conversion(job_id: Option<String>, input_path: &PathBuf, output_format: &str, result: Result<&String, &String>) {
+    let now = chrono::Utc::now();
+    let entry = HistoryEntry {
+        id: job_id.unwrap_or_else(|| now.format("%Y%m%d%H%M%S%f").to_string()),
+        created_at: now.to_rfc3339(),
+        input_path: input_path.to_string_lossy().to_string(),
+        output_path: result.ok().cloned(),
+        output_format: output_format.to_lowercase(),
+        status: if result.is_ok() { JobStatus::Completed } else { JobStatus::Failed },
+        error: result.err().cloned(),
+        ..Default::default()
+    };
+    
+    if let Err(e) = update_history(|history| history.record(entry)) {
+        warn!("Failed to record conversion history: {}", e);
+    }
+}
+
+/// Record how long a conversion took for its size, for batch estimates
+fn record_throughput(result: &ConversionResult, input_ext: &str, output_ext: &str) {
+    let Some(tool) = &result.tool else { return };
+    let key = history::throughput_key(tool, input_ext, output_ext);
+    let sample = ThroughputSample { input_bytes: result.input_size, elapsed_ms: result.elapsed_ms };
+    if let Err(e) = update_history(|history| history.record_throughput(&key, sample)) {
+        warn!("Failed to record conversion throughput: {}", e);
+    }
+}
+
+/// Stand-in command line for conversions that only copy the file
+fn copy_command_line(input_path: &PathBuf, output_path: &PathBuf) -> String {
+    let paths = [input_path.to_string_lossy().to_string(), output_path.to_string_lossy().to_string()];
+    conversion::format_command_line("copy", &paths)
+}
+
+/// The full command line of a Command, for showing to the user
+fn command_line(command: &Command) -> String {
+    let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+    conversion::format_command_line(&command.get_program().to_string_lossy(), &args)
+}
+
+/// `command` set up to run at low CPU priority: through `nice` on Unix, and with
+/// BELOW_NORMAL_PRIORITY_CLASS on Windows. Child processes of the tool inherit it.
+fn at_low_priority(command: Command) -> Command {
+    #[cfg(target_os = "windows")]
+    {
+        let mut command = command;
+        command.creation_flags(CREATE_NO_WINDOW | scheduler::BELOW_NORMAL_PRIORITY_CLASS);
+        command
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut niced = Command::new("nice");
+        niced.args(scheduler::nice_args(command.get_program(), command.get_args()));
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => niced.env(key, value),
+                None => niced.env_remove(key),
+            };
+        }
+        if let Some(dir) = command.get_current_dir() {
+            niced.current_dir(dir);
+        }
+        niced
+    }
+}
+
+/// FFmpeg output options limiting its threads, when the settings ask for that
+fn ffmpeg_thread_args(settings: &ConversionSettings) -> Vec<String> {
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    match scheduler::ffmpeg_threads(settings.ffmpeg_threads, settings.low_priority, cores) {
+        Some(threads) => vec!["-threads".to_string(), threads.to_string(), "-filter_threads".to_string(), threads.to_string()],
+        None => Vec::new(),
+    }
+}
+
+/// Fixes the timestamps tools that read SOURCE_DATE_EPOCH write, for deterministic output
+fn set_deterministic_env(command: &mut Command, settings: &ConversionSettings) {
+    for (key, value) in conversion::deterministic_env(settings) {
+        command.env(key, value);
+    }
+}
+
 /// Helper function to create a Command that doesn't show a console window on Windows
 fn create_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
     #[cfg(target_os = "windows")]
@@ -113,7 +803,8 @@ fn create_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
 /// Get the log directory path
 #[tauri::command]
 async fn check_app_update(app: AppHandle) -> Result<bool, String> {
-    match app.updater() {
+    refresh_remote_feature_flags().await;
+    match app_updater(&app) {
         Ok(updater) => {
             match updater.check().await {
                 Ok(Some(_update)) => Ok(true),
@@ -133,7 +824,8 @@ async fn check_app_update(app: AppHandle) -> Result<bool, String> {
 
 #[tauri::command]
 async fn install_app_update(app: AppHandle) -> Result<(), String> {
-    match app.updater() {
+    ensure_unlocked("Installing app updates")?;
+    match app_updater(&app) {
         Ok(updater) => {
             match updater.check().await {
                 Ok(Some(update)) => {
@@ -203,587 +895,124 @@ async fn open_log_directory(app: AppHandle) -> Result<(), String> {
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+/// What a file can be converted to. With `input_path` the format is taken from the file
+/// (see check_input_format), so a file without an extension gets options too.
 #[tauri::command]
-fn get_available_formats(input_extension: String) -> Vec<ConversionOption> {
+fn get_available_formats(input_extension: String, input_path: Option<String>) -> Vec<ConversionOption> {
     // Debug logging
     info!("Getting available formats for extension: '{}'", input_extension);
     
-    // This is a simplified version - in production, you'd have more sophisticated mapping
-    let mut options = Vec::new();
-    
     // Convert to lowercase for case-insensitive matching
-    let input_extension = input_extension.to_lowercase();
-    
-    match input_extension.as_str() {
-        // Video formats
-        "mp4" | "mov" | "avi" | "mkv" | "webm" | "flv" | "wmv" | "m4v" | "mpg" | "mpeg" | "3gp" => {
-            // Video output formats
-            if input_extension != "mp4" {
-                options.push(ConversionOption {
-                    format: "mp4".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "MP4 Video".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "mov" {
-                options.push(ConversionOption {
-                    format: "mov".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "QuickTime Video".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "avi" {
-                options.push(ConversionOption {
-                    format: "avi".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "AVI Video".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "mkv" {
-                options.push(ConversionOption {
-                    format: "mkv".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Matroska Video".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "webm" {
-                options.push(ConversionOption {
-                    format: "webm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "WebM Video".to_string(),
-                    color: "green".to_string(),
-                });
-            }
-            // GIF from video
-            options.push(ConversionOption {
-                format: "gif".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "Animated GIF".to_string(),
-                color: "pink".to_string(),
-            });
-            // Audio extraction
-            options.push(ConversionOption {
-                format: "mp3".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "MP3 Audio".to_string(),
-                color: "green".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "wav".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "WAV Audio".to_string(),
-                color: "light-tan".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "flac".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "FLAC Audio (Lossless)".to_string(),
-                color: "aquamarine".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "ogg".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "OGG Audio".to_string(),
-                color: "orange".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "m4a".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "M4A Audio".to_string(),
-                color: "light-purple".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "aac".to_string(),
-                tool: "ffmpeg".to_string(),
-                display_name: "AAC Audio".to_string(),
-                color: "yellow".to_string(),
-            });
-        }
-        // Audio formats
-        "mp3" | "wav" | "flac" | "ogg" | "m4a" | "wma" | "aac" => {
-            if input_extension != "mp3" {
-                options.push(ConversionOption {
-                    format: "mp3".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "MP3 Audio".to_string(),
-                    color: "green".to_string(),
-                });
-            }
-            if input_extension != "wav" {
-                options.push(ConversionOption {
-                    format: "wav".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "WAV Audio".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            if input_extension != "flac" {
-                options.push(ConversionOption {
-                    format: "flac".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "FLAC Audio (Lossless)".to_string(),
-                    color: "aquamarine".to_string(),
-                });
-            }
-            if input_extension != "ogg" {
-                options.push(ConversionOption {
-                    format: "ogg".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "OGG Audio".to_string(),
-                    color: "orange".to_string(),
-                });
-            }
-            if input_extension != "m4a" {
-                options.push(ConversionOption {
-                    format: "m4a".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "M4A Audio".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            if input_extension != "aac" {
-                options.push(ConversionOption {
-                    format: "aac".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "AAC Audio".to_string(),
-                    color: "yellow".to_string(),
-                });
-            }
-        }
-        "docx" | "doc" | "odt" => {
-            options.push(ConversionOption {
-                format: "pdf".to_string(),
-                tool: "libreoffice".to_string(),
-                display_name: "PDF Document".to_string(),
-                color: "pink".to_string(),
-            });
-            if ENABLE_PANDOC {
-                options.push(ConversionOption {
-                    format: "epub".to_string(),
-                    tool: "pandoc".to_string(),
-                    display_name: "E-Book".to_string(),
-                    color: "blue".to_string(),
-                });
-                options.push(ConversionOption {
-                    format: "txt".to_string(),
-                    tool: "pandoc".to_string(),
-                    display_name: "Plain Text".to_string(),
-                    color: "lavender".to_string(),
-                });
-            }
-        }
-        "md" | "markdown" if ENABLE_PANDOC => {
-            // Markdown can convert to many formats via Pandoc
-            options.push(ConversionOption {
-                format: "html".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "HTML Document".to_string(),
-                color: "orange".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "docx".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Word Document".to_string(),
-                color: "blue".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "epub".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "E-Book".to_string(),
-                color: "pink".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "txt".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Plain Text".to_string(),
-                color: "lavender".to_string(),
-            });
-        }
-        "html" | "htm" if ENABLE_PANDOC => {
-            // HTML can convert via Pandoc
-            options.push(ConversionOption {
-                format: "md".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Markdown".to_string(),
-                color: "blue".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "docx".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Word Document".to_string(),
-                color: "blue".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "epub".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "E-Book".to_string(),
-                color: "pink".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "txt".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Plain Text".to_string(),
-                color: "lavender".to_string(),
-            });
-        }
-        "txt" if ENABLE_PANDOC => {
-            // Plain text can convert via Pandoc
-            options.push(ConversionOption {
-                format: "md".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Markdown".to_string(),
-                color: "blue".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "html".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "HTML Document".to_string(),
-                color: "orange".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "docx".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "Word Document".to_string(),
-                color: "blue".to_string(),
-            });
-            options.push(ConversionOption {
-                format: "epub".to_string(),
-                tool: "pandoc".to_string(),
-                display_name: "E-Book".to_string(),
-                color: "pink".to_string(),
-            });
-        }
-        // Standard image formats + Modern + Professional + Legacy + RAW + Animation
-        "png" | "jpg" | "jpeg" | "bmp" | "tiff" | "tif" | "webp" | "gif" | 
-        // Modern formats
-        "heic" | "heif" | "avif" | "jxl" |
-        // Professional/High-end
-        "tga" | "ppm" | "pgm" | "pbm" | "pam" | "xbm" | "xpm" | "dds" | "dpx" | "exr" | "hdr" | "ico" | "j2k" | "jp2" | "pcx" | "pfm" | "sgi" | "sun" | "xwd" |
-        // Adobe/Photoshop
-        "psd" | "psb" |
-        // Vector (rasterized)
-        "svg" | "svgz" |
-        // Animation
-        "apng" |
-        // GIMP
-        "xcf" |
-        // Windows
-        "cur" | "emf" | "wmf" |
-        // Camera RAW formats
-        "arw" | "cr2" | "cr3" | "crw" | "dng" | "nef" | "nrw" | "orf" | "raf" | "raw" | "rw2" | "rwl" | "srw" => {
-            // Standard formats
-            // JPG <-> JPEG simple rename conversions
-            if input_extension == "jpg" {
-                options.push(ConversionOption {
-                    format: "jpeg".to_string(),
-                    tool: "rename".to_string(),
-                    display_name: "JPEG (rename extension)".to_string(),
-                    color: "yellow".to_string(),
-                });
-            }
-            if input_extension == "jpeg" {
-                options.push(ConversionOption {
-                    format: "jpg".to_string(),
-                    tool: "rename".to_string(),
-                    display_name: "JPG (rename extension)".to_string(),
-                    color: "yellow".to_string(),
-                });
-            }
-            
-            if input_extension != "jpg" && input_extension != "jpeg" {
-                options.push(ConversionOption {
-                    format: "jpg".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "JPEG Image (.jpg)".to_string(),
-                    color: "yellow".to_string(),
-                });
-                options.push(ConversionOption {
-                    format: "jpeg".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "JPEG Image (.jpeg)".to_string(),
-                    color: "yellow".to_string(),
-                });
-            }
-            if input_extension != "png" {
-                options.push(ConversionOption {
-                    format: "png".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "PNG Image".to_string(),
-                    color: "orange".to_string(),
-                });
-            }
-            if input_extension != "gif" {
-                options.push(ConversionOption {
-                    format: "gif".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "GIF Image".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "bmp" {
-                options.push(ConversionOption {
-                    format: "bmp".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Bitmap Image".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            if input_extension != "webp" {
-                options.push(ConversionOption {
-                    format: "webp".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "WebP Image".to_string(),
-                    color: "green".to_string(),
-                });
-            }
-            if input_extension != "tiff" {
-                options.push(ConversionOption {
-                    format: "tiff".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "TIFF Image".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            
-            // Modern formats
-            // HEIC/HEIF encoding now supported via ImageMagick
-            if input_extension != "heic" && input_extension != "heif" {
-                options.push(ConversionOption {
-                    format: "heic".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "HEIC (High Efficiency)".to_string(),
-                    color: "pink".to_string(),
-                });
-                options.push(ConversionOption {
-                    format: "heif".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "HEIF (High Efficiency)".to_string(),
-                    color: "pink".to_string(),
-                });
-            }
-            if input_extension != "avif" {
-                options.push(ConversionOption {
-                    format: "avif".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "AVIF (AV1 Image)".to_string(),
-                    color: "aquamarine".to_string(),
-                });
-            }
-            if input_extension != "jxl" {
-                options.push(ConversionOption {
-                    format: "jxl".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "JPEG XL".to_string(),
-                    color: "aquamarine".to_string(),
-                });
-            }
-            
-            // Professional/High-end formats
-            if input_extension != "tga" {
-                options.push(ConversionOption {
-                    format: "tga".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Targa Image".to_string(),
-                    color: "pink".to_string(),
-                });
-            }
-            if input_extension != "exr" {
-                options.push(ConversionOption {
-                    format: "exr".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "OpenEXR (HDR)".to_string(),
-                    color: "aquamarine".to_string(),
-                });
-            }
-            if input_extension != "hdr" {
-                options.push(ConversionOption {
-                    format: "hdr".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Radiance HDR".to_string(),
-                    color: "aquamarine".to_string(),
-                });
-            }
-            if input_extension != "dpx" {
-                options.push(ConversionOption {
-                    format: "dpx".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Digital Picture Exchange".to_string(),
-                    color: "pink".to_string(),
-                });
-            }
-            
-            // JPEG 2000 formats
-            if input_extension != "j2k" && input_extension != "jp2" {
-                options.push(ConversionOption {
-                    format: "j2k".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "JPEG 2000".to_string(),
-                    color: "yellow".to_string(),
-                });
-            }
-            
-            // Legacy/Specialized formats
-            if input_extension != "pcx" {
-                options.push(ConversionOption {
-                    format: "pcx".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "PCX Image".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            if input_extension != "ico" {
-                options.push(ConversionOption {
-                    format: "ico".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Windows Icon".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            if input_extension != "sgi" {
-                options.push(ConversionOption {
-                    format: "sgi".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Silicon Graphics Image".to_string(),
-                    color: "green".to_string(),
-                });
-            }
-            
-            // Raw/Uncompressed formats
-            if input_extension != "ppm" {
-                options.push(ConversionOption {
-                    format: "ppm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Portable Pixmap".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            if input_extension != "pgm" {
-                options.push(ConversionOption {
-                    format: "pgm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Portable Graymap".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            if input_extension != "pbm" {
-                options.push(ConversionOption {
-                    format: "pbm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Portable Bitmap".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            if input_extension != "pam" {
-                options.push(ConversionOption {
-                    format: "pam".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "Portable Arbitrary Map".to_string(),
-                    color: "light-tan".to_string(),
-                });
-            }
-            
-            // X Window System formats
-            if input_extension != "xbm" {
-                options.push(ConversionOption {
-                    format: "xbm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "X11 Bitmap".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            if input_extension != "xpm" {
-                options.push(ConversionOption {
-                    format: "xpm".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "X11 Pixmap".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            if input_extension != "xwd" {
-                options.push(ConversionOption {
-                    format: "xwd".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "X Window Dump".to_string(),
-                    color: "light-purple".to_string(),
-                });
-            }
-            
-            // Gaming/3D formats
-            if input_extension != "dds" {
-                options.push(ConversionOption {
-                    format: "dds".to_string(),
-                    tool: "ffmpeg".to_string(),
-                    display_name: "DirectDraw Surface".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            
-            // Adobe/Photoshop format
-            if input_extension != "psd" && input_extension != "psb" {
-                options.push(ConversionOption {
-                    format: "psd".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "Photoshop Document".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            
-            // Animation format
-            if input_extension != "apng" {
-                options.push(ConversionOption {
-                    format: "apng".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "Animated PNG".to_string(),
-                    color: "orange".to_string(),
-                });
-            }
-            
-            // Windows cursor format
-            if input_extension != "cur" {
-                options.push(ConversionOption {
-                    format: "cur".to_string(),
-                    tool: "imagemagick".to_string(),
-                    display_name: "Windows Cursor".to_string(),
-                    color: "blue".to_string(),
-                });
-            }
-            
-            // Document format - PDF output from images
-            options.push(ConversionOption {
-                format: "pdf".to_string(),
-                tool: "imagemagick".to_string(),
-                display_name: "PDF Document".to_string(),
-                color: "pink".to_string(),
-            });
-        }
-        _ => {
-            info!("No conversion options found for extension: '{}'", input_extension);
-        }
+    let input_extension = match input_path {
+        Some(path) => check_input_format(&PathBuf::from(path), None).format,
+        None => input_extension.to_lowercase(),
+    };
+    let options = conversion::format_registry().options(&input_extension);
+    if options.is_empty() {
+        info!("No conversion options found for extension: '{}'", input_extension);
     }
     
     info!("Found {} format options for '{}'", options.len(), input_extension);
     options
 }
 
+/// Every input the app converts, what to, and which of those the installed tools can do,
+/// so the UI can grey out options instead of keeping its own format lists
+#[tauri::command]
+fn get_conversion_matrix() -> ConversionMatrix {
+    let matrix = conversion::format_registry().matrix(|tool| get_tool_path(tool).is_ok());
+    let missing: Vec<&String> = matrix.tools.iter().filter(|(_, installed)| !**installed).map(|(tool, _)| tool).collect();
+    info!("Conversion matrix: {} categories, missing tools: {:?}", matrix.categories.len(), missing);
+    matrix
+}
+
+/// What a drop of different kinds of files (e.g. photos and videos) can be converted to
+/// together, and which tool each file would use, so the UI can offer "convert everything to X"
+#[tauri::command]
+fn plan_batch(input_paths: Vec<String>) -> BatchPlan {
+    let inputs: Vec<(String, String)> = input_paths
+        .into_iter()
+        .map(|path| {
+            let format = check_input_format(&PathBuf::from(&path), None).format;
+            (path, format)
+        })
+        .collect();
+    let plan = conversion::format_registry().plan_batch(&inputs);
+    info!(
+        "Batch plan for {} files: {} common formats, {} unsupported",
+        inputs.len(),
+        plan.common_formats.len(),
+        plan.unsupported.len()
+    );
+    plan
+}
+
 /// Generate a unique file path by adding a numbered suffix if the file already exists
 /// Example: "file.png" -> "file (1).png" -> "file (2).png" etc.
 fn get_unique_output_path(base_dir: &PathBuf, file_stem: &str, extension: &str) -> PathBuf {
-    let initial_path = base_dir.join(format!("{}.{}", file_stem, extension));
+    let reserved = RESERVED_OUTPUTS.lock().map(|reserved| reserved.clone()).unwrap_or_default();
+    next_free_output_path(base_dir, file_stem, extension, &reserved)
+}
+
+/// Creates a new folder named `name` (or "name (1)", ...) in `base_dir` for a command
+/// that writes several files
+fn create_unique_output_dir(base_dir: &PathBuf, name: &str) -> Result<PathBuf, String> {
+    // Held until the folder exists, so a parallel job can't pick the same name
+    let reservation = reserve_output_path(base_dir, name, "");
+    std::fs::create_dir_all(&reservation.path)
+        .map_err(|e| format!("Failed to create output folder: {}", e))?;
+    Ok(reservation.path.clone())
+}
+
+/// Like get_unique_output_path, but also claims the path until the returned
+/// reservation is dropped (once the conversion has written the file)
+fn reserve_output_path(base_dir: &PathBuf, file_stem: &str, extension: &str) -> OutputReservation {
+    let mut reserved = RESERVED_OUTPUTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let path = next_free_output_path(base_dir, file_stem, extension, &reserved);
+    reserved.insert(path.clone());
+    drop(reserved);
+    // Noted on disk too: if the app dies before the output is finished, the next launch removes it
+    let (output, pid) = (path.to_string_lossy().to_string(), std::process::id());
+    if let Err(e) = update_queue_journal(|journal| journal.writing.insert(output, pid)) {
+        warn!("Could not note the output being written: {}", e);
+    }
+    OutputReservation { path }
+}
+
+/// An output path claimed by reserve_output_path, released when dropped
+struct OutputReservation {
+    path: PathBuf,
+}
+
+impl Drop for OutputReservation {
+    fn drop(&mut self) {
+        if let Ok(mut reserved) = RESERVED_OUTPUTS.lock() {
+            reserved.remove(&self.path);
+        }
+        let output = self.path.to_string_lossy().to_string();
+        if let Err(e) = update_queue_journal(|journal| journal.writing.remove(&output)) {
+            warn!("Could not note the output as written: {}", e);
+        }
+    }
+}
+
+/// The first of "file.png", "file (1).png", ... that doesn't exist and isn't reserved.
+/// An empty extension names a folder: "frames", "frames (1)", ...
+fn next_free_output_path(base_dir: &PathBuf, file_stem: &str, extension: &str, reserved: &BTreeSet<PathBuf>) -> PathBuf {
+    let is_free = |path: &PathBuf| !path.exists() && !reserved.contains(path);
+    let dot_extension = if extension.is_empty() { String::new() } else { format!(".{}", extension) };
+    let initial_path = base_dir.join(format!("{}{}", file_stem, dot_extension));
     
     // If the file doesn't exist, use the original name
-    if !initial_path.exists() {
+    if is_free(&initial_path) {
         return initial_path;
     }
     
     // File exists, so find the next available number
     let mut counter = 1;
     loop {
-        let numbered_path = base_dir.join(format!("{} ({}).{}", file_stem, counter, extension));
-        if !numbered_path.exists() {
+        let numbered_path = base_dir.join(format!("{} ({}){}", file_stem, counter, dot_extension));
+        if is_free(&numbered_path) {
             return numbered_path;
         }
         counter += 1;
@@ -795,7 +1024,339 @@ fn get_unique_output_path(base_dir: &PathBuf, file_stem: &str, extension: &str)
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs();
-            return base_dir.join(format!("{} ({}).{}", file_stem, timestamp, extension));
+            return base_dir.join(format!("{} ({}){}", file_stem, timestamp, dot_extension));
+        }
+    }
+}
+
+/// Render the output file stem from the conversion's naming template, or the saved default,
+/// adapted to the conversion's file name mode
+fn render_output_name(input_path: &PathBuf, file_stem: &str, output_format: &str, settings: &ConversionSettings) -> Result<String, String> {
+    let rendered = render_template_name(input_path, file_stem, output_format, settings)?;
+    Ok(naming::sanitize_file_name(&rendered, settings.file_name_mode))
+}
+
+fn render_template_name(input_path: &PathBuf, file_stem: &str, output_format: &str, settings: &ConversionSettings) -> Result<String, String> {
+    let template = match &settings.name_template {
+        Some(template) => template.clone(),
+        None => load_config()
+            .ok()
+            .and_then(|config| config.name_template)
+            .unwrap_or_else(|| naming::DEFAULT_NAME_TEMPLATE.to_string()),
+    };
+    if template == naming::DEFAULT_NAME_TEMPLATE {
+        return Ok(file_stem.to_string());
+    }
+    naming::validate_template(&template)?;
+    
+    let (width, height) = if naming::uses_dimensions(&template) {
+        input_dimensions(input_path).map_or((None, None), |(w, h)| (Some(w), Some(h)))
+    } else {
+        (None, None)
+    };
+    
+    let context = NamingContext {
+        name: file_stem.to_string(),
+        format: output_format.to_lowercase(),
+        date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+        counter: settings.name_counter.unwrap_or(1),
+        width,
+        height,
+    };
+    Ok(naming::render_template(&template, &context))
+}
+
+/// Font for FFmpeg's drawtext on Windows, whose FFmpeg builds can't look fonts up by name
+/// (None elsewhere, where fontconfig picks one)
+fn drawtext_font_file() -> Option<String> {
+    std::env::var("WINDIR").ok()
+        .map(|windir| PathBuf::from(windir).join("Fonts").join("arial.ttf"))
+        .filter(|font| cfg!(windows) && font.exists())
+        .map(|font| font.to_string_lossy().replace('\\', "/"))
+}
+
+/// Size of the picture a watermark is drawn on (see conversion::watermark_canvas)
+fn watermark_canvas(input_path: &PathBuf, settings: &ConversionSettings) -> Result<(u32, u32), String> {
+    let (width, height) = input_dimensions(input_path)
+        .or_else(|| image::image_dimensions(input_path).ok())
+        .ok_or("Could not read the picture size to place the watermark")?;
+    let is_image = input_path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| conversion::is_image_format(&ext.to_lowercase()));
+    let orientation = if is_image { read_exif_orientation(input_path) } else { 1 };
+    Ok(conversion::watermark_canvas(width, height, orientation, settings))
+}
+
+/// Width and height of an image (via ImageMagick) or video (via FFmpeg), if they can be read
+fn input_dimensions(input_path: &PathBuf) -> Option<(u32, u32)> {
+    let ext = check_input_format(input_path, None).format;
+    
+    if conversion::is_image_format(&ext) {
+        let tool_path = get_tool_path("imagemagick").ok()?;
+        let mut job_dir = None;
+        let input = imagemagick_input(input_path, Some(0), &mut job_dir).ok()?;
+        let mut command = create_command(&tool_path);
+        command.arg("identify").arg("-format").arg("%w %h").arg(input);
+        run_in_job_dir(&mut command, &job_dir);
+        let output = command.output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut parts = text.split_whitespace().map(|n| n.parse::<u32>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(width)), Some(Ok(height))) => Some((width, height)),
+            _ => None,
+        }
+    } else if conversion::is_video_format(&ext) {
+        let ffmpeg_path = get_tool_path("ffmpeg").ok()?;
+        let output = create_command(&ffmpeg_path)
+            .arg("-hide_banner")
+            .arg("-i")
+            .arg(input_path)
+            .output()
+            .ok()?;
+        let video = probe::parse_ffmpeg_banner(&String::from_utf8_lossy(&output.stderr)).video?;
+        Some((video.width?, video.height?))
+    } else {
+        None
+    }
+}
+
+/// The saved default naming template
+#[tauri::command]
+fn get_default_name_template() -> Result<String, String> {
+    let config = load_config()?;
+    Ok(config.name_template.unwrap_or_else(|| naming::DEFAULT_NAME_TEMPLATE.to_string()))
+}
+
+/// Save the default naming template used when a conversion doesn't set one (None resets it)
+#[tauri::command]
+fn set_default_name_template(template: Option<String>) -> Result<(), String> {
+    ensure_unlocked("Changing settings")?;
+    if let Some(ref template) = template {
+        naming::validate_template(template)?;
+    }
+    let mut config = load_config()?;
+    config.name_template = template.filter(|t| t != naming::DEFAULT_NAME_TEMPLATE);
+    save_config(&config)
+}
+
+/// The saved FFmpeg log level, or the default when none (or an unknown one) is saved
+fn ffmpeg_log_level() -> String {
+    load_config()
+        .ok()
+        .and_then(|config| config.ffmpeg_log_level)
+        .filter(|level| conversion::FFMPEG_LOG_LEVELS.contains(&level.as_str()))
+        .unwrap_or_else(|| conversion::DEFAULT_FFMPEG_LOG_LEVEL.to_string())
+}
+
+/// How much FFmpeg prints during conversions. At "warning" and "error" everything it prints
+/// is reported with the result; "info" and "verbose" are for reading the log.
+#[tauri::command]
+fn get_ffmpeg_log_level() -> String {
+    ffmpeg_log_level()
+}
+
+/// Save the FFmpeg log level (None resets it to "warning")
+#[tauri::command]
+fn set_ffmpeg_log_level(level: Option<String>) -> Result<(), String> {
+    ensure_unlocked("Changing settings")?;
+    if let Some(ref level) = level {
+        if !conversion::FFMPEG_LOG_LEVELS.contains(&level.as_str()) {
+            return Err(format!("Unknown FFmpeg log level \"{}\" (use one of {})", level, conversion::FFMPEG_LOG_LEVELS.join(", ")));
+        }
+    }
+    let mut config = load_config()?;
+    config.ffmpeg_log_level = level.filter(|level| level != conversion::DEFAULT_FFMPEG_LOG_LEVEL);
+    save_config(&config)
+}
+
+/// Resolves the feature flags from the config and puts them in effect
+fn apply_feature_flags(config: &ToolConfig) -> Vec<FeatureFlag> {
+    let flags = feature_flags::resolve(&config.feature_flags, &config.remote_feature_flags);
+    feature_flags::apply(&flags);
+    flags
+}
+
+/// Fetches the remote feature flag manifest and saves its flags. Failing is fine: the
+/// flags from the last check (or the defaults) stay in effect.
+async fn refresh_remote_feature_flags() {
+    // A kiosk keeps the features it was provisioned with
+    if kiosk_mode() {
+        return;
+    }
+    let client = match create_api_client() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Could not check feature flags: {}", e);
+            return;
+        }
+    };
+    let manifest = match send_with_retry("Checking feature flags", RetryPolicy::CHECK, || client.get(feature_flags::REMOTE_MANIFEST_URL)).await {
+        Ok(response) if response.status().is_success() => response.json::<RemoteManifest>().await,
+        Ok(response) => {
+            debug!("No feature flag manifest ({})", response.status());
+            return;
+        }
+        Err(e) => {
+            debug!("Could not fetch the feature flag manifest: {}", e);
+            return;
+        }
+    };
+    let manifest = match manifest {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!("Ignoring an unreadable feature flag manifest: {}", e);
+            return;
+        }
+    };
+    let Ok(mut config) = load_config() else {
+        return;
+    };
+    if config.remote_feature_flags != manifest.flags {
+        config.remote_feature_flags = manifest.flags;
+        if let Err(e) = save_config(&config) {
+            warn!("Could not save the remote feature flags: {}", e);
+        }
+        apply_feature_flags(&config);
+    }
+}
+
+/// Every feature flag, whether it's on and what decided that
+#[tauri::command]
+fn get_feature_flags() -> Result<Vec<FeatureFlag>, String> {
+    let config = load_config()?;
+    Ok(feature_flags::resolve(&config.feature_flags, &config.remote_feature_flags))
+}
+
+/// Switch a feature flag on or off (None goes back to the remote or built-in default).
+/// Takes effect for the next conversion.
+#[tauri::command]
+fn set_feature_flag(name: String, enabled: Option<bool>) -> Result<Vec<FeatureFlag>, String> {
+    ensure_unlocked("Changing settings")?;
+    if !feature_flags::is_known(&name) {
+        return Err(format!("Unknown feature flag \"{}\"", name));
+    }
+    let mut config = load_config()?;
+    match enabled {
+        Some(enabled) => config.feature_flags.insert(name.clone(), enabled),
+        None => config.feature_flags.remove(&name),
+    };
+    save_config(&config)?;
+    info!("Feature flag {} set to {:?}", name, enabled);
+    Ok(apply_feature_flags(&config))
+}
+
+/// Size and modification time of `path` right now
+fn sample_file(path: &PathBuf) -> Option<FileSample> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(FileSample { len: metadata.len(), modified: metadata.modified().ok() })
+}
+
+/// Whether another program has `path` open for writing. Only Windows can tell: opening the
+/// file without sharing write access fails while a writer has it open.
+fn is_locked(path: &PathBuf) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_SHARE_READ: u32 = 0x1;
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        let opened = std::fs::OpenOptions::new().read(true).share_mode(FILE_SHARE_READ).open(path);
+        matches!(opened.map_err(|e| e.raw_os_error()), Err(Some(32 | 33)))
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// An input's state as far as it can be told without waiting
+enum FirstLook {
+    Known(InputState),
+    /// Written in the last few seconds: compared with a second sample after SETTLE_INTERVAL
+    Watch(FileSample),
+}
+
+fn first_look(path: &PathBuf) -> FirstLook {
+    let downloading = input_state::is_partial_download(path)
+        || input_state::partial_download_names(path).iter().any(|partial| partial.exists());
+    if downloading {
+        return FirstLook::Known(InputState::Downloading);
+    }
+    if is_locked(path) {
+        return FirstLook::Known(InputState::Locked);
+    }
+    match sample_file(path) {
+        Some(before) if input_state::recently_modified(before.modified, std::time::SystemTime::now()) => FirstLook::Watch(before),
+        _ => FirstLook::Known(InputState::Ready),
+    }
+}
+
+/// What keeps each of `paths` from being converted right now. Only files written in the last
+/// few seconds are watched, all in the same SETTLE_INTERVAL, to see whether they're still growing.
+fn input_states(paths: &[PathBuf]) -> Vec<InputState> {
+    let looks: Vec<FirstLook> = paths.iter().map(first_look).collect();
+    if looks.iter().any(|look| matches!(look, FirstLook::Watch(_))) {
+        std::thread::sleep(input_state::SETTLE_INTERVAL);
+    }
+    paths
+        .iter()
+        .zip(looks)
+        .map(|(path, look)| match look {
+            FirstLook::Known(state) => state,
+            FirstLook::Watch(before) => match sample_file(path) {
+                Some(after) if input_state::is_settled(&before, &after) => InputState::Ready,
+                _ => InputState::Growing,
+            },
+        })
+        .collect()
+}
+
+/// What keeps `path` from being converted right now (see input_states)
+fn input_state(path: &PathBuf) -> InputState {
+    input_states(std::slice::from_ref(path))[0]
+}
+
+/// Waits up to `wait_secs` for an input that is still downloading, being written or open in
+/// another program, then fails with a "file in use" error instead of converting partial data
+fn wait_for_input(path: &PathBuf, wait_secs: u32) -> Result<(), String> {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(wait_secs as u64);
+    let mut logged = false;
+    loop {
+        let state = input_state(path);
+        if state == InputState::Ready {
+            if logged {
+                info!("{} is ready", path.display());
+            }
+            return Ok(());
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(input_state::busy_message(path, state, wait_secs));
+        }
+        if !logged {
+            info!("Waiting up to {}s for {} ({:?})", wait_secs, path.display(), state);
+            logged = true;
+        }
+        std::thread::sleep(input_state::SETTLE_INTERVAL);
+    }
+}
+
+/// wait_for_input on a blocking thread, so its sleeps don't hold up the async runtime
+async fn wait_for_input_async(path: PathBuf, wait_secs: u32) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || wait_for_input(&path, wait_secs))
+        .await
+        .map_err(|e| format!("Could not wait for the input: {}", e))?
+}
+
+/// Whether each of `paths` can be converted now, for showing "still downloading" in the list
+#[tauri::command]
+async fn get_input_states(paths: Vec<String>) -> Vec<InputState> {
+    let count = paths.len();
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    match tauri::async_runtime::spawn_blocking(move || input_states(&paths)).await {
+        Ok(states) => states,
+        Err(e) => {
+            warn!("Could not check the inputs: {}", e);
+            vec![InputState::Ready; count]
         }
     }
 }
@@ -806,13 +1367,18 @@ async fn convert_file(
     output_format: String,
     output_directory: Option<String>,
     advanced_options: Option<String>,
-) -> Result<String, String> {
+    settings: Option<ConversionSettings>,
+    job_id: Option<String>,
+    input_format: Option<String>,
+) -> Result<ConversionResult, String> {
     // Log conversion details
     info!("Starting conversion: {} -> {}", input_path, output_format);
     info!("Output directory: {:?}", output_directory);
     if let Some(ref opts) = advanced_options {
         info!("Advanced options: {}", opts);
     }
+    let settings = settings.unwrap_or_default();
+    info!("Conversion settings: {:?}", settings);
     
     let input_path = PathBuf::from(&input_path);
     let file_stem = input_path.file_stem()
@@ -820,10 +1386,15 @@ async fn convert_file(
         .to_str()
         .ok_or("Invalid file name")?;
     
-    let input_extension = input_path.extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    // Route by what the file is, not what it's named: a JPEG saved as .png, or a download
+    // without an extension. `input_format` overrides both.
+    validate_input_format(input_format.as_deref())?;
+    let format_check = check_input_format(&input_path, input_format.as_deref());
+    let format_warning = format_check.warning();
+    if let Some(warning) = &format_warning {
+        warn!("{}: {}", input_path.display(), warning);
+    }
+    let input_extension = format_check.format.clone();
     
     let output_dir = if let Some(dir) = output_directory {
         PathBuf::from(dir)
@@ -834,845 +1405,4522 @@ async fn convert_file(
             .to_path_buf()
     };
     
+    // Photo libraries can be filed by when, or with what, each photo was taken
+    let output_dir = match settings.file_by {
+        Some(by) if conversion::is_image_format(&input_extension) => output_dir.join(filing_folder(&input_path, by).await),
+        _ => output_dir,
+    };
+    
     // Create output directory if it doesn't exist
     std::fs::create_dir_all(&output_dir)
         .map_err(|e| format!("Failed to create output directory: {}", e))?;
     
-    // Get a unique output path that won't overwrite existing files
-    let output_path = get_unique_output_path(&output_dir, file_stem, &output_format);
+    // A download that hasn't finished would convert partial data; waiting happens before
+    // taking a slot so it doesn't hold up other conversions
+    let wait_secs = settings.input_wait_secs.unwrap_or(input_state::DEFAULT_INPUT_WAIT_SECS);
+    if let Err(e) = wait_for_input_async(input_path.clone(), wait_secs).await {
+        warn!("{}", e);
+        record_conversion(job_id, &input_path, &output_format, Err(&e));
+        return Err(e);
+    }
     
-    // Determine which tool to use and perform the actual conversion
-    let output_format_lower = output_format.to_lowercase();
-    let conversion_result = match determine_conversion_tool(&input_extension, &output_format_lower) {
-        Some(tool) => {
-            execute_conversion(tool, &input_path, &output_path, advanced_options).await
+    // Wait for a free slot; user-started jobs go ahead of queued background jobs
+    let _slot = match ConversionSlot::acquire_async(settings.priority).await {
+        Ok(slot) => slot,
+        Err(e) => {
+            record_conversion(job_id, &input_path, &output_format, Err(&e));
+            return Err(e);
         }
-        None => {
+    };
+    // Timed from here so the wait for a slot doesn't count
+    let started = std::time::Instant::now();
+    
+    // Tools go by the name too, so a misnamed or extensionless file is converted from a copy
+    // named for its contents. Uppercase extensions (IMG_1234.JPG) only need the lowercase
+    // format for routing: the tools read them as they are.
+    let (tool_input, _format_dir) = if format_check.renamed() && !input_extension.is_empty() {
+        let mut dir = JobDir::create()?;
+        let staged = dir.path.join(dir.stage_as(&input_path, &input_extension)?);
+        (staged, Some(dir))
+    } else {
+        (input_path.clone(), None)
+    };
+    
+    // "compress" keeps the input format and only re-encodes it smaller
+    if output_format.to_lowercase() == conversion::COMPRESS_TARGET {
+        let result = compress_image(&tool_input, &output_dir, file_stem, &input_extension, &settings);
+        record_conversion(job_id, &input_path, &output_format, result.as_ref());
+        let output_path = result?;
+        return Ok(conversion_stats(&input_path, output_path, started, None));
+    }
+    
+    // Name the output from the template, then make sure it won't overwrite existing files
+    // (or the output of another conversion that's still running)
+    let output_stem = render_output_name(&input_path, file_stem, &output_format, &settings)?;
+    let reservation = reserve_output_path(&output_dir, &output_stem, &output_format);
+    let output_path = reservation.path.clone();
+    
+    // Determine which tool to use and perform the actual conversion
+    let output_format_lower = output_format.to_lowercase();
+    let tool = conversion_tool_for(&input_extension, &output_format_lower, &settings);
+    
+    let conversion_result = match (tool, settings.target_size_mb) {
+        // Target size mode replaces the usual single-pass FFmpeg encode
+        (Some("ffmpeg"), Some(target_mb)) if conversion::target_size_codecs(&output_format_lower).is_some() => {
+            encode_to_target_size(&tool_input, &output_path, target_mb, advanced_options, &settings).map(ToolRun::from)
+        }
+        (Some(tool), _) => {
+            execute_conversion(tool, &tool_input, &output_path, advanced_options, &settings).await
+        }
+        (None, _) => {
             let error_msg = format!("No conversion tool available for {} to {}", input_extension, output_format);
             error!("{}", error_msg);
+            record_conversion(job_id, &input_path, &output_format, Err(&error_msg));
             return Err(error_msg);
         }
     };
     
-    match conversion_result {
-        Ok(_) => {
+    let mut result = match conversion_result {
+        Ok(run) => {
+            if settings.keeps_metadata() {
+                copy_source_metadata(&input_path, &output_path, !settings.keep_orientation);
+            }
             info!("Conversion completed successfully: {}", output_path.display());
-            // Return the actual output path so the frontend can use it
-            Ok(output_path.to_string_lossy().to_string())
+            // Return the actual output path (and stats) so the frontend can use it
+            let mut stats = conversion_stats(&input_path, output_path.to_string_lossy().to_string(), started, Some(run.command_line));
+            stats.tool_warnings = run.tool_warnings;
+            let mut stats = match tool {
+                Some(tool) => with_conversion_notes(stats, tool, &tool_input, &input_extension, &output_format_lower),
+                None => stats,
+            };
+            stats.warnings.splice(0..0, format_warning);
+            Ok(stats)
         }
         Err(e) => {
             error!("Conversion failed: {}", e);
             Err(e)
         }
+    };
+    
+    record_conversion(job_id, &input_path, &output_format, result.as_ref().map(|r| &r.output_path));
+    if let Ok(result) = &mut result {
+        record_throughput(result, &input_extension, &output_format_lower);
+        if settings.checksum || settings.write_checksum_file {
+            add_checksum(result, settings.write_checksum_file);
+        }
+        if settings.write_sidecar {
+            write_sidecar(result, &input_path, &settings);
+        }
     }
+    result
 }
 
-/// Convert multiple images into a single multipage PDF
+/// Convert many images to one format on a pool of worker threads, one per CPU core.
+/// Small images spend most of their time starting the tool, so running several
+/// conversions at once is much faster than one after another.
 #[tauri::command]
-async fn convert_images_to_multipage_pdf(
+async fn convert_image_batch(
+    app: AppHandle,
     input_paths: Vec<String>,
+    output_format: String,
     output_directory: Option<String>,
-) -> Result<String, String> {
-    info!("Starting multipage PDF conversion with {} images", input_paths.len());
-    
-    if input_paths.is_empty() {
-        return Err("No input files provided".to_string());
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<BatchConversionResult, String> {
+    let output_directories = vec![output_directory; input_paths.len()];
+    run_batch(app, input_paths, output_format, output_directories, advanced_options, settings, None, true).await
+}
+
+/// Convert files of any kind to one format on a bounded pool of workers, instead of the
+/// frontend calling convert_file for each file in turn. `workers` overrides the default
+/// of one per CPU core, e.g. fewer for large videos that already use every core.
+#[tauri::command]
+async fn convert_batch(
+    app: AppHandle,
+    input_paths: Vec<String>,
+    output_format: String,
+    output_directory: Option<String>,
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+    workers: Option<usize>,
+) -> Result<BatchConversionResult, String> {
+    let output_directories = vec![output_directory; input_paths.len()];
+    run_batch(app, input_paths, output_format, output_directories, advanced_options, settings, workers, false).await
+}
+
+/// Convert the files in a folder to one format. `extensions` limits which files are picked
+/// (by default every file that can be converted to the target), `recursive` includes
+/// subfolders, and `mirror_structure` recreates the subfolders under `output_directory`
+/// instead of putting every output in one folder.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn convert_directory(
+    app: AppHandle,
+    folder_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    recursive: bool,
+    extensions: Option<Vec<String>>,
+    mirror_structure: bool,
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+    workers: Option<usize>,
+) -> Result<BatchConversionResult, String> {
+    let folder = PathBuf::from(&folder_path);
+    if !folder.is_dir() {
+        return Err(format!("{} is not a folder", folder_path));
     }
-    
-    // Convert string paths to PathBuf
-    let input_paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
-    
-    // Verify all input files exist
-    for path in &input_paths {
-        if !path.exists() {
-            return Err(format!("Input file not found: {}", path.display()));
-        }
+    let output_format = output_format.to_lowercase();
+    let extensions = extensions.unwrap_or_default();
+    let output_root = output_directory.as_ref().map(PathBuf::from);
+    
+    let files: Vec<PathBuf> = directory_files(&folder, recursive)
+        .into_iter()
+        .filter(|file| conversion::matches_extension_filter(file, &extensions, &output_format))
+        // Earlier outputs in an output folder inside the source folder aren't sources
+        .filter(|file| match &output_root {
+            Some(root) => root == &folder || !file.starts_with(root),
+            None => true,
+        })
+        .collect();
+    if files.is_empty() {
+        return Err(format!("No files to convert to {} in {}", output_format.to_uppercase(), folder_path));
     }
+    info!("Converting {} files from {} (recursive: {})", files.len(), folder.display(), recursive);
     
-    // Determine output directory - use the directory of the first file if not specified
-    let output_dir = if let Some(dir) = output_directory {
-        PathBuf::from(dir)
-    } else {
-        input_paths[0]
-            .parent()
-            .ok_or("Could not determine output directory")?
-            .to_path_buf()
+    let output_directories = files.iter()
+        .map(|file| match &output_root {
+            Some(root) if mirror_structure => {
+                Some(conversion::mirrored_output_dir(&folder, file, root).to_string_lossy().to_string())
+            }
+            _ => output_directory.clone(),
+        })
+        .collect();
+    let input_paths = files.iter().map(|file| file.to_string_lossy().to_string()).collect();
+    run_batch(app, input_paths, output_format, output_directories, advanced_options, settings, workers, false).await
+}
+
+/// Convert every image inside a .zip, keeping its folders. With `repackage` the results are
+/// packed into a new .zip in `output_directory` (by default next to the archive); otherwise
+/// they're written to a folder there named after the archive.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn convert_zip(
+    app: AppHandle,
+    zip_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    repackage: bool,
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+    workers: Option<usize>,
+) -> Result<ArchiveConversionResult, String> {
+    let zip_path = PathBuf::from(&zip_path);
+    let output_format = output_format.to_lowercase();
+    let zip_stem = zip_path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("Invalid archive name")?
+        .to_string();
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => zip_path.parent()
+            .ok_or("Could not determine archive directory")?
+            .to_path_buf(),
     };
     
-    // Generate output filename based on first file name
-    let first_file_stem = input_paths[0]
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("combined");
-    
-    // Get a unique output path
-    let output_path = get_unique_output_path(&output_dir, &format!("{}_multipage", first_file_stem), "pdf");
-    
-    // Get ImageMagick path
-    let tool_path = get_tool_path("imagemagick")
-        .map_err(|e| format!("ImageMagick is required for multipage PDF creation: {}", e))?;
+    let job_dir = JobDir::create()?;
+    let input_root = job_dir.path.join("input");
+    let (images, skipped) = {
+        let (app, zip_path, input_root) = (app.clone(), zip_path.clone(), input_root.clone());
+        tauri::async_runtime::spawn_blocking(move || extract_archive_images(&app, &zip_path, &input_root))
+            .await
+            .map_err(|e| format!("Failed to extract archive: {}", e))??
+    };
+    if images.is_empty() {
+        return Err(format!("No images found in {}", zip_path.display()));
+    }
+    info!("Converting {} images from {} ({} other files skipped)", images.len(), zip_path.display(), skipped.len());
     
-    // Build ImageMagick command: magick input1.jpg input2.png ... output.pdf
-    let mut command = create_command(&tool_path);
+    let results_root = if repackage { job_dir.path.join("output") } else { output_dir.join(&zip_stem) };
+    let output_directories = images.iter()
+        .map(|image| Some(conversion::mirrored_output_dir(&input_root, image, &results_root).to_string_lossy().to_string()))
+        .collect();
+    let input_paths = images.iter().map(|image| image.to_string_lossy().to_string()).collect();
+    let mut batch = run_batch(app.clone(), input_paths, output_format.clone(), output_directories, advanced_options, settings, workers, true).await?;
+    
+    if !repackage {
+        return Ok(ArchiveConversionResult {
+            batch,
+            archive_path: None,
+            output_directory: Some(results_root.to_string_lossy().to_string()),
+            skipped,
+        });
+    }
     
-    // On macOS, set environment variables for ImageMagick to find its bundled libraries
-    #[cfg(target_os = "macos")]
+    let reservation = reserve_output_path(&output_dir, &format!("{}_{}", zip_stem, output_format), "zip");
+    let outputs: Vec<PathBuf> = batch.items.iter()
+        .filter_map(|item| item.result.as_ref())
+        .map(|result| PathBuf::from(&result.output_path))
+        .collect();
     {
-        // tool_path is: ~/Library/Application Support/com.convertsave/imagemagick/bin/magick
-        if let Some(bin_dir) = tool_path.parent() {
-            if let Some(imagemagick_dir) = bin_dir.parent() {
-                let lib_dir = imagemagick_dir.join("lib");
-                let etc_dir = imagemagick_dir.join("etc").join("ImageMagick-7");
-                
-                info!("Setting DYLD_LIBRARY_PATH: {}", lib_dir.display());
-                info!("Setting MAGICK_HOME: {}", imagemagick_dir.display());
-                
-                command.env("DYLD_LIBRARY_PATH", &lib_dir);
-                command.env("MAGICK_HOME", &imagemagick_dir);
-                
-                // Set configuration path if it exists
-                if etc_dir.exists() {
-                    info!("Setting MAGICK_CONFIGURE_PATH: {}", etc_dir.display());
-                    command.env("MAGICK_CONFIGURE_PATH", &etc_dir);
-                } else {
-                    warn!("Configuration directory not found: {}", etc_dir.display());
-                }
-                
-                // Set module path for builds with --with-modules enabled
-                if let Ok(entries) = std::fs::read_dir(&lib_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.is_dir() && path.file_name()
-                            .and_then(|n| n.to_str())
-                            .map(|n| n.starts_with("ImageMagick-"))
-                            .unwrap_or(false) {
-                            let modules_coders = path.join("modules-Q16HDRI").join("coders");
-                            if modules_coders.exists() {
-                                info!("Setting MAGICK_CODER_MODULE_PATH: {}", modules_coders.display());
-                                command.env("MAGICK_CODER_MODULE_PATH", &modules_coders);
-                                break;
-                            }
-                        }
-                    }
-                }
+        let (app, archive_path, results_root) = (app.clone(), reservation.path.clone(), results_root.clone());
+        tauri::async_runtime::spawn_blocking(move || pack_archive(&app, &archive_path, &results_root, &outputs))
+            .await
+            .map_err(|e| format!("Failed to pack archive: {}", e))??;
+    }
+    
+    // The files behind the output paths are deleted with the job directory
+    for result in batch.items.iter_mut().filter_map(|item| item.result.as_mut()) {
+        if let Ok(relative) = PathBuf::from(&result.output_path).strip_prefix(&results_root) {
+            result.output_path = archive::archive_entry_name(relative);
+        }
+    }
+    info!("Packed converted images into {}", reservation.path.display());
+    Ok(ArchiveConversionResult {
+        batch,
+        archive_path: Some(reservation.path.to_string_lossy().to_string()),
+        output_directory: None,
+        skipped,
+    })
+}
+
+/// Extract the images in a user's .zip under `input_root`, keeping their folders. Returns the
+/// extracted images and the names of entries that were left out.
+fn extract_archive_images(app: &AppHandle, zip_path: &PathBuf, input_root: &PathBuf) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+    let file = std::fs::File::open(zip_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Not a valid zip archive: {}", e))?;
+    
+    let total = zip.len();
+    let mut images = Vec::new();
+    let mut skipped = Vec::new();
+    let mut last_percent = None;
+    for index in 0..total {
+        let mut entry = zip.by_index(index)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        let name = entry.name().to_string();
+        if entry.is_dir() || archive::is_junk_entry(&name) {
+            continue;
+        }
+        
+        let ext = std::path::Path::new(&name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let destination = match archive::entry_destination(&name, None) {
+            Some(relative) if conversion::is_image_format(&ext) => input_root.join(relative),
+            _ => {
+                skipped.push(name);
+                continue;
             }
+        };
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        let mut output = std::fs::File::create(&destination)
+            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+        std::io::copy(&mut entry, &mut output)
+            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+        images.push(destination);
+        
+        let percent = archive::percent_done(index + 1, total);
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            app.emit("archive-progress", ArchiveProgress { stage: "extracting".to_string(), percent }).ok();
         }
     }
+    Ok((images, skipped))
+}
+
+/// Pack `files` into a new .zip at `archive_path`, named by their path under `root`.
+/// A partly written archive is removed on failure.
+fn pack_archive(app: &AppHandle, archive_path: &PathBuf, root: &PathBuf, files: &[PathBuf]) -> Result<(), String> {
+    let write = || -> Result<(), String> {
+        let file = std::fs::File::create(archive_path)
+            .map_err(|e| format!("Failed to create archive: {}", e))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::FileOptions::default();
+        
+        let mut last_percent = None;
+        for (done, path) in files.iter().enumerate() {
+            let relative = path.strip_prefix(root)
+                .map_err(|_| format!("{} is outside the results folder", path.display()))?;
+            zip.start_file(archive::archive_entry_name(relative), options)
+                .map_err(|e| format!("Failed to add {} to archive: {}", path.display(), e))?;
+            let mut input = std::fs::File::open(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            std::io::copy(&mut input, &mut zip)
+                .map_err(|e| format!("Failed to add {} to archive: {}", path.display(), e))?;
+            
+            let percent = archive::percent_done(done + 1, files.len());
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                app.emit("archive-progress", ArchiveProgress { stage: "packing".to_string(), percent }).ok();
+            }
+        }
+        zip.finish().map_err(|e| format!("Failed to finish archive: {}", e))?;
+        Ok(())
+    };
     
-    // Add all input files
-    for input_path in &input_paths {
-        command.arg(input_path);
+    let result = write();
+    if result.is_err() {
+        let _ = std::fs::remove_file(archive_path);
+    }
+    result
+}
+
+/// The files directly in `folder`, or in all its subfolders too, skipping hidden ones
+fn directory_files(folder: &PathBuf, recursive: bool) -> Vec<PathBuf> {
+    if recursive {
+        return migration::collect_files(folder);
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(folder)
+        .map(|entries| {
+            entries.flatten()
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Shared by the batch commands; `output_directories` holds one entry per input and
+/// `images_only` rejects files that aren't images
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    app: AppHandle,
+    input_paths: Vec<String>,
+    output_format: String,
+    output_directories: Vec<Option<String>>,
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+    workers: Option<usize>,
+    images_only: bool,
+) -> Result<BatchConversionResult, String> {
+    if input_paths.is_empty() {
+        return Err("No input files provided".to_string());
     }
     
-    // Add PDF-specific options for good quality output
-    command.arg("-compress").arg("jpeg");  // Use JPEG compression for images
-    command.arg("-quality").arg("85");     // Good quality/size balance
-    command.arg("-density").arg("300");    // 300 DPI for print quality
-    
-    // Add output path
-    command.arg(&output_path);
-    
-    info!("Executing ImageMagick multipage PDF command...");
-    
-    let output = command.output()
-        .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        error!("ImageMagick multipage PDF failed - stderr: {}", stderr);
-        error!("ImageMagick multipage PDF failed - stdout: {}", stdout);
-        return Err(format!("Failed to create multipage PDF: {}", stderr));
+    let started = std::time::Instant::now();
+    let settings = settings.unwrap_or_default();
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let requested_workers = workers;
+    let workers = conversion::requested_worker_count(workers, cpus, input_paths.len());
+    info!("Converting {} files to {} on {} workers", input_paths.len(), output_format, workers);
+    
+    // Journaled so the rest of the batch can be resumed if the app crashes or is closed
+    let now = chrono::Utc::now();
+    let batch_id = format!("{}-{}", now.format("%Y%m%d%H%M%S%3f"), NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed));
+    let mut batch = QueuedBatch {
+        id: batch_id.clone(),
+        started_at: now.to_rfc3339(),
+        pid: std::process::id(),
+        output_format: output_format.clone(),
+        advanced_options: advanced_options.clone(),
+        settings: ConversionSettings::default(),
+        needs_pdf_password: false,
+        workers: requested_workers,
+        images_only,
+        jobs: input_paths.iter().zip(&output_directories)
+            .map(|(input_path, output_directory)| QueuedJob { input_path: input_path.clone(), output_directory: output_directory.clone(), done: false })
+            .collect(),
+    };
+    batch.settings_to_journal(&settings);
+    if let Err(e) = update_queue_journal(|journal| journal.batches.push(batch)) {
+        warn!("Could not journal the batch, it can't be resumed after a crash: {}", e);
     }
+    let journal_id = batch_id.clone();
+    let mark_done = move |index: usize| {
+        if let Err(e) = update_queue_journal(|journal| journal.mark_done(&journal_id, index)) {
+            warn!("Could not journal batch progress: {}", e);
+        }
+    };
     
-    info!("Multipage PDF created successfully: {}", output_path.display());
-    Ok(output_path.to_string_lossy().to_string())
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        // Every worker looks up the same tool, so resolve it once for the whole batch
+        let _tool_paths = ToolPathCache::enable();
+        let total = input_paths.len();
+        let completed = AtomicUsize::new(0);
+        let report = |item: &BatchItemResult| {
+            let _ = app.emit("batch-progress", BatchProgress {
+                completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                total,
+                input_path: item.input_path.clone(),
+                success: item.error.is_none(),
+            });
+        };
+        let convert_one = |index: usize| {
+            let item = convert_batch_item(&input_paths[index], index, &output_format, &output_directories[index], &advanced_options, &settings, images_only);
+            report(&item);
+            mark_done(index);
+            item
+        };
+        
+        // Thousands of small images convert far faster with a few mogrify calls than a process each
+        let shared_output = output_directories.iter().all(|dir| dir == &output_directories[0]);
+        let mogrify_dir = shared_output
+            .then(|| mogrify_output_dir(&input_paths, &output_format, &output_directories[0], &settings))
+            .flatten();
+        if let Some(output_dir) = mogrify_dir {
+            let chunk_size = conversion::mogrify_chunk_size(total, workers);
+            let chunks: Vec<Vec<usize>> = (0..total).collect::<Vec<_>>()
+                .chunks(chunk_size)
+                .map(|chunk| chunk.to_vec())
+                .collect();
+            info!("Converting with mogrify in {} calls of up to {} files", chunks.len(), chunk_size);
+            
+            let mut items: Vec<Option<BatchItemResult>> = vec![None; total];
+            let chunk_results = run_worker_pool(chunks.len(), workers, |chunk| {
+                let indexes = &chunks[chunk];
+                match run_mogrify(&input_paths, indexes, &output_dir, &output_format, advanced_options.as_deref(), &settings) {
+                    Ok(converted) => {
+                        converted.iter().for_each(|item| report(item));
+                        indexes.iter().for_each(|&index| mark_done(index));
+                        indexes.iter().copied().zip(converted).collect::<Vec<_>>()
+                    }
+                    Err(e) => {
+                        // Convert the chunk one file at a time to find out which input failed
+                        warn!("mogrify failed, converting {} files one by one: {}", indexes.len(), e);
+                        indexes.iter().map(|&index| (index, convert_one(index))).collect()
+                    }
+                }
+            });
+            for (index, item) in chunk_results.into_iter().flatten() {
+                items[index] = Some(item);
+            }
+            return items.into_iter().flatten().collect::<Vec<_>>();
+        }
+        
+        run_worker_pool(total, workers, convert_one)
+    })
+    .await;
+    if let Err(e) = update_queue_journal(|journal| journal.remove(&batch_id)) {
+        warn!("Could not remove the finished batch from the queue: {}", e);
+    }
+    let items = items.map_err(|e| format!("Batch conversion failed: {}", e))?;
+    
+    let failed = items.iter().filter(|item| item.error.is_some()).count();
+    info!("Batch finished: {} converted, {} failed", items.len() - failed, failed);
+    Ok(BatchConversionResult {
+        items,
+        workers,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
 }
 
-#[tauri::command]
-async fn get_file_info(path: String) -> Result<serde_json::Value, String> {
-    let path = PathBuf::from(&path);
-    let metadata = std::fs::metadata(&path)
-        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+/// Run `job(0..jobs)` on `workers` threads, returning the results in job order
+fn run_worker_pool<T, F>(jobs: usize, workers: usize, job: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, T)>> = Mutex::new(Vec::with_capacity(jobs));
+    
+    std::thread::scope(|scope| {
+        for _ in 0..workers.min(jobs) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= jobs {
+                    break;
+                }
+                let result = job(index);
+                if let Ok(mut results) = results.lock() {
+                    results.push((index, result));
+                }
+            });
+        }
+    });
     
-    let file_name = path.file_name()
-        .ok_or("Could not get file name")?
-        .to_str()
-        .ok_or("Invalid file name")?;
+    let mut results = results.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The folder a batch can be mogrified into, if it qualifies: the inputs allow it, none is
+/// still being written (converting one at a time waits for those), they all go to one
+/// folder, and every output name is free (mogrify overwrites without asking)
+fn mogrify_output_dir(
+    input_paths: &[String],
+    output_format: &str,
+    output_directory: &Option<String>,
+    settings: &ConversionSettings,
+) -> Option<PathBuf> {
+    let output_format = output_format.to_lowercase();
+    if !conversion::can_mogrify_batch(input_paths, &output_format, settings) {
+        return None;
+    }
+    let waits = settings.input_wait_secs.unwrap_or(input_state::DEFAULT_INPUT_WAIT_SECS) > 0;
+    if waits && !input_paths.iter().all(|path| input_state(&PathBuf::from(path)) == InputState::Ready) {
+        info!("Some inputs are still being written, converting them one at a time");
+        return None;
+    }
     
-    let extension = path.extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let parent = PathBuf::from(&input_paths[0]).parent()?.to_path_buf();
+            let shared = input_paths.iter().all(|path| PathBuf::from(path).parent() == Some(parent.as_path()));
+            if !shared {
+                return None;
+            }
+            parent
+        }
+    };
     
-    let file_info = serde_json::json!({
-        "name": file_name,
-        "size": metadata.len(),
-        "extension": extension
+    // The saved default naming template applies to batches too
+    let reserved = RESERVED_OUTPUTS.lock().ok()?;
+    let all_free = input_paths.iter().all(|path| {
+        let input_path = PathBuf::from(path);
+        let stem = input_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+        let named_as_input = render_output_name(&input_path, stem, &output_format, settings).ok().as_deref() == Some(stem);
+        let output_path = output_dir.join(format!("{}.{}", stem, output_format));
+        named_as_input && !output_path.exists() && !reserved.contains(&output_path)
     });
-    
-    Ok(file_info)
+    all_free.then_some(output_dir)
 }
 
-#[tauri::command]
-async fn test_directories() -> Result<serde_json::Value, String> {
-    let mut info = serde_json::Map::new();
-    
-    if let Some(docs) = dirs::document_dir() {
-        info.insert("documents".to_string(), serde_json::Value::String(docs.to_string_lossy().to_string()));
-    } else {
-        info.insert("documents".to_string(), serde_json::Value::Null);
+/// Convert some of a batch's files with one `magick mogrify` call. Fails as a whole,
+/// leaving no partial outputs behind, so the caller can retry the files one by one.
+fn run_mogrify(
+    input_paths: &[String],
+    indexes: &[usize],
+    output_dir: &PathBuf,
+    output_format: &str,
+    advanced_options: Option<&str>,
+    settings: &ConversionSettings,
+) -> Result<Vec<BatchItemResult>, String> {
+    // One mogrify call takes one slot, like the conversion of a single file
+    let _slot = ConversionSlot::acquire(settings.priority);
+    let started = std::time::Instant::now();
+    let output_format = output_format.to_lowercase();
+    let files: Vec<String> = indexes.iter().map(|&index| input_paths[index].clone()).collect();
+    let reservations: Vec<OutputReservation> = files.iter()
+        .map(|path| {
+            let stem = PathBuf::from(path).file_stem().and_then(|stem| stem.to_str()).unwrap_or("").to_string();
+            reserve_output_path(output_dir, &stem, &output_format)
+        })
+        .collect();
+    // mogrify names outputs after the inputs, so a name taken since planning can't be worked around
+    let renamed = files.iter().zip(&reservations).any(|(path, reservation)| {
+        PathBuf::from(path).file_stem() != reservation.path.file_stem()
+    });
+    if renamed {
+        return Err("An output name was taken while the batch was running".to_string());
     }
     
-    if let Some(home) = dirs::home_dir() {
-        info.insert("home".to_string(), serde_json::Value::String(home.to_string_lossy().to_string()));
-    } else {
-        info.insert("home".to_string(), serde_json::Value::Null);
+    let tool_path = get_tool_path("imagemagick")?;
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    if settings.color.convert_to_srgb {
+        icc::ensure_srgb_profile()?;
     }
+    let mut command = create_command(&tool_path);
+    set_imagemagick_env(&mut command, &tool_path);
+    set_deterministic_env(&mut command, settings);
+    command.args(conversion::mogrify_args(&output_dir.to_string_lossy(), &output_format, settings, advanced_options, &files));
     
-    if let Ok(current) = std::env::current_dir() {
-        info.insert("current".to_string(), serde_json::Value::String(current.to_string_lossy().to_string()));
-    } else {
-        info.insert("current".to_string(), serde_json::Value::Null);
+    debug!("Executing command: {:?}", command);
+    let output = output_with_limits(&mut command, "imagemagick", output_dir, settings);
+    let written = reservations.iter().all(|reservation| reservation.path.exists());
+    match output {
+        Ok(output) if output.status.success() && written => {}
+        result => {
+            for reservation in &reservations {
+                let _ = std::fs::remove_file(&reservation.path);
+            }
+            return Err(match result {
+                Ok(output) => format!("mogrify exited with {:?}: {}", output.status, String::from_utf8_lossy(&output.stderr)),
+                Err(e) => e,
+            });
+        }
     }
     
-    Ok(serde_json::Value::Object(info))
+    let command_line = command_line(&command);
+    let items = files.iter().zip(&reservations)
+        .map(|(input, reservation)| {
+            let input_path = PathBuf::from(input);
+            let output_path = reservation.path.to_string_lossy().to_string();
+            record_conversion(None, &input_path, &output_format, Ok(&output_path));
+            BatchItemResult {
+                input_path: input.clone(),
+                result: Some(
+                    conversion_stats(&input_path, output_path, started, Some(command_line.clone()))
+                        .with_notes("imagemagick", Vec::new(), Vec::new())
+                ),
+                error: None,
+                error_summary: None,
+            }
+        })
+        .collect();
+    Ok(items)
 }
 
-#[tauri::command]
-async fn open_folder(path: String) -> Result<(), String> {
-    let path = PathBuf::from(path);
+/// Convert one file of a batch (runs on a worker thread)
+fn convert_batch_item(
+    input_path: &str,
+    index: usize,
+    output_format: &str,
+    output_directory: &Option<String>,
+    advanced_options: &Option<String>,
+    settings: &ConversionSettings,
+    images_only: bool,
+) -> BatchItemResult {
+    // By content, so camera files without an extension still count as images
+    let input_ext = check_input_format(&PathBuf::from(input_path), None).format;
+    
+    let result = if !images_only || conversion::is_image_format(&input_ext) {
+        let settings = ConversionSettings {
+            name_counter: Some(index as u32 + 1),
+            ..settings.clone()
+        };
+        tauri::async_runtime::block_on(convert_file(
+            input_path.to_string(),
+            output_format.to_string(),
+            output_directory.clone(),
+            advanced_options.clone(),
+            Some(settings),
+            None,
+            None,
+        ))
+    } else {
+        Err(format!("{} is not an image", input_path))
+    };
     
-    #[cfg(target_os = "windows")]
-    {
-        // On Windows, if it's a file, use /select, to open Explorer and highlight the file
-        // If it's a folder, just open the folder normally
-        if path.is_file() {
-            create_command("explorer")
-                .arg("/select,")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        } else {
-            create_command("explorer")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        }
+    match result {
+        Ok(result) => BatchItemResult { input_path: input_path.to_string(), result: Some(result), error: None, error_summary: None },
+        Err(e) => BatchItemResult {
+            input_path: input_path.to_string(),
+            result: None,
+            error_summary: Some(error_summary::summarize(&e)),
+            error: Some(e),
+        },
     }
-    
-    #[cfg(target_os = "macos")]
-    {
-        // On macOS, use -R flag to reveal the file in Finder
-        if path.is_file() {
-            create_command("open")
-                .arg("-R")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
-        } else {
-            create_command("open")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to open folder: {}", e))?;
+}
+
+/// Convert one file to several formats, e.g. MP4 + WebM + a poster JPG for a web page.
+/// Video (and audio to audio) conversions share one FFmpeg pass, so the input is decoded
+/// once; anything else runs as separate conversions side by side.
+#[tauri::command]
+async fn convert_to_formats(
+    input_path: String,
+    output_formats: Vec<String>,
+    output_directory: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<Vec<FormatItemResult>, String> {
+    let mut formats: Vec<String> = Vec::new();
+    for format in output_formats.iter().map(|format| format.to_lowercase()) {
+        if !formats.contains(&format) {
+            formats.push(format);
         }
     }
+    if formats.is_empty() {
+        return Err("Choose at least one output format".to_string());
+    }
+    let settings = settings.unwrap_or_default();
+    let input = PathBuf::from(&input_path);
+    let input_ext = check_input_format(&input, None).format;
     
-    #[cfg(target_os = "linux")]
-    {
-        // On Linux, most file managers don't have a standard "reveal" option
-        // So we open the parent folder
-        let folder = if path.is_file() {
-            path.parent().ok_or("Could not find parent folder")?
-        } else {
-            &path
-        };
-        
-        create_command("xdg-open")
-            .arg(folder)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    if conversion::single_pass_outputs(&input_ext, &formats, &settings) && get_tool_path("ffmpeg").is_ok() {
+        return convert_in_shared_pass(&input, &formats, output_directory, &settings);
     }
     
-    Ok(())
+    let items = std::thread::scope(|scope| {
+        let workers: Vec<_> = formats.iter()
+            .map(|format| {
+                let (input_path, output_directory, settings) = (&input_path, &output_directory, &settings);
+                scope.spawn(move || {
+                    tauri::async_runtime::block_on(convert_file(
+                        input_path.clone(),
+                        format.clone(),
+                        output_directory.clone(),
+                        None,
+                        Some(settings.clone()),
+                        None,
+                        None,
+                    ))
+                })
+            })
+            .collect();
+        workers.into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|_| Err("The conversion stopped unexpectedly".to_string())))
+            .collect::<Vec<_>>()
+    });
+    Ok(formats.into_iter().zip(items)
+        .map(|(format, result)| match result {
+            Ok(result) => FormatItemResult { format, result: Some(result), error: None, error_summary: None },
+            Err(e) => FormatItemResult { format, result: None, error_summary: Some(error_summary::summarize(&e)), error: Some(e) },
+        })
+        .collect())
 }
 
-fn determine_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'static str> {
-    // JPG <-> JPEG simple rename (no conversion needed, same format)
-    if (input_ext == "jpg" && output_ext == "jpeg") || (input_ext == "jpeg" && output_ext == "jpg") {
-        return Some("rename");
+/// One FFmpeg run writing every format of convert_to_formats. If it fails, no output is kept.
+fn convert_in_shared_pass(
+    input: &PathBuf,
+    formats: &[String],
+    output_directory: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<Vec<FormatItemResult>, String> {
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input.display()));
     }
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).ok_or("Invalid file name")?;
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine input file directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    conversion::validate_audio_edits(settings)?;
+    wait_for_input(input, settings.input_wait_secs.unwrap_or(input_state::DEFAULT_INPUT_WAIT_SECS))?;
+    
+    let _slot = ConversionSlot::acquire(settings.priority);
+    let started = std::time::Instant::now();
+    let mut reservations = Vec::new();
+    for format in formats {
+        let stem = render_output_name(input, file_stem, format, settings)?;
+        reservations.push(reserve_output_path(&output_dir, &stem, format));
+    }
+    let outputs: Vec<(String, String)> = formats.iter().zip(&reservations)
+        .map(|(format, reservation)| (format.clone(), reservation.path.to_string_lossy().to_string()))
+        .collect();
     
-    // Image conversions - ImageMagick supports the widest range of formats
-    // FFmpeg is used as fallback for some formats
-    let image_inputs = [
-        // Standard/Common formats
-        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
-        // Modern formats
-        "heic", "heif", "avif", "jxl",
-        // Professional/High-end formats
-        "tga", "exr", "hdr", "dpx", "pfm", "psd", "psb",
-        // JPEG variants
-        "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
-        // Legacy/Specialized formats
-        "pcx", "ico", "sgi", "sun", "ras", "pict", "pct",
-        // Raw/Uncompressed formats
-        "ppm", "pgm", "pbm", "pam", "pnm", "pfm",
-        // X Window System formats
-        "xbm", "xpm", "xwd",
-        // Gaming/3D formats
-        "dds", "tga", "vtf",
-        // Vector/Document formats (rasterized)
-        "svg", "svgz", "ai", "eps", "ps", "pdf",
-        // Digital camera RAW formats
-        "arw", "cr2", "cr3", "crw", "dng", "nef", "nrw", "orf", "raf", "raw", "rw2", "rwl", "srw",
-        // Animation formats
-        "mng", "apng",
-        // Windows formats
-        "cur", "dib", "emf", "wmf",
-        // Adobe formats
-        "psd", "psb", "ai",
-        // Other formats
-        "fits", "flif", "jbig", "jng", "miff", "otb", "pal", "palm", "pam", "pcd", "pict", 
-        "pix", "plasma", "pnm", "pwp", "rgf", "sfw", "sgi", "sun", "tga", "uyvy", "vicar", 
-        "viff", "wbmp", "xbm", "xcf", "xpm", "xv", "yuv"
-    ];
-    let image_outputs_ffmpeg = [
-        // Standard formats
-        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
-        // Modern formats (AVIF only, HEIC/HEIF use ImageMagick)
-        "avif",
-        // Professional/High-end formats
-        "tga", "exr", "hdr", "dpx", "pfm",
-        // JPEG 2000
-        "j2k", "jp2",
-        // Legacy/Specialized formats
-        "pcx", "ico", "sgi", "sun",
-        // Raw/Uncompressed formats
-        "ppm", "pgm", "pbm", "pam",
-        // Gaming/3D formats
-        "dds"
-        // Note: X Window System formats (xbm, xpm, xwd) require ImageMagick
-    ];
-    let image_outputs_imagemagick = [
-        // Standard/Common formats
-        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
-        // Modern formats - ImageMagick supports HEIC/HEIF encoding
-        "heic", "heif", "avif", "jxl",
-        // Professional/High-end formats
-        "tga", "exr", "hdr", "dpx", "pfm", "psd", "psb",
-        // JPEG variants
-        "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
-        // Legacy/Specialized formats
-        "pcx", "ico", "sgi", "sun", "ras", "pict", "pct",
-        // Raw/Uncompressed formats
-        "ppm", "pgm", "pbm", "pam", "pnm",
-        // X Window System formats
-        "xbm", "xpm", "xwd",
-        // Gaming/3D formats
-        "dds", "tga", "vtf",
-        // Vector/Document formats (rasterized)
-        "svg", "svgz", "pdf",
-        // Animation formats
-        "mng", "apng",
-        // Windows formats
-        "cur", "dib", "emf", "wmf",
-        // Adobe formats
-        "psd", "psb",
-        // Other formats
-        "fits", "jbig", "jng", "miff", "otb", "pal", "palm", "pcd", "pict", 
-        "pix", "plasma", "sfw", "wbmp", "xcf", "xv", "yuv"
-    ];
-    
-    // Video/Audio conversions
-    let video_inputs = ["mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v", "mpg", "mpeg", "3gp"];
-    let audio_inputs = ["mp3", "wav", "flac", "ogg", "m4a", "wma", "aac"];
-    let av_outputs = ["mp4", "mov", "avi", "mkv", "webm", "mp3", "wav", "flac", "ogg", "m4a", "aac", "gif"];
-    
-    // Document conversions
-    // Note: PDF output requires LaTeX (not included), so it's removed from Pandoc outputs
-    let doc_inputs = ["md", "markdown", "txt", "html", "htm", "docx", "odt", "rtf", "tex", "latex", "epub", "rst"];
-    let doc_outputs = ["md", "html", "docx", "odt", "rtf", "tex", "epub", "txt"];
-    
-    let office_inputs = ["doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "rtf"];
-    let office_outputs = ["pdf", "html", "txt", "docx", "odt", "rtf"];
+    let ffmpeg_path = get_tool_path("ffmpeg")?;
+    let mut command = create_command(&ffmpeg_path);
+    command.args(conversion::ffmpeg_log_args(&ffmpeg_log_level()));
+    command.arg("-y").arg("-i").arg(input);
+    command.args(conversion::shared_pass_args(&outputs, &ffmpeg_thread_args(settings), settings)?);
+    let (command, ffmpeg_path) = ffmpeg_with_encoders(command, ffmpeg_path)?;
+    
+    info!("Converting {} to {} in one pass", input.display(), formats.join(", "));
+    // Progress and the job log of a failure go under the first output
+    let run = run_conversion_command(command, "ffmpeg", "ffmpeg", &ffmpeg_path, input, &reservations[0].path, settings);
+    let run = match run {
+        Ok(run) => run,
+        Err(e) => {
+            for reservation in &reservations {
+                let _ = std::fs::remove_file(&reservation.path);
+            }
+            return Err(e);
+        }
+    };
     
-    // Use ffmpeg for media and image conversions
-    if (video_inputs.contains(&input_ext) || audio_inputs.contains(&input_ext)) && av_outputs.contains(&output_ext) {
-        Some("ffmpeg")
-    } else if image_inputs.contains(&input_ext) && (output_ext == "heic" || output_ext == "heif") {
-        // HEIC/HEIF encoding requires ImageMagick
-        Some("imagemagick")
-    } else if image_inputs.contains(&input_ext) && (output_ext == "xbm" || output_ext == "xpm" || output_ext == "xwd") {
-        // X Window System formats require ImageMagick (FFmpeg doesn't support them properly)
-        Some("imagemagick")
-    } else if image_inputs.contains(&input_ext) && image_outputs_imagemagick.contains(&output_ext) {
-        // Try ImageMagick first for image conversions, but will fallback to FFmpeg if not available
-        Some("imagemagick")
-    } else if image_inputs.contains(&input_ext) && image_outputs_ffmpeg.contains(&output_ext) {
-        // Fallback to ffmpeg for formats ImageMagick doesn't support well
-        Some("ffmpeg")
-    } else if ENABLE_PANDOC && doc_inputs.contains(&input_ext) && doc_outputs.contains(&output_ext) {
-        Some("pandoc")
-    } else if office_inputs.contains(&input_ext) && office_outputs.contains(&output_ext) {
-        Some("libreoffice")
+    // The warnings are about the shared pass, so every output gets them
+    let ToolRun { command_line, tool_warnings } = run;
+    let items = formats.iter().zip(&outputs)
+        .map(|(format, (_, output_path))| {
+            record_conversion(None, input, format, Ok(output_path));
+            let mut result = conversion_stats(input, output_path.clone(), started, Some(command_line.clone()))
+                .with_notes("ffmpeg", Vec::new(), Vec::new());
+            result.tool_warnings = tool_warnings.clone();
+            if settings.checksum || settings.write_checksum_file {
+                add_checksum(&mut result, settings.write_checksum_file);
+            }
+            if settings.write_sidecar {
+                write_sidecar(&result, input, settings);
+            }
+            FormatItemResult { format: format.clone(), result: Some(result), error: None, error_summary: None }
+        })
+        .collect();
+    Ok(items)
+}
+
+/// A short, plain-language version of an error from any command, with the full text as the
+/// detail, so the UI can show one line (or a translation keyed by the kind) and expand it
+#[tauri::command]
+fn summarize_error(error: String) -> ErrorSummary {
+    error_summary::summarize(&error)
+}
+
+/// Explain why a conversion pair isn't offered (missing tool, disabled backend, license),
+/// so the UI can tell the user what to do instead of hiding the option
+#[tauri::command]
+async fn explain_unsupported(
+    cache: State<'_, license::LicenseCache>,
+    input_ext: String,
+    output_ext: String,
+) -> Result<UnsupportedExplanation, String> {
+    let (license_status, _) = cache.get(false).await;
+    let licensed = license_status.is_valid && !license_status.requires_activation;
+    let explanation = conversion::explain_unsupported(
+        &input_ext,
+        &output_ext,
+        |tool| get_tool_path(tool).is_ok(),
+        licensed,
+    );
+    debug!("{} -> {}: {:?}", input_ext, output_ext, explanation);
+    Ok(explanation)
+}
+
+/// Pick the tool for a conversion, honoring settings that change routing
+fn conversion_tool_for(input_ext: &str, output_ext: &str, settings: &ConversionSettings) -> Option<&'static str> {
+    // Vector output keeps vector-to-vector conversions in Inkscape instead of rasterizing
+    if settings.vector_output && conversion::supports_vector_conversion(input_ext, output_ext) {
+        Some("inkscape")
+    } else if conversion::prefers_svg_renderer(input_ext, output_ext) {
+        // ImageMagick's SVG delegate renders at 72 DPI and upscales, so SVGs are rendered in-process
+        Some("resvg")
     } else {
-        None
+        determine_conversion_tool(input_ext, output_ext)
     }
 }
 
-fn get_tool_path(tool_name: &str) -> Result<PathBuf, String> {
-    // Check for custom path first
-    if let Ok(mut config) = load_config() {
-        let custom_path = match tool_name {
-            "ffmpeg" => &config.ffmpeg_path,
-            "pandoc" => &config.pandoc_path,
-            "imagemagick" => &config.imagemagick_path,
-            _ => &None,
-        };
-        
-        if let Some(path_str) = custom_path {
-            let path = PathBuf::from(&path_str);
-            info!("Checking custom path for {}: {}", tool_name, path.display());
-            if path.exists() {
-                info!("Using custom path for {}: {}", tool_name, path.display());
-                return Ok(path);
-            } else {
-                warn!("Custom path for {} no longer exists: {}. Clearing from config.", tool_name, path.display());
-                // Clear the invalid custom path from config
-                match tool_name {
-                    "ffmpeg" => config.ffmpeg_path = None,
-                    "pandoc" => config.pandoc_path = None,
-                    "imagemagick" => config.imagemagick_path = None,
-                    _ => {}
-                }
-                // Save the updated config (ignore errors as this is cleanup)
-                let _ = save_config(&config);
+/// Temporary folder for document previews; a file there is replaced when the same
+/// document is previewed again
+fn document_preview_path(input_path: &PathBuf, pages: u32, extension: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("convertsave-preview-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+    let mut hasher = DefaultHasher::new();
+    input_path.hash(&mut hasher);
+    pages.hash(&mut hasher);
+    let stem = input_path.file_stem().and_then(|s| s.to_str()).unwrap_or("preview");
+    Ok(dir.join(format!("{}-{:x}.{}", stem, hasher.finish(), extension)))
+}
+
+/// Render just the first `pages` pages (LibreOffice, as PDF) or sections (Pandoc, as an
+/// HTML fragment) of what converting the document to `output_format` would produce, so
+/// the formatting can be checked before a long batch. PDF previews use the PDF export
+/// options from `settings`. Nothing is written next to the input.
+#[tauri::command]
+async fn preview_document_conversion(
+    input_path: String,
+    output_format: String,
+    pages: Option<u32>,
+    settings: Option<ConversionSettings>,
+) -> Result<DocumentPreview, String> {
+    let settings = settings.unwrap_or_default();
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let input_ext = check_input_format(&input, None).format;
+    let output_format = output_format.to_lowercase();
+    let pages = pages.unwrap_or(conversion::DEFAULT_PREVIEW_PAGES).clamp(1, conversion::MAX_PREVIEW_PAGES);
+    
+    match determine_conversion_tool(&input_ext, &output_format) {
+        Some("libreoffice") => {
+            let tool_path = get_tool_path("libreoffice").map_err(|_| LIBREOFFICE_NOT_FOUND.to_string())?;
+            settings.pdf_export.validate()?;
+            let mut job_dir = None;
+            let source = libreoffice_pdf_source(&input, &input_ext, &settings.spreadsheet_layout, &mut job_dir)?;
+            let preview_path = document_preview_path(&input, pages, "pdf")?;
+            let staging_dir = libreoffice_staging_dir(&preview_path);
+            let mut command = create_command(&tool_path);
+            command.args(conversion::libreoffice_args_with_target(
+                &source.to_string_lossy(),
+                &conversion::libreoffice_pdf_target(&input_ext, &settings.pdf_export, &settings.spreadsheet_layout, Some(pages)),
+                &staging_dir.to_string_lossy(),
+                &libreoffice_profile_dir().to_string_lossy(),
+            ));
+            run_libreoffice(command, &input, &preview_path, &settings)?;
+            
+            info!("Previewed first {} pages of {}", pages, input_path);
+            Ok(DocumentPreview {
+                preview_path: preview_path.to_string_lossy().to_string(),
+                preview_format: "pdf".to_string(),
+                tool: "libreoffice".to_string(),
+                pages,
+            })
+        }
+        Some("pandoc") => {
+            let tool_path = get_tool_path("pandoc")?;
+            // Without --standalone Pandoc writes a fragment, which is all a preview needs
+            let output = create_command(&tool_path)
+                .arg(&input)
+                .args(["-t", "html"])
+                .output()
+                .map_err(|e| format!("Failed to execute pandoc: {}", e))?;
+            if !output.status.success() {
+                return Err(format!("Pandoc could not convert this file: {}", String::from_utf8_lossy(&output.stderr).trim()));
             }
+            let html = String::from_utf8_lossy(&output.stdout);
+            let preview_path = document_preview_path(&input, pages, "html")?;
+            std::fs::write(&preview_path, conversion::html_preview_sections(&html, pages as usize))
+                .map_err(|e| format!("Failed to write preview: {}", e))?;
+            
+            info!("Previewed first {} sections of {}", pages, input_path);
+            Ok(DocumentPreview {
+                preview_path: preview_path.to_string_lossy().to_string(),
+                preview_format: "html".to_string(),
+                tool: "pandoc".to_string(),
+                pages,
+            })
         }
+        _ => Err(format!(
+            "Previews are only available for document conversions, not {} to {}",
+            input_ext.to_uppercase(),
+            output_format.to_uppercase()
+        )),
     }
-    
-    let platform_name = if cfg!(target_os = "windows") {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "macos"
-    } else {
-        "linux"
+}
+
+/// Show the exact commands convert_file would run for these arguments, without running them
+#[tauri::command]
+async fn preview_conversion_command(
+    input_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    advanced_options: Option<String>,
+    settings: Option<ConversionSettings>,
+    input_format: Option<String>,
+) -> Result<ConversionPreview, String> {
+    let settings = settings.unwrap_or_default();
+    let input_path = PathBuf::from(&input_path);
+    let file_stem = input_path.file_stem()
+        .ok_or("Invalid input file")?
+        .to_str()
+        .ok_or("Invalid file name")?;
+    // Routed as convert_file routes it: by contents, or as `input_format`
+    validate_input_format(input_format.as_deref())?;
+    let input_extension = check_input_format(&input_path, input_format.as_deref()).format;
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input_path.parent()
+            .ok_or("Could not determine input file directory")?
+            .to_path_buf(),
     };
+    let output_format_lower = output_format.to_lowercase();
     
-    let exe_name = match tool_name {
-        "ffmpeg" => {
-            if cfg!(target_os = "windows") {
-                "ffmpeg.exe"
-            } else {
-                "ffmpeg"
-            }
-        }
-        "pandoc" if ENABLE_PANDOC => {
-            if cfg!(target_os = "windows") {
-                "pandoc.exe"
-            } else {
-                "pandoc"
-            }
-        }
-        "imagemagick" => {
-            if cfg!(target_os = "windows") {
-                "magick.exe"
-            } else {
-                "magick"
-            }
-        }
-        _ => return Err(format!("Unknown tool: {}", tool_name)),
-    };
-    
-    // Try multiple possible locations
-    let mut possible_paths = vec![];
-    
-    // 1. App data directory (downloaded binaries) - CHECK THIS FIRST
-    // NOTE: This must match the path used in download_ffmpeg/download_pandoc
-    // We can't use app.path().app_data_dir() here since we don't have AppHandle,
-    // so we manually construct the same path that Tauri uses
-    if let Some(data_dir) = dirs::data_dir() {
-        // Tauri's app_data_dir() uses: {data_dir}/{identifier}
-        // APP_IDENTIFIER is "com.convertsave" for prod, "com.convertsave.dev" for dev
+    if output_format_lower == conversion::COMPRESS_TARGET && input_extension == "pdf" {
+        let tool_path = get_tool_path("ghostscript").map_err(|_| GHOSTSCRIPT_NOT_FOUND.to_string())?;
+        let output_path = get_unique_output_path(&output_dir, &format!("{}-compressed", file_stem), "pdf");
+        let quality = settings.quality.unwrap_or(conversion::DEFAULT_COMPRESS_QUALITY);
+        let mut command = create_command(&tool_path);
+        command.args(conversion::pdf_compress_args(&input_path.to_string_lossy(), &output_path.to_string_lossy(), quality));
         
-        // For ImageMagick on macOS, the binary is in bin/ subdirectory as per official structure
-        #[cfg(target_os = "macos")]
-        if tool_name == "imagemagick" {
-            let app_data_path = data_dir
-                .join(APP_IDENTIFIER)
-                .join(tool_name)
-                .join("bin")
-                .join(exe_name);
-            possible_paths.push(app_data_path);
-        } else {
-            let app_data_path = data_dir
-                .join(APP_IDENTIFIER)
-                .join(tool_name)
-                .join(exe_name);
-            possible_paths.push(app_data_path);
-        }
-        
-        // For other platforms, use flat structure
-        #[cfg(not(target_os = "macos"))]
-        {
-            let app_data_path = data_dir
-                .join(APP_IDENTIFIER)
-                .join(tool_name)
-                .join(exe_name);
-            possible_paths.push(app_data_path);
-        }
+        return Ok(ConversionPreview {
+            tool: "ghostscript".to_string(),
+            output_path: output_path.to_string_lossy().to_string(),
+            command_lines: vec![command_line(&command)],
+            note: settings.target_size_kb.map(|kb| format!(
+                "The image resolution is searched to fit {} KB, so Ghostscript runs several times with different settings",
+                kb
+            )),
+        });
     }
     
-    // 2. Project root tools directory (development only)
-    if let Ok(current) = std::env::current_dir() {
-        possible_paths.push(current.join("tools").join(platform_name).join(exe_name));
+    if output_format_lower == conversion::COMPRESS_TARGET {
+        let tool_path = get_tool_path("imagemagick")?;
+        let output_path = get_unique_output_path(&output_dir, &format!("{}-compressed", file_stem), &input_extension);
+        let quality = settings.quality.unwrap_or(conversion::DEFAULT_COMPRESS_QUALITY);
+        let mut job_dir = None;
+        let mut command = create_command(&tool_path);
+        command.arg(imagemagick_input(&input_path, None, &mut job_dir)?);
+        command.args(conversion::compress_args(&input_extension, quality, settings.keeps_metadata()));
+        command.arg(&output_path);
+        run_in_job_dir(&mut command, &job_dir);
+        
+        return Ok(ConversionPreview {
+            tool: "imagemagick".to_string(),
+            output_path: output_path.to_string_lossy().to_string(),
+            command_lines: vec![command_line(&command)],
+            note: settings.target_size_kb.map(|kb| format!(
+                "The quality is searched to fit {} KB, so the command runs several times with different -quality values",
+                kb
+            )),
+        });
     }
     
-    // 3. Check if we're in src-tauri directory during development
-    if let Ok(current) = std::env::current_dir() {
-        if let Some(parent) = current.parent() {
-            possible_paths.push(parent.join("tools").join(platform_name).join(exe_name));
+    let output_stem = render_output_name(&input_path, file_stem, &output_format, &settings)?;
+    let output_path = get_unique_output_path(&output_dir, &output_stem, &output_format);
+    let tool = conversion_tool_for(&input_extension, &output_format_lower, &settings)
+        .ok_or_else(|| format!("No conversion tool available for {} to {}", input_extension, output_format))?;
+    
+    let mut preview = ConversionPreview {
+        tool: tool.to_string(),
+        output_path: output_path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    
+    match settings.target_size_mb {
+        Some(target_mb) if tool == "ffmpeg" && conversion::target_size_codecs(&output_format_lower).is_some() => {
+            let plan = plan_target_size_encode(&input_path, &output_path, target_mb, advanced_options, &settings)?;
+            preview.command_lines = plan.commands.iter().map(|(_, command)| command_line(command)).collect();
         }
+        _ => match prepare_conversion(tool, &input_path, &output_path, advanced_options, &settings)? {
+            PreparedConversion::Copy => {
+                preview.command_lines = vec![copy_command_line(&input_path, &output_path)];
+                preview.note = Some("Only the extension changes, so the file is copied".to_string());
+            }
+            PreparedConversion::Builtin => {
+                preview.command_lines = vec![builtin_command_line(&input_path, &output_path)];
+                preview.note = Some(if pim::supports_pim_conversion(&input_extension, &output_format_lower) {
+                    "Calendars and contacts are converted by the built-in converter".to_string()
+                } else {
+                    "Neither ImageMagick nor FFmpeg is installed, so the built-in converter is used".to_string()
+                });
+            }
+            PreparedConversion::RenderSvg => {
+                preview.note = Some("The SVG is rendered by the built-in resvg renderer; outputs other than PNG are then converted from the rendered PNG".to_string());
+            }
+            PreparedConversion::DevelopRaw(tool_path) => {
+                let developed = PathBuf::from("developed.tiff");
+                let args = conversion::libraw_args(&input_path.to_string_lossy(), &developed.to_string_lossy(), &settings.raw);
+                preview.tool = "libraw".to_string();
+                preview.command_lines = vec![conversion::format_command_line(&tool_path.to_string_lossy(), &args)];
+                preview.note = Some("The RAW photo is developed by LibRaw into a TIFF in a temporary folder, which ImageMagick then converts".to_string());
+            }
+            PreparedConversion::HeicTiles(_) => {
+                preview.tool = "ffmpeg".to_string();
+                preview.note = Some("ImageMagick can't read HEIC here, so FFmpeg converts it; tile grids are extracted, stitched and encoded in several steps that depend on the image's grid layout".to_string());
+            }
+            PreparedConversion::Command { command, tool, .. } => {
+                if tool == "libreoffice" {
+                    preview.note = Some("LibreOffice writes into a temporary folder; the file is then moved to the output path".to_string());
+                }
+                preview.tool = tool;
+                preview.command_lines = vec![command_line(&command)];
+            }
+        },
     }
     
-    // 4. On macOS, check Homebrew locations as fallback (Apple Silicon and Intel)
-    #[cfg(target_os = "macos")]
-    {
-        // Apple Silicon Homebrew
-        possible_paths.push(PathBuf::from("/opt/homebrew/bin").join(exe_name));
-        // Intel Homebrew
-        possible_paths.push(PathBuf::from("/usr/local/bin").join(exe_name));
+    // Previews are shown and copied, so the PDF open password never appears in them
+    preview.command_lines = preview.command_lines.iter().map(|line| conversion::redact_pdf_password(line)).collect();
+    Ok(preview)
+}
+
+/// Collect the sizes and timing of a finished conversion
+fn conversion_stats(input_path: &PathBuf, output_path: String, started: std::time::Instant, command_line: Option<String>) -> ConversionResult {
+    let input_size = std::fs::metadata(input_path).map(|m| m.len()).unwrap_or(0);
+    let output_size = std::fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
+    ConversionResult::new(output_path, started.elapsed().as_millis() as u64, input_size, output_size, command_line)
+}
+
+/// The tool that runs for `tool`, and the one it stands in for: the built-in converter
+/// replaces missing external tools, and FFmpeg a missing ImageMagick (see prepare_conversion)
+fn tool_substitution(tool: &'static str, input_ext: &str, output_ext: &str) -> (&'static str, Option<&'static str>) {
+    if tool == conversion::BUILTIN_TOOL {
+        (tool, external_conversion_tool(input_ext, output_ext))
+    } else if tool == "imagemagick" && get_tool_path("imagemagick").is_err() {
+        ("ffmpeg", Some(tool))
+    } else {
+        (tool, Some(tool))
     }
-    
-    // On macOS, NEVER check inside the .app bundle - it's read-only and code-signed
-    // On Windows/Linux, we can check relative to executable for bundled binaries
-    #[cfg(not(target_os = "macos"))]
-    {
-        // Relative to executable (production)
-        if let Ok(exe) = std::env::current_exe() {
-            if let Some(parent) = exe.parent() {
-                possible_paths.push(parent.join("tools").join(platform_name).join(exe_name));
-            }
+}
+
+/// Add the tool used, fallbacks and lossy changes (like flattened transparency) to a result
+fn with_conversion_notes(
+    result: ConversionResult,
+    tool: &'static str,
+    input_path: &PathBuf,
+    input_ext: &str,
+    output_ext: &str,
+) -> ConversionResult {
+    let (used_tool, preferred_tool) = tool_substitution(tool, input_ext, output_ext);
+    // Only worth an extra identify call when the output can't keep an alpha channel
+    let input_has_alpha = conversion::OPAQUE_OUTPUTS.contains(&output_ext)
+        && conversion::is_image_format(input_ext)
+        && has_transparency(input_path);
+    let warnings = conversion::conversion_warnings(output_ext, input_has_alpha);
+    let fallbacks = conversion::fallback_notes(preferred_tool, used_tool);
+    for note in warnings.iter().chain(&fallbacks) {
+        info!("{}", note);
+    }
+    result.with_notes(used_tool, warnings, fallbacks)
+}
+
+/// Version of an installed tool, e.g. "6.1.1" for FFmpeg (None for the built-in converters
+/// and tools without a version flag)
+fn tool_version(tool: &str) -> Option<String> {
+    let args = sidecar::tool_version_args(tool)?;
+    let tool_path = get_tool_path(tool).ok()?;
+    if let Some(version) = TOOL_VERSIONS.lock().ok()?.get(&tool_path) {
+        return version.clone();
+    }
+    let mut command = create_command(&tool_path);
+    if tool == "imagemagick" {
+        set_imagemagick_env(&mut command, &tool_path);
+    }
+    let version = command.args(args).output()
+        .ok()
+        .and_then(|output| sidecar::parse_tool_version(&String::from_utf8_lossy(&output.stdout)));
+    if let Ok(mut versions) = TOOL_VERSIONS.lock() {
+        versions.insert(tool_path, version.clone());
+    }
+    version
+}
+
+/// Add the output's SHA-256 to `result`, and save it as `<file>.sha256` when asked. A checksum
+/// that can't be computed or written is a warning on the result rather than a failed conversion.
+fn add_checksum(result: &mut ConversionResult, write_file: bool) {
+    let output = PathBuf::from(&result.output_path);
+    let hash = match checksum::sha256_file(&output) {
+        Ok(hash) => hash,
+        Err(e) => {
+            warn!("{}", e);
+            result.warnings.push("The checksum of the output couldn't be computed".to_string());
+            return;
         }
-        
-        // Parent directory of executable + tools (alternative production layout)
-        if let Ok(exe) = std::env::current_exe() {
-            if let Some(parent) = exe.parent().and_then(|p| p.parent()) {
-                possible_paths.push(parent.join("tools").join(platform_name).join(exe_name));
+    };
+    if write_file {
+        let path = checksum::checksum_path(&output);
+        match std::fs::write(&path, checksum::checksum_line(&hash, &output)) {
+            Ok(()) => info!("Wrote {}", path.display()),
+            Err(e) => {
+                warn!("Failed to write {}: {}", path.display(), e);
+                result.warnings.push(format!("The checksum file couldn't be saved: {}", e));
             }
         }
     }
+    result.sha256 = Some(hash);
+}
+
+/// Whether a file still matches the `<file>.sha256` saved next to it
+#[tauri::command]
+async fn verify_checksum(path: String) -> Result<bool, String> {
+    let file = PathBuf::from(&path);
+    let checksum_file = checksum::checksum_path(&file);
+    let text = std::fs::read_to_string(&checksum_file)
+        .map_err(|e| format!("Failed to read {}: {}", checksum_file.display(), e))?;
+    let (expected, _) = checksum::parse_checksum_line(&text)
+        .ok_or_else(|| format!("{} isn't a SHA-256 checksum file", checksum_file.display()))?;
+    Ok(checksum::sha256_file(&file)? == expected)
+}
+
+/// Save how an output was made next to it. A sidecar that can't be written is logged and
+/// doesn't fail the conversion.
+fn write_sidecar(result: &ConversionResult, input_path: &PathBuf, settings: &ConversionSettings) {
+    let version = result.tool.as_deref().and_then(tool_version);
+    let sidecar = Sidecar::new(
+        result,
+        &input_path.to_string_lossy(),
+        settings,
+        version,
+        env!("CARGO_PKG_VERSION"),
+        chrono::Utc::now().to_rfc3339(),
+    );
+    let path = sidecar::sidecar_path(&PathBuf::from(&result.output_path));
+    let written = serde_json::to_string_pretty(&sidecar)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(&path, json).map_err(|e| e.to_string()));
+    match written {
+        Ok(()) => info!("Wrote {}", path.display()),
+        Err(e) => warn!("Failed to write {}: {}", path.display(), e),
+    }
+}
+
+/// EXIF orientation of a photo (1 when it has none). Only the start of the file is read,
+/// which holds a JPEG's Exif block; TIFFs that keep their directory at the end count as upright.
+fn read_exif_orientation(path: &PathBuf) -> u16 {
+    use std::io::Read;
+    
+    let mut head = Vec::new();
+    let read = std::fs::File::open(path).and_then(|file| file.take(128 * 1024).read_to_end(&mut head));
+    if read.is_err() {
+        return 1;
+    }
+    metadata::exif_orientation(&head).unwrap_or(1)
+}
+
+/// Copy metadata from the source onto a converted file. FFmpeg can't write EXIF into images,
+/// so photos get their tags (capture date, GPS) copied by exiftool when it's installed.
+/// The modification time is carried over too so the file sorts by capture date.
+/// `upright` outputs were rotated by the conversion, so their orientation tag isn't copied.
+fn copy_source_metadata(input_path: &PathBuf, output_path: &PathBuf, upright: bool) {
+    let output_ext = output_path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
     
-    for path in &possible_paths {
-        if path.exists() {
-            return Ok(path.clone());
+    if conversion::is_image_format(&output_ext) {
+        let copied = create_command("exiftool")
+            .args(metadata::exiftool_copy_args(&input_path.to_string_lossy(), upright))
+            .arg(output_path)
+            .output();
+        match copied {
+            Ok(output) if output.status.success() => info!("Copied metadata from {}", input_path.display()),
+            Ok(output) => warn!("exiftool could not copy metadata: {}", String::from_utf8_lossy(&output.stderr)),
+            Err(_) => debug!("exiftool not installed, keeping the metadata the converter wrote"),
+        }
+    }
+    
+    let modified = std::fs::metadata(input_path).and_then(|m| m.modified());
+    let output_file = std::fs::OpenOptions::new().write(true).open(output_path);
+    if let (Ok(modified), Ok(output_file)) = (modified, output_file) {
+        if let Err(e) = output_file.set_modified(modified) {
+            warn!("Failed to keep modification time: {}", e);
         }
     }
+}
+
+/// Attach a note and color label to a history entry or queued job (None clears them)
+#[tauri::command]
+fn set_job_label(job_id: String, note: Option<String>, label: Option<JobLabel>) -> Result<(), String> {
+    let now = chrono::Utc::now().to_rfc3339();
+    update_history(|history| history.set_label(&job_id, note, label, &now))
+}
+
+/// Estimate how long converting these files would take, from how long conversions of the
+/// same kind took before. Call it again as files finish to refine the estimate.
+#[tauri::command]
+fn estimate_batch_duration(
+    input_paths: Vec<String>,
+    output_format: String,
+    settings: Option<ConversionSettings>,
+    workers: Option<usize>,
+) -> Result<BatchEstimate, String> {
+    let settings = settings.unwrap_or_default();
+    let output_format = output_format.to_lowercase();
+    let job_history = {
+        let _guard = HISTORY_LOCK.lock().map_err(|_| "History is unavailable".to_string())?;
+        JobHistory::load(&get_history_path()?)?
+    };
     
-    // If none found, list all the paths we checked
-    let checked_paths: Vec<String> = possible_paths.iter()
-        .map(|p| p.display().to_string())
+    // Every file checks the same tools, so look each one up once
+    let _tool_paths = ToolPathCache::enable();
+    let mut measured_files = 0;
+    let file_ms: Vec<u64> = input_paths.iter()
+        .map(|path| {
+            let input_path = PathBuf::from(path);
+            let input_ext = input_path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+            let input_bytes = std::fs::metadata(&input_path).map(|m| m.len()).unwrap_or(0);
+            let measured = conversion_tool_for(&input_ext, &output_format, &settings).and_then(|tool| {
+                let (used_tool, _) = tool_substitution(tool, &input_ext, &output_format);
+                job_history.estimate_ms(&history::throughput_key(used_tool, &input_ext, &output_format), input_bytes)
+            });
+            if measured.is_some() {
+                measured_files += 1;
+            }
+            measured.unwrap_or_else(|| conversion::default_conversion_ms(&input_ext, &output_format, input_bytes))
+        })
         .collect();
     
-    let error_msg = format!("Tool not found: {} (checked: {})", tool_name, checked_paths.join(", "));
-    warn!("{}", error_msg);
-    Err(error_msg)
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let workers = conversion::requested_worker_count(workers, cpus, input_paths.len());
+    Ok(BatchEstimate {
+        total_ms: conversion::batch_wall_time_ms(&file_ms, workers),
+        file_ms,
+        workers,
+        measured_files,
+    })
 }
 
-// Helper function to handle HEIC tile grid reassembly
-fn convert_heic_with_tiles(
-    tool_path: &PathBuf,
+/// Search history by file name, format, note or label, newest first
+#[tauri::command]
+fn search_history(query: String) -> Result<Vec<HistoryEntry>, String> {
+    let _guard = HISTORY_LOCK.lock().map_err(|_| "History is unavailable".to_string())?;
+    let history = JobHistory::load(&get_history_path()?)?;
+    Ok(history.search(&query))
+}
+
+/// The FFmpeg commands for a target size encode, in order, with the job directory holding their pass log
+struct TargetSizePlan {
+    /// Stage name ("First pass", ...) and command
+    commands: Vec<(&'static str, Command)>,
+    /// Holds the two-pass statistics files; removed once the plan is dropped
+    job_dir: Option<JobDir>,
+}
+
+/// Work out the bitrates for `target_mb` and build the FFmpeg commands to hit it.
+/// Video uses a two-pass encode at the computed bitrate; audio-only outputs need a single pass.
+fn plan_target_size_encode(
     input_path: &PathBuf,
     output_path: &PathBuf,
-) -> Result<(), String> {
-    // Step 1: Get metadata to find tile grid dimensions and rotation
-    let metadata_output = create_command(tool_path)
+    target_mb: f64,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<TargetSizePlan, String> {
+    let ffmpeg_path = get_tool_path("ffmpeg")?;
+    
+    let output_ext = output_path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let (video_codec, audio_codec) = conversion::target_size_codecs(&output_ext)
+        .ok_or_else(|| format!("Target size isn't available for {} output", output_ext.to_uppercase()))?;
+    
+    // FFmpeg prints the duration and stream info to stderr when given only an input
+    let probe = create_command(&ffmpeg_path)
         .arg("-i")
         .arg(input_path)
-        .arg("-f")
-        .arg("null")
-        .arg("-")
         .output()
-        .map_err(|e| format!("Failed to get HEIC metadata: {}", e))?;
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    let probe_info = String::from_utf8_lossy(&probe.stderr);
+    let duration = conversion::parse_ffmpeg_duration(&probe_info)
+        .ok_or("Could not determine the media duration")?;
+    let has_video = video_codec.is_some() && probe_info.contains("Video:");
+    
+    let (video_kbps, audio_kbps) = conversion::compute_target_bitrates(target_mb, duration, has_video)?;
+    info!("Target size {} MB over {:.1}s: video {:?} kbps, audio {} kbps", target_mb, duration, video_kbps, audio_kbps);
+    
+    let mut extra_args: Vec<String> = conversion::metadata_args("ffmpeg", settings)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if let Some(options) = advanced_options {
+        extra_args.extend(options.split_whitespace().map(String::from));
+    }
     
-    let stderr = String::from_utf8_lossy(&metadata_output.stderr);
+    let (video_kbps, video_codec) = match (video_kbps, video_codec) {
+        (Some(video_kbps), Some(video_codec)) => (video_kbps, video_codec),
+        _ => {
+            // Audio-only: a single constant-bitrate pass is accurate enough
+            let mut command = create_command(&ffmpeg_path);
+            command.arg("-y").arg("-i").arg(input_path).arg("-vn");
+            command.arg("-c:a").arg(audio_codec).arg("-b:a").arg(format!("{}k", audio_kbps));
+            command.args(&extra_args);
+            command.arg(output_path);
+            return Ok(TargetSizePlan {
+                commands: vec![("Encoding", command)],
+                job_dir: None,
+            });
+        }
+    };
     
-    // Parse resolution from tile grid
-    let mut width = 0u32;
-    let mut height = 0u32;
-    let mut has_rotation = false;
-    let mut rotation_degrees = 0i32;
-    
-    if let Some(tile_grid_line) = stderr.lines().find(|line| line.contains("Tile Grid:") && line.contains("hevc") && line.contains("default")) {
-        debug!("HEIC tile grid: {}", tile_grid_line);
-        
-        use std::str::FromStr;
-        for word in tile_grid_line.split_whitespace() {
-            if word.contains('x') && !word.starts_with("0x") {
-                if let Some((w, h)) = word.split_once('x') {
-                    if let (Ok(w_val), Ok(h_val)) = (u32::from_str(w), u32::from_str(h)) {
-                        if w_val >= 100 && w_val < 100000 && h_val >= 100 && h_val < 100000 {
-                            width = w_val;
-                            height = h_val;
-                            info!("HEIC resolution: {}x{}", width, height);
-                            break;
-                        }
-                    }
-                }
+    // Each job gets its own pass log so concurrent two-pass encodes don't share statistics
+    let job_dir = JobDir::create()?;
+    let passlog = job_dir.path.join("2pass");
+    let null_output = if cfg!(target_os = "windows") { "NUL" } else { "/dev/null" };
+    
+    // Pass 1: analyze only, no audio and no real output
+    let mut first_pass = create_command(&ffmpeg_path);
+    first_pass.arg("-y").arg("-i").arg(input_path);
+    first_pass.arg("-c:v").arg(video_codec).arg("-b:v").arg(format!("{}k", video_kbps));
+    first_pass.arg("-pass").arg("1").arg("-passlogfile").arg(&passlog);
+    first_pass.arg("-an");
+    first_pass.args(&extra_args);
+    first_pass.arg("-f").arg("null").arg(null_output);
+    
+    // Pass 2: the real encode using the first pass statistics
+    let mut second_pass = create_command(&ffmpeg_path);
+    second_pass.arg("-y").arg("-i").arg(input_path);
+    second_pass.arg("-c:v").arg(video_codec).arg("-b:v").arg(format!("{}k", video_kbps));
+    second_pass.arg("-pass").arg("2").arg("-passlogfile").arg(&passlog);
+    second_pass.arg("-pix_fmt").arg("yuv420p");
+    if output_ext == "mp4" || output_ext == "mov" || output_ext == "m4v" {
+        second_pass.arg("-movflags").arg("+faststart");
+    }
+    second_pass.arg("-c:a").arg(audio_codec).arg("-b:a").arg(format!("{}k", audio_kbps));
+    second_pass.args(&extra_args);
+    second_pass.arg(output_path);
+    
+    Ok(TargetSizePlan {
+        commands: vec![("First pass", first_pass), ("Second pass", second_pass)],
+        job_dir: Some(job_dir),
+    })
+}
+
+/// Encode video/audio so the output lands just under `target_mb`.
+/// Returns the command lines that ran, one per line.
+fn encode_to_target_size(
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    target_mb: f64,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    // The pass log files live in the job directory, removed once it goes out of scope
+    let TargetSizePlan { commands, job_dir: _job_dir } =
+        plan_target_size_encode(input_path, output_path, target_mb, advanced_options, settings)?;
+    
+    let mut command_lines = Vec::new();
+    let mut result = Ok(());
+    for (stage, mut command) in commands {
+        debug!("Executing command: {:?}", command);
+        let output = match output_with_limits(&mut command, "ffmpeg", output_path, settings) {
+            Ok(output) => output,
+            Err(e) => {
+                result = Err(e);
+                break;
             }
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            result = Err(format!("{} failed. Error details: {}", stage, stderr));
+            break;
         }
+        command_lines.push(command_line(&command));
     }
     
-    // Check for rotation
-    if stderr.contains("rotation of -90") {
-        has_rotation = true;
-        rotation_degrees = -90;
-        info!("HEIC rotation: -90 degrees");
-    } else if stderr.contains("rotation of 90") {
-        has_rotation = true;
-        rotation_degrees = 90;
-        info!("HEIC rotation: 90 degrees");
-    } else if stderr.contains("rotation of 180") || stderr.contains("rotation of -180") {
-        has_rotation = true;
-        rotation_degrees = 180;
-        info!("HEIC rotation: 180 degrees");
+    result.map(|_| command_lines.join("\n"))
+}
+
+/// Re-encode an image in its own format at a lower quality, or at the highest quality that fits `target_size_kb`
+fn compress_image(
+    input_path: &PathBuf,
+    output_dir: &PathBuf,
+    file_stem: &str,
+    input_extension: &str,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    if input_extension == "pdf" {
+        return compress_pdf(input_path, output_dir, file_stem, settings);
+    }
+    if !conversion::supports_compression(input_extension) {
+        return Err(format!("{} images can't be compressed in place", input_extension.to_uppercase()));
     }
     
-    if width == 0 || height == 0 {
-        return Err("Could not determine HEIC tile grid dimensions".to_string());
+    let tool_path = get_tool_path("imagemagick").map_err(|_| {
+        "ImageMagick is required to compress images but is not installed.\n\n\
+        Please install ImageMagick from the Tools Manager in Settings.".to_string()
+    })?;
+    
+    let output_path = get_unique_output_path(output_dir, &format!("{}-compressed", file_stem), input_extension);
+    
+    let mut job_dir = None;
+    let input = imagemagick_input(input_path, None, &mut job_dir)?;
+    let encode = |quality: u8| -> Result<u64, String> {
+        let mut command = create_command(&tool_path);
+        command.arg(&input);
+        for arg in conversion::compress_args(input_extension, quality, settings.keeps_metadata()) {
+            command.arg(arg);
+        }
+        set_deterministic_env(&mut command, settings);
+        command.arg(&output_path);
+        run_in_job_dir(&mut command, &job_dir);
+        
+        debug!("Executing command: {:?}", command);
+        let output = command.output()
+            .map_err(|e| format!("Failed to execute imagemagick: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Compression failed. Error details: {}", stderr));
+        }
+        
+        std::fs::metadata(&output_path).map(|m| m.len()).map_err(|e| e.to_string())
+    };
+    
+    // PNG is lossless, so there is no quality to search over
+    match settings.target_size_kb.filter(|_| input_extension != "png") {
+        Some(target_kb) => {
+            let max_bytes = target_kb * 1024;
+            let (quality, fits) = conversion::find_quality_for_size(max_bytes, encode)?;
+            if !fits {
+                warn!("Could not get {} under {} KB, keeping the smallest result", input_path.display(), target_kb);
+            }
+            info!("Selected quality {} for target size {} KB", quality, target_kb);
+            // The last attempt isn't necessarily the chosen one, so write the final result again
+            encode(quality)?;
+        }
+        None => {
+            encode(settings.quality.unwrap_or(conversion::DEFAULT_COMPRESS_QUALITY))?;
+        }
     }
     
-    // Step 2: Create temp directory for tiles
-    let temp_dir = std::env::temp_dir().join(format!("heic_tiles_{}", std::process::id()));
-    std::fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    info!("Compression completed successfully: {}", output_path.display());
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Rewrite a PDF with Ghostscript at a lower image resolution, or at the highest quality that fits `target_size_kb`
+fn compress_pdf(
+    input_path: &PathBuf,
+    output_dir: &PathBuf,
+    file_stem: &str,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    let tool_path = get_tool_path("ghostscript").map_err(|_| {
+        "Ghostscript is required to compress PDFs but is not installed.\n\n\
+        Please install Ghostscript from the Tools Manager in Settings.".to_string()
+    })?;
     
-    // Step 3: Extract tiles
-    info!("Extracting HEIC tiles to: {}", temp_dir.display());
-    let tile_pattern = temp_dir.join("tile_%02d.png");
+    let output_path = get_unique_output_path(output_dir, &format!("{}-compressed", file_stem), "pdf");
+    let input = input_path.to_string_lossy();
+    let output = output_path.to_string_lossy();
     
-    let extract_output = create_command(tool_path)
-        .arg("-i")
-        .arg(input_path)
-        .arg("-map")
-        .arg("0:g:0")
-        .arg(&tile_pattern)
-        .arg("-y")
-        .output()
-        .map_err(|e| format!("Failed to extract tiles: {}", e))?;
+    let encode = |quality: u8| -> Result<u64, String> {
+        let mut command = create_command(&tool_path);
+        command.args(conversion::pdf_compress_args(&input, &output, quality));
+        set_deterministic_env(&mut command, settings);
+        
+        debug!("Executing command: {:?}", command);
+        let result = command.output()
+            .map_err(|e| format!("Failed to execute ghostscript: {}", e))?;
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            let stdout = String::from_utf8_lossy(&result.stdout);
+            return Err(format!("PDF compression failed. Error details: {}{}", stdout, stderr));
+        }
+        
+        std::fs::metadata(&output_path).map(|m| m.len()).map_err(|e| e.to_string())
+    };
     
-    if !extract_output.status.success() {
-        let _ = std::fs::remove_dir_all(&temp_dir);
-        return Err("Failed to extract HEIC tiles".to_string());
+    match settings.target_size_kb {
+        Some(target_kb) => {
+            let max_bytes = target_kb * 1024;
+            let (quality, fits) = conversion::find_quality_for_size(max_bytes, encode)?;
+            if !fits {
+                warn!("Could not get {} under {} KB, keeping the smallest result", input_path.display(), target_kb);
+            }
+            info!("Selected quality {} for target size {} KB", quality, target_kb);
+            // The last attempt isn't necessarily the chosen one, so write the final result again
+            encode(quality)?;
+        }
+        None => {
+            encode(settings.quality.unwrap_or(conversion::DEFAULT_COMPRESS_QUALITY))?;
+        }
     }
     
-    // Step 4: Calculate grid dimensions (tiles are 512x512)
-    let tile_size = 512u32;
-    let cols = (width + tile_size - 1) / tile_size;  // Round up
-    let rows = (height + tile_size - 1) / tile_size;  // Round up
-    info!("Tile grid: {}x{} ({}x{} tiles)", cols, rows, cols * tile_size, rows * tile_size);
+    info!("PDF compression completed successfully: {}", output_path.display());
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Put Ghostscript first on a command's PATH so ImageMagick can hand it PDF/PostScript inputs.
+/// Best-effort: without a Ghostscript of our own the command keeps its PATH, where
+/// ImageMagick may still find a system-wide one.
+fn add_ghostscript_to_path(command: &mut Command) {
+    let Ok(gs_path) = get_tool_path("ghostscript") else {
+        debug!("Ghostscript not found, leaving PATH unchanged");
+        return;
+    };
+    let Some(gs_dir) = gs_path.parent() else {
+        return;
+    };
     
-    // Step 5: Stitch tiles together
-    let stitched_path = temp_dir.join("stitched.png");
-    let tile_input = temp_dir.join("tile_%02d.png");
+    let mut paths = vec![gs_dir.to_path_buf()];
+    if let Some(existing) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&existing));
+    }
+    match std::env::join_paths(paths) {
+        Ok(joined) => {
+            command.env("PATH", joined);
+        }
+        Err(e) => warn!("Failed to add Ghostscript to PATH: {}", e),
+    }
     
-    let stitch_output = create_command(tool_path)
-        .arg("-i")
-        .arg(&tile_input)
-        .arg("-filter_complex")
-        .arg(format!("tile={}x{}", cols, rows))
-        .arg("-frames:v")
-        .arg("1")
-        .arg("-y")
-        .arg(&stitched_path)
-        .output()
-        .map_err(|e| format!("Failed to stitch tiles: {}", e))?;
+    // ImageMagick on Windows otherwise only looks for Ghostscript in the registry
+    #[cfg(target_os = "windows")]
+    command.env("MAGICK_GHOSTSCRIPT_PATH", gs_dir);
+}
+
+/// Returns a warning to show before converting, e.g. a mislabeled .aac/.m4a input or a raw AAC output
+#[tauri::command]
+fn get_format_warning(input_path: String, output_format: String) -> Option<String> {
+    let path = PathBuf::from(&input_path);
+    let format_check = check_input_format(&path, None);
+    // A file without an extension is checked as what its contents are
+    let input_extension = if format_check.extension.is_empty() {
+        format_check.format.clone()
+    } else {
+        format_check.extension.clone()
+    };
     
-    if !stitch_output.status.success() {
-        let _ = std::fs::remove_dir_all(&temp_dir);
-        return Err("Failed to stitch HEIC tiles".to_string());
+    // The container type is in the first few bytes
+    let mut header = [0u8; 12];
+    let header_len = std::fs::File::open(&path)
+        .and_then(|mut file| std::io::Read::read(&mut file, &mut header))
+        .unwrap_or(0);
+    
+    // The AAC warning is the more specific one for .aac/.m4a inputs
+    conversion::aac_format_warning(&input_extension, &header[..header_len], &output_format.to_lowercase())
+        .or_else(|| format_check.warning())
+}
+
+/// Render a web page to a PNG or PDF snapshot with headless Chromium
+#[tauri::command]
+async fn snapshot_url(
+    url: String,
+    output_format: String,
+    output_directory: String,
+    settings: Option<ConversionSettings>,
+) -> Result<String, String> {
+    info!("Starting snapshot: {} -> {}", url, output_format);
+    let settings = settings.unwrap_or_default();
+    info!("Snapshot settings: {:?}", settings);
+    
+    let url = url.trim();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err("Please enter a full web address starting with http:// or https://".to_string());
     }
     
-    // Step 6: Crop to exact dimensions and apply rotation
-    let mut filter_parts = vec![];
+    let output_ext = output_format.to_lowercase();
+    if !conversion::SNAPSHOT_OUTPUTS.contains(&output_ext.as_str()) {
+        return Err(format!("Snapshots can't be saved as {}", output_format));
+    }
     
-    // Crop if tiles are larger than actual image
-    let stitched_width = cols * tile_size;
-    let stitched_height = rows * tile_size;
-    if stitched_width != width || stitched_height != height {
-        filter_parts.push(format!("crop={}:{}:0:0", width, height));
+    let tool_path = get_tool_path("chromium").map_err(|_| CHROMIUM_NOT_FOUND.to_string())?;
+    
+    let output_dir = PathBuf::from(&output_directory);
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = conversion::snapshot_file_stem(url);
+    let output_path = get_unique_output_path(&output_dir, &file_stem, &output_ext);
+    
+    let mut command = create_command(&tool_path);
+    for arg in conversion::chromium_snapshot_args(url, &output_path.to_string_lossy(), &output_ext, &settings) {
+        command.arg(arg);
+    }
+    
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to start headless browser: {}", e))?;
+    
+    if !output.status.success() || !output_path.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Snapshot failed: {}", stderr);
+        return Err(format!("Snapshot failed. The page may be unreachable.\n\nError details: {}", stderr));
+    }
+    
+    if settings.full_page && output_ext == "png" {
+        trim_snapshot_bottom(&output_path);
     }
     
-    // Add rotation filter
-    if has_rotation {
-        match rotation_degrees {
-            -90 => filter_parts.push("transpose=1".to_string()),  // 90° CCW
-            90 => filter_parts.push("transpose=2".to_string()),    // 90° CW
-            180 => filter_parts.push("hflip,vflip".to_string()),   // 180°
-            _ => {}
+    info!("Snapshot completed successfully: {}", output_path.display());
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Shrink an existing animated GIF/WebP in place of a format conversion and report the savings
+#[tauri::command]
+async fn optimize_animation(
+    input_path: String,
+    output_directory: Option<String>,
+    options: Option<AnimationOptimizeOptions>,
+) -> Result<OptimizationReport, String> {
+    info!("Starting animation optimization: {}", input_path);
+    let options = options.unwrap_or_default();
+    info!("Optimization options: {:?}", options);
+    
+    let input_path = PathBuf::from(&input_path);
+    let file_stem = input_path.file_stem()
+        .ok_or("Invalid input file")?
+        .to_str()
+        .ok_or("Invalid file name")?;
+    
+    let input_extension = input_path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    
+    if input_extension != "gif" && input_extension != "webp" {
+        return Err(format!("Only GIF and WebP animations can be optimized, not {}", input_extension));
+    }
+    
+    let tool_path = get_tool_path("imagemagick").map_err(|_| {
+        "ImageMagick is required to optimize animations but is not installed.\n\n\
+        Please install ImageMagick from the Tools Manager in Settings.".to_string()
+    })?;
+    
+    let output_dir = if let Some(dir) = output_directory {
+        PathBuf::from(dir)
+    } else {
+        input_path.parent()
+            .ok_or("Could not determine input file directory")?
+            .to_path_buf()
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let output_path = get_unique_output_path(&output_dir, &format!("{}-optimized", file_stem), &input_extension);
+    
+    let mut job_dir = None;
+    let input = imagemagick_input(&input_path, None, &mut job_dir)?;
+    
+    // Frame dropping needs the per-frame delays so the result keeps its original duration
+    let plan = match options.keep_every {
+        Some(keep_every) if keep_every > 1 => {
+            let mut identify = create_command(&tool_path);
+            identify.arg("identify").arg("-format").arg("%T\n").arg(&input);
+            run_in_job_dir(&mut identify, &job_dir);
+            let identify = identify.output()
+                .map_err(|e| format!("Failed to read animation frames: {}", e))?;
+            let delays: Vec<u32> = String::from_utf8_lossy(&identify.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .collect();
+            info!("Animation has {} frames", delays.len());
+            conversion::plan_frame_drop(&delays, keep_every)
+        }
+        _ => None,
+    };
+    
+    let mut command = create_command(&tool_path);
+    command.arg(&input);
+    for arg in conversion::animation_optimize_args(&input_extension, &options, plan.as_ref()) {
+        command.arg(arg);
+    }
+    command.arg(&output_path);
+    run_in_job_dir(&mut command, &job_dir);
+    
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute imagemagick: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Optimization failed: {}", stderr);
+        return Err(format!("Optimization failed. Error details: {}", stderr));
+    }
+    
+    let original_size = std::fs::metadata(&input_path).map(|m| m.len()).map_err(|e| e.to_string())?;
+    let optimized_size = std::fs::metadata(&output_path).map(|m| m.len()).map_err(|e| e.to_string())?;
+    let report = OptimizationReport::new(output_path.to_string_lossy().to_string(), original_size, optimized_size);
+    
+    info!("Optimization completed: {} -> {} bytes ({}% saved)", original_size, optimized_size, report.saved_percent);
+    Ok(report)
+}
+
+/// Full-page snapshots are rendered into a very tall viewport; trim the empty space below the page.
+/// Best effort - the untrimmed snapshot is kept if ImageMagick isn't available.
+fn trim_snapshot_bottom(image_path: &PathBuf) {
+    let tool_path = match get_tool_path("imagemagick") {
+        Ok(path) => path,
+        Err(_) => {
+            warn!("ImageMagick not available, keeping untrimmed full-page snapshot");
+            return;
+        }
+    };
+    
+    // Only trim the bottom edge so page margins and width stay intact
+    let output = create_command(&tool_path)
+        .arg(image_path)
+        .arg("-fuzz").arg("1%")
+        .arg("-define").arg("trim:edges=south")
+        .arg("-trim")
+        .arg("+repage")
+        .arg(image_path)
+        .output();
+    
+    match output {
+        Ok(output) if output.status.success() => {
+            info!("Trimmed full-page snapshot: {}", image_path.display());
+        }
+        Ok(output) => {
+            warn!("Failed to trim snapshot: {}", String::from_utf8_lossy(&output.stderr));
+        }
+        Err(e) => {
+            warn!("Failed to run ImageMagick for snapshot trim: {}", e);
+        }
+    }
+}
+
+/// Checks the first video stream of a file for the HEVC codec
+fn is_hevc_video(path: &std::path::Path) -> bool {
+    let ffmpeg_path = match get_tool_path("ffmpeg") {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+    
+    // FFmpeg prints stream info to stderr, e.g. "Stream #0:0: Video: hevc (Main) (hvc1 / 0x31637668)"
+    create_command(&ffmpeg_path)
+        .arg("-i")
+        .arg(path)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stderr).contains("Video: hevc"))
+        .unwrap_or(false)
+}
+
+/// Synthesize the integration test fixtures with the installed tools (dev builds only).
+/// Missing files are generated into `output_directory` (default: the source tree's
+/// tests/fixtures); `overwrite` regenerates every one.
+#[tauri::command]
+async fn generate_test_fixtures(output_directory: Option<String>, overwrite: Option<bool>) -> Result<FixtureReport, String> {
+    if !cfg!(feature = "dev-build") {
+        return Err("Test fixtures can only be generated in development builds".to_string());
+    }
+    
+    let fixtures_dir = output_directory
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures"));
+    let installed: Vec<&str> = ["imagemagick", "ffmpeg"]
+        .into_iter()
+        .filter(|tool| get_tool_path(tool).is_ok())
+        .collect();
+    
+    info!("Generating test fixtures in {} with {:?}", fixtures_dir.display(), installed);
+    let report = fixtures::generate(&fixtures_dir, &installed, overwrite.unwrap_or(false), |tool, args| {
+        let tool_path = get_tool_path(tool)?;
+        let output = create_command(&tool_path)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", tool, e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(stderr.lines().last().unwrap_or("failed").trim().to_string())
+        }
+    });
+    info!(
+        "Test fixtures: {} created, {} existing, {} skipped, {} failed",
+        report.created.len(), report.existing.len(), report.skipped.len(), report.failed.len()
+    );
+    for failure in &report.failed {
+        warn!("Fixture failed: {}", failure);
+    }
+    Ok(report)
+}
+
+/// Summarize a folder: files and bytes per format, likely savings per target format and
+/// files that can't be converted
+#[tauri::command]
+async fn scan_folder_report(dir: String) -> Result<FolderReport, String> {
+    let folder = PathBuf::from(&dir);
+    if !folder.is_dir() {
+        return Err(format!("Folder not found: {}", dir));
+    }
+    
+    info!("Auditing {}", dir);
+    let report = audit::scan_folder(&folder);
+    info!(
+        "Folder audit: {} files ({} bytes) in {} formats, {} unsupported",
+        report.file_count, report.total_bytes, report.formats.len(), report.unsupported.len()
+    );
+    Ok(report)
+}
+
+/// Scan a photo folder and report what migrating it to `target_format` ("jpg" or "heic") would do
+#[tauri::command]
+async fn scan_heic_migration(folder: String, target_format: String) -> Result<MigrationScan, String> {
+    let folder_path = PathBuf::from(&folder);
+    if !folder_path.is_dir() {
+        return Err(format!("Folder not found: {}", folder));
+    }
+    
+    let target_format = target_format.to_lowercase();
+    if target_format != "jpg" && target_format != "heic" {
+        return Err(format!("Migration target must be JPG or HEIC, not {}", target_format));
+    }
+    
+    info!("Scanning {} for migration to {}", folder, target_format);
+    let scan = migration::scan_folder(&folder_path, &target_format, is_hevc_video);
+    info!(
+        "Migration scan: {} files ({} bytes -> ~{} bytes), {} duplicates, {} HEVC videos",
+        scan.files.len(), scan.total_bytes, scan.projected_bytes, scan.duplicate_count, scan.hevc_video_count
+    );
+    Ok(scan)
+}
+
+/// Convert every photo found by the migration scan, keeping metadata and timestamps,
+/// and write a manifest that can be used to roll the migration back
+#[tauri::command]
+async fn run_heic_migration(folder: String, target_format: String) -> Result<MigrationResult, String> {
+    let scan = scan_heic_migration(folder.clone(), target_format).await?;
+    let mut result = MigrationResult {
+        skipped: scan.duplicate_count,
+        ..Default::default()
+    };
+    let mut manifest = MigrationManifest {
+        created_at: chrono::Utc::now().to_rfc3339(),
+        folder: folder.clone(),
+        target_format: scan.target_format.clone(),
+        entries: Vec::new(),
+    };
+    
+    for file in &scan.files {
+        let source = PathBuf::from(file);
+        let output = migration::migration_output_path(&source, &scan.target_format);
+        
+        // ImageMagick keeps EXIF (date taken, GPS, camera) unless told to strip it
+        match execute_conversion("imagemagick", &source, &output, None, &ConversionSettings::default()).await {
+            Ok(_) => {
+                // Keep the original file date so photo libraries sort the copy correctly
+                if let Ok(modified) = std::fs::metadata(&source).and_then(|m| m.modified()) {
+                    if let Ok(output_file) = std::fs::File::options().write(true).open(&output) {
+                        let _ = output_file.set_modified(modified);
+                    }
+                }
+                manifest.entries.push(MigrationEntry {
+                    source: file.clone(),
+                    output: output.to_string_lossy().to_string(),
+                });
+                result.converted += 1;
+            }
+            Err(e) => {
+                warn!("Migration failed for {}: {}", file, e);
+                result.failed.push(format!("{}: {}", file, e));
+            }
+        }
+    }
+    
+    if !manifest.entries.is_empty() {
+        let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+        let manifest_path = PathBuf::from(&folder).join(format!("convertsave-migration-{}.json", stamp));
+        manifest.save(&manifest_path)?;
+        result.manifest_path = Some(manifest_path.to_string_lossy().to_string());
+    }
+    
+    info!("Migration finished: {} converted, {} skipped, {} failed", result.converted, result.skipped, result.failed.len());
+    Ok(result)
+}
+
+/// Undo a migration by deleting the files listed in its manifest (originals are never touched)
+#[tauri::command]
+async fn rollback_heic_migration(manifest_path: String) -> Result<RollbackReport, String> {
+    let manifest_path = PathBuf::from(&manifest_path);
+    let manifest = MigrationManifest::load(&manifest_path)?;
+    
+    let report = migration::rollback(&manifest);
+    info!("Rolled back migration: {} removed, {} already missing", report.removed, report.missing);
+    
+    std::fs::remove_file(&manifest_path)
+        .map_err(|e| format!("Failed to remove migration manifest: {}", e))?;
+    Ok(report)
+}
+
+/// Convert multiple images into a single multipage PDF
+#[tauri::command]
+async fn convert_images_to_multipage_pdf(
+    input_paths: Vec<String>,
+    output_directory: Option<String>,
+) -> Result<String, String> {
+    info!("Starting multipage PDF conversion with {} images", input_paths.len());
+    
+    if input_paths.is_empty() {
+        return Err("No input files provided".to_string());
+    }
+    
+    // Convert string paths to PathBuf
+    let input_paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
+    
+    // Verify all input files exist
+    for path in &input_paths {
+        if !path.exists() {
+            return Err(format!("Input file not found: {}", path.display()));
+        }
+    }
+    
+    // Determine output directory - use the directory of the first file if not specified
+    let output_dir = if let Some(dir) = output_directory {
+        PathBuf::from(dir)
+    } else {
+        input_paths[0]
+            .parent()
+            .ok_or("Could not determine output directory")?
+            .to_path_buf()
+    };
+    
+    // Generate output filename based on first file name
+    let first_file_stem = input_paths[0]
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("combined");
+    
+    // Get a unique output path
+    let output_path = get_unique_output_path(&output_dir, &format!("{}_multipage", first_file_stem), "pdf");
+    
+    // Get ImageMagick path
+    let tool_path = get_tool_path("imagemagick")
+        .map_err(|e| format!("ImageMagick is required for multipage PDF creation: {}", e))?;
+    
+    // Build ImageMagick command: magick input1.jpg input2.png ... output.pdf
+    let mut command = create_command(&tool_path);
+    
+    // On macOS, ImageMagick needs to be told where its bundled libraries are
+    set_imagemagick_env(&mut command, &tool_path);
+    
+    // Add all input files
+    let mut job_dir = None;
+    let mut magick = MagickCommand::new();
+    for input_path in &input_paths {
+        magick = magick.input(imagemagick_input(&PathBuf::from(input_path), None, &mut job_dir)?);
+    }
+    run_in_job_dir(&mut command, &job_dir);
+    
+    // Add PDF-specific options for good quality output; phone photos are turned upright first
+    let magick = magick
+        .op(MagickOp::AutoOrient)
+        .op(MagickOp::Compress("jpeg".to_string()))  // Use JPEG compression for images
+        .op(MagickOp::Quality(85))                   // Good quality/size balance
+        .op(MagickOp::Density(300))                  // 300 DPI for print quality
+        .output(&output_path);
+    command.args(magick.args());
+    
+    info!("Executing ImageMagick multipage PDF command...");
+    
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        error!("ImageMagick multipage PDF failed - stderr: {}", stderr);
+        error!("ImageMagick multipage PDF failed - stdout: {}", stdout);
+        return Err(format!("Failed to create multipage PDF: {}", stderr));
+    }
+    
+    info!("Multipage PDF created successfully: {}", output_path.display());
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+/// Combine audio files, in the order given, into a single MP3 or M4A. `reencode` converts
+/// every input through FFmpeg's concat filter (needed when their formats differ); without
+/// it the streams are copied as they are by the concat demuxer.
+#[tauri::command]
+async fn concat_audio_files(
+    input_paths: Vec<String>,
+    output_format: String,
+    output_directory: Option<String>,
+    reencode: bool,
+) -> Result<String, String> {
+    info!("Combining {} audio files into {}", input_paths.len(), output_format);
+    let _busy = BusyGuard::start();
+    
+    if input_paths.len() < 2 {
+        return Err("Select at least two audio files to combine".to_string());
+    }
+    let output_format = output_format.to_lowercase();
+    if !conversion::CONCAT_AUDIO_OUTPUTS.contains(&output_format.as_str()) {
+        return Err(format!("Audio files can only be combined into {}", conversion::CONCAT_AUDIO_OUTPUTS.join(" or ").to_uppercase()));
+    }
+    
+    let input_paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
+    for path in &input_paths {
+        if !path.exists() {
+            return Err(format!("Input file not found: {}", path.display()));
+        }
+    }
+    let input_exts: Vec<String> = input_paths.iter()
+        .map(|path| path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase())
+        .collect();
+    if !reencode && !conversion::can_concat_by_copy(&input_exts, &output_format) {
+        return Err(format!(
+            "These files can't be joined without re-encoding because they aren't all {}.\n\nTurn on re-encoding to combine them.",
+            output_format.to_uppercase()
+        ));
+    }
+    
+    // Determine output directory - use the directory of the first file if not specified
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input_paths[0]
+            .parent()
+            .ok_or("Could not determine output directory")?
+            .to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let first_file_stem = input_paths[0]
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("combined");
+    let reservation = reserve_output_path(&output_dir, &format!("{}_combined", first_file_stem), &output_format);
+    
+    let absolute: Vec<String> = input_paths.iter()
+        .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone()).to_string_lossy().to_string())
+        .collect();
+    let tool_path = get_tool_path("ffmpeg")
+        .map_err(|e| format!("FFmpeg is required to combine audio files: {}", e))?;
+    let mut command = create_command(&tool_path);
+    // Kept until FFmpeg has read the list
+    let job_dir = JobDir::create()?;
+    if reencode {
+        command.args(conversion::concat_audio_reencode_args(&absolute, &output_format));
+    } else {
+        // The concat demuxer reads the inputs from a list file
+        let list_path = job_dir.path.join("inputs.txt");
+        std::fs::write(&list_path, conversion::concat_list(&absolute))
+            .map_err(|e| format!("Failed to write concat list: {}", e))?;
+        command.args(conversion::concat_audio_args(&list_path.to_string_lossy(), &output_format));
+    }
+    command.arg("-y").arg(&reservation.path);
+    let (mut command, _) = ffmpeg_with_encoders(command, tool_path)?;
+    
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Combining audio failed - stderr: {}", stderr);
+        let _ = std::fs::remove_file(&reservation.path);
+        return Err(format!("Failed to combine audio files: {}", stderr));
+    }
+    
+    info!("Combined audio written to {}", reservation.path.display());
+    Ok(reservation.path.to_string_lossy().to_string())
+}
+
+/// Joins video clips into one file, in the order given. Clips that share codecs and
+/// dimensions are joined by stream copy; anything else is re-encoded onto the first clip's
+/// frame size. Emits "concat-progress" as each clip starts being written.
+#[tauri::command]
+async fn concat_videos(
+    app: AppHandle,
+    input_paths: Vec<String>,
+    output_format: String,
+    output_directory: Option<String>,
+) -> Result<String, String> {
+    use std::io::{BufRead, Read};
+    
+    info!("Combining {} videos into {}", input_paths.len(), output_format);
+    let _busy = BusyGuard::start();
+    
+    if input_paths.len() < 2 {
+        return Err("Select at least two videos to combine".to_string());
+    }
+    let output_format = output_format.to_lowercase();
+    
+    let mut clips = Vec::new();
+    for path in &input_paths {
+        let info = probe_media(path.clone()).await?;
+        if info.video.is_none() {
+            return Err(format!("{} has no video stream", path));
+        }
+        clips.push(info);
+    }
+    let stream_copy = conversion::can_concat_videos_by_copy(&clips, &output_format);
+    let with_audio = clips.iter().all(|clip| clip.audio.is_some());
+    let durations: Vec<f64> = clips.iter().map(|clip| clip.duration_secs.unwrap_or(0.0)).collect();
+    
+    let input_paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input_paths[0]
+            .parent()
+            .ok_or("Could not determine output directory")?
+            .to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let first_file_stem = input_paths[0]
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("combined");
+    let reservation = reserve_output_path(&output_dir, &format!("{}_combined", first_file_stem), &output_format);
+    
+    let tool_path = get_tool_path("ffmpeg")
+        .map_err(|e| format!("FFmpeg is required to combine videos: {}", e))?;
+    let mut command = create_command(&tool_path);
+    let job_dir = JobDir::create()?;
+    if stream_copy {
+        let list_path = job_dir.path.join("inputs.txt");
+        let absolute: Vec<String> = input_paths.iter()
+            .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone()).to_string_lossy().to_string())
+            .collect();
+        std::fs::write(&list_path, conversion::concat_list(&absolute))
+            .map_err(|e| format!("Failed to write concat list: {}", e))?;
+        command.args(["-f", "concat", "-safe", "0", "-i"]).arg(&list_path).args(["-c", "copy"]);
+    } else {
+        // Everything is fitted onto the first clip's frame
+        let first = clips[0].video.as_ref().expect("checked above");
+        let width = first.width.unwrap_or(1280);
+        let height = first.height.unwrap_or(720);
+        let fps = first.frame_rate.filter(|fps| *fps > 0.0).unwrap_or(30.0) as f32;
+        for path in &input_paths {
+            command.arg("-i").arg(path);
+        }
+        let graph = conversion::concat_reencode_graph(clips.len(), width, height, fps, with_audio);
+        command.arg("-filter_complex").arg(graph.render()).args(["-map", "[v]"]);
+        if with_audio {
+            command.args(["-map", "[a]"]);
+        }
+        command.args(conversion::concat_video_codec_args(&output_format));
+    }
+    if matches!(output_format.as_str(), "mp4" | "m4v" | "mov") {
+        command.args(["-movflags", "+faststart"]);
+    }
+    command.args(["-progress", "pipe:1", "-nostats", "-y"]).arg(&reservation.path);
+    let (mut command, _) = ffmpeg_with_encoders(command, tool_path)?;
+    
+    debug!("Executing command: {:?}", command);
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    
+    // Drain stderr on its own thread so a chatty FFmpeg can't fill the pipe and stall
+    let mut stderr_pipe = child.stderr.take().ok_or("Failed to read FFmpeg output")?;
+    let stderr_reader = std::thread::spawn(move || {
+        let mut stderr = String::new();
+        let _ = stderr_pipe.read_to_string(&mut stderr);
+        stderr
+    });
+    
+    let emit_segment = |segment: usize| {
+        app.emit("concat-progress", ConcatProgress {
+            segment: segment + 1,
+            total: input_paths.len(),
+            input_path: input_paths[segment].to_string_lossy().to_string(),
+            stream_copy,
+        }).ok();
+    };
+    emit_segment(0);
+    let mut current = 0;
+    if let Some(stdout) = child.stdout.take() {
+        for line in std::io::BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(elapsed) = conversion::parse_progress_time(&line) {
+                let segment = conversion::concat_segment_at(&durations, elapsed);
+                if segment > current {
+                    current = segment;
+                    emit_segment(segment);
+                }
+            }
+        }
+    }
+    
+    let status = child.wait().map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    if !status.success() {
+        error!("Combining videos failed - stderr: {}", stderr);
+        let _ = std::fs::remove_file(&reservation.path);
+        return Err(format!("Failed to combine videos: {}", stderr));
+    }
+    
+    info!(
+        "Combined video written to {} ({})",
+        reservation.path.display(),
+        if stream_copy { "stream copy" } else { "re-encoded" }
+    );
+    Ok(reservation.path.to_string_lossy().to_string())
+}
+
+/// Combine documents (DOCX, spreadsheets, slides, images, PDFs, ...) into one PDF, in the
+/// order given. Anything that isn't already a PDF is converted with the tool convert_file
+/// would use, then Ghostscript joins the pages. Emits "merge-progress" per document.
+#[tauri::command]
+async fn merge_documents(
+    app: AppHandle,
+    input_paths: Vec<String>,
+    output_directory: Option<String>,
+) -> Result<String, String> {
+    info!("Merging {} documents into a PDF", input_paths.len());
+    let _busy = BusyGuard::start();
+    
+    if input_paths.len() < 2 {
+        return Err("Select at least two documents to merge".to_string());
+    }
+    let input_paths: Vec<PathBuf> = input_paths.iter().map(PathBuf::from).collect();
+    let mut input_exts = Vec::new();
+    for path in &input_paths {
+        if !path.exists() {
+            return Err(format!("Input file not found: {}", path.display()));
+        }
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+        if ext != "pdf" && conversion_tool_for(&ext, "pdf", &ConversionSettings::default()).is_none() {
+            return Err(format!("{} can't be converted to PDF, so it can't be merged", path.display()));
+        }
+        input_exts.push(ext);
+    }
+    let gs_path = get_tool_path("ghostscript").map_err(|_| {
+        "Ghostscript is required to merge PDFs but is not installed.\n\n\
+        Please install Ghostscript from the Tools Manager in Settings.".to_string()
+    })?;
+    
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input_paths[0]
+            .parent()
+            .ok_or("Could not determine output directory")?
+            .to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let first_file_stem = input_paths[0]
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("merged");
+    let reservation = reserve_output_path(&output_dir, &format!("{}_merged", first_file_stem), "pdf");
+    
+    // Converted parts are numbered, so documents with the same name can't collide
+    let job_dir = JobDir::create()?;
+    let settings = ConversionSettings::default();
+    let mut parts = Vec::new();
+    for (index, (path, ext)) in input_paths.iter().zip(&input_exts).enumerate() {
+        app.emit("merge-progress", MergeProgress {
+            current: index + 1,
+            total: input_paths.len(),
+            input_path: path.to_string_lossy().to_string(),
+        }).ok();
+        
+        if ext == "pdf" {
+            parts.push(path.to_string_lossy().to_string());
+            continue;
+        }
+        let tool = conversion_tool_for(ext, "pdf", &settings).expect("checked above");
+        let part = job_dir.path.join(format!("part-{:03}.pdf", index + 1));
+        execute_conversion(tool, path, &part, None, &settings).await
+            .map_err(|e| format!("Failed to convert {} to PDF: {}", path.display(), e))?;
+        parts.push(part.to_string_lossy().to_string());
+    }
+    
+    let mut command = create_command(&gs_path);
+    command.args(conversion::pdf_merge_args(&parts, &reservation.path.to_string_lossy()));
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute ghostscript: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        error!("Merging PDFs failed - stderr: {}", stderr);
+        let _ = std::fs::remove_file(&reservation.path);
+        return Err(format!("Failed to merge documents. Error details: {}{}", stdout, stderr));
+    }
+    
+    info!("Merged {} documents into {}", input_paths.len(), reservation.path.display());
+    Ok(reservation.path.to_string_lossy().to_string())
+}
+
+/// Saves frames of a video as numbered images (frame_00001.png, ...) in a new
+/// "<name>_frames" folder: evenly spaced at `fps` per second, or one at each of `timestamps`
+#[tauri::command]
+async fn extract_frames(
+    input_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    fps: Option<f32>,
+    timestamps: Option<Vec<f64>>,
+) -> Result<FrameExtractionResult, String> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let output_format = match output_format.to_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        other => other.to_string(),
+    };
+    if !conversion::FRAME_OUTPUTS.contains(&output_format.as_str()) {
+        return Err(format!("Frames can only be saved as {}", conversion::FRAME_OUTPUTS.join(" or ").to_uppercase()));
+    }
+    let selection = FrameSelection::from_request(fps, timestamps)?;
+    
+    let base_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&base_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    let frames_dir = create_unique_output_dir(&base_dir, &format!("{}_frames", file_stem))?;
+    info!("Extracting frames from {} into {}", input_path, frames_dir.display());
+    
+    let tool_path = get_tool_path("ffmpeg")
+        .map_err(|e| format!("FFmpeg is required to extract frames: {}", e))?;
+    let run = |mut command: Command| -> Result<(), String> {
+        debug!("Executing command: {:?}", command);
+        let output = command.output()
+            .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            error!("Frame extraction failed - stderr: {}", stderr);
+            Err(format!("Failed to extract frames: {}", stderr))
+        }
+    };
+    
+    let mut frames = Vec::new();
+    let mut skipped_timestamps = Vec::new();
+    let result = match selection {
+        FrameSelection::Fps(fps) => {
+            let mut command = create_command(&tool_path);
+            command.arg("-i").arg(&input)
+                .args(conversion::extract_frames_args(Some(fps), &output_format))
+                .arg(frames_dir.join(format!("frame_%05d.{}", output_format)));
+            run(command).map(|()| {
+                // FFmpeg numbers the frames itself; count up until the sequence ends
+                frames.extend(
+                    (1..)
+                        .map(|index| frames_dir.join(conversion::frame_file_name(index, &output_format)))
+                        .take_while(|path| path.exists())
+                        .map(|path| path.to_string_lossy().to_string()),
+                );
+            })
+        }
+        FrameSelection::Timestamps(timestamps) => {
+            // A fast seek per frame beats decoding the whole video for a handful of frames
+            timestamps.into_iter().try_for_each(|timestamp| {
+                let output = frames_dir.join(conversion::frame_file_name(frames.len() + 1, &output_format));
+                let mut command = create_command(&tool_path);
+                command.arg("-ss").arg(format!("{:.3}", timestamp)).arg("-i").arg(&input)
+                    .args(conversion::extract_frames_args(None, &output_format))
+                    .arg("-y").arg(&output);
+                run(command)?;
+                // Seeking past the end succeeds without writing anything
+                if output.exists() {
+                    frames.push(output.to_string_lossy().to_string());
+                } else {
+                    warn!("No frame at {}s in {}", timestamp, input_path);
+                    skipped_timestamps.push(timestamp);
+                }
+                Ok::<(), String>(())
+            })
+        }
+    };
+    
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(&frames_dir);
+        return Err(e);
+    }
+    if frames.is_empty() {
+        let _ = std::fs::remove_dir_all(&frames_dir);
+        return Err("No frames were extracted. The times may be past the end of the video.".to_string());
+    }
+    
+    info!("Extracted {} frames into {}", frames.len(), frames_dir.display());
+    Ok(FrameExtractionResult {
+        output_directory: frames_dir.to_string_lossy().to_string(),
+        frames,
+        skipped_timestamps,
+    })
+}
+
+/// Tiles `columns` x `rows` frames sampled evenly across a video into one image
+/// ("<name>_contact_sheet.jpg"), optionally with each frame's time burned in
+#[tauri::command]
+async fn generate_contact_sheet(
+    input_path: String,
+    output_directory: Option<String>,
+    columns: Option<u32>,
+    rows: Option<u32>,
+    tile_width: Option<u32>,
+    show_timestamps: bool,
+    output_format: Option<String>,
+) -> Result<String, String> {
+    let input = PathBuf::from(&input_path);
+    let columns = columns.unwrap_or(4);
+    let rows = rows.unwrap_or(4);
+    if !(1..=conversion::MAX_CONTACT_SHEET_SIDE).contains(&columns) || !(1..=conversion::MAX_CONTACT_SHEET_SIDE).contains(&rows) {
+        return Err(format!("Columns and rows must be between 1 and {}", conversion::MAX_CONTACT_SHEET_SIDE));
+    }
+    // Even widths keep the tiles valid for yuv420p encoders
+    let tile_width = tile_width.unwrap_or(320).clamp(64, 1920) / 2 * 2;
+    let output_format = match output_format.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("jpg") | Some("jpeg") => "jpg".to_string(),
+        Some("png") => "png".to_string(),
+        Some(other) => return Err(format!("Contact sheets can be saved as JPG or PNG, not {}", other.to_uppercase())),
+    };
+    
+    let info = probe_media(input_path.clone()).await?;
+    if info.video.is_none() {
+        return Err(format!("{} has no video stream", input_path));
+    }
+    let duration = info.duration_secs.filter(|d| *d > 0.0)
+        .ok_or("Could not determine the video's length")?;
+    
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    let reservation = reserve_output_path(&output_dir, &format!("{}_contact_sheet", file_stem), &output_format);
+    
+    let font_file = drawtext_font_file();
+    let filters = conversion::contact_sheet_filters(duration, columns, rows, tile_width, show_timestamps, font_file.as_deref());
+    
+    let tool_path = get_tool_path("ffmpeg")
+        .map_err(|e| format!("FFmpeg is required to make contact sheets: {}", e))?;
+    let mut command = create_command(&tool_path);
+    command.arg("-i").arg(&input)
+        .arg("-vf").arg(filter_graph::chain(&filters))
+        .args(["-frames:v", "1"]);
+    if output_format == "jpg" {
+        command.args(["-q:v", "2"]);
+    }
+    command.arg("-y").arg(&reservation.path);
+    
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !output.status.success() || !reservation.path.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Contact sheet failed - stderr: {}", stderr);
+        let _ = std::fs::remove_file(&reservation.path);
+        return Err(format!("Failed to create contact sheet: {}", stderr));
+    }
+    
+    info!("Contact sheet written to {}", reservation.path.display());
+    Ok(reservation.path.to_string_lossy().to_string())
+}
+
+/// Saves subtitle tracks of a video as SRT or ASS files next to it (or in
+/// `output_directory`), e.g. "film.eng.srt". Every text track is extracted when
+/// `track_indices` (positions among the subtitle tracks) isn't given.
+#[tauri::command]
+async fn extract_subtitles(
+    input_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    track_indices: Option<Vec<u32>>,
+) -> Result<Vec<String>, String> {
+    let input = PathBuf::from(&input_path);
+    let output_format = output_format.to_lowercase();
+    if !conversion::SUBTITLE_OUTPUTS.contains(&output_format.as_str()) {
+        return Err(format!("Subtitles can be saved as {}", conversion::SUBTITLE_OUTPUTS.join(" or ").to_uppercase()));
+    }
+    
+    let info = probe_media(input_path.clone()).await?;
+    if info.subtitles.is_empty() {
+        return Err("This file has no subtitle tracks".to_string());
+    }
+    let tracks: Vec<&SubtitleStreamInfo> = match &track_indices {
+        Some(indices) => {
+            let tracks: Vec<_> = info.subtitles.iter().filter(|track| indices.contains(&track.index)).collect();
+            if tracks.len() != indices.len() {
+                return Err(format!("This file has {} subtitle tracks", info.subtitles.len()));
+            }
+            if let Some(bitmap) = tracks.iter().find(|track| !conversion::is_text_subtitle_codec(&track.codec)) {
+                return Err(format!(
+                    "Track {} is a picture-based subtitle ({}) and can't be saved as text",
+                    bitmap.index + 1, bitmap.codec
+                ));
+            }
+            tracks
+        }
+        None => info.subtitles.iter().filter(|track| conversion::is_text_subtitle_codec(&track.codec)).collect(),
+    };
+    if tracks.is_empty() {
+        return Err("This file only has picture-based subtitles, which can't be saved as text".to_string());
+    }
+    
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("subtitles");
+    let tool_path = get_tool_path("ffmpeg")
+        .map_err(|e| format!("FFmpeg is required to extract subtitles: {}", e))?;
+    
+    let mut written = Vec::new();
+    for track in tracks {
+        let same_language = info.subtitles.iter().filter(|other| other.language == track.language).count();
+        let name = conversion::subtitle_file_name(file_stem, track, same_language, &output_format);
+        let stem = name.strip_suffix(&format!(".{}", output_format)).unwrap_or(&name);
+        let reservation = reserve_output_path(&output_dir, stem, &output_format);
+        
+        let mut command = create_command(&tool_path);
+        command.arg("-i").arg(&input)
+            .args(conversion::extract_subtitle_args(track.index, &output_format))
+            .arg("-y").arg(&reservation.path);
+        debug!("Executing command: {:?}", command);
+        let output = command.output()
+            .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            error!("Subtitle extraction failed - stderr: {}", stderr);
+            let _ = std::fs::remove_file(&reservation.path);
+            return Err(format!("Failed to extract subtitle track {}: {}", track.index + 1, stderr));
+        }
+        written.push(reservation.path.to_string_lossy().to_string());
+    }
+    
+    info!("Extracted {} subtitle tracks from {}", written.len(), input_path);
+    Ok(written)
+}
+
+/// The video of a Live Photo or motion photo as a file: the paired MOV, or the embedded
+/// MP4 written into `job_dir`
+fn live_photo_video(photo: &PathBuf, job_dir: &JobDir) -> Result<PathBuf, String> {
+    match live_photo::find_live_video(photo)? {
+        Some(LiveVideo::Paired(video)) => Ok(video),
+        Some(LiveVideo::Embedded(range)) => {
+            let data = std::fs::read(photo).map_err(|e| format!("Failed to read photo: {}", e))?;
+            let video = job_dir.path.join("motion.mp4");
+            std::fs::write(&video, &data[range]).map_err(|e| format!("Failed to write video: {}", e))?;
+            Ok(video)
+        }
+        None => Err("This photo has no Live Photo or motion photo video".to_string()),
+    }
+}
+
+/// Saves the video of a Live Photo (the MOV next to it) or motion photo (the MP4 inside
+/// it) as "<name>_video.mov/.mp4" next to the photo or in `output_directory`
+#[tauri::command]
+async fn extract_live_photo_video(input_path: String, output_directory: Option<String>) -> Result<String, String> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("photo");
+    
+    let job_dir = JobDir::create()?;
+    let video = live_photo_video(&input, &job_dir)?;
+    let extension = video.extension().and_then(|ext| ext.to_str()).unwrap_or("mp4").to_lowercase();
+    let reservation = reserve_output_path(&output_dir, &format!("{}_video", file_stem), &extension);
+    std::fs::copy(&video, &reservation.path)
+        .map_err(|e| format!("Failed to save video: {}", e))?;
+    
+    info!("Saved the video of {} as {}", input_path, reservation.path.display());
+    Ok(reservation.path.to_string_lossy().to_string())
+}
+
+/// Converts the video of a Live Photo or motion photo into one animated GIF or MP4, named
+/// after the photo
+#[tauri::command]
+async fn convert_live_photo(
+    input_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<ConversionResult, String> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let output_format = output_format.to_lowercase();
+    if !live_photo::LIVE_PHOTO_OUTPUTS.contains(&output_format.as_str()) {
+        return Err(format!("Live Photos can be converted to {}", live_photo::LIVE_PHOTO_OUTPUTS.join(" or ").to_uppercase()));
+    }
+    let settings = settings.unwrap_or_default();
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("photo");
+    
+    let started = std::time::Instant::now();
+    let job_dir = JobDir::create()?;
+    let video = live_photo_video(&input, &job_dir)?;
+    let reservation = reserve_output_path(&output_dir, file_stem, &output_format);
+    info!("Converting the video of {} to {}", input_path, output_format.to_uppercase());
+    let run = execute_conversion("ffmpeg", &video, &reservation.path, None, &settings).await?;
+    
+    let mut result = conversion_stats(&input, reservation.path.to_string_lossy().to_string(), started, Some(run.command_line));
+    result.tool_warnings = run.tool_warnings;
+    Ok(result)
+}
+
+/// Display names and colors for formats, so the frontend doesn't keep its own copy
+#[tauri::command]
+fn get_format_details(formats: Vec<String>) -> Vec<FormatDetails> {
+    formats.iter().map(|format| conversion::format_details(format)).collect()
+}
+
+#[tauri::command]
+async fn get_file_info(path: String) -> Result<serde_json::Value, String> {
+    let path = PathBuf::from(&path);
+    let metadata = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    
+    let file_name = path.file_name()
+        .ok_or("Could not get file name")?
+        .to_str()
+        .ok_or("Invalid file name")?;
+    
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    
+    let format_check = check_input_format(&path, None);
+    if let Some(warning) = format_check.warning() {
+        info!("{}: {}", path.display(), warning);
+    }
+    
+    let file_info = serde_json::json!({
+        "name": file_name,
+        "size": metadata.len(),
+        "extension": extension,
+        // What to offer conversions for: the extension, unless the contents say otherwise
+        "format": format_check.format,
+        "detected_format": format_check.detected,
+        "format_mismatch": format_check.mismatch
+    });
+    
+    Ok(file_info)
+}
+
+/// Rejects an `input_format` the app doesn't convert from. It ends up as the extension of
+/// the staged input, so anything else (e.g. a path) never gets that far.
+fn validate_input_format(input_format: Option<&str>) -> Result<(), String> {
+    match input_format.map(conversion::normalize_extension) {
+        Some(format) if !format.is_empty() && !conversion::is_supported_input(&format) => {
+            Err(format!("Converting from {} isn't supported", format.to_uppercase()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The input's extension checked against its first bytes (see signature), or `declared`
+/// when the caller gave the format. A file that can't be read is taken by its extension.
+fn check_input_format(input_path: &PathBuf, declared: Option<&str>) -> FormatCheck {
+    use std::io::Read;
+    let extension = input_path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let mut header = Vec::with_capacity(signature::SNIFF_BYTES);
+    if let Err(e) = std::fs::File::open(input_path).and_then(|file| file.take(signature::SNIFF_BYTES as u64).read_to_end(&mut header)) {
+        debug!("Could not read the start of {}: {}", input_path.display(), e);
+    }
+    let check = signature::check_format(extension, &header);
+    match declared.filter(|format| !format.trim_start_matches('.').is_empty()) {
+        Some(format) => check.declare(format),
+        None => check,
+    }
+}
+
+/// Inspect a media file: duration, resolution, codecs, frame rate, bit depth, channels and container tags.
+/// Uses ffprobe when it sits next to FFmpeg, otherwise parses the `ffmpeg -i` banner.
+#[tauri::command]
+async fn probe_media(path: String) -> Result<MediaInfo, String> {
+    inspect_media(&PathBuf::from(&path))
+}
+
+/// Every stream of a media file, for choosing the audio and subtitle tracks to keep
+#[tauri::command]
+async fn get_streams(path: String) -> Result<Vec<StreamInfo>, String> {
+    inspect_media(&PathBuf::from(&path)).map(|info| info.streams)
+}
+
+/// Reads stream details with ffprobe, or from FFmpeg's banner when ffprobe isn't installed
+fn inspect_media(input_path: &PathBuf) -> Result<MediaInfo, String> {
+    if !input_path.exists() {
+        return Err(format!("File not found: {}", input_path.display()));
+    }
+    
+    let ffmpeg_path = get_tool_path("ffmpeg")
+        .map_err(|_| "FFmpeg is required to inspect media files. Please install it from the Tools Manager in Settings.".to_string())?;
+    
+    // Full FFmpeg builds (and Homebrew) ship ffprobe alongside ffmpeg
+    let ffprobe_path = ffmpeg_path.with_file_name(if cfg!(windows) { "ffprobe.exe" } else { "ffprobe" });
+    if ffprobe_path.exists() {
+        let output = create_command(&ffprobe_path)
+            .arg("-v").arg("error")
+            .arg("-print_format").arg("json")
+            .arg("-show_format")
+            .arg("-show_streams")
+            .arg("-show_chapters")
+            .arg(input_path)
+            .output()
+            .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+        
+        if output.status.success() {
+            match serde_json::from_slice::<serde_json::Value>(&output.stdout) {
+                Ok(json) => return Ok(probe::parse_ffprobe_json(&json)),
+                Err(e) => warn!("Failed to parse ffprobe output, falling back to ffmpeg: {}", e),
+            }
+        } else {
+            warn!("ffprobe failed, falling back to ffmpeg: {}", String::from_utf8_lossy(&output.stderr));
+        }
+    }
+    
+    // FFmpeg exits with an error when given no output, but still prints the stream info
+    let output = create_command(&ffmpeg_path)
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(input_path)
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    
+    let info = probe::parse_ffmpeg_banner(&stderr);
+    if info.video.is_none() && info.audio.is_none() {
+        return Err("No audio or video streams found. The file may be damaged or not a media file.".to_string());
+    }
+    Ok(info)
+}
+
+/// Built-in presets (format + settings) the frontend offers for audio outputs
+#[tauri::command]
+fn get_audio_presets() -> Vec<AudioPreset> {
+    conversion::builtin_audio_presets()
+}
+
+/// Read the EXIF/IPTC/XMP metadata embedded in an image.
+/// Uses exiftool when it's installed (it knows far more tags), otherwise `magick identify -verbose`.
+#[tauri::command]
+async fn read_image_metadata(path: String) -> Result<ImageMetadata, String> {
+    let input_path = PathBuf::from(&path);
+    if !input_path.exists() {
+        return Err(format!("File not found: {}", path));
+    }
+    
+    // exiftool isn't a managed tool, so only use it if it's on the PATH
+    if let Ok(output) = create_command("exiftool").arg("-json").arg("-G").arg(&input_path).output() {
+        if output.status.success() {
+            match serde_json::from_slice::<serde_json::Value>(&output.stdout) {
+                Ok(json) => return Ok(ImageMetadata::new("exiftool", metadata::parse_exiftool_json(&json))),
+                Err(e) => warn!("Failed to parse exiftool output, falling back to ImageMagick: {}", e),
+            }
+        }
+    }
+    
+    let tool_path = get_tool_path("imagemagick")
+        .map_err(|_| "ImageMagick is required to read image metadata. Please install it from the Tools Manager in Settings.".to_string())?;
+    
+    // Only the first frame - animations would repeat the block for every frame
+    let mut job_dir = None;
+    let mut command = create_command(&tool_path);
+    command.arg("identify").arg("-verbose").arg(imagemagick_input(&input_path, Some(0), &mut job_dir)?);
+    run_in_job_dir(&mut command, &job_dir);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read image metadata: {}", stderr));
+    }
+    
+    let tags = metadata::parse_identify_verbose(&String::from_utf8_lossy(&output.stdout));
+    Ok(ImageMetadata::new("imagemagick", tags))
+}
+
+/// Subfolder an image is filed in by `by`. Unreadable metadata files it as undated or
+/// unknown camera rather than failing the conversion.
+async fn filing_folder(input_path: &PathBuf, by: FileBy) -> PathBuf {
+    let metadata = read_image_metadata(input_path.to_string_lossy().to_string()).await.unwrap_or_else(|e| {
+        warn!("Could not read the metadata of {} for filing: {}", input_path.display(), e);
+        ImageMetadata::default()
+    });
+    let folder = metadata.filing_folder(by);
+    debug!("Filing {} in {}", input_path.display(), folder.display());
+    folder
+}
+
+#[tauri::command]
+async fn test_directories() -> Result<serde_json::Value, String> {
+    let mut info = serde_json::Map::new();
+    
+    if let Some(docs) = dirs::document_dir() {
+        info.insert("documents".to_string(), serde_json::Value::String(docs.to_string_lossy().to_string()));
+    } else {
+        info.insert("documents".to_string(), serde_json::Value::Null);
+    }
+    
+    if let Some(home) = dirs::home_dir() {
+        info.insert("home".to_string(), serde_json::Value::String(home.to_string_lossy().to_string()));
+    } else {
+        info.insert("home".to_string(), serde_json::Value::Null);
+    }
+    
+    if let Ok(current) = std::env::current_dir() {
+        info.insert("current".to_string(), serde_json::Value::String(current.to_string_lossy().to_string()));
+    } else {
+        info.insert("current".to_string(), serde_json::Value::Null);
+    }
+    
+    Ok(serde_json::Value::Object(info))
+}
+
+#[tauri::command]
+async fn open_folder(path: String) -> Result<(), String> {
+    let path = PathBuf::from(path);
+    
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, if it's a file, use /select, to open Explorer and highlight the file
+        // If it's a folder, just open the folder normally
+        if path.is_file() {
+            create_command("explorer")
+                .arg("/select,")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        } else {
+            create_command("explorer")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        }
+    }
+    
+    #[cfg(target_os = "macos")]
+    {
+        // On macOS, use -R flag to reveal the file in Finder
+        if path.is_file() {
+            create_command("open")
+                .arg("-R")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        } else {
+            create_command("open")
+                .arg(&path)
+                .spawn()
+                .map_err(|e| format!("Failed to open folder: {}", e))?;
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        // On Linux, most file managers don't have a standard "reveal" option
+        // So we open the parent folder
+        let folder = if path.is_file() {
+            path.parent().ok_or("Could not find parent folder")?
+        } else {
+            &path
+        };
+        
+        create_command("xdg-open")
+            .arg(folder)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+/// Pick the tool for a conversion, falling back to the built-in converter for common
+/// image pairs when neither ImageMagick nor FFmpeg is installed
+fn determine_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'static str> {
+    let tool = external_conversion_tool(input_ext, output_ext);
+    conversion::builtin_fallback(tool, input_ext, output_ext, |tool| get_tool_path(tool).is_ok())
+}
+
+fn external_conversion_tool(input_ext: &str, output_ext: &str) -> Option<&'static str> {
+    // JPG <-> JPEG simple rename (no conversion needed, same format)
+    if (input_ext == "jpg" && output_ext == "jpeg") || (input_ext == "jpeg" && output_ext == "jpg") {
+        return Some("rename");
+    }
+
+    // Calendars and contacts are converted in-process (see convert_pim)
+    if pim::supports_pim_conversion(input_ext, output_ext) {
+        return Some(conversion::BUILTIN_TOOL);
+    }
+    
+    // Animated formats keep every frame (e.g. GIF -> MP4, MP4 -> APNG)
+    if let Some(tool) = conversion::animated_conversion_tool(input_ext, output_ext) {
+        return Some(tool);
+    }
+    
+    // Image conversions - ImageMagick supports the widest range of formats
+    // FFmpeg is used as fallback for some formats
+    let image_inputs = [
+        // Standard/Common formats
+        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
+        // Modern formats
+        "heic", "heif", "avif", "jxl",
+        // Professional/High-end formats
+        "tga", "exr", "hdr", "dpx", "pfm", "psd", "psb",
+        // JPEG variants
+        "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
+        // Legacy/Specialized formats
+        "pcx", "ico", "sgi", "sun", "ras", "pict", "pct",
+        // Raw/Uncompressed formats
+        "ppm", "pgm", "pbm", "pam", "pnm", "pfm",
+        // X Window System formats
+        "xbm", "xpm", "xwd",
+        // Gaming/3D formats
+        "dds", "tga", "vtf",
+        // Vector/Document formats (rasterized)
+        "svg", "svgz", "ai", "eps", "ps", "pdf",
+        // Digital camera RAW formats
+        "arw", "cr2", "cr3", "crw", "dng", "nef", "nrw", "orf", "raf", "raw", "rw2", "rwl", "srw",
+        // Animation formats
+        "mng", "apng",
+        // Windows formats
+        "cur", "dib", "emf", "wmf",
+        // Adobe formats
+        "psd", "psb", "ai",
+        // Other formats
+        "fits", "flif", "jbig", "jng", "miff", "otb", "pal", "palm", "pam", "pcd", "pict", 
+        "pix", "plasma", "pnm", "pwp", "rgf", "sfw", "sgi", "sun", "tga", "uyvy", "vicar", 
+        "viff", "wbmp", "xbm", "xcf", "xpm", "xv", "yuv"
+    ];
+    let image_outputs_ffmpeg = [
+        // Standard formats
+        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
+        // Modern formats (AVIF only, HEIC/HEIF use ImageMagick)
+        "avif",
+        // Professional/High-end formats
+        "tga", "exr", "hdr", "dpx", "pfm",
+        // JPEG 2000
+        "j2k", "jp2",
+        // Legacy/Specialized formats
+        "pcx", "ico", "sgi", "sun",
+        // Raw/Uncompressed formats
+        "ppm", "pgm", "pbm", "pam",
+        // Gaming/3D formats
+        "dds"
+        // Note: X Window System formats (xbm, xpm, xwd) require ImageMagick
+    ];
+    let image_outputs_imagemagick = [
+        // Standard/Common formats
+        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp",
+        // Modern formats - ImageMagick supports HEIC/HEIF encoding
+        "heic", "heif", "avif", "jxl",
+        // Professional/High-end formats
+        "tga", "exr", "hdr", "dpx", "pfm", "psd", "psb",
+        // JPEG variants
+        "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
+        // Legacy/Specialized formats
+        "pcx", "ico", "sgi", "sun", "ras", "pict", "pct",
+        // Raw/Uncompressed formats
+        "ppm", "pgm", "pbm", "pam", "pnm",
+        // X Window System formats
+        "xbm", "xpm", "xwd",
+        // Gaming/3D formats
+        "dds", "tga", "vtf",
+        // Vector/Document formats (rasterized)
+        "svg", "svgz", "pdf",
+        // Animation formats
+        "mng", "apng",
+        // Windows formats
+        "cur", "dib", "emf", "wmf",
+        // Adobe formats
+        "psd", "psb",
+        // Other formats
+        "fits", "jbig", "jng", "miff", "otb", "pal", "palm", "pcd", "pict", 
+        "pix", "plasma", "sfw", "wbmp", "xcf", "xv", "yuv"
+    ];
+    
+    // Video/Audio conversions
+    let video_inputs = ["mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v", "mpg", "mpeg", "3gp"];
+    let audio_inputs = ["mp3", "wav", "flac", "ogg", "m4a", "wma", "aac"];
+    let av_outputs = ["mp4", "mov", "avi", "mkv", "webm", "mp3", "wav", "flac", "ogg", "m4a", "aac", "gif"];
+    
+    // Document conversions
+    // Note: PDF output requires LaTeX (not included), so it's removed from Pandoc outputs
+    let doc_inputs = ["md", "markdown", "txt", "html", "htm", "docx", "odt", "rtf", "tex", "latex", "epub", "rst"];
+    let doc_outputs = ["md", "html", "docx", "odt", "rtf", "tex", "epub", "txt"];
+    
+    let office_inputs = ["doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "rtf"];
+    let office_outputs = ["pdf", "html", "txt", "docx", "odt", "rtf"];
+    
+    // Use ffmpeg for media and image conversions
+    if (video_inputs.contains(&input_ext) || audio_inputs.contains(&input_ext)) && av_outputs.contains(&output_ext) {
+        Some("ffmpeg")
+    } else if image_inputs.contains(&input_ext) && (output_ext == "heic" || output_ext == "heif") {
+        // HEIC/HEIF encoding requires ImageMagick
+        Some("imagemagick")
+    } else if image_inputs.contains(&input_ext) && (output_ext == "xbm" || output_ext == "xpm" || output_ext == "xwd") {
+        // X Window System formats require ImageMagick (FFmpeg doesn't support them properly)
+        Some("imagemagick")
+    } else if image_inputs.contains(&input_ext) && image_outputs_imagemagick.contains(&output_ext) {
+        // Try ImageMagick first for image conversions, but will fallback to FFmpeg if not available
+        Some("imagemagick")
+    } else if image_inputs.contains(&input_ext) && image_outputs_ffmpeg.contains(&output_ext) {
+        // Fallback to ffmpeg for formats ImageMagick doesn't support well
+        Some("ffmpeg")
+    } else if let Some(tool) = conversion::ebook_conversion_tool(input_ext, output_ext) {
+        // E-books via Pandoc (when enabled) or Calibre
+        Some(tool)
+    } else if conversion::pandoc_enabled() && doc_inputs.contains(&input_ext) && doc_outputs.contains(&output_ext) {
+        Some("pandoc")
+    } else if conversion::supports_html_snapshot(input_ext, output_ext) {
+        // HTML pages are rendered by headless Chromium
+        Some("chromium")
+    } else if email::supports_email_conversion(input_ext, output_ext) {
+        // Emails are laid out as HTML (see email::render_html) and printed by Chromium
+        Some("chromium")
+    } else if office_inputs.contains(&input_ext) && office_outputs.contains(&output_ext) {
+        Some("libreoffice")
+    } else if conversion::supports_vector_conversion(input_ext, output_ext) {
+        // Vector-only pairs (e.g. SVG -> EPS) have no raster path, so Inkscape handles them
+        Some("inkscape")
+    } else {
+        None
+    }
+}
+
+/// Find a tool's executable, from the cache while a batch is running
+fn get_tool_path(tool_name: &str) -> Result<PathBuf, String> {
+    if let Ok(cache) = TOOL_PATH_CACHE.lock() {
+        if let Some(path) = cache.1.get(tool_name) {
+            return Ok(path.clone());
+        }
+    }
+    
+    let path = find_tool_path(tool_name)?;
+    if let Ok(mut cache) = TOOL_PATH_CACHE.lock() {
+        if cache.0 > 0 {
+            cache.1.insert(tool_name.to_string(), path.clone());
+        }
+    }
+    Ok(path)
+}
+
+/// Keeps resolved tool paths cached until every running batch has dropped its guard
+struct ToolPathCache;
+
+impl ToolPathCache {
+    fn enable() -> Self {
+        if let Ok(mut cache) = TOOL_PATH_CACHE.lock() {
+            cache.0 += 1;
+        }
+        ToolPathCache
+    }
+}
+
+impl Drop for ToolPathCache {
+    fn drop(&mut self) {
+        if let Ok(mut cache) = TOOL_PATH_CACHE.lock() {
+            cache.0 = cache.0.saturating_sub(1);
+            if cache.0 == 0 {
+                cache.1.clear();
+            }
+        }
+    }
+}
+
+fn find_tool_path(tool_name: &str) -> Result<PathBuf, String> {
+    // Check for custom path first
+    if let Ok(mut config) = load_config() {
+        if let Some(path_str) = custom_tool_path(&config, tool_name).cloned() {
+            let path = PathBuf::from(&path_str);
+            info!("Checking custom path for {}: {}", tool_name, path.display());
+            if path.exists() {
+                info!("Using custom path for {}: {}", tool_name, path.display());
+                return Ok(path);
+            } else {
+                warn!("Custom path for {} no longer exists: {}. Clearing from config.", tool_name, path.display());
+                // Clear the invalid custom path from config
+                match tool_name {
+                    "ffmpeg" => config.ffmpeg_path = None,
+                    "pandoc" => config.pandoc_path = None,
+                    "imagemagick" => config.imagemagick_path = None,
+                    "inkscape" => config.inkscape_path = None,
+                    "chromium" => config.chromium_path = None,
+                    "libreoffice" => config.libreoffice_path = None,
+                    "calibre" => config.calibre_path = None,
+                    "ghostscript" => config.ghostscript_path = None,
+                    _ => {}
+                }
+                // Save the updated config (ignore errors as this is cleanup)
+                let _ = save_config(&config);
+            }
+        }
+    }
+    
+    let search = search_tool(tool_name)?;
+    match search.selected {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => {
+            let error_msg = search.not_found_message();
+            warn!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+/// Where `tool_name` is looked for and which copy is used, for diagnosing tool problems
+#[tauri::command]
+fn where_is_tool(name: String) -> Result<ToolSearch, String> {
+    let search = search_tool(&name)?;
+    info!("{}: {}", name, search.reason);
+    Ok(search)
+}
+
+/// Every place `tool_name` is looked for, in order, without changing the config
+fn search_tool(tool_name: &str) -> Result<ToolSearch, String> {
+    let mut candidates: Vec<(PathBuf, ToolSource, bool)> = Vec::new();
+    if let Some(path) = load_config().ok().and_then(|config| custom_tool_path(&config, tool_name).cloned()) {
+        let key = format!("{}_path", tool_name);
+        let from_machine = load_machine_config().values.get(&key).and_then(|value| value.as_str()) == Some(path.as_str());
+        let source = if from_machine { ToolSource::Config } else { ToolSource::Custom };
+        let path = PathBuf::from(path);
+        let exists = path.exists();
+        candidates.push((path, source, exists));
+    }
+    // Files only: with per-tool folders, "ffmpeg" can also be the folder itself
+    candidates.extend(tool_search_paths(tool_name)?.into_iter().map(|(path, source)| {
+        let exists = path.is_file();
+        (path, source, exists)
+    }));
+    Ok(tool_search::select(tool_name, candidates))
+}
+
+/// The path set for `tool_name` in the config, if any
+fn custom_tool_path<'a>(config: &'a ToolConfig, tool_name: &str) -> Option<&'a String> {
+    match tool_name {
+        "ffmpeg" => config.ffmpeg_path.as_ref(),
+        "pandoc" => config.pandoc_path.as_ref(),
+        "imagemagick" => config.imagemagick_path.as_ref(),
+        "inkscape" => config.inkscape_path.as_ref(),
+        "chromium" => config.chromium_path.as_ref(),
+        "libreoffice" => config.libreoffice_path.as_ref(),
+        "calibre" => config.calibre_path.as_ref(),
+        "ghostscript" => config.ghostscript_path.as_ref(),
+        _ => None,
+    }
+}
+
+/// The places `tool_name` is looked for after its custom path, in order
+fn tool_search_paths(tool_name: &str) -> Result<Vec<(PathBuf, ToolSource)>, String> {
+    let platform_name = if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else {
+        "linux"
+    };
+    
+    let exe_name = match tool_name {
+        "ffmpeg" => {
+            if cfg!(target_os = "windows") {
+                "ffmpeg.exe"
+            } else {
+                "ffmpeg"
+            }
+        }
+        "pandoc" if conversion::pandoc_enabled() => {
+            if cfg!(target_os = "windows") {
+                "pandoc.exe"
+            } else {
+                "pandoc"
+            }
+        }
+        "imagemagick" => {
+            if cfg!(target_os = "windows") {
+                "magick.exe"
+            } else {
+                "magick"
+            }
+        }
+        "inkscape" => {
+            if cfg!(target_os = "windows") {
+                "inkscape.exe"
+            } else {
+                "inkscape"
+            }
+        }
+        "chromium" => {
+            if cfg!(target_os = "windows") {
+                "chrome-headless-shell.exe"
+            } else {
+                "chrome-headless-shell"
+            }
+        }
+        "libreoffice" => {
+            if cfg!(target_os = "windows") {
+                "soffice.exe"
+            } else {
+                "soffice"
+            }
+        }
+        "calibre" => {
+            if cfg!(target_os = "windows") {
+                "ebook-convert.exe"
+            } else {
+                "ebook-convert"
+            }
+        }
+        "libraw" => {
+            // LibRaw's dcraw-compatible sample, shipped by the libraw tools packages
+            if cfg!(target_os = "windows") {
+                "dcraw_emu.exe"
+            } else {
+                "dcraw_emu"
+            }
+        }
+        "tesseract" => {
+            if cfg!(target_os = "windows") {
+                "tesseract.exe"
+            } else {
+                "tesseract"
+            }
+        }
+        "ghostscript" => {
+            // The console build; gswin64.exe opens a window for every run
+            if cfg!(target_os = "windows") {
+                "gswin64c.exe"
+            } else {
+                "gs"
+            }
+        }
+        _ => return Err(format!("Unknown tool: {}", tool_name)),
+    };
+    
+    // Try multiple possible locations
+    let mut candidates = vec![];
+    let mut possible_paths = vec![];
+    
+    // 1. App data directory (downloaded binaries, or bundled ones copied there on first
+    // run) - CHECK THIS FIRST
+    // NOTE: This must match the path used in download_ffmpeg/download_pandoc
+    // We can't use app.path().app_data_dir() here since we don't have AppHandle,
+    // so we manually construct the same path that Tauri uses
+    if let Some(data_dir) = dirs::data_dir() {
+        // Tauri's app_data_dir() uses: {data_dir}/{identifier}
+        // APP_IDENTIFIER is "com.convertsave" for prod, "com.convertsave.dev" for dev
+        // Each tool keeps the layout of its archive (ImageMagick on macOS uses bin/, see bundled_tools)
+        possible_paths.extend(bundled_tools::tool_candidates(&data_dir.join(APP_IDENTIFIER), tool_name, exe_name));
+    }
+    
+    candidates.extend(possible_paths.drain(..).map(|path| (path, ToolSource::AppData)));
+    
+    // 2. Project root tools directory (development only), per tool or loose
+    if let Ok(current) = std::env::current_dir() {
+        let tools_dir = current.join("tools").join(platform_name);
+        possible_paths.extend(bundled_tools::tool_candidates(&tools_dir, tool_name, exe_name));
+        possible_paths.push(tools_dir.join(exe_name));
+    }
+    
+    // 3. Check if we're in src-tauri directory during development
+    if let Ok(current) = std::env::current_dir() {
+        if let Some(parent) = current.parent() {
+            let tools_dir = parent.join("tools").join(platform_name);
+            possible_paths.extend(bundled_tools::tool_candidates(&tools_dir, tool_name, exe_name));
+            possible_paths.push(tools_dir.join(exe_name));
+        }
+    }
+    
+    candidates.extend(possible_paths.drain(..).map(|path| (path, ToolSource::Development)));
+    
+    // 4. On macOS, check Homebrew locations as fallback (Apple Silicon and Intel)
+    #[cfg(target_os = "macos")]
+    {
+        // Apple Silicon Homebrew
+        possible_paths.push(PathBuf::from("/opt/homebrew/bin").join(exe_name));
+        // Intel Homebrew
+        possible_paths.push(PathBuf::from("/usr/local/bin").join(exe_name));
+    }
+    
+    candidates.extend(possible_paths.drain(..).map(|path| (path, ToolSource::Homebrew)));
+    
+    // 5. Inkscape is commonly installed system-wide by its own installer
+    if tool_name == "inkscape" {
+        #[cfg(target_os = "windows")]
+        possible_paths.push(PathBuf::from(r"C:\Program Files\Inkscape\bin").join(exe_name));
+        #[cfg(target_os = "macos")]
+        possible_paths.push(PathBuf::from("/Applications/Inkscape.app/Contents/MacOS").join(exe_name));
+        #[cfg(target_os = "linux")]
+        possible_paths.push(PathBuf::from("/usr/bin").join(exe_name));
+    }
+    
+    // 6. Any installed Chrome, Chromium or Edge can render headless snapshots too
+    if tool_name == "chromium" {
+        #[cfg(target_os = "windows")]
+        {
+            possible_paths.push(PathBuf::from(r"C:\Program Files\Google\Chrome\Application\chrome.exe"));
+            possible_paths.push(PathBuf::from(r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe"));
+            possible_paths.push(PathBuf::from(r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe"));
+        }
+        #[cfg(target_os = "macos")]
+        {
+            possible_paths.push(PathBuf::from("/Applications/Google Chrome.app/Contents/MacOS/Google Chrome"));
+            possible_paths.push(PathBuf::from("/Applications/Chromium.app/Contents/MacOS/Chromium"));
+            possible_paths.push(PathBuf::from("/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge"));
+        }
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin/chromium"));
+            possible_paths.push(PathBuf::from("/usr/bin/chromium-browser"));
+            possible_paths.push(PathBuf::from("/usr/bin/google-chrome"));
+        }
+    }
+    
+    // 7. LibreOffice installed system-wide, per user, or as a snap/Flatpak
+    if tool_name == "libreoffice" {
+        #[cfg(target_os = "windows")]
+        {
+            possible_paths.push(PathBuf::from(r"C:\Program Files\LibreOffice\program").join(exe_name));
+            possible_paths.push(PathBuf::from(r"C:\Program Files (x86)\LibreOffice\program").join(exe_name));
+        }
+        #[cfg(target_os = "macos")]
+        {
+            possible_paths.push(PathBuf::from("/Applications/LibreOffice.app/Contents/MacOS").join(exe_name));
+            if let Some(home) = dirs::home_dir() {
+                possible_paths.push(home.join("Applications/LibreOffice.app/Contents/MacOS").join(exe_name));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin/soffice"));
+            possible_paths.push(PathBuf::from("/usr/bin/libreoffice"));
+            possible_paths.push(PathBuf::from("/usr/lib/libreoffice/program/soffice"));
+            possible_paths.push(PathBuf::from("/opt/libreoffice/program/soffice"));
+            possible_paths.push(PathBuf::from("/snap/bin/libreoffice"));
+            // Flatpak exports a launcher that forwards its arguments to soffice
+            possible_paths.push(PathBuf::from("/var/lib/flatpak/exports/bin/org.libreoffice.LibreOffice"));
+            if let Some(data_dir) = dirs::data_dir() {
+                possible_paths.push(data_dir.join("flatpak/exports/bin/org.libreoffice.LibreOffice"));
+            }
+        }
+    }
+    
+    // 8. Calibre's ebook-convert ships inside its own installation
+    if tool_name == "calibre" {
+        #[cfg(target_os = "windows")]
+        {
+            possible_paths.push(PathBuf::from(r"C:\Program Files\Calibre2").join(exe_name));
+            possible_paths.push(PathBuf::from(r"C:\Program Files (x86)\Calibre2").join(exe_name));
+        }
+        #[cfg(target_os = "macos")]
+        possible_paths.push(PathBuf::from("/Applications/calibre.app/Contents/MacOS").join(exe_name));
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin").join(exe_name));
+            // calibre's own installer puts everything in /opt/calibre
+            possible_paths.push(PathBuf::from("/opt/calibre").join(exe_name));
+        }
+    }
+    
+    // 9. Ghostscript's Windows installer uses a versioned folder (C:\Program Files\gs\gs10.04.0\bin)
+    if tool_name == "ghostscript" {
+        #[cfg(target_os = "windows")]
+        {
+            let gs_root = PathBuf::from(r"C:\Program Files\gs");
+            if let Ok(entries) = std::fs::read_dir(&gs_root) {
+                let names: Vec<String> = entries.flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect();
+                if let Some(latest) = conversion::latest_ghostscript_dir(names.iter().map(String::as_str)) {
+                    possible_paths.push(gs_root.join(latest).join("bin").join(exe_name));
+                }
+            }
+        }
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin").join(exe_name));
+            possible_paths.push(PathBuf::from("/usr/local/bin").join(exe_name));
+        }
+    }
+    
+    // 10. LibRaw's tools come from the distribution packages (libraw-bin, LibRaw)
+    if tool_name == "libraw" {
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin").join(exe_name));
+            possible_paths.push(PathBuf::from("/usr/local/bin").join(exe_name));
+        }
+    }
+    
+    // 11. Tesseract from its Windows installer (UB Mannheim builds) or the distribution
+    if tool_name == "tesseract" {
+        #[cfg(target_os = "windows")]
+        {
+            possible_paths.push(PathBuf::from(r"C:\Program Files\Tesseract-OCR").join(exe_name));
+            possible_paths.push(PathBuf::from(r"C:\Program Files (x86)\Tesseract-OCR").join(exe_name));
+        }
+        #[cfg(target_os = "linux")]
+        {
+            possible_paths.push(PathBuf::from("/usr/bin").join(exe_name));
+            possible_paths.push(PathBuf::from("/usr/local/bin").join(exe_name));
+        }
+    }
+    
+    candidates.extend(possible_paths.drain(..).map(|path| (path, ToolSource::System)));
+    
+    // On macOS, NEVER check inside the .app bundle - it's read-only and code-signed
+    // On Windows/Linux, we can check relative to executable for bundled binaries
+    #[cfg(not(target_os = "macos"))]
+    {
+        // Relative to executable (production)
+        if let Ok(exe) = std::env::current_exe() {
+            if let Some(parent) = exe.parent() {
+                possible_paths.push(parent.join("tools").join(platform_name).join(exe_name));
+            }
+        }
+        
+        // Parent directory of executable + tools (alternative production layout)
+        if let Ok(exe) = std::env::current_exe() {
+            if let Some(parent) = exe.parent().and_then(|p| p.parent()) {
+                possible_paths.push(parent.join("tools").join(platform_name).join(exe_name));
+            }
+        }
+        
+        // Installer resources of bundled-tools builds (Windows keeps them next to the
+        // executable), until install_bundled_tools has copied them to app data
+        if let Ok(exe) = std::env::current_exe() {
+            if let Some(parent) = exe.parent() {
+                for platform in bundled_tools::bundle_platform_dirs(std::env::consts::OS, std::env::consts::ARCH) {
+                    possible_paths.extend(bundled_tools::tool_candidates(&parent.join("tools").join(platform), tool_name, exe_name));
+                }
+            }
+        }
+    }
+    
+    candidates.extend(possible_paths.drain(..).map(|path| (path, ToolSource::Bundled)));
+    Ok(candidates)
+}
+
+// Last resort for HEIC without an ImageMagick that reads it: tiled pictures are
+// reassembled from their HEVC tiles with FFmpeg, untiled ones converted directly
+fn convert_heic_with_tiles(
+    tool_path: &PathBuf,
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    let run = |command: &mut Command| output_with_limits(command, "ffmpeg", output_path, settings);
+    
+    // Step 1: Get metadata to find tile grid dimensions and rotation
+    let metadata_output = run(create_command(tool_path)
+        .arg("-i")
+        .arg(input_path)
+        .arg("-f")
+        .arg("null")
+        .arg("-"))
+        .map_err(|e| format!("Failed to get HEIC metadata: {}", e))?;
+    
+    let stderr = String::from_utf8_lossy(&metadata_output.stderr);
+    
+    let Some(grid) = conversion::parse_heic_grid(&stderr) else {
+        // Not tiled: FFmpeg decodes the picture (applying its rotation) like any other
+        info!("HEIC isn't tiled, converting {} directly", input_path.display());
+        let mut command = create_command(tool_path);
+        command
+            .arg("-i")
+            .arg(input_path)
+            .arg("-map")
+            .arg("0:v:0")
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-y")
+            .arg(output_path);
+        let output = run(&mut command)
+            .map_err(|e| format!("Failed to convert HEIC: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            error!("Converting HEIC failed: {}", stderr);
+            return Err(format!("Failed to convert HEIC. Error details: {}", stderr));
+        }
+        return Ok(command_line(&command));
+    };
+    let (cols, rows) = grid.tiles();
+    info!(
+        "HEIC {}x{} in {}x{} tiles of {}x{}, rotation {}",
+        grid.width, grid.height, cols, rows, grid.tile_width, grid.tile_height, grid.rotation
+    );
+    
+    // Step 2: Extract tiles into a job directory
+    let job_dir = JobDir::create()?;
+    info!("Extracting HEIC tiles to: {}", job_dir.path.display());
+    let tile_pattern = job_dir.path.join("tile_%02d.png");
+    
+    let extract_output = run(create_command(tool_path)
+        .arg("-i")
+        .arg(input_path)
+        .arg("-map")
+        .arg("0:g:0")
+        .arg(&tile_pattern)
+        .arg("-y"))
+        .map_err(|e| format!("Failed to extract tiles: {}", e))?;
+    
+    if !extract_output.status.success() {
+        return Err("Failed to extract HEIC tiles".to_string());
+    }
+    
+    // Step 3: Stitch tiles together
+    let stitched_path = job_dir.path.join("stitched.png");
+    
+    let stitch_output = run(create_command(tool_path)
+        .arg("-i")
+        .arg(&tile_pattern)
+        .arg("-filter_complex")
+        .arg(Filter::Tile { cols, rows }.render())
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-y")
+        .arg(&stitched_path))
+        .map_err(|e| format!("Failed to stitch tiles: {}", e))?;
+    
+    if !stitch_output.status.success() {
+        return Err("Failed to stitch HEIC tiles".to_string());
+    }
+    
+    // Step 4: Crop to exact dimensions and apply rotation
+    let mut filter_parts = vec![];
+    
+    // Crop if the edge tiles overhang the picture
+    if cols * grid.tile_width != grid.width || rows * grid.tile_height != grid.height {
+        filter_parts.push(Filter::crop(grid.width, grid.height, 0, 0));
+    }
+    
+    // Add rotation filter (FFmpeg reports the display matrix rotation, which is counter-clockwise)
+    if grid.rotation != 0 {
+        filter_parts.extend(filter_graph::rotation(-grid.rotation));
+    }
+    
+    // Step 5: Convert to final format
+    let mut final_command = create_command(tool_path);
+    final_command
+        .arg("-i")
+        .arg(&stitched_path);
+    
+    if !filter_parts.is_empty() {
+        final_command
+            .arg("-vf")
+            .arg(filter_graph::chain(&filter_parts));
+    }
+    
+    final_command
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-y")
+        .arg(output_path);
+    
+    let final_output = run(&mut final_command)
+        .map_err(|e| format!("Failed to convert final image: {}", e))?;
+    
+    if final_output.status.success() {
+        Ok(command_line(&final_command))
+    } else {
+        Err("Failed to convert HEIC to final format".to_string())
+    }
+}
+
+/// First pass of two-pass loudness normalization. None (a single, dynamic pass follows)
+/// when the audio couldn't be measured, e.g. because it's silent.
+fn measure_loudness(ffmpeg_path: &PathBuf, input_path: &PathBuf, lufs: f32, settings: &ConversionSettings) -> Option<LoudnessMeasurement> {
+    let mut command = create_command(ffmpeg_path);
+    command
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(input_path)
+        .args(conversion::loudness_analysis_args(lufs));
+    let output = output_with_limits(&mut command, "ffmpeg", input_path, settings);
+    let measured = match output {
+        Ok(output) if output.status.success() => conversion::parse_loudnorm_json(&String::from_utf8_lossy(&output.stderr)),
+        Ok(output) => {
+            warn!("Loudness analysis of {} failed: {}", input_path.display(), String::from_utf8_lossy(&output.stderr));
+            None
+        }
+        Err(e) => {
+            warn!("Failed to run the loudness analysis: {}", e);
+            None
+        }
+    };
+    match &measured {
+        Some(measured) => info!("Measured {} at {} LUFS, {} dBTP", input_path.display(), measured.input_i, measured.input_tp),
+        None => warn!("Couldn't measure the loudness of {}; normalizing in one pass", input_path.display()),
+    }
+    measured
+}
+
+/// Check if an image has transparency (alpha channel) using ImageMagick or FFmpeg
+fn has_transparency(image_path: &PathBuf) -> bool {
+    info!("Checking transparency for: {}", image_path.display());
+    
+    // Try ImageMagick first (if available)
+    if let Ok(tool_path) = get_tool_path("imagemagick") {
+        info!("Using ImageMagick to check transparency");
+        // ImageMagick 7 syntax: magick identify -format "%[channels]" image.png
+        // Returns something like "srgba" (with alpha) or "srgb" (no alpha)
+        let output = create_command(&tool_path)
+            .arg("identify")
+            .arg("-format")
+            .arg("%[channels]")
+            .arg(image_path)
+            .output();
+        
+        if let Ok(output) = output {
+            if output.status.success() {
+                let channels = String::from_utf8_lossy(&output.stdout);
+                info!("Image channels detected: '{}'", channels);
+                let has_alpha = conversion::identify_channels_have_alpha(&channels);
+                info!("Has transparency: {}", has_alpha);
+                return has_alpha;
+            }
+        }
+    }
+    
+    // Fallback to FFmpeg if ImageMagick isn't available
+    if let Ok(ffmpeg_path) = get_tool_path("ffmpeg") {
+        info!("Using FFmpeg to check transparency");
+        // Use FFmpeg itself to get stream info (works without ffprobe)
+        // ffmpeg -i input.png will output stream info to stderr
+        let output = create_command(&ffmpeg_path)
+            .arg("-i")
+            .arg(image_path)
+            .output();
+        
+        if let Ok(output) = output {
+            // FFmpeg outputs stream info to stderr
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            info!("FFmpeg output (first 500 chars): {}", &stderr.chars().take(500).collect::<String>());
+            
+            // Look for pixel format information in the stderr output
+            // Example: "Stream #0:0: Video: png, rgba, 1920x1080"
+            let has_alpha = conversion::ffmpeg_stream_has_alpha(&stderr);
+            
+            info!("Has transparency: {}", has_alpha);
+            return has_alpha;
+        }
+    }
+    
+    warn!("Could not check transparency - no tools available");
+    false
+}
+
+/// A conversion ready to run, built by prepare_conversion
+enum PreparedConversion {
+    /// Same format under another extension (JPG <-> JPEG), the file is just copied
+    Copy,
+    /// HEIC decoded by FFmpeg when ImageMagick can't read it, see convert_heic_with_tiles
+    HeicTiles(PathBuf),
+    /// SVGs rendered in-process by resvg, see render_svg
+    RenderSvg,
+    /// Camera RAW photos developed by LibRaw (the tool path) and then converted, see develop_raw
+    DevelopRaw(PathBuf),
+    /// Common image formats converted in-process, see convert_builtin
+    Builtin,
+    /// A single external command
+    Command {
+        command: Command,
+        tool: String,
+        tool_path: PathBuf,
+        /// Staged inputs the command reads, kept until it has run
+        job_dir: Option<JobDir>,
+    },
+}
+
+/// Build the command for a conversion without running it (used for both execution and previews)
+fn prepare_conversion(
+    tool_name: &str,
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<PreparedConversion, String> {
+    // Handle special "rename" tool for JPG <-> JPEG conversions
+    if tool_name == "rename" {
+        return Ok(PreparedConversion::Copy);
+    }
+    
+    if let Some(crop) = &settings.crop {
+        crop.region()?;
+    }
+    if let Some(watermark) = &settings.watermark {
+        watermark.validate()?;
+        if let Some(image) = watermark.image() {
+            if !PathBuf::from(image).exists() {
+                return Err(format!("Watermark picture not found: {}", image));
+            }
+        }
+    }
+    settings.raw.validate()?;
+    conversion::validate_audio_edits(settings)?;
+    // ImageMagick reads the target profile from a file
+    if settings.color.convert_to_srgb {
+        icc::ensure_srgb_profile()?;
+    }
+    
+    // SVG rasterization doesn't need an external tool
+    if tool_name == "resvg" {
+        return Ok(PreparedConversion::RenderSvg);
+    }
+    
+    // LibRaw develops camera RAW photos better than ImageMagick's own reader, when installed
+    if tool_name == "imagemagick" {
+        let input_ext = input_path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+        if conversion::is_camera_raw(&input_ext) {
+            if let Ok(libraw_path) = get_tool_path("libraw") {
+                return Ok(PreparedConversion::DevelopRaw(libraw_path));
+            }
+        }
+    }
+    
+    if tool_name == conversion::BUILTIN_TOOL {
+        return Ok(PreparedConversion::Builtin);
+    }
+    
+    // Determine the actual tool to use (with ImageMagick fallback logic)
+    let (actual_tool, tool_path) = match get_tool_path(tool_name) {
+        Ok(path) => (tool_name, path),
+        Err(e) => {
+            // If ImageMagick is not available, try to fallback to FFmpeg for image conversions
+            if tool_name == "imagemagick" {
+                let output_ext = output_path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+                
+                // HEIC/HEIF requires ImageMagick, no fallback available
+                if output_ext == "heic" || output_ext == "heif" {
+                    return Err(format!(
+                        "ImageMagick is required for HEIC/HEIF encoding but is not installed.\n\n\
+                        Please install ImageMagick from the Tools Manager in Settings."
+                    ));
+                }
+                
+                // X Window System formats require ImageMagick, no fallback available
+                if output_ext == "xbm" || output_ext == "xpm" || output_ext == "xwd" {
+                    return Err(format!(
+                        "ImageMagick is required for {} format but is not installed.\n\n\
+                        {} is an X Window System format not supported by FFmpeg.\n\n\
+                        Please install ImageMagick from the Tools Manager in Settings.",
+                        output_ext.to_uppercase(), output_ext.to_uppercase()
+                    ));
+                }
+                
+                // Try to use FFmpeg as fallback for other image formats
+                match get_tool_path("ffmpeg") {
+                    Ok(ffmpeg_path) => {
+                        info!("ImageMagick not available, using FFmpeg fallback for image conversion");
+                        ("ffmpeg", ffmpeg_path)
+                    }
+                    Err(_) => {
+                        return Err(format!(
+                            "ImageMagick is not installed and FFmpeg fallback failed.\n\n{}", e
+                        ));
+                    }
+                }
+            } else if tool_name == "inkscape" {
+                // Vector output has no raster fallback - rasterizing would defeat the point
+                return Err(
+                    "Inkscape is required for vector output but is not installed.\n\n\
+                    Please install Inkscape from the Tools Manager in Settings, \
+                    or turn off vector output to rasterize with ImageMagick.".to_string()
+                );
+            } else if tool_name == "chromium" {
+                return Err(CHROMIUM_NOT_FOUND.to_string());
+            } else if tool_name == "libreoffice" {
+                return Err(LIBREOFFICE_NOT_FOUND.to_string());
+            } else if tool_name == "calibre" {
+                return Err(CALIBRE_NOT_FOUND.to_string());
+            } else {
+                return Err(e);
+            }
+        }
+    };
+    
+    // ImageMagick decodes HEIC with libheif; builds without it leave FFmpeg's tile stitching
+    let input_ext = input_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let (actual_tool, tool_path) = if actual_tool == "imagemagick" && conversion::is_heic(&input_ext) && !imagemagick_reads(&tool_path, "HEIC") {
+        match get_tool_path("ffmpeg") {
+            Ok(ffmpeg_path) => {
+                warn!("This ImageMagick can't read HEIC (no libheif), using FFmpeg for {}", input_path.display());
+                ("ffmpeg", ffmpeg_path)
+            }
+            Err(_) => {
+                return Err(
+                    "This ImageMagick was built without HEIC support (libheif).\n\n\
+                    Please install ImageMagick from the Tools Manager in Settings (it includes libheif), or install FFmpeg.".to_string()
+                );
+            }
+        }
+    } else {
+        (actual_tool, tool_path)
+    };
+    
+    let mut command = create_command(&tool_path);
+    let mut job_dir = None;
+    
+    // On macOS, set environment variables for ImageMagick to find its bundled libraries
+    #[cfg(target_os = "macos")]
+    if actual_tool == "imagemagick" {
+        // tool_path is: ~/Library/Application Support/com.convertsave/imagemagick/bin/magick
+        if let Some(bin_dir) = tool_path.parent() {
+            if let Some(imagemagick_dir) = bin_dir.parent() {
+                let lib_dir = imagemagick_dir.join("lib");
+                let etc_dir = imagemagick_dir.join("etc").join("ImageMagick-7");
+                
+                info!("Setting DYLD_LIBRARY_PATH: {}", lib_dir.display());
+                info!("Setting MAGICK_HOME: {}", imagemagick_dir.display());
+                
+                command.env("DYLD_LIBRARY_PATH", &lib_dir);
+                command.env("MAGICK_HOME", &imagemagick_dir);
+                
+                // Set configuration path if it exists
+                if etc_dir.exists() {
+                    info!("Setting MAGICK_CONFIGURE_PATH: {}", etc_dir.display());
+                    command.env("MAGICK_CONFIGURE_PATH", &etc_dir);
+                } else {
+                    warn!("Configuration directory not found: {}", etc_dir.display());
+                    // List what's actually in the imagemagick directory
+                    if let Ok(entries) = std::fs::read_dir(&imagemagick_dir) {
+                        warn!("Contents of imagemagick directory:");
+                        for entry in entries.flatten() {
+                            warn!("  - {}", entry.path().display());
+                        }
+                    }
+                }
+                
+                // Set module path for builds with --with-modules enabled
+                // Look for ImageMagick-* directory in lib
+                if let Ok(entries) = std::fs::read_dir(&lib_dir) {
+                    for entry in entries.flatten() {
+                        let path = entry.path();
+                        if path.is_dir() && path.file_name()
+                            .and_then(|n| n.to_str())
+                            .map(|n| n.starts_with("ImageMagick-"))
+                            .unwrap_or(false) {
+                            let modules_coders = path.join("modules-Q16HDRI").join("coders");
+                            if modules_coders.exists() {
+                                info!("Setting MAGICK_CODER_MODULE_PATH: {}", modules_coders.display());
+                                command.env("MAGICK_CODER_MODULE_PATH", &modules_coders);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Animated-to-animated conversions keep every frame instead of going through the still-image paths
+    let output_ext = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let is_heic_sequence = actual_tool == "imagemagick"
+        && conversion::is_heic(&input_ext)
+        && conversion::HEIC_SEQUENCE_OUTPUTS.contains(&output_ext.as_str())
+        && heic_picture_count(&tool_path, input_path) > 1;
+    if is_heic_sequence {
+        info!("{} holds several pictures, keeping them all as frames", input_path.display());
+    }
+    let is_animated = conversion::is_animated_conversion(&input_ext, &output_ext) || is_heic_sequence;
+    if is_animated && settings.watermark.is_some() {
+        warn!("Watermarks aren't drawn on animations; converting {} without it", input_path.display());
+    }
+    
+    match actual_tool {
+        "imagemagick" if is_animated => {
+            info!("Converting animated {} to {} with all frames", input_ext.to_uppercase(), output_ext.to_uppercase());
+            let magick = conversion::imagemagick_animation_command(input_path, output_path, &output_ext, settings, advanced_options);
+            command.args(magick.args());
+        }
+        "ffmpeg" if is_animated => {
+            info!("Converting animated {} to {} with all frames", input_ext.to_uppercase(), output_ext.to_uppercase());
+            command.arg("-i").arg(input_path);
+            
+            for arg in conversion::ffmpeg_animation_args(&output_ext, settings) {
+                command.arg(arg);
+            }
+            
+            for arg in conversion::metadata_args("ffmpeg", settings) {
+                command.arg(arg);
+            }
+            
+            // Add advanced options if provided
+            if let Some(options) = advanced_options {
+                let options_parts: Vec<&str> = options.split_whitespace().collect();
+                for part in options_parts {
+                    command.arg(part);
+                }
+            }
+            
+            command.arg("-y").arg(output_path);
+        }
+        "imagemagick" => {
+            // ImageMagick 7 syntax: magick input.jpg [options] output.heic
+            // Note: ImageMagick 7 doesn't use "convert" as a subcommand
+            
+            // Check input and output formats
+            let input_ext = input_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            let output_ext = output_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            
+            // PDF and PostScript inputs are rasterized by Ghostscript behind the scenes
+            if conversion::needs_ghostscript(&input_ext) {
+                add_ghostscript_to_path(&mut command);
+            }
+            
+            // Animation formats that can have multiple frames
+            let animation_formats = ["gif", "webp", "apng", "mng"];
+            
+            // If converting from an animation format to a static format, extract first frame only
+            // This prevents animated GIFs from creating artifacts when converted to static images
+            let frame = if animation_formats.contains(&input_ext.as_str()) && !animation_formats.contains(&output_ext.as_str()) {
+                info!("Extracting first frame from animated {}", input_ext.to_uppercase());
+                Some(0)
+            } else if conversion::is_heic(&input_ext) {
+                // The main picture of a burst or sequence, instead of one output file per picture
+                Some(0)
+            } else {
+                None
+            };
+            let input = imagemagick_input(input_path, frame, &mut job_dir)?;
+            
+            // If input has transparency and output format doesn't support it, flatten with white background
+            let flatten = conversion::OPAQUE_OUTPUTS.contains(&output_ext.as_str()) && has_transparency(input_path);
+            if flatten {
+                info!("Detected transparency in input image, flattening with white background for {} output", output_ext);
+            }
+            
+            // Format-specific quality, metadata stripping and advanced options (which override defaults)
+            let mut magick = conversion::imagemagick_still_command(input, output_path, &output_ext, flatten, settings, advanced_options);
+            if conversion::is_camera_raw(&input_ext) {
+                if settings.raw.exposure != 0.0 {
+                    warn!("Exposure compensation needs LibRaw (dcraw_emu); developing {} without it", input_path.display());
+                }
+                magick = magick.ops(conversion::imagemagick_raw_ops(&output_ext, settings));
+            }
+            if let Some(watermark) = &settings.watermark {
+                let canvas = watermark_canvas(input_path, settings)?;
+                let image = match watermark.image() {
+                    Some(image) => Some(imagemagick_input(&PathBuf::from(image), None, &mut job_dir)?.to_string_lossy().to_string()),
+                    None => None,
+                };
+                magick = magick.ops(conversion::imagemagick_watermark_ops(watermark, image.as_deref(), canvas));
+            }
+            command.args(magick.args());
+        }
+        "ffmpeg" => {
+            // Check input format for special HEIC handling
+            let input_ext = input_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            
+            // Check output format
+            let output_ext = output_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            
+            // HEIC/HEIF files need special tile reassembly handling
+            if input_ext == "heic" || input_ext == "heif" {
+                return Ok(PreparedConversion::HeicTiles(tool_path));
+            }
+            
+            // Warnings only, plus the progress stats
+            command.args(conversion::ffmpeg_log_args(&ffmpeg_log_level()));
+            
+            // FFmpeg doesn't reliably apply EXIF orientation to photos (it depends on the
+            // version), so it's told not to and the rotation is done with filters instead.
+            // The crop applies to the upright picture.
+            let mut frame_filters = if conversion::is_image_format(&input_ext) {
+                command.arg("-noautorotate");
+                if settings.keep_orientation {
+                    Vec::new()
+                } else {
+                    filter_graph::exif_orientation(read_exif_orientation(input_path))
+                }
+            } else {
+                Vec::new()
+            };
+            frame_filters.extend(conversion::ffmpeg_crop_filters(settings));
+            
+            // A text watermark is one more frame filter; a picture is read by the movie
+            // source from the job directory and overlaid after the frame filters
+            let mut image_watermark = None;
+            if let Some(watermark) = &settings.watermark {
+                if let Some(text) = watermark.text() {
+                    frame_filters.push(conversion::ffmpeg_text_watermark(watermark, text, drawtext_font_file()));
+                } else if let Some(image) = watermark.image() {
+                    let canvas = watermark_canvas(input_path, settings)?;
+                    if job_dir.is_none() {
+                        job_dir = Some(JobDir::create()?);
+                    }
+                    let staged = job_dir.as_mut().expect("created above").stage(&PathBuf::from(image))?;
+                    image_watermark = Some((watermark, staged, canvas));
+                }
+            }
+            // The frame filters as a -vf graph, with the picture watermark drawn last
+            let video_filter = |frame_filters: Vec<Filter>, image_watermark: Option<(&Watermark, String, (u32, u32))>| {
+                match image_watermark {
+                    Some((watermark, staged, canvas)) => {
+                        conversion::ffmpeg_image_watermark(FilterGraph::new(), "in", frame_filters, watermark, &staged, canvas, &[])
+                    }
+                    None => FilterGraph::new().chain(&[], frame_filters, &[]),
+                }
+            };
+            
+            command.arg("-i").arg(input_path);
+            
+            // AVIF is a still AV1 picture: its codec options replace the image handling below
+            if output_ext == "avif" {
+                let graph = video_filter(std::mem::take(&mut frame_filters), image_watermark.take());
+                for arg in conversion::ffmpeg_avif_args(has_transparency(input_path), graph) {
+                    command.arg(arg);
+                }
+                
+                for arg in conversion::metadata_args("ffmpeg", settings) {
+                    command.arg(arg);
+                }
+                
+                // Add advanced options if provided
+                if let Some(options) = advanced_options {
+                    let options_parts: Vec<&str> = options.split_whitespace().collect();
+                    for part in options_parts {
+                        command.arg(part);
+                    }
+                }
+                
+                command.args(ffmpeg_thread_args(settings));
+                command.arg("-y").arg(output_path);
+            } else {
+                // External subtitles. Muxing adds a second input, so this comes before any output option.
+                let mut muxed_subtitles = false;
+                if let Some(subtitles) = &settings.subtitles {
+                    if conversion::is_video_format(&output_ext) {
+                        let subtitle_path = PathBuf::from(&subtitles.path);
+                        if !subtitle_path.exists() {
+                            return Err(format!("Subtitle file not found: {}", subtitles.path));
+                        }
+                        match subtitles.mode {
+                            // The stream selection below maps the extra input itself
+                            SubtitleMode::Mux if settings.stream_selection.is_some() => {
+                                command.arg("-i").arg(&subtitle_path);
+                                muxed_subtitles = true;
+                            }
+                            SubtitleMode::Mux => {
+                                command.args(conversion::subtitle_args(subtitles.mode, &subtitles.path, &output_ext)?);
+                            }
+                            SubtitleMode::Burn => {
+                                // The subtitles filter reads a plain name from the job directory
+                                if job_dir.is_none() {
+                                    job_dir = Some(JobDir::create()?);
+                                }
+                                let staged = job_dir.as_mut().expect("created above").stage(&subtitle_path)?;
+                                let mut args = conversion::subtitle_args(subtitles.mode, &staged, &output_ext)?;
+                                // One -vf: crop first, so the subtitles are laid out on the cropped frame
+                                let graph = video_filter(std::mem::take(&mut frame_filters), image_watermark.take());
+                                if !graph.is_empty() {
+                                    args[1] = format!("{},{}", graph.render(), args[1]);
+                                }
+                                command.args(args);
+                            }
+                        }
+                    } else {
+                        warn!("Ignoring subtitles for {} output, which isn't a video", output_ext);
+                    }
+                }
+                
+                // Chapters of a cue sheet next to an audio file that has none of its own, read
+                // from an ffmetadata file as a second input (subtitles are only muxed into video)
+                let mut cue_chapters = false;
+                if settings.keep_chapters && conversion::is_audio_format(&output_ext) {
+                    if let Some(cue) = chapters::cue_sheet_for(input_path) {
+                        let info = inspect_media(input_path)?;
+                        if info.chapters.is_empty() {
+                            let text = std::fs::read(&cue).map_err(|e| format!("Failed to read {}: {}", cue.display(), e))?;
+                            let cue_sheet = chapters::parse_cue_sheet(&String::from_utf8_lossy(&text));
+                            let resolved = chapters::resolve_chapter_ends(&cue_sheet, info.duration_secs);
+                            if !resolved.is_empty() {
+                                if job_dir.is_none() {
+                                    job_dir = Some(JobDir::create()?);
+                                }
+                                let metadata_path = job_dir.as_ref().expect("created above").path.join("chapters.ffmeta");
+                                std::fs::write(&metadata_path, chapters::ffmetadata(&resolved))
+                                    .map_err(|e| format!("Failed to write chapters: {}", e))?;
+                                command.arg("-f").arg("ffmetadata").arg("-i").arg(&metadata_path);
+                                cue_chapters = true;
+                            }
+                        }
+                    }
+                }
+                
+                // Chosen audio and subtitle tracks instead of FFmpeg's pick of one of each
+                if let Some(selection) = &settings.stream_selection {
+                    if conversion::is_video_format(&output_ext) || conversion::is_audio_format(&output_ext) {
+                        let streams = inspect_media(input_path)?.streams;
+                        command.args(conversion::stream_selection_args(selection, &streams, &output_ext, muxed_subtitles)?);
+                    }
+                }
+                
+                // Animation formats that can have multiple frames
+                let animation_formats = ["gif", "webp", "apng", "mng"];
+                
+                // If converting from an animation format to a static format, extract first frame only
+                if animation_formats.contains(&input_ext.as_str()) && !animation_formats.contains(&output_ext.as_str()) {
+                    info!("Extracting first frame from animated {}", input_ext.to_uppercase());
+                    command.arg("-frames:v").arg("1");
+                }
+                
+                // Check if we need to handle transparency -> opaque conversion
+                // Formats that don't support alpha transparency (or only binary transparency like GIF)
+                let formats_without_transparency = [
+                    "jpg", "jpeg", "bmp", "gif", "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
+                    "hdr", "pbm", "pgm", "ppm"
+                ];
+                
+                // If input has transparency and output format doesn't support it, flatten with white background
+                // For FFmpeg, we use a filter to composite the image over a white background
+                let needs_transparency_handling = formats_without_transparency.contains(&output_ext.as_str()) && has_transparency(input_path);
+                
+                // Handle transparency flattening if needed  
+                if needs_transparency_handling {
+                    info!("🎨 TRANSPARENCY DETECTED! Adding white background for {} output using FFmpeg", output_ext);
+                    // Exact command: -f lavfi -i color=c=white -filter_complex "[1][0]scale=rw:rh[bg];[bg][0]overlay=shortest=1" -q:v 1
+                    command.arg("-f").arg("lavfi");
+                    command.arg("-i").arg("color=c=white");
+                    command.arg("-filter_complex");
+                
+                    // Scale the white color source to the image, then draw the image over it
+                    let overlay = Filter::Overlay { x: "0".to_string(), y: "0".to_string(), shortest: true };
+                    let (mut graph, image) = match image_watermark.take() {
+                        Some((watermark, staged, canvas)) => {
+                            let graph = conversion::ffmpeg_image_watermark(FilterGraph::new(), "0", frame_filters.clone(), watermark, &staged, canvas, &["upright"]);
+                            (graph, "upright")
+                        }
+                        None if frame_filters.is_empty() => (FilterGraph::new(), "0"),
+                        None => (FilterGraph::new().chain(&["0"], frame_filters.clone(), &["upright"]), "upright"),
+                    };
+                    graph = graph
+                        .chain(&["1", image], vec![Filter::scale("rw", "rh")], &["bg"])
+                        .chain(&["bg", image], vec![overlay], &[]);
+                
+                    // Some formats need explicit pixel format conversion for proper color handling
+                    let problematic_formats = ["hdr", "pbm", "pgm", "ppm"];
+                    if problematic_formats.contains(&output_ext.as_str()) {
+                        graph = graph.then(Filter::Format("rgb24".to_string()));
+                    }
+                
+                    command.arg(graph.render());
+                    command.arg("-q:v").arg("1");
+                }
+                
+                // ICO format requires resizing to max 256x256
+                if output_ext == "ico" {
+                    let graph = video_filter(frame_filters.clone(), image_watermark.take()).then(Filter::fit_within(256, 256));
+                    command.arg("-vf");
+                    command.arg(graph.render());
+                } else if !needs_transparency_handling {
+                    let graph = video_filter(frame_filters.clone(), image_watermark.take());
+                    if !graph.is_empty() {
+                        command.arg("-vf").arg(graph.render());
+                    }
+                }
+                
+                // 16-bit PNG and TIFF; profiles are ImageMagick's job
+                if conversion::is_image_format(&input_ext) {
+                    if settings.color.convert_to_srgb || settings.color.icc_profile == IccProfileMode::Strip {
+                        warn!("FFmpeg can't convert or remove color profiles; install ImageMagick for color management");
+                    }
+                    if settings.color.keep_16_bit {
+                        command.args(conversion::ffmpeg_color_args(&output_ext, has_transparency(input_path), settings));
+                    }
+                }
+                
+                // AAC needs an explicit muxer: ADTS for raw .aac, MP4 (ipod) for .m4a
+                for arg in conversion::aac_muxer_args(&output_ext) {
+                    command.arg(arg);
+                }
+                
+                // Loudness, channels, bitrate, chapters and tags (e.g. the podcast presets)
+                let analysis = AudioAnalysis {
+                    loudness: match settings.loudness_lufs {
+                        Some(lufs) if settings.loudness_two_pass => measure_loudness(&tool_path, input_path, lufs, settings),
+                        _ => None,
+                    },
+                    // A fade-out needs the length up front
+                    duration_secs: match settings.fade_out_ms {
+                        Some(ms) if ms > 0 && conversion::is_audio_format(&output_ext) => {
+                            let duration = inspect_media(input_path).ok().and_then(|info| info.duration_secs);
+                            if duration.is_none() {
+                                warn!("The length of {} isn't known, so it isn't faded out", input_path.display());
+                            }
+                            duration
+                        }
+                        _ => None,
+                    },
+                    cue_chapters,
+                };
+                for arg in conversion::audio_processing_args(&output_ext, settings, &analysis) {
+                    command.arg(arg);
+                }
+                
+                // MP4 format: Use compatible settings for broad playback support
+                if output_ext == "mp4" {
+                    command.arg("-pix_fmt").arg("yuv420p");
+                    command.arg("-profile:v").arg("main");
+                    command.arg("-movflags").arg("+faststart");
+                }
+                
+                // Drop or copy container and stream tags as requested
+                for arg in conversion::metadata_args("ffmpeg", settings) {
+                    command.arg(arg);
+                }
+                
+                // Add advanced options if provided
+                if let Some(options) = advanced_options {
+                    let options_parts: Vec<&str> = options.split_whitespace().collect();
+                    for part in options_parts {
+                        command.arg(part);
+                    }
+                }
+                
+                // For video/multi-frame input to single image output, specify one frame
+                // NOTE: gif is excluded because video->gif should create animated GIF
+                let video_formats = ["mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v", "mpg", "mpeg", "3gp", "ogv"];
+                let static_image_formats = ["jpg", "jpeg", "png", "webp", "bmp", "tiff", "tif", "ico"];
+                
+                if video_formats.contains(&input_ext.as_str()) && static_image_formats.contains(&output_ext.as_str()) {
+                    command.arg("-frames:v").arg("1");
+                }
+                
+                // For single image output (not animated GIF), use -update flag to write one file (not a sequence)
+                if static_image_formats.contains(&output_ext.as_str()) {
+                    command.arg("-update").arg("1");
+                }
+                
+                command.args(ffmpeg_thread_args(settings));
+                command.arg("-y").arg(output_path); // -y to overwrite output file
+            }
+        }
+        "inkscape" => {
+            // Inkscape 1.x syntax: inkscape input.svg --export-type=pdf --export-filename output.pdf
+            let output_ext = output_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            
+            command.arg(input_path);
+            command.arg(format!("--export-type={}", output_ext));
+            
+            // Plain SVG drops Inkscape-specific namespaces for better compatibility
+            if output_ext == "svg" {
+                command.arg("--export-plain-svg");
+            }
+            
+            // Add advanced options if provided
+            if let Some(options) = advanced_options {
+                let options_parts: Vec<&str> = options.split_whitespace().collect();
+                for part in options_parts {
+                    command.arg(part);
+                }
+            }
+            
+            command.arg("--export-filename").arg(output_path);
+        }
+        "chromium" => {
+            // Headless Chromium loads the local HTML file like a browser tab. Emails are
+            // laid out as a page in the job directory first.
+            let page_path = if email::EMAIL_INPUTS.contains(&input_ext.as_str()) {
+                let message = email::read_email(input_path)?;
+                if job_dir.is_none() {
+                    job_dir = Some(JobDir::create()?);
+                }
+                let page = job_dir.as_ref().expect("created above").path.join("email.html");
+                std::fs::write(&page, email::render_html(&message))
+                    .map_err(|e| format!("Failed to write email page: {}", e))?;
+                page
+            } else {
+                input_path.clone()
+            };
+            let target = conversion::file_path_to_url(&page_path.to_string_lossy());
+            
+            // Add advanced options if provided (browser flags go before the page URL)
+            if let Some(options) = advanced_options {
+                let options_parts: Vec<&str> = options.split_whitespace().collect();
+                for part in options_parts {
+                    command.arg(part);
+                }
+            }
+            
+            for arg in conversion::chromium_snapshot_args(&target, &output_path.to_string_lossy(), &output_ext, settings) {
+                command.arg(arg);
+            }
+        }
+        "libreoffice" => {
+            // soffice names the output after the input, so it's written to a staging
+            // directory and moved to output_path by run_libreoffice
+            let staging_dir = libreoffice_staging_dir(output_path);
+            let args = if output_ext == "pdf" {
+                settings.pdf_export.validate()?;
+                let source = libreoffice_pdf_source(input_path, &input_ext, &settings.spreadsheet_layout, &mut job_dir)?;
+                conversion::libreoffice_args_with_target(
+                    &source.to_string_lossy(),
+                    &conversion::libreoffice_pdf_target(&input_ext, &settings.pdf_export, &settings.spreadsheet_layout, None),
+                    &staging_dir.to_string_lossy(),
+                    &libreoffice_profile_dir().to_string_lossy(),
+                )
+            } else {
+                conversion::libreoffice_args(
+                    &input_path.to_string_lossy(),
+                    &output_ext,
+                    &staging_dir.to_string_lossy(),
+                    &libreoffice_profile_dir().to_string_lossy(),
+                )
+            };
+            for arg in args {
+                command.arg(arg);
+            }
+        }
+        "calibre" => {
+            // ebook-convert input.epub output.mobi [options] - formats come from the extensions
+            command.arg(input_path).arg(output_path);
+            
+            // Add advanced options if provided
+            if let Some(options) = advanced_options {
+                let options_parts: Vec<&str> = options.split_whitespace().collect();
+                for part in options_parts {
+                    command.arg(part);
+                }
+            }
         }
+        "pandoc" if conversion::pandoc_enabled() => {
+            command.arg(input_path).arg("-o").arg(output_path);
+            
+            // Add advanced options if provided
+            if let Some(options) = advanced_options {
+                let options_parts: Vec<&str> = options.split_whitespace().collect();
+                for part in options_parts {
+                    command.arg(part);
+                }
+            }
+        }
+        _ => return Err(format!("Unknown tool: {}", tool_name)),
     }
     
-    // Step 7: Convert to final format
-    let mut final_command = create_command(tool_path);
-    final_command
-        .arg("-i")
-        .arg(&stitched_path);
-    
-    if !filter_parts.is_empty() {
-        final_command
-            .arg("-vf")
-            .arg(filter_parts.join(","));
-    }
-    
-    final_command
-        .arg("-frames:v")
-        .arg("1")
-        .arg("-y")
-        .arg(output_path);
-    
-    let final_output = final_command.output()
-        .map_err(|e| format!("Failed to convert final image: {}", e))?;
-    
-    // Clean up temp directory
-    let _ = std::fs::remove_dir_all(&temp_dir);
+    run_in_job_dir(&mut command, &job_dir);
     
-    if final_output.status.success() {
-        Ok(())
+    // Homebrew's FFmpeg leaves out some encoders (e.g. libfdk_aac) that the static build has
+    let (command, tool_path) = if actual_tool == "ffmpeg" {
+        ffmpeg_with_encoders(command, tool_path)?
     } else {
-        Err("Failed to convert HEIC to final format".to_string())
-    }
+        (command, tool_path)
+    };
+    
+    Ok(PreparedConversion::Command {
+        command,
+        tool: actual_tool.to_string(),
+        tool_path,
+        job_dir,
+    })
 }
 
-/// Check if an image has transparency (alpha channel) using ImageMagick or FFmpeg
-fn has_transparency(image_path: &PathBuf) -> bool {
-    info!("Checking transparency for: {}", image_path.display());
+/// Working directory for one job under the temp dir, removed when dropped
+struct JobDir {
+    path: PathBuf,
+    staged: usize,
+}
+
+impl JobDir {
+    fn create() -> Result<Self, String> {
+        let id = NEXT_JOB_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("convertsave-job-{}-{}", std::process::id(), id));
+        std::fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create job directory: {}", e))?;
+        Ok(JobDir { path, staged: 0 })
+    }
     
-    // Try ImageMagick first (if available)
-    if let Ok(tool_path) = get_tool_path("imagemagick") {
-        info!("Using ImageMagick to check transparency");
-        // ImageMagick 7 syntax: magick identify -format "%[channels]" image.png
-        // Returns something like "srgba" (with alpha) or "srgb" (no alpha)
-        let output = create_command(&tool_path)
-            .arg("identify")
-            .arg("-format")
-            .arg("%[channels]")
-            .arg(image_path)
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let channels = String::from_utf8_lossy(&output.stdout).to_lowercase();
-                info!("Image channels detected: '{}'", channels);
-                let has_alpha = channels.contains("a");
-                info!("Has transparency: {}", has_alpha);
-                return has_alpha;
-            }
-        }
+    /// Link (or copy, across volumes) an input into the directory under a plain name
+    fn stage(&mut self, input_path: &PathBuf) -> Result<String, String> {
+        let input_ext = input_path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_string();
+        self.stage_as(input_path, &input_ext)
     }
     
-    // Fallback to FFmpeg if ImageMagick isn't available
-    if let Ok(ffmpeg_path) = get_tool_path("ffmpeg") {
-        info!("Using FFmpeg to check transparency");
-        // Use FFmpeg itself to get stream info (works without ffprobe)
-        // ffmpeg -i input.png will output stream info to stderr
-        let output = create_command(&ffmpeg_path)
-            .arg("-i")
-            .arg(image_path)
-            .output();
-        
-        if let Ok(output) = output {
-            // FFmpeg outputs stream info to stderr
-            let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
-            info!("FFmpeg output (first 500 chars): {}", &stderr.chars().take(500).collect::<String>());
-            
-            // Look for pixel format information in the stderr output
-            // Example: "Stream #0:0: Video: png, rgba, 1920x1080"
-            // Look for rgba, yuva420p, gbrap, etc. (formats with alpha)
-            let has_alpha = stderr.contains("rgba") || 
-                           stderr.contains("yuva") || 
-                           stderr.contains("gbra") ||
-                           stderr.contains("ya8") ||
-                           stderr.contains("ya16") ||
-                           stderr.contains("yuva420p") ||
-                           stderr.contains("yuva422p") ||
-                           stderr.contains("yuva444p");
-            
-            info!("Has transparency: {}", has_alpha);
-            return has_alpha;
+    /// Like stage, with `input_ext` as the extension of the staged name
+    fn stage_as(&mut self, input_path: &PathBuf, input_ext: &str) -> Result<String, String> {
+        let name = conversion::staged_input_name(self.staged, input_ext);
+        let staged_path = self.path.join(&name);
+        if std::fs::hard_link(input_path, &staged_path).is_err() {
+            std::fs::copy(input_path, &staged_path)
+                .map_err(|e| format!("Failed to stage input file: {}", e))?;
         }
+        self.staged += 1;
+        Ok(name)
     }
-    
-    warn!("Could not check transparency - no tools available");
-    false
 }
 
-async fn execute_conversion(
-    tool_name: &str,
-    input_path: &PathBuf,
-    output_path: &PathBuf,
-    advanced_options: Option<String>,
-) -> Result<(), String> {
-    // Handle special "rename" tool for JPG <-> JPEG conversions
-    if tool_name == "rename" {
-        info!("Performing file rename/copy from {} to {}", input_path.display(), output_path.display());
-        std::fs::copy(input_path, output_path)
-            .map_err(|e| format!("Failed to copy file: {}", e))?;
-        return Ok(());
+impl Drop for JobDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The ImageMagick argument for reading `input_path`, optionally just one frame.
+/// Paths ImageMagick would misread (brackets, wildcards, a leading @) are staged into
+/// `job_dir` under a plain name, so the command has to run there (see run_in_job_dir).
+fn imagemagick_input(input_path: &PathBuf, frame: Option<u32>, job_dir: &mut Option<JobDir>) -> Result<std::ffi::OsString, String> {
+    let path = input_path.to_string_lossy();
+    if conversion::is_imagemagick_literal_path(&path) {
+        return Ok(match frame {
+            Some(_) => conversion::imagemagick_input_spec(&path, frame).into(),
+            None => input_path.as_os_str().to_os_string(),
+        });
     }
     
-    // Determine the actual tool to use (with ImageMagick fallback logic)
-    let (actual_tool, tool_path) = match get_tool_path(tool_name) {
-        Ok(path) => (tool_name, path),
-        Err(e) => {
-            // If ImageMagick is not available, try to fallback to FFmpeg for image conversions
-            if tool_name == "imagemagick" {
-                let output_ext = output_path.extension()
-                    .and_then(|ext| ext.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                
-                // HEIC/HEIF requires ImageMagick, no fallback available
-                if output_ext == "heic" || output_ext == "heif" {
-                    return Err(format!(
-                        "ImageMagick is required for HEIC/HEIF encoding but is not installed.\n\n\
-                        Please install ImageMagick from the Tools Manager in Settings."
-                    ));
-                }
-                
-                // X Window System formats require ImageMagick, no fallback available
-                if output_ext == "xbm" || output_ext == "xpm" || output_ext == "xwd" {
-                    return Err(format!(
-                        "ImageMagick is required for {} format but is not installed.\n\n\
-                        {} is an X Window System format not supported by FFmpeg.\n\n\
-                        Please install ImageMagick from the Tools Manager in Settings.",
-                        output_ext.to_uppercase(), output_ext.to_uppercase()
-                    ));
-                }
-                
-                // Try to use FFmpeg as fallback for other image formats
-                match get_tool_path("ffmpeg") {
-                    Ok(ffmpeg_path) => {
-                        info!("ImageMagick not available, using FFmpeg fallback for image conversion");
-                        ("ffmpeg", ffmpeg_path)
-                    }
-                    Err(_) => {
-                        return Err(format!(
-                            "ImageMagick is not installed and FFmpeg fallback failed.\n\n{}", e
-                        ));
-                    }
-                }
-            } else {
-                return Err(e);
-            }
-        }
+    let mut dir = match job_dir.take() {
+        Some(dir) => dir,
+        None => JobDir::create()?,
     };
-    
-    let mut command = create_command(&tool_path);
-    
-    // On macOS, set environment variables for ImageMagick to find its bundled libraries
+    let name = dir.stage(input_path);
+    *job_dir = Some(dir);
+    Ok(conversion::imagemagick_input_spec(&name?, frame).into())
+}
+
+/// Point a command running the downloaded macOS ImageMagick at its bundled libraries
+/// and configuration (no-op elsewhere)
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+fn set_imagemagick_env(command: &mut Command, tool_path: &PathBuf) {
     #[cfg(target_os = "macos")]
-    if actual_tool == "imagemagick" {
+    {
         // tool_path is: ~/Library/Application Support/com.convertsave/imagemagick/bin/magick
         if let Some(bin_dir) = tool_path.parent() {
             if let Some(imagemagick_dir) = bin_dir.parent() {
@@ -1691,17 +5939,9 @@ async fn execute_conversion(
                     command.env("MAGICK_CONFIGURE_PATH", &etc_dir);
                 } else {
                     warn!("Configuration directory not found: {}", etc_dir.display());
-                    // List what's actually in the imagemagick directory
-                    if let Ok(entries) = std::fs::read_dir(&imagemagick_dir) {
-                        warn!("Contents of imagemagick directory:");
-                        for entry in entries.flatten() {
-                            warn!("  - {}", entry.path().display());
-                        }
-                    }
                 }
                 
                 // Set module path for builds with --with-modules enabled
-                // Look for ImageMagick-* directory in lib
                 if let Ok(entries) = std::fs::read_dir(&lib_dir) {
                     for entry in entries.flatten() {
                         let path = entry.path();
@@ -1721,290 +5961,334 @@ async fn execute_conversion(
             }
         }
     }
+}
+
+/// Run a command from its job directory, if inputs were staged into one
+fn run_in_job_dir(command: &mut Command, job_dir: &Option<JobDir>) {
+    if let Some(dir) = job_dir {
+        command.current_dir(&dir.path);
+    }
+}
+
+/// List the encoders an FFmpeg binary supports (None if it couldn't be asked)
+fn ffmpeg_encoders(ffmpeg_path: &PathBuf) -> Option<Vec<String>> {
+    if let Some(encoders) = FFMPEG_ENCODERS.lock().ok()?.get(ffmpeg_path) {
+        return Some(encoders.clone());
+    }
     
-    match actual_tool {
-        "imagemagick" => {
-            // ImageMagick 7 syntax: magick input.jpg [options] output.heic
-            // Note: ImageMagick 7 doesn't use "convert" as a subcommand
-            
-            // Check input and output formats
-            let input_ext = input_path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            let output_ext = output_path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            
-            // Animation formats that can have multiple frames
-            let animation_formats = ["gif", "webp", "apng", "mng"];
-            
-            // If converting from an animation format to a static format, extract first frame only
-            // This prevents animated GIFs from creating artifacts when converted to static images
-            if animation_formats.contains(&input_ext.as_str()) && !animation_formats.contains(&output_ext.as_str()) {
-                // Use [0] syntax to select only the first frame
-                let input_with_frame = format!("{}[0]", input_path.display());
-                info!("Extracting first frame from animated {}: {}", input_ext.to_uppercase(), input_with_frame);
-                command.arg(&input_with_frame);
-            } else {
-                command.arg(input_path);
-            }
-            
-            // Check if we need to handle transparency -> opaque conversion
-            // Formats that don't support alpha transparency (or only binary transparency like GIF)
-            let formats_without_transparency = [
-                "jpg", "jpeg", "bmp", "gif", "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
-                "hdr", "pbm", "pgm", "ppm"
-            ];
-            
-            // If input has transparency and output format doesn't support it, flatten with white background
-            if formats_without_transparency.contains(&output_ext.as_str()) && has_transparency(input_path) {
-                info!("Detected transparency in input image, flattening with white background for {} output", output_ext);
-                command.arg("-background").arg("white");
-                command.arg("-flatten");
-            }
-            
-            // Format-specific quality and options
-            match output_ext.as_str() {
-                // ICO format requires special handling - must be resized to fit icon size limits
-                "ico" => {
-                    // ICO files have size limitations (typically max 256x256)
-                    // Resize to 256x256 maintaining aspect ratio, then use extent to make it square
-                    command.arg("-resize").arg("256x256");
-                    command.arg("-gravity").arg("center");
-                    command.arg("-extent").arg("256x256");
-                    command.arg("-background").arg("transparent");
-                }
-                // Modern compressed formats
-                "heic" | "heif" => {
-                    command.arg("-quality").arg("85");
-                }
-                "avif" => {
-                    command.arg("-quality").arg("85");
-                }
-                "jxl" => {
-                    command.arg("-quality").arg("90"); // JPEG XL benefits from higher quality
-                }
-                "webp" => {
-                    command.arg("-quality").arg("90");
-                }
-                // Standard lossy formats
-                "jpg" | "jpeg" => {
-                    command.arg("-quality").arg("90");
-                }
-                // JPEG 2000 variants
-                "j2k" | "jp2" | "jpc" | "jpf" | "jpx" | "jpm" => {
-                    command.arg("-quality").arg("85");
-                }
-                // Professional formats (high quality)
-                "tiff" | "tif" | "exr" | "hdr" | "dpx" => {
-                    command.arg("-quality").arg("100");
-                }
-                // PDF output from images - use JPEG compression for reasonable file size
-                "pdf" => {
-                    command.arg("-compress").arg("jpeg"); // Compress images appropriately, preserves transparency
-                    command.arg("-density").arg("300");   // Display at correct zoom level
-                }
-                // Vector formats
-                "svg" | "svgz" => {
-                    command.arg("-density").arg("300"); // 300 DPI for vector
-                }
-                // Everything else uses ImageMagick defaults
-                _ => {}
-            }
-            
-            // Add advanced options if provided (will override defaults)
-            if let Some(options) = advanced_options {
-                let options_parts: Vec<&str> = options.split_whitespace().collect();
-                for part in options_parts {
-                    command.arg(part);
-                }
-            }
-            
-            command.arg(output_path);
-        }
-        "ffmpeg" => {
-            // Check input format for special HEIC handling
-            let input_ext = input_path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            
-            // Check output format
-            let output_ext = output_path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            
-            // HEIC/HEIF files need special tile reassembly handling
-            if input_ext == "heic" || input_ext == "heif" {
-                return convert_heic_with_tiles(&tool_path, input_path, output_path);
-            }
-            
-            command.arg("-i").arg(input_path);
-            
-            // Animation formats that can have multiple frames
-            let animation_formats = ["gif", "webp", "apng", "mng"];
-            
-            // If converting from an animation format to a static format, extract first frame only
-            if animation_formats.contains(&input_ext.as_str()) && !animation_formats.contains(&output_ext.as_str()) {
-                info!("Extracting first frame from animated {}", input_ext.to_uppercase());
-                command.arg("-frames:v").arg("1");
-            }
-            
-            // Check if we need to handle transparency -> opaque conversion
-            // Formats that don't support alpha transparency (or only binary transparency like GIF)
-            let formats_without_transparency = [
-                "jpg", "jpeg", "bmp", "gif", "j2k", "jp2", "jpc", "jpf", "jpx", "jpm",
-                "hdr", "pbm", "pgm", "ppm"
-            ];
-            
-            // If input has transparency and output format doesn't support it, flatten with white background
-            // For FFmpeg, we use a filter to composite the image over a white background
-            let needs_transparency_handling = formats_without_transparency.contains(&output_ext.as_str()) && has_transparency(input_path);
-            
-            // Handle transparency flattening if needed  
-            if needs_transparency_handling {
-                info!("🎨 TRANSPARENCY DETECTED! Adding white background for {} output using FFmpeg", output_ext);
-                // Exact command: -f lavfi -i color=c=white -filter_complex "[1][0]scale=rw:rh[bg];[bg][0]overlay=shortest=1" -q:v 1
-                command.arg("-f").arg("lavfi");
-                command.arg("-i").arg("color=c=white");
-                command.arg("-filter_complex");
-                
-                // Build filter string based on format requirements
-                let mut filter = String::from("[1][0]scale=rw:rh[bg];[bg][0]overlay=shortest=1");
-                
-                // Some formats need explicit pixel format conversion for proper color handling
-                let problematic_formats = ["hdr", "pbm", "pgm", "ppm"];
-                if problematic_formats.contains(&output_ext.as_str()) {
-                    filter.push_str(",format=rgb24");
+    let output = create_command(ffmpeg_path)
+        .arg("-hide_banner")
+        .arg("-encoders")
+        .output()
+        .ok()?;
+    let encoders = conversion::parse_ffmpeg_encoders(&String::from_utf8_lossy(&output.stdout));
+    if encoders.is_empty() {
+        return None;
+    }
+    
+    FFMPEG_ENCODERS.lock().ok()?.insert(ffmpeg_path.clone(), encoders.clone());
+    Some(encoders)
+}
+
+/// Whether an ImageMagick binary can read `format` (uppercase, as in `magick -list format`).
+/// Assumed true if the list couldn't be read, so a failed probe never blocks a conversion.
+fn imagemagick_reads(magick_path: &PathBuf, format: &str) -> bool {
+    if let Some(formats) = MAGICK_READ_FORMATS.lock().ok().and_then(|cache| cache.get(magick_path).cloned()) {
+        return formats.iter().any(|name| name == format);
+    }
+    
+    let mut command = create_command(magick_path);
+    set_imagemagick_env(&mut command, magick_path);
+    let formats = match command.arg("-list").arg("format").output() {
+        Ok(output) => conversion::parse_magick_read_formats(&String::from_utf8_lossy(&output.stdout)),
+        Err(_) => return true,
+    };
+    if formats.is_empty() {
+        return true;
+    }
+    
+    let reads = formats.iter().any(|name| name == format);
+    if let Ok(mut cache) = MAGICK_READ_FORMATS.lock() {
+        cache.insert(magick_path.clone(), formats);
+    }
+    reads
+}
+
+/// Number of pictures in a HEIC (bursts and sequences have several), 1 if it can't be told
+fn heic_picture_count(magick_path: &PathBuf, input_path: &PathBuf) -> usize {
+    let mut command = create_command(magick_path);
+    set_imagemagick_env(&mut command, magick_path);
+    command.arg("identify").arg("-ping").arg("-format").arg("%n\n").arg(input_path);
+    command.output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).lines().next()?.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// The static FFmpeg build downloaded by download_ffmpeg, if it's installed
+fn managed_ffmpeg_path() -> Option<PathBuf> {
+    let path = dirs::data_dir()?
+        .join(APP_IDENTIFIER)
+        .join("ffmpeg")
+        .join(if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" });
+    path.exists().then_some(path)
+}
+
+/// Make sure the FFmpeg running a command has every encoder it asks for. A Homebrew build
+/// missing one is swapped for the managed static build; without that, the user is told how
+/// to install it instead of getting "Unknown encoder".
+fn ffmpeg_with_encoders(command: Command, ffmpeg_path: PathBuf) -> Result<(Command, PathBuf), String> {
+    let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+    let requested = conversion::requested_encoders(&args);
+    let missing_from = |path: &PathBuf| -> Vec<String> {
+        match ffmpeg_encoders(path) {
+            Some(available) => requested.iter().filter(|e| !available.contains(e)).cloned().collect(),
+            None => Vec::new(),
+        }
+    };
+    
+    if requested.is_empty() || !conversion::is_homebrew_path(&ffmpeg_path.to_string_lossy()) {
+        return Ok((command, ffmpeg_path));
+    }
+    
+    let missing = missing_from(&ffmpeg_path);
+    if missing.is_empty() {
+        return Ok((command, ffmpeg_path));
+    }
+    
+    match managed_ffmpeg_path() {
+        Some(managed) if managed != ffmpeg_path && missing_from(&managed).is_empty() => {
+            info!("Homebrew FFmpeg lacks {}, using {} for this job", missing.join(", "), managed.display());
+            let mut fallback = create_command(&managed);
+            fallback.args(command.get_args());
+            for (key, value) in command.get_envs() {
+                if let Some(value) = value {
+                    fallback.env(key, value);
                 }
-                
-                command.arg(&filter);
-                command.arg("-q:v").arg("1");
-            }
-            
-            // AVIF codec options must come AFTER inputs and filters
-            if output_ext == "avif" {
-                // Clear previous arguments to ensure correct order for AVIF
-                // We need to restructure the command completely for AVIF
-                // Logic below handles the reconstruction
-            } else {
-                // For non-AVIF formats, add the input argument here if not already added
-                // (Note: we already added -i input_path above, so this else block is just a placeholder concept)
             }
+            Ok((fallback, managed))
+        }
+        _ => Err(format!(
+            "The Homebrew FFmpeg at {} doesn't include the {} encoder.\n\n\
+            Install the static FFmpeg build from the Tools Manager in Settings to convert this file.",
+            ffmpeg_path.display(),
+            missing.join(", ")
+        )),
+    }
+}
 
-            // AVIF Special Handling based on platform and transparency
-            if output_ext == "avif" {
-                // Reset command arguments for AVIF to ensure correct order
-                // We need to rebuild the command from scratch because AVIF requires specific parameter ordering
-                command = create_command(&tool_path);
-                
-                // Add input file
-                command.arg("-i").arg(input_path);
+/// What a finished conversion command reports back
+struct ToolRun {
+    command_line: String,
+    /// Parsed from FFmpeg's output when it ran at a level that only prints warnings
+    tool_warnings: Vec<ToolWarning>,
+}
 
-                // Check for transparency
-                let has_alpha = has_transparency(input_path);
-                
-                if has_alpha {
-                    // Transparent AVIF settings
-                    command.arg("-map").arg("0:v").arg("-map").arg("0:v");
-                    command.arg("-filter:v:1").arg("alphaextract");
-                    command.arg("-frames:v").arg("1");
-                    command.arg("-c:v").arg("libaom-av1");
-                    command.arg("-still-picture").arg("1");
-                    command.arg("-cpu-used").arg("6");
-                    command.arg("-crf").arg("28");
-                    command.arg("-b:v").arg("0");
-                    command.arg("-row-mt").arg("1");
-                } else {
-                    // Non-transparent AVIF settings
-                    command.arg("-frames:v").arg("1");
-                    command.arg("-c:v").arg("libaom-av1");
-                    command.arg("-still-picture").arg("1");
-                    command.arg("-cpu-used").arg("6");
-                    command.arg("-crf").arg("28");
-                    command.arg("-b:v").arg("0");
-                    command.arg("-row-mt").arg("1");
-                }
+impl From<String> for ToolRun {
+    fn from(command_line: String) -> Self {
+        ToolRun { command_line, tool_warnings: Vec::new() }
+    }
+}
 
-                // Add advanced options if provided
-                if let Some(options) = advanced_options {
-                    let options_parts: Vec<&str> = options.split_whitespace().collect();
-                    for part in options_parts {
-                        command.arg(part);
-                    }
-                }
+/// The warnings FFmpeg printed, when `command` ran it at the warning or error level
+fn ffmpeg_tool_warnings(command: &Command, stderr: &[u8]) -> Vec<ToolWarning> {
+    let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+    let reports_warnings = args
+        .windows(2)
+        .find(|pair| pair[0] == "-loglevel")
+        .is_some_and(|pair| conversion::log_level_reports_warnings(&pair[1]));
+    if !reports_warnings {
+        return Vec::new();
+    }
+    let warnings = conversion::parse_ffmpeg_warnings(&String::from_utf8_lossy(stderr));
+    for warning in &warnings {
+        warn!("FFmpeg warning ({}x): {}", warning.count, warning.message);
+    }
+    warnings
+}
 
-                command.arg("-y").arg(output_path);
-            } else {
-                // Standard handling for other formats (continuation of previous logic)
-                
-                // ICO format requires resizing to max 256x256
-                if output_ext == "ico" {
-                    command.arg("-vf");
-                    command.arg("scale='min(256,iw)':'min(256,ih)':force_original_aspect_ratio=decrease");
-                }
-                
-                // MP4 format: Use compatible settings for broad playback support
-                if output_ext == "mp4" {
-                    command.arg("-pix_fmt").arg("yuv420p");
-                    command.arg("-profile:v").arg("main");
-                    command.arg("-movflags").arg("+faststart");
+/// Runs `command` like Command::output, but passes each line it prints on as it comes: to
+/// the log file and as a "tool-output" event. A run that goes past `limits` is killed and
+/// reported as timed out.
+fn output_streaming(command: &mut Command, tool: &str, output_path: &std::path::Path, limits: Limits) -> Result<std::process::Output, String> {
+    use std::io::Read;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    let mut child = command
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute {}: {}", tool, e))?;
+    let started = Instant::now();
+    let last_output = Arc::new(Mutex::new(started));
+
+    // One thread per pipe, so neither fills up and stalls the tool while the other is read
+    let forward = |pipe: Option<Box<dyn Read + Send>>, stream: &'static str| {
+        let tool = tool.to_string();
+        let output_path = output_path.to_string_lossy().to_string();
+        let last_output = Arc::clone(&last_output);
+        std::thread::spawn(move || {
+            let mut all = Vec::new();
+            let Some(mut pipe) = pipe else {
+                return all;
+            };
+            let mut lines = conversion::OutputLines::default();
+            let mut buffer = [0u8; 8192];
+            let emit = |line: String| {
+                let progress = conversion::is_ffmpeg_stats_line(&line);
+                if !progress {
+                    debug!("[{} {}] {}", tool, stream, line);
                 }
-                
-                // Add advanced options if provided
-                if let Some(options) = advanced_options {
-                    let options_parts: Vec<&str> = options.split_whitespace().collect();
-                    for part in options_parts {
-                        command.arg(part);
-                    }
+                if let Some(app) = APP_HANDLE.get() {
+                    app.emit("tool-output", ToolOutputLine {
+                        output_path: output_path.clone(),
+                        tool: tool.clone(),
+                        stream: stream.to_string(),
+                        line,
+                        progress,
+                    }).ok();
                 }
-                
-                // For video/multi-frame input to single image output, specify one frame
-                // NOTE: gif is excluded because video->gif should create animated GIF
-                let video_formats = ["mp4", "mov", "avi", "mkv", "webm", "flv", "wmv", "m4v", "mpg", "mpeg", "3gp", "ogv"];
-                let static_image_formats = ["jpg", "jpeg", "png", "webp", "bmp", "tiff", "tif", "ico"];
-                
-                if video_formats.contains(&input_ext.as_str()) && static_image_formats.contains(&output_ext.as_str()) {
-                    command.arg("-frames:v").arg("1");
+            };
+            while let Ok(read) = pipe.read(&mut buffer) {
+                if read == 0 {
+                    break;
                 }
-                
-                // For single image output (not animated GIF), use -update flag to write one file (not a sequence)
-                if static_image_formats.contains(&output_ext.as_str()) {
-                    command.arg("-update").arg("1");
+                if let Ok(mut last) = last_output.lock() {
+                    *last = Instant::now();
                 }
-                
-                command.arg("-y").arg(output_path); // -y to overwrite output file
+                all.extend_from_slice(&buffer[..read]);
+                lines.push(&buffer[..read]).into_iter().for_each(&emit);
             }
-        }
-        "pandoc" if ENABLE_PANDOC => {
-            command.arg(input_path).arg("-o").arg(output_path);
-            
-            // Add advanced options if provided
-            if let Some(options) = advanced_options {
-                let options_parts: Vec<&str> = options.split_whitespace().collect();
-                for part in options_parts {
-                    command.arg(part);
-                }
+            lines.finish().into_iter().for_each(&emit);
+            all
+        })
+    };
+    let stdout = forward(child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>), "stdout");
+    let stderr = forward(child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>), "stderr");
+
+    let status = if limits.is_unlimited() {
+        child.wait().map_err(|e| format!("Failed to execute {}: {}", tool, e))?
+    } else {
+        loop {
+            if let Some(status) = child.try_wait().map_err(|e| format!("Failed to execute {}: {}", tool, e))? {
+                break status;
+            }
+            let last = last_output.lock().map(|last| *last).unwrap_or(started);
+            if let Some(hang) = limits.check(started, last, Instant::now()) {
+                let message = watchdog::hang_message(tool, hang);
+                error!("{}", message);
+                let _ = child.kill();
+                let _ = child.wait();
+                // The readers are left to finish on their own: a helper the tool started
+                // can keep the pipes open after the tool itself is gone
+                return Err(message);
             }
+            std::thread::sleep(watchdog::POLL_INTERVAL);
         }
-        _ => return Err(format!("Unknown tool: {}", tool_name)),
+    };
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Runs a tool with output_streaming under the time limits in `settings`. Only FFmpeg
+/// prints while it works (its -stats), so silence means a stall for it alone.
+fn output_with_limits(command: &mut Command, tool: &str, output_path: &std::path::Path, settings: &ConversionSettings) -> Result<std::process::Output, String> {
+    let reports_progress = tool == "ffmpeg" && command.get_args().any(|arg| arg == "-stats");
+    let limits = Limits::from_minutes(settings.timeout_minutes, settings.stall_minutes, reports_progress);
+    output_streaming(command, tool, output_path, limits)
+}
+
+async fn execute_conversion(
+    tool_name: &str,
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<ToolRun, String> {
+    let (command, actual_tool, tool_path, _job_dir) = match prepare_conversion(tool_name, input_path, output_path, advanced_options.clone(), settings)? {
+        PreparedConversion::Copy => {
+            info!("Performing file rename/copy from {} to {}", input_path.display(), output_path.display());
+            std::fs::copy(input_path, output_path)
+                .map_err(|e| format!("Failed to copy file: {}", e))?;
+            return Ok(copy_command_line(input_path, output_path).into());
+        }
+        PreparedConversion::HeicTiles(tool_path) => {
+            return convert_heic_with_tiles(&tool_path, input_path, output_path, settings).map(ToolRun::from);
+        }
+        PreparedConversion::RenderSvg => {
+            return render_svg(input_path, output_path, advanced_options, settings).map(ToolRun::from);
+        }
+        PreparedConversion::DevelopRaw(tool_path) => {
+            return develop_raw(&tool_path, input_path, output_path, advanced_options, settings).map(ToolRun::from);
+        }
+        PreparedConversion::Builtin => {
+            return convert_builtin(input_path, output_path, settings).map(ToolRun::from);
+        }
+        PreparedConversion::Command { command, tool, tool_path, job_dir } => (command, tool, tool_path, job_dir),
+    };
+    
+    if actual_tool == "libreoffice" {
+        return run_libreoffice(command, input_path, output_path, settings).map(ToolRun::from);
+    }
+    run_conversion_command(command, &actual_tool, tool_name, &tool_path, input_path, output_path, settings)
+}
+
+/// `command` set up the way every conversion tool runs: with the settings' environment
+/// and priority. output_with_limits then streams its output and applies the time limits.
+fn tool_command(mut command: Command, settings: &ConversionSettings) -> Command {
+    set_deterministic_env(&mut command, settings);
+    if settings.low_priority {
+        command = at_low_priority(command);
     }
+    command
+}
+
+/// Runs a prepared conversion command under the settings' priority and time limits, with
+/// its output streamed as it comes. A failure is explained in plain words and gets a job log.
+fn run_conversion_command(
+    mut command: Command,
+    actual_tool: &str,
+    tool_name: &str,
+    // Only needed for the macOS failure diagnostics below
+    #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+    tool_path: &PathBuf,
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    settings: &ConversionSettings,
+) -> Result<ToolRun, String> {
+    let output_ext = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
     
     // Log the actual command being executed
     debug!("Executing command: {:?}", command);
     
-    let output = command.output()
-        .map_err(|e| format!("Failed to execute {}: {}", tool_name, e))?;
+    let started_at = chrono::Utc::now();
+    let started = std::time::Instant::now();
+    command = tool_command(command, settings);
+    let output = match output_with_limits(&mut command, actual_tool, output_path, settings) {
+        Ok(output) => output,
+        Err(e) => {
+            if e.starts_with(watchdog::TIMEOUT_PREFIX) {
+                let _ = std::fs::remove_file(output_path);
+            }
+            return Err(e);
+        }
+    };
     
     if output.status.success() {
-        Ok(())
+        if actual_tool == "chromium" && settings.full_page && output_ext == "png" {
+            trim_snapshot_bottom(output_path);
+        }
+        let tool_warnings = if actual_tool == "ffmpeg" { ffmpeg_tool_warnings(&command, &output.stderr) } else { Vec::new() };
+        Ok(ToolRun { command_line: command_line(&command), tool_warnings })
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -2133,22 +6417,572 @@ async fn execute_conversion(
             // For other errors, show the technical details
             format!("Conversion failed. Error details: {}", stderr)
         };
-        
-        Err(error_msg)
+        
+        let job_log = JobLog {
+            tool: actual_tool.to_string(),
+            input_path: input_path.to_string_lossy().to_string(),
+            output_path: output_path.to_string_lossy().to_string(),
+            command_line: command_line(&command),
+            env: command_env(&command),
+            started_at: started_at.to_rfc3339(),
+            duration_secs: started.elapsed().as_secs_f64(),
+            exit_status: output.status.to_string(),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        };
+        match write_job_log(&job_log, &started_at.format("%Y%m%d-%H%M%S").to_string(), input_path) {
+            Some(log_path) => Err(job_log::with_log_path(&error_msg, &log_path)),
+            None => Err(error_msg),
+        }
+    }
+}
+
+/// Variables set (or removed) on `command`, for its job log
+fn command_env(command: &Command) -> Vec<(String, Option<String>)> {
+    command
+        .get_envs()
+        .map(|(key, value)| (key.to_string_lossy().to_string(), value.map(|value| value.to_string_lossy().to_string())))
+        .collect()
+}
+
+/// Writes a failed run's job log to the log directory and deletes the oldest ones beyond
+/// MAX_JOB_LOGS. Returns where it went, or None if it couldn't be written.
+fn write_job_log(log: &JobLog, stamp: &str, input_path: &std::path::Path) -> Option<PathBuf> {
+    let dir = APP_HANDLE.get()?.path().app_log_dir().ok()?.join(job_log::JOB_LOG_DIR);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        warn!("Could not create the job log folder {}: {}", dir.display(), e);
+        return None;
+    }
+    let path = dir.join(job_log::job_log_file_name(stamp, NEXT_JOB_LOG.fetch_add(1, Ordering::Relaxed), &log.tool, input_path));
+    if let Err(e) = std::fs::write(&path, log.render()) {
+        warn!("Could not write the job log {}: {}", path.display(), e);
+        return None;
+    }
+    info!("Job log written to {}", path.display());
+
+    let names = std::fs::read_dir(&dir)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    for name in job_log::logs_to_prune(names, job_log::MAX_JOB_LOGS) {
+        let _ = std::fs::remove_file(dir.join(name));
+    }
+    Some(path)
+}
+
+/// Rasterize an SVG/SVGZ with resvg at the size from the settings. PNG output is written
+/// directly; other formats are rendered to a PNG in a job directory and converted from there.
+fn render_svg(
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    use resvg::{tiny_skia, usvg};
+    
+    let data = std::fs::read(input_path)
+        .map_err(|e| format!("Failed to read SVG: {}", e))?;
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    // Relative image references resolve against the SVG's own folder
+    options.resources_dir = input_path.parent().map(|dir| dir.to_path_buf());
+    let tree = usvg::Tree::from_data(&data, &options)
+        .map_err(|e| format!("Could not read this SVG: {}", e))?;
+    
+    let size = tree.size();
+    let (width, height) = conversion::svg_raster_size(
+        size.width(),
+        size.height(),
+        settings.raster_width,
+        settings.raster_height,
+        settings.raster_dpi,
+    );
+    info!("Rendering {} at {}x{} with resvg", input_path.display(), width, height);
+    
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("Cannot render an SVG at {}x{}", width, height))?;
+    let transform = tiny_skia::Transform::from_scale(width as f32 / size.width(), height as f32 / size.height());
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    
+    let output_ext = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let render_line = |png_path: &PathBuf| conversion::format_command_line("resvg", &[
+        "-w".to_string(),
+        width.to_string(),
+        "-h".to_string(),
+        height.to_string(),
+        input_path.to_string_lossy().to_string(),
+        png_path.to_string_lossy().to_string(),
+    ]);
+    
+    if output_ext == "png" {
+        pixmap.save_png(output_path)
+            .map_err(|e| format!("Failed to save rendered SVG: {}", e))?;
+        return Ok(render_line(output_path));
+    }
+    
+    // Other formats (JPG flattening, ICO sizing, ...) are handled by the usual PNG conversion
+    let job_dir = JobDir::create()?;
+    let png_path = job_dir.path.join("render.png");
+    pixmap.save_png(&png_path)
+        .map_err(|e| format!("Failed to save rendered SVG: {}", e))?;
+    
+    let tool = determine_conversion_tool("png", &output_ext)
+        .ok_or_else(|| format!("No conversion tool available for png to {}", output_ext))?;
+    let mut command = match prepare_conversion(tool, &png_path, output_path, advanced_options, settings)? {
+        PreparedConversion::Command { command, .. } => command,
+        PreparedConversion::Builtin => {
+            let converted = convert_builtin(&png_path, output_path, settings)?;
+            return Ok(format!("{} && {}", render_line(&png_path), converted));
+        }
+        _ => return Err(format!("Cannot convert a rendered SVG to {}", output_ext)),
+    };
+    
+    debug!("Executing command: {:?}", command);
+    let output = command.output()
+        .map_err(|e| format!("Failed to execute {}: {}", tool, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Converting rendered SVG failed: {}", stderr);
+        return Err(format!("Conversion failed. Error details: {}", stderr));
+    }
+    
+    Ok(format!("{} && {}", render_line(&png_path), command_line(&command)))
+}
+
+/// Develop a camera RAW photo with LibRaw into a TIFF in a job directory (white balance,
+/// exposure and demosaicing from the RAW options), then convert that with ImageMagick
+fn develop_raw(
+    tool_path: &PathBuf,
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    advanced_options: Option<String>,
+    settings: &ConversionSettings,
+) -> Result<String, String> {
+    let job_dir = JobDir::create()?;
+    let tiff_path = job_dir.path.join("developed.tiff");
+    let args = conversion::libraw_args(&input_path.to_string_lossy(), &tiff_path.to_string_lossy(), &settings.raw);
+    let mut develop = create_command(tool_path);
+    develop.args(&args);
+    info!("Developing {} with LibRaw", input_path.display());
+    debug!("Executing command: {:?}", develop);
+    let output = output_with_limits(&mut develop, "libraw", output_path, settings)
+        .map_err(|e| format!("Failed to execute LibRaw: {}", e))?;
+    if !output.status.success() || !tiff_path.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Developing RAW photo failed: {}", stderr);
+        return Err(format!("Could not develop this RAW photo. Error details: {}", stderr));
+    }
+    
+    // A 16-bit development stays 16-bit wherever the output format allows it
+    let mut developed = settings.clone();
+    developed.color.keep_16_bit |= settings.raw.bit_depth == 16;
+    let mut command = match prepare_conversion("imagemagick", &tiff_path, output_path, advanced_options, &developed)? {
+        PreparedConversion::Command { command, .. } => command,
+        PreparedConversion::Builtin => {
+            let converted = convert_builtin(&tiff_path, output_path, &developed)?;
+            return Ok(format!("{} && {}", command_line(&develop), converted));
+        }
+        _ => return Err("Cannot convert the developed RAW photo".to_string()),
+    };
+    
+    debug!("Executing command: {:?}", command);
+    let output = output_with_limits(&mut command, "imagemagick", output_path, settings)
+        .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Converting developed RAW photo failed: {}", stderr);
+        return Err(format!("Conversion failed. Error details: {}", stderr));
+    }
+    
+    Ok(format!("{} && {}", command_line(&develop), command_line(&command)))
+}
+
+/// Stand-in command line for conversions done by the built-in converter
+fn builtin_command_line(input_path: &PathBuf, output_path: &PathBuf) -> String {
+    let paths = [input_path.to_string_lossy().to_string(), output_path.to_string_lossy().to_string()];
+    conversion::format_command_line(conversion::BUILTIN_TOOL, &paths)
+}
+
+/// Convert a calendar or contact list to or from CSV (see pim)
+fn convert_pim(input_path: &PathBuf, output_path: &PathBuf, input_ext: &str, output_ext: &str) -> Result<String, String> {
+    info!("Converting {} to {} with the built-in converter", input_path.display(), output_ext.to_uppercase());
+    let bytes = std::fs::read(input_path).map_err(|e| format!("Failed to read input file: {}", e))?;
+    let converted = pim::convert(&String::from_utf8_lossy(&bytes), input_ext, output_ext, chrono::Utc::now().naive_utc())?;
+    std::fs::write(output_path, converted).map_err(|e| format!("Failed to write output file: {}", e))?;
+    Ok(builtin_command_line(input_path, output_path))
+}
+
+/// Convert between common image formats with the `image` crate, for when no external tool
+/// is installed. Transparent images are flattened onto white for formats without alpha.
+/// Calendars and contacts are handed to convert_pim.
+fn convert_builtin(input_path: &PathBuf, output_path: &PathBuf, settings: &ConversionSettings) -> Result<String, String> {
+    let output_ext = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let input_ext = input_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if pim::supports_pim_conversion(&input_ext, &output_ext) {
+        return convert_pim(input_path, output_path, &input_ext, &output_ext);
+    }
+    let format = image::ImageFormat::from_extension(&output_ext)
+        .ok_or_else(|| format!("The built-in converter can't write {}", output_ext.to_uppercase()))?;
+    
+    // The image crate writes no color profile, so there's nothing to convert to
+    if settings.color.convert_to_srgb {
+        return Err("Converting colors to sRGB needs ImageMagick".to_string());
+    }
+    
+    info!("Converting {} with the built-in converter", input_path.display());
+    let image = image::ImageReader::open(input_path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to read image: {}", e))?
+        .decode()
+        .map_err(|e| format!("Could not decode this image: {}", e))?;
+    
+    // The image crate leaves EXIF orientation to the caller
+    let orientation = if settings.keep_orientation { 1 } else { read_exif_orientation(input_path) };
+    let image = match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    };
+    
+    let image = match settings.crop.as_ref().map(|crop| crop.region()).transpose()?.flatten() {
+        Some(region) => {
+            let (x, y, width, height) = region.rect_within(image.width(), image.height());
+            image.crop_imm(x, y, width, height)
+        }
+        None => image,
+    };
+    
+    let image = match &settings.watermark {
+        Some(watermark) => draw_builtin_watermark(image, watermark)?,
+        None => image,
+    };
+    
+    let image = if conversion::OPAQUE_OUTPUTS.contains(&output_ext.as_str()) && image.color().has_alpha() {
+        let mut rgba = image.to_rgba8();
+        for pixel in rgba.pixels_mut() {
+            let alpha = pixel[3];
+            for channel in 0..3 {
+                pixel[channel] = conversion::blend_over_white(pixel[channel], alpha);
+            }
+            pixel[3] = 255;
+        }
+        image::DynamicImage::ImageRgb8(image::DynamicImage::ImageRgba8(rgba).to_rgb8())
+    } else {
+        image
+    };
+    
+    let saved = if format == image::ImageFormat::Jpeg {
+        let file = std::fs::File::create(output_path)
+            .map_err(|e| format!("Failed to create output file: {}", e))?;
+        let quality = settings.quality.unwrap_or(90).clamp(1, 100);
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(std::io::BufWriter::new(file), quality);
+        encoder.encode_image(&image)
+    } else {
+        image.save_with_format(output_path, format)
+    };
+    saved.map_err(|e| format!("Failed to save converted image: {}", e))?;
+    
+    Ok(builtin_command_line(input_path, output_path))
+}
+
+/// Draws a picture watermark for the built-in converter, which can't render text
+fn draw_builtin_watermark(image: image::DynamicImage, watermark: &Watermark) -> Result<image::DynamicImage, String> {
+    let path = watermark.image().ok_or("Text watermarks need ImageMagick or FFmpeg")?;
+    let logo = image::open(path).map_err(|e| format!("Could not read the watermark picture: {}", e))?;
+    let width = watermark.image_width(image.width());
+    let height = ((logo.height() as u64 * width as u64) / logo.width().max(1) as u64).max(1) as u32;
+    let mut logo = logo.resize_exact(width, height, image::imageops::FilterType::Lanczos3).to_rgba8();
+    for pixel in logo.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * watermark.opacity).round() as u8;
+    }
+    
+    let (x, y) = watermark.origin(image.width(), image.height(), width, height);
+    let mut base = image.to_rgba8();
+    image::imageops::overlay(&mut base, &logo, x as i64, y as i64);
+    Ok(image::DynamicImage::ImageRgba8(base))
+}
+
+/// The file LibreOffice should export to PDF: the input itself, or for spreadsheet layouts
+/// it can't apply on export (orientation, gridlines, sheet choice) a patched copy of the
+/// XLSX in `job_dir`. The copy keeps the input's file name, so the output is named the same.
+fn libreoffice_pdf_source(
+    input_path: &PathBuf,
+    input_ext: &str,
+    layout: &SpreadsheetLayout,
+    job_dir: &mut Option<JobDir>,
+) -> Result<PathBuf, String> {
+    if !layout.needs_workbook_changes() {
+        return Ok(input_path.clone());
+    }
+    if input_ext != "xlsx" {
+        return Err("Orientation, gridlines and sheet selection are only available for XLSX spreadsheets".to_string());
+    }
+    
+    if job_dir.is_none() {
+        *job_dir = Some(JobDir::create()?);
+    }
+    let dir = job_dir.as_ref().map(|dir| dir.path.clone()).ok_or("Failed to create job directory")?;
+    let patched = dir.join(input_path.file_name().ok_or("Invalid input file")?);
+    patch_xlsx_layout(input_path, &patched, layout)?;
+    Ok(patched)
+}
+
+/// Copies an XLSX with the layout written into its workbook and worksheet parts; every
+/// other part is copied as is, without recompressing
+fn patch_xlsx_layout(source: &PathBuf, destination: &PathBuf, layout: &SpreadsheetLayout) -> Result<(), String> {
+    use std::io::{Read, Write};
+    
+    let file = std::fs::File::open(source)
+        .map_err(|e| format!("Failed to open spreadsheet: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Not a valid XLSX file: {}", e))?;
+    let output = std::fs::File::create(destination)
+        .map_err(|e| format!("Failed to create spreadsheet copy: {}", e))?;
+    let mut writer = zip::ZipWriter::new(output);
+    let write_error = |e: &dyn std::fmt::Display| format!("Failed to write spreadsheet copy: {}", e);
+    
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)
+            .map_err(|e| format!("Failed to read spreadsheet: {}", e))?;
+        let name = entry.name().to_string();
+        let is_worksheet = name.starts_with("xl/worksheets/") && name.ends_with(".xml") && !name[14..].contains('/');
+        let patch_workbook = name == "xl/workbook.xml" && !layout.sheets.is_empty();
+        let patch_sheet = is_worksheet && layout.needs_worksheet_changes();
+        if !patch_workbook && !patch_sheet {
+            writer.raw_copy_file(entry).map_err(|e| write_error(&e))?;
+            continue;
+        }
+        
+        let mut xml = String::new();
+        entry.read_to_string(&mut xml)
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        let xml = if patch_workbook {
+            spreadsheet::select_sheets(&xml, &layout.sheets)?
+        } else {
+            spreadsheet::patch_worksheet(&xml, layout)
+        };
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        writer.start_file(name, options).map_err(|e| write_error(&e))?;
+        writer.write_all(xml.as_bytes()).map_err(|e| write_error(&e))?;
+    }
+    writer.finish().map_err(|e| write_error(&e))?;
+    Ok(())
+}
+
+/// LibreOffice writes into a staging directory per output file (see prepare_conversion)
+fn libreoffice_staging_dir(output_path: &PathBuf) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    output_path.hash(&mut hasher);
+    std::env::temp_dir().join(format!("convertsave-office-{}-{:x}", std::process::id(), hasher.finish()))
+}
+
+/// Our own LibreOffice profile, kept apart from the user's so an open LibreOffice window
+/// doesn't take over headless conversions
+fn libreoffice_profile_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(APP_IDENTIFIER)
+        .join("libreoffice-profile")
+}
+
+/// Run a prepared LibreOffice conversion and move the result to output_path. Its output is
+/// streamed like any other tool's (see tool_command).
+fn run_libreoffice(command: Command, input_path: &PathBuf, output_path: &PathBuf, settings: &ConversionSettings) -> Result<String, String> {
+    let output_ext = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let staging_dir = libreoffice_staging_dir(output_path);
+    std::fs::create_dir_all(&staging_dir)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    
+    let mut command = tool_command(command, settings);
+    // PDF export options may carry a password
+    let logged_command = conversion::redact_pdf_password(&command_line(&command));
+    debug!("Executing command: {}", logged_command);
+    
+    let (started_at, started, output) = {
+        // Instances sharing a profile hand their work to the first one, so run one at a time
+        let _guard = LIBREOFFICE_LOCK.lock().map_err(|_| "LibreOffice is busy".to_string())?;
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+        (started_at, started, output_with_limits(&mut command, "libreoffice", output_path, settings))
+    };
+    
+    let result = output.and_then(|output| {
+        let staged = staging_dir.join(conversion::libreoffice_output_name(&input_path.to_string_lossy(), &output_ext));
+        
+        // soffice can exit successfully without writing anything, so check for the file itself
+        if !staged.exists() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            error!("=== LIBREOFFICE FAILED ===");
+            error!("Exit status: {:?}", output.status);
+            error!("STDOUT:\n{}", stdout);
+            error!("STDERR:\n{}", stderr);
+            error!("=========================");
+            
+            let job_log = JobLog {
+                tool: "libreoffice".to_string(),
+                input_path: input_path.to_string_lossy().to_string(),
+                output_path: output_path.to_string_lossy().to_string(),
+                command_line: logged_command.clone(),
+                env: command_env(&command),
+                started_at: started_at.to_rfc3339(),
+                duration_secs: started.elapsed().as_secs_f64(),
+                exit_status: output.status.to_string(),
+                stdout: stdout.to_string(),
+                stderr: stderr.to_string(),
+            };
+            let details = if stderr.trim().is_empty() { stdout } else { stderr };
+            let error_msg = format!("LibreOffice could not convert this file. Error details: {}", details.trim());
+            return match write_job_log(&job_log, &started_at.format("%Y%m%d-%H%M%S").to_string(), input_path) {
+                Some(log_path) => Err(job_log::with_log_path(&error_msg, &log_path)),
+                None => Err(error_msg),
+            };
+        }
+        
+        // rename fails across drives, so fall back to copying
+        if std::fs::rename(&staged, output_path).is_err() {
+            std::fs::copy(&staged, output_path)
+                .map_err(|e| format!("Failed to save converted file: {}", e))?;
+        }
+        Ok(logged_command)
+    });
+    
+    let _ = std::fs::remove_dir_all(&staging_dir);
+    result
+}
+
+// Binary download functions
+
+/// Sends the request `request` builds, retrying connection failures, timeouts and
+/// temporary server errors as `policy` allows. Each retry is logged and emitted as
+/// "network-retry". Returns the last response or error, like RequestBuilder::send.
+async fn send_with_retry(
+    operation: &str,
+    policy: RetryPolicy,
+    request: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let started = std::time::Instant::now();
+    let mut attempt = 1;
+    loop {
+        let result = request().send().await;
+        let failure = match &result {
+            Ok(response) if policy.retries_status(response.status().as_u16()) => format!("HTTP {}", response.status()),
+            Ok(_) => return result,
+            // A request that may have reached the server is only sent again if that's harmless
+            Err(e) if e.is_connect() || (policy.idempotent && (e.is_timeout() || e.is_request())) => e.to_string(),
+            Err(_) => return result,
+        };
+        let Some(delay) = policy.next_delay(attempt, started.elapsed(), retry::clock_jitter()) else {
+            warn!("{} failed after {} attempts: {}", operation, attempt, failure);
+            return result;
+        };
+        attempt += 1;
+        notify_retry(operation, policy, attempt, delay, failure);
+        tokio::time::sleep(delay).await;
     }
 }
 
-// Binary download functions
+/// Downloads the body of the request `request` builds, with send_with_retry's retries. A
+/// connection that drops while the body is read starts the download over, as `policy`
+/// allows. A status other than success comes back with an empty body for the caller to report.
+async fn download_with_retry(
+    operation: &str,
+    policy: RetryPolicy,
+    request: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<(reqwest::StatusCode, Vec<u8>), reqwest::Error> {
+    let started = std::time::Instant::now();
+    let mut attempt = 1;
+    loop {
+        let response = send_with_retry(operation, policy, &request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Ok((status, Vec::new()));
+        }
+        let error = match response.bytes().await {
+            Ok(bytes) => return Ok((status, bytes.into())),
+            Err(e) => e,
+        };
+        let Some(delay) = policy.next_delay(attempt, started.elapsed(), retry::clock_jitter()) else {
+            warn!("{} failed after {} attempts: {}", operation, attempt, error);
+            return Err(error);
+        };
+        attempt += 1;
+        notify_retry(operation, policy, attempt, delay, error.to_string());
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Logs a retry and emits it as "network-retry"
+fn notify_retry(operation: &str, policy: RetryPolicy, attempt: u32, delay: std::time::Duration, error: String) {
+    warn!("{} failed ({}), attempt {} of {} in {:.1}s", operation, error, attempt, policy.attempts, delay.as_secs_f64());
+    if let Some(app) = APP_HANDLE.get() {
+        app.emit("network-retry", RetryEvent {
+            operation: operation.to_string(),
+            attempt,
+            attempts: policy.attempts,
+            delay_ms: delay.as_millis() as u64,
+            error,
+        }).ok();
+    }
+}
+
+/// Routes `builder` through the configured proxy, if any
+fn with_proxy(builder: reqwest::ClientBuilder) -> Result<reqwest::ClientBuilder, String> {
+    match load_config().ok().and_then(|config| config.proxy).filter(|proxy| !proxy.trim().is_empty()) {
+        Some(proxy) => {
+            let proxy = reqwest::Proxy::all(proxy.trim()).map_err(|e| format!("Invalid proxy \"{}\": {}", proxy, e))?;
+            Ok(builder.proxy(proxy))
+        }
+        None => Ok(builder),
+    }
+}
+
+/// Create an HTTP client for API calls (version checks, licensing)
+fn create_api_client() -> Result<reqwest::Client, String> {
+    with_proxy(reqwest::Client::builder())?
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
 
 /// Create a configured HTTP client for downloads
 fn create_http_client() -> Result<reqwest::Client, String> {
-    reqwest::Client::builder()
+    with_proxy(reqwest::Client::builder())?
         .timeout(std::time::Duration::from_secs(300)) // 5 minute timeout
         .user_agent("ConvertSave/1.0")
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))
 }
 
+/// The app updater, through the configured proxy
+fn app_updater(app: &AppHandle) -> Result<tauri_plugin_updater::Updater, String> {
+    let mut builder = app.updater_builder();
+    if let Some(proxy) = load_config().ok().and_then(|config| config.proxy).filter(|proxy| !proxy.trim().is_empty()) {
+        let url = reqwest::Url::parse(proxy.trim()).map_err(|e| format!("Invalid proxy \"{}\": {}", proxy, e))?;
+        builder = builder.proxy(url);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
 /// Check if Homebrew is available on the system
 #[cfg(target_os = "macos")]
 fn is_homebrew_available() -> bool {
@@ -2301,12 +7135,18 @@ async fn install_via_homebrew(app: AppHandle, package: &str) -> Result<String, S
     Ok(format!("{} installed successfully via Homebrew", package))
 }
 
+/// Install FFmpeg. `static_build` skips Homebrew on macOS and downloads the static build,
+/// which includes encoders Homebrew's FFmpeg leaves out.
 #[tauri::command]
-async fn download_ffmpeg(app: AppHandle) -> Result<String, String> {
+async fn download_ffmpeg(
+    app: AppHandle,
+    #[cfg_attr(not(target_os = "macos"), allow(unused_variables))] static_build: Option<bool>,
+) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
     // On macOS, prefer Homebrew but fall back to manual download
     #[cfg(target_os = "macos")]
     {
-        if is_homebrew_available() {
+        if !static_build.unwrap_or(false) && is_homebrew_available() {
             app.emit("download-progress", DownloadProgress {
                 status: "checking".to_string(),
                 message: "Using Homebrew for installation...".to_string(),
@@ -2336,16 +7176,14 @@ async fn download_ffmpeg(app: AppHandle) -> Result<String, String> {
         }).map_err(|e| e.to_string())?;
         
         let client = create_http_client()?;
-        let response = client.get(&download_url).send().await.map_err(|e| {
+        let (status, bytes) = download_with_retry("Downloading FFmpeg", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
             format!("Failed to download FFmpeg: {}. Try again or check your internet connection.", e)
         })?;
         
-        if !response.status().is_success() {
-            return Err(format!("Download failed with status: {}. The file may not be available.", response.status()));
+        if !status.is_success() {
+            return Err(format!("Download failed with status: {}. The file may not be available.", status));
         }
         
-        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-        
         let archive_path = data_dir.join(&filename);
         std::fs::write(&archive_path, bytes).map_err(|e| e.to_string())?;
         
@@ -2380,6 +7218,7 @@ async fn download_ffmpeg(app: AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 async fn download_pandoc(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
     // On macOS, prefer Homebrew but fall back to manual download
     #[cfg(target_os = "macos")]
     {
@@ -2413,16 +7252,14 @@ async fn download_pandoc(app: AppHandle) -> Result<String, String> {
         }).map_err(|e| e.to_string())?;
         
         let client = create_http_client()?;
-        let response = client.get(&download_url).send().await.map_err(|e| {
+        let (status, bytes) = download_with_retry("Downloading Pandoc", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
             format!("Failed to download Pandoc: {}. Try again or check your internet connection.", e)
         })?;
         
-        if !response.status().is_success() {
-            return Err(format!("Download failed with status: {}. The file may not be available.", response.status()));
+        if !status.is_success() {
+            return Err(format!("Download failed with status: {}. The file may not be available.", status));
         }
         
-        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-        
         let archive_path = data_dir.join(&filename);
         std::fs::write(&archive_path, bytes).map_err(|e| e.to_string())?;
         
@@ -2457,6 +7294,7 @@ async fn download_pandoc(app: AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
     // On macOS, prefer Homebrew but fall back to manual download
     #[cfg(target_os = "macos")]
     {
@@ -2508,8 +7346,7 @@ async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
     // Create a properly configured HTTP client
     let client = create_http_client()?;
     
-    let response = client.get(&download_url)
-        .send()
+    let (status, bytes) = download_with_retry("Downloading ImageMagick", RetryPolicy::DOWNLOAD, || client.get(&download_url))
         .await
         .map_err(|e| {
             println!("Download request failed: {}", e);
@@ -2523,17 +7360,12 @@ async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
             }
         })?;
     
-    println!("Download response status: {:?}", response.status());
+    println!("Download response status: {:?}", status);
     
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {}. The file may not be available.", response.status()));
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
     }
     
-    let bytes = response.bytes().await.map_err(|e| {
-        println!("Failed to read bytes: {}", e);
-        format!("Failed to read download data: {}", e)
-    })?;
-    
     println!("Downloaded {} bytes", bytes.len());
     
     let extract_dir = data_dir.join("imagemagick");
@@ -2558,34 +7390,18 @@ async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
         }
     } else {
         // Windows and macOS need extraction
-        let archive_path = data_dir.join(&filename);
-        println!("Writing archive to: {}", archive_path.display());
-        
-        std::fs::write(&archive_path, bytes).map_err(|e| {
-            println!("Failed to write archive: {}", e);
-            e.to_string()
-        })?;
-        
-        println!("Archive written successfully, size: {} bytes", std::fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0));
-        
-        app.emit("download-progress", DownloadProgress {
-            status: "extracting".to_string(),
-            message: "Extracting ImageMagick...".to_string(),
-        }).map_err(|e| e.to_string())?;
-        
         println!("Starting extraction to: {}", extract_dir.display());
         println!("Looking for binary: {}", magick_exe);
         
         // Windows uses .7z, macOS uses .tar.gz
         if is_sevenz {
-            // ImageMagick portable .7z archive (Windows)
+            // ImageMagick portable .7z archive (Windows) - extracted straight from memory,
+            // so the archive never sits on disk next to its own contents
             println!("Extracting ImageMagick .7z archive...");
-            sevenz_rust::decompress_file(&archive_path, &extract_dir)
-                .map_err(|e| {
-                    println!("7z extraction failed: {}", e);
-                    std::fs::remove_file(&archive_path).ok();
-                    format!("Failed to extract ImageMagick .7z: {}", e)
-                })?;
+            extract_7z_streaming(&app, &bytes, &extract_dir, "ImageMagick").map_err(|e| {
+                println!("7z extraction failed: {}", e);
+                e
+            })?;
             println!("ImageMagick extraction successful!");
             
             // Check if files are in a subdirectory and move them up if needed
@@ -2645,6 +7461,21 @@ async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
                 }
             }
         } else {
+            let archive_path = data_dir.join(&filename);
+            println!("Writing archive to: {}", archive_path.display());
+            
+            std::fs::write(&archive_path, bytes).map_err(|e| {
+                println!("Failed to write archive: {}", e);
+                e.to_string()
+            })?;
+            
+            println!("Archive written successfully, size: {} bytes", std::fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0));
+            
+            app.emit("download-progress", DownloadProgress {
+                status: "extracting".to_string(),
+                message: "Extracting ImageMagick...".to_string(),
+            }).map_err(|e| e.to_string())?;
+            
             // macOS tar.gz extraction - Keep ImageMagick structure as-is (bin/ and lib/ directories)
             // This matches what the official ImageMagick documentation says to do
             println!("Extracting tarball to: {}", extract_dir.display());
@@ -2675,60 +7506,606 @@ async fn download_imagemagick(app: AppHandle) -> Result<String, String> {
             if let Ok(entries) = std::fs::read_dir(&extract_dir) {
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.is_dir() && path.file_name().unwrap().to_string_lossy().starts_with("ImageMagick") {
-                        imagemagick_root = Some(path);
-                        break;
+                    if path.is_dir() && path.file_name().unwrap().to_string_lossy().starts_with("ImageMagick") {
+                        imagemagick_root = Some(path);
+                        break;
+                    }
+                }
+            }
+            
+            if let Some(im_root) = imagemagick_root {
+                println!("Found ImageMagick root directory: {}", im_root.display());
+                
+                // Move all subdirectories (bin/, lib/, etc.) to extract_dir
+                if let Ok(entries) = std::fs::read_dir(&im_root) {
+                    for entry in entries.flatten() {
+                        let source_path = entry.path();
+                        let name = source_path.file_name().unwrap();
+                        let dest_path = extract_dir.join(name);
+                        
+                        println!("Moving {} to {}", source_path.display(), dest_path.display());
+                        if let Err(e) = std::fs::rename(&source_path, &dest_path) {
+                            println!("Failed to move {}: {}", source_path.display(), e);
+                        }
+                    }
+                }
+                
+                // Clean up the now-empty ImageMagick directory
+                let _ = std::fs::remove_dir_all(&im_root);
+                
+                println!("Final structure:");
+                list_all_files(&extract_dir, "");
+            }
+            
+            println!("Removing archive file: {}", archive_path.display());
+            std::fs::remove_file(&archive_path).map_err(|e| e.to_string())?;
+        }
+    }
+    
+    // Verify the file was actually extracted
+    if !magick_path.exists() {
+        println!("ERROR: ImageMagick binary still not found at: {}", magick_path.display());
+        println!("Final directory contents:");
+        if let Ok(entries) = std::fs::read_dir(extract_dir) {
+            for entry in entries.flatten() {
+                println!("  - {}", entry.path().display());
+            }
+        }
+        return Err(format!("ImageMagick binary not found after extraction at: {}", magick_path.display()));
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "ImageMagick downloaded successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("ImageMagick downloaded successfully".to_string())
+}
+
+#[tauri::command]
+async fn download_inkscape(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
+    // On macOS, Inkscape is only distributed as an app bundle, so install it via Homebrew
+    #[cfg(target_os = "macos")]
+    {
+        if is_homebrew_available() {
+            app.emit("download-progress", DownloadProgress {
+                status: "checking".to_string(),
+                message: "Using Homebrew for installation...".to_string(),
+            }).ok();
+            return install_via_homebrew(app, "inkscape").await;
+        }
+        // Fall through - get_inkscape_download_info explains the manual install
+    }
+    
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    
+    let (download_url, _filename, is_sevenz) = get_inkscape_download_info().await?;
+    info!("Downloading Inkscape from: {}", download_url);
+    
+    let inkscape_exe = if cfg!(windows) { "inkscape.exe" } else { "inkscape" };
+    let inkscape_dir = data_dir.join("inkscape");
+    
+    // The portable Windows build keeps inkscape.exe in bin/ next to its DLLs,
+    // the Linux AppImage is a single file in the root
+    let inkscape_path = if is_sevenz {
+        inkscape_dir.join("bin").join(inkscape_exe)
+    } else {
+        inkscape_dir.join(inkscape_exe)
+    };
+    
+    // If Inkscape already exists, remove it to allow updating
+    if inkscape_dir.exists() {
+        info!("Removing existing Inkscape installation for update...");
+        std::fs::remove_dir_all(&inkscape_dir).map_err(|e| format!("Failed to remove old Inkscape: {}", e))?;
+    }
+    std::fs::create_dir_all(&inkscape_dir).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "downloading".to_string(),
+        message: "Downloading Inkscape...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    let client = create_http_client()?;
+    let (status, bytes) = download_with_retry("Downloading Inkscape", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
+        format!("Failed to download Inkscape: {}. Try again or check your internet connection.", e)
+    })?;
+    
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
+    }
+    
+    if is_sevenz {
+        extract_7z_streaming(&app, &bytes, &inkscape_dir, "Inkscape")?;
+        
+        // The wrapping "inkscape/" folder is stripped while extracting; if bin/ ended up
+        // nested deeper, move its root up
+        if !inkscape_path.exists() {
+            fn find_bin_dir(dir: &std::path::Path, exe: &str) -> Option<std::path::PathBuf> {
+                if dir.join("bin").join(exe).exists() {
+                    return Some(dir.to_path_buf());
+                }
+                if let Ok(entries) = std::fs::read_dir(dir) {
+                    for entry in entries.flatten() {
+                        let path = entry.path();
+                        if path.is_dir() {
+                            if let Some(found) = find_bin_dir(&path, exe) {
+                                return Some(found);
+                            }
+                        }
+                    }
+                }
+                None
+            }
+            
+            if let Some(root) = find_bin_dir(&inkscape_dir, inkscape_exe) {
+                info!("Found Inkscape root directory: {}", root.display());
+                if let Ok(entries) = std::fs::read_dir(&root) {
+                    for entry in entries.flatten() {
+                        let source_path = entry.path();
+                        let dest_path = inkscape_dir.join(entry.file_name());
+                        if let Err(e) = std::fs::rename(&source_path, &dest_path) {
+                            warn!("Failed to move {}: {}", source_path.display(), e);
+                        }
+                    }
+                }
+                let _ = std::fs::remove_dir_all(&root);
+            }
+        }
+    } else {
+        // Linux AppImage - a single self-contained executable
+        app.emit("download-progress", DownloadProgress {
+            status: "installing".to_string(),
+            message: "Installing Inkscape...".to_string(),
+        }).map_err(|e| e.to_string())?;
+        
+        std::fs::write(&inkscape_path, bytes).map_err(|e| e.to_string())?;
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&inkscape_path, std::fs::Permissions::from_mode(0o755))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    
+    // Verify the binary is where get_tool_path expects it
+    if !inkscape_path.exists() {
+        return Err(format!("Inkscape binary not found after extraction at: {}", inkscape_path.display()));
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "Inkscape downloaded successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("Inkscape downloaded successfully".to_string())
+}
+
+#[tauri::command]
+async fn download_chromium(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "checking".to_string(),
+        message: "Finding the latest headless Chromium...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    let (download_url, filename) = get_chromium_download_info().await?;
+    info!("Downloading Chromium from: {}", download_url);
+    
+    let chromium_dir = data_dir.join("chromium");
+    let chromium_path = chromium_dir.join(if cfg!(windows) { "chrome-headless-shell.exe" } else { "chrome-headless-shell" });
+    
+    // If Chromium already exists, remove it to allow updating
+    if chromium_dir.exists() {
+        info!("Removing existing Chromium installation for update...");
+        std::fs::remove_dir_all(&chromium_dir).map_err(|e| format!("Failed to remove old Chromium: {}", e))?;
+    }
+    std::fs::create_dir_all(&chromium_dir).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "downloading".to_string(),
+        message: "Downloading Chromium...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    let client = create_http_client()?;
+    let (status, bytes) = download_with_retry("Downloading Chromium", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
+        format!("Failed to download Chromium: {}. Try again or check your internet connection.", e)
+    })?;
+    
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
+    }
+    
+    let archive_path = data_dir.join(&filename);
+    std::fs::write(&archive_path, bytes).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "extracting".to_string(),
+        message: "Extracting Chromium...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    // The shell needs its resource files next to it, so extract everything
+    extract_zip_all(&archive_path, &chromium_dir)?;
+    std::fs::remove_file(&archive_path).map_err(|e| e.to_string())?;
+    
+    // The archive wraps everything in a "chrome-headless-shell-<platform>/" folder - move its contents up
+    if let Ok(entries) = std::fs::read_dir(&chromium_dir) {
+        for entry in entries.flatten() {
+            let nested_dir = entry.path();
+            if !nested_dir.is_dir() || !entry.file_name().to_string_lossy().starts_with("chrome-headless-shell") {
+                continue;
+            }
+            if let Ok(nested_entries) = std::fs::read_dir(&nested_dir) {
+                for nested in nested_entries.flatten() {
+                    let dest_path = chromium_dir.join(nested.file_name());
+                    if let Err(e) = std::fs::rename(nested.path(), &dest_path) {
+                        warn!("Failed to move {}: {}", nested.path().display(), e);
+                    }
+                }
+            }
+            let _ = std::fs::remove_dir_all(&nested_dir);
+        }
+    }
+    
+    // Verify the binary is where get_tool_path expects it
+    if !chromium_path.exists() {
+        return Err(format!("Chromium binary not found after extraction at: {}", chromium_path.display()));
+    }
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&chromium_path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "Chromium downloaded successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("Chromium downloaded successfully".to_string())
+}
+
+/// LibreOffice is too large to bundle, so it's installed the way each platform expects:
+/// Homebrew on macOS, an unpacked MSI on Windows and Flatpak on Linux
+#[tauri::command]
+async fn download_libreoffice(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
+    install_libreoffice(app).await
+}
+
+#[cfg(target_os = "macos")]
+async fn install_libreoffice(app: AppHandle) -> Result<String, String> {
+    if !is_homebrew_available() {
+        return Err("Automatic LibreOffice installation on macOS requires Homebrew.\n\n\
+            Install Homebrew, or download LibreOffice from libreoffice.org and \
+            set its path in the Tools Manager.".to_string());
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "checking".to_string(),
+        message: "Using Homebrew for installation...".to_string(),
+    }).ok();
+    install_via_homebrew(app, "libreoffice").await
+}
+
+#[cfg(target_os = "windows")]
+async fn install_libreoffice(app: AppHandle) -> Result<String, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    
+    let version = fetch_latest_libreoffice_version().await?;
+    let download_url = format!(
+        "https://download.documentfoundation.org/libreoffice/stable/{0}/win/x86_64/LibreOffice_{0}_Win_x86-64.msi",
+        version
+    );
+    info!("Downloading LibreOffice from: {}", download_url);
+    
+    let libreoffice_dir = data_dir.join("libreoffice");
+    let soffice_path = libreoffice_dir.join("program").join("soffice.exe");
+    
+    // If LibreOffice already exists, remove it to allow updating
+    if libreoffice_dir.exists() {
+        info!("Removing existing LibreOffice installation for update...");
+        std::fs::remove_dir_all(&libreoffice_dir).map_err(|e| format!("Failed to remove old LibreOffice: {}", e))?;
+    }
+    std::fs::create_dir_all(&libreoffice_dir).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "downloading".to_string(),
+        message: format!("Downloading LibreOffice {}...", version),
+    }).map_err(|e| e.to_string())?;
+    
+    let client = create_http_client()?;
+    let (status, bytes) = download_with_retry("Downloading LibreOffice", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
+        format!("Failed to download LibreOffice: {}. Try again or check your internet connection.", e)
+    })?;
+    
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
+    }
+    let msi_path = data_dir.join(format!("LibreOffice_{}.msi", version));
+    std::fs::write(&msi_path, bytes).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "extracting".to_string(),
+        message: "Extracting LibreOffice...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    // An administrative install only unpacks the MSI, so it needs no admin rights
+    let output = create_command("msiexec")
+        .arg("/a")
+        .arg(&msi_path)
+        .arg("/qn")
+        .arg(format!("TARGETDIR={}", libreoffice_dir.display()))
+        .output()
+        .map_err(|e| format!("Failed to run msiexec: {}", e));
+    std::fs::remove_file(&msi_path).ok();
+    let output = output?;
+    
+    if !output.status.success() {
+        return Err(format!("Failed to extract LibreOffice (msiexec exit status {:?})", output.status.code()));
+    }
+    
+    // The MSI nests everything under its own Program Files layout - move the install root up
+    if !soffice_path.exists() {
+        fn find_program_dir(dir: &std::path::Path) -> Option<std::path::PathBuf> {
+            if dir.join("program").join("soffice.exe").exists() {
+                return Some(dir.to_path_buf());
+            }
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        if let Some(found) = find_program_dir(&path) {
+                            return Some(found);
+                        }
                     }
                 }
             }
-            
-            if let Some(im_root) = imagemagick_root {
-                println!("Found ImageMagick root directory: {}", im_root.display());
-                
-                // Move all subdirectories (bin/, lib/, etc.) to extract_dir
-                if let Ok(entries) = std::fs::read_dir(&im_root) {
-                    for entry in entries.flatten() {
-                        let source_path = entry.path();
-                        let name = source_path.file_name().unwrap();
-                        let dest_path = extract_dir.join(name);
-                        
-                        println!("Moving {} to {}", source_path.display(), dest_path.display());
-                        if let Err(e) = std::fs::rename(&source_path, &dest_path) {
-                            println!("Failed to move {}: {}", source_path.display(), e);
-                        }
+            None
+        }
+        
+        if let Some(root) = find_program_dir(&libreoffice_dir) {
+            info!("Found LibreOffice root directory: {}", root.display());
+            if let Ok(entries) = std::fs::read_dir(&root) {
+                for entry in entries.flatten() {
+                    let source_path = entry.path();
+                    let dest_path = libreoffice_dir.join(entry.file_name());
+                    if let Err(e) = std::fs::rename(&source_path, &dest_path) {
+                        warn!("Failed to move {}: {}", source_path.display(), e);
                     }
                 }
-                
-                // Clean up the now-empty ImageMagick directory
-                let _ = std::fs::remove_dir_all(&im_root);
-                
-                println!("Final structure:");
-                list_all_files(&extract_dir, "");
             }
         }
-        
-        println!("Removing archive file: {}", archive_path.display());
-        std::fs::remove_file(&archive_path).map_err(|e| e.to_string())?;
     }
     
-    // Verify the file was actually extracted
-    if !magick_path.exists() {
-        println!("ERROR: ImageMagick binary still not found at: {}", magick_path.display());
-        println!("Final directory contents:");
-        if let Ok(entries) = std::fs::read_dir(extract_dir) {
-            for entry in entries.flatten() {
-                println!("  - {}", entry.path().display());
-            }
+    // Verify the binary is where get_tool_path expects it
+    if !soffice_path.exists() {
+        return Err(format!("LibreOffice not found after extraction at: {}", soffice_path.display()));
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "LibreOffice downloaded successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("LibreOffice downloaded successfully".to_string())
+}
+
+#[cfg(target_os = "linux")]
+async fn install_libreoffice(app: AppHandle) -> Result<String, String> {
+    let has_flatpak = create_command("flatpak")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if !has_flatpak {
+        return Err("Automatic LibreOffice installation on Linux requires Flatpak.\n\n\
+            Install LibreOffice with your package manager (e.g. sudo apt install libreoffice), \
+            or set its path in the Tools Manager.".to_string());
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "installing".to_string(),
+        message: "Installing LibreOffice via Flatpak...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    // Per-user installs don't need root; Flathub may not be configured for the user yet
+    let _ = create_command("flatpak")
+        .args(["remote-add", "--user", "--if-not-exists", "flathub", "https://dl.flathub.org/repo/flathub.flatpakrepo"])
+        .output();
+    
+    let output = create_command("flatpak")
+        .args(["install", "--user", "--noninteractive", "-y", "flathub", "org.libreoffice.LibreOffice"])
+        .output()
+        .map_err(|e| format!("Failed to run flatpak: {}", e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to install LibreOffice via Flatpak: {}", stderr));
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "LibreOffice installed successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("LibreOffice installed successfully via Flatpak".to_string())
+}
+
+/// Ghostscript is installed the way each platform expects: Homebrew on macOS,
+/// the official installer on Windows and the static build on Linux
+#[tauri::command]
+async fn download_ghostscript(app: AppHandle) -> Result<String, String> {
+    ensure_unlocked("Downloading tools")?;
+    install_ghostscript(app).await
+}
+
+#[cfg(target_os = "macos")]
+async fn install_ghostscript(app: AppHandle) -> Result<String, String> {
+    if !is_homebrew_available() {
+        return Err("Automatic Ghostscript installation on macOS requires Homebrew.\n\n\
+            Install Homebrew, or install Ghostscript yourself and \
+            set the location of gs in the Tools Manager.".to_string());
+    }
+    app.emit("download-progress", DownloadProgress {
+        status: "checking".to_string(),
+        message: "Using Homebrew for installation...".to_string(),
+    }).ok();
+    install_via_homebrew(app, "ghostscript").await
+}
+
+#[cfg(target_os = "windows")]
+async fn install_ghostscript(app: AppHandle) -> Result<String, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    
+    let (download_url, filename) = fetch_latest_ghostscript_release().await?;
+    info!("Downloading Ghostscript from: {}", download_url);
+    
+    let ghostscript_dir = data_dir.join("ghostscript");
+    let gs_path = ghostscript_dir.join("bin").join("gswin64c.exe");
+    
+    // If Ghostscript already exists, remove it to allow updating
+    if ghostscript_dir.exists() {
+        info!("Removing existing Ghostscript installation for update...");
+        std::fs::remove_dir_all(&ghostscript_dir).map_err(|e| format!("Failed to remove old Ghostscript: {}", e))?;
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "downloading".to_string(),
+        message: "Downloading Ghostscript...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    let client = create_http_client()?;
+    let (status, bytes) = download_with_retry("Downloading Ghostscript", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
+        format!("Failed to download Ghostscript: {}. Try again or check your internet connection.", e)
+    })?;
+    
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
+    }
+    let installer_path = data_dir.join(&filename);
+    std::fs::write(&installer_path, bytes).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "installing".to_string(),
+        message: "Installing Ghostscript (Windows may ask for permission)...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    // A silent NSIS install; /D= must come last and unquoted, even with spaces in the path.
+    // The installer asks for administrator rights, so it's started elevated (the UAC prompt)
+    // through PowerShell; its exit code is passed back through PowerShell's.
+    let quote = |path: &std::path::Path| path.display().to_string().replace('\'', "''");
+    let script = format!(
+        "$installer = Start-Process -FilePath '{}' -ArgumentList '/S /D={}' -Verb RunAs -Wait -PassThru; exit $installer.ExitCode",
+        quote(&installer_path),
+        quote(&ghostscript_dir),
+    );
+    let output = create_command("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .map_err(|e| format!("Failed to run the Ghostscript installer: {}", e));
+    std::fs::remove_file(&installer_path).ok();
+    let output = output?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("canceled by the user") {
+            return Err("Ghostscript installation was cancelled. Windows needs your permission to install it.".to_string());
         }
-        return Err(format!("ImageMagick binary not found after extraction at: {}", magick_path.display()));
+        return Err(format!("Ghostscript installation failed (exit status {:?})", output.status.code()));
+    }
+    
+    // Verify the binary is where get_tool_path expects it
+    if !gs_path.exists() {
+        return Err(format!("Ghostscript not found after installation at: {}", gs_path.display()));
     }
     
     app.emit("download-progress", DownloadProgress {
         status: "complete".to_string(),
-        message: "ImageMagick downloaded successfully!".to_string(),
+        message: "Ghostscript downloaded successfully!".to_string(),
     }).map_err(|e| e.to_string())?;
     
-    Ok("ImageMagick downloaded successfully".to_string())
+    Ok("Ghostscript downloaded successfully".to_string())
+}
+
+#[cfg(target_os = "linux")]
+async fn install_ghostscript(app: AppHandle) -> Result<String, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
+    
+    let (download_url, _filename) = fetch_latest_ghostscript_release().await?;
+    info!("Downloading Ghostscript from: {}", download_url);
+    
+    let ghostscript_dir = data_dir.join("ghostscript");
+    let gs_path = ghostscript_dir.join("gs");
+    
+    // If Ghostscript already exists, remove it to allow updating
+    if ghostscript_dir.exists() {
+        info!("Removing existing Ghostscript installation for update...");
+        std::fs::remove_dir_all(&ghostscript_dir).map_err(|e| format!("Failed to remove old Ghostscript: {}", e))?;
+    }
+    std::fs::create_dir_all(&ghostscript_dir).map_err(|e| e.to_string())?;
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "downloading".to_string(),
+        message: "Downloading Ghostscript...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    let client = create_http_client()?;
+    let (status, bytes) = download_with_retry("Downloading Ghostscript", RetryPolicy::DOWNLOAD, || client.get(&download_url)).await.map_err(|e| {
+        format!("Failed to download Ghostscript: {}. Try again or check your internet connection.", e)
+    })?;
+    
+    if !status.is_success() {
+        return Err(format!("Download failed with status: {}. The file may not be available.", status));
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "installing".to_string(),
+        message: "Installing Ghostscript...".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    // The tarball holds a single static binary named like gs-10040-linux-x86_64
+    let decoder = flate2::read::GzDecoder::new(std::io::Cursor::new(&bytes[..]));
+    let mut archive = tar::Archive::new(decoder);
+    let mut found = false;
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let is_binary = entry.path()
+            .map(|path| path.file_name().map_or(false, |name| name.to_string_lossy().starts_with("gs-")))
+            .unwrap_or(false);
+        if is_binary {
+            let mut file = std::fs::File::create(&gs_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut entry, &mut file).map_err(|e| e.to_string())?;
+            found = true;
+            break;
+        }
+    }
+    if !found {
+        return Err("Ghostscript binary not found in the downloaded archive".to_string());
+    }
+    
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&gs_path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+    
+    app.emit("download-progress", DownloadProgress {
+        status: "complete".to_string(),
+        message: "Ghostscript downloaded successfully!".to_string(),
+    }).map_err(|e| e.to_string())?;
+    
+    Ok("Ghostscript downloaded successfully".to_string())
 }
 
 #[tauri::command]
@@ -2740,9 +8117,12 @@ async fn test_tool(tool_name: String) -> Result<String, String> {
         }
     };
     
-    // ImageMagick uses -version, FFmpeg and Pandoc use -version too
+    // ImageMagick uses -version, FFmpeg and Pandoc use -version too; Inkscape only accepts --version
     let mut command = create_command(&tool_path);
-    command.arg("-version");
+    match tool_name.as_str() {
+        "inkscape" | "chromium" | "libreoffice" | "calibre" => command.arg("--version"),
+        _ => command.arg("-version"),
+    };
     
     // On macOS, set environment variables for ImageMagick
     #[cfg(target_os = "macos")]
@@ -2793,47 +8173,544 @@ async fn test_tool(tool_name: String) -> Result<String, String> {
             let lower = combined_output.to_lowercase();
             lower.contains("imagemagick") || lower.contains("version: imagemagick")
         },
+        "inkscape" => combined_output.to_lowercase().contains("inkscape"),
+        "chromium" => {
+            let lower = combined_output.to_lowercase();
+            lower.contains("chrom") || lower.contains("edge")
+        },
+        "libreoffice" => combined_output.to_lowercase().contains("libreoffice"),
+        "calibre" => combined_output.to_lowercase().contains("calibre"),
+        "ghostscript" => combined_output.to_lowercase().contains("ghostscript"),
         _ => output.status.success(),
     };
-    
-    if is_valid {
-        Ok(format!("{} is working! {}\n\nLocation: {}", tool_name, first_line, tool_path.display()))
-    } else {
-        Err(format!("{} test failed", tool_name))
+    
+    if is_valid {
+        Ok(format!("{} is working! {}\n\nLocation: {}", tool_name, first_line, tool_path.display()))
+    } else {
+        Err(format!("{} test failed", tool_name))
+    }
+}
+
+#[tauri::command]
+async fn get_thumbnail(file_path: String) -> Result<String, String> {
+    let path = PathBuf::from(&file_path);
+    
+    // Read the file
+    let data = std::fs::read(&path)
+        .map_err(|e| format!("Failed to read image file: {}", e))?;
+    
+    // The format determines the MIME type: the extension, unless the contents say otherwise
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("");
+    let format = signature::check_format(extension, &data[..data.len().min(signature::SNIFF_BYTES)]).format;
+    
+    Ok(conversion::thumbnail_data_url(&format, &data))
+}
+
+/// App icons for each of `platforms` (all of them when not given) from one picture, ideally
+/// a 1024px square: a macOS .icns, a Windows .ico, an iOS AppIcon set and Android mipmaps,
+/// in a "<name>-icons" folder
+#[tauri::command]
+async fn generate_icon_set(
+    input_path: String,
+    output_directory: Option<String>,
+    platforms: Option<Vec<IconPlatform>>,
+) -> Result<IconSetResult, String> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let platforms = platforms.unwrap_or_else(|| icon_set::ALL_PLATFORMS.to_vec());
+    if platforms.is_empty() {
+        return Err("Choose at least one platform".to_string());
+    }
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("icon");
+    let magick_path = get_tool_path("imagemagick")?;
+    // Staged under a plain name, so ImageMagick reads any file name as a file; the first
+    // frame of a multi-frame picture is the one used
+    let mut job_dir = JobDir::create()?;
+    let source = conversion::imagemagick_input_spec(&job_dir.stage(&input)?, Some(0));
+
+    let mut warnings = Vec::new();
+    let mut identify = create_command(&magick_path);
+    set_imagemagick_env(&mut identify, &magick_path);
+    identify.current_dir(&job_dir.path);
+    let dimensions = identify.arg("identify").arg("-ping").arg("-format").arg("%w %h").arg(&source).output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .and_then(|text| {
+            let mut parts = text.split_whitespace().map(|part| part.parse::<u32>().ok());
+            Some((parts.next()??, parts.next()??))
+        });
+    if let Some((width, height)) = dimensions {
+        if width != height {
+            warnings.push(format!("The picture isn't square ({}x{}), so the icons are padded", width, height));
+        }
+        if width.min(height) < icon_set::RECOMMENDED_SOURCE_SIZE {
+            warnings.push(format!(
+                "The picture is smaller than {0}x{0}, so the larger icons are scaled up",
+                icon_set::RECOMMENDED_SOURCE_SIZE
+            ));
+        }
+    }
+
+    // Render each size once, then put the files together
+    let files = icon_set::icon_files(&platforms, file_stem);
+    let mut transparent = BTreeMap::new();
+    let mut opaque = BTreeMap::new();
+    for (size, flatten) in icon_set::render_sizes(&files) {
+        let png_name = format!("{}{}.png", size, if flatten { "-opaque" } else { "" });
+        let args = icon_set::icon_render_args(&source, &png_name, size, flatten);
+        let mut command = create_command(&magick_path);
+        set_imagemagick_env(&mut command, &magick_path);
+        command.current_dir(&job_dir.path);
+        let output = command.args(&args).output()
+            .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("Failed to render the {0}x{0} icon: {1}", size, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        let png = std::fs::read(job_dir.path.join(&png_name))
+            .map_err(|e| format!("Failed to read the {0}x{0} icon: {1}", size, e))?;
+        let rendered = if flatten { &mut opaque } else { &mut transparent };
+        rendered.insert(size, png);
+    }
+
+    let reservation = reserve_output_path(&output_dir, &format!("{}-icons", file_stem), "");
+    let set_dir = reservation.path.clone();
+    let mut written = Vec::new();
+    for file in &files {
+        let data = match file {
+            IconFile::Png { size, opaque: true, .. } => opaque[size].clone(),
+            IconFile::Png { size, .. } => transparent[size].clone(),
+            IconFile::Icns { .. } => icon_set::build_icns(&transparent)?,
+            IconFile::Ico { .. } => icon_set::build_ico(&transparent)?,
+            IconFile::IosContents { .. } => serde_json::to_vec_pretty(&icon_set::ios_contents_json())
+                .map_err(|e| format!("Failed to write Contents.json: {}", e))?,
+        };
+        let path = set_dir.join(file.path());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, data)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        written.push(file.path().to_string());
+    }
+
+    info!("Generated {} icon files from {} in {}", written.len(), input_path, set_dir.display());
+    Ok(IconSetResult {
+        output_directory: set_dir.to_string_lossy().to_string(),
+        files: written,
+        warnings,
+    })
+}
+
+/// Where a watch rule's ingest reports are kept
+fn get_ingest_history_path(rule_id: &str) -> Result<PathBuf, String> {
+    let config_path = get_config_path()?;
+    Ok(config_path.with_file_name(ingest::history_file_name(rule_id)))
+}
+
+/// One run of a photo folder rule: converts the new photos as background jobs, skips ones
+/// the rule has already ingested (same content, even under another name) and saves a report
+/// of what happened, also emitted as "ingest-report"
+#[tauri::command]
+async fn ingest_photos(
+    app: AppHandle,
+    rule_id: String,
+    input_paths: Vec<String>,
+    output_format: String,
+    output_directory: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<IngestReport, String> {
+    let settings = ConversionSettings { priority: JobPriority::Background, ..settings.unwrap_or_default() };
+    let history_path = get_ingest_history_path(&rule_id)?;
+    let mut history = IngestHistory::load(&history_path)?;
+    let mut report = IngestReport {
+        rule_id: rule_id.clone(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        ..Default::default()
+    };
+    info!("Ingest run for rule {}: {} files", rule_id, input_paths.len());
+    
+    // Checksums of this run's photos, to name which file a duplicate repeats
+    let mut in_run: BTreeMap<String, String> = BTreeMap::new();
+    for input in input_paths {
+        let input_path = PathBuf::from(&input);
+        let hash = match checksum::sha256_file(&input_path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                report.failures.push(IngestFailure { input_path: input, reason: e });
+                continue;
+            }
+        };
+        if let Some(first) = in_run.get(&hash) {
+            report.duplicates.push(IngestDuplicate { input_path: input, duplicate_of: Some(first.clone()) });
+            continue;
+        }
+        if history.seen.contains(&hash) {
+            report.duplicates.push(IngestDuplicate { input_path: input, duplicate_of: None });
+            continue;
+        }
+        in_run.insert(hash.clone(), input.clone());
+        
+        let orientation = read_exif_orientation(&input_path);
+        let converted = convert_file(
+            input.clone(),
+            output_format.clone(),
+            output_directory.clone(),
+            None,
+            Some(settings.clone()),
+            None,
+            None,
+        ).await;
+        match converted {
+            Ok(result) => {
+                report.converted += 1;
+                if ingest::is_auto_rotated(orientation, settings.keep_orientation) {
+                    report.auto_rotated += 1;
+                }
+                report.outputs.push(result.output_path);
+                history.seen.insert(hash);
+            }
+            Err(e) => report.failures.push(IngestFailure { input_path: input, reason: e }),
+        }
+    }
+    
+    report.finished_at = chrono::Utc::now().to_rfc3339();
+    info!("Ingest run for rule {}: {}", rule_id, report.summary());
+    history.push_report(report.clone());
+    if let Err(e) = history.save(&history_path) {
+        warn!("Could not save the ingest report for rule {}: {}", rule_id, e);
+    }
+    app.emit("ingest-report", &report).ok();
+    Ok(report)
+}
+
+/// The saved reports of a photo folder rule, newest last
+#[tauri::command]
+fn get_ingest_reports(rule_id: String) -> Result<Vec<IngestReport>, String> {
+    let history = IngestHistory::load(&get_ingest_history_path(&rule_id)?)?;
+    Ok(history.reports)
+}
+
+/// A picture at several widths and formats for a `srcset` (by default 480, 960 and 1920 px
+/// in AVIF, WebP and JPEG) in a "<name>-responsive" folder, with a manifest.json holding the
+/// file list and a `<picture>` element. Widths above the picture's own are skipped.
+#[tauri::command]
+async fn generate_responsive_set(
+    image: String,
+    widths: Option<Vec<u32>>,
+    formats: Option<Vec<String>>,
+    output_directory: Option<String>,
+    sizes: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<ResponsiveSet, String> {
+    let input = PathBuf::from(&image);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", image));
+    }
+    let settings = settings.unwrap_or_default();
+    let widths = widths.unwrap_or_else(|| responsive::DEFAULT_WIDTHS.to_vec());
+    let mut set_formats: Vec<String> = Vec::new();
+    for format in formats.unwrap_or_else(|| responsive::DEFAULT_FORMATS.iter().map(|f| f.to_string()).collect()) {
+        let format = format.to_lowercase();
+        if !responsive::RESPONSIVE_FORMATS.contains(&format.as_str()) {
+            return Err(format!("{} isn't a web image format (use {})", format.to_uppercase(), responsive::RESPONSIVE_FORMATS.join(", ")));
+        }
+        if !set_formats.contains(&format) {
+            set_formats.push(format);
+        }
+    }
+    if set_formats.is_empty() || widths.is_empty() {
+        return Err("Choose at least one width and one format".to_string());
+    }
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let stem = naming::sanitize_file_name(file_stem, settings.file_name_mode);
+    let magick_path = get_tool_path("imagemagick")?;
+    wait_for_input(&input, settings.input_wait_secs.unwrap_or(input_state::DEFAULT_INPUT_WAIT_SECS))?;
+    
+    // Staged under a plain name like the icon sets; the first frame is used
+    let mut job_dir = JobDir::create()?;
+    let source = conversion::imagemagick_input_spec(&job_dir.stage(&input)?, Some(0));
+    let mut identify = create_command(&magick_path);
+    set_imagemagick_env(&mut identify, &magick_path);
+    identify.current_dir(&job_dir.path);
+    // %[orientation] tells whether -auto-orient will swap width and height
+    let dimensions = identify.arg("identify").arg("-ping").arg("-format").arg("%w %h %[orientation]").arg(&source).output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .and_then(|text| {
+            let mut parts = text.split_whitespace();
+            let (width, height) = (parts.next()?.parse::<u32>().ok()?, parts.next()?.parse::<u32>().ok()?);
+            let rotated = parts.next().is_some_and(|orientation| orientation.starts_with("Left") || orientation.starts_with("Right"));
+            Some(if rotated { (height, width) } else { (width, height) })
+        });
+    
+    let mut warnings = Vec::new();
+    let planned = responsive::plan_widths(&widths, dimensions.map(|(width, _)| width));
+    if planned.len() < widths.len() {
+        if let Some((width, _)) = dimensions {
+            warnings.push(format!("The picture is {} px wide, so larger widths were left out", width));
+        }
+    }
+    
+    let reservation = reserve_output_path(&output_dir, &format!("{}-responsive", stem), "");
+    let set_dir = reservation.path.clone();
+    std::fs::create_dir_all(&set_dir)
+        .map_err(|e| format!("Failed to create {}: {}", set_dir.display(), e))?;
+    
+    let mut images = Vec::new();
+    for width in &planned {
+        let outputs: Vec<(String, String, Option<u8>)> = set_formats.iter()
+            .map(|format| {
+                let file = set_dir.join(responsive::image_file_name(&stem, *width, format)).to_string_lossy().to_string();
+                (format.clone(), file, settings.quality.or_else(|| responsive::default_quality(format)))
+            })
+            .collect();
+        let mut command = create_command(&magick_path);
+        set_imagemagick_env(&mut command, &magick_path);
+        command.current_dir(&job_dir.path);
+        set_deterministic_env(&mut command, &settings);
+        command.args(responsive::render_args(&source, *width, &outputs, !settings.preserve_metadata || settings.deterministic));
+        debug!("Executing command: {:?}", command);
+        let output = command.output()
+            .map_err(|e| format!("Failed to execute ImageMagick: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_dir_all(&set_dir);
+            return Err(format!("Failed to render the {} px images: {}", width, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        for (format, file, _) in outputs {
+            let path = PathBuf::from(&file);
+            images.push(ResponsiveImage {
+                file: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(file),
+                format,
+                width: *width,
+                height: responsive::scaled_height(*width, dimensions),
+                size: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            });
+        }
+    }
+    
+    let (sources, html) = responsive::picture(&images, &set_formats, sizes.as_deref().unwrap_or(responsive::DEFAULT_SIZES));
+    let set = ResponsiveSet {
+        output_directory: set_dir.to_string_lossy().to_string(),
+        images,
+        sources,
+        html,
+        warnings,
+    };
+    let manifest = serde_json::to_string_pretty(&set).map_err(|e| e.to_string())?;
+    std::fs::write(set_dir.join(responsive::MANIFEST_FILE), manifest)
+        .map_err(|e| format!("Failed to write {}: {}", responsive::MANIFEST_FILE, e))?;
+    
+    info!("Generated {} responsive images from {} in {}", set.images.len(), image, set_dir.display());
+    Ok(set)
+}
+
+/// A two-second, 240p looping WebP of a video to play while the pointer is over it in the
+/// file list. Previews are cached in the app's cache folder until the video changes; the
+/// path returned is loaded through the asset protocol (convertFileSrc).
+#[tauri::command]
+async fn get_hover_preview(app: AppHandle, video: String) -> Result<String, String> {
+    let input_path = PathBuf::from(&video);
+    let metadata = std::fs::metadata(&input_path)
+        .map_err(|e| format!("Failed to read {}: {}", input_path.display(), e))?;
+    let modified = metadata.modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_secs());
+
+    let cache_dir = app.path().app_cache_dir()
+        .map_err(|e| format!("Failed to get cache directory: {}", e))?
+        .join("previews");
+    std::fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("Failed to create preview cache: {}", e))?;
+    let name = conversion::hover_preview_file_name(&input_path.to_string_lossy(), metadata.len(), modified);
+    let preview_path = cache_dir.join(&name);
+    if preview_path.is_file() {
+        return Ok(preview_path.to_string_lossy().to_string());
+    }
+
+    let ffmpeg_path = get_tool_path("ffmpeg")?;
+    let duration = inspect_media(&input_path).ok().and_then(|info| info.duration_secs);
+    // Written under another name first, so a half-written preview is never served
+    let partial_path = cache_dir.join(format!("partial-{}", name));
+    let args = conversion::ffmpeg_hover_preview_args(
+        &input_path.to_string_lossy(),
+        &partial_path.to_string_lossy(),
+        conversion::hover_preview_start(duration),
+    );
+    let output = create_command(&ffmpeg_path)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&partial_path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Hover preview of {} failed: {}", input_path.display(), stderr);
+        return Err(format!("Failed to create a preview of {}", input_path.display()));
+    }
+    std::fs::rename(&partial_path, &preview_path)
+        .map_err(|e| format!("Failed to save preview: {}", e))?;
+    Ok(preview_path.to_string_lossy().to_string())
+}
+
+/// Chapters of an audiobook or long recording: its own markers, or else those of a cue
+/// sheet next to it, with every end filled in
+#[tauri::command]
+async fn get_chapters(path: String) -> Result<Vec<Chapter>, String> {
+    let input = PathBuf::from(&path);
+    let info = inspect_media(&input)?;
+    if !info.chapters.is_empty() {
+        return Ok(chapters::resolve_chapter_ends(&info.chapters, info.duration_secs));
+    }
+    match chapters::cue_sheet_for(&input) {
+        Some(cue) => {
+            let text = std::fs::read(&cue).map_err(|e| format!("Failed to read {}: {}", cue.display(), e))?;
+            let cue_chapters = chapters::parse_cue_sheet(&String::from_utf8_lossy(&text));
+            Ok(chapters::resolve_chapter_ends(&cue_chapters, info.duration_secs))
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Splits an audiobook into one file per chapter ("01 - Opening Credits.mp3", ...) in a
+/// "<name>-chapters" folder. Each file is tagged with its chapter title and track number;
+/// the other audio settings (bitrate, loudness, fades) apply to every chapter.
+#[tauri::command]
+async fn split_by_chapters(
+    input_path: String,
+    output_format: String,
+    output_directory: Option<String>,
+    settings: Option<ConversionSettings>,
+) -> Result<Vec<ConversionResult>, String> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(format!("Input file not found: {}", input_path));
+    }
+    let input_ext = input.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    if !chapters::CHAPTER_INPUTS.contains(&input_ext.as_str()) {
+        return Err(format!("{} files can't be split by chapter", input_ext.to_uppercase()));
+    }
+    let output_format = output_format.to_lowercase();
+    if !conversion::is_audio_format(&output_format) {
+        return Err(format!("Chapters can only be split into audio files, not {}", output_format.to_uppercase()));
+    }
+    let settings = settings.unwrap_or_default();
+    conversion::validate_audio_edits(&settings)?;
+    let book_chapters = get_chapters(input_path.clone()).await?;
+    if book_chapters.is_empty() {
+        return Err("This file has no chapters, and there is no cue sheet next to it".to_string());
+    }
+
+    let output_dir = match output_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => input.parent().ok_or("Could not determine output directory")?.to_path_buf(),
+    };
+    let file_stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("audiobook");
+    let reservation = reserve_output_path(&output_dir, &format!("{}-chapters", file_stem), "");
+    std::fs::create_dir_all(&reservation.path)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    let ffmpeg_path = get_tool_path("ffmpeg")?;
+
+    // Measured once over the whole book, so every chapter gets the same gain
+    let loudness = match settings.loudness_lufs {
+        Some(lufs) if settings.loudness_two_pass => measure_loudness(&ffmpeg_path, &input, lufs, &settings),
+        _ => None,
+    };
+    let count = book_chapters.len();
+    let mut results = Vec::new();
+    for (index, chapter) in book_chapters.iter().enumerate() {
+        let started = std::time::Instant::now();
+        let stem = naming::sanitize_file_name(&chapters::chapter_file_stem(chapter, index, count), settings.file_name_mode);
+        let output_path = reservation.path.join(format!("{}.{}", stem, output_format));
+
+        let mut chapter_settings = settings.clone();
+        chapter_settings.keep_chapters = false;
+        chapter_settings.audio_tags.title = Some(chapters::chapter_title(chapter, index));
+        chapter_settings.audio_tags.track = Some(format!("{}/{}", index + 1, count));
+        let analysis = AudioAnalysis {
+            loudness,
+            duration_secs: chapter.end_secs.map(|end| end - chapter.start_secs),
+            cue_chapters: false,
+        };
+
+        let mut command = create_command(&ffmpeg_path);
+        command.arg("-hide_banner");
+        command.args(chapters::chapter_split_args(&input_path, chapter));
+        command.args(conversion::aac_muxer_args(&output_format));
+        command.args(conversion::audio_processing_args(&output_format, &chapter_settings, &analysis));
+        command.arg("-y").arg(&output_path);
+
+        debug!("Executing command: {:?}", command);
+        let output = command.output().map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&output_path);
+            return Err(format!(
+                "Failed to split chapter {} ({}): {}",
+                index + 1,
+                chapters::chapter_title(chapter, index),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        results.push(conversion_stats(&input, output_path.to_string_lossy().to_string(), started, Some(command_line(&command))));
     }
+
+    info!("Split {} into {} chapters in {}", input_path, count, reservation.path.display());
+    Ok(results)
 }
 
+
+/// The first few hundred characters of text Tesseract finds in an image or the first page
+/// of a document, so scans can be told apart in the file list. None when there is no text.
 #[tauri::command]
-async fn get_thumbnail(file_path: String) -> Result<String, String> {
-    let path = PathBuf::from(&file_path);
-    
-    // Read the file
-    let data = std::fs::read(&path)
-        .map_err(|e| format!("Failed to read image file: {}", e))?;
-    
-    // Get the file extension to determine MIME type
-    let extension = path.extension()
+async fn quick_text_preview(image: String) -> Result<Option<String>, String> {
+    let input_path = PathBuf::from(&image);
+    let tesseract_path = get_tool_path("tesseract")
+        .map_err(|_| "Text previews need Tesseract OCR, which isn't installed".to_string())?;
+    let input_ext = input_path.extension()
         .and_then(|ext| ext.to_str())
         .unwrap_or("")
         .to_lowercase();
-    
-    let mime_type = match extension.as_str() {
-        "jpg" | "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        "gif" => "image/gif",
-        "webp" => "image/webp",
-        "bmp" => "image/bmp",
-        "svg" => "image/svg+xml",
-        "ico" => "image/x-icon",
-        "tiff" | "tif" => "image/tiff",
-        _ => "image/jpeg", // default
+
+    // Anything Tesseract can't open is rendered to a PNG first
+    let job_dir = JobDir::create()?;
+    let ocr_input = if conversion::tesseract_reads(&input_ext) {
+        input_path.clone()
+    } else {
+        let magick_path = get_tool_path("imagemagick")?;
+        let page = job_dir.path.join("page.png");
+        let args = conversion::ocr_raster_args(&input_path.to_string_lossy(), &page.to_string_lossy());
+        let mut command = create_command(&magick_path);
+        set_imagemagick_env(&mut command, &magick_path);
+        let output = command.args(&args).output()
+            .map_err(|e| format!("Failed to render {} for OCR: {}", input_path.display(), e))?;
+        if !output.status.success() {
+            return Err(format!("Failed to render {} for OCR: {}", input_path.display(), String::from_utf8_lossy(&output.stderr)));
+        }
+        page
     };
-    
-    // Convert to base64
-    let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &data);
-    
-    // Return as data URL
-    Ok(format!("data:{};base64,{}", mime_type, base64_data))
+
+    let output = create_command(&tesseract_path)
+        .args(conversion::tesseract_preview_args(&ocr_input.to_string_lossy()))
+        .output()
+        .map_err(|e| format!("Failed to run Tesseract: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Tesseract failed on {}: {}", input_path.display(), stderr);
+        return Err(format!("Text recognition failed: {}", stderr.trim()));
+    }
+    Ok(conversion::text_snippet(&String::from_utf8_lossy(&output.stdout), conversion::OCR_PREVIEW_CHARS))
 }
 
 #[tauri::command]
@@ -2891,11 +8768,97 @@ async fn check_tools_status() -> Result<serde_json::Value, String> {
     };
     status.insert("imagemagick".to_string(), imagemagick_status);
     
+    // Check inkscape
+    let inkscape_status = match get_tool_path("inkscape") {
+        Ok(path) => {
+            serde_json::json!({
+                "available": true,
+                "path": path.to_string_lossy().to_string()
+            })
+        }
+        Err(_) => {
+            serde_json::json!({
+                "available": false,
+                "path": null
+            })
+        }
+    };
+    status.insert("inkscape".to_string(), inkscape_status);
+    
+    // Check chromium
+    let chromium_status = match get_tool_path("chromium") {
+        Ok(path) => {
+            serde_json::json!({
+                "available": true,
+                "path": path.to_string_lossy().to_string()
+            })
+        }
+        Err(_) => {
+            serde_json::json!({
+                "available": false,
+                "path": null
+            })
+        }
+    };
+    status.insert("chromium".to_string(), chromium_status);
+    
+    // Check libreoffice
+    let libreoffice_status = match get_tool_path("libreoffice") {
+        Ok(path) => {
+            serde_json::json!({
+                "available": true,
+                "path": path.to_string_lossy().to_string()
+            })
+        }
+        Err(_) => {
+            serde_json::json!({
+                "available": false,
+                "path": null
+            })
+        }
+    };
+    status.insert("libreoffice".to_string(), libreoffice_status);
+    
+    // Check calibre
+    let calibre_status = match get_tool_path("calibre") {
+        Ok(path) => {
+            serde_json::json!({
+                "available": true,
+                "path": path.to_string_lossy().to_string()
+            })
+        }
+        Err(_) => {
+            serde_json::json!({
+                "available": false,
+                "path": null
+            })
+        }
+    };
+    status.insert("calibre".to_string(), calibre_status);
+    
+    // Check ghostscript
+    let ghostscript_status = match get_tool_path("ghostscript") {
+        Ok(path) => {
+            serde_json::json!({
+                "available": true,
+                "path": path.to_string_lossy().to_string()
+            })
+        }
+        Err(_) => {
+            serde_json::json!({
+                "available": false,
+                "path": null
+            })
+        }
+    };
+    status.insert("ghostscript".to_string(), ghostscript_status);
+    
     Ok(serde_json::Value::Object(status))
 }
 
 #[tauri::command]
 async fn set_custom_tool_path(tool_name: String, path: String) -> Result<(), String> {
+    ensure_unlocked("Changing tool locations")?;
     info!("Attempting to set custom path for {}: {}", tool_name, path);
     
     // Verify the path exists and is executable
@@ -2912,8 +8875,9 @@ async fn set_custom_tool_path(tool_name: String, path: String) -> Result<(), Str
     let mut command = create_command(&path);
     
     // FFmpeg uses -version (single dash), while most other tools use --version
+    // (Ghostscript's --version prints only the number, without its name)
     match tool_name.as_str() {
-        "ffmpeg" => command.arg("-version"),
+        "ffmpeg" | "ghostscript" => command.arg("-version"),
         _ => command.arg("--version"),
     };
     
@@ -2956,6 +8920,11 @@ async fn set_custom_tool_path(tool_name: String, path: String) -> Result<(), Str
                 "ffmpeg" => combined_output.contains("ffmpeg version"),
                 "pandoc" => combined_output.contains("pandoc"),
                 "imagemagick" => combined_output.contains("imagemagick") || combined_output.contains("version: imagemagick"),
+                "inkscape" => combined_output.contains("inkscape"),
+                "chromium" => combined_output.contains("chrom") || combined_output.contains("edge"),
+                "libreoffice" => combined_output.contains("libreoffice"),
+                "calibre" => combined_output.contains("calibre"),
+                "ghostscript" => combined_output.contains("ghostscript"),
                 _ => output.status.success(),
             };
             
@@ -2969,6 +8938,11 @@ async fn set_custom_tool_path(tool_name: String, path: String) -> Result<(), Str
                     "ffmpeg" => config.ffmpeg_path = Some(path.clone()),
                     "pandoc" => config.pandoc_path = Some(path.clone()),
                     "imagemagick" => config.imagemagick_path = Some(path.clone()),
+                    "inkscape" => config.inkscape_path = Some(path.clone()),
+                    "chromium" => config.chromium_path = Some(path.clone()),
+                    "libreoffice" => config.libreoffice_path = Some(path.clone()),
+                    "calibre" => config.calibre_path = Some(path.clone()),
+                    "ghostscript" => config.ghostscript_path = Some(path.clone()),
                     _ => return Err(format!("Unknown tool: {}", tool_name)),
                 }
                 
@@ -2994,12 +8968,27 @@ async fn set_custom_tool_path(tool_name: String, path: String) -> Result<(), Str
 
 #[tauri::command]
 async fn clear_custom_tool_path(tool_name: String) -> Result<(), String> {
+    ensure_unlocked("Changing tool locations")?;
     let mut config = load_config().unwrap_or_default();
     
+    // A location from the machine config comes back as soon as the user's is cleared, so
+    // there's nothing to clear unless the user set one of their own over it
+    let key = format!("{}_path", tool_name);
+    let machine_path = load_machine_config().values.get(&key).filter(|value| !value.is_null()).cloned();
+    let current_path = serde_json::to_value(&config).ok().and_then(|value| value.get(&key).cloned());
+    if machine_path.is_some() && machine_path == current_path {
+        return Err(format!("The {} location is set by your administrator and can't be cleared.", tool_name));
+    }
+    
     match tool_name.as_str() {
         "ffmpeg" => config.ffmpeg_path = None,
         "pandoc" => config.pandoc_path = None,
         "imagemagick" => config.imagemagick_path = None,
+        "inkscape" => config.inkscape_path = None,
+        "chromium" => config.chromium_path = None,
+        "libreoffice" => config.libreoffice_path = None,
+        "calibre" => config.calibre_path = None,
+        "ghostscript" => config.ghostscript_path = None,
         _ => return Err(format!("Unknown tool: {}", tool_name)),
     }
     
@@ -3394,14 +9383,82 @@ async fn get_imagemagick_download_info() -> Result<(String, String, bool), Strin
     }
 }
 
+async fn get_inkscape_download_info() -> Result<(String, String, bool), String> {
+    if cfg!(target_os = "windows") {
+        // For Windows - portable .7z build from ConvertSave-Libraries GitHub releases
+        Ok((
+            "https://github.com/Hunter-Boone/ConvertSave-Libraries/releases/download/latest/inkscape-windows-x64.7z".to_string(),
+            "inkscape-windows-x64.7z".to_string(),
+            true, // .7z file
+        ))
+    } else if cfg!(target_os = "macos") {
+        // Inkscape for macOS ships as a .dmg app bundle that we can't install silently
+        Err("Automatic Inkscape installation on macOS requires Homebrew.\n\n\
+            Install Homebrew, or download Inkscape from inkscape.org and \
+            set its path in the Tools Manager.".to_string())
+    } else {
+        // For Linux - AppImage from ConvertSave-Libraries GitHub releases
+        Ok((
+            "https://github.com/Hunter-Boone/ConvertSave-Libraries/releases/download/latest/inkscape-linux-x64.AppImage".to_string(),
+            "inkscape-linux-x64.AppImage".to_string(),
+            false,
+        ))
+    }
+}
+
+/// Looks up the current stable chrome-headless-shell build from Chrome for Testing
+async fn get_chromium_download_info() -> Result<(String, String), String> {
+    let platform = if cfg!(target_os = "windows") {
+        "win64".to_string()
+    } else if cfg!(target_os = "macos") {
+        if get_macos_architecture() == "arm64" {
+            "mac-arm64".to_string()
+        } else {
+            "mac-x64".to_string()
+        }
+    } else {
+        "linux64".to_string()
+    };
+    
+    let url = "https://googlechromelabs.github.io/chrome-for-testing/last-known-good-versions-with-downloads.json";
+    let client = create_http_client()?;
+    let response = send_with_retry("Checking Chromium versions", RetryPolicy::CHECK, || client.get(url))
+        .await
+        .map_err(|e| format!("Failed to fetch Chromium versions: {}", e))?;
+    
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch Chromium versions: HTTP {}", response.status()));
+    }
+    
+    let json: serde_json::Value = response.json()
+        .await
+        .map_err(|e| format!("Failed to parse Chromium version data: {}", e))?;
+    
+    let downloads = json["channels"]["Stable"]["downloads"]["chrome-headless-shell"]
+        .as_array()
+        .ok_or("Chromium version data is missing headless shell downloads")?;
+    
+    for download in downloads {
+        if download["platform"].as_str() == Some(platform.as_str()) {
+            if let Some(download_url) = download["url"].as_str() {
+                return Ok((
+                    download_url.to_string(),
+                    format!("chrome-headless-shell-{}.zip", platform),
+                ));
+            }
+        }
+    }
+    
+    Err(format!("No headless Chromium build available for {}", platform))
+}
+
 /// Fetches the latest ImageMagick portable version from the binaries page
 async fn fetch_latest_imagemagick_version() -> Result<String, String> {
     println!("Fetching latest ImageMagick version from binaries page...");
     
     let url = "https://imagemagick.org/archive/binaries/";
     let client = create_http_client()?;
-    let response = client.get(url)
-        .send()
+    let response = send_with_retry("Checking ImageMagick versions", RetryPolicy::CHECK, || client.get(url))
         .await
         .map_err(|e| format!("Failed to fetch binaries page: {}", e))?;
     
@@ -3444,9 +9501,7 @@ async fn fetch_latest_ffmpeg_version() -> Result<String, String> {
     // Fetch the most recent releases (not /latest, as that might return a "latest" tag)
     let url = "https://api.github.com/repos/BtbN/FFmpeg-Builds/releases?per_page=10";
     let client = create_http_client()?;
-    let response = client
-        .get(url)
-        .send()
+    let response = send_with_retry("Checking FFmpeg versions", RetryPolicy::CHECK, || client.get(url))
         .await
         .map_err(|e| format!("Failed to fetch FFmpeg releases: {}", e))?;
     
@@ -3480,9 +9535,7 @@ async fn fetch_latest_pandoc_version() -> Result<String, String> {
     
     let url = "https://api.github.com/repos/jgm/pandoc/releases/latest";
     let client = create_http_client()?;
-    let response = client
-        .get(url)
-        .send()
+    let response = send_with_retry("Checking Pandoc versions", RetryPolicy::CHECK, || client.get(url))
         .await
         .map_err(|e| format!("Failed to fetch Pandoc releases: {}", e))?;
     
@@ -3503,6 +9556,65 @@ async fn fetch_latest_pandoc_version() -> Result<String, String> {
     Ok(tag_name)
 }
 
+/// Look up the current stable LibreOffice release from the download server's listing
+#[cfg(target_os = "windows")]
+async fn fetch_latest_libreoffice_version() -> Result<String, String> {
+    let url = "https://download.documentfoundation.org/libreoffice/stable/";
+    let client = create_http_client()?;
+    let response = send_with_retry("Checking LibreOffice versions", RetryPolicy::CHECK, || client.get(url))
+        .await
+        .map_err(|e| format!("Failed to fetch LibreOffice releases: {}", e))?;
+    
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch LibreOffice releases: HTTP {}", response.status()));
+    }
+    
+    let listing = response.text()
+        .await
+        .map_err(|e| format!("Failed to read LibreOffice releases: {}", e))?;
+    
+    let version = conversion::latest_libreoffice_version(&listing)
+        .ok_or("Could not find a LibreOffice release")?;
+    info!("Found latest LibreOffice version: {}", version);
+    Ok(version)
+}
+
+/// Finds this platform's build in the latest Ghostscript release, returning its URL and file name
+#[cfg(not(target_os = "macos"))]
+async fn fetch_latest_ghostscript_release() -> Result<(String, String), String> {
+    let platform = if cfg!(target_os = "windows") { "windows" } else { "linux" };
+    
+    let url = "https://api.github.com/repos/ArtifexSoftware/ghostpdl-downloads/releases/latest";
+    let client = create_http_client()?;
+    let response = send_with_retry("Checking Ghostscript versions", RetryPolicy::CHECK, || client.get(url))
+        .await
+        .map_err(|e| format!("Failed to fetch Ghostscript releases: {}", e))?;
+    
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch Ghostscript releases: HTTP {}", response.status()));
+    }
+    
+    let json: serde_json::Value = response.json()
+        .await
+        .map_err(|e| format!("Failed to parse Ghostscript release data: {}", e))?;
+    
+    let assets = json["assets"]
+        .as_array()
+        .ok_or("Ghostscript release data is missing its downloads")?;
+    
+    for asset in assets {
+        let name = asset["name"].as_str().unwrap_or("");
+        if conversion::is_ghostscript_asset(name, platform) {
+            if let Some(download_url) = asset["browser_download_url"].as_str() {
+                info!("Found latest Ghostscript build: {}", name);
+                return Ok((download_url.to_string(), name.to_string()));
+            }
+        }
+    }
+    
+    Err(format!("No Ghostscript build available for {}", platform))
+}
+
 /// Fix hardcoded library paths in ImageMagick binary on macOS
 #[cfg(target_os = "macos")]
 #[allow(dead_code)]
@@ -3651,6 +9763,83 @@ fn fix_library_references(lib_path: &PathBuf, install_dir: &PathBuf) -> Result<(
     Ok(())
 }
 
+/// Copy the tools bundled into the installer into the app data folder, where get_tool_path
+/// finds them like downloaded ones. Runs on every start but only copies on first run and
+/// after an update, never over a tool the user downloaded (see bundled_tools::should_install).
+#[cfg(feature = "bundled-tools")]
+fn install_bundled_tools(app: &AppHandle) -> Result<(), String> {
+    let resources = app.path().resource_dir().map_err(|e| e.to_string())?.join("tools");
+    let platforms = bundled_tools::bundle_platform_dirs(std::env::consts::OS, std::env::consts::ARCH);
+    let Some(bundle_dir) = platforms.iter().map(|platform| resources.join(platform)).find(|dir| dir.is_dir()) else {
+        warn!("No bundled tools for {} in {}", platforms.join(" or "), resources.display());
+        return Ok(());
+    };
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let version = env!("CARGO_PKG_VERSION");
+    
+    for tool in bundled_tools::BUNDLED_TOOLS {
+        let source = bundle_dir.join(tool);
+        if !source.is_dir() {
+            continue;
+        }
+        let target = data_dir.join(tool);
+        let stamp = std::fs::read_to_string(target.join(bundled_tools::STAMP_FILE)).ok();
+        let in_use = std::fs::read_dir(&target).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+        if !bundled_tools::should_install(stamp.as_deref(), in_use, version) {
+            continue;
+        }
+        
+        info!("Installing bundled {} from {}", tool, source.display());
+        if stamp.is_some() {
+            let _ = std::fs::remove_dir_all(&target);
+        }
+        copy_bundled_tool(&source, &target, &target)?;
+        std::fs::write(target.join(bundled_tools::STAMP_FILE), version)
+            .map_err(|e| format!("Failed to mark the bundled {} as installed: {}", tool, e))?;
+    }
+    Ok(())
+}
+
+/// Copy a bundled tool folder, marking its programs executable. On macOS the copies are
+/// also taken out of quarantine and signed ad hoc, which they need to run outside the
+/// signed app bundle.
+#[cfg(feature = "bundled-tools")]
+fn copy_bundled_tool(source: &PathBuf, target: &PathBuf, tool_root: &PathBuf) -> Result<(), String> {
+    std::fs::create_dir_all(target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    let entries = std::fs::read_dir(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    for entry in entries.flatten() {
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        if from.is_dir() {
+            copy_bundled_tool(&from, &to, tool_root)?;
+            continue;
+        }
+        std::fs::copy(&from, &to).map_err(|e| format!("Failed to copy {}: {}", from.display(), e))?;
+        
+        let relative = to.strip_prefix(tool_root).unwrap_or(&to);
+        if !bundled_tools::needs_signing(relative) {
+            continue;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&to, std::fs::Permissions::from_mode(0o755));
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let _ = Command::new("xattr").arg("-d").arg("com.apple.quarantine").arg(&to).output();
+            match Command::new("codesign").args(["--force", "--sign", "-"]).arg(&to).output() {
+                Ok(output) if !output.status.success() => {
+                    warn!("Could not sign {}: {}", to.display(), String::from_utf8_lossy(&output.stderr));
+                }
+                Err(e) => warn!("Could not run codesign for {}: {}", to.display(), e),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
 fn extract_zip(archive_path: &PathBuf, extract_dir: &PathBuf, binary_name: &str) -> Result<(), String> {
     let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
     let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
@@ -3759,7 +9948,70 @@ fn extract_zip(archive_path: &PathBuf, extract_dir: &PathBuf, binary_name: &str)
     Err(format!("{} binary not found in archive (checked all files)", binary_name))
 }
 
+/// Extracts a downloaded .7z straight from memory, one entry at a time.
+///
+/// The archive's wrapping folder is stripped so binaries land directly in `extract_dir`,
+/// and a download-progress event is emitted whenever the percentage of files done changes.
+fn extract_7z_streaming(app: &AppHandle, bytes: &[u8], extract_dir: &std::path::Path, label: &str) -> Result<(), String> {
+    let mut reader = sevenz_rust::SevenZReader::new(
+        std::io::Cursor::new(bytes),
+        bytes.len() as u64,
+        sevenz_rust::Password::empty(),
+    ).map_err(|e| format!("Failed to open {} .7z: {}", label, e))?;
+    
+    let files = &reader.archive().files;
+    let total = files.iter().filter(|entry| !entry.is_directory()).count();
+    let root = archive::archive_root(files.iter().map(|entry| entry.name()));
+    info!("Extracting {} files from {} .7z (root folder: {:?})", total, label, root);
+    
+    let mut done = 0;
+    let mut last_percent = None;
+    reader.for_each_entries(|entry, data| {
+        let destination = match archive::entry_destination(entry.name(), root.as_deref()) {
+            Some(relative) => extract_dir.join(relative),
+            None => {
+                if !entry.is_directory() && !entry.name().is_empty() {
+                    warn!("Skipping archive entry outside the install folder: {}", entry.name());
+                }
+                std::io::copy(data, &mut std::io::sink())?;
+                return Ok(true);
+            }
+        };
+        
+        if entry.is_directory() {
+            std::fs::create_dir_all(&destination)?;
+            return Ok(true);
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::File::create(&destination)?;
+        std::io::copy(data, &mut file)?;
+        
+        done += 1;
+        let percent = archive::percent_done(done, total);
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            app.emit("download-progress", DownloadProgress {
+                status: "extracting".to_string(),
+                message: format!("Extracting {}... {}% ({}/{} files)", label, percent, done, total),
+            }).ok();
+        }
+        Ok(true)
+    }).map_err(|e| format!("Failed to extract {} .7z: {}", label, e))
+}
+
 // Extract ALL files from a tar.gz archive (used for ImageMagick to get dylibs)
+fn extract_zip_all(archive_path: &PathBuf, extract_dir: &PathBuf) -> Result<(), String> {
+    let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    
+    // Extract all files
+    archive.extract(extract_dir).map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
 fn extract_tar_gz_all(archive_path: &PathBuf, extract_dir: &PathBuf) -> Result<(), String> {
     let file = std::fs::File::open(archive_path).map_err(|e| e.to_string())?;
     
@@ -3851,22 +10103,114 @@ fn extract_tar_gz(archive_path: &PathBuf, extract_dir: &PathBuf, binary_name: &s
 // ═══════════════════════════════════════════════════════════════════════════
 
 /// Check the current license status
-/// Called on app startup to determine if user is licensed
+/// Called on app startup to determine if user is licensed.
+/// Results are cached for a few minutes; pass `force` to skip the cache.
 #[tauri::command]
-async fn check_license_status() -> Result<license::LicenseStatus, String> {
+async fn check_license_status(
+    app: AppHandle,
+    cache: State<'_, license::LicenseCache>,
+    force: Option<bool>,
+) -> Result<license::LicenseStatus, String> {
     info!("Checking license status...");
-    let status = license::check_license_status().await;
+    let (status, previous) = cache.get(force.unwrap_or(false)).await;
     info!("License status: {:?}", status);
+    if previous.is_some() {
+        let _ = app.emit(license::LICENSE_STATUS_EVENT, status.clone());
+    }
     Ok(status)
 }
 
+/// Recent jobs sent with get_app_state
+const STARTUP_HISTORY_ENTRIES: usize = 20;
+
+/// What the frontend restores at launch, gathered in one call
+#[derive(Serialize)]
+struct AppState {
+    app_version: String,
+    queue: QueueStatus,
+    /// As check_tools_status returns it; None if the check failed
+    tools: Option<serde_json::Value>,
+    /// None if the license couldn't be checked
+    license: Option<license::LicenseStatus>,
+    recent_history: Vec<HistoryEntry>,
+    /// Batches the last run didn't finish, to offer resuming
+    interrupted_batches: Vec<InterruptedBatch>,
+}
+
+/// Queue, tools, license, recent history and interrupted batches in one round-trip, for
+/// startup. The tool and license checks run side by side. Each part degrades on its own:
+/// a failed tool or license check comes back as None, and a history or queue that can't
+/// be read comes back empty.
+#[tauri::command]
+async fn get_app_state(app: AppHandle, cache: State<'_, license::LicenseCache>) -> Result<AppState, String> {
+    let (tools, license) = tokio::join!(check_tools_status(), check_license_status(app, cache, None));
+    let tools = tools.map_err(|e| warn!("Failed to check tools: {}", e)).ok();
+    let license = license.map_err(|e| warn!("Failed to check the license: {}", e)).ok();
+    let recent_history = match HISTORY_LOCK.lock() {
+        Ok(_guard) => match get_history_path().and_then(|path| JobHistory::load(&path)) {
+            Ok(history) => history.recent(STARTUP_HISTORY_ENTRIES),
+            Err(e) => {
+                warn!("Failed to load history: {}", e);
+                Vec::new()
+            }
+        },
+        Err(_) => {
+            warn!("History is unavailable");
+            Vec::new()
+        }
+    };
+    let interrupted_batches = get_interrupted_batches().unwrap_or_else(|e| {
+        warn!("Failed to load the queue: {}", e);
+        Vec::new()
+    });
+    Ok(AppState {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        queue: get_queue_status(),
+        tools,
+        license,
+        recent_history,
+        interrupted_batches,
+    })
+}
+
+/// Store a new license status and tell every window if it changed
+async fn publish_license_status(app: &AppHandle, cache: &license::LicenseCache, status: license::LicenseStatus) {
+    if cache.set(status.clone()).await {
+        info!("License status changed: {:?}", status);
+        let _ = app.emit(license::LICENSE_STATUS_EVENT, status);
+    }
+}
+
+/// Re-check the license in the background so expiry or a deactivation from another
+/// device reaches the UI without it having to poll
+fn spawn_license_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(license::LICENSE_CACHE_TTL).await;
+            let cache = app.state::<license::LicenseCache>();
+            let (status, previous) = cache.get(true).await;
+            if previous.is_some() {
+                info!("License status changed: {:?}", status);
+                let _ = app.emit(license::LICENSE_STATUS_EVENT, status);
+            }
+        }
+    });
+}
+
 /// Activate the app with a product key
 #[tauri::command]
-async fn activate_license(product_key: String, device_name: Option<String>) -> Result<license::LicenseStatus, String> {
+async fn activate_license(
+    app: AppHandle,
+    cache: State<'_, license::LicenseCache>,
+    product_key: String,
+    device_name: Option<String>,
+) -> Result<license::LicenseStatus, String> {
+    ensure_unlocked("Changing the license")?;
     info!("Activating license with product key...");
     match license::activate_with_product_key(&product_key, device_name.as_deref()).await {
         Ok(status) => {
             info!("License activated successfully");
+            publish_license_status(&app, &cache, status.clone()).await;
             Ok(status)
         }
         Err(e) => {
@@ -3878,11 +10222,14 @@ async fn activate_license(product_key: String, device_name: Option<String>) -> R
 
 /// Deactivate this device
 #[tauri::command]
-async fn deactivate_license() -> Result<(), String> {
+async fn deactivate_license(app: AppHandle, cache: State<'_, license::LicenseCache>) -> Result<(), String> {
+    ensure_unlocked("Changing the license")?;
     info!("Deactivating license...");
     match license::deactivate_device().await {
         Ok(()) => {
             info!("License deactivated successfully");
+            let status = license::LicenseStatus::default();
+            publish_license_status(&app, &cache, status).await;
             Ok(())
         }
         Err(e) => {
@@ -3892,10 +10239,10 @@ async fn deactivate_license() -> Result<(), String> {
     }
 }
 
-/// Get the device's MAC address (for display in settings)
+/// Get the device ID the license is tied to (for display in settings)
 #[tauri::command]
 fn get_device_id() -> Result<String, String> {
-    license::get_mac_address()
+    license::get_device_id()
 }
 
 /// Get the current product key from local license
@@ -3906,11 +10253,18 @@ fn get_current_product_key() -> Result<String, String> {
 
 /// Change the product key for this device
 #[tauri::command]
-async fn change_product_key(new_product_key: String, device_name: Option<String>) -> Result<license::LicenseStatus, String> {
+async fn change_product_key(
+    app: AppHandle,
+    cache: State<'_, license::LicenseCache>,
+    new_product_key: String,
+    device_name: Option<String>,
+) -> Result<license::LicenseStatus, String> {
+    ensure_unlocked("Changing the license")?;
     info!("Changing product key...");
     match license::change_product_key(&new_product_key, device_name.as_deref()).await {
         Ok(status) => {
             info!("Product key changed successfully");
+            publish_license_status(&app, &cache, status.clone()).await;
             Ok(status)
         }
         Err(e) => {
@@ -3920,6 +10274,46 @@ async fn change_product_key(new_product_key: String, device_name: Option<String>
     }
 }
 
+/// Start a purchase: opens the checkout page in the browser with a token bound to this device.
+/// The frontend then calls poll_checkout with the returned token until it completes.
+#[tauri::command]
+async fn start_checkout(app: AppHandle, plan_type: license::PlanType) -> Result<license::CheckoutSession, String> {
+    ensure_unlocked("Changing the license")?;
+    info!("Starting checkout for {:?} plan...", plan_type);
+    let session = license::create_checkout_session(&plan_type).await?;
+    open_in_browser(&app, &session.checkout_url)?;
+    Ok(session)
+}
+
+/// Check whether a checkout has been paid; the purchased key is activated automatically
+#[tauri::command]
+async fn poll_checkout(
+    app: AppHandle,
+    cache: State<'_, license::LicenseCache>,
+    token: String,
+) -> Result<license::CheckoutStatus, String> {
+    let status = license::poll_checkout(&token).await?;
+    if let Some(license_status) = &status.license_status {
+        info!("Purchase completed and activated");
+        publish_license_status(&app, &cache, license_status.clone()).await;
+    }
+    Ok(status)
+}
+
+/// Open a web page in the default browser, through the shell plugin so the URL is never
+/// parsed by a command interpreter
+fn open_in_browser(app: &AppHandle, url: &str) -> Result<(), String> {
+    use tauri_plugin_shell::ShellExt;
+
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(format!("Refusing to open a non-web address: {}", url));
+    }
+    #[allow(deprecated)]
+    app.shell()
+        .open(url, None)
+        .map_err(|e| format!("Failed to open browser: {}", e))
+}
+
 // ═══════════════════════════════════════════════════════════════════════════
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -3945,6 +10339,21 @@ pub fn run() {
             
             info!("ConvertSave application started");
             info!("Version: {}", env!("CARGO_PKG_VERSION"));
+            
+            let _ = APP_HANDLE.set(app.handle().clone());
+            remove_partial_outputs();
+            load_format_registry();
+            match load_config() {
+                Ok(config) => {
+                    apply_feature_flags(&config);
+                }
+                Err(e) => warn!("Could not read the feature flags: {}", e),
+            }
+            spawn_license_watcher(app.handle().clone());
+            #[cfg(feature = "bundled-tools")]
+            if let Err(e) = install_bundled_tools(app.handle()) {
+                error!("Could not install the bundled tools: {}", e);
+            }
             Ok(())
         })
         .plugin(tauri_plugin_fs::init())
@@ -3952,17 +10361,83 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(license::LicenseCache::default())
         .invoke_handler(tauri::generate_handler![
             get_available_formats,
+            get_conversion_matrix,
+            plan_batch,
+            get_format_details,
+            quick_text_preview,
+            get_hover_preview,
+            get_chapters,
+            split_by_chapters,
+            get_input_states,
+            convert_to_formats,
+            verify_checksum,
+            generate_icon_set,
+            generate_responsive_set,
+            ingest_photos,
+            get_ingest_reports,
             convert_file,
+            convert_image_batch,
+            convert_batch,
+            convert_directory,
+            convert_zip,
+            estimate_batch_duration,
+            get_queue_status,
+            get_power_settings,
+            get_kiosk_mode,
+            where_is_tool,
+            get_interrupted_batches,
+            resume_batch,
+            dismiss_interrupted_batch,
+            get_locked_settings,
+            set_prevent_sleep,
+            set_after_queue_action,
+            preview_conversion_command,
+            explain_unsupported,
+            snapshot_url,
+            get_format_warning,
+            optimize_animation,
+            scan_folder_report,
+            generate_test_fixtures,
+            scan_heic_migration,
+            run_heic_migration,
+            rollback_heic_migration,
             convert_images_to_multipage_pdf,
+            concat_audio_files,
+            concat_videos,
+            merge_documents,
+            extract_frames,
+            generate_contact_sheet,
+            extract_subtitles,
+            extract_live_photo_video,
+            convert_live_photo,
+            get_streams,
+            preview_document_conversion,
             get_file_info,
+            probe_media,
+            read_image_metadata,
+            get_audio_presets,
+            set_job_label,
+            search_history,
+            get_default_name_template,
+            set_default_name_template,
+            get_ffmpeg_log_level,
+            get_feature_flags,
+            summarize_error,
+            set_feature_flag,
+            set_ffmpeg_log_level,
             get_thumbnail,
             test_directories,
             open_folder,
             download_ffmpeg,
             download_pandoc,
             download_imagemagick,
+            download_inkscape,
+            download_chromium,
+            download_libreoffice,
+            download_ghostscript,
             test_tool,
             check_tools_status,
             check_for_updates,
@@ -3974,11 +10449,14 @@ pub fn run() {
             install_app_update,
             // License commands
             check_license_status,
+            get_app_state,
             activate_license,
             deactivate_license,
             get_device_id,
             get_current_product_key,
-            change_product_key
+            change_product_key,
+            start_checkout,
+            poll_checkout
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,375 @@
+//! Image metadata - Parses EXIF/IPTC/XMP tags from exiftool or ImageMagick `identify -verbose`
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Property prefixes from `identify -verbose` that come from embedded metadata
+/// (as opposed to values ImageMagick computed itself, like `signature`)
+pub const METADATA_PROPERTY_PREFIXES: &[&str] = &[
+    "exif:", "iptc:", "xmp:", "tiff:", "photoshop:", "icc:", "dc:", "comment"
+];
+
+/// Folder for photos without a capture date, when filing by date
+pub const UNDATED_FOLDER: &str = "Undated";
+
+/// Folder for photos without a camera model, when filing by camera
+pub const UNKNOWN_CAMERA_FOLDER: &str = "Unknown camera";
+
+/// How converted photos are filed into subfolders of the output folder
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileBy {
+    /// YYYY/MM of when the photo was taken
+    Date,
+    /// The camera (make and model) that took it
+    Camera,
+}
+
+/// Metadata found in an image
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ImageMetadata {
+    /// Which tool read the tags ("exiftool" or "imagemagick")
+    pub tool: String,
+    pub tags: BTreeMap<String, String>,
+    /// True if GPS coordinates are embedded - worth warning about before sharing
+    pub has_location: bool,
+}
+
+impl ImageMetadata {
+    pub fn new(tool: &str, tags: BTreeMap<String, String>) -> Self {
+        let has_location = tags.keys().any(|key| key.to_lowercase().contains("gps"));
+        Self {
+            tool: tool.to_string(),
+            tags,
+            has_location,
+        }
+    }
+
+    /// A tag by name in any group: "EXIF:Model" from exiftool, "exif:Model" from ImageMagick
+    fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.rsplit(':').next().is_some_and(|tag| tag.eq_ignore_ascii_case(name)))
+            .map(|(_, value)| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Year and month the photo was taken, from the first EXIF date that is set
+    pub fn capture_month(&self) -> Option<(u16, u8)> {
+        ["DateTimeOriginal", "CreateDate", "DateTimeDigitized", "DateTime"].iter().find_map(|name| {
+            // "2024:07:04 10:22:33"
+            let date = self.tag(name)?;
+            let year: u16 = date.get(0..4)?.parse().ok()?;
+            let month: u8 = date.get(5..7)?.parse().ok()?;
+            (year > 0 && (1..=12).contains(&month)).then_some((year, month))
+        })
+    }
+
+    /// "Canon EOS R5", "Apple iPhone 15 Pro": the model, led by the make unless it already is
+    pub fn camera(&self) -> Option<String> {
+        let model = self.tag("Model")?;
+        let make = self.tag("Make").and_then(|make| make.split_whitespace().next());
+        match make {
+            Some(make) if !model.to_lowercase().starts_with(&make.to_lowercase()) => Some(format!("{} {}", make, model)),
+            _ => Some(model.to_string()),
+        }
+    }
+
+    /// Subfolder of the output folder this photo is filed in
+    pub fn filing_folder(&self, by: FileBy) -> PathBuf {
+        match by {
+            FileBy::Date => match self.capture_month() {
+                Some((year, month)) => PathBuf::from(format!("{:04}", year)).join(format!("{:02}", month)),
+                None => PathBuf::from(UNDATED_FOLDER),
+            },
+            FileBy::Camera => {
+                let camera = self.camera().map(|camera| folder_name(&camera)).filter(|name| !name.is_empty());
+                PathBuf::from(camera.unwrap_or_else(|| UNKNOWN_CAMERA_FOLDER.to_string()))
+            }
+        }
+    }
+}
+
+/// `name` with the characters folders can't have on any system replaced
+fn folder_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    name.trim().trim_end_matches('.').trim().to_string()
+}
+
+/// Parses the `Properties:` and `Profiles:` sections of `magick identify -verbose`
+pub fn parse_identify_verbose(output: &str) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+    let mut section = "";
+
+    for line in output.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+
+        // Section headers sit two spaces in, their entries four
+        if indent == 2 {
+            section = match trimmed {
+                "Properties:" => "properties",
+                "Profiles:" => "profiles",
+                _ => "",
+            };
+            continue;
+        }
+        if indent != 4 || section.is_empty() {
+            continue;
+        }
+
+        let (key, value) = match trimmed.split_once(": ") {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        match section {
+            "properties" => {
+                if METADATA_PROPERTY_PREFIXES.iter().any(|prefix| key.starts_with(prefix)) {
+                    tags.insert(key.to_string(), value.trim().to_string());
+                }
+            }
+            "profiles" => {
+                // "Profile-exif: 1234 bytes" -> "profile:exif"
+                let name = key.trim_start_matches("Profile-");
+                tags.insert(format!("profile:{}", name), value.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    tags
+}
+
+/// Parses `exiftool -json -G` output (an array with one object per file)
+pub fn parse_exiftool_json(json: &serde_json::Value) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+
+    if let Some(object) = json.get(0).and_then(|file| file.as_object()) {
+        for (key, value) in object {
+            // File system details aren't image metadata
+            if key == "SourceFile" || key.starts_with("File:") || key.starts_with("ExifTool:") {
+                continue;
+            }
+            let value = match value.as_str() {
+                Some(text) => text.to_string(),
+                None => value.to_string(),
+            };
+            tags.insert(key.clone(), value);
+        }
+    }
+
+    tags
+}
+
+/// exiftool arguments that copy every tag from `source` onto the file appended after them.
+/// Used when the converter itself drops EXIF (FFmpeg image outputs, some format pairs).
+/// `upright` leaves out the orientation tag, for outputs whose pixels were already rotated.
+pub fn exiftool_copy_args(source: &str, upright: bool) -> Vec<String> {
+    let mut args = vec![
+        "-TagsFromFile".to_string(),
+        source.to_string(),
+        "-all:all".to_string(),
+    ];
+    if upright {
+        args.push("--Orientation".to_string());
+    }
+    args.push("-overwrite_original".to_string());
+    args
+}
+
+/// EXIF orientation (1-8) of a JPEG or TIFF-based image (TIFF, DNG and most camera raws),
+/// read from the first image directory
+pub fn exif_orientation(bytes: &[u8]) -> Option<u16> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return tiff_orientation(bytes);
+    }
+
+    // JPEG: walk the segments up to the image data, looking for the APP1 Exif block
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = bytes[pos + 1];
+        if marker == 0xFF {
+            // Fill byte before a marker
+            pos += 1;
+            continue;
+        }
+        if marker == 0xDA || marker == 0xD9 {
+            return None;
+        }
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = bytes.get(pos + 4..pos + 2 + length)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return tiff_orientation(&segment[6..]);
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// The Orientation tag (0x0112) of a TIFF structure's first directory
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let little_endian = match tiff.get(..4)? {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return None,
+    };
+    let read_u16 = |at: usize| {
+        let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let read_u32 = |at: usize| {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    };
+
+    let directory = read_u32(4)? as usize;
+    let entries = read_u16(directory)? as usize;
+    (0..entries)
+        .map(|entry| directory + 2 + entry * 12)
+        .find(|entry| read_u16(*entry) == Some(0x0112))
+        .and_then(|entry| read_u16(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTIFY_OUTPUT: &str = "Image:
+  Filename: photo.jpg
+  Format: JPEG (Joint Photographic Experts Group JFIF format)
+  Properties:
+    date:create: 2024-06-01T10:00:00+00:00
+    exif:DateTime: 2023:05:01 12:00:00
+    exif:GPSLatitude: 37/1, 46/1, 3012/100
+    exif:Make: Apple
+    jpeg:colorspace: 2
+    signature: 0f1e2d
+  Profiles:
+    Profile-exif: 2048 bytes
+    Profile-xmp: 512 bytes
+  Artifacts:
+    verbose: true
+  Tainted: False
+";
+
+    #[test]
+    fn test_parse_identify_verbose() {
+        let tags = parse_identify_verbose(IDENTIFY_OUTPUT);
+        assert_eq!(tags.get("exif:Make").map(String::as_str), Some("Apple"));
+        assert_eq!(tags.get("exif:GPSLatitude").map(String::as_str), Some("37/1, 46/1, 3012/100"));
+        assert_eq!(tags.get("profile:xmp").map(String::as_str), Some("512 bytes"));
+        // Computed properties and artifacts are left out
+        assert!(!tags.contains_key("signature"));
+        assert!(!tags.contains_key("date:create"));
+        assert!(!tags.contains_key("verbose"));
+    }
+
+    #[test]
+    fn test_has_location() {
+        let tags = parse_identify_verbose(IDENTIFY_OUTPUT);
+        assert!(ImageMetadata::new("imagemagick", tags).has_location);
+
+        let mut no_gps = BTreeMap::new();
+        no_gps.insert("exif:Make".to_string(), "Canon".to_string());
+        assert!(!ImageMetadata::new("imagemagick", no_gps).has_location);
+    }
+
+    fn photo(tags: &[(&str, &str)]) -> ImageMetadata {
+        ImageMetadata::new("exiftool", tags.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    #[test]
+    fn test_filing_folder_by_date() {
+        let taken = photo(&[("EXIF:DateTimeOriginal", "2024:07:04 10:22:33"), ("EXIF:ModifyDate", "2025:01:01 00:00:00")]);
+        assert_eq!(taken.filing_folder(FileBy::Date), PathBuf::from("2024").join("07"));
+        // Falls back to the other dates, as ImageMagick names them
+        let tags = parse_identify_verbose(IDENTIFY_OUTPUT);
+        assert_eq!(ImageMetadata::new("imagemagick", tags).filing_folder(FileBy::Date), PathBuf::from("2023").join("05"));
+        let unset = photo(&[("EXIF:DateTimeOriginal", "0000:00:00 00:00:00")]);
+        assert_eq!(unset.filing_folder(FileBy::Date), PathBuf::from(UNDATED_FOLDER));
+    }
+
+    #[test]
+    fn test_filing_folder_by_camera() {
+        let iphone = photo(&[("EXIF:Make", "Apple"), ("EXIF:Model", "iPhone 15 Pro")]);
+        assert_eq!(iphone.filing_folder(FileBy::Camera), PathBuf::from("Apple iPhone 15 Pro"));
+        let nikon = photo(&[("EXIF:Make", "NIKON CORPORATION"), ("EXIF:Model", "NIKON D850")]);
+        assert_eq!(nikon.filing_folder(FileBy::Camera), PathBuf::from("NIKON D850"));
+        let odd = photo(&[("EXIF:Model", "Cam/Model: X?")]);
+        assert_eq!(odd.filing_folder(FileBy::Camera), PathBuf::from("Cam_Model_ X_"));
+        assert_eq!(photo(&[]).filing_folder(FileBy::Camera), PathBuf::from(UNKNOWN_CAMERA_FOLDER));
+    }
+
+    #[test]
+    fn test_exiftool_copy_args() {
+        assert_eq!(
+            exiftool_copy_args("/photos/IMG_0001.heic", false),
+            vec!["-TagsFromFile", "/photos/IMG_0001.heic", "-all:all", "-overwrite_original"]
+        );
+        assert_eq!(
+            exiftool_copy_args("/photos/IMG_0001.heic", true),
+            vec!["-TagsFromFile", "/photos/IMG_0001.heic", "-all:all", "--Orientation", "-overwrite_original"]
+        );
+    }
+
+    /// A TIFF header with one directory holding just the Orientation tag
+    fn tiff_with_orientation(little_endian: bool, orientation: u16) -> Vec<u8> {
+        let u16_bytes = |value: u16| if little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+        let u32_bytes = |value: u32| if little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+        let mut tiff = if little_endian { b"II*\0".to_vec() } else { b"MM\0*".to_vec() };
+        tiff.extend(u32_bytes(8));
+        tiff.extend(u16_bytes(1));
+        tiff.extend(u16_bytes(0x0112));
+        tiff.extend(u16_bytes(3));
+        tiff.extend(u32_bytes(1));
+        tiff.extend(u16_bytes(orientation));
+        tiff.extend([0, 0]);
+        tiff.extend(u32_bytes(0));
+        tiff
+    }
+
+    #[test]
+    fn test_exif_orientation_in_jpeg() {
+        let exif = [b"Exif\0\0".to_vec(), tiff_with_orientation(false, 6)].concat();
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend([0xFF, 0xE1]);
+        jpeg.extend(((exif.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(exif);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x02]);
+        assert_eq!(exif_orientation(&jpeg), Some(6));
+    }
+
+    #[test]
+    fn test_exif_orientation_in_tiff() {
+        assert_eq!(exif_orientation(&tiff_with_orientation(true, 8)), Some(8));
+        assert_eq!(exif_orientation(&tiff_with_orientation(true, 0)), None);
+        // No Exif block before the image data
+        assert_eq!(exif_orientation(&[0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02]), None);
+        assert_eq!(exif_orientation(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn test_parse_exiftool_json() {
+        let json = serde_json::json!([{
+            "SourceFile": "photo.jpg",
+            "File:FileSize": "2.1 MB",
+            "EXIF:Make": "Apple",
+            "EXIF:ISO": 100,
+            "Composite:GPSPosition": "37 deg 46' 30.12\" N, 122 deg 25' 9.84\" W"
+        }]);
+        let tags = parse_exiftool_json(&json);
+        assert_eq!(tags.get("EXIF:Make").map(String::as_str), Some("Apple"));
+        assert_eq!(tags.get("EXIF:ISO").map(String::as_str), Some("100"));
+        assert!(!tags.contains_key("SourceFile"));
+        assert!(!tags.contains_key("File:FileSize"));
+    }
+}
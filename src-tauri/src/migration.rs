@@ -0,0 +1,323 @@
+//! HEIC migration assistant - Scans photo folders and tracks batch HEIC <-> JPEG conversions
+//!
+//! The actual conversions run through the normal ImageMagick path in main.rs; this module
+//! plans the job (what to convert, what to skip, how much space it will take) and keeps a
+//! manifest of created files so a migration can be rolled back.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Apple's still image formats
+pub const HEIC_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// JPEG extensions
+pub const JPEG_EXTENSIONS: &[&str] = &["jpg", "jpeg"];
+
+/// Video containers iPhones record HEVC into
+pub const HEVC_VIDEO_EXTENSIONS: &[&str] = &["mov", "hevc"];
+
+/// Typical size change when re-encoding at similar visual quality
+pub const HEIC_TO_JPEG_SIZE_RATIO: f64 = 2.0;
+pub const JPEG_TO_HEIC_SIZE_RATIO: f64 = 0.5;
+
+/// What a migration of a folder would do, shown to the user before converting
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MigrationScan {
+    pub folder: String,
+    pub target_format: String,
+    /// Files that will be converted
+    pub files: Vec<String>,
+    pub total_bytes: u64,
+    pub projected_bytes: u64,
+    /// Projected change in disk usage (negative means space is saved)
+    pub projected_change_bytes: i64,
+    /// Files skipped because a converted copy already exists next to them
+    pub duplicate_count: usize,
+    /// HEVC videos found in the folder (reported only, not converted)
+    pub hevc_video_count: usize,
+}
+
+/// One converted file, recorded so it can be removed again
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MigrationEntry {
+    pub source: String,
+    pub output: String,
+}
+
+/// Record of everything a migration created, saved next to the converted photos
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MigrationManifest {
+    pub created_at: String,
+    pub folder: String,
+    pub target_format: String,
+    pub entries: Vec<MigrationEntry>,
+}
+
+/// Outcome of a migration run
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MigrationResult {
+    pub converted: usize,
+    /// Files skipped because a converted copy already exists
+    pub skipped: usize,
+    /// "path: error" for every file that failed to convert
+    pub failed: Vec<String>,
+    /// Manifest to pass to rollback (None if nothing was converted)
+    pub manifest_path: Option<String>,
+}
+
+/// Outcome of undoing a migration
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RollbackReport {
+    pub removed: usize,
+    /// Outputs that were already gone (moved or deleted by the user)
+    pub missing: usize,
+}
+
+impl MigrationManifest {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize migration manifest: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write migration manifest: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read migration manifest: {}", e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse migration manifest: {}", e))
+    }
+}
+
+/// Returns the lowercase extension of a path
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Checks if a file extension is converted when migrating to `target_format` ("jpg" or "heic")
+pub fn is_migration_source(ext: &str, target_format: &str) -> bool {
+    if HEIC_EXTENSIONS.contains(&target_format) {
+        JPEG_EXTENSIONS.contains(&ext)
+    } else {
+        HEIC_EXTENSIONS.contains(&ext)
+    }
+}
+
+/// Estimates the converted size of `bytes` worth of source photos
+pub fn projected_size(bytes: u64, target_format: &str) -> u64 {
+    let ratio = if HEIC_EXTENSIONS.contains(&target_format) {
+        JPEG_TO_HEIC_SIZE_RATIO
+    } else {
+        HEIC_TO_JPEG_SIZE_RATIO
+    };
+    (bytes as f64 * ratio).round() as u64
+}
+
+/// The converted copy is written next to the source with the same name
+pub fn migration_output_path(source: &Path, target_format: &str) -> PathBuf {
+    source.with_extension(target_format)
+}
+
+/// Checks if a converted copy of `source` already exists (e.g. iPhone "Most Compatible" exports)
+pub fn has_existing_copy(source: &Path, target_format: &str) -> bool {
+    let candidates: &[&str] = if HEIC_EXTENSIONS.contains(&target_format) {
+        HEIC_EXTENSIONS
+    } else {
+        JPEG_EXTENSIONS
+    };
+
+    candidates.iter().any(|ext| {
+        source.with_extension(ext).exists() || source.with_extension(ext.to_uppercase()).exists()
+    })
+}
+
+/// Recursively lists the files in `folder`, skipping hidden files and folders. Symlinked
+/// folders aren't followed, so a link back up the tree can't make the walk loop forever.
+pub fn collect_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![folder.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_symlink() && path.is_dir() {
+                continue;
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Scans `folder` for photos to migrate to `target_format`.
+///
+/// `is_hevc` decides whether a video candidate really is HEVC (main.rs probes it with FFmpeg).
+pub fn scan_folder<F>(folder: &Path, target_format: &str, is_hevc: F) -> MigrationScan
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut scan = MigrationScan {
+        folder: folder.to_string_lossy().to_string(),
+        target_format: target_format.to_string(),
+        ..Default::default()
+    };
+
+    for path in collect_files(folder) {
+        let ext = extension_of(&path);
+
+        if HEVC_VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+            if is_hevc(&path) {
+                scan.hevc_video_count += 1;
+            }
+            continue;
+        }
+
+        if !is_migration_source(&ext, target_format) {
+            continue;
+        }
+
+        if has_existing_copy(&path, target_format) {
+            scan.duplicate_count += 1;
+            continue;
+        }
+
+        scan.total_bytes += std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        scan.files.push(path.to_string_lossy().to_string());
+    }
+
+    scan.projected_bytes = projected_size(scan.total_bytes, target_format);
+    scan.projected_change_bytes = scan.projected_bytes as i64 - scan.total_bytes as i64;
+    scan
+}
+
+/// Removes every output listed in the manifest. Source photos are never touched.
+pub fn rollback(manifest: &MigrationManifest) -> RollbackReport {
+    let mut report = RollbackReport::default();
+
+    for entry in &manifest.entries {
+        let output = Path::new(&entry.output);
+        if output.exists() && std::fs::remove_file(output).is_ok() {
+            report.removed += 1;
+        } else {
+            report.missing += 1;
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty scratch folder for a test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("convertsave-migration-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_migration_sources() {
+        assert!(is_migration_source("heic", "jpg"));
+        assert!(is_migration_source("heif", "jpg"));
+        assert!(!is_migration_source("jpg", "jpg"));
+        assert!(is_migration_source("jpeg", "heic"));
+        assert!(!is_migration_source("heic", "heic"));
+        assert!(!is_migration_source("png", "jpg"));
+    }
+
+    #[test]
+    fn test_projected_size() {
+        assert_eq!(projected_size(1000, "jpg"), 2000);
+        assert_eq!(projected_size(1000, "heic"), 500);
+    }
+
+    #[test]
+    fn test_scan_folder_counts_and_skips_duplicates() {
+        let dir = scratch_dir("scan");
+        std::fs::write(dir.join("IMG_0001.HEIC"), vec![0u8; 100]).unwrap();
+        std::fs::write(dir.join("IMG_0002.heic"), vec![0u8; 300]).unwrap();
+        // Already has a JPEG copy next to it
+        std::fs::write(dir.join("IMG_0002.jpg"), vec![0u8; 10]).unwrap();
+        std::fs::create_dir_all(dir.join("trip")).unwrap();
+        std::fs::write(dir.join("trip").join("IMG_0003.heif"), vec![0u8; 200]).unwrap();
+        std::fs::write(dir.join("IMG_0004.MOV"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.join(".hidden.heic"), vec![0u8; 10]).unwrap();
+
+        let scan = scan_folder(&dir, "jpg", |_| true);
+        assert_eq!(scan.files.len(), 2);
+        assert_eq!(scan.duplicate_count, 1);
+        assert_eq!(scan.hevc_video_count, 1);
+        assert_eq!(scan.total_bytes, 300);
+        assert_eq!(scan.projected_bytes, 600);
+        assert_eq!(scan.projected_change_bytes, 300);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_files_skips_symlinked_folders() {
+        let dir = scratch_dir("symlink");
+        std::fs::create_dir_all(dir.join("trip")).unwrap();
+        std::fs::write(dir.join("trip").join("IMG_0001.heic"), vec![0u8; 10]).unwrap();
+        // A link back up the tree
+        std::os::unix::fs::symlink(&dir, dir.join("trip").join("loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("trip").join("IMG_0001.heic"), dir.join("link.heic")).unwrap();
+
+        let files = collect_files(&dir);
+        assert_eq!(files, vec![dir.join("link.heic"), dir.join("trip").join("IMG_0001.heic")]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_manifest_round_trip_and_rollback() {
+        let dir = scratch_dir("rollback");
+        let source = dir.join("IMG_0001.heic");
+        let output = migration_output_path(&source, "jpg");
+        std::fs::write(&source, b"source").unwrap();
+        std::fs::write(&output, b"output").unwrap();
+
+        let manifest = MigrationManifest {
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            folder: dir.to_string_lossy().to_string(),
+            target_format: "jpg".to_string(),
+            entries: vec![
+                MigrationEntry { source: source.to_string_lossy().to_string(), output: output.to_string_lossy().to_string() },
+                MigrationEntry { source: "gone.heic".to_string(), output: dir.join("gone.jpg").to_string_lossy().to_string() },
+            ],
+        };
+        let manifest_path = dir.join("manifest.json");
+        manifest.save(&manifest_path).unwrap();
+        let loaded = MigrationManifest::load(&manifest_path).unwrap();
+        assert_eq!(loaded, manifest);
+
+        let report = rollback(&loaded);
+        assert_eq!(report, RollbackReport { removed: 1, missing: 1 });
+        assert!(source.exists());
+        assert!(!output.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
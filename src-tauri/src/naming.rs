@@ -0,0 +1,262 @@
+//! Output naming templates - Renders file names like `{name}-{width}x{height}` for converted files
+//!
+//! The rendered name is only the file stem; main.rs still adds the extension and a
+//! " (1)" suffix when the file already exists. `sanitize_file_name` then adapts the stem to
+//! the file system it's written to: USB sticks (FAT32/exFAT) and SMB shares reject names
+//! that are fine on the local disk.
+
+use serde::{Deserialize, Serialize};
+
+/// Template used when neither the conversion nor the config sets one
+pub const DEFAULT_NAME_TEMPLATE: &str = "{name}";
+
+/// Tokens a template may contain
+pub const NAME_TOKENS: &[&str] = &["name", "format", "date", "counter", "width", "height"];
+
+/// Characters that aren't allowed in file names on at least one platform
+const INVALID_FILE_NAME_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows, FAT and SMB reserve regardless of extension (`con.jpg` fails too)
+const RESERVED_FILE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest portable stem in bytes. File systems cap names at 255, which leaves room for
+/// the extension and a " (12)" suffix.
+const MAX_PORTABLE_STEM_BYTES: usize = 200;
+
+/// How output file names are adapted to the destination file system
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FileNameMode {
+    /// Keep the name as rendered (already valid on the local disk)
+    #[default]
+    Native,
+    /// Safe on FAT32, exFAT and SMB shares: no reserved device names, no trailing dots
+    /// or spaces, and a length limit
+    Portable,
+    /// Portable, with letters from other scripts transliterated to plain ASCII
+    Ascii,
+}
+
+/// Values substituted into a naming template
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NamingContext {
+    /// Input file stem
+    pub name: String,
+    /// Output format (extension without the dot)
+    pub format: String,
+    /// Conversion date, YYYY-MM-DD
+    pub date: String,
+    /// Position of the file in a batch, starting at 1
+    pub counter: u32,
+    /// Input dimensions, when the input has them (resizing isn't taken into account)
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Checks that every `{token}` in the template is known and braces are balanced
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Naming template can't be empty".to_string());
+    }
+
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed '{{' in naming template: {}", template))?;
+        let token = &rest[start + 1..start + end];
+        if !NAME_TOKENS.contains(&token) {
+            return Err(format!(
+                "Unknown token {{{}}} in naming template. Available: {}",
+                token,
+                NAME_TOKENS.iter().map(|t| format!("{{{}}}", t)).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    Ok(())
+}
+
+/// Checks if rendering the template needs the input's dimensions (which costs a probe)
+pub fn uses_dimensions(template: &str) -> bool {
+    template.contains("{width}") || template.contains("{height}")
+}
+
+/// Renders a template into a file stem. Unknown dimensions render as 0; characters that
+/// aren't valid in file names are replaced, and an empty result falls back to the input name.
+///
+/// The template is read once from left to right, so a value that contains a token (an
+/// input named `{date}`) is never substituted again. Unknown tokens are kept as they are.
+pub fn render_template(template: &str, context: &NamingContext) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let token = &rest[start + 1..start + end];
+        match token {
+            "name" => rendered.push_str(&context.name),
+            "format" => rendered.push_str(&context.format),
+            "date" => rendered.push_str(&context.date),
+            "counter" => rendered.push_str(&context.counter.to_string()),
+            "width" => rendered.push_str(&context.width.unwrap_or(0).to_string()),
+            "height" => rendered.push_str(&context.height.unwrap_or(0).to_string()),
+            _ => rendered.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+
+    let sanitized: String = rendered
+        .chars()
+        .map(|c| if INVALID_FILE_NAME_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    // Windows rejects names ending in a dot or space
+    let sanitized = sanitized.trim().trim_end_matches('.').to_string();
+
+    if sanitized.is_empty() {
+        context.name.clone()
+    } else {
+        sanitized
+    }
+}
+
+/// Adapts a file stem to `mode`. An empty result falls back to "file".
+pub fn sanitize_file_name(stem: &str, mode: FileNameMode) -> String {
+    if mode == FileNameMode::Native {
+        return stem.to_string();
+    }
+
+    let transliterated;
+    let stem = if mode == FileNameMode::Ascii {
+        transliterated = deunicode::deunicode_with_tofu(stem, "_");
+        transliterated.as_str()
+    } else {
+        stem
+    };
+
+    let mut sanitized: String = stem
+        .chars()
+        .map(|c| if INVALID_FILE_NAME_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect();
+    // Cut on a character boundary, never in the middle of a multi-byte letter
+    if sanitized.len() > MAX_PORTABLE_STEM_BYTES {
+        let mut end = MAX_PORTABLE_STEM_BYTES;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+    let mut sanitized = sanitized.trim().trim_end_matches(['.', ' ']).to_string();
+
+    // "con.backup" is as reserved as "con"
+    let device = sanitized.split('.').next().unwrap_or("");
+    if RESERVED_FILE_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(device)) {
+        sanitized.insert(device.len(), '_');
+    }
+
+    if sanitized.is_empty() {
+        "file".to_string()
+    } else {
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> NamingContext {
+        NamingContext {
+            name: "holiday".to_string(),
+            format: "webp".to_string(),
+            date: "2024-07-04".to_string(),
+            counter: 3,
+            width: Some(1920),
+            height: Some(1080),
+        }
+    }
+
+    #[test]
+    fn test_render_tokens() {
+        assert_eq!(render_template(DEFAULT_NAME_TEMPLATE, &context()), "holiday");
+        assert_eq!(
+            render_template("{date}_{name}-{counter}_{width}x{height}.{format}", &context()),
+            "2024-07-04_holiday-3_1920x1080.webp"
+        );
+    }
+
+    #[test]
+    fn test_render_substitutes_once() {
+        let ctx = NamingContext { name: "{date} {format}".to_string(), ..context() };
+        assert_eq!(render_template("{name}-{counter}", &ctx), "{date} {format}-3");
+        assert_eq!(render_template("{size}_{name", &context()), "{size}_{name");
+    }
+
+    #[test]
+    fn test_render_sanitizes() {
+        assert_eq!(render_template("{name}/../{format}", &context()), "holiday_.._webp");
+        assert_eq!(render_template("{name}. ", &context()), "holiday");
+        // Nothing usable left falls back to the input name
+        assert_eq!(render_template(" . ", &context()), "holiday");
+    }
+
+    #[test]
+    fn test_missing_dimensions() {
+        let ctx = NamingContext { width: None, height: None, ..context() };
+        assert_eq!(render_template("{name}_{width}x{height}", &ctx), "holiday_0x0");
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("{name}-{counter}").is_ok());
+        assert!(validate_template("plain").is_ok());
+        assert!(validate_template("").is_err());
+        assert!(validate_template("{name").is_err());
+        assert!(validate_template("{size}").unwrap_err().contains("{size}"));
+    }
+
+    #[test]
+    fn test_sanitize_native_keeps_name() {
+        assert_eq!(sanitize_file_name("Café — résumé", FileNameMode::Native), "Café — résumé");
+    }
+
+    #[test]
+    fn test_sanitize_portable() {
+        assert_eq!(sanitize_file_name("Café: draft?", FileNameMode::Portable), "Café_ draft_");
+        assert_eq!(sanitize_file_name("notes. . ", FileNameMode::Portable), "notes");
+        assert_eq!(sanitize_file_name("CON", FileNameMode::Portable), "CON_");
+        assert_eq!(sanitize_file_name("aux.old", FileNameMode::Portable), "aux_.old");
+        assert_eq!(sanitize_file_name("console", FileNameMode::Portable), "console");
+        assert_eq!(sanitize_file_name("...", FileNameMode::Portable), "file");
+    }
+
+    #[test]
+    fn test_sanitize_portable_truncates_on_char_boundary() {
+        let long = "é".repeat(150);
+        let sanitized = sanitize_file_name(&long, FileNameMode::Portable);
+        assert_eq!(sanitized.len(), MAX_PORTABLE_STEM_BYTES);
+        assert!(sanitized.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn test_sanitize_ascii_transliterates() {
+        assert_eq!(sanitize_file_name("Café résumé", FileNameMode::Ascii), "Cafe resume");
+        assert_eq!(sanitize_file_name("Жуков", FileNameMode::Ascii), "Zhukov");
+        assert!(sanitize_file_name("東京 2024", FileNameMode::Ascii).is_ascii());
+    }
+
+    #[test]
+    fn test_uses_dimensions() {
+        assert!(uses_dimensions("{name}_{width}x{height}"));
+        assert!(!uses_dimensions("{name}_{date}"));
+    }
+}
@@ -0,0 +1,1012 @@
+//! Calendars and contacts - ICS <-> CSV and VCF <-> CSV, converted in-process
+//!
+//! iCalendar (RFC 5545) and vCard (RFC 2426/6350) share a line format: `NAME;PARAM=x:value`
+//! with long lines folded onto continuation lines that start with a space. Events and
+//! contacts are read into plain structs and written as CSV with the column names calendar
+//! and address book imports understand (Google, Outlook). CSV input is matched by those
+//! names and common aliases, so exports from other apps convert back without editing.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Input/output pairs handled here
+pub const PIM_CONVERSIONS: &[(&str, &str)] = &[("ics", "csv"), ("csv", "ics"), ("vcf", "csv"), ("csv", "vcf")];
+
+pub fn supports_pim_conversion(input_ext: &str, output_ext: &str) -> bool {
+    PIM_CONVERSIONS.contains(&(input_ext, output_ext))
+}
+
+/// Converts the text of a calendar, contact list or CSV file between the formats above
+pub fn convert(text: &str, input_ext: &str, output_ext: &str, timestamp: NaiveDateTime) -> Result<String, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    match (input_ext, output_ext) {
+        ("ics", "csv") => Ok(events_to_csv(&parse_ics(text)?)),
+        ("csv", "ics") => Ok(events_to_ics(&csv_to_events(text)?, timestamp)),
+        ("vcf", "csv") => Ok(contacts_to_csv(&parse_vcf(text)?)),
+        ("csv", "vcf") => Ok(contacts_to_vcf(&csv_to_contacts(text)?)),
+        _ => Err(format!("Converting {} to {} isn't supported", input_ext.to_uppercase(), output_ext.to_uppercase())),
+    }
+}
+
+// ==========================================
+// Content lines
+// ==========================================
+
+/// One `NAME;PARAM=value:value` line, with the name and parameter names uppercased
+#[derive(Debug, Clone, PartialEq)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Every TYPE given, whether as `TYPE=a,b`, repeated `TYPE=` or vCard 2.1 bare words
+    fn types(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|(key, _)| key == "TYPE" || key.is_empty())
+            .flat_map(|(_, value)| value.split(','))
+            .map(|value| value.trim_matches('"').to_lowercase())
+            .collect()
+    }
+
+    /// The value with escapes decoded (and quoted-printable, which vCard 2.1 files use)
+    fn text(&self) -> String {
+        if self.param("ENCODING").is_some_and(|encoding| encoding.eq_ignore_ascii_case("QUOTED-PRINTABLE")) {
+            decode_quoted_printable(&self.value)
+        } else {
+            unescape_value(&self.value)
+        }
+    }
+
+    /// The `;`-separated components of a structured value (N, ADR, ORG), decoded
+    fn components(&self) -> Vec<String> {
+        let value = if self.param("ENCODING").is_some_and(|encoding| encoding.eq_ignore_ascii_case("QUOTED-PRINTABLE")) {
+            decode_quoted_printable(&self.value)
+        } else {
+            self.value.clone()
+        };
+        split_unescaped(&value, ';').iter().map(|component| unescape_value(component)).collect()
+    }
+}
+
+/// Joins folded lines. Quoted-printable values continue with a trailing `=` instead.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match lines.last_mut() {
+            Some(last) if line.starts_with(' ') || line.starts_with('\t') => last.push_str(&line[1..]),
+            Some(last) if last.ends_with('=') && last.to_uppercase().contains("QUOTED-PRINTABLE") => {
+                last.pop();
+                last.push_str(line);
+            }
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // The value starts at the first colon outside a quoted parameter
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(index),
+        _ => None,
+    })?;
+    let mut parts = line[..colon].split(';');
+    // vCard lines can carry a group prefix (item1.EMAIL)
+    let name = parts.next()?.rsplit('.').next()?.trim().to_uppercase();
+    let params = parts
+        .map(|param| match param.split_once('=') {
+            Some((key, value)) => (key.trim().to_uppercase(), value.trim().to_string()),
+            None => (String::new(), param.trim().to_string()),
+        })
+        .collect();
+    Some(Property { name, params, value: line[colon + 1..].to_string() })
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits on `separator` where it isn't backslash-escaped, keeping the escapes
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        if c == separator && !escaped {
+            parts.push(String::new());
+            continue;
+        }
+        escaped = c == '\\' && !escaped;
+        parts.last_mut().expect("never empty").push(c);
+    }
+    parts
+}
+
+fn decode_quoted_printable(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[index], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'=', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Folds a content line at 75 bytes, without splitting a character
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// The properties of every top-level `component` block (VEVENT, VCARD); properties of
+/// blocks nested inside it (VALARM) are skipped
+fn components(text: &str, component: &str) -> Vec<Vec<Property>> {
+    let mut found = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    for property in unfold(text).iter().filter_map(|line| parse_property(line)) {
+        match property.name.as_str() {
+            "BEGIN" => {
+                let name = property.value.trim().to_uppercase();
+                if name == component && !stack.iter().any(|open| open == component) {
+                    found.push(Vec::new());
+                }
+                stack.push(name);
+            }
+            "END" => {
+                stack.pop();
+            }
+            _ if stack.last().map(String::as_str) == Some(component) => {
+                if let Some(current) = found.last_mut() {
+                    current.push(property);
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+// ==========================================
+// CSV
+// ==========================================
+
+/// Parses CSV with quoted fields (which may hold commas, quotes and line breaks).
+/// Semicolon-separated files, as Excel writes them in some locales, are detected too.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let header = text.lines().next().unwrap_or("");
+    let separator = if header.matches(';').count() > header.matches(',').count() { ';' } else { ',' };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut csv = header.iter().map(|name| csv_field(name)).collect::<Vec<_>>().join(",");
+    csv.push_str("\r\n");
+    for row in rows {
+        csv.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Lowercase letters and digits of a column name, so "E-mail Address" matches "emailaddress"
+fn header_key(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// The first column whose name is one of `aliases`
+fn find_column(header: &[String], aliases: &[&str]) -> Option<usize> {
+    aliases.iter().find_map(|alias| header.iter().position(|name| header_key(name) == *alias))
+}
+
+fn cell(row: &[String], column: Option<usize>) -> String {
+    column.and_then(|column| row.get(column)).map(|value| value.trim().to_string()).unwrap_or_default()
+}
+
+// ==========================================
+// Calendars
+// ==========================================
+
+/// When an event starts or ends: a day for all-day events, otherwise a time that is
+/// UTC ("UTC"), in a named zone, or floating (no zone)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventTime {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub time_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub start: Option<EventTime>,
+    /// Inclusive for all-day events (the last day), unlike ICS where DTEND is the day after
+    pub end: Option<EventTime>,
+    pub description: String,
+    pub location: String,
+    pub uid: String,
+}
+
+const EVENT_COLUMNS: &[&str] = &[
+    "Subject", "Start Date", "Start Time", "End Date", "End Time", "All Day Event",
+    "Time Zone", "Description", "Location", "UID",
+];
+
+fn parse_ics_time(property: &Property) -> Option<EventTime> {
+    let value = property.value.trim();
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    if property.param("VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return Some(EventTime { date, time: None, time_zone: None });
+    }
+    let time = NaiveTime::parse_from_str(value.get(9..15)?, "%H%M%S").ok()?;
+    let time_zone = if value.ends_with('Z') {
+        Some("UTC".to_string())
+    } else {
+        property.param("TZID").map(|zone| zone.trim_matches('"').to_string())
+    };
+    Some(EventTime { date, time: Some(time), time_zone })
+}
+
+/// An ISO 8601 duration such as `PT1H30M` or `P1D`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut rest = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut in_time = false;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('T') {
+            in_time = true;
+            rest = after;
+            continue;
+        }
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: i64 = rest[..digits].parse().ok()?;
+        // Out of range amounts are rejected rather than overflowing
+        let part = match (rest[digits..].chars().next()?, in_time) {
+            ('W', false) => Duration::try_weeks(amount),
+            ('D', false) => Duration::try_days(amount),
+            ('H', true) => Duration::try_hours(amount),
+            ('M', true) => Duration::try_minutes(amount),
+            ('S', true) => Duration::try_seconds(amount),
+            _ => return None,
+        };
+        total = total.checked_add(&part?)?;
+        rest = &rest[digits + 1..];
+    }
+    Some(if negative { -total } else { total })
+}
+
+/// The end of an event lasting `duration`, or None when it's past the dates chrono can hold
+fn add_duration(start: &EventTime, duration: Duration) -> Option<EventTime> {
+    match start.time {
+        Some(time) => {
+            let end = start.date.and_time(time).checked_add_signed(duration)?;
+            Some(EventTime { date: end.date(), time: Some(end.time()), time_zone: start.time_zone.clone() })
+        }
+        None => Some(EventTime { date: start.date.checked_add_signed(duration)?, time: None, time_zone: None }),
+    }
+}
+
+/// Reads the events of an iCalendar file
+pub fn parse_ics(text: &str) -> Result<Vec<CalendarEvent>, String> {
+    let events: Vec<CalendarEvent> = components(text, "VEVENT")
+        .into_iter()
+        .map(|properties| {
+            let mut event = CalendarEvent::default();
+            let mut duration = None;
+            for property in &properties {
+                match property.name.as_str() {
+                    "SUMMARY" => event.summary = property.text(),
+                    "DTSTART" => event.start = parse_ics_time(property),
+                    "DTEND" => event.end = parse_ics_time(property),
+                    "DURATION" => duration = parse_duration(&property.value),
+                    "DESCRIPTION" => event.description = property.text(),
+                    "LOCATION" => event.location = property.text(),
+                    "UID" => event.uid = property.text(),
+                    _ => {}
+                }
+            }
+            if event.end.is_none() {
+                event.end = event.start.as_ref().zip(duration).and_then(|(start, duration)| add_duration(start, duration));
+            }
+            // All-day events end the day before their exclusive DTEND
+            if let (Some(start), Some(end)) = (&event.start, &mut event.end) {
+                if start.time.is_none() && end.time.is_none() && end.date > start.date {
+                    end.date = end.date.pred_opt().unwrap_or(end.date);
+                }
+            }
+            event
+        })
+        .collect();
+    if events.is_empty() {
+        return Err("No events were found in this calendar".to_string());
+    }
+    Ok(events)
+}
+
+pub fn events_to_csv(events: &[CalendarEvent]) -> String {
+    let date = |time: Option<&EventTime>| time.map(|time| time.date.format("%Y-%m-%d").to_string()).unwrap_or_default();
+    let clock = |time: Option<&EventTime>| {
+        time.and_then(|time| time.time).map(|time| time.format("%H:%M").to_string()).unwrap_or_default()
+    };
+    let rows: Vec<Vec<String>> = events
+        .iter()
+        .map(|event| {
+            let start = event.start.as_ref();
+            let end = event.end.as_ref();
+            let all_day = start.is_some_and(|start| start.time.is_none());
+            vec![
+                event.summary.clone(),
+                date(start),
+                clock(start),
+                date(end),
+                clock(end),
+                if all_day { "True" } else { "False" }.to_string(),
+                start.and_then(|start| start.time_zone.clone()).unwrap_or_default(),
+                event.description.clone(),
+                event.location.clone(),
+                event.uid.clone(),
+            ]
+        })
+        .collect();
+    write_csv(EVENT_COLUMNS, &rows)
+}
+
+fn parse_csv_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%m/%d/%Y", "%Y/%m/%d", "%d.%m.%Y", "%Y%m%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+fn parse_csv_time(value: &str) -> Option<NaiveTime> {
+    let value = value.trim().to_uppercase();
+    ["%H:%M", "%H:%M:%S", "%I:%M %p", "%I:%M:%S %p", "%I:%M%p", "%I %p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&value, format).ok())
+}
+
+/// A date column that may also hold the time ("2024-03-15 09:30", "2024-03-15T09:30")
+fn parse_csv_date_time(date: &str, time: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if let Some(day) = parse_csv_date(date) {
+        let time = if time.is_empty() { None } else { Some(parse_csv_time(time)?) };
+        return Some((day, time));
+    }
+    let (day, clock) = date.split_once(['T', ' '])?;
+    Some((parse_csv_date(day)?, Some(parse_csv_time(clock)?)))
+}
+
+fn is_true(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "true" | "yes" | "y" | "1" | "x")
+}
+
+/// Reads events from a CSV with a header row. Subject and Start Date are required.
+pub fn csv_to_events(text: &str) -> Result<Vec<CalendarEvent>, String> {
+    let rows = parse_csv(text);
+    let (header, rows) = rows.split_first().ok_or("The CSV file is empty")?;
+    let summary = find_column(header, &["subject", "summary", "title", "event", "eventname", "name"]);
+    let start_date = find_column(header, &["startdate", "start", "date", "begin"]);
+    let (Some(_), Some(_)) = (summary, start_date) else {
+        return Err("The CSV needs at least a Subject and a Start Date column to become a calendar".to_string());
+    };
+    let start_time = find_column(header, &["starttime", "begintime", "time"]);
+    let end_date = find_column(header, &["enddate", "end", "finish"]);
+    let end_time = find_column(header, &["endtime", "finishtime"]);
+    let all_day = find_column(header, &["alldayevent", "allday"]);
+    let time_zone = find_column(header, &["timezone", "tz", "tzid"]);
+    let description = find_column(header, &["description", "notes", "note", "details"]);
+    let location = find_column(header, &["location", "place", "where"]);
+    let uid = find_column(header, &["uid", "id"]);
+
+    let mut events = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        // Row numbers as a spreadsheet shows them, after the header
+        let line = index + 2;
+        let (date, mut time) = parse_csv_date_time(&cell(row, start_date), &cell(row, start_time))
+            .ok_or_else(|| format!("Row {} has a start date or time that can't be read", line))?;
+        let is_all_day = is_true(&cell(row, all_day)) || time.is_none();
+        if is_all_day {
+            time = None;
+        }
+        let zone = Some(cell(row, time_zone)).filter(|zone| !zone.is_empty() && !is_all_day);
+        let start = EventTime { date, time, time_zone: zone.clone() };
+
+        let end_date_value = cell(row, end_date);
+        let end_time_value = cell(row, end_time);
+        let end = if end_date_value.is_empty() && end_time_value.is_empty() {
+            None
+        } else {
+            let (end_day, end_clock) = if end_date_value.is_empty() {
+                (date, parse_csv_time(&end_time_value).map(Some))
+            } else {
+                parse_csv_date_time(&end_date_value, &end_time_value)
+                    .map(|(day, clock)| (day, Some(clock)))
+                    .ok_or_else(|| format!("Row {} has an end date or time that can't be read", line))?
+            };
+            let end_clock = end_clock.ok_or_else(|| format!("Row {} has an end time that can't be read", line))?;
+            Some(EventTime {
+                date: end_day,
+                time: if is_all_day { None } else { end_clock.or(time) },
+                time_zone: zone,
+            })
+        };
+
+        events.push(CalendarEvent {
+            summary: cell(row, summary),
+            start: Some(start),
+            end,
+            description: cell(row, description),
+            location: cell(row, location),
+            uid: cell(row, uid),
+        });
+    }
+    if events.is_empty() {
+        return Err("The CSV has a header but no events".to_string());
+    }
+    Ok(events)
+}
+
+fn ics_time_property(name: &str, time: &EventTime) -> String {
+    match (time.time, time.time_zone.as_deref()) {
+        (None, _) => format!("{};VALUE=DATE:{}", name, time.date.format("%Y%m%d")),
+        (Some(clock), Some("UTC")) => format!("{}:{}Z", name, time.date.and_time(clock).format("%Y%m%dT%H%M%S")),
+        (Some(clock), Some(zone)) => format!("{};TZID={}:{}", name, zone, time.date.and_time(clock).format("%Y%m%dT%H%M%S")),
+        (Some(clock), None) => format!("{}:{}", name, time.date.and_time(clock).format("%Y%m%dT%H%M%S")),
+    }
+}
+
+/// Writes an iCalendar file. `timestamp` (UTC) is the DTSTAMP every event needs; events
+/// without a UID get one derived from their contents, so converting twice gives the same IDs.
+pub fn events_to_ics(events: &[CalendarEvent], timestamp: NaiveDateTime) -> String {
+    let mut ics = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//ConvertSave//Calendar//EN\r\nCALSCALE:GREGORIAN\r\n");
+    for event in events {
+        let Some(start) = &event.start else { continue };
+        let uid = if event.uid.is_empty() {
+            let mut hasher = DefaultHasher::new();
+            (&event.summary, start.date, start.time, &event.location).hash(&mut hasher);
+            format!("{:016x}@convertsave", hasher.finish())
+        } else {
+            event.uid.clone()
+        };
+        ics.push_str("BEGIN:VEVENT\r\n");
+        ics.push_str(&fold(&format!("UID:{}", escape_value(&uid))));
+        ics.push_str(&format!("DTSTAMP:{}Z\r\n", timestamp.format("%Y%m%dT%H%M%S")));
+        ics.push_str(&fold(&ics_time_property("DTSTART", start)));
+        match &event.end {
+            // DTEND of an all-day event is the day after it ends
+            Some(end) if end.time.is_none() => {
+                let next_day = EventTime { date: end.date.succ_opt().unwrap_or(end.date), time: None, time_zone: None };
+                ics.push_str(&fold(&ics_time_property("DTEND", &next_day)));
+            }
+            Some(end) => ics.push_str(&fold(&ics_time_property("DTEND", end))),
+            None => {}
+        }
+        ics.push_str(&fold(&format!("SUMMARY:{}", escape_value(&event.summary))));
+        if !event.location.is_empty() {
+            ics.push_str(&fold(&format!("LOCATION:{}", escape_value(&event.location))));
+        }
+        if !event.description.is_empty() {
+            ics.push_str(&fold(&format!("DESCRIPTION:{}", escape_value(&event.description))));
+        }
+        ics.push_str("END:VEVENT\r\n");
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+// ==========================================
+// Contacts
+// ==========================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhoneKind {
+    Mobile,
+    Work,
+    Home,
+    Other,
+}
+
+impl PhoneKind {
+    fn from_types(types: &[String]) -> Self {
+        if types.iter().any(|kind| matches!(kind.as_str(), "cell" | "mobile" | "iphone")) {
+            PhoneKind::Mobile
+        } else if types.iter().any(|kind| kind == "work") {
+            PhoneKind::Work
+        } else if types.iter().any(|kind| kind == "home") {
+            PhoneKind::Home
+        } else {
+            PhoneKind::Other
+        }
+    }
+
+    fn vcard_type(self) -> &'static str {
+        match self {
+            PhoneKind::Mobile => "CELL",
+            PhoneKind::Work => "WORK,VOICE",
+            PhoneKind::Home => "HOME,VOICE",
+            PhoneKind::Other => "VOICE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Contact {
+    pub full_name: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub organization: String,
+    pub title: String,
+    pub emails: Vec<String>,
+    pub phones: Vec<(PhoneKind, String)>,
+    pub address: String,
+    /// YYYY-MM-DD, or as written when it isn't a full date (`--0412`)
+    pub birthday: String,
+    pub note: String,
+}
+
+const CONTACT_COLUMNS: &[&str] = &[
+    "Full Name", "First Name", "Last Name", "Organization", "Title", "Email",
+    "Mobile Phone", "Work Phone", "Home Phone", "Other Phone", "Address", "Birthday", "Notes",
+];
+
+/// Separates several emails or phone numbers in one CSV cell
+const MULTI_VALUE_SEPARATOR: &str = "; ";
+
+fn normalize_birthday(value: &str) -> String {
+    let value = value.trim();
+    let day = value.get(..10).filter(|day| day.len() == 10).unwrap_or(value);
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(day, "%Y-%m-%d"))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Reads the contacts of a vCard file (versions 2.1, 3.0 and 4.0)
+pub fn parse_vcf(text: &str) -> Result<Vec<Contact>, String> {
+    let contacts: Vec<Contact> = components(text, "VCARD")
+        .into_iter()
+        .map(|properties| {
+            let mut contact = Contact::default();
+            for property in &properties {
+                match property.name.as_str() {
+                    "FN" => contact.full_name = property.text(),
+                    "N" => {
+                        let parts = property.components();
+                        contact.last_name = parts.first().cloned().unwrap_or_default();
+                        contact.first_name = parts.get(1).cloned().unwrap_or_default();
+                    }
+                    "ORG" => contact.organization = property.components().into_iter().filter(|part| !part.is_empty()).collect::<Vec<_>>().join(", "),
+                    "TITLE" => contact.title = property.text(),
+                    "EMAIL" => contact.emails.push(property.text()),
+                    "TEL" => contact.phones.push((PhoneKind::from_types(&property.types()), property.text().trim_start_matches("tel:").to_string())),
+                    "ADR" if contact.address.is_empty() => {
+                        contact.address = property.components().into_iter().map(|part| part.replace('\n', ", ")).filter(|part| !part.trim().is_empty()).collect::<Vec<_>>().join(", ");
+                    }
+                    "BDAY" => contact.birthday = normalize_birthday(&property.text()),
+                    "NOTE" => contact.note = property.text(),
+                    _ => {}
+                }
+            }
+            if contact.full_name.is_empty() {
+                contact.full_name = [contact.first_name.as_str(), contact.last_name.as_str()]
+                    .iter()
+                    .filter(|part| !part.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+            }
+            contact
+        })
+        .collect();
+    if contacts.is_empty() {
+        return Err("No contacts were found in this vCard file".to_string());
+    }
+    Ok(contacts)
+}
+
+pub fn contacts_to_csv(contacts: &[Contact]) -> String {
+    let phones = |contact: &Contact, kind: PhoneKind| {
+        contact.phones.iter().filter(|(phone_kind, _)| *phone_kind == kind).map(|(_, number)| number.as_str()).collect::<Vec<_>>().join(MULTI_VALUE_SEPARATOR)
+    };
+    let rows: Vec<Vec<String>> = contacts
+        .iter()
+        .map(|contact| {
+            vec![
+                contact.full_name.clone(),
+                contact.first_name.clone(),
+                contact.last_name.clone(),
+                contact.organization.clone(),
+                contact.title.clone(),
+                contact.emails.join(MULTI_VALUE_SEPARATOR),
+                phones(contact, PhoneKind::Mobile),
+                phones(contact, PhoneKind::Work),
+                phones(contact, PhoneKind::Home),
+                phones(contact, PhoneKind::Other),
+                contact.address.clone(),
+                contact.birthday.clone(),
+                contact.note.clone(),
+            ]
+        })
+        .collect();
+    write_csv(CONTACT_COLUMNS, &rows)
+}
+
+fn split_values(value: &str) -> Vec<String> {
+    value.split(';').map(str::trim).filter(|value| !value.is_empty()).map(String::from).collect()
+}
+
+/// Reads contacts from a CSV with a header row, including Google and Outlook exports
+pub fn csv_to_contacts(text: &str) -> Result<Vec<Contact>, String> {
+    let rows = parse_csv(text);
+    let (header, rows) = rows.split_first().ok_or("The CSV file is empty")?;
+    let full_name = find_column(header, &["fullname", "name", "displayname"]);
+    let first_name = find_column(header, &["firstname", "givenname"]);
+    let last_name = find_column(header, &["lastname", "familyname", "surname"]);
+    let organization = find_column(header, &["organization", "organisation", "company", "organization1name"]);
+    let title = find_column(header, &["title", "jobtitle", "organization1title"]);
+    let address = find_column(header, &["address", "homeaddress", "businessaddress", "address1formatted", "street"]);
+    let birthday = find_column(header, &["birthday", "bday", "dateofbirth"]);
+    let note = find_column(header, &["notes", "note"]);
+    // Any number of email and phone columns ("E-mail 2 Address", "Phone 1 - Value")
+    let emails: Vec<usize> = (0..header.len())
+        .filter(|column| {
+            let key = header_key(&header[*column]);
+            key.starts_with("email") && !key.contains("type") && !key.contains("display")
+        })
+        .collect();
+    let phones: Vec<(usize, PhoneKind)> = (0..header.len())
+        .filter_map(|column| {
+            let key = header_key(&header[*column]);
+            let kind = match key.as_str() {
+                "mobilephone" | "mobile" | "cellphone" | "cell" => PhoneKind::Mobile,
+                "workphone" | "businessphone" | "businessphone2" | "companymainphone" => PhoneKind::Work,
+                "homephone" | "homephone2" => PhoneKind::Home,
+                "phone" | "otherphone" | "primaryphone" | "telephone" => PhoneKind::Other,
+                key if key.starts_with("phone") && key.ends_with("value") => PhoneKind::Other,
+                _ => return None,
+            };
+            Some((column, kind))
+        })
+        .collect();
+    if full_name.is_none() && first_name.is_none() && last_name.is_none() && emails.is_empty() {
+        return Err("The CSV needs a Name, First Name, Last Name or Email column to become contacts".to_string());
+    }
+
+    let contacts: Vec<Contact> = rows
+        .iter()
+        .map(|row| {
+            let mut contact = Contact {
+                full_name: cell(row, full_name),
+                first_name: cell(row, first_name),
+                last_name: cell(row, last_name),
+                organization: cell(row, organization),
+                title: cell(row, title),
+                emails: emails.iter().flat_map(|column| split_values(&cell(row, Some(*column)))).collect(),
+                phones: phones
+                    .iter()
+                    .flat_map(|(column, kind)| split_values(&cell(row, Some(*column))).into_iter().map(move |number| (*kind, number)))
+                    .collect(),
+                address: cell(row, address),
+                birthday: normalize_birthday(&cell(row, birthday)),
+                note: cell(row, note),
+            };
+            if contact.full_name.is_empty() {
+                let parts = [contact.first_name.as_str(), contact.last_name.as_str()];
+                contact.full_name = parts.iter().filter(|part| !part.is_empty()).cloned().collect::<Vec<_>>().join(" ");
+            }
+            contact
+        })
+        .collect();
+    Ok(contacts)
+}
+
+/// Writes vCard 3.0, which every address book imports
+pub fn contacts_to_vcf(contacts: &[Contact]) -> String {
+    let mut vcf = String::new();
+    for contact in contacts {
+        // FN is required; fall back to whatever identifies the contact
+        let full_name = [&contact.full_name, &contact.organization]
+            .into_iter()
+            .chain(contact.emails.first())
+            .find(|name| !name.is_empty())
+            .cloned()
+            .unwrap_or_default();
+        vcf.push_str("BEGIN:VCARD\r\nVERSION:3.0\r\n");
+        vcf.push_str(&fold(&format!("FN:{}", escape_value(&full_name))));
+        vcf.push_str(&fold(&format!("N:{};{};;;", escape_value(&contact.last_name), escape_value(&contact.first_name))));
+        if !contact.organization.is_empty() {
+            vcf.push_str(&fold(&format!("ORG:{}", escape_value(&contact.organization))));
+        }
+        if !contact.title.is_empty() {
+            vcf.push_str(&fold(&format!("TITLE:{}", escape_value(&contact.title))));
+        }
+        for email in &contact.emails {
+            vcf.push_str(&fold(&format!("EMAIL;TYPE=INTERNET:{}", escape_value(email))));
+        }
+        for (kind, number) in &contact.phones {
+            vcf.push_str(&fold(&format!("TEL;TYPE={}:{}", kind.vcard_type(), escape_value(number))));
+        }
+        if !contact.address.is_empty() {
+            // The whole address goes in the street component
+            vcf.push_str(&fold(&format!("ADR:;;{};;;;", escape_value(&contact.address))));
+        }
+        if !contact.birthday.is_empty() {
+            vcf.push_str(&fold(&format!("BDAY:{}", contact.birthday)));
+        }
+        if !contact.note.is_empty() {
+            vcf.push_str(&fold(&format!("NOTE:{}", escape_value(&contact.note))));
+        }
+        vcf.push_str("END:VCARD\r\n");
+    }
+    vcf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:abc@example.com\r\n\
+        DTSTART;TZID=Europe/Berlin:20240315T093000\r\n\
+        DTEND;TZID=Europe/Berlin:20240315T103000\r\n\
+        SUMMARY:Planning\\, Q2\r\n\
+        DESCRIPTION:Agenda:\\n1. Budget\r\n\
+        LOCATION:Room 4\r\n\
+        BEGIN:VALARM\r\n\
+        DESCRIPTION:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;VALUE=DATE:20240401\r\n\
+        DTEND;VALUE=DATE:20240403\r\n\
+        SUMMARY:Offsite with a very long title that has to be folded across more than one\r\n  content line\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART:20240501T080000Z\r\n\
+        DURATION:PT1H30M\r\n\
+        SUMMARY:Call\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics() {
+        let events = parse_ics(CALENDAR).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].summary, "Planning, Q2");
+        assert_eq!(events[0].description, "Agenda:\n1. Budget");
+        assert_eq!(events[0].start.as_ref().unwrap().time_zone.as_deref(), Some("Europe/Berlin"));
+        // The alarm's description doesn't replace the event's
+        assert_eq!(events[0].location, "Room 4");
+        assert!(events[1].summary.ends_with("more than one content line"));
+        // All-day DTEND is exclusive; the CSV gets the last day
+        assert_eq!(events[1].end.as_ref().unwrap().date, NaiveDate::from_ymd_opt(2024, 4, 2).unwrap());
+        assert_eq!(events[2].end.as_ref().unwrap().time, NaiveTime::from_hms_opt(9, 30, 0));
+    }
+
+    #[test]
+    fn test_ics_to_csv() {
+        let csv = convert(CALENDAR, "ics", "csv", stamp()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), "Subject,Start Date,Start Time,End Date,End Time,All Day Event,Time Zone,Description,Location,UID");
+        assert_eq!(
+            lines.next().unwrap(),
+            "\"Planning, Q2\",2024-03-15,09:30,2024-03-15,10:30,False,Europe/Berlin,\"Agenda:"
+        );
+        assert!(csv.contains(",2024-04-01,,2024-04-02,,True,,,,"));
+        assert!(csv.contains("Call,2024-05-01,08:00,2024-05-01,09:30,False,UTC,,,"));
+    }
+
+    #[test]
+    fn test_csv_to_ics_round_trip() {
+        let csv = convert(CALENDAR, "ics", "csv", stamp()).unwrap();
+        let ics = convert(&csv, "csv", "ics", stamp()).unwrap();
+        assert!(ics.contains("DTSTART;TZID=Europe/Berlin:20240315T093000\r\n"));
+        assert!(ics.contains("SUMMARY:Planning\\, Q2\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240401\r\nDTEND;VALUE=DATE:20240403\r\n"));
+        assert!(ics.contains("DTSTART:20240501T080000Z\r\nDTEND:20240501T093000Z\r\n"));
+        assert!(ics.contains("DTSTAMP:20240101T120000Z\r\n"));
+        // Events without a UID get a generated one; everything else survives the trip
+        let without_uids = |events: Vec<CalendarEvent>| {
+            events.into_iter().map(|event| CalendarEvent { uid: String::new(), ..event }).collect::<Vec<_>>()
+        };
+        assert_eq!(without_uids(parse_ics(&ics).unwrap()), without_uids(parse_ics(CALENDAR).unwrap()));
+    }
+
+    #[test]
+    fn test_csv_to_events_accepts_other_layouts() {
+        let csv = "Title;Date;Time;Notes\n\"Dentist\";03/15/2024;2:30 PM;Bring forms\nHoliday;2024-12-25;;\n";
+        let events = csv_to_events(csv).unwrap();
+        assert_eq!(events[0].start.as_ref().unwrap().time, NaiveTime::from_hms_opt(14, 30, 0));
+        assert_eq!(events[0].description, "Bring forms");
+        assert!(events[1].start.as_ref().unwrap().time.is_none());
+        assert!(csv_to_events("Subject\nLunch\n").unwrap_err().contains("Start Date"));
+        assert!(csv_to_events("Subject,Start Date\nLunch,someday\n").unwrap_err().contains("Row 2"));
+    }
+
+    const CONTACTS: &str = "BEGIN:VCARD\r\n\
+        VERSION:3.0\r\n\
+        FN:Ada Lovelace\r\n\
+        N:Lovelace;Ada;;;\r\n\
+        ORG:Analytical Engines;Research\r\n\
+        item1.EMAIL;TYPE=INTERNET,pref:ada@example.com\r\n\
+        EMAIL;TYPE=INTERNET:ada@work.example.com\r\n\
+        TEL;TYPE=CELL:+44 20 7946 0000\r\n\
+        TEL;TYPE=WORK,VOICE:+44 20 7946 0001\r\n\
+        ADR;TYPE=HOME:;;12 St James's Square;London;;SW1Y 4JH;UK\r\n\
+        BDAY:1815-12-10\r\n\
+        NOTE:First programmer\\, arguably\r\n\
+        END:VCARD\r\n\
+        BEGIN:VCARD\r\n\
+        VERSION:2.1\r\n\
+        N;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:M=C3=BCller;J=C3=\r\n\
+        =BCrgen;;;\r\n\
+        TEL;CELL:0171 1234567\r\n\
+        END:VCARD\r\n";
+
+    #[test]
+    fn test_parse_vcf() {
+        let contacts = parse_vcf(CONTACTS).unwrap();
+        assert_eq!(contacts[0].organization, "Analytical Engines, Research");
+        assert_eq!(contacts[0].emails, vec!["ada@example.com", "ada@work.example.com"]);
+        assert_eq!(contacts[0].phones[0], (PhoneKind::Mobile, "+44 20 7946 0000".to_string()));
+        assert_eq!(contacts[0].phones[1].0, PhoneKind::Work);
+        assert_eq!(contacts[0].address, "12 St James's Square, London, SW1Y 4JH, UK");
+        assert_eq!(contacts[0].note, "First programmer, arguably");
+        // vCard 2.1 with quoted-printable, soft line breaks and bare TYPE words
+        assert_eq!(contacts[1].last_name, "Müller");
+        assert_eq!(contacts[1].full_name, "Jürgen Müller");
+        assert_eq!(contacts[1].phones[0].0, PhoneKind::Mobile);
+    }
+
+    #[test]
+    fn test_vcf_to_csv_and_back() {
+        let csv = convert(CONTACTS, "vcf", "csv", stamp()).unwrap();
+        assert!(csv.contains("Ada Lovelace,Ada,Lovelace,\"Analytical Engines, Research\",,ada@example.com; ada@work.example.com,+44 20 7946 0000,+44 20 7946 0001,,,"));
+        let vcf = convert(&csv, "csv", "vcf", stamp()).unwrap();
+        assert!(vcf.contains("N:Lovelace;Ada;;;\r\n"));
+        assert!(vcf.contains("EMAIL;TYPE=INTERNET:ada@work.example.com\r\n"));
+        assert!(vcf.contains("TEL;TYPE=CELL:+44 20 7946 0000\r\n"));
+        assert!(vcf.contains("BDAY:1815-12-10\r\n"));
+        let contacts = parse_vcf(&vcf).unwrap();
+        assert_eq!(contacts[0].emails.len(), 2);
+        assert_eq!(contacts[1].full_name, "Jürgen Müller");
+    }
+
+    #[test]
+    fn test_csv_to_contacts_outlook_headers() {
+        let csv = "First Name,Last Name,E-mail Address,E-mail 2 Address,Mobile Phone,Business Phone\nGrace,Hopper,grace@example.com,,555-0100,555-0101\n";
+        let contacts = csv_to_contacts(csv).unwrap();
+        assert_eq!(contacts[0].full_name, "Grace Hopper");
+        assert_eq!(contacts[0].emails, vec!["grace@example.com"]);
+        assert_eq!(contacts[0].phones, vec![(PhoneKind::Mobile, "555-0100".to_string()), (PhoneKind::Work, "555-0101".to_string())]);
+        assert!(csv_to_contacts("Company\nAcme\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let rows = parse_csv("a,b\r\n\"x, \"\"y\"\"\",\"line\nbreak\"\r\n\r\n");
+        assert_eq!(rows, vec![vec!["a", "b"], vec!["x, \"y\"", "line\nbreak"]]);
+    }
+
+    #[test]
+    fn test_fold() {
+        let folded = fold(&format!("SUMMARY:{}", "é".repeat(40)));
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(unfold(&folded), vec![format!("SUMMARY:{}", "é".repeat(40))]);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1W2D"), Some(Duration::days(9)));
+        assert_eq!(parse_duration("-PT15M"), Some(Duration::minutes(-15)));
+        assert_eq!(parse_duration("1H"), None);
+        // Too long for chrono: rejected, not a panic
+        assert_eq!(parse_duration("P9223372036854775807W"), None);
+        assert_eq!(parse_duration("P999999999999999D"), None);
+        assert_eq!(parse_duration("P15250284452471WT2000000000000H"), None);
+
+        let start = EventTime { date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(), time: None, time_zone: None };
+        assert_eq!(add_duration(&start, Duration::days(1)).unwrap().date, NaiveDate::from_ymd_opt(2026, 1, 2).unwrap());
+        assert_eq!(add_duration(&start, Duration::days(1_000_000_000)), None);
+    }
+}
@@ -0,0 +1,107 @@
+//! Power management - Keeping the machine awake for conversions, and what to do after
+//!
+//! While conversions are running or queued, a helper process holds a sleep inhibitor
+//! (`caffeinate` on macOS, `systemd-inhibit` on Linux, SetThreadExecutionState from
+//! PowerShell on Windows); stopping it lets the machine sleep again. For overnight batches
+//! the machine can also be put to sleep or shut down once the queue has been empty for a
+//! little while, so a batch moving to its next file doesn't count as done.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long the queue has to stay empty before it counts as finished
+pub const IDLE_GRACE: Duration = Duration::from_secs(30);
+
+/// Seconds Windows waits before shutting down, so it can still be cancelled (`shutdown /a`)
+pub const SHUTDOWN_DELAY_SECS: u32 = 60;
+
+/// What to do once every conversion has finished
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterQueue {
+    #[default]
+    Nothing,
+    Sleep,
+    Shutdown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PowerSettings {
+    /// Keep the machine awake while conversions are queued
+    pub prevent_sleep: bool,
+    /// For this session only: a shutdown isn't something to remember across restarts
+    pub after_queue: AfterQueue,
+}
+
+/// Program and arguments holding a sleep inhibitor until they're stopped or `app_pid` exits
+pub fn inhibitor_command(os: &str, app_pid: u32) -> Option<(String, Vec<String>)> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    match os {
+        // -i: no idle sleep, -w: until the app exits
+        "macos" => Some(("caffeinate".to_string(), args(&["-i", "-w", &app_pid.to_string()]))),
+        "linux" => {
+            let wait = format!("while kill -0 {} 2>/dev/null; do sleep 30; done", app_pid);
+            Some((
+                "systemd-inhibit".to_string(),
+                args(&["--what=sleep:idle", "--who=ConvertSave", "--why=Converting files", "--mode=block", "sh", "-c", &wait]),
+            ))
+        }
+        "windows" => {
+            // ES_CONTINUOUS | ES_SYSTEM_REQUIRED, held by this PowerShell process
+            let script = format!(
+                "$t = Add-Type -MemberDefinition '[DllImport(\"kernel32.dll\")] public static extern uint SetThreadExecutionState(uint f);' -Name Power -Namespace ConvertSave -PassThru; \
+                 [void]$t::SetThreadExecutionState(0x80000001); \
+                 while (Get-Process -Id {} -ErrorAction SilentlyContinue) {{ Start-Sleep -Seconds 30 }}",
+                app_pid
+            );
+            Some(("powershell".to_string(), args(&["-NoProfile", "-NonInteractive", "-Command", &script])))
+        }
+        _ => None,
+    }
+}
+
+/// Program and arguments that put the machine to sleep or shut it down
+pub fn after_queue_command(os: &str, action: AfterQueue) -> Option<(String, Vec<String>)> {
+    let command = |program: &str, args: &[&str]| Some((program.to_string(), args.iter().map(|arg| arg.to_string()).collect()));
+    match (os, action) {
+        (_, AfterQueue::Nothing) => None,
+        ("macos", AfterQueue::Sleep) => command("pmset", &["sleepnow"]),
+        ("macos", AfterQueue::Shutdown) => command("osascript", &["-e", "tell application \"System Events\" to shut down"]),
+        ("linux", AfterQueue::Sleep) => command("systemctl", &["suspend"]),
+        ("linux", AfterQueue::Shutdown) => command("systemctl", &["poweroff"]),
+        ("windows", AfterQueue::Sleep) => command("rundll32.exe", &["powrprof.dll,SetSuspendState", "0,1,0"]),
+        ("windows", AfterQueue::Shutdown) => {
+            let delay = SHUTDOWN_DELAY_SECS.to_string();
+            command("shutdown", &["/s", "/t", &delay, "/c", "ConvertSave finished converting"])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inhibitor_command() {
+        let (program, args) = inhibitor_command("macos", 4242).unwrap();
+        assert_eq!((program.as_str(), args), ("caffeinate", vec!["-i".to_string(), "-w".to_string(), "4242".to_string()]));
+
+        let (program, args) = inhibitor_command("linux", 4242).unwrap();
+        assert_eq!(program, "systemd-inhibit");
+        assert_eq!(args.last().unwrap(), "while kill -0 4242 2>/dev/null; do sleep 30; done");
+
+        let (_, args) = inhibitor_command("windows", 4242).unwrap();
+        assert!(args.last().unwrap().contains("Get-Process -Id 4242"));
+        assert_eq!(inhibitor_command("freebsd", 1), None);
+    }
+
+    #[test]
+    fn test_after_queue_command() {
+        assert_eq!(after_queue_command("linux", AfterQueue::Nothing), None);
+        assert_eq!(after_queue_command("linux", AfterQueue::Sleep), Some(("systemctl".to_string(), vec!["suspend".to_string()])));
+        let (program, args) = after_queue_command("windows", AfterQueue::Shutdown).unwrap();
+        assert_eq!(program, "shutdown");
+        assert_eq!(args[..3], ["/s", "/t", "60"]);
+    }
+}
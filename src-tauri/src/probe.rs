@@ -0,0 +1,715 @@
+//! Media inspection - Turns ffprobe JSON (or the `ffmpeg -i` banner when ffprobe isn't installed)
+//! into structured stream information for the frontend.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Details of the first video stream
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct VideoStreamInfo {
+    pub codec: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub pixel_format: Option<String>,
+    pub bit_depth: Option<u32>,
+}
+
+/// Details of the first audio stream
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AudioStreamInfo {
+    pub codec: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub bit_depth: Option<u32>,
+}
+
+/// One subtitle track
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SubtitleStreamInfo {
+    /// Position among the file's subtitle tracks, as used by `-map 0:s:<index>`
+    pub index: u32,
+    pub codec: String,
+    /// ISO 639 language tag such as "eng", when the file has one
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+/// What a stream carries
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    /// Timecode tracks, fonts and other attachments
+    #[default]
+    Other,
+}
+
+impl StreamKind {
+    fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "video" => StreamKind::Video,
+            "audio" => StreamKind::Audio,
+            "subtitle" => StreamKind::Subtitle,
+            _ => StreamKind::Other,
+        }
+    }
+}
+
+/// One stream of a file, for picking which ones a conversion keeps
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct StreamInfo {
+    /// Position in the file, as used by `-map 0:<index>`
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub channels: Option<u32>,
+    pub default: bool,
+    /// Cover art stored as a one-frame video stream
+    pub attached_picture: bool,
+}
+
+/// A chapter marker of an audiobook, podcast or film
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Chapter {
+    pub start_secs: f64,
+    /// None when the end isn't known (the last track of a cue sheet runs to the end)
+    pub end_secs: Option<f64>,
+    pub title: Option<String>,
+}
+
+/// Everything we know about a media file
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    pub container: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub bitrate_kbps: Option<u64>,
+    pub video: Option<VideoStreamInfo>,
+    pub audio: Option<AudioStreamInfo>,
+    pub subtitles: Vec<SubtitleStreamInfo>,
+    /// Every stream in file order, including the ones summarized above
+    pub streams: Vec<StreamInfo>,
+    /// Container-level tags (title, artist, creation_time, ...)
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+/// Bit depth implied by an FFmpeg pixel format, e.g. "yuv420p10le" -> 10
+pub fn bit_depth_from_pix_fmt(pix_fmt: &str) -> Option<u32> {
+    // Packed RGB formats give the total bits per pixel (rgb48 = 3 x 16, rgba64 = 4 x 16)
+    if pix_fmt.contains("48") || pix_fmt.contains("64") {
+        return Some(16);
+    }
+    for depth in [16, 14, 12, 10, 9] {
+        if pix_fmt.contains(&format!("p{}", depth)) || pix_fmt.ends_with(&format!("{}le", depth)) || pix_fmt.ends_with(&format!("{}be", depth)) {
+            return Some(depth);
+        }
+    }
+    if pix_fmt.starts_with("yuv") || pix_fmt.starts_with("nv") || pix_fmt.starts_with("rgb") || pix_fmt.starts_with("bgr") || pix_fmt.starts_with("gray") {
+        return Some(8);
+    }
+    None
+}
+
+/// Bit depth implied by an FFmpeg sample format; float formats have none
+pub fn bit_depth_from_sample_fmt(sample_fmt: &str) -> Option<u32> {
+    match sample_fmt.trim_end_matches('p') {
+        "u8" => Some(8),
+        "s16" => Some(16),
+        "s24" => Some(24),
+        "s32" => Some(32),
+        "s64" => Some(64),
+        _ => None,
+    }
+}
+
+/// Channel count for an FFmpeg channel layout name
+pub fn channels_from_layout(layout: &str) -> Option<u32> {
+    let base = layout.split('(').next().unwrap_or(layout).trim();
+    match base {
+        "mono" => Some(1),
+        "stereo" | "2.0" => Some(2),
+        "2.1" | "3.0" => Some(3),
+        "quad" | "4.0" | "3.1" => Some(4),
+        "4.1" | "5.0" => Some(5),
+        "5.1" | "6.0" => Some(6),
+        "6.1" | "7.0" => Some(7),
+        "7.1" => Some(8),
+        _ => base.strip_suffix(" channels").and_then(|n| n.trim().parse().ok()),
+    }
+}
+
+/// Parses a rational frame rate like "30000/1001" into frames per second
+pub fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let fps = match rate.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => rate.trim().parse().ok()?,
+    };
+    if fps <= 0.0 {
+        return None;
+    }
+    Some((fps * 1000.0).round() / 1000.0)
+}
+
+/// Reads a JSON field that ffprobe may emit as either a string or a number
+fn json_u64(value: &serde_json::Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+fn json_f64(value: &serde_json::Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Parses the output of `ffprobe -print_format json -show_format -show_streams -show_chapters`
+pub fn parse_ffprobe_json(json: &serde_json::Value) -> MediaInfo {
+    let format = &json["format"];
+    let mut info = MediaInfo {
+        container: format["format_name"].as_str().map(String::from),
+        duration_secs: json_f64(&format["duration"]),
+        bitrate_kbps: json_u64(&format["bit_rate"]).map(|bps| bps / 1000),
+        ..Default::default()
+    };
+
+    if let Some(tags) = format["tags"].as_object() {
+        for (key, value) in tags {
+            if let Some(value) = value.as_str() {
+                info.metadata.insert(key.to_lowercase(), value.to_string());
+            }
+        }
+    }
+
+    if let Some(streams) = json["streams"].as_array() {
+        for stream in streams {
+            let codec = stream["codec_name"].as_str().unwrap_or("unknown").to_string();
+            let tag = |key: &str| stream["tags"][key].as_str().filter(|v| !v.is_empty()).map(String::from);
+            info.streams.push(StreamInfo {
+                index: json_u64(&stream["index"]).map_or(info.streams.len() as u32, |i| i as u32),
+                kind: StreamKind::from_name(stream["codec_type"].as_str().unwrap_or("")),
+                codec: codec.clone(),
+                language: tag("language").filter(|language| language != "und"),
+                title: tag("title"),
+                channels: json_u64(&stream["channels"]).map(|c| c as u32),
+                default: stream["disposition"]["default"].as_u64() == Some(1),
+                attached_picture: stream["disposition"]["attached_pic"].as_u64() == Some(1),
+            });
+
+            match stream["codec_type"].as_str() {
+                Some("video") if info.video.is_none() => {
+                    // Cover art shows up as a single-frame video stream; skip it
+                    if stream["disposition"]["attached_pic"].as_u64() == Some(1) {
+                        continue;
+                    }
+                    let pixel_format = stream["pix_fmt"].as_str().map(String::from);
+                    let bit_depth = json_u64(&stream["bits_per_raw_sample"])
+                        .map(|d| d as u32)
+                        .or_else(|| pixel_format.as_deref().and_then(bit_depth_from_pix_fmt));
+                    let frame_rate = stream["avg_frame_rate"].as_str()
+                        .and_then(parse_frame_rate)
+                        .or_else(|| stream["r_frame_rate"].as_str().and_then(parse_frame_rate));
+                    info.video = Some(VideoStreamInfo {
+                        codec,
+                        width: json_u64(&stream["width"]).map(|w| w as u32),
+                        height: json_u64(&stream["height"]).map(|h| h as u32),
+                        frame_rate,
+                        pixel_format,
+                        bit_depth,
+                    });
+                }
+                Some("audio") if info.audio.is_none() => {
+                    let channel_layout = stream["channel_layout"].as_str().map(String::from);
+                    let bit_depth = json_u64(&stream["bits_per_raw_sample"])
+                        .filter(|d| *d > 0)
+                        .map(|d| d as u32)
+                        .or_else(|| stream["sample_fmt"].as_str().and_then(bit_depth_from_sample_fmt));
+                    info.audio = Some(AudioStreamInfo {
+                        codec,
+                        sample_rate: json_u64(&stream["sample_rate"]).map(|r| r as u32),
+                        channels: json_u64(&stream["channels"]).map(|c| c as u32),
+                        channel_layout,
+                        bit_depth,
+                    });
+                }
+                Some("subtitle") => {
+                    info.subtitles.push(SubtitleStreamInfo {
+                        index: info.subtitles.len() as u32,
+                        codec,
+                        language: tag("language").filter(|language| language != "und"),
+                        title: tag("title"),
+                        default: stream["disposition"]["default"].as_u64() == Some(1),
+                        forced: stream["disposition"]["forced"].as_u64() == Some(1),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(chapters) = json["chapters"].as_array() {
+        info.chapters = chapters
+            .iter()
+            .filter_map(|chapter| {
+                Some(Chapter {
+                    start_secs: json_f64(&chapter["start_time"])?,
+                    end_secs: json_f64(&chapter["end_time"]),
+                    title: chapter["tags"]["title"].as_str().filter(|t| !t.trim().is_empty()).map(|t| t.trim().to_string()),
+                })
+            })
+            .collect();
+    }
+
+    info
+}
+
+/// Splits a stream description on commas that aren't inside parentheses or brackets
+fn split_top_level(description: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+
+    for c in description.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+
+    parts
+}
+
+fn parse_video_description(description: &str) -> VideoStreamInfo {
+    let parts = split_top_level(description);
+    let mut video = VideoStreamInfo {
+        codec: parts.first()
+            .and_then(|p| p.split_whitespace().next())
+            .unwrap_or("unknown")
+            .to_string(),
+        ..Default::default()
+    };
+
+    if let Some(pix_fmt) = parts.get(1) {
+        let pix_fmt = pix_fmt.split('(').next().unwrap_or("").trim();
+        if !pix_fmt.is_empty() {
+            video.bit_depth = bit_depth_from_pix_fmt(pix_fmt);
+            video.pixel_format = Some(pix_fmt.to_string());
+        }
+    }
+
+    for part in &parts {
+        let first = part.split_whitespace().next().unwrap_or("");
+        if let Some((w, h)) = first.split_once('x') {
+            if let (Ok(w), Ok(h)) = (w.parse(), h.parse()) {
+                video.width = Some(w);
+                video.height = Some(h);
+            }
+        }
+        if let Some(fps) = part.strip_suffix(" fps") {
+            video.frame_rate = parse_frame_rate(fps);
+        }
+    }
+
+    video
+}
+
+fn parse_audio_description(description: &str) -> AudioStreamInfo {
+    let parts = split_top_level(description);
+    let mut audio = AudioStreamInfo {
+        codec: parts.first()
+            .and_then(|p| p.split_whitespace().next())
+            .unwrap_or("unknown")
+            .to_string(),
+        ..Default::default()
+    };
+
+    if let Some(rate) = parts.get(1).and_then(|p| p.strip_suffix(" Hz")) {
+        audio.sample_rate = rate.trim().parse().ok();
+    }
+    if let Some(layout) = parts.get(2) {
+        audio.channels = channels_from_layout(layout);
+        audio.channel_layout = Some(layout.clone());
+    }
+    if let Some(sample_fmt) = parts.get(3) {
+        // e.g. "s32 (24 bit)" - the explicit bit count wins
+        audio.bit_depth = sample_fmt.split_once('(')
+            .and_then(|(_, bits)| bits.trim_end_matches(')').trim().strip_suffix(" bit"))
+            .and_then(|bits| bits.trim().parse().ok())
+            .or_else(|| bit_depth_from_sample_fmt(sample_fmt.split_whitespace().next().unwrap_or("")));
+    }
+
+    audio
+}
+
+/// Parses a banner stream line such as
+/// `Stream #0:1[0x2](eng): Audio: aac (LC), 48000 Hz, stereo, fltp (default)`
+fn parse_banner_stream(line: &str, fallback_index: u32) -> Option<StreamInfo> {
+    let (id, rest) = line.strip_prefix("Stream #")?.split_once(": ")?;
+    let (kind, description) = rest.split_once(": ")?;
+    let index = id
+        .split(':')
+        .nth(1)
+        .map(|index| index.chars().take_while(|c| c.is_ascii_digit()).collect::<String>())
+        .and_then(|index| index.parse().ok())
+        .unwrap_or(fallback_index);
+    let language = id
+        .rsplit_once('(')
+        .map(|(_, language)| language.trim_end_matches(')').to_string())
+        .filter(|language| language != "und");
+    let kind = StreamKind::from_name(kind);
+    let parts = split_top_level(description);
+
+    Some(StreamInfo {
+        index,
+        kind,
+        codec: parts.first()
+            .and_then(|p| p.split_whitespace().next())
+            .unwrap_or("unknown")
+            .to_string(),
+        language,
+        title: None,
+        channels: if kind == StreamKind::Audio { parts.get(2).and_then(|layout| channels_from_layout(layout)) } else { None },
+        default: description.contains("(default)"),
+        attached_picture: description.contains("(attached pic)"),
+    })
+}
+
+/// Parses the stream banner FFmpeg prints to stderr for `ffmpeg -i <file>`
+pub fn parse_ffmpeg_banner(stderr: &str) -> MediaInfo {
+    let mut info = MediaInfo::default();
+    let mut in_container_metadata = false;
+    // Chapter metadata follows its "Chapter #0:n" line, indented further
+    let mut in_chapter = false;
+
+    for line in stderr.lines() {
+        let trimmed = line.trim();
+
+        if let Some(rest) = trimmed.strip_prefix("Chapter #") {
+            // "Chapter #0:1: start 1806.000000, end 3600.500000"
+            let time = |name: &str| {
+                rest.split(", ")
+                    .find_map(|part| part.split_once(name).map(|(_, value)| value.trim()))
+                    .and_then(|value| value.parse::<f64>().ok())
+            };
+            if let Some(start_secs) = time("start ") {
+                info.chapters.push(Chapter { start_secs, end_secs: time("end "), title: None });
+                in_chapter = true;
+            }
+            continue;
+        }
+        if in_chapter {
+            if line.starts_with("      ") {
+                if let Some(("title", value)) = trimmed.split_once(':').map(|(key, value)| (key.trim(), value.trim())) {
+                    if let Some(chapter) = info.chapters.last_mut() {
+                        chapter.title = Some(value.to_string()).filter(|title| !title.is_empty());
+                    }
+                }
+                continue;
+            }
+            in_chapter = false;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("Input #0, ") {
+            // "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'in.mp4':"
+            info.container = rest.split(", from").next().map(|c| c.trim().to_string());
+            continue;
+        }
+
+        // The container's "Metadata:" block is indented by two spaces, stream blocks by four
+        if line.starts_with("  Metadata:") {
+            in_container_metadata = true;
+            continue;
+        }
+        if in_container_metadata {
+            if line.starts_with("    ") {
+                if let Some((key, value)) = trimmed.split_once(':') {
+                    info.metadata.insert(key.trim().to_lowercase(), value.trim().to_string());
+                }
+                continue;
+            }
+            in_container_metadata = false;
+        }
+
+        if trimmed.starts_with("Duration:") {
+            for part in trimmed.split(", ") {
+                if let Some(duration) = part.strip_prefix("Duration: ") {
+                    info.duration_secs = crate::conversion::parse_ffmpeg_duration(&format!("Duration: {},", duration));
+                } else if let Some(bitrate) = part.strip_prefix("bitrate: ") {
+                    info.bitrate_kbps = bitrate.trim_end_matches(" kb/s").parse().ok();
+                }
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("Stream #") {
+            if let Some(stream) = parse_banner_stream(trimmed, info.streams.len() as u32) {
+                info.streams.push(stream);
+            }
+            if let Some((_, description)) = trimmed.split_once("Video: ") {
+                if info.video.is_none() && !description.contains("(attached pic)") {
+                    info.video = Some(parse_video_description(description));
+                }
+            } else if let Some((_, description)) = trimmed.split_once("Audio: ") {
+                if info.audio.is_none() {
+                    info.audio = Some(parse_audio_description(description));
+                }
+            } else if let Some((stream, description)) = trimmed.split_once(": Subtitle: ") {
+                // "Stream #0:2(eng): Subtitle: subrip (default) (forced)"
+                let language = stream.rsplit_once('(')
+                    .map(|(_, language)| language.trim_end_matches(')').to_string())
+                    .filter(|language| language != "und");
+                info.subtitles.push(SubtitleStreamInfo {
+                    index: info.subtitles.len() as u32,
+                    codec: description.split([',', ' ']).next().filter(|c| !c.is_empty()).unwrap_or("unknown").to_string(),
+                    language,
+                    title: None,
+                    default: description.contains("(default)"),
+                    forced: description.contains("(forced)"),
+                });
+            }
+        }
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANNER: &str = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'clip.mp4':
+  Metadata:
+    major_brand     : isom
+    title           : Holiday
+    creation_time   : 2024-06-01T10:00:00.000000Z
+  Duration: 00:00:10.50, start: 0.000000, bitrate: 4128 kb/s
+  Stream #0:0[0x1](und): Video: hevc (Main 10) (hvc1 / 0x31637668), yuv420p10le(tv, bt2020nc/bt2020/arib-std-b67), 3840x2160, 3990 kb/s, 29.97 fps, 29.97 tbr, 600 tbn (default)
+    Metadata:
+      handler_name    : Core Media Video
+  Stream #0:1[0x2](und): Audio: aac (LC) (mp4a / 0x6134706D), 48000 Hz, stereo, fltp, 128 kb/s (default)
+";
+
+    #[test]
+    fn test_parse_banner_container_and_duration() {
+        let info = parse_ffmpeg_banner(BANNER);
+        assert_eq!(info.container.as_deref(), Some("mov,mp4,m4a,3gp,3g2,mj2"));
+        assert_eq!(info.duration_secs, Some(10.5));
+        assert_eq!(info.bitrate_kbps, Some(4128));
+        assert_eq!(info.metadata.get("title").map(String::as_str), Some("Holiday"));
+        // Stream-level metadata isn't mixed into the container tags
+        assert!(!info.metadata.contains_key("handler_name"));
+    }
+
+    #[test]
+    fn test_parse_banner_streams() {
+        let info = parse_ffmpeg_banner(BANNER);
+        let video = info.video.unwrap();
+        assert_eq!(video.codec, "hevc");
+        assert_eq!((video.width, video.height), (Some(3840), Some(2160)));
+        assert_eq!(video.frame_rate, Some(29.97));
+        assert_eq!(video.pixel_format.as_deref(), Some("yuv420p10le"));
+        assert_eq!(video.bit_depth, Some(10));
+
+        let audio = info.audio.unwrap();
+        assert_eq!(audio.codec, "aac");
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.bit_depth, None);
+    }
+
+    #[test]
+    fn test_parse_banner_audio_only_with_cover_art() {
+        let banner = "Input #0, flac, from 'song.flac':
+  Duration: 00:03:00.00, start: 0.000000, bitrate: 900 kb/s
+  Stream #0:0: Audio: flac, 96000 Hz, 5.1(side), s32 (24 bit)
+  Stream #0:1: Video: mjpeg (Baseline), yuvj420p(pc, bt470bg/unknown/unknown), 600x600, 90k tbr, 90k tbn (attached pic)
+";
+        let info = parse_ffmpeg_banner(banner);
+        assert_eq!(info.video, None);
+        let audio = info.audio.unwrap();
+        assert_eq!(audio.channels, Some(6));
+        assert_eq!(audio.bit_depth, Some(24));
+        assert_eq!(audio.sample_rate, Some(96000));
+    }
+
+    #[test]
+    fn test_parse_banner_stream_list() {
+        let banner = "Input #0, matroska,webm, from 'film.mkv':
+  Stream #0:0: Video: h264 (High), yuv420p(progressive), 1920x1080, 23.98 fps (default)
+  Stream #0:1(eng): Audio: ac3, 48000 Hz, 5.1(side), fltp, 448 kb/s (default)
+  Stream #0:2(fre): Audio: aac (LC), 48000 Hz, stereo, fltp
+  Stream #0:3(eng): Subtitle: subrip
+";
+        let streams = parse_ffmpeg_banner(banner).streams;
+        assert_eq!(streams.len(), 4);
+        assert_eq!((streams[0].kind, streams[0].default), (StreamKind::Video, true));
+        assert_eq!(streams[1].channels, Some(6));
+        assert_eq!(streams[2].index, 2);
+        assert_eq!(streams[2].language.as_deref(), Some("fre"));
+        assert_eq!(streams[2].channels, Some(2));
+        assert!(!streams[2].default);
+        assert_eq!((streams[3].kind, streams[3].codec.as_str()), (StreamKind::Subtitle, "subrip"));
+    }
+
+    #[test]
+    fn test_parse_banner_subtitles() {
+        let banner = "Input #0, matroska,webm, from 'film.mkv':
+  Duration: 01:40:00.00, start: 0.000000, bitrate: 5000 kb/s
+  Stream #0:0: Video: h264 (High), yuv420p(progressive), 1920x1080, 23.98 fps
+  Stream #0:1(eng): Subtitle: subrip (default)
+  Stream #0:2(und): Subtitle: hdmv_pgs_subtitle, 1920x1080 (forced)
+";
+        let info = parse_ffmpeg_banner(banner);
+        assert_eq!(info.subtitles.len(), 2);
+        assert_eq!(info.subtitles[0].codec, "subrip");
+        assert_eq!(info.subtitles[0].language.as_deref(), Some("eng"));
+        assert!(info.subtitles[0].default);
+        assert_eq!(info.subtitles[1].index, 1);
+        assert_eq!(info.subtitles[1].codec, "hdmv_pgs_subtitle");
+        assert_eq!(info.subtitles[1].language, None);
+        assert!(info.subtitles[1].forced);
+    }
+
+    #[test]
+    fn test_parse_ffprobe_subtitles() {
+        let json = serde_json::json!({
+            "format": {},
+            "streams": [
+                { "codec_type": "subtitle", "codec_name": "ass", "tags": { "language": "jpn", "title": "Signs" },
+                  "disposition": { "default": 0, "forced": 1 } },
+                { "codec_type": "subtitle", "codec_name": "mov_text", "tags": { "language": "und" } }
+            ]
+        });
+        let info = parse_ffprobe_json(&json);
+        assert_eq!(info.subtitles[0], SubtitleStreamInfo {
+            index: 0,
+            codec: "ass".to_string(),
+            language: Some("jpn".to_string()),
+            title: Some("Signs".to_string()),
+            default: false,
+            forced: true,
+        });
+        assert_eq!(info.subtitles[1].index, 1);
+        assert_eq!(info.subtitles[1].language, None);
+    }
+
+    #[test]
+    fn test_parse_chapters() {
+        let banner = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'book.m4b':
+  Duration: 01:00:00.50, start: 0.000000, bitrate: 64 kb/s
+  Chapters:
+    Chapter #0:0: start 0.000000, end 1806.000000
+      Metadata:
+        title           : Opening Credits
+    Chapter #0:1: start 1806.000000, end 3600.500000
+      Metadata:
+        title           : Chapter 1: The Storm
+  Stream #0:0[0x1](eng): Audio: aac (LC) (mp4a / 0x6134706D), 44100 Hz, mono, fltp, 62 kb/s (default)
+";
+        let info = parse_ffmpeg_banner(banner);
+        assert_eq!(info.chapters, vec![
+            Chapter { start_secs: 0.0, end_secs: Some(1806.0), title: Some("Opening Credits".to_string()) },
+            Chapter { start_secs: 1806.0, end_secs: Some(3600.5), title: Some("Chapter 1: The Storm".to_string()) },
+        ]);
+        assert_eq!(info.audio.unwrap().channels, Some(1));
+
+        let json = serde_json::json!({
+            "format": {},
+            "streams": [],
+            "chapters": [
+                { "id": 0, "start_time": "0.000000", "end_time": "1806.000000", "tags": { "title": "Opening Credits" } },
+                { "id": 1, "start_time": "1806.000000", "end_time": "3600.500000", "tags": {} }
+            ]
+        });
+        let chapters = parse_ffprobe_json(&json).chapters;
+        assert_eq!(chapters[1], Chapter { start_secs: 1806.0, end_secs: Some(3600.5), title: None });
+    }
+
+    #[test]
+    fn test_parse_ffprobe_json() {
+        let json = serde_json::json!({
+            "format": {
+                "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+                "duration": "12.345000",
+                "bit_rate": "2500000",
+                "tags": { "TITLE": "Demo" }
+            },
+            "streams": [
+                { "codec_type": "video", "codec_name": "mjpeg", "disposition": { "attached_pic": 1 } },
+                {
+                    "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+                    "avg_frame_rate": "30000/1001", "pix_fmt": "yuv420p", "bits_per_raw_sample": "8"
+                },
+                {
+                    "codec_type": "audio", "codec_name": "pcm_s24le", "sample_rate": "48000",
+                    "channels": 2, "channel_layout": "stereo", "sample_fmt": "s32", "bits_per_raw_sample": "24"
+                }
+            ]
+        });
+
+        let info = parse_ffprobe_json(&json);
+        assert_eq!(info.streams.len(), 3);
+        assert!(info.streams[0].attached_picture);
+        assert_eq!((info.streams[2].index, info.streams[2].kind), (2, StreamKind::Audio));
+        assert_eq!(info.duration_secs, Some(12.345));
+        assert_eq!(info.bitrate_kbps, Some(2500));
+        assert_eq!(info.metadata.get("title").map(String::as_str), Some("Demo"));
+
+        let video = info.video.unwrap();
+        assert_eq!(video.codec, "h264");
+        assert_eq!(video.frame_rate, Some(29.97));
+        assert_eq!(video.bit_depth, Some(8));
+
+        let audio = info.audio.unwrap();
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.bit_depth, Some(24));
+    }
+
+    #[test]
+    fn test_bit_depth_helpers() {
+        assert_eq!(bit_depth_from_pix_fmt("yuv420p"), Some(8));
+        assert_eq!(bit_depth_from_pix_fmt("yuv422p10le"), Some(10));
+        assert_eq!(bit_depth_from_pix_fmt("rgb48be"), Some(16));
+        assert_eq!(bit_depth_from_sample_fmt("s16p"), Some(16));
+        assert_eq!(bit_depth_from_sample_fmt("fltp"), None);
+    }
+
+    #[test]
+    fn test_parse_frame_rate() {
+        assert_eq!(parse_frame_rate("30000/1001"), Some(29.97));
+        assert_eq!(parse_frame_rate("25/1"), Some(25.0));
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("59.94"), Some(59.94));
+    }
+
+    #[test]
+    fn test_channels_from_layout() {
+        assert_eq!(channels_from_layout("mono"), Some(1));
+        assert_eq!(channels_from_layout("7.1(wide)"), Some(8));
+        assert_eq!(channels_from_layout("3 channels"), Some(3));
+        assert_eq!(channels_from_layout("weird"), None);
+    }
+}
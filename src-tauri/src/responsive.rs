@@ -0,0 +1,253 @@
+//! Responsive image sets - One picture at several widths and formats, ready for `srcset`
+//!
+//! Each width is resized once and written in every format by one ImageMagick run
+//! (`-write` from clones), so a 3x3 set decodes the source three times, not nine. A
+//! manifest.json next to the images lists them and holds a `<picture>` element to paste.
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_WIDTHS: &[u32] = &[480, 960, 1920];
+
+/// Most efficient first, so browsers pick the first `<source>` they support
+pub const DEFAULT_FORMATS: &[&str] = &["avif", "webp", "jpg"];
+
+/// Formats a set can be written in
+pub const RESPONSIVE_FORMATS: &[&str] = &["avif", "webp", "jpg", "jpeg", "png"];
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// `sizes` attribute when none is given: the image spans the viewport
+pub const DEFAULT_SIZES: &str = "100vw";
+
+/// One image of the set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResponsiveImage {
+    /// File name inside the set's folder
+    pub file: String,
+    pub format: String,
+    pub width: u32,
+    pub height: Option<u32>,
+    pub size: u64,
+}
+
+/// All widths of one format, as a `<source>` (or the fallback `<img>`) uses them
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResponsiveSource {
+    pub format: String,
+    pub mime_type: String,
+    /// "hero-480w.webp 480w, hero-960w.webp 960w"
+    pub srcset: String,
+}
+
+/// What generate_responsive_set wrote; also saved as the manifest
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResponsiveSet {
+    pub output_directory: String,
+    pub images: Vec<ResponsiveImage>,
+    pub sources: Vec<ResponsiveSource>,
+    /// The `<picture>` element for the set
+    pub html: String,
+    pub warnings: Vec<String>,
+}
+
+/// Widths to render, smallest first. Widths above the source's are left out, since scaling up
+/// only makes files bigger; if that leaves none, the source width is used.
+pub fn plan_widths(requested: &[u32], source_width: Option<u32>) -> Vec<u32> {
+    let mut widths: Vec<u32> = requested
+        .iter()
+        .copied()
+        .filter(|width| *width > 0 && !source_width.is_some_and(|source| *width > source))
+        .collect();
+    widths.sort_unstable();
+    widths.dedup();
+    if widths.is_empty() {
+        widths.extend(source_width);
+    }
+    widths
+}
+
+/// "hero-960w.webp"
+pub fn image_file_name(stem: &str, width: u32, format: &str) -> String {
+    format!("{}-{}w.{}", stem, width, format)
+}
+
+/// Height of the source scaled to `width`, for the `<img>` size that prevents layout shift
+pub fn scaled_height(width: u32, source: Option<(u32, u32)>) -> Option<u32> {
+    let (source_width, source_height) = source.filter(|(w, h)| *w > 0 && *h > 0)?;
+    Some(((width as f64 * source_height as f64 / source_width as f64).round() as u32).max(1))
+}
+
+pub fn mime_type(format: &str) -> &'static str {
+    match format {
+        "avif" => "image/avif",
+        "webp" => "image/webp",
+        "png" => "image/png",
+        _ => "image/jpeg",
+    }
+}
+
+/// Quality for web images when the conversion doesn't set one. AVIF looks as good at a much
+/// lower number.
+pub fn default_quality(format: &str) -> Option<u8> {
+    match format {
+        "avif" => Some(50),
+        "webp" => Some(80),
+        "jpg" | "jpeg" => Some(82),
+        _ => None,
+    }
+}
+
+/// ImageMagick arguments writing `source` at `width` in each of `outputs` (format, file,
+/// quality). Every output is written from its own clone, so a JPEG flattened onto white
+/// doesn't affect the others.
+pub fn render_args(source: &str, width: u32, outputs: &[(String, String, Option<u8>)], strip: bool) -> Vec<String> {
+    let mut args = vec![source.to_string(), "-auto-orient".to_string()];
+    if strip {
+        args.push("-strip".to_string());
+    }
+    // ">" only ever shrinks
+    args.extend(["-resize".to_string(), format!("{}x>", width)]);
+    for (format, file, quality) in outputs {
+        args.extend(["(".to_string(), "+clone".to_string()]);
+        if matches!(format.as_str(), "jpg" | "jpeg") {
+            args.extend(["-background", "white", "-alpha", "remove", "-alpha", "off"].map(str::to_string));
+        }
+        if let Some(quality) = quality {
+            args.extend(["-quality".to_string(), quality.to_string()]);
+        }
+        args.extend(["-write".to_string(), format!("{}:{}", format.replace("jpg", "jpeg"), file), "+delete".to_string(), ")".to_string()]);
+    }
+    args.push("null:".to_string());
+    args
+}
+
+/// A file name as a URL path segment: srcset splits on spaces and commas
+fn url_segment(file: &str) -> String {
+    file.replace('%', "%25").replace(' ', "%20").replace(',', "%2C").replace('"', "%22")
+}
+
+fn srcset(images: &[&ResponsiveImage]) -> String {
+    images
+        .iter()
+        .map(|image| format!("{} {}w", url_segment(&image.file), image.width))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
+
+/// The set's `<source>`s in the order of `formats`, and a `<picture>` element using them: a
+/// `<source>` per format but the last, which is the `<img>` browsers without the others load
+pub fn picture(images: &[ResponsiveImage], formats: &[String], sizes: &str) -> (Vec<ResponsiveSource>, String) {
+    let sources: Vec<ResponsiveSource> = formats
+        .iter()
+        .filter_map(|format| {
+            let of_format: Vec<&ResponsiveImage> = images.iter().filter(|image| &image.format == format).collect();
+            (!of_format.is_empty()).then(|| ResponsiveSource {
+                format: format.clone(),
+                mime_type: mime_type(format).to_string(),
+                srcset: srcset(&of_format),
+            })
+        })
+        .collect();
+    if sources.is_empty() {
+        return (sources, String::new());
+    }
+    let (fallback, others) = sources.split_last().expect("checked above");
+
+    let sizes = escape_attribute(sizes);
+    let mut html = String::from("<picture>\n");
+    for source in others {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"{}\">\n",
+            source.mime_type,
+            escape_attribute(&source.srcset),
+            sizes
+        ));
+    }
+    // The largest fallback is the src for browsers without srcset
+    let largest = images.iter().filter(|image| image.format == fallback.format).max_by_key(|image| image.width);
+    if let Some(largest) = largest {
+        let height = largest.height.map(|height| format!(" height=\"{}\"", height)).unwrap_or_default();
+        html.push_str(&format!(
+            "  <img src=\"{}\" srcset=\"{}\" sizes=\"{}\" width=\"{}\"{} alt=\"\" loading=\"lazy\" decoding=\"async\">\n",
+            escape_attribute(&url_segment(&largest.file)),
+            escape_attribute(&fallback.srcset),
+            sizes,
+            largest.width,
+            height
+        ));
+    }
+    html.push_str("</picture>\n");
+    (sources, html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(file: &str, format: &str, width: u32, height: u32) -> ResponsiveImage {
+        ResponsiveImage { file: file.to_string(), format: format.to_string(), width, height: Some(height), size: 1000 }
+    }
+
+    #[test]
+    fn test_plan_widths() {
+        assert_eq!(plan_widths(&[1920, 480, 960, 480], Some(4000)), vec![480, 960, 1920]);
+        assert_eq!(plan_widths(DEFAULT_WIDTHS, Some(1200)), vec![480, 960]);
+        assert_eq!(plan_widths(DEFAULT_WIDTHS, Some(300)), vec![300]);
+        assert_eq!(plan_widths(&[0, 640], None), vec![640]);
+    }
+
+    #[test]
+    fn test_scaled_height() {
+        assert_eq!(scaled_height(960, Some((4000, 3000))), Some(720));
+        assert_eq!(scaled_height(480, Some((1920, 1081))), Some(270));
+        assert_eq!(scaled_height(480, None), None);
+    }
+
+    #[test]
+    fn test_render_args() {
+        let outputs = vec![
+            ("avif".to_string(), "hero-960w.avif".to_string(), Some(50)),
+            ("jpg".to_string(), "hero-960w.jpg".to_string(), Some(82)),
+        ];
+        let args = render_args("input-0.png[0]", 960, &outputs, true);
+        assert_eq!(args[..5], ["input-0.png[0]", "-auto-orient", "-strip", "-resize", "960x>"]);
+        assert!(args.windows(2).any(|w| w == ["-write", "avif:hero-960w.avif"]));
+        assert!(args.windows(2).any(|w| w == ["-write", "jpeg:hero-960w.jpg"]));
+        // Only the JPEG is flattened
+        assert_eq!(args.iter().filter(|arg| *arg == "-background").count(), 1);
+        assert_eq!(args.last().unwrap(), "null:");
+    }
+
+    #[test]
+    fn test_picture() {
+        let images = vec![
+            image("hero-480w.webp", "webp", 480, 270),
+            image("hero-960w.webp", "webp", 960, 540),
+            image("hero-480w.jpg", "jpg", 480, 270),
+            image("hero-960w.jpg", "jpg", 960, 540),
+        ];
+        let formats = vec!["avif".to_string(), "webp".to_string(), "jpg".to_string()];
+        let (sources, html) = picture(&images, &formats, DEFAULT_SIZES);
+
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].srcset, "hero-480w.webp 480w, hero-960w.webp 960w");
+        assert_eq!(
+            html,
+            "<picture>\n\
+             \x20 <source type=\"image/webp\" srcset=\"hero-480w.webp 480w, hero-960w.webp 960w\" sizes=\"100vw\">\n\
+             \x20 <img src=\"hero-960w.jpg\" srcset=\"hero-480w.jpg 480w, hero-960w.jpg 960w\" sizes=\"100vw\" width=\"960\" height=\"540\" alt=\"\" loading=\"lazy\" decoding=\"async\">\n\
+             </picture>\n"
+        );
+    }
+
+    #[test]
+    fn test_srcset_escapes_file_names() {
+        let images = vec![image("my hero, final-480w.jpg", "jpg", 480, 270)];
+        let (sources, _) = picture(&images, &["jpg".to_string()], DEFAULT_SIZES);
+        assert_eq!(sources[0].srcset, "my%20hero%2C%20final-480w.jpg 480w");
+    }
+}
@@ -0,0 +1,252 @@
+//! Interrupted batches - Picking a batch up again after a crash or a quit
+//!
+//! While a batch runs, its jobs and which of them are done are kept in queue.json next to
+//! the config, along with every output being written at that moment. A batch from another
+//! run of the app that is still in the file was interrupted: the app offers to convert the
+//! files it didn't get to. Outputs that were being written when it stopped are half-written
+//! and are removed at launch, whether or not the batch is resumed. The PDF open password
+//! is never written to the file; resuming a batch that had one asks for it again.
+
+use crate::conversion::ConversionSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const QUEUE_FILE: &str = "queue.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueuedJob {
+    pub input_path: String,
+    pub output_directory: Option<String>,
+    /// Converted or failed; either way it isn't run again
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// A batch as it was started, with its progress
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueuedBatch {
+    pub id: String,
+    /// RFC 3339
+    pub started_at: String,
+    /// The app process running it, so a batch still running isn't taken for an interrupted one
+    pub pid: u32,
+    pub output_format: String,
+    pub advanced_options: Option<String>,
+    /// Without the PDF password (see `needs_pdf_password`)
+    #[serde(default)]
+    pub settings: ConversionSettings,
+    /// The batch made password-protected PDFs, so the password has to be given to resume it
+    #[serde(default)]
+    pub needs_pdf_password: bool,
+    pub workers: Option<usize>,
+    #[serde(default)]
+    pub images_only: bool,
+    pub jobs: Vec<QueuedJob>,
+}
+
+impl QueuedBatch {
+    /// Journals `settings` without the PDF password
+    pub fn settings_to_journal(&mut self, settings: &ConversionSettings) {
+        self.needs_pdf_password = settings.pdf_export.password.is_some();
+        self.settings = settings.without_secrets();
+    }
+
+    /// The jobs it didn't get to
+    pub fn remaining_jobs(&self) -> Vec<QueuedJob> {
+        self.jobs.iter().filter(|job| !job.done).cloned().collect()
+    }
+}
+
+/// What the app offers to resume at launch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InterruptedBatch {
+    pub id: String,
+    pub started_at: String,
+    pub output_format: String,
+    pub total: usize,
+    pub remaining: usize,
+    #[serde(default)]
+    pub needs_pdf_password: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct QueueJournal {
+    #[serde(default)]
+    pub batches: Vec<QueuedBatch>,
+    /// Outputs being written, with the process writing them
+    #[serde(default)]
+    pub writing: BTreeMap<String, u32>,
+}
+
+impl QueueJournal {
+    /// The journal at `path`. One that can't be parsed is started over rather than
+    /// keeping every batch from being journaled.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read the queue: {}", e))?;
+        Ok(Self::parse(&contents))
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        serde_json::from_str(contents).unwrap_or_default()
+    }
+
+    /// Writes a temporary file next to `path` and renames it over, so a crash mid-write
+    /// never leaves a cut-off journal
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the queue: {}", e))?;
+        let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+        std::fs::write(&temp_path, json)
+            .map_err(|e| format!("Failed to write the queue: {}", e))?;
+        std::fs::rename(&temp_path, path)
+            .map_err(|e| format!("Failed to write the queue: {}", e))
+    }
+
+    /// Batches left behind by runs of the app that are no longer running, with jobs still
+    /// to do, oldest first
+    pub fn interrupted(&self, is_running: impl Fn(u32) -> bool) -> Vec<InterruptedBatch> {
+        self.batches
+            .iter()
+            .filter(|batch| !is_running(batch.pid))
+            .map(|batch| InterruptedBatch {
+                id: batch.id.clone(),
+                started_at: batch.started_at.clone(),
+                output_format: batch.output_format.clone(),
+                total: batch.jobs.len(),
+                remaining: batch.jobs.iter().filter(|job| !job.done).count(),
+                needs_pdf_password: batch.needs_pdf_password,
+            })
+            .filter(|batch| batch.remaining > 0)
+            .collect()
+    }
+
+    /// Removes and returns the outputs runs of the app that are no longer running were
+    /// writing when they stopped
+    pub fn take_partial_outputs(&mut self, is_running: impl Fn(u32) -> bool) -> Vec<String> {
+        let (partial, current): (BTreeMap<_, _>, BTreeMap<_, _>) = std::mem::take(&mut self.writing)
+            .into_iter()
+            .partition(|(_, writer)| !is_running(*writer));
+        self.writing = current;
+        partial.into_keys().collect()
+    }
+
+    pub fn mark_done(&mut self, id: &str, index: usize) {
+        if let Some(job) = self.batches.iter_mut().find(|batch| batch.id == id).and_then(|batch| batch.jobs.get_mut(index)) {
+            job.done = true;
+        }
+    }
+
+    /// Removes a finished, resumed or dismissed batch
+    pub fn remove(&mut self, id: &str) -> Option<QueuedBatch> {
+        let index = self.batches.iter().position(|batch| batch.id == id)?;
+        Some(self.batches.remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(id: &str, pid: u32, done: &[bool]) -> QueuedBatch {
+        QueuedBatch {
+            id: id.to_string(),
+            started_at: "2026-10-16T09:00:00Z".to_string(),
+            pid,
+            output_format: "webp".to_string(),
+            advanced_options: None,
+            settings: ConversionSettings::default(),
+            needs_pdf_password: false,
+            workers: None,
+            images_only: true,
+            jobs: done
+                .iter()
+                .enumerate()
+                .map(|(i, done)| QueuedJob { input_path: format!("/in/{}.jpg", i), output_directory: None, done: *done })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let journal = QueueJournal {
+            batches: vec![
+                batch("old", 10, &[true, false, false]),
+                batch("running", 20, &[false]),
+                batch("other window", 30, &[false]),
+                batch("finished", 10, &[true]),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            journal.interrupted(|pid| pid == 20 || pid == 30),
+            vec![InterruptedBatch {
+                id: "old".to_string(),
+                started_at: "2026-10-16T09:00:00Z".to_string(),
+                output_format: "webp".to_string(),
+                total: 3,
+                remaining: 2,
+                needs_pdf_password: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mark_done_and_remove() {
+        let mut journal = QueueJournal { batches: vec![batch("a", 10, &[false, false])], ..Default::default() };
+        journal.mark_done("a", 1);
+        journal.mark_done("a", 7);
+        journal.mark_done("missing", 0);
+        let batch = journal.remove("a").unwrap();
+        assert_eq!(batch.remaining_jobs(), vec![QueuedJob { input_path: "/in/0.jpg".to_string(), output_directory: None, done: false }]);
+        assert!(journal.batches.is_empty());
+        assert_eq!(journal.remove("a"), None);
+    }
+
+    #[test]
+    fn test_take_partial_outputs() {
+        let mut journal = QueueJournal::default();
+        journal.writing.insert("/out/a.webp".to_string(), 10);
+        journal.writing.insert("/out/b.webp".to_string(), 20);
+        journal.writing.insert("/out/c.webp".to_string(), 30);
+        let running = |pid| pid == 20 || pid == 30;
+        assert_eq!(journal.take_partial_outputs(running), vec!["/out/a.webp".to_string()]);
+        assert_eq!(journal.writing.len(), 2);
+        assert!(journal.take_partial_outputs(running).is_empty());
+    }
+
+    #[test]
+    fn test_journal_leaves_out_the_pdf_password() {
+        let mut settings = ConversionSettings::default();
+        settings.pdf_export.password = Some("hunter2".to_string());
+        let mut queued = batch("a", 10, &[false]);
+        queued.settings_to_journal(&settings);
+        assert!(queued.needs_pdf_password);
+        assert_eq!(queued.settings.pdf_export.password, None);
+        let journal = QueueJournal { batches: vec![queued], ..Default::default() };
+        assert!(!serde_json::to_string(&journal).unwrap().contains("hunter2"));
+        assert!(journal.interrupted(|_| false)[0].needs_pdf_password);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("convertsave-resume-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(QUEUE_FILE);
+        let journal = QueueJournal { batches: vec![batch("a", 10, &[true, false])], ..Default::default() };
+        journal.save(&path).unwrap();
+        assert_eq!(QueueJournal::load(&path).unwrap(), journal);
+        assert!(!dir.join("queue.json.tmp").exists());
+
+        // Cut off mid-write by an older version
+        std::fs::write(&path, "{\"batches\": [{\"id\": \"a\"").unwrap();
+        assert_eq!(QueueJournal::load(&path).unwrap(), QueueJournal::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! Network retries - Exponential backoff with jitter for flaky connections
+//!
+//! Version checks, tool downloads and license calls go through one retry helper in
+//! main.rs, so a dropped Wi-Fi packet costs a short wait instead of a failed download.
+//! Each kind of call has a policy: how many attempts, how the wait grows, and a budget
+//! for the whole call after which it gives up. Calls that change something on the server
+//! (activating a license) are only retried when the request never got there.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in all, including the first
+    pub attempts: u32,
+    /// Wait before the second attempt; doubled for each one after
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Time the call may take in all, waits included
+    pub budget: Duration,
+    /// Whether sending the request twice is harmless. If not, only connection failures and
+    /// responses where the server refused the request (429, 503) are retried.
+    pub idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Version checks and manifests: quick, since nothing waits on them for long
+    pub const CHECK: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(4),
+        budget: Duration::from_secs(30),
+        idempotent: true,
+    };
+
+    /// Tool downloads, which are large and worth waiting for
+    pub const DOWNLOAD: RetryPolicy = RetryPolicy {
+        attempts: 4,
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(30),
+        budget: Duration::from_secs(15 * 60),
+        idempotent: true,
+    };
+
+    /// License lookups and status checks
+    pub const LICENSE: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(8),
+        budget: Duration::from_secs(30),
+        idempotent: true,
+    };
+
+    /// License changes (activation, deactivation, checkout)
+    pub const LICENSE_CHANGE: RetryPolicy = RetryPolicy { idempotent: false, ..RetryPolicy::LICENSE };
+
+    /// Wait before attempt `attempt + 1` (attempts count from 1): the doubled delay, capped,
+    /// of which the upper half is scaled by `jitter` (0..1) so clients don't retry in step
+    pub fn backoff(&self, attempt: u32, jitter: f64) -> Duration {
+        let doubled = self.base_delay.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let delay = doubled.min(self.max_delay);
+        delay / 2 + delay.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+    }
+
+    /// The wait before retrying after attempt `attempt` failed `elapsed` into the call, or
+    /// None when the attempts or the budget are used up
+    pub fn next_delay(&self, attempt: u32, elapsed: Duration, jitter: f64) -> Option<Duration> {
+        if attempt >= self.attempts {
+            return None;
+        }
+        let delay = self.backoff(attempt, jitter);
+        (elapsed + delay < self.budget).then_some(delay)
+    }
+
+    /// Whether a response with this status is worth retrying
+    pub fn retries_status(&self, status: u16) -> bool {
+        match status {
+            429 | 503 => true,
+            408 | 425 | 500 | 502 | 504 => self.idempotent,
+            _ => false,
+        }
+    }
+}
+
+/// Emitted as "network-retry" before each retry, so the UI can say "Retrying (2 of 4)..."
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RetryEvent {
+    /// e.g. "Downloading FFmpeg"
+    pub operation: String,
+    /// The attempt about to start
+    pub attempt: u32,
+    pub attempts: u32,
+    pub delay_ms: u64,
+    /// Why the previous attempt failed
+    pub error: String,
+}
+
+/// A value in 0..1 that differs from call to call, for jitter
+pub fn clock_jitter() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since| since.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 1000) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::DOWNLOAD;
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1, 1.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3, 1.0), Duration::from_secs(8));
+        // Capped at max_delay
+        assert_eq!(policy.backoff(10, 1.0), Duration::from_secs(30));
+        assert_eq!(policy.backoff(u32::MAX, 0.5), Duration::from_secs(15) + Duration::from_millis(7500));
+    }
+
+    #[test]
+    fn test_next_delay() {
+        let policy = RetryPolicy::CHECK;
+        assert!(policy.next_delay(1, Duration::ZERO, 0.5).is_some());
+        assert!(policy.next_delay(2, Duration::ZERO, 0.5).is_some());
+        assert_eq!(policy.next_delay(3, Duration::ZERO, 0.5), None);
+        // Over budget
+        assert_eq!(policy.next_delay(1, Duration::from_secs(30), 0.5), None);
+    }
+
+    #[test]
+    fn test_retries_status() {
+        assert!(RetryPolicy::CHECK.retries_status(502));
+        assert!(!RetryPolicy::CHECK.retries_status(404));
+        assert!(RetryPolicy::LICENSE_CHANGE.retries_status(503));
+        assert!(!RetryPolicy::LICENSE_CHANGE.retries_status(502));
+    }
+}
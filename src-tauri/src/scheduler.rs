@@ -0,0 +1,194 @@
+//! Conversion scheduling - Decides which waiting conversion gets the next free slot
+//!
+//! Only so many conversions run at once. When every slot is busy, jobs the user started
+//! (drag and drop, the convert button) go ahead of queued background jobs such as
+//! watch-folder conversions, so a big sync can't hold up interactive work. Running jobs
+//! are never interrupted; only their place in the queue changes.
+//!
+//! Separately, tools can run at a low CPU priority with fewer FFmpeg threads, so a long
+//! encode doesn't make the machine unusable while it runs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
+
+/// Who a conversion is for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    /// Started by the user, who is waiting for it
+    #[default]
+    Foreground,
+    /// Started automatically (e.g. a watch folder); runs when no foreground job is waiting
+    Background,
+}
+
+/// How busy the conversion slots are, for the queue UI
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct QueueStatus {
+    pub capacity: usize,
+    pub running: usize,
+    pub waiting_foreground: usize,
+    pub waiting_background: usize,
+}
+
+/// Conversion slots and the jobs waiting for one, in arrival order
+#[derive(Debug, Clone)]
+pub struct SlotQueue {
+    capacity: usize,
+    running: usize,
+    waiting: VecDeque<(u64, JobPriority)>,
+    next_ticket: u64,
+}
+
+impl SlotQueue {
+    pub fn new(capacity: usize) -> Self {
+        SlotQueue {
+            capacity: capacity.max(1),
+            running: 0,
+            waiting: VecDeque::new(),
+            next_ticket: 0,
+        }
+    }
+
+    /// Joins the queue, returning the ticket to start (or cancel) the job with
+    pub fn enqueue(&mut self, priority: JobPriority) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.waiting.push_back((ticket, priority));
+        ticket
+    }
+
+    /// The ticket that gets the next free slot: the oldest foreground job, or the oldest
+    /// background job when no foreground job is waiting
+    fn next_in_line(&self) -> Option<u64> {
+        self.waiting
+            .iter()
+            .find(|(_, priority)| *priority == JobPriority::Foreground)
+            .or_else(|| self.waiting.front())
+            .map(|(ticket, _)| *ticket)
+    }
+
+    /// Takes a slot for `ticket` if one is free and it's next in line
+    pub fn try_start(&mut self, ticket: u64) -> bool {
+        if self.running >= self.capacity || self.next_in_line() != Some(ticket) {
+            return false;
+        }
+        self.waiting.retain(|(waiting, _)| *waiting != ticket);
+        self.running += 1;
+        true
+    }
+
+    /// Frees the slot of a job that finished
+    pub fn finish(&mut self) {
+        self.running = self.running.saturating_sub(1);
+    }
+
+    /// Leaves the queue without running
+    pub fn cancel(&mut self, ticket: u64) {
+        self.waiting.retain(|(waiting, _)| *waiting != ticket);
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let waiting_foreground = self
+            .waiting
+            .iter()
+            .filter(|(_, priority)| *priority == JobPriority::Foreground)
+            .count();
+        QueueStatus {
+            capacity: self.capacity,
+            running: self.running,
+            waiting_foreground,
+            waiting_background: self.waiting.len() - waiting_foreground,
+        }
+    }
+}
+
+/// How much `nice` lowers the CPU priority of low-priority tool runs on Unix (19 is lowest)
+pub const LOW_PRIORITY_NICENESS: i32 = 10;
+
+/// Windows process creation flag for a below-normal CPU priority
+pub const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+
+/// Arguments for `nice` running `program` with `args` at low priority
+pub fn nice_args<'a>(program: &'a OsStr, args: impl IntoIterator<Item = &'a OsStr>) -> Vec<OsString> {
+    let mut nice = vec![OsString::from("-n"), OsString::from(LOW_PRIORITY_NICENESS.to_string()), program.to_os_string()];
+    nice.extend(args.into_iter().map(OsStr::to_os_string));
+    nice
+}
+
+/// Threads FFmpeg may use: the number asked for, or in low-priority mode half the cores so
+/// the rest of the machine stays responsive (None leaves it to FFmpeg)
+pub fn ffmpeg_threads(requested: Option<u32>, low_priority: bool, cores: usize) -> Option<u32> {
+    match requested.filter(|threads| *threads > 0) {
+        Some(threads) => Some(threads),
+        None if low_priority => Some((cores / 2).max(1) as u32),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nice_args() {
+        let args = nice_args(OsStr::new("ffmpeg"), [OsStr::new("-i"), OsStr::new("in.mov")]);
+        assert_eq!(args, ["-n", "10", "ffmpeg", "-i", "in.mov"]);
+    }
+
+    #[test]
+    fn test_ffmpeg_threads() {
+        assert_eq!(ffmpeg_threads(Some(3), false, 8), Some(3));
+        assert_eq!(ffmpeg_threads(None, true, 8), Some(4));
+        assert_eq!(ffmpeg_threads(Some(0), true, 1), Some(1));
+        assert_eq!(ffmpeg_threads(None, false, 8), None);
+    }
+
+    #[test]
+    fn test_starts_while_slots_are_free() {
+        let mut queue = SlotQueue::new(2);
+        let a = queue.enqueue(JobPriority::Background);
+        let b = queue.enqueue(JobPriority::Background);
+        let c = queue.enqueue(JobPriority::Background);
+        assert!(queue.try_start(a));
+        assert!(queue.try_start(b));
+        assert!(!queue.try_start(c));
+        queue.finish();
+        assert!(queue.try_start(c));
+    }
+
+    #[test]
+    fn test_foreground_jumps_queued_background() {
+        let mut queue = SlotQueue::new(1);
+        let running = queue.enqueue(JobPriority::Background);
+        assert!(queue.try_start(running));
+        let background = queue.enqueue(JobPriority::Background);
+        let foreground = queue.enqueue(JobPriority::Foreground);
+
+        queue.finish();
+        assert!(!queue.try_start(background));
+        assert!(queue.try_start(foreground));
+        queue.finish();
+        assert!(queue.try_start(background));
+    }
+
+    #[test]
+    fn test_same_priority_runs_in_arrival_order() {
+        let mut queue = SlotQueue::new(1);
+        let first = queue.enqueue(JobPriority::Foreground);
+        let second = queue.enqueue(JobPriority::Foreground);
+        assert!(!queue.try_start(second));
+        assert!(queue.try_start(first));
+    }
+
+    #[test]
+    fn test_cancel_and_status() {
+        let mut queue = SlotQueue::new(0);
+        let a = queue.enqueue(JobPriority::Foreground);
+        queue.enqueue(JobPriority::Background);
+        assert_eq!(queue.status(), QueueStatus { capacity: 1, running: 0, waiting_foreground: 1, waiting_background: 1 });
+        queue.cancel(a);
+        assert_eq!(queue.status().waiting_foreground, 0);
+    }
+}
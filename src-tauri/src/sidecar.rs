@@ -0,0 +1,158 @@
+//! Reproducibility sidecars - How an output was made, saved next to it
+//!
+//! With `write_sidecar` on, every output gets a `<file>.convertsave.json` recording the
+//! app and tool versions, the command line and the settings, so a deliverable can be
+//! audited or made again long after the conversion.
+
+use crate::conversion::{ConversionResult, ConversionSettings};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Appended to the output's file name
+pub const SIDECAR_SUFFIX: &str = ".convertsave.json";
+
+/// Bumped when fields change meaning, so readers can tell old sidecars apart
+pub const SIDECAR_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SidecarFile {
+    pub path: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Everything needed to redo a conversion
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Sidecar {
+    pub sidecar_version: u32,
+    pub app_version: String,
+    /// When the output was written (RFC 3339, UTC)
+    pub created_at: String,
+    /// `std::env::consts` OS and architecture
+    pub platform: String,
+    pub input: SidecarFile,
+    pub output: SidecarFile,
+    pub tool: Option<String>,
+    /// First line of the tool's version output, e.g. "6.1.1" for FFmpeg
+    pub tool_version: Option<String>,
+    pub command_line: Option<String>,
+    pub elapsed_ms: u64,
+    pub settings: ConversionSettings,
+    pub warnings: Vec<String>,
+}
+
+impl Sidecar {
+    pub fn new(
+        result: &ConversionResult,
+        input_path: &str,
+        settings: &ConversionSettings,
+        tool_version: Option<String>,
+        app_version: &str,
+        created_at: String,
+    ) -> Self {
+        Sidecar {
+            sidecar_version: SIDECAR_VERSION,
+            app_version: app_version.to_string(),
+            created_at,
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            input: SidecarFile { path: input_path.to_string(), size: result.input_size, sha256: None },
+            output: SidecarFile { path: result.output_path.clone(), size: result.output_size, sha256: result.sha256.clone() },
+            tool: result.tool.clone(),
+            tool_version,
+            command_line: result.command_line.clone(),
+            elapsed_ms: result.elapsed_ms,
+            settings: settings.without_secrets(),
+            warnings: result.warnings.iter().chain(&result.fallbacks).cloned().collect(),
+        }
+    }
+}
+
+/// Where the sidecar of `output` goes: `photo.webp` -> `photo.webp.convertsave.json`
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    output.with_file_name(name)
+}
+
+/// Arguments that make a tool print its version, for the tools that have them
+pub fn tool_version_args(tool: &str) -> Option<&'static [&'static str]> {
+    match tool {
+        "ffmpeg" | "imagemagick" => Some(&["-version"]),
+        "pandoc" | "libreoffice" | "calibre" | "ghostscript" | "inkscape" | "chromium" | "tesseract" => Some(&["--version"]),
+        _ => None,
+    }
+}
+
+/// The version in the first line of a tool's version output: the word after "version" when
+/// it has a number in it ("ffmpeg version n6.1.1 Copyright ..." -> "n6.1.1"), otherwise the
+/// first word starting with a digit ("Version: ImageMagick 7.1.1-29 Q16-HDRI ..." -> "7.1.1-29")
+pub fn parse_tool_version(output: &str) -> Option<String> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    let words: Vec<&str> = line
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| c == '(' || c == ')' || c == ','))
+        .collect();
+    let after_version = words
+        .windows(2)
+        .find(|pair| pair[0].trim_end_matches(':').eq_ignore_ascii_case("version"))
+        .map(|pair| pair[1])
+        .filter(|word| word.contains(|c: char| c.is_ascii_digit()));
+    after_version
+        .or_else(|| words.iter().copied().find(|word| word.starts_with(|c: char| c.is_ascii_digit())))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(sidecar_path(Path::new("/out/photo.webp")), Path::new("/out/photo.webp.convertsave.json"));
+        assert_eq!(sidecar_path(Path::new("clip.v2.mp4")), Path::new("clip.v2.mp4.convertsave.json"));
+    }
+
+    #[test]
+    fn test_parse_tool_version() {
+        assert_eq!(parse_tool_version("ffmpeg version 6.1.1 Copyright (c) 2000-2023").as_deref(), Some("6.1.1"));
+        assert_eq!(parse_tool_version("ffmpeg version N-113000-g1a2b3c4 Copyright (c) 2000-2024").as_deref(), Some("N-113000-g1a2b3c4"));
+        assert_eq!(
+            parse_tool_version("Version: ImageMagick 7.1.1-29 Q16-HDRI aarch64 22023 https://imagemagick.org\nCopyright: ...").as_deref(),
+            Some("7.1.1-29")
+        );
+        assert_eq!(parse_tool_version("\n10.02.1\n").as_deref(), Some("10.02.1"));
+        assert_eq!(parse_tool_version("ebook-convert (calibre 7.3.0)").as_deref(), Some("7.3.0"));
+        assert_eq!(parse_tool_version("LibreOffice 7.6.4.1 e19e193f88cd6c0525a17fb7a176ed8e6a3e2aa1").as_deref(), Some("7.6.4.1"));
+        assert_eq!(parse_tool_version("no version here"), None);
+        assert!(tool_version_args("libraw").is_none());
+    }
+
+    #[test]
+    fn test_sidecar_records_the_conversion() {
+        let mut result = ConversionResult::new("/out/a.webp".to_string(), 120, 2000, 500, Some("magick a.png a.webp".to_string()));
+        result.tool = Some("imagemagick".to_string());
+        let settings = ConversionSettings { quality: Some(80), ..Default::default() };
+        let sidecar = Sidecar::new(&result, "/in/a.png", &settings, Some("7.1.1-29".to_string()), "0.6.1", "2026-01-01T00:00:00Z".to_string());
+
+        let json = serde_json::to_value(&sidecar).unwrap();
+        assert_eq!(json["input"]["size"], 2000);
+        assert_eq!(json["command_line"], "magick a.png a.webp");
+        assert_eq!(json["settings"]["quality"], 80);
+        let read_back: Sidecar = serde_json::from_value(json).unwrap();
+        assert_eq!(read_back, sidecar);
+    }
+
+    #[test]
+    fn test_sidecar_leaves_out_the_pdf_password() {
+        let result = ConversionResult::new("/out/a.pdf".to_string(), 120, 2000, 500, None);
+        let mut settings = ConversionSettings::default();
+        settings.pdf_export.password = Some("hunter2".to_string());
+        settings.pdf_export.lossless_images = true;
+        let sidecar = Sidecar::new(&result, "/in/a.docx", &settings, None, "0.6.1", "2026-01-01T00:00:00Z".to_string());
+
+        assert_eq!(sidecar.settings.pdf_export.password, None);
+        assert!(sidecar.settings.pdf_export.lossless_images);
+        assert!(!serde_json::to_string(&sidecar).unwrap().contains("hunter2"));
+    }
+}
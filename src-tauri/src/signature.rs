@@ -0,0 +1,419 @@
+//! File signatures - What a file is by its first bytes, not its name
+//!
+//! Downloads without an extension, or photos renamed to the wrong one, would otherwise be
+//! routed by their name and fail in the tool. The start of the file is compared against
+//! known signatures; when it names a format the extension doesn't belong to (or there's
+//! no extension), the file is converted as the format its contents are. Formats without a
+//! signature (plain text, CSV, Markdown) are still taken by their extension.
+
+use serde::{Deserialize, Serialize};
+
+/// How much of the file to read: enough for the ZIP and OLE directory entries that tell
+/// Office documents apart
+pub const SNIFF_BYTES: usize = 8192;
+
+/// Extensions that name the same kind of file, so a file detected as one of them and named
+/// as another is fine. The first one is what detection reports.
+const FAMILIES: &[&[&str]] = &[
+    &["jpg", "jpeg", "jpe", "jfif"],
+    &["png", "apng"],
+    &["tiff", "tif", "dng", "nef", "nrw", "arw", "srf", "sr2", "srw", "pef", "erf", "3fr", "mef", "mos", "iiq", "kdc", "dcr"],
+    &["bmp", "dib"],
+    &["psd", "psb"],
+    &["heic", "heif", "hif"],
+    &["jp2", "j2k", "jpf", "jpx"],
+    &["mp4", "m4v", "m4a", "m4b", "mov", "qt", "3gp", "3g2", "f4v"],
+    &["mkv", "webm", "mka", "mk3d"],
+    &["ogg", "oga", "ogv", "opus", "spx"],
+    &["wmv", "wma", "asf"],
+    &["mpg", "mpeg", "vob", "m2v"],
+    &["ts", "mts", "m2ts", "m2t"],
+    &["aiff", "aif", "aifc"],
+    &["pdf", "ai"],
+    &["ps", "eps", "epsf", "epsi"],
+    &["html", "htm", "xhtml"],
+    &["ics", "ifb"],
+    &["vcf", "vcard"],
+    &["mobi", "azw", "azw3", "prc"],
+    &["docx", "docm", "dotx"],
+    &["xlsx", "xlsm", "xltx"],
+    &["pptx", "pptm", "potx"],
+    &["odt", "ott"],
+    &["ods", "ots"],
+    &["odp", "otp"],
+    &["svg", "xml"],
+];
+
+/// The format a file's first bytes (`header`, up to SNIFF_BYTES of them) belong to, as a
+/// lowercase extension. None for formats without a signature and for generic containers
+/// (a ZIP or an OLE file that isn't a known document).
+pub fn detect_format(header: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, bytes: &[u8]| header.get(offset..offset + bytes.len()) == Some(bytes);
+
+    // Images
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        return Some("jpg");
+    }
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        return Some("png");
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some("gif");
+    }
+    if at(0, b"RIFF") {
+        return match header.get(8..12) {
+            Some(b"WEBP") => Some("webp"),
+            Some(b"WAVE") => Some("wav"),
+            Some(b"AVI ") => Some("avi"),
+            _ => None,
+        };
+    }
+    if at(0, b"II*\0") || at(0, b"MM\0*") {
+        return Some(if at(8, b"CR\x02") { "cr2" } else { "tiff" });
+    }
+    if at(0, b"IIU\0") {
+        return Some("rw2");
+    }
+    if at(0, b"IIRO") || at(0, b"IIRS") || at(0, b"MMOR") {
+        return Some("orf");
+    }
+    if at(0, b"FUJIFILMCCD-RAW") {
+        return Some("raf");
+    }
+    if at(0, b"BM") && at(6, &[0, 0, 0, 0]) && matches!(header.get(14).copied(), Some(12 | 40 | 52 | 56 | 108 | 124)) {
+        return Some("bmp");
+    }
+    // Icon directories: a non-zero image count, then entries with a zero reserved byte
+    let icon_entries = at(9, &[0]) && !at(4, &[0, 0]);
+    if at(0, &[0, 0, 1, 0]) && icon_entries {
+        return Some("ico");
+    }
+    if at(0, &[0, 0, 2, 0]) && icon_entries {
+        return Some("cur");
+    }
+    if at(0, b"8BPS") {
+        return Some("psd");
+    }
+    if at(0, &[0xFF, 0x0A]) || at(0, b"\0\0\0\x0cJXL \r\n\x87\n") {
+        return Some("jxl");
+    }
+    if at(0, b"\0\0\0\x0cjP  \r\n\x87\n") {
+        return Some("jp2");
+    }
+    if at(0, &[0xFF, 0x4F, 0xFF, 0x51]) {
+        return Some("j2k");
+    }
+    if at(0, &[0x76, 0x2F, 0x31, 0x01]) {
+        return Some("exr");
+    }
+    if at(0, b"#?RADIANCE") || at(0, b"#?RGBE") {
+        return Some("hdr");
+    }
+    if at(0, b"SDPX") || at(0, b"XPDS") {
+        return Some("dpx");
+    }
+
+    // ISO media: MP4, QuickTime, HEIC, AVIF, CR3, by the brand after "ftyp"
+    if at(4, b"ftyp") {
+        return Some(match header.get(8..12) {
+            Some(b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1") => "heic",
+            Some(b"avif" | b"avis") => "avif",
+            Some(b"crx ") => "cr3",
+            Some(b"qt  ") => "mov",
+            Some(b"M4A " | b"M4B ") => "m4a",
+            Some(brand) if brand.starts_with(b"3g") => "3gp",
+            _ => "mp4",
+        });
+    }
+
+    // Audio and video
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        return Some(if contains(header, b"webm") { "webm" } else { "mkv" });
+    }
+    if at(0, b"FLV\x01") {
+        return Some("flv");
+    }
+    if at(0, &[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+        return Some("wmv");
+    }
+    if at(0, &[0, 0, 1, 0xBA]) || at(0, &[0, 0, 1, 0xB3]) {
+        return Some("mpg");
+    }
+    // Transport streams: a sync byte every 188 bytes (192 in M2TS, after a 4-byte timestamp)
+    if (at(0, &[0x47]) && at(188, &[0x47]) && at(376, &[0x47])) || (at(4, &[0x47]) && at(196, &[0x47]) && at(388, &[0x47])) {
+        return Some("ts");
+    }
+    if at(0, b"fLaC") {
+        return Some("flac");
+    }
+    if at(0, b"OggS") {
+        return Some(if at(28, b"OpusHead") { "opus" } else { "ogg" });
+    }
+    if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+        return Some("aiff");
+    }
+    if at(0, b"ID3") {
+        return Some("mp3");
+    }
+    // UTF-16 text starts with FF FE or FE FF, which would otherwise pass for a frame sync
+    if at(0, &[0xFF, 0xFE]) || at(0, &[0xFE, 0xFF]) {
+        return detect_text(header);
+    }
+    if let &[0xFF, second, third, ..] = header {
+        // ADTS AAC has layer bits 00; MPEG audio frames 01-11, with a bitrate index that
+        // isn't "bad" (1111) and a sample rate index that isn't reserved (11)
+        if second & 0xF6 == 0xF0 {
+            return Some("aac");
+        }
+        if second & 0xE0 == 0xE0 && (second >> 1) & 0x03 != 0 && third >> 4 != 0x0F && (third >> 2) & 0x03 != 0x03 {
+            return Some("mp3");
+        }
+    }
+
+    // Documents
+    if at(0, b"%PDF-") {
+        return Some("pdf");
+    }
+    if at(0, b"%!PS") {
+        let first_line = header.split(|&byte| byte == b'\n').next().unwrap_or_default();
+        return Some(if contains(first_line, b"EPSF") { "eps" } else { "ps" });
+    }
+    if at(0, &[0xC5, 0xD0, 0xD3, 0xC6]) {
+        return Some("eps");
+    }
+    if at(0, b"{\\rtf") {
+        return Some("rtf");
+    }
+    if at(60, b"BOOKMOBI") {
+        return Some("mobi");
+    }
+    if at(0, b"PK\x03\x04") {
+        return detect_zip(header);
+    }
+    if at(0, &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1]) {
+        return detect_ole(header);
+    }
+
+    detect_text(header)
+}
+
+/// Office, OpenDocument and EPUB files are ZIPs; EPUB and OpenDocument start with their
+/// uncompressed mimetype entry, Office files have a folder named for the application
+fn detect_zip(header: &[u8]) -> Option<&'static str> {
+    let mimetypes: &[(&[u8], &str)] = &[
+        (b"mimetypeapplication/epub+zip", "epub"),
+        (b"mimetypeapplication/vnd.oasis.opendocument.text", "odt"),
+        (b"mimetypeapplication/vnd.oasis.opendocument.spreadsheet", "ods"),
+        (b"mimetypeapplication/vnd.oasis.opendocument.presentation", "odp"),
+    ];
+    if let Some((_, format)) = mimetypes.iter().find(|(mimetype, _)| header.get(30..30 + mimetype.len()) == Some(*mimetype)) {
+        return Some(*format);
+    }
+    let folders: &[(&[u8], &str)] = &[(b"word/", "docx"), (b"xl/", "xlsx"), (b"ppt/", "pptx")];
+    folders
+        .iter()
+        .find(|(folder, _)| contains(header, folder))
+        .map(|(_, format)| *format)
+}
+
+/// Word, Excel and PowerPoint 97 files and Outlook messages are OLE files, told apart by
+/// the name of their main stream in the directory
+fn detect_ole(header: &[u8]) -> Option<&'static str> {
+    [("WordDocument", "doc"), ("Workbook", "xls"), ("Book", "xls"), ("PowerPoint Document", "ppt"), ("__substg1.0_", "msg")]
+        .into_iter()
+        .find(|(stream, _)| contains(header, &utf16(stream)))
+        .map(|(_, format)| format)
+}
+
+fn detect_text(header: &[u8]) -> Option<&'static str> {
+    let text = match header {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(header).into_owned(),
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let lower = text.chars().take(512).collect::<String>().to_lowercase();
+    if lower.starts_with("begin:vcalendar") {
+        Some("ics")
+    } else if lower.starts_with("begin:vcard") {
+        Some("vcf")
+    } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("html")
+    } else if lower.starts_with("<?xml") || lower.starts_with("<svg") || lower.starts_with("<!--") {
+        if lower.contains("<svg") {
+            Some("svg")
+        } else if lower.contains("<fictionbook") {
+            Some("fb2")
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    char::decode_utf16(bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])))
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Whether two extensions name the same kind of file
+pub fn same_format(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+        || FAMILIES.iter().any(|family| {
+            family.iter().any(|ext| ext.eq_ignore_ascii_case(a)) && family.iter().any(|ext| ext.eq_ignore_ascii_case(b))
+        })
+}
+
+/// A file's extension checked against its contents
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FormatCheck {
+    /// Lowercase, empty when the file has none
+    pub extension: String,
+    /// What the contents are, if they have a known signature
+    pub detected: Option<String>,
+    /// What to convert the file as
+    pub format: String,
+    /// The contents are another format than the extension says
+    pub mismatch: bool,
+    /// `format` was given by the caller rather than worked out
+    #[serde(default)]
+    pub declared: bool,
+}
+
+impl FormatCheck {
+    /// This check with the file converted as `format` regardless, for callers that know
+    /// better than the name and the signature (e.g. a raw camera file named .bin)
+    pub fn declare(self, format: &str) -> Self {
+        FormatCheck { format: format.trim_start_matches('.').to_lowercase(), mismatch: false, declared: true, ..self }
+    }
+
+    /// Whether the file has to be converted as something its name doesn't say
+    pub fn renamed(&self) -> bool {
+        self.format != self.extension
+    }
+
+    /// Why the file is converted as another format than its name says, if it is
+    pub fn warning(&self) -> Option<String> {
+        if self.declared {
+            None
+        } else if self.mismatch {
+            Some(format!(
+                "The file is named .{} but its contents are {}, so it's converted as {}",
+                self.extension,
+                self.format.to_uppercase(),
+                self.format.to_uppercase()
+            ))
+        } else if self.renamed() {
+            Some(format!("The file has no extension; converting it as {} from its contents", self.format.to_uppercase()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks `extension` against the file's first bytes. The extension wins when it agrees
+/// with the contents (it can be more specific, e.g. NEF over TIFF) or the contents are
+/// unknown; otherwise the detected format does.
+pub fn check_format(extension: &str, header: &[u8]) -> FormatCheck {
+    let extension = extension.trim_start_matches('.').to_lowercase();
+    let detected = detect_format(header);
+    let mismatch = detected.is_some_and(|detected| !extension.is_empty() && !same_format(&extension, detected));
+    let format = match detected {
+        Some(detected) if mismatch || extension.is_empty() => detected.to_string(),
+        _ => extension.clone(),
+    };
+    FormatCheck { extension, detected: detected.map(str::to_string), format, mismatch, declared: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(&[0xFF, 0xD8, 0xFF, 0xE1, 0, 0]), Some("jpg"));
+        assert_eq!(detect_format(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("png"));
+        assert_eq!(detect_format(b"RIFF\0\0\0\0WEBPVP8 "), Some("webp"));
+        assert_eq!(detect_format(b"\0\0\0\x18ftypheic\0\0\0\0"), Some("heic"));
+        assert_eq!(detect_format(b"\0\0\0\x20ftypisom\0\0\x02\0"), Some("mp4"));
+        assert_eq!(detect_format(b"%PDF-1.7\n"), Some("pdf"));
+        assert_eq!(detect_format(b"%!PS-Adobe-3.0 EPSF-3.0\n"), Some("eps"));
+        assert_eq!(detect_format(b"\xEF\xBB\xBFBEGIN:VCARD\r\nVERSION:3.0"), Some("vcf"));
+        assert_eq!(detect_format(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\">"), Some("svg"));
+        assert_eq!(detect_format(b"# Notes\n\nPlain text"), None);
+        assert_eq!(detect_format(b"BM is not a bitmap"), None);
+        assert_eq!(detect_format(b""), None);
+    }
+
+    #[test]
+    fn test_detect_audio_frames() {
+        // MPEG-1 layer III, 128 kbit/s, 44.1 kHz
+        assert_eq!(detect_format(&[0xFF, 0xFB, 0x90, 0x64]), Some("mp3"));
+        assert_eq!(detect_format(&[0xFF, 0xF1, 0x50, 0x80]), Some("aac"));
+        // Bad bitrate or reserved sample rate: not a frame
+        assert_eq!(detect_format(&[0xFF, 0xFB, 0xF0, 0x64]), None);
+        assert_eq!(detect_format(&[0xFF, 0xFB, 0x9C, 0x64]), None);
+
+        // UTF-16 byte order marks are text, not frame syncs
+        let mut vcard = vec![0xFF, 0xFE];
+        vcard.extend(utf16("BEGIN:VCARD\r\nVERSION:3.0"));
+        assert_eq!(detect_format(&vcard), Some("vcf"));
+        let mut notes = vec![0xFF, 0xFE];
+        notes.extend(utf16("hello"));
+        assert_eq!(detect_format(&notes), None);
+        let calendar: Vec<u8> = [0xFE, 0xFF].into_iter().chain("BEGIN:VCALENDAR".encode_utf16().flat_map(u16::to_be_bytes)).collect();
+        assert_eq!(detect_format(&calendar), Some("ics"));
+    }
+
+    #[test]
+    fn test_detect_containers() {
+        let mut epub = b"PK\x03\x04".to_vec();
+        epub.resize(30, 0);
+        epub.extend_from_slice(b"mimetypeapplication/epub+zip");
+        assert_eq!(detect_format(&epub), Some("epub"));
+
+        let mut docx = b"PK\x03\x04".to_vec();
+        docx.resize(30, 0);
+        docx.extend_from_slice(b"[Content_Types].xml...PK\x03\x04word/document.xml");
+        assert_eq!(detect_format(&docx), Some("docx"));
+        assert_eq!(detect_format(b"PK\x03\x04\x14\0\0\0photos/1.jpg"), None);
+
+        let mut doc = vec![0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+        doc.resize(1024, 0);
+        doc.extend(utf16("WordDocument"));
+        assert_eq!(detect_format(&doc), Some("doc"));
+    }
+
+    #[test]
+    fn test_check_format() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0];
+        let check = check_format("PNG", &jpeg);
+        assert_eq!((check.extension.as_str(), check.format.as_str(), check.mismatch), ("png", "jpg", true));
+        assert_eq!(check.warning().unwrap(), "The file is named .png but its contents are JPG, so it's converted as JPG");
+
+        let check = check_format("", &jpeg);
+        assert_eq!((check.format.as_str(), check.mismatch), ("jpg", false));
+        assert!(check.renamed());
+
+        // Agreeing extensions are kept, as they can be more specific
+        assert_eq!(check_format("jpeg", &jpeg).format, "jpeg");
+        assert_eq!(check_format("nef", b"MM\0*\0\0\0\x08").format, "nef");
+        let samsung = check_format("srw", b"II*\0\x08\0\0\0");
+        assert_eq!((samsung.format.as_str(), samsung.mismatch), ("srw", false));
+        assert_eq!(check_format("ai", b"%PDF-1.5").format, "ai");
+        assert_eq!(check_format("txt", b"hello").warning(), None);
+
+        let declared = check_format("", b"\0\0\0\0").declare(".DNG");
+        assert_eq!((declared.format.as_str(), declared.renamed(), declared.warning()), ("dng", true, None));
+    }
+}
@@ -0,0 +1,204 @@
+//! Spreadsheet page setup - Print settings applied to XLSX files before LibreOffice exports them
+//!
+//! LibreOffice's PDF export filter can only squeeze each sheet onto one page
+//! (`SinglePageSheets`). Orientation, gridlines and which sheets print are page setup
+//! stored in the workbook itself, so they're written into a copy of the XLSX
+//! (`xl/workbook.xml` and the `xl/worksheets/*.xml` parts) that LibreOffice then exports.
+
+use serde::{Deserialize, Serialize};
+
+/// Page layout for spreadsheets converted to PDF
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SpreadsheetLayout {
+    /// Put each sheet on a single page instead of cutting it across pages
+    pub fit_to_page: bool,
+    pub landscape: bool,
+    /// Print the cell gridlines
+    pub gridlines: bool,
+    /// Names of the sheets to include (empty = every visible sheet)
+    pub sheets: Vec<String>,
+}
+
+impl SpreadsheetLayout {
+    /// Whether the workbook has to be rewritten (everything but fit_to_page)
+    pub fn needs_workbook_changes(&self) -> bool {
+        self.landscape || self.gridlines || !self.sheets.is_empty()
+    }
+
+    /// Whether the worksheet parts have to be rewritten
+    pub fn needs_worksheet_changes(&self) -> bool {
+        self.landscape || self.gridlines
+    }
+}
+
+/// Worksheet elements that follow the cell data, in the order the schema requires them
+const WORKSHEET_TAIL: &[&str] = &[
+    "printOptions", "pageMargins", "pageSetup", "headerFooter", "rowBreaks", "colBreaks",
+    "customProperties", "cellWatches", "ignoredErrors", "smartTags", "drawing",
+    "legacyDrawing", "legacyDrawingHF", "picture", "oleObjects", "controls",
+    "webPublishItems", "tableParts", "extLst",
+];
+
+/// Byte range of the first `<name ...>` start tag (not `<nameOther`)
+fn find_tag(xml: &str, name: &str) -> Option<(usize, usize)> {
+    let open = format!("<{}", name);
+    xml.match_indices(&open).find_map(|(start, _)| {
+        let rest = &xml[start + open.len()..];
+        if !rest.starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            return None;
+        }
+        rest.find('>').map(|end| (start, start + open.len() + end + 1))
+    })
+}
+
+/// Sets `attribute` on the start tag in `tag`, replacing any existing value
+fn set_attribute(tag: &str, attribute: &str, value: &str) -> String {
+    let key = format!(" {}=\"", attribute);
+    if let Some(start) = tag.find(&key) {
+        let value_start = start + key.len();
+        if let Some(length) = tag[value_start..].find('"') {
+            return format!("{}{}{}", &tag[..value_start], value, &tag[value_start + length..]);
+        }
+    }
+    let insert_at = if tag.ends_with("/>") { tag.len() - 2 } else { tag.len() - 1 };
+    format!("{} {}=\"{}\"{}", tag[..insert_at].trim_end(), attribute, value, &tag[insert_at..])
+}
+
+/// Sets an attribute on a worksheet's `<name>` element, adding the element in its schema
+/// position when the sheet doesn't have one
+fn set_worksheet_attribute(xml: &str, name: &str, attribute: &str, value: &str) -> String {
+    if let Some((start, end)) = find_tag(xml, name) {
+        return format!("{}{}{}", &xml[..start], set_attribute(&xml[start..end], attribute, value), &xml[end..]);
+    }
+
+    let following = WORKSHEET_TAIL
+        .iter()
+        .skip_while(|element| **element != name)
+        .skip(1)
+        .find_map(|element| find_tag(xml, element).map(|(start, _)| start));
+    let Some(insert_at) = following.or_else(|| xml.rfind("</worksheet>")) else {
+        return xml.to_string();
+    };
+    format!("{}<{} {}=\"{}\"/>{}", &xml[..insert_at], name, attribute, value, &xml[insert_at..])
+}
+
+/// Applies orientation and gridlines to one `xl/worksheets/sheetN.xml` part
+pub fn patch_worksheet(xml: &str, layout: &SpreadsheetLayout) -> String {
+    let mut xml = xml.to_string();
+    if layout.gridlines {
+        xml = set_worksheet_attribute(&xml, "printOptions", "gridLines", "1");
+    }
+    if layout.landscape {
+        xml = set_worksheet_attribute(&xml, "pageSetup", "orientation", "landscape");
+    }
+    xml
+}
+
+/// Decodes the XML entities a sheet name can contain
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The value of `attribute` in a start tag
+fn attribute_value<'a>(tag: &'a str, attribute: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", attribute);
+    let start = tag.find(&key)? + key.len();
+    let length = tag[start..].find('"')?;
+    Some(&tag[start..start + length])
+}
+
+/// Hides every sheet in `xl/workbook.xml` except the named ones (compared ignoring case),
+/// so LibreOffice leaves them out of the PDF
+pub fn select_sheets(workbook_xml: &str, keep: &[String]) -> Result<String, String> {
+    let mut result = String::with_capacity(workbook_xml.len());
+    let mut rest = workbook_xml;
+    let mut kept = 0;
+    let mut names = Vec::new();
+
+    while let Some((start, end)) = find_tag(rest, "sheet") {
+        let tag = &rest[start..end];
+        let name = attribute_value(tag, "name").map(unescape_xml).unwrap_or_default();
+        let wanted = keep.iter().any(|keep| keep.trim().eq_ignore_ascii_case(&name));
+        result.push_str(&rest[..start]);
+        if wanted {
+            kept += 1;
+            result.push_str(&set_attribute(tag, "state", "visible"));
+        } else {
+            result.push_str(&set_attribute(tag, "state", "hidden"));
+        }
+        names.push(name);
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+
+    if kept == 0 {
+        return Err(format!(
+            "None of the selected sheets were found. This workbook has: {}",
+            names.join(", ")
+        ));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> SpreadsheetLayout {
+        SpreadsheetLayout { landscape: true, gridlines: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_patch_updates_existing_elements() {
+        let xml = r#"<worksheet><sheetData/><printOptions horizontalCentered="1"/><pageMargins left="0.7"/><pageSetup paperSize="9" orientation="portrait"/></worksheet>"#;
+        assert_eq!(
+            patch_worksheet(xml, &layout()),
+            r#"<worksheet><sheetData/><printOptions horizontalCentered="1" gridLines="1"/><pageMargins left="0.7"/><pageSetup paperSize="9" orientation="landscape"/></worksheet>"#
+        );
+    }
+
+    #[test]
+    fn test_patch_inserts_in_schema_order() {
+        let xml = r#"<worksheet><sheetData/><pageMargins left="0.7"/><drawing r:id="rId1"/></worksheet>"#;
+        assert_eq!(
+            patch_worksheet(xml, &layout()),
+            r#"<worksheet><sheetData/><printOptions gridLines="1"/><pageMargins left="0.7"/><pageSetup orientation="landscape"/><drawing r:id="rId1"/></worksheet>"#
+        );
+        // Without any trailing elements they go at the end
+        assert_eq!(
+            patch_worksheet("<worksheet><sheetData/></worksheet>", &SpreadsheetLayout { landscape: true, ..Default::default() }),
+            r#"<worksheet><sheetData/><pageSetup orientation="landscape"/></worksheet>"#
+        );
+    }
+
+    #[test]
+    fn test_find_tag_skips_longer_names() {
+        let xml = r#"<sheets><sheetPr/><sheet name="A"/></sheets>"#;
+        let (start, end) = find_tag(xml, "sheet").unwrap();
+        assert_eq!(&xml[start..end], r#"<sheet name="A"/>"#);
+    }
+
+    #[test]
+    fn test_select_sheets() {
+        let xml = r#"<sheets><sheet name="Summary" sheetId="1" r:id="rId1"/><sheet name="R&amp;D" sheetId="2" state="hidden" r:id="rId2"/><sheet name="Raw" sheetId="3" r:id="rId3"/></sheets>"#;
+        let selected = select_sheets(xml, &["summary".to_string(), "R&D".to_string()]).unwrap();
+        assert_eq!(
+            selected,
+            r#"<sheets><sheet name="Summary" sheetId="1" r:id="rId1" state="visible"/><sheet name="R&amp;D" sheetId="2" state="visible" r:id="rId2"/><sheet name="Raw" sheetId="3" r:id="rId3" state="hidden"/></sheets>"#
+        );
+        assert!(select_sheets(xml, &["Missing".to_string()]).unwrap_err().contains("Summary, R&D, Raw"));
+    }
+
+    #[test]
+    fn test_needs_changes() {
+        assert!(!SpreadsheetLayout { fit_to_page: true, ..Default::default() }.needs_workbook_changes());
+        assert!(SpreadsheetLayout { sheets: vec!["A".to_string()], ..Default::default() }.needs_workbook_changes());
+        assert!(!SpreadsheetLayout { sheets: vec!["A".to_string()], ..Default::default() }.needs_worksheet_changes());
+    }
+}
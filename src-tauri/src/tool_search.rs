@@ -0,0 +1,156 @@
+//! Tool search - Where a tool was looked for, and which copy is used
+//!
+//! A tool is looked for in a fixed order: the path set in Settings (or by the machine
+//! config), the copy downloaded to app data, the development tools folder, Homebrew on
+//! macOS, the usual system install locations, and the tools next to the executable. The
+//! first that exists is used. `where_is_tool` returns the whole search, so it's possible to
+//! tell why a tool wasn't found or why an unexpected copy of it runs.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Why a place was checked
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    /// The path the user set in Settings
+    Custom,
+    /// A path set by the machine config
+    Config,
+    /// Downloaded or bundled tools in the app data folder
+    AppData,
+    /// The project's tools folder, when running from a checkout
+    Development,
+    Homebrew,
+    /// Where the tool's own installer or the distribution puts it
+    System,
+    /// Next to the executable
+    Bundled,
+}
+
+impl ToolSource {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ToolSource::Custom => "the custom path set in Settings",
+            ToolSource::Config => "the path set by your administrator",
+            ToolSource::AppData => "the copy installed by ConvertSave",
+            ToolSource::Development => "the development tools folder",
+            ToolSource::Homebrew => "Homebrew",
+            ToolSource::System => "the system installation",
+            ToolSource::Bundled => "the tools shipped with the app",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCandidate {
+    pub path: String,
+    pub source: ToolSource,
+    pub exists: bool,
+    pub selected: bool,
+}
+
+/// Everything checked for one tool, in order
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolSearch {
+    pub tool: String,
+    pub candidates: Vec<ToolCandidate>,
+    pub selected: Option<String>,
+    /// Why `selected` was picked, or that nothing was found
+    pub reason: String,
+}
+
+impl ToolSearch {
+    /// The error for a tool that wasn't found
+    pub fn not_found_message(&self) -> String {
+        format!("Tool not found: {} ({} checked)", self.tool, places(self.candidates.len()))
+    }
+}
+
+fn places(count: usize) -> String {
+    format!("{} place{}", count, if count == 1 { "" } else { "s" })
+}
+
+/// The search over `candidates` (path, source, whether it exists), in the order checked.
+/// A path listed twice is only checked the first time.
+pub fn select(tool: &str, candidates: Vec<(PathBuf, ToolSource, bool)>) -> ToolSearch {
+    let mut checked: Vec<ToolCandidate> = Vec::with_capacity(candidates.len());
+    for (path, source, exists) in candidates {
+        let path = path.display().to_string();
+        if checked.iter().all(|candidate| candidate.path != path) {
+            checked.push(ToolCandidate { path, source, exists, selected: false });
+        }
+    }
+
+    let selected = checked.iter().position(|candidate| candidate.exists);
+    let reason = match selected {
+        Some(index) => {
+            let source = checked[index].source.description();
+            let skipped: Vec<&str> = checked[..index]
+                .iter()
+                .map(|candidate| candidate.source)
+                .filter(|source| matches!(source, ToolSource::Custom | ToolSource::Config))
+                .map(|source| source.description())
+                .collect();
+            match skipped.first() {
+                Some(missing) => format!("Using {}, because {} doesn't exist", source, missing),
+                None if index == 0 => format!("Using {}", source),
+                None => format!("Using {}, the first of the places checked that has it", source),
+            }
+        }
+        None => format!("Not found in {} checked", places(checked.len())),
+    };
+    if let Some(index) = selected {
+        checked[index].selected = true;
+    }
+
+    ToolSearch {
+        tool: tool.to_string(),
+        selected: selected.map(|index| checked[index].path.clone()),
+        candidates: checked,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_first_existing() {
+        let search = select(
+            "ffmpeg",
+            vec![
+                (PathBuf::from("/data/ffmpeg/ffmpeg"), ToolSource::AppData, false),
+                (PathBuf::from("/opt/homebrew/bin/ffmpeg"), ToolSource::Homebrew, true),
+                (PathBuf::from("/usr/local/bin/ffmpeg"), ToolSource::Homebrew, true),
+            ],
+        );
+        assert_eq!(search.selected.as_deref(), Some("/opt/homebrew/bin/ffmpeg"));
+        assert_eq!(search.candidates.iter().filter(|candidate| candidate.selected).count(), 1);
+        assert!(search.candidates[1].selected);
+        assert_eq!(search.reason, "Using Homebrew, the first of the places checked that has it");
+    }
+
+    #[test]
+    fn test_select_missing_custom_path() {
+        let search = select(
+            "magick",
+            vec![
+                (PathBuf::from("/old/magick"), ToolSource::Custom, false),
+                (PathBuf::from("/usr/bin/magick"), ToolSource::System, true),
+                (PathBuf::from("/usr/bin/magick"), ToolSource::System, true),
+            ],
+        );
+        assert_eq!(search.candidates.len(), 2);
+        assert_eq!(search.reason, "Using the system installation, because the custom path set in Settings doesn't exist");
+    }
+
+    #[test]
+    fn test_select_nothing_found() {
+        let search = select("gs", vec![(PathBuf::from("/usr/bin/gs"), ToolSource::System, false)]);
+        assert_eq!(search.selected, None);
+        assert_eq!(search.reason, "Not found in 1 place checked");
+        assert_eq!(search.not_found_message(), "Tool not found: gs (1 place checked)");
+    }
+}
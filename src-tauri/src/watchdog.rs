@@ -0,0 +1,111 @@
+//! Watchdog - Stops tools that hang
+//!
+//! A damaged input can leave ImageMagick or FFmpeg running forever, and with it the
+//! conversion slot. Every tool run can have an overall time limit, and FFmpeg runs that
+//! print their progress stats are also stopped when those stop coming (a stall), which
+//! catches a hang long before any reasonable time limit would.
+
+use std::time::{Duration, Instant};
+
+/// Errors about stopped tools start with this
+pub const TIMEOUT_PREFIX: &str = "Timed out: ";
+
+/// Minutes without progress before FFmpeg counts as stalled, when the settings don't say
+pub const DEFAULT_STALL_MINUTES: u32 = 10;
+
+/// How often a running tool is checked
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Why a tool was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hang {
+    /// It ran longer than the time limit
+    TimedOut(Duration),
+    /// It printed nothing for this long
+    Stalled(Duration),
+}
+
+/// The limits of one tool run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    /// Only for tools that print progress while they work
+    pub stall: Option<Duration>,
+}
+
+impl Limits {
+    /// Limits from the settings' minutes, where 0 means no limit. `reports_progress` is
+    /// whether the tool prints while it works, without which silence means nothing.
+    pub fn from_minutes(timeout_minutes: Option<u32>, stall_minutes: Option<u32>, reports_progress: bool) -> Self {
+        let minutes = |minutes: u32| (minutes > 0).then(|| Duration::from_secs(minutes as u64 * 60));
+        Limits {
+            timeout: timeout_minutes.and_then(minutes),
+            stall: if reports_progress { minutes(stall_minutes.unwrap_or(DEFAULT_STALL_MINUTES)) } else { None },
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.timeout.is_none() && self.stall.is_none()
+    }
+
+    /// Whether a run started at `started` that last printed at `last_output` has hung by `now`
+    pub fn check(&self, started: Instant, last_output: Instant, now: Instant) -> Option<Hang> {
+        if let Some(timeout) = self.timeout.filter(|timeout| now.duration_since(started) >= *timeout) {
+            return Some(Hang::TimedOut(timeout));
+        }
+        let silent = now.duration_since(last_output.max(started));
+        self.stall.filter(|stall| silent >= *stall).map(|_| Hang::Stalled(silent))
+    }
+}
+
+fn minutes(duration: Duration) -> String {
+    match (duration.as_secs() + 30) / 60 {
+        0 => format!("{} seconds", duration.as_secs()),
+        1 => "1 minute".to_string(),
+        n => format!("{} minutes", n),
+    }
+}
+
+/// The error for a tool the watchdog stopped
+pub fn hang_message(tool: &str, hang: Hang) -> String {
+    let reason = match hang {
+        Hang::TimedOut(limit) => format!("{} was stopped after {}, the time limit for a conversion.", tool, minutes(limit)),
+        Hang::Stalled(silent) => format!("{} made no progress for {} and was stopped. The file may be damaged.", tool, minutes(silent)),
+    };
+    format!("{}{}", TIMEOUT_PREFIX, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_minutes() {
+        let limits = Limits::from_minutes(Some(30), None, true);
+        assert_eq!(limits.timeout, Some(Duration::from_secs(1800)));
+        assert_eq!(limits.stall, Some(Duration::from_secs(DEFAULT_STALL_MINUTES as u64 * 60)));
+        assert_eq!(Limits::from_minutes(Some(30), Some(5), false).stall, None);
+        assert!(Limits::from_minutes(Some(0), Some(0), true).is_unlimited());
+    }
+
+    #[test]
+    fn test_check() {
+        let started = Instant::now();
+        let limits = Limits { timeout: Some(Duration::from_secs(600)), stall: Some(Duration::from_secs(60)) };
+        let at = |secs: u64| started + Duration::from_secs(secs);
+
+        assert_eq!(limits.check(started, at(100), at(130)), None);
+        assert_eq!(limits.check(started, at(100), at(170)), Some(Hang::Stalled(Duration::from_secs(70))));
+        assert_eq!(limits.check(started, at(599), at(600)), Some(Hang::TimedOut(Duration::from_secs(600))));
+        // Silence from the start counts too
+        assert_eq!(limits.check(started, started, at(60)), Some(Hang::Stalled(Duration::from_secs(60))));
+        assert_eq!(Limits::default().check(started, started, at(100_000)), None);
+    }
+
+    #[test]
+    fn test_hang_message() {
+        let message = hang_message("ffmpeg", Hang::Stalled(Duration::from_secs(605)));
+        assert_eq!(message, "Timed out: ffmpeg made no progress for 10 minutes and was stopped. The file may be damaged.");
+        assert!(hang_message("imagemagick", Hang::TimedOut(Duration::from_secs(1800))).contains("after 30 minutes"));
+    }
+}
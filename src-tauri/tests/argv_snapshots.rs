@@ -0,0 +1,118 @@
+//! Golden-file tests for the command lines the conversion builders produce
+//!
+//! Each case renders the exact arguments for a representative conversion and compares them
+//! with `tests/snapshots/<case>.txt` (one argument per line). A change that alters any
+//! command line fails here until the snapshot is updated on purpose, with
+//! `UPDATE_SNAPSHOTS=1 cargo test --test argv_snapshots`, and reviewed along with the change.
+
+use convertsave_lib::conversion::{self, ConversionSettings};
+use std::path::PathBuf;
+
+fn snapshot_path(case: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.txt", case))
+}
+
+/// Compares `args` with the snapshot for `case`, or rewrites it when UPDATE_SNAPSHOTS is set
+fn assert_snapshot(case: &str, args: &[String]) {
+    let path = snapshot_path(case);
+    let actual: String = args.iter().map(|arg| format!("{}\n", arg)).collect();
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("No snapshot for {}; run with UPDATE_SNAPSHOTS=1 to record it", case)
+    });
+    // Snapshots checked out on Windows may have CRLF line endings
+    let expected = expected.replace("\r\n", "\n");
+    if expected != actual {
+        panic!(
+            "Arguments for {} changed.\n--- snapshot ({})\n{}--- now\n{}\nRun with UPDATE_SNAPSHOTS=1 if the change is intended.",
+            case,
+            path.display(),
+            expected,
+            actual
+        );
+    }
+}
+
+fn defaults() -> ConversionSettings {
+    ConversionSettings::default()
+}
+
+#[test]
+fn transparent_png_to_jpg_flattens_onto_white() {
+    let args = conversion::imagemagick_still_command("in.png", "out.jpg", "jpg", true, &defaults(), None).args_lossy();
+    assert_snapshot("magick_transparent_png_to_jpg", &args);
+}
+
+#[test]
+fn png_to_ico() {
+    let args = conversion::imagemagick_still_command("in.png", "out.ico", "ico", false, &defaults(), None).args_lossy();
+    assert_snapshot("magick_png_to_ico", &args);
+}
+
+#[test]
+fn jpg_to_heic() {
+    let args = conversion::imagemagick_still_command("in.jpg", "out.heic", "heic", false, &defaults(), None).args_lossy();
+    assert_snapshot("magick_jpg_to_heic", &args);
+}
+
+#[test]
+fn heic_to_jpg() {
+    let args = conversion::imagemagick_still_command("in.heic", "out.jpg", "jpg", false, &defaults(), None).args_lossy();
+    assert_snapshot("magick_heic_to_jpg", &args);
+}
+
+#[test]
+fn png_to_pdf() {
+    let args = conversion::imagemagick_still_command("in.png", "out.pdf", "pdf", false, &defaults(), None).args_lossy();
+    assert_snapshot("magick_png_to_pdf", &args);
+}
+
+#[test]
+fn pdf_first_page_to_png() {
+    let input = conversion::imagemagick_input_spec("in.pdf", Some(0));
+    let args = conversion::imagemagick_still_command(input, "out.png", "png", false, &defaults(), None).args_lossy();
+    assert_snapshot("magick_pdf_page_to_png", &args);
+}
+
+#[test]
+fn pdf_compress() {
+    let args = conversion::pdf_compress_args("in.pdf", "out.pdf", conversion::MAX_COMPRESS_QUALITY);
+    assert_snapshot("ghostscript_pdf_compress", &args);
+}
+
+#[test]
+fn animated_gif_to_webp() {
+    let args = conversion::imagemagick_animation_command("in.gif", "out.webp", "webp", &defaults(), None).args_lossy();
+    assert_snapshot("magick_animated_gif_to_webp", &args);
+}
+
+#[test]
+fn video_to_gif() {
+    assert_snapshot("ffmpeg_animation_to_gif", &conversion::ffmpeg_animation_args("gif", &defaults()));
+}
+
+#[test]
+fn avif_with_alpha() {
+    assert_snapshot("ffmpeg_avif_alpha", &conversion::ffmpeg_avif_args(true));
+}
+
+#[test]
+fn avif_opaque() {
+    assert_snapshot("ffmpeg_avif_opaque", &conversion::ffmpeg_avif_args(false));
+}
+
+#[test]
+fn mogrify_batch_to_webp() {
+    let files = ["a.png".to_string(), "b.png".to_string()];
+    let args = conversion::mogrify_args("out", "webp", &defaults(), None, &files);
+    assert_snapshot("magick_mogrify_to_webp", &args);
+}
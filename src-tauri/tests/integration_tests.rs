@@ -6,12 +6,45 @@
 use std::path::PathBuf;
 use std::fs;
 use std::process::Command;
+use std::sync::Once;
 
-/// Get the test fixtures directory
+static GENERATE_FIXTURES: Once = Once::new();
+
+/// Get the test fixtures directory, generating any missing fixtures on first use
 fn get_fixtures_dir() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("tests")
-        .join("fixtures")
+        .join("fixtures");
+    GENERATE_FIXTURES.call_once(|| generate_missing_fixtures(&dir));
+    dir
+}
+
+/// Synthesize fixtures that aren't on disk with whichever tools are installed
+fn generate_missing_fixtures(dir: &std::path::Path) {
+    let installed: Vec<&str> = ["imagemagick", "ffmpeg"]
+        .into_iter()
+        .filter(|tool| tool_available(tool))
+        .collect();
+    let report = convertsave_lib::fixtures::generate(dir, &installed, false, |tool, args| {
+        let exe = match (tool, cfg!(target_os = "windows")) {
+            ("imagemagick", true) => "magick.exe",
+            ("imagemagick", false) => "magick",
+            (_, true) => "ffmpeg.exe",
+            (_, false) => "ffmpeg",
+        };
+        let output = Command::new(exe).args(args).output().map_err(|e| e.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    });
+    if !report.created.is_empty() {
+        eprintln!("Generated {} missing fixtures", report.created.len());
+    }
+    for failure in &report.failed {
+        eprintln!("Could not generate fixture {}", failure);
+    }
 }
 
 /// Get the test output directory (created if doesn't exist)
@@ -373,7 +406,7 @@ mod ffmpeg_audio_conversion_tests {
     fn test_wav_to_mp3() {
         if skip_if_no_ffmpeg() { return; }
         let Some(input) = get_wav_fixture() else { 
-            eprintln!("Skipping - wav fixture not found. Run: npm run test:fixtures");
+            eprintln!("Skipping - wav fixture not found (FFmpeg is needed to generate it)");
             return; 
         };
         let output = get_output_dir().join("test_wav_to_mp3.mp3");
@@ -1357,7 +1390,7 @@ mod fixture_tests {
     fn test_audio_fixtures_directory() {
         let audio = get_fixtures_dir().join("audio");
         if !audio.exists() {
-            eprintln!("Note: Audio fixtures directory not found. Run: npm run test:fixtures");
+            eprintln!("Note: Audio fixtures directory not found (FFmpeg is needed to generate it)");
         }
     }
 
@@ -1408,7 +1441,7 @@ mod fixture_tests {
             let metadata = fs::metadata(&sample).unwrap();
             assert!(metadata.len() > 0, "sample.mp3 should not be empty");
         } else {
-            eprintln!("Note: sample.mp3 not found. Run: npm run test:fixtures");
+            eprintln!("Note: sample.mp3 not found (FFmpeg is needed to generate it)");
         }
     }
 
@@ -6,7 +6,8 @@ import { X } from "lucide-react";
 import ToolDownloader from "./components/ToolDownloader";
 import LicenseActivation from "./components/LicenseActivation";
 import { CustomSelect } from "./components/CustomSelect";
-import { FileInfo } from "./types";
+import { AppState, ConversionResult, FileInfo } from "./types";
+import { getFileExtension } from "./lib/utils";
 
 // License status type from Rust
 interface LicenseStatus {
@@ -48,7 +49,7 @@ function FileItem({
     "heif",
     "avif",
   ];
-  const isImage = imageExtensions.includes(file.extension.toLowerCase());
+  const isImage = imageExtensions.includes((file.format ?? file.extension).toLowerCase());
 
   useEffect(() => {
     if (!isImage) {
@@ -180,25 +181,38 @@ function App() {
   const [updateAvailable, setUpdateAvailable] = useState(false);
   const isProcessingDrop = useRef(false);
 
-  // Check license on startup
+  // Restore license and tool status on startup in one round-trip
   useEffect(() => {
+    const licenseCheckFailed: LicenseStatus = {
+      isValid: false,
+      isActivated: false,
+      planType: null,
+      daysRemaining: null,
+      inGracePeriod: false,
+      error: "Failed to check license status",
+      requiresActivation: true,
+      productKey: null,
+    };
+
     const checkLicense = async () => {
       try {
-        const status = await invoke<LicenseStatus>("check_license_status");
-        setLicenseStatus(status);
+        const state = await invoke<AppState<LicenseStatus, ToolStatus>>(
+          "get_app_state"
+        );
+        // Assume needs activation if the license check failed
+        setLicenseStatus(state.license ?? licenseCheckFailed);
+        if (state.tools) {
+          setToolsReady(
+            state.tools.ffmpeg.available || state.tools.imagemagick.available
+          );
+        } else {
+          checkToolsStatus();
+        }
       } catch (err) {
         console.error("Failed to check license:", err);
         // Assume needs activation if check fails
-        setLicenseStatus({
-          isValid: false,
-          isActivated: false,
-          planType: null,
-          daysRemaining: null,
-          inGracePeriod: false,
-          error: "Failed to check license status",
-          requiresActivation: true,
-          productKey: null,
-        });
+        setLicenseStatus(licenseCheckFailed);
+        checkToolsStatus();
       } finally {
         setLicenseChecked(true);
       }
@@ -237,9 +251,6 @@ function App() {
     // }
     // ========== END PLATFORM DETECTION ==========
 
-    // Check if tools are ready
-    checkToolsStatus();
-
     // Set up Tauri file drop listener using window API
     let unlisten: (() => void) | undefined;
 
@@ -269,6 +280,9 @@ function App() {
                 name: string;
                 size: number;
                 extension: string;
+                format: string;
+                detected_format: string | null;
+                format_mismatch: boolean;
               };
 
               console.log("Got file stats:", stats);
@@ -278,13 +292,16 @@ function App() {
                 path: filePath,
                 size: stats.size,
                 extension: stats.extension,
+                format: stats.format,
+                detected_format: stats.detected_format,
+                format_mismatch: stats.format_mismatch,
               };
             } catch (error) {
               console.error("Error getting file info:", error);
               // Fallback if we can't get file info
               const fileName =
                 String(filePath).split(/[\\/]/).pop() || "Unknown";
-              const extension = (fileName.split(".").pop() || "").toLowerCase();
+              const extension = getFileExtension(fileName);
 
               return {
                 name: fileName,
@@ -371,9 +388,13 @@ function App() {
       }
 
       try {
-        // Get unique extensions from selected files
+        // Get unique formats from selected files (by contents, for files without an extension)
         const uniqueExtensions = Array.from(
-          new Set(selectedFiles.map((file) => file.extension.toLowerCase()))
+          new Set(
+            selectedFiles.map((file) =>
+              (file.format ?? file.extension).toLowerCase()
+            )
+          )
         );
 
         // Get available formats for each unique extension
@@ -497,7 +518,7 @@ function App() {
           "psb",
         ];
         const imageFiles = selectedFiles.filter((f) =>
-          imageExtensions.includes(f.extension.toLowerCase())
+          imageExtensions.includes((f.format ?? f.extension).toLowerCase())
         );
 
         // Add pdf-multipage option if there are multiple image files and PDF is available
@@ -620,7 +641,7 @@ function App() {
         const file = selectedFiles[i];
 
         try {
-          await invoke<string>("convert_file", {
+          await invoke<ConversionResult>("convert_file", {
             inputPath: file.path,
             outputFormat: selectedFormat,
             outputDirectory: outputDirectory || undefined,
@@ -776,6 +797,9 @@ function App() {
                 name: string;
                 size: number;
                 extension: string;
+                format: string;
+                detected_format: string | null;
+                format_mismatch: boolean;
               };
 
               return {
@@ -783,12 +807,15 @@ function App() {
                 path: filePath,
                 size: stats.size,
                 extension: stats.extension,
+                format: stats.format,
+                detected_format: stats.detected_format,
+                format_mismatch: stats.format_mismatch,
               };
             } catch (error) {
               // Fallback if we can't get file info
               const fileName =
                 String(filePath).split(/[\\/]/).pop() || "Unknown";
-              const extension = (fileName.split(".").pop() || "").toLowerCase();
+              const extension = getFileExtension(fileName);
 
               return {
                 name: fileName,
@@ -816,6 +843,9 @@ function App() {
             name: string;
             size: number;
             extension: string;
+            format: string;
+            detected_format: string | null;
+            format_mismatch: boolean;
           };
 
           const fileInfo: FileInfo = {
@@ -823,6 +853,9 @@ function App() {
             path: selected,
             size: stats.size,
             extension: stats.extension,
+            format: stats.format,
+            detected_format: stats.detected_format,
+            format_mismatch: stats.format_mismatch,
           };
 
           setSelectedFiles((prev) => {
@@ -9,61 +9,23 @@ interface ConversionOptionsProps {
   onFormatSelect: (format: string) => void;
 }
 
-const colorMap: Record<string, string> = {
-  // Standard formats
-  pdf: "bg-pink",
-  mp4: "bg-aquamarine",
-  mp3: "bg-yellow",
-  jpg: "bg-yellow",
-  jpeg: "bg-yellow",
-  png: "bg-orange",
-  txt: "bg-light-tan",
-  docx: "bg-pink",
-  epub: "bg-aquamarine",
-  webp: "bg-green",
-  wav: "bg-tan",
-  gif: "bg-blue",
-  html: "bg-light-tan",
-  bmp: "bg-light-purple",
-  tiff: "bg-light-tan",
-
-  // Modern formats
-  heic: "bg-aquamarine",
-  heif: "bg-aquamarine",
-  avif: "bg-aquamarine",
-
-  // Professional/High-end formats
-  tga: "bg-pink",
-  exr: "bg-aquamarine",
-  hdr: "bg-aquamarine",
-  dpx: "bg-pink",
-  pfm: "bg-aquamarine",
-
-  // JPEG 2000
-  j2k: "bg-yellow",
-  jp2: "bg-yellow",
-
-  // Legacy/Specialized formats
-  pcx: "bg-light-purple",
-  ico: "bg-blue",
-  sgi: "bg-green",
-  sun: "bg-orange",
-
-  // Raw/Uncompressed formats
-  ppm: "bg-light-tan",
-  pgm: "bg-light-tan",
-  pbm: "bg-light-tan",
-  pam: "bg-light-tan",
-
-  // X Window System formats
-  xbm: "bg-light-purple",
-  xpm: "bg-light-purple",
-  xwd: "bg-light-purple",
-
-  // Gaming/3D formats
-  dds: "bg-blue",
+// Color categories come from the backend (get_format_color); the class names are spelled
+// out so Tailwind picks them up
+const colorClasses: Record<string, string> = {
+  blue: "bg-blue-accent",
+  green: "bg-mint-accent",
+  orange: "bg-pink-accent",
+  pink: "bg-pink",
+  aquamarine: "bg-aquamarine",
+  yellow: "bg-yellow",
+  "light-tan": "bg-light-tan",
+  "light-purple": "bg-light-purple",
+  lavender: "bg-lighter-bg",
 };
 
+const formatColorClass = (option: ConversionOption) =>
+  colorClasses[option.color] || "bg-tan";
+
 export default function ConversionOptions({
   inputFile,
   selectedFormat,
@@ -87,7 +49,8 @@ export default function ConversionOptions({
         const formats = await invoke<ConversionOption[]>(
           "get_available_formats",
           {
-            inputExtension: inputFile.extension,
+            inputExtension: inputFile.format ?? inputFile.extension,
+            inputPath: inputFile.path,
           }
         );
         setAvailableFormats(formats);
@@ -141,7 +104,7 @@ export default function ConversionOptions({
     (f) => f.format === selectedFormat
   );
   const selectedBgColor = selectedFormatData
-    ? colorMap[selectedFormatData.format] || "bg-tan"
+    ? formatColorClass(selectedFormatData)
     : "bg-light-grey";
 
   const handleFormatSelect = (format: string) => {
@@ -188,7 +151,7 @@ export default function ConversionOptions({
     return (
       <div className="text-center py-8">
         <p className="text-secondary">
-          No conversion options available for .{inputFile.extension} files
+          No conversion options available for .{inputFile.format ?? inputFile.extension} files
         </p>
       </div>
     );
@@ -242,7 +205,7 @@ export default function ConversionOptions({
               <div className="grid grid-cols-3 sm:grid-cols-4 lg:grid-cols-5 xl:grid-cols-6 gap-2">
                 {availableFormats.map((format) => {
                   const isSelected = selectedFormat === format.format;
-                  const bgColor = colorMap[format.format] || "bg-tan";
+                  const bgColor = formatColorClass(format);
 
                   return (
                     <button
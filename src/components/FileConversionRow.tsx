@@ -41,7 +41,7 @@ export default function FileConversionRow({
     "heif",
     "avif",
   ];
-  const isImage = imageExtensions.includes(file.extension.toLowerCase());
+  const isImage = imageExtensions.includes((file.format ?? file.extension).toLowerCase());
 
   useEffect(() => {
     if (!isImage) {
@@ -44,6 +44,16 @@ interface ToolStatus {
     available: boolean;
     path: string | null;
   };
+  // Optional - only needed for office documents (DOCX, XLSX, PPTX...)
+  libreoffice?: {
+    available: boolean;
+    path: string | null;
+  };
+  // Optional - lets ImageMagick read PDF/EPS and powers PDF compression
+  ghostscript?: {
+    available: boolean;
+    path: string | null;
+  };
 }
 
 interface DownloadProgress {
@@ -72,6 +82,8 @@ export default function ToolDownloader({
   const [successMessage, setSuccessMessage] = useState<string | null>(null);
   const [ffmpegAdvancedOpen, setFfmpegAdvancedOpen] = useState(false);
   const [imagemagickAdvancedOpen, setImagemagickAdvancedOpen] = useState(false);
+  const [libreofficeAdvancedOpen, setLibreofficeAdvancedOpen] = useState(false);
+  const [ghostscriptAdvancedOpen, setGhostscriptAdvancedOpen] = useState(false);
   const [licenseAttributionOpen, setLicenseAttributionOpen] = useState(false);
   const [supportOpen, setSupportOpen] = useState(false);
   const [showLicensesModal, setShowLicensesModal] = useState(false);
@@ -166,6 +178,10 @@ export default function ToolDownloader({
         //   await invoke("download_pandoc");
       } else if (toolName === "imagemagick") {
         await invoke("download_imagemagick");
+      } else if (toolName === "libreoffice") {
+        await invoke("download_libreoffice");
+      } else if (toolName === "ghostscript") {
+        await invoke("download_ghostscript");
       }
       // Note: Success is handled by the download-progress event listener
     } catch (err) {
@@ -887,6 +903,224 @@ export default function ToolDownloader({
                     </div>
                   </div>
                 </div>
+
+                {/* LibreOffice Card (optional) */}
+                <div className="bg-white border-2 border-dark-purple rounded-xl p-6">
+                  <div className="flex items-center justify-between mb-4">
+                    <div className="flex-1">
+                      <div className="flex items-center space-x-3 flex-wrap mb-2">
+                        <h3 className="text-xl font-bold text-dark-purple">
+                          LibreOffice
+                        </h3>
+                        {toolStatus.libreoffice?.available ? (
+                          <div className="flex items-center space-x-1 bg-mint-accent text-dark-purple px-3 py-1 rounded-full text-sm font-bold">
+                            <Check className="w-4 h-4" />
+                            <span>Ready</span>
+                          </div>
+                        ) : (
+                          <div className="flex items-center space-x-1 bg-pink-accent text-dark-purple px-3 py-1 rounded-full text-sm font-bold">
+                            <X className="w-4 h-4" />
+                            <span>Not Found</span>
+                          </div>
+                        )}
+                      </div>
+                      <p className="text-secondary">
+                        Optional. Needed for Word, Excel and PowerPoint documents.
+                      </p>
+                    </div>
+                    {!toolStatus.libreoffice?.available && (
+                      <button
+                        onClick={() => downloadTool("libreoffice")}
+                        disabled={downloadingTools.has("libreoffice")}
+                        className="btn-chunky bg-mint-accent border-2 border-dark-purple text-dark-purple px-6 py-3 flex items-center space-x-2"
+                      >
+                        {downloadingTools.has("libreoffice") ? (
+                          <>
+                            <Loader className="w-5 h-5 animate-spin" />
+                            <span>Install</span>
+                          </>
+                        ) : (
+                          <span>Install</span>
+                        )}
+                      </button>
+                    )}
+                  </div>
+
+                  {/* Advanced Section */}
+                  <button
+                    onClick={() =>
+                      setLibreofficeAdvancedOpen(!libreofficeAdvancedOpen)
+                    }
+                    className="flex items-center space-x-2 text-dark-purple font-bold hover:text-secondary transition-colors"
+                  >
+                    <span>Advanced</span>
+                    <ChevronDown
+                      className={`w-4 h-4 transition-transform duration-300 ${
+                        libreofficeAdvancedOpen ? "rotate-180" : ""
+                      }`}
+                    />
+                  </button>
+
+                  {/* Animated Advanced Content */}
+                  <div
+                    className={`grid transition-all duration-300 ease-in-out ${
+                      libreofficeAdvancedOpen
+                        ? "grid-rows-[1fr] opacity-100"
+                        : "grid-rows-[0fr] opacity-0"
+                    }`}
+                  >
+                    <div className="overflow-hidden">
+                      <div className="pt-4 space-y-3">
+                        <p className="text-sm text-secondary">
+                          You can also install{" "}
+                          <a
+                            href="#"
+                            onClick={async (e) => {
+                              e.preventDefault();
+                              try {
+                                await openUrl("https://www.libreoffice.org/download/");
+                              } catch (err) {
+                                setError(`Failed to open link: ${err}`);
+                              }
+                            }}
+                            className="text-blue-accent underline hover:text-dark-purple"
+                          >
+                            LibreOffice
+                          </a>{" "}
+                          directly and select a custom path to soffice.
+                        </p>
+                        <div className="flex space-x-2">
+                          <button
+                            onClick={() => selectCustomPath("libreoffice")}
+                            className="btn-chunky bg-white border-2 border-dark-purple text-dark-purple px-4 py-2 text-sm hover:bg-light-bg"
+                          >
+                            Select Custom Path
+                          </button>
+                          <button
+                            onClick={() => useDefaultPath("libreoffice")}
+                            className="btn-chunky bg-white border-2 border-dark-purple text-dark-purple px-4 py-2 text-sm hover:bg-light-bg"
+                          >
+                            Use Default Path
+                          </button>
+                        </div>
+                        {toolStatus.libreoffice?.path && (
+                          <p className="text-xs text-secondary font-mono mt-2 break-all">
+                            {toolStatus.libreoffice.path}
+                          </p>
+                        )}
+                      </div>
+                    </div>
+                  </div>
+                </div>
+
+                {/* Ghostscript Card (optional) */}
+                <div className="bg-white border-2 border-dark-purple rounded-xl p-6">
+                  <div className="flex items-center justify-between mb-4">
+                    <div className="flex-1">
+                      <div className="flex items-center space-x-3 flex-wrap mb-2">
+                        <h3 className="text-xl font-bold text-dark-purple">
+                          Ghostscript
+                        </h3>
+                        {toolStatus.ghostscript?.available ? (
+                          <div className="flex items-center space-x-1 bg-mint-accent text-dark-purple px-3 py-1 rounded-full text-sm font-bold">
+                            <Check className="w-4 h-4" />
+                            <span>Ready</span>
+                          </div>
+                        ) : (
+                          <div className="flex items-center space-x-1 bg-pink-accent text-dark-purple px-3 py-1 rounded-full text-sm font-bold">
+                            <X className="w-4 h-4" />
+                            <span>Not Found</span>
+                          </div>
+                        )}
+                      </div>
+                      <p className="text-secondary">
+                        Optional. Needed to read PDF and EPS files and to compress PDFs.
+                      </p>
+                    </div>
+                    {!toolStatus.ghostscript?.available && (
+                      <button
+                        onClick={() => downloadTool("ghostscript")}
+                        disabled={downloadingTools.has("ghostscript")}
+                        className="btn-chunky bg-mint-accent border-2 border-dark-purple text-dark-purple px-6 py-3 flex items-center space-x-2"
+                      >
+                        {downloadingTools.has("ghostscript") ? (
+                          <>
+                            <Loader className="w-5 h-5 animate-spin" />
+                            <span>Install</span>
+                          </>
+                        ) : (
+                          <span>Install</span>
+                        )}
+                      </button>
+                    )}
+                  </div>
+
+                  {/* Advanced Section */}
+                  <button
+                    onClick={() =>
+                      setGhostscriptAdvancedOpen(!ghostscriptAdvancedOpen)
+                    }
+                    className="flex items-center space-x-2 text-dark-purple font-bold hover:text-secondary transition-colors"
+                  >
+                    <span>Advanced</span>
+                    <ChevronDown
+                      className={`w-4 h-4 transition-transform duration-300 ${
+                        ghostscriptAdvancedOpen ? "rotate-180" : ""
+                      }`}
+                    />
+                  </button>
+
+                  {/* Animated Advanced Content */}
+                  <div
+                    className={`grid transition-all duration-300 ease-in-out ${
+                      ghostscriptAdvancedOpen
+                        ? "grid-rows-[1fr] opacity-100"
+                        : "grid-rows-[0fr] opacity-0"
+                    }`}
+                  >
+                    <div className="overflow-hidden">
+                      <div className="pt-4 space-y-3">
+                        <p className="text-sm text-secondary">
+                          You can also install{" "}
+                          <a
+                            href="#"
+                            onClick={async (e) => {
+                              e.preventDefault();
+                              try {
+                                await openUrl("https://ghostscript.com/releases/gsdnld.html");
+                              } catch (err) {
+                                setError(`Failed to open link: ${err}`);
+                              }
+                            }}
+                            className="text-blue-accent underline hover:text-dark-purple"
+                          >
+                            Ghostscript
+                          </a>{" "}
+                          directly and select a custom path to gs (gswin64c.exe on Windows).
+                        </p>
+                        <div className="flex space-x-2">
+                          <button
+                            onClick={() => selectCustomPath("ghostscript")}
+                            className="btn-chunky bg-white border-2 border-dark-purple text-dark-purple px-4 py-2 text-sm hover:bg-light-bg"
+                          >
+                            Select Custom Path
+                          </button>
+                          <button
+                            onClick={() => useDefaultPath("ghostscript")}
+                            className="btn-chunky bg-white border-2 border-dark-purple text-dark-purple px-4 py-2 text-sm hover:bg-light-bg"
+                          >
+                            Use Default Path
+                          </button>
+                        </div>
+                        {toolStatus.ghostscript?.path && (
+                          <p className="text-xs text-secondary font-mono mt-2 break-all">
+                            {toolStatus.ghostscript.path}
+                          </p>
+                        )}
+                      </div>
+                    </div>
+                  </div>
+                </div>
               </div>
 
               {/* Continue Button */}
@@ -3,6 +3,10 @@ export interface FileInfo {
   path: string;
   size: number;
   extension: string;
+  /** From get_file_info: what to convert the file as, by its contents when they don't match the extension */
+  format?: string;
+  detected_format?: string | null;
+  format_mismatch?: boolean;
   selectedFormat?: string;
 }
 
@@ -18,6 +22,421 @@ export interface ConversionOption {
   tool: string;
   display_name: string;
   color: string;
+  /** Output format's category in the format registry, e.g. "video" or "ebook" */
+  category: string;
+}
+
+export interface MatrixOutput extends ConversionOption {
+  /** The tool is installed, or the conversion doesn't need one */
+  available: boolean;
+}
+
+export interface MatrixInput {
+  format: string;
+  display_name: string;
+  color: string;
+  outputs: MatrixOutput[];
+}
+
+export interface MatrixCategory {
+  category: string;
+  inputs: MatrixInput[];
+}
+
+export interface ConversionMatrix {
+  categories: MatrixCategory[];
+  /** Each external tool the matrix needs, and whether it's installed */
+  tools: Record<string, boolean>;
+}
+
+export interface PlanTarget extends ConversionOption {
+  /** How many files can be converted to it, counting those already in it */
+  files: number;
+  /** How many of the files are already in it; they're skipped */
+  already: number;
+  tools: string[];
+}
+
+export interface PlannedFile {
+  input_path: string;
+  format: string;
+  /** Tool per output format */
+  tools: Record<string, string>;
+  /** Target formats it's already in, so converting to them skips it */
+  already_in: string[];
+}
+
+export interface BatchPlan {
+  common_formats: PlanTarget[];
+  partial_formats: PlanTarget[];
+  files: PlannedFile[];
+  unsupported: string[];
+}
+
+export interface FormatDetails {
+  format: string;
+  display_name: string;
+  color: string;
+}
+
+export interface ConversionResult {
+  output_path: string;
+  elapsed_ms: number;
+  input_size: number;
+  output_size: number;
+  compression_ratio: number; // output size / input size
+  command_line: string | null;
+  tool: string | null; // e.g. "ffmpeg", "imagemagick", "builtin"
+  warnings: string[]; // e.g. transparency filled with white
+  fallbacks: string[]; // tools used because the preferred one isn't installed
+  tool_warnings: ToolWarning[]; // printed by the tool during a conversion that still succeeded
+  sha256: string | null; // when the checksum setting is on
+}
+
+export interface ToolWarning {
+  component: string | null; // e.g. "mp4" from "[mp4 @ 0x7f...]"
+  message: string;
+  count: number;
+}
+
+export interface BatchItemResult {
+  input_path: string;
+  result: ConversionResult | null;
+  error: string | null;
+  error_summary: ErrorSummary | null;
+}
+
+// One format of convert_to_formats
+export interface FormatItemResult {
+  format: string;
+  result: ConversionResult | null;
+  error: string | null;
+  error_summary: ErrorSummary | null;
+}
+
+// From summarize_error, and with each failed batch item
+export interface ErrorSummary {
+  kind:
+    | 'file_in_use'
+    | 'input_missing'
+    | 'tool_missing'
+    | 'tool_failed_to_start'
+    | 'format_unsupported'
+    | 'codec_unavailable'
+    | 'no_audio_stream'
+    | 'output_not_writable'
+    | 'timed_out'
+    | 'conversion_failed'
+    | 'other'; // stable, for translated messages
+  summary: string; // one sentence, at most 120 characters
+  detail: string; // the full error
+  log_path: string | null;
+}
+
+export interface BatchConversionResult {
+  items: BatchItemResult[]; // in input order
+  workers: number;
+  elapsed_ms: number;
+}
+
+export interface ArchiveConversionResult {
+  batch: BatchConversionResult; // output paths are entry names when repackaged
+  archive_path: string | null;
+  output_directory: string | null;
+  skipped: string[]; // entries that aren't images
+}
+
+export interface ArchiveProgress {
+  stage: 'extracting' | 'packing';
+  percent: number;
+}
+
+export interface ConcatProgress {
+  segment: number; // 1-based clip being written
+  total: number;
+  input_path: string;
+  stream_copy: boolean;
+}
+
+// Emitted as "merge-progress" while merge_documents converts each document
+export interface MergeProgress {
+  current: number; // 1-based
+  total: number;
+  input_path: string;
+}
+
+// Emitted as "tool-output" for each line a conversion tool prints
+export interface ToolOutputLine {
+  output_path: string;
+  tool: string;
+  stream: 'stdout' | 'stderr';
+  line: string;
+  progress: boolean; // FFmpeg stats line; replaces the previous one
+}
+
+// From get_feature_flags / set_feature_flag
+export interface FeatureFlag {
+  name: string; // e.g. "libvips"
+  description: string;
+  enabled: boolean;
+  source: 'default' | 'remote' | 'user'; // the user's setting wins over the remote manifest
+}
+
+// One run of a photo folder rule, from ingest_photos and the "ingest-report" event
+export interface IngestReport {
+  rule_id: string;
+  started_at: string;
+  finished_at: string;
+  converted: number;
+  auto_rotated: number; // turned upright from their EXIF orientation
+  duplicates: { input_path: string; duplicate_of: string | null }[]; // null: ingested in an earlier run
+  failures: { input_path: string; reason: string }[];
+  outputs: string[];
+}
+
+// Emitted as "network-retry" before a download, version check or license call is retried
+export interface RetryEvent {
+  operation: string; // e.g. "Downloading FFmpeg"
+  attempt: number; // the attempt about to start
+  attempts: number;
+  delay_ms: number;
+  error: string;
+}
+
+export interface FrameExtractionResult {
+  output_directory: string; // new folder holding the frames
+  frames: string[]; // frame_00001.png, ... in video order
+  skipped_timestamps: number[]; // requested times past the end of the video
+}
+
+export interface DocumentPreview {
+  preview_path: string; // temporary file, replaced when the document is previewed again
+  preview_format: 'pdf' | 'html';
+  tool: string;
+  pages: number; // pages (PDF) or top-level sections (HTML) included
+}
+
+export interface BatchEstimate {
+  total_ms: number; // wall-clock time for the whole batch
+  file_ms: number[]; // in input order
+  workers: number;
+  measured_files: number; // estimated from earlier conversions; the rest use rough defaults
+}
+
+export interface FormatSummary {
+  format: string; // lowercase extension, "" for files without one
+  count: number;
+  total_bytes: number;
+}
+
+export interface TargetSavings {
+  format: string;
+  file_count: number; // files that usually shrink when converted to this format
+  current_bytes: number;
+  projected_bytes: number;
+  saved_bytes: number;
+}
+
+export interface FolderReport {
+  folder: string;
+  file_count: number;
+  total_bytes: number;
+  formats: FormatSummary[]; // largest total first
+  savings: TargetSavings[]; // biggest saving first
+  unsupported: string[]; // paths no conversion accepts
+  unsupported_bytes: number;
+}
+
+// Background jobs (e.g. watch folders) wait while user-started jobs are queued
+export type JobPriority = 'foreground' | 'background';
+
+// LibreOffice PDF export options; unset fields keep LibreOffice's defaults
+export interface PdfExportOptions {
+  embed_standard_fonts?: boolean;
+  pdf_a?: 1 | 2 | 3 | null; // archival PDF/A part (can't be combined with a password)
+  lossless_images?: boolean;
+  password?: string | null;
+}
+
+// Spreadsheet -> PDF page layout. Orientation, gridlines and sheet selection need XLSX input.
+export interface SpreadsheetLayout {
+  fit_to_page?: boolean; // each sheet on a single page
+  landscape?: boolean;
+  gridlines?: boolean;
+  sheets?: string[]; // sheet names to include; empty = all visible sheets
+}
+
+// External SRT/ASS file added to a video: 'mux' as a track, 'burn' into the picture
+export interface ExternalSubtitles {
+  path: string;
+  mode: 'mux' | 'burn';
+}
+
+// One stream of a media file, from get_streams
+// Part of the picture to keep; width and height (pixels of the upright picture) win over aspect
+export interface CropOptions {
+  x?: number;
+  y?: number;
+  width?: number | null;
+  height?: number | null;
+  aspect?: string | null; // e.g. "16:9", "1:1", "4:5", "9:16"
+}
+
+export type WatermarkPosition =
+  | 'top-left' | 'top' | 'top-right'
+  | 'left' | 'center' | 'right'
+  | 'bottom-left' | 'bottom' | 'bottom-right';
+
+// Saved with presets as part of ConversionSettings.watermark; give an image or text, not both
+export interface Watermark {
+  image_path?: string | null;
+  text?: string | null;
+  position?: WatermarkPosition; // default 'bottom-right'
+  opacity?: number; // 0-1, default 0.5
+  scale?: number | null; // share of the frame: image width (default 0.2) or text height (default 0.05)
+}
+
+// ConversionSettings.color
+export interface ColorOptions {
+  convert_to_srgb?: boolean; // from the embedded profile (Adobe RGB, ProPhoto, CMYK); needs ImageMagick
+  icc_profile?: 'embed' | 'strip'; // default 'embed'
+  keep_16_bit?: boolean; // TIFF, PNG and EXR outputs
+}
+
+// ConversionSettings.raw, for camera RAW inputs; exposure needs LibRaw (dcraw_emu) installed
+export interface RawOptions {
+  white_balance?: 'camera' | 'auto'; // default 'camera' (as shot)
+  exposure?: number; // stops, -2 to +3
+  bit_depth?: 8 | 16; // default 8; 16 is kept for TIFF, PNG and EXR
+  fast_demosaic?: boolean; // bilinear instead of AHD
+}
+
+export interface StreamInfo {
+  index: number; // absolute stream index, as used by stream_selection
+  kind: 'video' | 'audio' | 'subtitle' | 'other';
+  codec: string;
+  language?: string | null;
+  title?: string | null;
+  channels?: number | null;
+  default: boolean;
+  attached_picture: boolean; // cover art stored as a video stream
+}
+
+// From generate_responsive_set, also saved as manifest.json in the set's folder
+export interface ResponsiveImage {
+  file: string;
+  format: string;
+  width: number;
+  height: number | null;
+  size: number;
+}
+
+export interface ResponsiveSet {
+  output_directory: string;
+  images: ResponsiveImage[];
+  sources: { format: string; mime_type: string; srcset: string }[];
+  html: string; // <picture> element to paste
+  warnings: string[];
+}
+
+// From get_input_states: whether a file can be converted now
+export type InputState = 'ready' | 'downloading' | 'growing' | 'locked';
+
+// From get_chapters: embedded chapter markers, or those of a .cue sheet next to the file
+export interface Chapter {
+  start_secs: number;
+  end_secs: number | null;
+  title: string | null;
+}
+
+// Audio and subtitle streams to keep, by StreamInfo.index. The first audio track becomes the default.
+export interface StreamSelection {
+  audio?: number[];
+  subtitles?: number[];
+}
+
+// 'portable' makes output names safe for USB drives and network shares; 'ascii' also transliterates
+export type FileNameMode = 'native' | 'portable' | 'ascii';
+
+export interface QueueStatus {
+  capacity: number; // conversions that run at once
+  running: number;
+  waiting_foreground: number;
+  waiting_background: number;
+}
+
+// Also emitted as "after-queue-action" when the machine is about to sleep or shut down
+export type AfterQueue = "nothing" | "sleep" | "shutdown";
+
+export interface PowerSettings {
+  prevent_sleep: boolean; // keep the machine awake while conversions are queued
+  after_queue: AfterQueue; // this session only; back to "nothing" once it has run
+}
+
+// get_kiosk_mode(): when true, tool downloads, custom tool paths, settings and license
+// changes are refused, so hide their controls; conversions still work.
+// get_locked_settings(): config keys fixed by the machine-wide config (dotted for nested
+// keys, e.g. "feature_flags.plugins"); show them as set by the administrator.
+
+export type JobLabel =
+  | "red"
+  | "orange"
+  | "yellow"
+  | "green"
+  | "blue"
+  | "purple"
+  | "gray";
+
+export interface HistoryEntry {
+  id: string;
+  created_at: string;
+  input_path: string;
+  output_path: string | null;
+  output_format: string;
+  status: "queued" | "completed" | "failed";
+  error: string | null;
+  note: string | null;
+  label: JobLabel | null;
+}
+
+// Returned by get_app_state at startup; license and tools keep the shapes of
+// check_license_status and check_tools_status
+export interface AppState<License, Tools> {
+  app_version: string;
+  queue: QueueStatus;
+  tools: Tools | null; // null if the tool check failed
+  license: License | null; // null if the license couldn't be checked
+  recent_history: HistoryEntry[];
+  interrupted_batches: InterruptedBatch[]; // offer resume_batch(id) or dismiss_interrupted_batch(id)
+}
+
+export interface InterruptedBatch {
+  id: string;
+  started_at: string;
+  output_format: string;
+  total: number;
+  remaining: number; // files it didn't get to
+  needs_pdf_password: boolean; // pass pdf_password to resume_batch, it isn't journaled
+}
+
+export interface BatchProgress {
+  completed: number;
+  total: number;
+  input_path: string;
+  success: boolean;
+}
+
+export type UnsupportedReason =
+  | "tool_not_installed"
+  | "feature_disabled"
+  | "license_required"
+  | "not_supported";
+
+export interface UnsupportedExplanation {
+  supported: boolean;
+  tool: string | null;
+  reasons: UnsupportedReason[];
+  message: string;
 }
 
 export interface BatchConversionSettings {
@@ -26,3 +445,20 @@ export interface BatchConversionSettings {
     isMixed: boolean; // true when files of same type have different target formats
   };
 }
+
+export type ToolSource = "custom" | "config" | "app_data" | "development" | "homebrew" | "system" | "bundled";
+
+export interface ToolCandidate {
+  path: string;
+  source: ToolSource; // "config" = set by the machine config
+  exists: boolean;
+  selected: boolean;
+}
+
+// where_is_tool(name): every place checked, in order
+export interface ToolSearch {
+  tool: string;
+  candidates: ToolCandidate[];
+  selected: string | null;
+  reason: string;
+}